@@ -0,0 +1,62 @@
+use starknet::ContractAddress;
+
+#[starknet::interface]
+trait IPaymaster<TContractState> {
+    fn __validate_paymaster__(
+        self: @TContractState, sender: ContractAddress, transaction_hash: felt252
+    ) -> felt252;
+    fn set_sponsored(ref self: TContractState, account: ContractAddress, sponsored: bool);
+    fn is_sponsored(self: @TContractState, account: ContractAddress) -> bool;
+}
+
+// A sample paymaster paying the fees of the transactions sent by the accounts its owner sponsors.
+// The paymaster must hold enough fee tokens to pay for the sponsored transactions.
+#[starknet::contract]
+mod Paymaster {
+    use starknet::{ContractAddress, get_caller_address};
+
+    #[storage]
+    struct Storage {
+        owner: ContractAddress,
+        sponsored: LegacyMap::<ContractAddress, bool>,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    enum Event {
+        SponsorshipUpdated: SponsorshipUpdated,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    struct SponsorshipUpdated {
+        account: ContractAddress,
+        sponsored: bool,
+    }
+
+    #[constructor]
+    fn constructor(ref self: ContractState, owner: ContractAddress) {
+        self.owner.write(owner);
+    }
+
+    #[abi(embed_v0)]
+    impl PaymasterImpl of super::IPaymaster<ContractState> {
+        // Called by the sequencer before executing a transaction sponsored by this paymaster.
+        // The transaction is rejected unless it returns `VALID`.
+        fn __validate_paymaster__(
+            self: @ContractState, sender: ContractAddress, transaction_hash: felt252
+        ) -> felt252 {
+            assert(self.sponsored.read(sender), 'PAYMASTER_NOT_SPONSORED');
+            starknet::VALIDATED
+        }
+
+        fn set_sponsored(ref self: ContractState, account: ContractAddress, sponsored: bool) {
+            assert(get_caller_address() == self.owner.read(), 'PAYMASTER_NOT_OWNER');
+            self.sponsored.write(account, sponsored);
+            self.emit(SponsorshipUpdated { account, sponsored });
+        }
+
+        fn is_sponsored(self: @ContractState, account: ContractAddress) -> bool {
+            self.sponsored.read(account)
+        }
+    }
+}
@@ -35,7 +35,6 @@ pub struct CommitmentStateDiffWorker<B: BlockT, C, H> {
     storage_event_stream: StorageEventStream<B::Hash>,
     tx: mpsc::Sender<BlockDAData>,
     msg: Option<BlockDAData>,
-    backend: Arc<mc_db::Backend<B>>,
     phantom: PhantomData<H>,
 }
 
@@ -43,11 +42,11 @@ impl<B: BlockT, C, H> CommitmentStateDiffWorker<B, C, H>
 where
     C: BlockchainEvents<B>,
 {
-    pub fn new(client: Arc<C>, backend: Arc<mc_db::Backend<B>>, tx: mpsc::Sender<BlockDAData>) -> Self {
+    pub fn new(client: Arc<C>, tx: mpsc::Sender<BlockDAData>) -> Self {
         let storage_event_stream = client
             .storage_changes_notification_stream(None, None)
             .expect("the node storage changes notification stream should be up and running");
-        Self { client, storage_event_stream, tx, msg: Default::default(), backend, phantom: PhantomData }
+        Self { client, storage_event_stream, tx, msg: Default::default(), phantom: PhantomData }
     }
 }
 
@@ -75,11 +74,7 @@ where
                 Poll::Ready(Some(storage_notification)) => {
                     let block_hash = storage_notification.block;
 
-                    match build_commitment_state_diff::<B, C>(
-                        self_as_mut.client.clone(),
-                        self_as_mut.backend.clone(),
-                        storage_notification,
-                    ) {
+                    match build_commitment_state_diff::<B, C>(self_as_mut.client.clone(), storage_notification) {
                         Ok(msg) => self_as_mut.msg = Some(msg),
                         Err(e) => {
                             log::error!(
@@ -138,7 +133,6 @@ enum BuildCommitmentStateDiffError {
 
 fn build_commitment_state_diff<B: BlockT, C>(
     client: Arc<C>,
-    backend: Arc<mc_db::Backend<B>>,
     storage_notification: StorageNotification<B::Hash>,
 ) -> Result<BlockDAData, BuildCommitmentStateDiffError>
 where
@@ -224,10 +218,14 @@ where
         }
     }
 
-    let current_block = {
-        let header = client.header(storage_notification.block)?.ok_or(BuildCommitmentStateDiffError::BlockNotFound)?;
-        let digest = header.digest();
-        mp_digest_log::find_starknet_block(digest)?
+    let header = client.header(storage_notification.block)?.ok_or(BuildCommitmentStateDiffError::BlockNotFound)?;
+    let current_block = mp_digest_log::find_starknet_block(header.digest())?;
+    let previous_state_root = if current_block.header().block_number > 0 {
+        let parent_header =
+            client.header(*header.parent_hash())?.ok_or(BuildCommitmentStateDiffError::BlockNotFound)?;
+        mp_digest_log::find_starknet_block(parent_header.digest())?.header().global_state_root
+    } else {
+        StarkHash::ZERO
     };
 
     Ok(BlockDAData {
@@ -235,8 +233,7 @@ where
         state_diff: commitment_state_diff,
         num_addr_accessed: accessed_addrs.len(),
         block_number: current_block.header().block_number,
-        // TODO: fix when we implement state root
-        new_state_root: backend.temporary_global_state_root_getter(),
-        previous_state_root: backend.temporary_global_state_root_getter(),
+        new_state_root: current_block.header().global_state_root,
+        previous_state_root,
     })
 }
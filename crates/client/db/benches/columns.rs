@@ -0,0 +1,130 @@
+//! Compares the lookups of the columns with heavy read amplification, with and without the
+//! column tuning and the shared block cache of `DatabaseOptions::default()`.
+//!
+//! Run with `cargo bench -p mc-db`.
+
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mc_db::{Backend, DatabaseOptions, MappingCommitment, StorageMode};
+use sc_client_db::DatabaseSource;
+use sp_runtime::generic::{Block as GenericBlock, Header};
+use sp_runtime::traits::BlakeTwo256;
+use sp_runtime::OpaqueExtrinsic;
+use starknet_api::block::BlockHash;
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::ContractClass;
+use starknet_api::transaction::TransactionHash;
+
+type Block = GenericBlock<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+const CLASSES: u128 = 256;
+/// Felts in the Sierra program of each class, about 100 KiB once encoded
+const SIERRA_PROGRAM_LENGTH: u128 = 3_200;
+const BLOCKS: u128 = 500;
+const TRANSACTIONS_PER_BLOCK: u128 = 100;
+
+/// The column options and the block cache are measured separately, then together
+fn configurations() -> [(&'static str, DatabaseOptions); 4] {
+    let tuned = DatabaseOptions::default();
+    let untuned = DatabaseOptions::untuned();
+    [
+        ("untuned", untuned.clone()),
+        ("cache_only", DatabaseOptions { cache_size: tuned.cache_size, ..untuned }),
+        ("columns_only", DatabaseOptions { cache_size: 0, ..tuned.clone() }),
+        ("tuned", tuned),
+    ]
+}
+
+fn open(path: &Path, options: &DatabaseOptions) -> Backend<Block> {
+    let source = DatabaseSource::RocksDb { path: path.to_path_buf(), cache_size: 0 };
+    Backend::open(&source, path, Some(StorageMode::Archive), options).unwrap()
+}
+
+fn class_hash(index: u128) -> ClassHash {
+    ClassHash(StarkFelt::from(index))
+}
+
+fn transaction_hash(index: u128) -> TransactionHash {
+    TransactionHash(StarkFelt::from(index))
+}
+
+/// Fill the database, then reopen it so that the values are flushed from the write buffers to disk
+fn populated_database(path: &Path, options: &DatabaseOptions) -> Backend<Block> {
+    let backend = open(path, options);
+
+    for index in 0..CLASSES {
+        let class = ContractClass {
+            sierra_program: (0..SIERRA_PROGRAM_LENGTH).map(|felt| StarkFelt::from(felt * index)).collect(),
+            ..Default::default()
+        };
+        backend.sierra_classes().store_sierra_class(class_hash(index), class).unwrap();
+    }
+
+    for block in 0..BLOCKS {
+        let mut substrate_block_hash = [0u8; 32];
+        substrate_block_hash[..16].copy_from_slice(&block.to_be_bytes());
+        backend
+            .mapping()
+            .write_hashes(MappingCommitment {
+                block_hash: substrate_block_hash.into(),
+                starknet_block_hash: BlockHash(StarkFelt::from(block)),
+                starknet_transaction_hashes: (0..TRANSACTIONS_PER_BLOCK)
+                    .map(|index| transaction_hash(block * TRANSACTIONS_PER_BLOCK + index))
+                    .collect(),
+                starknet_transaction_receipts: Vec::new(),
+                starknet_block_number: block as u64,
+                starknet_storage_diffs: None,
+                starknet_state_diff: None,
+            })
+            .unwrap();
+    }
+
+    drop(backend);
+    open(path, options)
+}
+
+fn lookups(c: &mut Criterion) {
+    for (name, options) in configurations() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = populated_database(dir.path(), &options);
+
+        let mut index = 0;
+        c.bench_with_input(BenchmarkId::new("sierra_class_lookup", name), &backend, |b, backend| {
+            b.iter(|| {
+                index = (index + 97) % CLASSES;
+                backend.sierra_classes().get_sierra_class(class_hash(index)).unwrap().unwrap()
+            })
+        });
+
+        // A small working set, which fits in the block cache once it is warm
+        let mut index = 0;
+        c.bench_with_input(BenchmarkId::new("sierra_class_hot_lookup", name), &backend, |b, backend| {
+            b.iter(|| {
+                index = (index + 1) % 8;
+                backend.sierra_classes().get_sierra_class(class_hash(index)).unwrap().unwrap()
+            })
+        });
+
+        let mut index = 0;
+        c.bench_with_input(BenchmarkId::new("transaction_mapping_lookup", name), &backend, |b, backend| {
+            b.iter(|| {
+                index = (index + 7919) % (BLOCKS * TRANSACTIONS_PER_BLOCK);
+                backend.mapping().block_hash_from_transaction_hash(transaction_hash(index)).unwrap().unwrap()
+            })
+        });
+
+        // Transactions not in the mapping, typically the pending ones, only hit the bloom filters
+        let mut index = BLOCKS * TRANSACTIONS_PER_BLOCK;
+        c.bench_with_input(BenchmarkId::new("transaction_mapping_miss", name), &backend, |b, backend| {
+            b.iter(|| {
+                index += 1;
+                backend.mapping().block_hash_from_transaction_hash(transaction_hash(index)).unwrap()
+            })
+        });
+    }
+}
+
+criterion_group!(benches, lookups);
+criterion_main!(benches);
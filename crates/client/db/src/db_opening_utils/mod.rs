@@ -16,52 +16,72 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-mod parity_db_adapter;
+#[cfg(feature = "parity-db")]
+pub(crate) mod parity_db_adapter;
+#[cfg(feature = "rocksdb")]
+pub(crate) mod rocksdb_adapter;
 
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::{Database, DatabaseSettings, DatabaseSource, DbHash};
+use crate::{Database, DatabaseOptions, DatabaseSettings, DatabaseSource, DbHash};
+
+/// Return the value read, stopping the node on a read error
+///
+/// The getters of [`Database`] return an `Option` and have no way to report an error. The
+/// `sp_database` kvdb adapter which opened the RocksDB database before the column options, and the
+/// parity-db adapter, both panic on read errors. Reporting them as missing values instead would
+/// make the node answer "not found" for data it holds, and build its indexes on data it did not
+/// actually read. Write errors are returned by [`Database::commit`] and surface as
+/// [`crate::DbError::CommitError`].
+pub(crate) fn handle_read_err<T, E: std::fmt::Debug>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|e| panic!("Critical database error: {e:?}"))
+}
 
 pub(crate) fn open_database(config: &DatabaseSettings) -> Result<Arc<dyn Database<DbHash>>, String> {
     let db: Arc<dyn Database<DbHash>> = match &config.source {
         DatabaseSource::ParityDb { path } => open_parity_db(path)?,
-        DatabaseSource::RocksDb { path, .. } => open_kvdb_rocksdb(path, true)?,
-        DatabaseSource::Auto { paritydb_path, rocksdb_path, .. } => match open_kvdb_rocksdb(rocksdb_path, false) {
-            Ok(db) => db,
-            Err(_) => open_parity_db(paritydb_path)?,
-        },
+        DatabaseSource::RocksDb { path, .. } => open_rocksdb(path, true, &config.options)?,
+        DatabaseSource::Auto { paritydb_path, rocksdb_path, .. } => {
+            match open_rocksdb(rocksdb_path, false, &config.options) {
+                Ok(db) => db,
+                Err(_) => open_parity_db(paritydb_path)?,
+            }
+        }
         _ => return Err("Missing feature flags `parity-db`".to_string()),
     };
     Ok(db)
 }
 
-#[cfg(feature = "kvdb-rocksdb")]
-fn open_kvdb_rocksdb(path: &Path, create: bool) -> Result<Arc<dyn Database<DbHash>>, String> {
-    let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(crate::columns::NUM_COLUMNS);
-    db_config.create_if_missing = create;
-
-    let db = kvdb_rocksdb::Database::open(&db_config, path).map_err(|err| format!("{}", err))?;
-    Ok(sp_database::as_database(db))
+#[cfg(feature = "rocksdb")]
+fn open_rocksdb(path: &Path, create: bool, options: &DatabaseOptions) -> Result<Arc<dyn Database<DbHash>>, String> {
+    let db = rocksdb_adapter::DbAdapter::open(path, create, options).map_err(|err| format!("{}", err))?;
+    Ok(Arc::new(db))
 }
 
-#[cfg(not(feature = "kvdb-rocksdb"))]
-fn open_kvdb_rocksdb(
-    _client: Arc<C>,
-    _path: &Path,
-    _create: bool,
-    _source: &DatabaseSource,
-) -> Result<Arc<dyn Database<DbHash>>, String> {
-    Err("Missing feature flags `kvdb-rocksdb`".to_string())
+#[cfg(not(feature = "rocksdb"))]
+fn open_rocksdb(_path: &Path, _create: bool, _options: &DatabaseOptions) -> Result<Arc<dyn Database<DbHash>>, String> {
+    Err("Missing feature flags `rocksdb`".to_string())
 }
 
 #[cfg(feature = "parity-db")]
 fn open_parity_db(path: &Path) -> Result<Arc<dyn Database<DbHash>>, String> {
     let mut config = parity_db::Options::with_columns(path, crate::columns::NUM_COLUMNS as u8);
     config.columns[crate::columns::BLOCK_MAPPING as usize].btree_index = true;
+    parity_db_adapter::set_ref_counted_columns(&mut config, crate::columns::REF_COUNTED);
+
+    // Unlike rocksdb, parity-db does not create the missing columns of an existing database
+    if let Some(metadata) = config.load_metadata().map_err(|err| format!("{}", err))? {
+        let mut existing_config = config.clone();
+        existing_config.columns = metadata.columns;
+        for column in config.columns.iter().skip(existing_config.columns.len()) {
+            parity_db::Db::add_column(&mut existing_config, column.clone()).map_err(|err| format!("{}", err))?;
+        }
+    }
 
-    let db = parity_db::Db::open_or_create(&config).map_err(|err| format!("{}", err))?;
-    Ok(Arc::new(parity_db_adapter::DbAdapter(db)))
+    let db =
+        parity_db_adapter::DbAdapter::open(&config, crate::columns::REF_COUNTED).map_err(|err| format!("{}", err))?;
+    Ok(Arc::new(db))
 }
 
 #[cfg(not(feature = "parity-db"))]
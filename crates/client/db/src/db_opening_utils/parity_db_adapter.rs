@@ -16,41 +16,88 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use parity_db::{ColId, Operation};
 use sp_database::error::DatabaseError;
 use sp_database::{Change, ColumnId, Database, Transaction};
 
-fn handle_err<T>(result: parity_db::Result<T>) -> T {
-    match result {
-        Ok(r) => r,
-        Err(e) => {
-            panic!("Critical database error: {:?}", e);
-        }
+use super::handle_read_err;
+
+pub struct DbAdapter {
+    db: parity_db::Db,
+    /// Columns supporting `Store`, `Reference` and `Release` changes
+    ref_counted_columns: Vec<ColumnId>,
+}
+
+impl DbAdapter {
+    /// Open the database, `ref_counted_columns` must match the options the database was created
+    /// with
+    pub fn open(config: &parity_db::Options, ref_counted_columns: &[ColumnId]) -> parity_db::Result<Self> {
+        Ok(Self { db: parity_db::Db::open_or_create(config)?, ref_counted_columns: ref_counted_columns.to_vec() })
+    }
+
+    fn is_ref_counted(&self, col: ColumnId) -> bool {
+        self.ref_counted_columns.contains(&col)
     }
 }
 
-pub struct DbAdapter(pub parity_db::Db);
+/// Configure `ref_counted_columns` as reference counted columns
+pub fn set_ref_counted_columns(config: &mut parity_db::Options, ref_counted_columns: &[ColumnId]) {
+    for col in ref_counted_columns {
+        let column = &mut config.columns[*col as usize];
+        column.ref_counted = true;
+        column.preimage = true;
+        // Values are accessed by hash, there is nothing to iterate over
+        column.btree_index = false;
+    }
+}
 
 impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
     fn commit(&self, transaction: Transaction<H>) -> Result<(), DatabaseError> {
-        handle_err(self.0.commit(transaction.0.into_iter().map(|change| match change {
-            Change::Set(col, key, value) => (col as u8, key, Some(value)),
-            Change::Remove(col, key) => (col as u8, key, None),
-            _ => unimplemented!(),
-        })));
+        let mut not_ref_counted_columns = Vec::new();
+        let changes: Vec<(ColId, Operation<Vec<u8>, Vec<u8>>)> = transaction
+            .0
+            .into_iter()
+            .filter_map(|change| {
+                Some(match change {
+                    Change::Set(col, key, value) => (col as ColId, Operation::Set(key, value)),
+                    Change::Remove(col, key) => (col as ColId, Operation::Dereference(key)),
+                    Change::Store(col, key, value) if self.is_ref_counted(col) => {
+                        (col as ColId, Operation::Set(key.as_ref().to_vec(), value))
+                    }
+                    Change::Reference(col, key) if self.is_ref_counted(col) => {
+                        (col as ColId, Operation::Reference(key.as_ref().to_vec()))
+                    }
+                    Change::Release(col, key) if self.is_ref_counted(col) => {
+                        (col as ColId, Operation::Dereference(key.as_ref().to_vec()))
+                    }
+                    Change::Store(col, ..) | Change::Reference(col, _) | Change::Release(col, _) => {
+                        not_ref_counted_columns.push(col);
+                        return None;
+                    }
+                })
+            })
+            .collect();
+
+        if !not_ref_counted_columns.is_empty() {
+            return Err(DatabaseError(Box::new(parity_db::Error::InvalidInput(format!(
+                "Ref counted operation on non ref counted columns {:?}",
+                not_ref_counted_columns
+            )))));
+        }
 
-        Ok(())
+        self.db.commit_changes(changes).map_err(|e| DatabaseError(Box::new(e)))
     }
 
     fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
-        handle_err(self.0.get(col as u8, key))
+        handle_read_err(self.db.get(col as ColId, key))
     }
 
     fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
-        handle_err(self.0.get_size(col as u8, key)).is_some()
+        handle_read_err(self.db.get_size(col as ColId, key)).is_some()
     }
 
     fn value_size(&self, col: ColumnId, key: &[u8]) -> Option<usize> {
-        handle_err(self.0.get_size(col as u8, key)).map(|s| s as usize)
+        handle_read_err(self.db.get_size(col as ColId, key)).map(|s| s as usize)
     }
 
     fn supports_ref_counting(&self) -> bool {
@@ -61,3 +108,71 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
         let _prefix = key.drain(0..key.len() - crate::DB_HASH_LEN);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: ColumnId = 0;
+    const REF_COUNTED: ColumnId = 1;
+
+    fn open_adapter(path: &std::path::Path) -> DbAdapter {
+        let mut config = parity_db::Options::with_columns(path, 2);
+        set_ref_counted_columns(&mut config, &[REF_COUNTED]);
+        DbAdapter::open(&config, &[REF_COUNTED]).unwrap()
+    }
+
+    #[test]
+    fn store_reference_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_adapter(dir.path());
+        let hash = [1u8; 32];
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.store(REF_COUNTED, hash, b"class".to_vec());
+        db.commit(transaction).unwrap();
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.reference(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), Some(b"class".to_vec()));
+
+        // Still referenced once
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.release(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), Some(b"class".to_vec()));
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.release(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), None);
+    }
+
+    #[test]
+    fn ref_counted_change_on_plain_column_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_adapter(dir.path());
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.store(PLAIN, [1u8; 32], b"class".to_vec());
+
+        assert!(db.commit(transaction).is_err());
+        assert!(!Database::<[u8; 32]>::contains(&db, PLAIN, &[1u8; 32]));
+    }
+
+    #[test]
+    fn set_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_adapter(dir.path());
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.set(PLAIN, b"key", b"value");
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::value_size(&db, PLAIN, b"key"), Some(5));
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.remove(PLAIN, b"key");
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, PLAIN, b"key"), None);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rocksdb::{
+    BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, Options, WriteBatch, DB,
+};
+use sp_database::error::DatabaseError;
+use sp_database::{Change, ColumnId, Database, Transaction};
+
+use super::handle_read_err;
+use crate::{ColumnOptions, Compression, DatabaseOptions};
+
+const MIB: usize = 1024 * 1024;
+
+/// Name of the column family of a column, the same as kvdb-rocksdb so that databases created
+/// with it can still be opened
+pub(crate) fn column_family_name(column: u32) -> String {
+    format!("col{column}")
+}
+
+fn column_family_options(options: &ColumnOptions, cache: Option<&Cache>) -> Options {
+    let mut block_options = BlockBasedOptions::default();
+    block_options.set_block_size(options.block_size * 1024);
+    match cache {
+        Some(cache) => block_options.set_block_cache(cache),
+        None => block_options.disable_cache(),
+    }
+    if let Some(bits_per_key) = options.bloom_filter_bits {
+        block_options.set_bloom_filter(bits_per_key, false);
+        block_options.set_cache_index_and_filter_blocks(true);
+        block_options.set_pin_l0_filter_and_index_blocks_in_cache(true);
+    }
+
+    let mut column_options = Options::default();
+    column_options.set_block_based_table_factory(&block_options);
+    column_options.set_write_buffer_size(options.write_buffer_size * MIB);
+    column_options.set_compression_type(match options.compression {
+        Compression::None => DBCompressionType::None,
+        Compression::Lz4 => DBCompressionType::Lz4,
+        Compression::Zstd => DBCompressionType::Zstd,
+    });
+    column_options
+}
+
+pub struct DbAdapter(DB);
+
+impl DbAdapter {
+    pub fn open(path: &Path, create: bool, options: &DatabaseOptions) -> Result<Self, rocksdb::Error> {
+        let mut db_options = Options::default();
+        db_options.create_if_missing(create);
+        db_options.create_missing_column_families(true);
+        db_options.increase_parallelism(num_cpus());
+
+        let cache = (options.cache_size > 0).then(|| Cache::new_lru_cache(options.cache_size * MIB));
+        let column_families = (0..crate::columns::NUM_COLUMNS).map(|column| {
+            ColumnFamilyDescriptor::new(
+                column_family_name(column),
+                column_family_options(&options.column(column), cache.as_ref()),
+            )
+        });
+
+        Ok(Self(DB::open_cf_descriptors(&db_options, path, column_families)?))
+    }
+
+    fn column(&self, column: ColumnId) -> &ColumnFamily {
+        self.0.cf_handle(&column_family_name(column)).expect("Every column family is opened; qed")
+    }
+}
+
+fn num_cpus() -> i32 {
+    std::thread::available_parallelism().map(|n| n.get() as i32).unwrap_or(2)
+}
+
+/// Key of the reference count of a value of a reference counted column, the same as kvdb
+fn counter_key(key: &[u8]) -> Vec<u8> {
+    let mut counter_key = key.to_vec();
+    counter_key.push(0);
+    counter_key
+}
+
+fn unsupported(message: String) -> DatabaseError {
+    DatabaseError(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, message)))
+}
+
+impl DbAdapter {
+    /// Return the reference count of `key`, taking the changes of the current transaction into
+    /// account
+    fn counter(
+        &self,
+        counters: &HashMap<(ColumnId, Vec<u8>), u32>,
+        col: ColumnId,
+        key: &[u8],
+    ) -> Result<u32, DatabaseError> {
+        if let Some(counter) = counters.get(&(col, key.to_vec())) {
+            return Ok(*counter);
+        }
+        match self.0.get_cf(self.column(col), counter_key(key)).map_err(|e| DatabaseError(Box::new(e)))? {
+            Some(data) => {
+                let data: [u8; 4] =
+                    data.try_into().map_err(|_| unsupported(format!("Invalid reference count for {key:?}")))?;
+                Ok(u32::from_le_bytes(data))
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
+    fn commit(&self, transaction: Transaction<H>) -> Result<(), DatabaseError> {
+        let mut batch = WriteBatch::default();
+        let mut counters = HashMap::new();
+
+        for change in transaction.0 {
+            match change {
+                Change::Set(col, key, value) => batch.put_cf(self.column(col), key, value),
+                Change::Remove(col, key) => batch.delete_cf(self.column(col), key),
+                Change::Store(col, ..) | Change::Reference(col, _) | Change::Release(col, _)
+                    if !crate::columns::REF_COUNTED.contains(&col) =>
+                {
+                    return Err(unsupported(format!("Ref counted operation on non ref counted column {col}")));
+                }
+                Change::Store(col, key, value) => {
+                    let key = key.as_ref();
+                    let counter = self.counter(&counters, col, key)?;
+                    if counter == 0 {
+                        batch.put_cf(self.column(col), key, value);
+                    }
+                    batch.put_cf(self.column(col), counter_key(key), (counter + 1).to_le_bytes());
+                    counters.insert((col, key.to_vec()), counter + 1);
+                }
+                Change::Reference(col, key) => {
+                    let key = key.as_ref();
+                    let counter = self.counter(&counters, col, key)?;
+                    if counter > 0 {
+                        batch.put_cf(self.column(col), counter_key(key), (counter + 1).to_le_bytes());
+                        counters.insert((col, key.to_vec()), counter + 1);
+                    }
+                }
+                Change::Release(col, key) => {
+                    let key = key.as_ref();
+                    match self.counter(&counters, col, key)? {
+                        0 => {}
+                        1 => {
+                            batch.delete_cf(self.column(col), key);
+                            batch.delete_cf(self.column(col), counter_key(key));
+                            counters.insert((col, key.to_vec()), 0);
+                        }
+                        counter => {
+                            batch.put_cf(self.column(col), counter_key(key), (counter - 1).to_le_bytes());
+                            counters.insert((col, key.to_vec()), counter - 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.0.write(batch).map_err(|e| DatabaseError(Box::new(e)))
+    }
+
+    fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+        handle_read_err(self.0.get_cf(self.column(col), key))
+    }
+
+    fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
+        handle_read_err(self.0.get_pinned_cf(self.column(col), key)).is_some()
+    }
+
+    fn value_size(&self, col: ColumnId, key: &[u8]) -> Option<usize> {
+        handle_read_err(self.0.get_pinned_cf(self.column(col), key)).map(|value| value.len())
+    }
+
+    fn supports_ref_counting(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: ColumnId = crate::columns::META;
+    const REF_COUNTED: ColumnId = crate::columns::SIERRA_CLASSES;
+
+    fn open_adapter(path: &Path) -> DbAdapter {
+        DbAdapter::open(path, true, &DatabaseOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn store_reference_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_adapter(dir.path());
+        let hash = [1u8; 32];
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.store(REF_COUNTED, hash, b"class".to_vec());
+        db.commit(transaction).unwrap();
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.reference(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), Some(b"class".to_vec()));
+
+        // Still referenced once
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.release(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), Some(b"class".to_vec()));
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.release(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), None);
+        assert!(!Database::<[u8; 32]>::contains(&db, REF_COUNTED, &counter_key(&hash)));
+    }
+
+    #[test]
+    fn changes_of_a_transaction_are_counted_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_adapter(dir.path());
+        let hash = [1u8; 32];
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.store(REF_COUNTED, hash, b"class".to_vec());
+        transaction.store(REF_COUNTED, hash, b"class".to_vec());
+        transaction.release(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), Some(b"class".to_vec()));
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.release(REF_COUNTED, hash);
+        db.commit(transaction).unwrap();
+        assert_eq!(Database::<[u8; 32]>::get(&db, REF_COUNTED, &hash), None);
+    }
+
+    #[test]
+    fn ref_counted_change_on_plain_column_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_adapter(dir.path());
+
+        let mut transaction = Transaction::<[u8; 32]>::new();
+        transaction.store(PLAIN, [1u8; 32], b"class".to_vec());
+
+        assert!(db.commit(transaction).is_err());
+        assert!(!Database::<[u8; 32]>::contains(&db, PLAIN, &[1u8; 32]));
+    }
+}
@@ -8,4 +8,10 @@ pub enum DbError {
     Uuid(#[from] uuid::Error),
     #[error("A value was queryied that was not initialized at column: `{0}` key: `{1}`")]
     ValueNotInitialized(u32, String),
+    #[error("Database schema version `{0}` is not supported, this node supports up to version `{1}`")]
+    UnsupportedSchemaVersion(u32, u32),
+    #[error("Failed to migrate the database from schema version `{0}`: {1}")]
+    MigrationFailed(u32, Box<DbError>),
+    #[error("Cannot index the storage of block #{0}, the next block to index is #{1}")]
+    IndexingOutOfOrder(u64, u64),
 }
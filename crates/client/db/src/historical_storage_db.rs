@@ -0,0 +1,433 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+// Substrate
+use parity_scale_codec::{Decode, Encode};
+use sp_database::Database;
+use sp_runtime::traits::Block as BlockT;
+use starknet_api::core::ContractAddress;
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+use crate::{DbError, DbHash};
+
+/// A storage value written by a block
+pub type StorageDiff = (ContractAddress, StorageKey, StarkFelt);
+
+/// Prefix of the storage diffs of the synced blocks which are not indexed yet, by Substrate block
+/// hash
+const PENDING_DIFFS_PREFIX: u8 = 0;
+/// Prefix of the hashes of the synced blocks with pending storage diffs, by block number
+const PENDING_BLOCKS_PREFIX: u8 = 1;
+/// Prefix of the number of the last indexed block writing to a storage slot, by slot
+const SLOT_HEAD_PREFIX: u8 = 2;
+/// Prefix of the [`HistoryEntry`] of each indexed write, by slot and block number
+const SLOT_ENTRY_PREFIX: u8 = 3;
+
+/// Allow interaction with the historical storage db
+///
+/// Only maintained in [`crate::StorageMode::Archive`] mode. The storage diffs of the synced
+/// blocks are recorded along the block mapping, see [`crate::MappingCommitment`], whatever fork
+/// they belong to. The index does not go past a block synced without its diffs until they are
+/// written by the indexing backfill, see [`crate::IndexingBackfill`]. Once a block is finalized,
+/// the diffs of the canonical block at its height are moved to the index, which holds one entry per
+/// storage slot and block writing to it, and the diffs of the other blocks at this height are
+/// dropped. Since finalized blocks are never reverted, the index does not have to handle reorgs.
+///
+/// The writes to a slot are linked to the previous one and to an older one, forming a skip list
+/// which can be walked back to any block without reading every write in between.
+pub struct HistoricalStorageDb<B: BlockT> {
+    pub(crate) db: Arc<dyn Database<DbHash>>,
+    pub(crate) _marker: PhantomData<B>,
+}
+
+/// A write to a storage slot
+#[derive(Clone, Debug, Encode, Decode)]
+struct HistoryEntry {
+    value: StarkFelt,
+    /// Number of writes to the slot indexed before this one
+    index: u64,
+    /// Number of the block of the previous write to the slot
+    previous: Option<u64>,
+    /// Number of the block of the write at index `skip_index(index)`
+    skip: Option<u64>,
+}
+
+fn slot_key(contract_address: &ContractAddress, key: &StorageKey) -> Vec<u8> {
+    let mut db_key = Vec::with_capacity(1 + 64 + 8);
+    db_key.push(SLOT_HEAD_PREFIX);
+    db_key.extend_from_slice(contract_address.0.key().bytes());
+    db_key.extend_from_slice(key.0.key().bytes());
+    db_key
+}
+
+fn entry_key(slot_key: &[u8], block_number: u64) -> Vec<u8> {
+    let mut db_key = slot_key.to_vec();
+    db_key[0] = SLOT_ENTRY_PREFIX;
+    db_key.extend_from_slice(&block_number.to_be_bytes());
+    db_key
+}
+
+fn pending_diffs_key<H: Encode>(block_hash: &H) -> Vec<u8> {
+    let mut db_key = vec![PENDING_DIFFS_PREFIX];
+    block_hash.encode_to(&mut db_key);
+    db_key
+}
+
+fn pending_blocks_key(block_number: u64) -> Vec<u8> {
+    let mut db_key = vec![PENDING_BLOCKS_PREFIX];
+    db_key.extend_from_slice(&block_number.to_be_bytes());
+    db_key
+}
+
+fn invert_lowest_one(n: u64) -> u64 {
+    n & n.wrapping_sub(1)
+}
+
+/// Index of the write the skip pointer of the write at `index` leads to
+///
+/// Same distribution as the skip pointers of the Bitcoin Core block index, which makes reaching
+/// a previous write take a number of steps logarithmic in the distance to it.
+fn skip_index(index: u64) -> u64 {
+    if index < 2 {
+        0
+    } else if index & 1 == 1 {
+        invert_lowest_one(invert_lowest_one(index - 1)) + 1
+    } else {
+        invert_lowest_one(index)
+    }
+}
+
+impl<B: BlockT> HistoricalStorageDb<B> {
+    /// Return the number of the last block whose storage diffs are indexed
+    pub fn last_indexed_block(&self) -> Result<Option<u64>, DbError> {
+        match self.db.get(crate::columns::META, crate::static_keys::LAST_INDEXED_STORAGE_BLOCK) {
+            Some(raw) => Ok(Some(u64::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the value of the storage slot at the end of block `block_number`
+    ///
+    /// Returns `None` if the block is not indexed yet or if no block up to `block_number` wrote to
+    /// this slot, in which case the value has to be read from the chain state. The block must be
+    /// part of the canonical chain.
+    pub fn get_storage_at(
+        &self,
+        contract_address: &ContractAddress,
+        key: &StorageKey,
+        block_number: u64,
+    ) -> Result<Option<StarkFelt>, DbError> {
+        if !self.last_indexed_block()?.is_some_and(|last_indexed_block| block_number <= last_indexed_block) {
+            return Ok(None);
+        }
+
+        let slot_key = slot_key(contract_address, key);
+        let Some((mut number, mut entry)) = self.head(&slot_key)? else {
+            return Ok(None);
+        };
+        while number > block_number {
+            number = match (entry.skip, entry.previous) {
+                (Some(skip), _) if skip > block_number => skip,
+                (_, Some(previous)) => previous,
+                (_, None) => return Ok(None),
+            };
+            entry = self.entry(&slot_key, number)?;
+        }
+
+        Ok(Some(entry.value))
+    }
+
+    /// Record the storage diffs of a synced block, to be indexed once it is finalized
+    pub(crate) fn set_block_diffs(
+        &self,
+        transaction: &mut sp_database::Transaction<DbHash>,
+        block_hash: &B::Hash,
+        block_number: u64,
+        storage_diffs: &[StorageDiff],
+    ) -> Result<(), DbError> {
+        // Blocks below the index are either indexed already or not part of the canonical chain
+        if self.last_indexed_block()?.is_some_and(|last_indexed_block| block_number <= last_indexed_block) {
+            return Ok(());
+        }
+
+        let mut block_hashes = self.pending_blocks(block_number)?;
+        if !block_hashes.contains(block_hash) {
+            block_hashes.push(*block_hash);
+            transaction.set(
+                crate::columns::HISTORICAL_STORAGE,
+                &pending_blocks_key(block_number),
+                &block_hashes.encode(),
+            );
+        }
+        transaction.set(crate::columns::HISTORICAL_STORAGE, &pending_diffs_key(block_hash), &storage_diffs.encode());
+
+        Ok(())
+    }
+
+    /// Index the storage diffs of the finalized block `block_number`, whose hash is `block_hash`,
+    /// and drop the ones of the other blocks at this height
+    ///
+    /// Blocks must be indexed in order. Returns `false` if the storage diffs of the block are not
+    /// recorded yet, in which case nothing is written.
+    pub fn index_block(&self, block_number: u64, block_hash: &B::Hash) -> Result<bool, DbError> {
+        let expected_block_number = self.last_indexed_block()?.map_or(0, |last_indexed_block| last_indexed_block + 1);
+        if block_number != expected_block_number {
+            return Err(DbError::IndexingOutOfOrder(block_number, expected_block_number));
+        }
+
+        let storage_diffs = match self.db.get(crate::columns::HISTORICAL_STORAGE, &pending_diffs_key(block_hash)) {
+            Some(raw) => Vec::<StorageDiff>::decode(&mut &raw[..])?,
+            // The genesis block does not contain any transaction, it might have been synced before
+            // the storage diffs were recorded
+            None if block_number == 0 => Vec::new(),
+            None => return Ok(false),
+        };
+        // Each slot is written once per block, the last value being the final one
+        let storage_diffs = storage_diffs
+            .into_iter()
+            .map(|(contract_address, key, value)| (slot_key(&contract_address, &key), value))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut transaction = sp_database::Transaction::new();
+        for (slot_key, value) in storage_diffs {
+            let entry = match self.head(&slot_key)? {
+                None => HistoryEntry { value, index: 0, previous: None, skip: None },
+                Some((head_number, head)) => {
+                    let index = head.index + 1;
+                    let (skip_number, _) = self.entry_at_index(&slot_key, head_number, head, skip_index(index))?;
+                    HistoryEntry { value, index, previous: Some(head_number), skip: Some(skip_number) }
+                }
+            };
+            transaction.set(crate::columns::HISTORICAL_STORAGE, &entry_key(&slot_key, block_number), &entry.encode());
+            transaction.set(crate::columns::HISTORICAL_STORAGE, &slot_key, &block_number.encode());
+        }
+
+        for pending_block_hash in self.pending_blocks(block_number)? {
+            transaction.remove(crate::columns::HISTORICAL_STORAGE, &pending_diffs_key(&pending_block_hash));
+        }
+        transaction.remove(crate::columns::HISTORICAL_STORAGE, &pending_blocks_key(block_number));
+        transaction.set(crate::columns::META, crate::static_keys::LAST_INDEXED_STORAGE_BLOCK, &block_number.encode());
+
+        self.db.commit(transaction)?;
+
+        Ok(true)
+    }
+
+    fn pending_blocks(&self, block_number: u64) -> Result<Vec<B::Hash>, DbError> {
+        match self.db.get(crate::columns::HISTORICAL_STORAGE, &pending_blocks_key(block_number)) {
+            Some(raw) => Ok(Vec::<B::Hash>::decode(&mut &raw[..])?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Return the last write to the slot, along with the number of the block writing it
+    fn head(&self, slot_key: &[u8]) -> Result<Option<(u64, HistoryEntry)>, DbError> {
+        match self.db.get(crate::columns::HISTORICAL_STORAGE, slot_key) {
+            Some(raw) => {
+                let block_number = u64::decode(&mut &raw[..])?;
+                Ok(Some((block_number, self.entry(slot_key, block_number)?)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn entry(&self, slot_key: &[u8], block_number: u64) -> Result<HistoryEntry, DbError> {
+        match self.db.get(crate::columns::HISTORICAL_STORAGE, &entry_key(slot_key, block_number)) {
+            Some(raw) => Ok(HistoryEntry::decode(&mut &raw[..])?),
+            None => Err(DbError::ValueNotInitialized(
+                crate::columns::HISTORICAL_STORAGE,
+                format!("storage history entry at block #{block_number}"),
+            )),
+        }
+    }
+
+    /// Walk back from the write `entry` at `block_number` to the one at `index`
+    fn entry_at_index(
+        &self,
+        slot_key: &[u8],
+        mut block_number: u64,
+        mut entry: HistoryEntry,
+        index: u64,
+    ) -> Result<(u64, HistoryEntry), DbError> {
+        while entry.index > index {
+            let entry_skip_index = skip_index(entry.index);
+            let previous_skip_index = skip_index(entry.index - 1);
+            // Only follow the skip pointer when the one of the previous entry does not get closer
+            let follow_skip = entry_skip_index == index
+                || (entry_skip_index > index
+                    && !(previous_skip_index + 2 < entry_skip_index && previous_skip_index >= index));
+            block_number = match (entry.skip, entry.previous) {
+                (Some(skip), _) if follow_skip => skip,
+                (_, Some(previous)) => previous,
+                (_, None) => unreachable!("Only the first write to a slot has no predecessor"),
+            };
+            entry = self.entry(slot_key, block_number)?;
+        }
+
+        Ok((block_number, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_database::MemDb;
+    use sp_runtime::generic::{Block as GenericBlock, Header};
+    use sp_runtime::testing::H256;
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_runtime::OpaqueExtrinsic;
+    use starknet_api::core::PatriciaKey;
+
+    use super::*;
+
+    type Block = GenericBlock<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+    fn historical_storage_db() -> HistoricalStorageDb<Block> {
+        HistoricalStorageDb { db: Arc::new(MemDb::new()), _marker: PhantomData }
+    }
+
+    fn slot() -> (ContractAddress, StorageKey) {
+        (ContractAddress(PatriciaKey(StarkFelt::from(1u8))), StorageKey(PatriciaKey(StarkFelt::from(2u8))))
+    }
+
+    fn record_block(
+        db: &HistoricalStorageDb<Block>,
+        block_number: u64,
+        block_hash: H256,
+        storage_diffs: &[StorageDiff],
+    ) {
+        let mut transaction = sp_database::Transaction::new();
+        db.set_block_diffs(&mut transaction, &block_hash, block_number, storage_diffs).unwrap();
+        db.db.commit(transaction).unwrap();
+    }
+
+    fn storage_at(db: &HistoricalStorageDb<Block>, block_number: u64) -> Option<StarkFelt> {
+        let (contract_address, key) = slot();
+        db.get_storage_at(&contract_address, &key, block_number).unwrap()
+    }
+
+    #[test]
+    fn storage_is_read_at_any_indexed_block() {
+        let db = historical_storage_db();
+        let (contract_address, key) = slot();
+
+        // The slot is written every third block
+        for block_number in 0..200u64 {
+            let block_hash = H256::from_low_u64_be(block_number);
+            let storage_diffs = if block_number % 3 == 1 {
+                vec![(contract_address, key, StarkFelt::from(block_number))]
+            } else {
+                Vec::new()
+            };
+            record_block(&db, block_number, block_hash, &storage_diffs);
+            assert!(db.index_block(block_number, &block_hash).unwrap());
+        }
+        assert_eq!(db.last_indexed_block().unwrap(), Some(199));
+
+        assert_eq!(storage_at(&db, 0), None);
+        for block_number in 1..200u64 {
+            let last_write = block_number - (block_number + 2) % 3;
+            assert_eq!(storage_at(&db, block_number), Some(StarkFelt::from(last_write)), "block {block_number}");
+        }
+        // Not indexed yet
+        assert_eq!(storage_at(&db, 200), None);
+    }
+
+    #[test]
+    fn blocks_are_indexed_in_order_once_their_diffs_are_recorded() {
+        let db = historical_storage_db();
+
+        assert!(db.index_block(1, &H256::repeat_byte(1)).is_err());
+        assert!(db.index_block(0, &H256::repeat_byte(0)).unwrap());
+        assert_eq!(db.last_indexed_block().unwrap(), Some(0));
+
+        assert!(!db.index_block(1, &H256::repeat_byte(1)).unwrap());
+        assert_eq!(db.last_indexed_block().unwrap(), Some(0));
+
+        record_block(&db, 1, H256::repeat_byte(1), &[]);
+        assert!(db.index_block(1, &H256::repeat_byte(1)).unwrap());
+        assert_eq!(db.last_indexed_block().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn blocks_synced_without_their_diffs_wait_for_the_backfill() {
+        let db = Arc::new(historical_storage_db());
+        let mapping = MappingDb::new(db.db.clone(), db.clone());
+        let (contract_address, key) = slot();
+        let block_hash = H256::repeat_byte(1);
+
+        assert!(db.index_block(0, &H256::repeat_byte(0)).unwrap());
+        // The receipts of the block could not be computed when it was synced
+        mapping
+            .write_hashes(MappingCommitment {
+                block_hash,
+                starknet_block_hash: BlockHash(StarkFelt::from(1u8)),
+                starknet_transaction_hashes: Vec::new(),
+                starknet_transaction_receipts: Vec::new(),
+                starknet_block_number: 1,
+                starknet_storage_diffs: None,
+                starknet_state_diff: None,
+            })
+            .unwrap();
+        assert!(!db.index_block(1, &block_hash).unwrap());
+        assert_eq!(storage_at(&db, 1), None);
+
+        mapping
+            .write_receipts(&block_hash, 1, &[], Some(&[(contract_address, key, StarkFelt::from(7u8))][..]))
+            .unwrap();
+        assert!(db.index_block(1, &block_hash).unwrap());
+        assert_eq!(storage_at(&db, 1), Some(StarkFelt::from(7u8)));
+    }
+
+    #[test]
+    fn only_the_finalized_fork_is_indexed() {
+        let db = historical_storage_db();
+        let (contract_address, key) = slot();
+
+        record_block(&db, 0, H256::repeat_byte(0), &[]);
+        assert!(db.index_block(0, &H256::repeat_byte(0)).unwrap());
+
+        // Two forks write to the slot at block 1, the sync going from the tips to their ancestors
+        record_block(&db, 1, H256::repeat_byte(0xb), &[(contract_address, key, StarkFelt::from(0xbu8))]);
+        record_block(&db, 1, H256::repeat_byte(0xa), &[(contract_address, key, StarkFelt::from(0xau8))]);
+        assert_eq!(storage_at(&db, 1), None);
+
+        assert!(db.index_block(1, &H256::repeat_byte(0xa)).unwrap());
+        assert_eq!(storage_at(&db, 1), Some(StarkFelt::from(0xau8)));
+
+        // The diffs of the other fork are dropped, and late writes for indexed blocks ignored
+        assert!(db.db.get(crate::columns::HISTORICAL_STORAGE, &pending_diffs_key(&H256::repeat_byte(0xb))).is_none());
+        assert!(db.db.get(crate::columns::HISTORICAL_STORAGE, &pending_blocks_key(1)).is_none());
+        record_block(&db, 1, H256::repeat_byte(0xc), &[(contract_address, key, StarkFelt::from(0xcu8))]);
+        assert!(db.db.get(crate::columns::HISTORICAL_STORAGE, &pending_diffs_key(&H256::repeat_byte(0xc))).is_none());
+        assert_eq!(storage_at(&db, 1), Some(StarkFelt::from(0xau8)));
+    }
+
+    #[test]
+    fn skip_pointers_lead_to_older_writes() {
+        for index in 1..10_000u64 {
+            assert!(skip_index(index) < index);
+        }
+        let db = historical_storage_db();
+        let slot_key = slot_key(&slot().0, &slot().1);
+        let mut transaction = sp_database::Transaction::new();
+        let mut head = None;
+        for index in 0..1000u64 {
+            let entry = HistoryEntry {
+                value: StarkFelt::from(index),
+                index,
+                previous: index.checked_sub(1),
+                skip: (index > 0).then(|| skip_index(index)),
+            };
+            transaction.set(crate::columns::HISTORICAL_STORAGE, &entry_key(&slot_key, index), &entry.encode());
+            head = Some(entry);
+        }
+        db.db.commit(transaction).unwrap();
+
+        for index in [0, 1, 2, 500, 998, 999] {
+            let (block_number, entry) = db.entry_at_index(&slot_key, 999, head.clone().unwrap(), index).unwrap();
+            assert_eq!((block_number, entry.value), (index, StarkFelt::from(index)));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use crate::{DatabaseSettings, DatabaseSource};
+
+/// Statistics about a column of the database
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnStats {
+    pub column: u32,
+    pub name: &'static str,
+    /// Number of entries in the column
+    pub entries: u64,
+    /// Total size of the values stored in the column, in bytes
+    pub values_size: u64,
+}
+
+pub(crate) fn column_stats(config: &DatabaseSettings) -> Result<Vec<ColumnStats>, String> {
+    match &config.source {
+        DatabaseSource::ParityDb { path } => parity_db_column_stats(path),
+        DatabaseSource::RocksDb { path, .. } => rocksdb_column_stats(path),
+        DatabaseSource::Auto { paritydb_path, rocksdb_path, .. } => {
+            rocksdb_column_stats(rocksdb_path).or_else(|_| parity_db_column_stats(paritydb_path))
+        }
+        _ => Err("Supported db sources: `rocksdb` | `paritydb` | `auto`".to_string()),
+    }
+}
+
+fn stats(column: u32, entries: u64, values_size: u64) -> ColumnStats {
+    ColumnStats { column, name: crate::columns::NAMES[column as usize], entries, values_size }
+}
+
+#[cfg(feature = "rocksdb")]
+fn rocksdb_column_stats(path: &Path) -> Result<Vec<ColumnStats>, String> {
+    use crate::db_opening_utils::rocksdb_adapter::column_family_name;
+
+    let options = rocksdb::Options::default();
+    // Databases created before a column was added don't have its column family yet
+    let existing_column_families = rocksdb::DB::list_cf(&options, path).map_err(|err| format!("{}", err))?;
+    let db = rocksdb::DB::open_cf_for_read_only(&options, path, &existing_column_families, false)
+        .map_err(|err| format!("{}", err))?;
+
+    (0..crate::columns::NUM_COLUMNS)
+        .map(|column| {
+            let (mut entries, mut values_size) = (0, 0);
+            if let Some(column_family) = db.cf_handle(&column_family_name(column)) {
+                for entry in db.iterator_cf(column_family, rocksdb::IteratorMode::Start) {
+                    let (_, value) = entry.map_err(|err| format!("{}", err))?;
+                    entries += 1;
+                    values_size += value.len() as u64;
+                }
+            }
+            Ok(stats(column, entries, values_size))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "rocksdb"))]
+fn rocksdb_column_stats(_path: &Path) -> Result<Vec<ColumnStats>, String> {
+    Err("Missing feature flags `rocksdb`".to_string())
+}
+
+#[cfg(feature = "parity-db")]
+fn parity_db_column_stats(path: &Path) -> Result<Vec<ColumnStats>, String> {
+    let mut config = parity_db::Options::with_columns(path, crate::columns::NUM_COLUMNS as u8);
+    config.columns[crate::columns::BLOCK_MAPPING as usize].btree_index = true;
+    crate::db_opening_utils::parity_db_adapter::set_ref_counted_columns(&mut config, crate::columns::REF_COUNTED);
+    let db = parity_db::Db::open_read_only(&config).map_err(|err| format!("{}", err))?;
+
+    (0..crate::columns::NUM_COLUMNS)
+        .map(|column| {
+            let (mut entries, mut values_size) = (0, 0);
+            if config.columns[column as usize].btree_index {
+                let mut iter = db.iter(column as u8).map_err(|err| format!("{}", err))?;
+                iter.seek_to_first().map_err(|err| format!("{}", err))?;
+                while let Some((_, value)) = iter.next().map_err(|err| format!("{}", err))? {
+                    entries += 1;
+                    values_size += value.len() as u64;
+                }
+            } else {
+                db.iter_column_while(column as u8, |state| {
+                    entries += 1;
+                    values_size += state.value.len() as u64;
+                    true
+                })
+                .map_err(|err| format!("{}", err))?;
+            }
+            Ok(stats(column, entries, values_size))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "parity-db"))]
+fn parity_db_column_stats(_path: &Path) -> Result<Vec<ColumnStats>, String> {
+    Err("Missing feature flags `parity-db`".to_string())
+}
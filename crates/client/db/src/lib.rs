@@ -8,8 +8,9 @@
 //! requested Starknet one, we maintain a StarknetBlockHash to SubstrateBlock hash mapping.
 //!
 //! # Databases supported
-//! `paritydb` and `rocksdb` are both supported, behind the `kvdb-rocksd` and `parity-db` feature
+//! `paritydb` and `rocksdb` are both supported, behind the `rocksdb` and `parity-db` feature
 //! flags. Support for custom databases is possible but not supported yet.
+//! The RocksDB columns can be tuned with [`DatabaseOptions`].
 
 mod error;
 pub use error::DbError;
@@ -17,27 +18,40 @@ pub use error::DbError;
 mod mapping_db;
 pub use mapping_db::MappingCommitment;
 use sierra_classes_db::SierraClassesDb;
-use starknet_api::hash::StarkHash;
 mod da_db;
 mod db_opening_utils;
 mod messaging_db;
 pub mod sierra_classes_db;
 pub use messaging_db::LastSyncedEventBlock;
+mod historical_storage_db;
+mod inspect;
 mod l1_handler_tx_fee;
 mod meta_db;
-
+pub use meta_db::{GatewaySyncedBlock, IndexingBackfill, L1AcceptedBlock};
+pub mod migrations;
+mod options;
+mod receipts_db;
+mod state_diffs_db;
+mod storage_mode;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use da_db::DaDb;
+pub use historical_storage_db::{HistoricalStorageDb, StorageDiff};
+pub use inspect::ColumnStats;
 use l1_handler_tx_fee::L1HandlerTxFeeDb;
 use mapping_db::MappingDb;
 use messaging_db::MessagingDb;
 use meta_db::MetaDb;
+pub use options::{ColumnOptions, Compression, DatabaseOptions};
+use receipts_db::ReceiptsDb;
+pub use receipts_db::{FeeUnit, StoredTransactionReceipt, TransactionReceiptKind};
 use sc_client_db::DatabaseSource;
 use sp_database::Database;
 use sp_runtime::traits::Block as BlockT;
+use state_diffs_db::StateDiffsDb;
+pub use storage_mode::{StorageMode, DEFAULT_PRUNING_BLOCKS};
 
 const DB_HASH_LEN: usize = 32;
 /// Hash type that this backend uses for the database.
@@ -46,14 +60,14 @@ pub type DbHash = [u8; DB_HASH_LEN];
 struct DatabaseSettings {
     /// Where to find the database.
     pub source: DatabaseSource,
+    pub options: DatabaseOptions,
 }
 
 pub(crate) mod columns {
     /// Total number of columns.
-    // ===== /!\ ===================================================================================
-    // MUST BE INCREMENTED WHEN A NEW COLUMN IN ADDED
-    // ===== /!\ ===================================================================================
-    pub const NUM_COLUMNS: u32 = 8;
+    ///
+    /// Adding a column changes the database schema, see [`crate::migrations`].
+    pub const NUM_COLUMNS: u32 = STATE_DIFFS + 1;
 
     pub const META: u32 = 0;
     pub const BLOCK_MAPPING: u32 = 1;
@@ -64,17 +78,62 @@ pub(crate) mod columns {
     /// This column contains last synchronized L1 block.
     pub const MESSAGING: u32 = 5;
 
-    /// This column contains the Sierra contract classes
+    /// This column contains the Sierra contract classes stored before schema version 2, which are
+    /// still read, the new ones are stored in [`SIERRA_CLASSES`]
     pub const SIERRA_CONTRACT_CLASSES: u32 = 6;
 
     /// This column stores the fee paid on l1 for L1Handler transactions
     pub const L1_HANDLER_PAID_FEE: u32 = 7;
+
+    /// This column stores the receipt of each transaction, by transaction hash
+    pub const TRANSACTION_RECEIPTS: u32 = 8;
+
+    /// This column stores the history of each storage slot, only written in archive mode, see
+    /// [`crate::HistoricalStorageDb`]
+    pub const HISTORICAL_STORAGE: u32 = 9;
+
+    /// This column contains the Sierra contract classes by class hash, reference counted as the
+    /// same class can be declared by several blocks, on different forks for instance
+    pub const SIERRA_CLASSES: u32 = 10;
+
+    /// This column stores the state diff of each block, by Substrate block hash
+    pub const STATE_DIFFS: u32 = 11;
+
+    /// Columns whose values are reference counted, written with `Store`, `Reference` and
+    /// `Release` changes rather than `Set` and `Remove`
+    ///
+    /// Values shared by many keys, such as classes addressed by their hash, belong to such
+    /// columns. Making an existing column reference counted changes the database schema, see
+    /// [`crate::migrations`].
+    pub const REF_COUNTED: &[u32] = &[SIERRA_CLASSES];
+
+    /// Name of each column, for display purposes
+    pub const NAMES: [&str; NUM_COLUMNS as usize] = [
+        "meta",
+        "block_mapping",
+        "transaction_mapping",
+        "synced_mapping",
+        "da",
+        "messaging",
+        "sierra_contract_classes",
+        "l1_handler_paid_fee",
+        "transaction_receipts",
+        "historical_storage",
+        "sierra_classes",
+        "state_diffs",
+    ];
 }
 
 pub mod static_keys {
     pub const CURRENT_SYNCING_TIPS: &[u8] = b"CURRENT_SYNCING_TIPS";
     pub const LAST_PROVED_BLOCK: &[u8] = b"LAST_PROVED_BLOCK";
+    pub const LAST_L1_ACCEPTED_BLOCK: &[u8] = b"LAST_L1_ACCEPTED_BLOCK";
+    pub const LAST_GATEWAY_SYNCED_BLOCK: &[u8] = b"LAST_GATEWAY_SYNCED_BLOCK";
     pub const LAST_SYNCED_L1_EVENT_BLOCK: &[u8] = b"LAST_SYNCED_L1_EVENT_BLOCK";
+    pub const STORAGE_MODE: &[u8] = b"STORAGE_MODE";
+    pub const SCHEMA_VERSION: &[u8] = b"SCHEMA_VERSION";
+    pub const INDEXING_BACKFILL: &[u8] = b"INDEXING_BACKFILL";
+    pub const LAST_INDEXED_STORAGE_BLOCK: &[u8] = b"LAST_INDEXED_STORAGE_BLOCK";
 }
 
 /// The Madara client database backend
@@ -89,6 +148,10 @@ pub struct Backend<B: BlockT> {
     messaging: Arc<MessagingDb>,
     sierra_classes: Arc<SierraClassesDb>,
     l1_handler_paid_fee: Arc<L1HandlerTxFeeDb>,
+    receipts: Arc<ReceiptsDb>,
+    state_diffs: Arc<StateDiffsDb<B>>,
+    historical_storage: Arc<HistoricalStorageDb<B>>,
+    storage_mode: StorageMode,
 }
 
 /// Returns the Starknet database directory.
@@ -96,40 +159,139 @@ pub fn starknet_database_dir(db_config_dir: &Path, db_path: &str) -> PathBuf {
     db_config_dir.join("starknet").join(db_path)
 }
 
+/// Return the location of the Starknet database, next to the Substrate one
+fn starknet_database_source(database: &DatabaseSource, db_config_dir: &Path) -> Result<DatabaseSource, String> {
+    Ok(match database {
+        DatabaseSource::RocksDb { .. } => {
+            DatabaseSource::RocksDb { path: starknet_database_dir(db_config_dir, "rockdb"), cache_size: 0 }
+        }
+        DatabaseSource::ParityDb { .. } => {
+            DatabaseSource::ParityDb { path: starknet_database_dir(db_config_dir, "paritydb") }
+        }
+        DatabaseSource::Auto { .. } => DatabaseSource::Auto {
+            rocksdb_path: starknet_database_dir(db_config_dir, "rockdb"),
+            paritydb_path: starknet_database_dir(db_config_dir, "paritydb"),
+            cache_size: 0,
+        },
+        _ => return Err("Supported db sources: `rocksdb` | `paritydb` | `auto`".to_string()),
+    })
+}
+
+/// Open the Starknet database without running the schema migrations
+fn open_database(
+    database: &DatabaseSource,
+    db_config_dir: &Path,
+    options: &DatabaseOptions,
+) -> Result<Arc<dyn Database<DbHash>>, String> {
+    db_opening_utils::open_database(&DatabaseSettings {
+        source: starknet_database_source(database, db_config_dir)?,
+        options: options.clone(),
+    })
+}
+
+/// Return the number of entries and the size of each column of the Starknet database
+///
+/// The database must not be opened elsewhere, which means the node has to be stopped.
+pub fn column_stats(database: &DatabaseSource, db_config_dir: &Path) -> Result<Vec<ColumnStats>, String> {
+    inspect::column_stats(&DatabaseSettings {
+        source: starknet_database_source(database, db_config_dir)?,
+        options: DatabaseOptions::default(),
+    })
+}
+
+/// Return the schema version of the Starknet database and the migrations that would be run
+/// when opening it, without running them
+pub fn pending_migrations(
+    database: &DatabaseSource,
+    db_config_dir: &Path,
+) -> Result<(u32, &'static [migrations::Migration]), String> {
+    let db = open_database(database, db_config_dir, &DatabaseOptions::default())?;
+    let schema_version = migrations::schema_version(&*db).map_err(|e| e.to_string())?;
+    let pending = migrations::pending_migrations(schema_version).map_err(|e| e.to_string())?;
+
+    Ok((schema_version, pending))
+}
+
+/// Run the pending schema migrations of the Starknet database and return the ones applied
+pub fn migrate(database: &DatabaseSource, db_config_dir: &Path) -> Result<&'static [migrations::Migration], String> {
+    let db = open_database(database, db_config_dir, &DatabaseOptions::default())?;
+
+    migrations::migrate(&*db).map_err(|e| e.to_string())
+}
+
 impl<B: BlockT> Backend<B> {
     /// Open the database
     ///
-    /// The database will be created at db_config_dir.join(<db_type_name>)
-    pub fn open(database: &DatabaseSource, db_config_dir: &Path) -> Result<Self, String> {
-        Self::new(&DatabaseSettings {
-            source: match database {
-                DatabaseSource::RocksDb { .. } => {
-                    DatabaseSource::RocksDb { path: starknet_database_dir(db_config_dir, "rockdb"), cache_size: 0 }
-                }
-                DatabaseSource::ParityDb { .. } => {
-                    DatabaseSource::ParityDb { path: starknet_database_dir(db_config_dir, "paritydb") }
-                }
-                DatabaseSource::Auto { .. } => DatabaseSource::Auto {
-                    rocksdb_path: starknet_database_dir(db_config_dir, "rockdb"),
-                    paritydb_path: starknet_database_dir(db_config_dir, "paritydb"),
-                    cache_size: 0,
-                },
-                _ => return Err("Supported db sources: `rocksdb` | `paritydb` | `auto`".to_string()),
-            },
-        })
+    /// The database will be created at db_config_dir.join(<db_type_name>) and the pending schema
+    /// migrations are run, see [`migrations`].
+    ///
+    /// When `storage_mode` is `None`, the mode recorded in the database is used, or the default
+    /// one for a new database. Opening an existing database in an incompatible mode fails.
+    /// `options` are only used by the RocksDB backend.
+    pub fn open(
+        database: &DatabaseSource,
+        db_config_dir: &Path,
+        storage_mode: Option<StorageMode>,
+        options: &DatabaseOptions,
+    ) -> Result<Self, String> {
+        let db = open_database(database, db_config_dir, options)?;
+
+        migrations::migrate(&*db).map_err(|e| e.to_string())?;
+
+        let meta = MetaDb::<B> { db: db.clone(), _marker: PhantomData };
+        let recorded_storage_mode = meta.storage_mode().map_err(|e| e.to_string())?;
+        let storage_mode = match (recorded_storage_mode, storage_mode) {
+            (Some(recorded), Some(requested)) if !recorded.is_compatible_with(&requested) => {
+                return Err(format!(
+                    "The database was created in {recorded} mode and cannot be opened in {requested} mode"
+                ));
+            }
+            (_, Some(requested)) => requested,
+            (Some(recorded), None) => recorded,
+            (None, None) => StorageMode::default(),
+        };
+        if recorded_storage_mode != Some(storage_mode) {
+            meta.write_storage_mode(storage_mode).map_err(|e| e.to_string())?;
+        }
+
+        Ok(Self::new(db, storage_mode))
     }
 
-    fn new(config: &DatabaseSettings) -> Result<Self, String> {
-        let db = db_opening_utils::open_database(config)?;
+    /// Open an existing database without modifying it, for the commands checking its content
+    ///
+    /// Fails if the database has pending schema migrations, see [`migrate`].
+    pub fn open_without_migrating(database: &DatabaseSource, db_config_dir: &Path) -> Result<Self, String> {
+        let db = open_database(database, db_config_dir, &DatabaseOptions::default())?;
+
+        let schema_version = migrations::schema_version(&*db).map_err(|e| e.to_string())?;
+        if !migrations::pending_migrations(schema_version).map_err(|e| e.to_string())?.is_empty() {
+            return Err(format!(
+                "The database is at schema version {schema_version} and has to be migrated to version {}, run `madara \
+                 db migrate` first",
+                migrations::CURRENT_SCHEMA_VERSION
+            ));
+        }
 
-        Ok(Self {
-            mapping: Arc::new(MappingDb::new(db.clone())),
+        let meta = MetaDb::<B> { db: db.clone(), _marker: PhantomData };
+        let storage_mode = meta.storage_mode().map_err(|e| e.to_string())?.unwrap_or_default();
+
+        Ok(Self::new(db, storage_mode))
+    }
+
+    fn new(db: Arc<dyn Database<DbHash>>, storage_mode: StorageMode) -> Self {
+        let historical_storage = Arc::new(HistoricalStorageDb { db: db.clone(), _marker: PhantomData });
+        Self {
+            mapping: Arc::new(MappingDb::new(db.clone(), historical_storage.clone())),
             meta: Arc::new(MetaDb { db: db.clone(), _marker: PhantomData }),
             da: Arc::new(DaDb { db: db.clone() }),
             messaging: Arc::new(MessagingDb { db: db.clone() }),
             sierra_classes: Arc::new(SierraClassesDb { db: db.clone() }),
             l1_handler_paid_fee: Arc::new(L1HandlerTxFeeDb { db: db.clone() }),
-        })
+            receipts: Arc::new(ReceiptsDb { db: db.clone() }),
+            state_diffs: Arc::new(StateDiffsDb { db: db.clone(), _marker: PhantomData }),
+            historical_storage,
+            storage_mode,
+        }
     }
 
     /// Return the mapping database manager
@@ -162,10 +324,23 @@ impl<B: BlockT> Backend<B> {
         &self.l1_handler_paid_fee
     }
 
-    /// In the future, we will compute the block global state root asynchronously in the client,
-    /// using the Starknet-Bonzai-trie.
-    /// That what replaces it for now :)
-    pub fn temporary_global_state_root_getter(&self) -> StarkHash {
-        Default::default()
+    /// Return the transaction receipts database manager
+    pub fn receipts(&self) -> &Arc<ReceiptsDb> {
+        &self.receipts
+    }
+
+    /// Return the block state diffs database manager
+    pub fn state_diffs(&self) -> &Arc<StateDiffsDb<B>> {
+        &self.state_diffs
+    }
+
+    /// Return the historical storage database manager
+    pub fn historical_storage(&self) -> &Arc<HistoricalStorageDb<B>> {
+        &self.historical_storage
+    }
+
+    /// Return the storage mode the database was opened with
+    pub fn storage_mode(&self) -> StorageMode {
+        self.storage_mode
     }
 }
@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
 
+use mp_block::commitments::BlockStateDiff;
 // Substrate
 use parity_scale_codec::{Decode, Encode};
 use sp_database::Database;
@@ -8,7 +9,7 @@ use sp_runtime::traits::Block as BlockT;
 use starknet_api::block::BlockHash;
 use starknet_api::transaction::TransactionHash;
 
-use crate::{DbError, DbHash};
+use crate::{DbError, DbHash, HistoricalStorageDb, StorageDiff, StoredTransactionReceipt};
 
 /// The mapping to write in db
 #[derive(Debug)]
@@ -16,19 +17,29 @@ pub struct MappingCommitment<B: BlockT> {
     pub block_hash: B::Hash,
     pub starknet_block_hash: BlockHash,
     pub starknet_transaction_hashes: Vec<TransactionHash>,
+    pub starknet_transaction_receipts: Vec<StoredTransactionReceipt>,
+    pub starknet_block_number: u64,
+    /// Final value of each storage slot written by the block, only computed in archive mode
+    ///
+    /// `None` when the node is not in archive mode or when the receipts of the block could not be
+    /// computed, in which case the diffs are written along the receipts later on.
+    pub starknet_storage_diffs: Option<Vec<StorageDiff>>,
+    /// State diff of the block, `None` when the runtime did not record it
+    pub starknet_state_diff: Option<BlockStateDiff>,
 }
 
 /// Allow interaction with the mapping db
 pub struct MappingDb<B: BlockT> {
     db: Arc<dyn Database<DbHash>>,
+    historical_storage: Arc<HistoricalStorageDb<B>>,
     write_lock: Arc<Mutex<()>>,
     _marker: PhantomData<B>,
 }
 
 impl<B: BlockT> MappingDb<B> {
     /// Creates a new instance of the mapping database.
-    pub fn new(db: Arc<dyn Database<DbHash>>) -> Self {
-        Self { db, write_lock: Arc::new(Mutex::new(())), _marker: PhantomData }
+    pub fn new(db: Arc<dyn Database<DbHash>>, historical_storage: Arc<HistoricalStorageDb<B>>) -> Self {
+        Self { db, historical_storage, write_lock: Arc::new(Mutex::new(())), _marker: PhantomData }
     }
 
     /// Check if the given block hash has already been processed
@@ -70,6 +81,8 @@ impl<B: BlockT> MappingDb<B> {
         let mut transaction = sp_database::Transaction::new();
 
         let substrate_hashes = match self.block_hash(commitment.starknet_block_hash) {
+            // The block is synced again
+            Ok(Some(data)) if data.contains(&commitment.block_hash) => data,
             Ok(Some(mut data)) => {
                 data.push(commitment.block_hash);
                 log::warn!(
@@ -91,6 +104,10 @@ impl<B: BlockT> MappingDb<B> {
 
         transaction.set(crate::columns::SYNCED_MAPPING, &commitment.block_hash.encode(), &true.encode());
 
+        if let Some(state_diff) = &commitment.starknet_state_diff {
+            transaction.set(crate::columns::STATE_DIFFS, &commitment.block_hash.encode(), &state_diff.encode());
+        }
+
         for transaction_hash in commitment.starknet_transaction_hashes.iter() {
             transaction.set(
                 crate::columns::TRANSACTION_MAPPING,
@@ -99,8 +116,94 @@ impl<B: BlockT> MappingDb<B> {
             );
         }
 
+        self.set_receipts(
+            &mut transaction,
+            &commitment.block_hash,
+            commitment.starknet_block_number,
+            &commitment.starknet_transaction_receipts,
+            commitment.starknet_storage_diffs.as_deref(),
+        )?;
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Remove the Substrate blocks for which `is_stale` returns `true` from the ones the Starknet
+    /// block with given hash is mapped to, along with their synced flag and state diff, and return
+    /// how many were removed
+    pub fn purge_block_hashes(
+        &self,
+        starknet_block_hash: BlockHash,
+        is_stale: impl Fn(&B::Hash) -> bool,
+    ) -> Result<usize, DbError> {
+        let _lock = self.write_lock.lock();
+
+        let Some(substrate_hashes) = self.block_hash(starknet_block_hash)? else {
+            return Ok(0);
+        };
+        let (stale_hashes, substrate_hashes): (Vec<_>, Vec<_>) =
+            substrate_hashes.into_iter().partition(|block_hash| is_stale(block_hash));
+        if stale_hashes.is_empty() {
+            return Ok(0);
+        }
+
+        let mut transaction = sp_database::Transaction::new();
+
+        for block_hash in &stale_hashes {
+            transaction.remove(crate::columns::SYNCED_MAPPING, &block_hash.encode());
+            transaction.remove(crate::columns::STATE_DIFFS, &block_hash.encode());
+        }
+        if substrate_hashes.is_empty() {
+            transaction.remove(crate::columns::BLOCK_MAPPING, &starknet_block_hash.encode());
+        } else {
+            transaction.set(crate::columns::BLOCK_MAPPING, &starknet_block_hash.encode(), &substrate_hashes.encode());
+        }
+
         self.db.commit(transaction)?;
 
+        Ok(stale_hashes.len())
+    }
+
+    /// Write the receipts and storage diffs of a block synced without them
+    pub fn write_receipts(
+        &self,
+        block_hash: &B::Hash,
+        starknet_block_number: u64,
+        receipts: &[StoredTransactionReceipt],
+        storage_diffs: Option<&[StorageDiff]>,
+    ) -> Result<(), DbError> {
+        let _lock = self.write_lock.lock();
+
+        let mut transaction = sp_database::Transaction::new();
+
+        self.set_receipts(&mut transaction, block_hash, starknet_block_number, receipts, storage_diffs)?;
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    fn set_receipts(
+        &self,
+        transaction: &mut sp_database::Transaction<DbHash>,
+        block_hash: &B::Hash,
+        starknet_block_number: u64,
+        receipts: &[StoredTransactionReceipt],
+        storage_diffs: Option<&[StorageDiff]>,
+    ) -> Result<(), DbError> {
+        for receipt in receipts {
+            transaction.set(
+                crate::columns::TRANSACTION_RECEIPTS,
+                &receipt.transaction_hash.encode(),
+                &receipt.encode(),
+            );
+        }
+
+        if let Some(storage_diffs) = storage_diffs {
+            self.historical_storage.set_block_diffs(transaction, block_hash, starknet_block_number, storage_diffs)?;
+        }
+
         Ok(())
     }
 
@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
@@ -5,8 +6,58 @@ use std::sync::Arc;
 use parity_scale_codec::{Decode, Encode};
 use sp_database::Database;
 use sp_runtime::traits::Block as BlockT;
+use starknet_api::hash::StarkHash;
 
-use crate::{DbError, DbHash};
+use crate::{DbError, DbHash, StorageMode};
+
+/// The last Starknet block whose state has been accepted on L1 by the core contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct L1AcceptedBlock {
+    pub block_number: u64,
+    pub block_hash: StarkHash,
+}
+
+/// The last block imported from the feeder gateway of an upstream sequencer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct GatewaySyncedBlock {
+    pub block_number: u64,
+    pub block_hash: StarkHash,
+}
+
+/// Blocks synced without their receipts, and storage diffs in archive mode, left to index.
+///
+/// Receipts are built from the execution outcome recorded at import, or by re-executing the blocks
+/// imported before it was recorded. A block whose receipts could not be computed when it was
+/// synced is retried once, then skipped. The blocks synced before receipts were persisted are
+/// indexed from `next_block` down to the first block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct IndexingBackfill {
+    /// Next block to index, `None` to start from the best block
+    pub next_block: Option<u64>,
+    /// Blocks to index again before going on with `next_block`
+    pub retry_blocks: BTreeSet<u64>,
+    /// Blocks whose receipts could not be computed, the RPC does not serve them
+    pub skipped_blocks: BTreeSet<u64>,
+}
+
+impl IndexingBackfill {
+    /// Backfill of every block synced so far
+    pub fn from_best_block() -> Self {
+        Self { next_block: None, ..Default::default() }
+    }
+
+    /// Returns `true` if nothing is left to index
+    pub fn is_done(&self) -> bool {
+        self.next_block == Some(0) && self.retry_blocks.is_empty()
+    }
+
+    /// Returns `true` if the receipts and storage diffs of the block have been indexed
+    pub fn is_indexed(&self, block_number: u64) -> bool {
+        self.next_block.is_some_and(|next_block| block_number > next_block)
+            && !self.retry_blocks.contains(&block_number)
+            && !self.skipped_blocks.contains(&block_number)
+    }
+}
 
 /// Allow interaction with the meta db
 ///
@@ -36,4 +87,145 @@ impl<B: BlockT> MetaDb<B> {
 
         Ok(())
     }
+
+    /// Retrieve the storage mode the database was created with, if it has been recorded
+    pub fn storage_mode(&self) -> Result<Option<StorageMode>, DbError> {
+        match self.db.get(crate::columns::META, crate::static_keys::STORAGE_MODE) {
+            Some(raw) => Ok(Some(StorageMode::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record the storage mode of the database
+    pub fn write_storage_mode(&self, storage_mode: StorageMode) -> Result<(), DbError> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.set(crate::columns::META, crate::static_keys::STORAGE_MODE, &storage_mode.encode());
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Retrieve the last block accepted on L1, if any
+    pub fn last_l1_accepted_block(&self) -> Result<Option<L1AcceptedBlock>, DbError> {
+        match self.db.get(crate::columns::META, crate::static_keys::LAST_L1_ACCEPTED_BLOCK) {
+            Some(raw) => Ok(Some(L1AcceptedBlock::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the last block accepted on L1
+    pub fn write_last_l1_accepted_block(&self, block: &L1AcceptedBlock) -> Result<(), DbError> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.set(crate::columns::META, crate::static_keys::LAST_L1_ACCEPTED_BLOCK, &block.encode());
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Retrieve the last block imported from the feeder gateway, if any
+    pub fn last_gateway_synced_block(&self) -> Result<Option<GatewaySyncedBlock>, DbError> {
+        match self.db.get(crate::columns::META, crate::static_keys::LAST_GATEWAY_SYNCED_BLOCK) {
+            Some(raw) => Ok(Some(GatewaySyncedBlock::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the last block imported from the feeder gateway
+    pub fn write_last_gateway_synced_block(&self, block: &GatewaySyncedBlock) -> Result<(), DbError> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.set(crate::columns::META, crate::static_keys::LAST_GATEWAY_SYNCED_BLOCK, &block.encode());
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Retrieve the blocks left to index, if any
+    pub fn indexing_backfill(&self) -> Result<Option<IndexingBackfill>, DbError> {
+        match self.db.get(crate::columns::META, crate::static_keys::INDEXING_BACKFILL) {
+            Some(raw) => Ok(Some(IndexingBackfill::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the blocks left to index, removing the record once every block has been indexed
+    pub fn write_indexing_backfill(&self, backfill: &IndexingBackfill) -> Result<(), DbError> {
+        let mut transaction = sp_database::Transaction::new();
+
+        if backfill.is_done() && backfill.skipped_blocks.is_empty() {
+            transaction.remove(crate::columns::META, crate::static_keys::INDEXING_BACKFILL);
+        } else {
+            transaction.set(crate::columns::META, crate::static_keys::INDEXING_BACKFILL, &backfill.encode());
+        }
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Schedule the indexing of a block whose receipts could not be computed when it was synced
+    pub fn schedule_indexing_retry(&self, block_number: u64) -> Result<(), DbError> {
+        let mut backfill =
+            self.indexing_backfill()?.unwrap_or(IndexingBackfill { next_block: Some(0), ..Default::default() });
+        backfill.retry_blocks.insert(block_number);
+
+        self.write_indexing_backfill(&backfill)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_database::MemDb;
+    use sp_runtime::generic::{Block as GenericBlock, Header};
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_runtime::OpaqueExtrinsic;
+
+    use super::*;
+
+    type Block = GenericBlock<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+    fn meta_db() -> MetaDb<Block> {
+        MetaDb { db: Arc::new(MemDb::new()), _marker: PhantomData }
+    }
+
+    #[test]
+    fn failed_blocks_are_retried_then_skipped() {
+        let meta = meta_db();
+        assert_eq!(meta.indexing_backfill().unwrap(), None);
+
+        meta.schedule_indexing_retry(5).unwrap();
+        let mut backfill = meta.indexing_backfill().unwrap().unwrap();
+        assert!(!backfill.is_done());
+        assert!(!backfill.is_indexed(5));
+        assert!(backfill.is_indexed(4));
+
+        // The retry failed as well
+        backfill.retry_blocks.remove(&5);
+        backfill.skipped_blocks.insert(5);
+        meta.write_indexing_backfill(&backfill).unwrap();
+        let backfill = meta.indexing_backfill().unwrap().unwrap();
+        assert!(backfill.is_done());
+        assert!(!backfill.is_indexed(5));
+    }
+
+    #[test]
+    fn backfill_is_removed_once_done() {
+        let meta = meta_db();
+        let mut backfill = IndexingBackfill::from_best_block();
+        meta.write_indexing_backfill(&backfill).unwrap();
+        assert!(!meta.indexing_backfill().unwrap().unwrap().is_indexed(1));
+
+        backfill.next_block = Some(10);
+        assert!(backfill.is_indexed(11));
+        assert!(!backfill.is_indexed(10));
+
+        backfill.next_block = Some(0);
+        meta.write_indexing_backfill(&backfill).unwrap();
+        assert_eq!(meta.indexing_backfill().unwrap(), None);
+    }
 }
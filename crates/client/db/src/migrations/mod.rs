@@ -0,0 +1,193 @@
+//! Versioning of the database schema
+//!
+//! The version of the schema a database was written with is stored in the meta column. When the
+//! layout of a column or the encoding of a value changes, [`CURRENT_SCHEMA_VERSION`] is bumped and
+//! a [`Migration`] upgrading databases from the previous version is appended to [`MIGRATIONS`].
+//! Pending migrations are run in order when the database is opened, see [`crate::Backend::open`].
+//!
+//! Migrations must be idempotent: a node stopped in the middle of a migration runs it again on
+//! restart, as the schema version is only bumped once the migration succeeded.
+
+#[cfg(test)]
+mod tests;
+
+use parity_scale_codec::{Decode, Encode};
+use sp_database::Database;
+
+use crate::{DbError, DbHash};
+
+/// Version of the schema written by this version of the node
+pub const CURRENT_SCHEMA_VERSION: u32 = 4;
+
+/// An upgrade of the database from one schema version to the next one
+pub struct Migration {
+    /// Version of the schema the migration upgrades from, the database is at version
+    /// `from_version + 1` once it ran
+    pub from_version: u32,
+    /// What the migration does, displayed to the node operator
+    pub description: &'static str,
+    migrate: fn(&dyn Database<DbHash>) -> Result<(), DbError>,
+}
+
+/// Every migration, ordered by `from_version`
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        from_version: 0,
+        description: "Check the values written before the schema was versioned can still be decoded and schedule the \
+                      indexing of the receipts of the synced blocks",
+        migrate: upgrade_unversioned_database,
+    },
+    Migration {
+        from_version: 1,
+        description: "Store the new Sierra classes in a reference counted column",
+        migrate: add_sierra_classes_column,
+    },
+    Migration {
+        from_version: 2,
+        description: "Store the state diff of the blocks imported from now on",
+        migrate: add_state_diffs_column,
+    },
+    Migration {
+        from_version: 3,
+        description: "Index the receipts of the synced blocks again to record the paymaster of the sponsored \
+                      transactions",
+        migrate: reindex_receipts_with_paymaster,
+    },
+];
+
+/// Return the schema version of the database
+///
+/// Databases written before the schema was versioned are at version 0. A new database, without
+/// any value in the meta column, is at the current version.
+pub fn schema_version(db: &dyn Database<DbHash>) -> Result<u32, DbError> {
+    match db.get(crate::columns::META, crate::static_keys::SCHEMA_VERSION) {
+        Some(raw) => Ok(u32::decode(&mut &raw[..])?),
+        None if is_unversioned(db) => Ok(0),
+        None => Ok(CURRENT_SCHEMA_VERSION),
+    }
+}
+
+fn write_schema_version(db: &dyn Database<DbHash>, version: u32) -> Result<(), DbError> {
+    let mut transaction = sp_database::Transaction::new();
+
+    transaction.set(crate::columns::META, crate::static_keys::SCHEMA_VERSION, &version.encode());
+
+    db.commit(transaction)?;
+
+    Ok(())
+}
+
+/// Return the migrations to run to bring a database at `schema_version` to the current version
+pub fn pending_migrations(schema_version: u32) -> Result<&'static [Migration], DbError> {
+    if schema_version > CURRENT_SCHEMA_VERSION {
+        return Err(DbError::UnsupportedSchemaVersion(schema_version, CURRENT_SCHEMA_VERSION));
+    }
+
+    let first_pending = MIGRATIONS.partition_point(|migration| migration.from_version < schema_version);
+    Ok(&MIGRATIONS[first_pending..])
+}
+
+/// Run the pending migrations and return the ones that were applied
+pub fn migrate(db: &dyn Database<DbHash>) -> Result<&'static [Migration], DbError> {
+    let migrations = pending_migrations(schema_version(db)?)?;
+
+    for migration in migrations {
+        log::info!(
+            "🛠️  Migrating the Madara database from schema version {} to {}: {}",
+            migration.from_version,
+            migration.from_version + 1,
+            migration.description
+        );
+        (migration.migrate)(db).map_err(|e| DbError::MigrationFailed(migration.from_version, Box::new(e)))?;
+        write_schema_version(db, migration.from_version + 1)?;
+    }
+
+    // Stamp new databases, so that they are not mistaken for unversioned ones later on
+    if db.get(crate::columns::META, crate::static_keys::SCHEMA_VERSION).is_none() {
+        write_schema_version(db, CURRENT_SCHEMA_VERSION)?;
+    }
+
+    Ok(migrations)
+}
+
+/// Unversioned databases always contain the syncing tips once a block has been synced
+fn is_unversioned(db: &dyn Database<DbHash>) -> bool {
+    db.contains(crate::columns::META, crate::static_keys::CURRENT_SYNCING_TIPS)
+        || db.contains(crate::columns::MESSAGING, crate::static_keys::LAST_SYNCED_L1_EVENT_BLOCK)
+        || db.contains(crate::columns::DA, crate::static_keys::LAST_PROVED_BLOCK)
+}
+
+/// Version 0 to 1
+///
+/// The layout did not change, but a value that cannot be decoded would otherwise only be noticed
+/// when it is read, possibly long after the node started.
+///
+/// The receipts, and storage diffs in archive mode, of the blocks synced by the nodes predating the
+/// schema versioning may be missing. They cannot be computed without the chain, so their indexing
+/// is scheduled for the mapping sync worker, see [`crate::IndexingBackfill`].
+fn upgrade_unversioned_database(db: &dyn Database<DbHash>) -> Result<(), DbError> {
+    check_unversioned_values(db)?;
+
+    let mut transaction = sp_database::Transaction::new();
+
+    transaction.set(
+        crate::columns::META,
+        crate::static_keys::INDEXING_BACKFILL,
+        &crate::IndexingBackfill::from_best_block().encode(),
+    );
+
+    db.commit(transaction)?;
+
+    Ok(())
+}
+
+fn check_unversioned_values(db: &dyn Database<DbHash>) -> Result<(), DbError> {
+    if let Some(raw) = db.get(crate::columns::META, crate::static_keys::CURRENT_SYNCING_TIPS) {
+        Vec::<DbHash>::decode(&mut &raw[..])?;
+    }
+    if let Some(raw) = db.get(crate::columns::MESSAGING, crate::static_keys::LAST_SYNCED_L1_EVENT_BLOCK) {
+        crate::LastSyncedEventBlock::decode(&mut &raw[..])?;
+    }
+    if let Some(raw) = db.get(crate::columns::DA, crate::static_keys::LAST_PROVED_BLOCK) {
+        starknet_api::hash::StarkFelt::decode(&mut &raw[..])?;
+    }
+
+    Ok(())
+}
+
+/// Version 1 to 2
+///
+/// The column is created when the database is opened. The classes stored before are left in
+/// [`crate::columns::SIERRA_CONTRACT_CLASSES`], as they cannot be listed through [`Database`], and
+/// are still read from there. The version bump keeps the nodes that only know the previous column
+/// from opening the database.
+fn add_sierra_classes_column(_db: &dyn Database<DbHash>) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Version 2 to 3
+///
+/// The column is created when the database is opened. The state diffs of the blocks imported
+/// before are not part of the chain state anymore, so they are not stored.
+fn add_state_diffs_column(_db: &dyn Database<DbHash>) -> Result<(), DbError> {
+    Ok(())
+}
+
+/// Version 3 to 4
+///
+/// The receipts now record the paymaster of the sponsored transactions. The receipts written before
+/// can still be decoded, without paymaster, until the indexing backfill writes them again, see
+/// [`crate::IndexingBackfill`]. The storage history index waits for the backfill in archive mode.
+fn reindex_receipts_with_paymaster(db: &dyn Database<DbHash>) -> Result<(), DbError> {
+    let mut transaction = sp_database::Transaction::new();
+
+    transaction.set(
+        crate::columns::META,
+        crate::static_keys::INDEXING_BACKFILL,
+        &crate::IndexingBackfill::from_best_block().encode(),
+    );
+
+    db.commit(transaction)?;
+
+    Ok(())
+}
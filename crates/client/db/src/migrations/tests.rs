@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use parity_scale_codec::{Decode, Encode};
+use sp_database::{Database, MemDb};
+use sp_runtime::generic::{Block as GenericBlock, Header};
+use sp_runtime::traits::BlakeTwo256;
+use sp_runtime::OpaqueExtrinsic;
+use starknet_api::hash::StarkFelt;
+
+use super::*;
+use crate::{DatabaseOptions, DatabaseSource, IndexingBackfill, LastSyncedEventBlock};
+
+type Block = GenericBlock<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+/// A database as written by a node predating the schema versioning
+fn unversioned_fixture() -> Arc<dyn Database<DbHash>> {
+    let db: Arc<dyn Database<DbHash>> = Arc::new(MemDb::new());
+    write_unversioned_values(&*db);
+
+    db
+}
+
+fn write_unversioned_values(db: &dyn Database<DbHash>) {
+    let mut transaction = sp_database::Transaction::new();
+    transaction.set(crate::columns::META, crate::static_keys::CURRENT_SYNCING_TIPS, &vec![[1u8; 32]].encode());
+    transaction.set(
+        crate::columns::MESSAGING,
+        crate::static_keys::LAST_SYNCED_L1_EVENT_BLOCK,
+        &LastSyncedEventBlock::new(42, 3).encode(),
+    );
+    transaction.set(crate::columns::DA, crate::static_keys::LAST_PROVED_BLOCK, &StarkFelt::from(7u128).encode());
+    transaction.set(crate::columns::BLOCK_MAPPING, &[2u8; 32], &vec![[3u8; 32]].encode());
+    db.commit(transaction).unwrap();
+}
+
+/// Check the values of the fixture survived the migrations, which scheduled the indexing of the
+/// receipts of the synced blocks
+fn assert_upgraded(db: &dyn Database<DbHash>) {
+    assert_eq!(schema_version(db).unwrap(), CURRENT_SCHEMA_VERSION);
+
+    let last_synced_event_block = LastSyncedEventBlock::decode(
+        &mut &db.get(crate::columns::MESSAGING, crate::static_keys::LAST_SYNCED_L1_EVENT_BLOCK).unwrap()[..],
+    )
+    .unwrap();
+    assert_eq!((last_synced_event_block.block_number, last_synced_event_block.event_index), (42, 3));
+    assert_eq!(db.get(crate::columns::BLOCK_MAPPING, &[2u8; 32]), Some(vec![[3u8; 32]].encode()));
+
+    let backfill = IndexingBackfill::decode(
+        &mut &db.get(crate::columns::META, crate::static_keys::INDEXING_BACKFILL).unwrap()[..],
+    )
+    .unwrap();
+    assert_eq!(backfill, IndexingBackfill::from_best_block());
+    assert!(!backfill.is_indexed(1));
+}
+
+/// Write the fixture to a database on disk, then reopen and migrate it as an upgraded node would
+fn upgrade_on_disk(database: DatabaseSource) {
+    let dir = tempfile::tempdir().unwrap();
+    let open = || crate::open_database(&database, dir.path(), &DatabaseOptions::default()).unwrap();
+
+    write_unversioned_values(&*open());
+    // The database is not migrated by the commands checking it
+    assert!(crate::Backend::<Block>::open_without_migrating(&database, dir.path()).is_err());
+
+    let db = open();
+    assert_eq!(schema_version(&*db).unwrap(), 0);
+    assert_eq!(migrate(&*db).unwrap().len(), MIGRATIONS.len());
+    drop(db);
+
+    let db = open();
+    assert!(migrate(&*db).unwrap().is_empty());
+    assert_upgraded(&*db);
+    drop(db);
+
+    assert!(crate::Backend::<Block>::open_without_migrating(&database, dir.path()).is_ok());
+}
+
+#[test]
+fn new_database_is_at_current_version() {
+    let db: Arc<dyn Database<DbHash>> = Arc::new(MemDb::new());
+
+    assert_eq!(schema_version(&*db).unwrap(), CURRENT_SCHEMA_VERSION);
+    assert!(migrate(&*db).unwrap().is_empty());
+    assert_eq!(db.get(crate::columns::META, crate::static_keys::SCHEMA_VERSION), Some(CURRENT_SCHEMA_VERSION.encode()));
+    // A new database has nothing to index
+    assert!(db.get(crate::columns::META, crate::static_keys::INDEXING_BACKFILL).is_none());
+}
+
+#[test]
+fn unversioned_database_is_upgraded() {
+    let db = unversioned_fixture();
+
+    assert_eq!(schema_version(&*db).unwrap(), 0);
+    assert_eq!(pending_migrations(0).unwrap().len(), MIGRATIONS.len());
+
+    let applied = migrate(&*db).unwrap();
+    assert_eq!(applied.len(), MIGRATIONS.len());
+    assert_upgraded(&*db);
+}
+
+#[cfg(feature = "rocksdb")]
+#[test]
+fn unversioned_rocksdb_database_is_upgraded() {
+    upgrade_on_disk(DatabaseSource::RocksDb { path: Default::default(), cache_size: 0 });
+}
+
+#[cfg(feature = "parity-db")]
+#[test]
+fn unversioned_parity_db_database_is_upgraded() {
+    upgrade_on_disk(DatabaseSource::ParityDb { path: Default::default() });
+}
+
+#[test]
+fn migrations_are_idempotent() {
+    let db = unversioned_fixture();
+
+    migrate(&*db).unwrap();
+    // Simulate a node stopped before the version was written
+    (MIGRATIONS[0].migrate)(&*db).unwrap();
+
+    assert!(migrate(&*db).unwrap().is_empty());
+    assert_eq!(schema_version(&*db).unwrap(), CURRENT_SCHEMA_VERSION);
+}
+
+#[test]
+fn corrupted_unversioned_database_fails_to_migrate() {
+    let db = unversioned_fixture();
+    let mut transaction = sp_database::Transaction::new();
+    transaction.set(crate::columns::MESSAGING, crate::static_keys::LAST_SYNCED_L1_EVENT_BLOCK, &[1u8, 2, 3]);
+    db.commit(transaction).unwrap();
+
+    assert!(matches!(migrate(&*db), Err(DbError::MigrationFailed(0, _))));
+    assert_eq!(schema_version(&*db).unwrap(), 0);
+}
+
+#[test]
+fn database_from_a_newer_node_is_rejected() {
+    let db: Arc<dyn Database<DbHash>> = Arc::new(MemDb::new());
+    write_schema_version(&*db, CURRENT_SCHEMA_VERSION + 1).unwrap();
+
+    assert!(matches!(
+        migrate(&*db),
+        Err(DbError::UnsupportedSchemaVersion(version, CURRENT_SCHEMA_VERSION)) if version == CURRENT_SCHEMA_VERSION + 1
+    ));
+}
+
+#[test]
+fn receipts_are_indexed_again_to_record_the_paymasters() {
+    let db: Arc<dyn Database<DbHash>> = Arc::new(MemDb::new());
+    write_schema_version(&*db, 3).unwrap();
+
+    assert_eq!(migrate(&*db).unwrap().len(), 1);
+    assert_eq!(schema_version(&*db).unwrap(), CURRENT_SCHEMA_VERSION);
+    let backfill = IndexingBackfill::decode(
+        &mut &db.get(crate::columns::META, crate::static_keys::INDEXING_BACKFILL).unwrap()[..],
+    )
+    .unwrap();
+    assert_eq!(backfill, IndexingBackfill::from_best_block());
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::columns;
+
+/// Compression algorithm of a column
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    #[default]
+    Lz4,
+    Zstd,
+}
+
+/// RocksDB options of a column
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnOptions {
+    /// Bits per key of the bloom filter, speeding up the lookup of absent keys. `None` disables it
+    pub bloom_filter_bits: Option<f64>,
+    pub compression: Compression,
+    /// Size of the in-memory write buffer, in MiB
+    pub write_buffer_size: usize,
+    /// Size of the data blocks, in KiB
+    pub block_size: usize,
+}
+
+impl Default for ColumnOptions {
+    /// RocksDB defaults
+    fn default() -> Self {
+        Self { bloom_filter_bits: None, compression: Compression::Lz4, write_buffer_size: 64, block_size: 4 }
+    }
+}
+
+impl ColumnOptions {
+    /// Columns only accessed by key, most of the lookups hit a single block
+    fn point_lookup() -> Self {
+        Self { bloom_filter_bits: Some(10.0), ..Default::default() }
+    }
+
+    /// Columns storing large values, such as contract classes
+    fn blobs() -> Self {
+        Self { bloom_filter_bits: Some(10.0), compression: Compression::Zstd, write_buffer_size: 128, block_size: 64 }
+    }
+}
+
+/// Options of the Starknet database
+///
+/// Only used by the RocksDB backend. Columns are designated by their name, such as
+/// `sierra_contract_classes` or `transaction_mapping`, the ones missing from `columns` use the
+/// RocksDB defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseOptions {
+    /// Size of the block cache shared by all the columns, in MiB
+    pub cache_size: usize,
+    pub columns: BTreeMap<String, ColumnOptions>,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        let tuned_columns = [
+            (columns::BLOCK_MAPPING, ColumnOptions::point_lookup()),
+            (columns::TRANSACTION_MAPPING, ColumnOptions::point_lookup()),
+            (columns::SYNCED_MAPPING, ColumnOptions::point_lookup()),
+            (columns::SIERRA_CONTRACT_CLASSES, ColumnOptions::blobs()),
+            (columns::SIERRA_CLASSES, ColumnOptions::blobs()),
+            (columns::L1_HANDLER_PAID_FEE, ColumnOptions::point_lookup()),
+            (columns::TRANSACTION_RECEIPTS, ColumnOptions::point_lookup()),
+            (columns::HISTORICAL_STORAGE, ColumnOptions::point_lookup()),
+        ];
+
+        Self {
+            cache_size: 128,
+            columns: tuned_columns
+                .into_iter()
+                .map(|(column, options)| (columns::NAMES[column as usize].to_string(), options))
+                .collect(),
+        }
+    }
+}
+
+impl DatabaseOptions {
+    /// Options without any tuning, as the database was opened before they were configurable
+    pub fn untuned() -> Self {
+        Self { cache_size: 0, columns: BTreeMap::new() }
+    }
+
+    /// Return the options of `column`
+    pub fn column(&self, column: u32) -> ColumnOptions {
+        self.columns.get(columns::NAMES[column as usize]).cloned().unwrap_or_default()
+    }
+
+    /// Replace the options of the columns present in `overrides`, and the cache size
+    pub fn with_overrides(mut self, overrides: DatabaseOptions) -> Self {
+        self.cache_size = overrides.cache_size;
+        self.columns.extend(overrides.columns);
+        self
+    }
+
+    /// Return an error if `columns` contains an unknown column name
+    pub fn validate(&self) -> Result<(), String> {
+        match self.columns.keys().find(|name| !columns::NAMES.contains(&name.as_str())) {
+            Some(name) => Err(format!("Unknown database column `{name}`, expected one of {:?}", columns::NAMES)),
+            None => Ok(()),
+        }
+    }
+}
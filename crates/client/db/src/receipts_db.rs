@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+// Substrate
+use parity_scale_codec::{Decode, Encode, Input};
+use sp_database::Database;
+use starknet_api::core::ContractAddress;
+use starknet_api::hash::StarkHash;
+use starknet_api::transaction::{Event, Fee, MessageToL1, TransactionHash};
+
+use crate::{DbError, DbHash};
+
+/// Unit of the fee paid by a transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum FeeUnit {
+    Wei,
+    Fri,
+}
+
+/// Receipt fields specific to each transaction type
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum TransactionReceiptKind {
+    Invoke,
+    Declare,
+    DeployAccount { contract_address: ContractAddress },
+    L1Handler { message_hash: [u8; 32] },
+}
+
+/// A transaction receipt, as computed by the mapping sync worker when its block is imported
+///
+/// It holds everything the receipt RPCs return, so that they are served from a single read.
+#[derive(Clone, Debug, PartialEq, Eq, Encode)]
+pub struct StoredTransactionReceipt {
+    pub transaction_hash: TransactionHash,
+    /// Hash of the Starknet block containing the transaction
+    pub block_hash: StarkHash,
+    pub block_number: u64,
+    /// Position of the transaction in its block
+    pub transaction_index: u32,
+    pub kind: TransactionReceiptKind,
+    pub actual_fee: Fee,
+    pub fee_unit: FeeUnit,
+    /// Execution resources used by the transaction, by resource name
+    pub execution_resources: Vec<(String, u64)>,
+    pub messages_sent: Vec<MessageToL1>,
+    /// Position of the first event of the transaction among the events of its block
+    pub first_event_index: u32,
+    pub events: Vec<Event>,
+    pub revert_reason: Option<String>,
+    /// Paymaster charged the fee instead of the sender, for sponsored transactions
+    pub paymaster: Option<ContractAddress>,
+}
+
+impl Decode for StoredTransactionReceipt {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+        Ok(Self {
+            transaction_hash: Decode::decode(input)?,
+            block_hash: Decode::decode(input)?,
+            block_number: Decode::decode(input)?,
+            transaction_index: Decode::decode(input)?,
+            kind: Decode::decode(input)?,
+            actual_fee: Decode::decode(input)?,
+            fee_unit: Decode::decode(input)?,
+            execution_resources: Decode::decode(input)?,
+            messages_sent: Decode::decode(input)?,
+            first_event_index: Decode::decode(input)?,
+            events: Decode::decode(input)?,
+            revert_reason: Decode::decode(input)?,
+            // The receipts written before the schema version 4 end with the revert reason, they are
+            // written again by the indexing backfill, see `crate::migrations`
+            paymaster: match input.remaining_len()? {
+                Some(0) => None,
+                _ => Decode::decode(input)?,
+            },
+        })
+    }
+}
+
+/// Allow interaction with the transaction receipts db
+///
+/// Receipts are written along the block mapping, see [`crate::MappingCommitment`].
+pub struct ReceiptsDb {
+    pub(crate) db: Arc<dyn Database<DbHash>>,
+}
+
+impl ReceiptsDb {
+    /// Return the receipt of the transaction with the given hash, if its block has been synced
+    pub fn get_receipt(&self, transaction_hash: TransactionHash) -> Result<Option<StoredTransactionReceipt>, DbError> {
+        match self.db.get(crate::columns::TRANSACTION_RECEIPTS, &transaction_hash.encode()) {
+            Some(raw) => Ok(Some(StoredTransactionReceipt::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_api::core::PatriciaKey;
+
+    use super::*;
+
+    fn receipt(paymaster: Option<ContractAddress>) -> StoredTransactionReceipt {
+        StoredTransactionReceipt {
+            transaction_hash: TransactionHash(StarkHash::from(1u8)),
+            block_hash: StarkHash::from(2u8),
+            block_number: 3,
+            transaction_index: 4,
+            kind: TransactionReceiptKind::Invoke,
+            actual_fee: Fee(5),
+            fee_unit: FeeUnit::Fri,
+            execution_resources: vec![("n_steps".to_string(), 6)],
+            messages_sent: Vec::new(),
+            first_event_index: 7,
+            events: Vec::new(),
+            revert_reason: Some("reverted".to_string()),
+            paymaster,
+        }
+    }
+
+    #[test]
+    fn receipts_round_trip() {
+        let sponsored = receipt(Some(ContractAddress(PatriciaKey::try_from(StarkHash::from(8u8)).unwrap())));
+        assert_eq!(StoredTransactionReceipt::decode(&mut &sponsored.encode()[..]).unwrap(), sponsored);
+        assert_eq!(StoredTransactionReceipt::decode(&mut &receipt(None).encode()[..]).unwrap(), receipt(None));
+    }
+
+    #[test]
+    fn receipts_written_without_the_paymaster_are_decoded() {
+        let receipt = receipt(None);
+        // The encoding of the receipts before the schema version 4
+        let mut encoded = receipt.encode();
+        encoded.pop();
+
+        assert_eq!(StoredTransactionReceipt::decode(&mut &encoded[..]).unwrap(), receipt);
+    }
+}
@@ -13,10 +13,22 @@ pub struct SierraClassesDb {
 }
 
 impl SierraClassesDb {
+    /// Store a class, or add a reference to it if it is already stored
     pub fn store_sierra_class(&self, class_hash: ClassHash, class: ContractClass) -> Result<(), DbError> {
         let mut transaction = sp_database::Transaction::new();
 
-        transaction.set(crate::columns::SIERRA_CONTRACT_CLASSES, &class_hash.encode(), &class.encode());
+        transaction.store(crate::columns::SIERRA_CLASSES, class_key(class_hash), class.encode());
+
+        self.db.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Remove a reference to a class, the class is deleted with its last reference
+    pub fn release_sierra_class(&self, class_hash: ClassHash) -> Result<(), DbError> {
+        let mut transaction = sp_database::Transaction::new();
+
+        transaction.release(crate::columns::SIERRA_CLASSES, class_key(class_hash));
 
         self.db.commit(transaction)?;
 
@@ -26,10 +38,79 @@ impl SierraClassesDb {
     pub fn get_sierra_class(&self, class_hash: ClassHash) -> Result<Option<ContractClass>, DbError> {
         let opt_contract_class = self
             .db
-            .get(crate::columns::SIERRA_CONTRACT_CLASSES, &class_hash.encode())
+            .get(crate::columns::SIERRA_CLASSES, &class_key(class_hash))
+            .or_else(|| self.db.get(crate::columns::SIERRA_CONTRACT_CLASSES, &class_hash.encode()))
             .map(|raw| ContractClass::decode(&mut &raw[..]))
             .transpose()?;
 
         Ok(opt_contract_class)
     }
 }
+
+fn class_key(class_hash: ClassHash) -> DbHash {
+    class_hash.0.bytes().try_into().expect("A felt is 32 bytes long; qed")
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_database::MemDb;
+    use starknet_api::hash::StarkFelt;
+
+    use super::*;
+    use crate::{DatabaseOptions, DatabaseSource};
+
+    fn class() -> ContractClass {
+        ContractClass { abi: "[]".to_string(), ..Default::default() }
+    }
+
+    fn classes_are_reference_counted(db: Arc<dyn Database<DbHash>>) {
+        let classes = SierraClassesDb { db };
+        let class_hash = ClassHash(StarkFelt::from(1u8));
+
+        // Declared by two blocks
+        classes.store_sierra_class(class_hash, class()).unwrap();
+        classes.store_sierra_class(class_hash, class()).unwrap();
+
+        classes.release_sierra_class(class_hash).unwrap();
+        assert_eq!(classes.get_sierra_class(class_hash).unwrap(), Some(class()));
+
+        classes.release_sierra_class(class_hash).unwrap();
+        assert_eq!(classes.get_sierra_class(class_hash).unwrap(), None);
+    }
+
+    fn on_disk(database: DatabaseSource) -> (tempfile::TempDir, Arc<dyn Database<DbHash>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = crate::open_database(&database, dir.path(), &DatabaseOptions::default()).unwrap();
+        (dir, db)
+    }
+
+    #[test]
+    fn classes_are_reference_counted_in_memory() {
+        classes_are_reference_counted(Arc::new(MemDb::new()));
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[test]
+    fn classes_are_reference_counted_in_rocksdb() {
+        let (_dir, db) = on_disk(DatabaseSource::RocksDb { path: Default::default(), cache_size: 0 });
+        classes_are_reference_counted(db);
+    }
+
+    #[cfg(feature = "parity-db")]
+    #[test]
+    fn classes_are_reference_counted_in_parity_db() {
+        let (_dir, db) = on_disk(DatabaseSource::ParityDb { path: Default::default() });
+        classes_are_reference_counted(db);
+    }
+
+    #[test]
+    fn classes_stored_before_the_reference_counting_are_read() {
+        let db: Arc<dyn Database<DbHash>> = Arc::new(MemDb::new());
+        let class_hash = ClassHash(StarkFelt::from(1u8));
+        let mut transaction = sp_database::Transaction::new();
+        transaction.set(crate::columns::SIERRA_CONTRACT_CLASSES, &class_hash.encode(), &class().encode());
+        db.commit(transaction).unwrap();
+
+        assert_eq!(SierraClassesDb { db }.get_sierra_class(class_hash).unwrap(), Some(class()));
+    }
+}
@@ -0,0 +1,82 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use mp_block::commitments::BlockStateDiff;
+use parity_scale_codec::{Decode, Encode};
+use sp_database::Database;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{DbError, DbHash};
+
+/// Allow interaction with the block state diffs db
+///
+/// State diffs are written along the block mapping, see [`crate::MappingCommitment`], and removed
+/// with the mapping of the stale blocks.
+pub struct StateDiffsDb<B: BlockT> {
+    pub(crate) db: Arc<dyn Database<DbHash>>,
+    pub(crate) _marker: PhantomData<B>,
+}
+
+impl<B: BlockT> StateDiffsDb<B> {
+    /// Return the state diff of the Substrate block with the given hash
+    ///
+    /// Returns `None` if the block has not been synced, or was imported before the state diffs were
+    /// stored.
+    pub fn get_state_diff(&self, block_hash: &B::Hash) -> Result<Option<BlockStateDiff>, DbError> {
+        match self.db.get(crate::columns::STATE_DIFFS, &block_hash.encode()) {
+            Some(raw) => Ok(Some(BlockStateDiff::decode(&mut &raw[..])?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_database::MemDb;
+    use sp_runtime::generic::{Block as GenericBlock, Header};
+    use sp_runtime::testing::H256;
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_runtime::OpaqueExtrinsic;
+    use starknet_api::block::BlockHash;
+    use starknet_api::core::{ContractAddress, Nonce, PatriciaKey};
+    use starknet_api::hash::StarkFelt;
+
+    use super::*;
+    use crate::{HistoricalStorageDb, MappingCommitment, MappingDb};
+
+    type Block = GenericBlock<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+    fn commitment(block_hash: H256, state_diff: Option<BlockStateDiff>) -> MappingCommitment<Block> {
+        MappingCommitment {
+            block_hash,
+            starknet_block_hash: BlockHash(StarkFelt::from(1u8)),
+            starknet_transaction_hashes: Vec::new(),
+            starknet_transaction_receipts: Vec::new(),
+            starknet_block_number: 1,
+            starknet_storage_diffs: None,
+            starknet_state_diff: state_diff,
+        }
+    }
+
+    #[test]
+    fn state_diffs_follow_the_block_mapping() {
+        let db: Arc<dyn Database<DbHash>> = Arc::new(MemDb::new());
+        let mapping =
+            MappingDb::new(db.clone(), Arc::new(HistoricalStorageDb { db: db.clone(), _marker: PhantomData }));
+        let state_diffs = StateDiffsDb::<Block> { db, _marker: PhantomData };
+        let mut state_diff = BlockStateDiff::default();
+        state_diff.nonces.insert(ContractAddress(PatriciaKey(StarkFelt::from(2u8))), Nonce(StarkFelt::ONE));
+
+        // The same Starknet block on two forks, the runtime of the second one not recording the diff
+        mapping.write_hashes(commitment(H256::repeat_byte(1), Some(state_diff.clone()))).unwrap();
+        mapping.write_hashes(commitment(H256::repeat_byte(2), None)).unwrap();
+        assert_eq!(state_diffs.get_state_diff(&H256::repeat_byte(1)).unwrap(), Some(state_diff));
+        assert_eq!(state_diffs.get_state_diff(&H256::repeat_byte(2)).unwrap(), None);
+
+        let purged = mapping
+            .purge_block_hashes(BlockHash(StarkFelt::from(1u8)), |block_hash| *block_hash == H256::repeat_byte(1))
+            .unwrap();
+        assert_eq!(purged, 1);
+        assert_eq!(state_diffs.get_state_diff(&H256::repeat_byte(1)).unwrap(), None);
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use parity_scale_codec::{Decode, Encode};
+
+/// Number of blocks of state kept by default in pruned mode, same as Substrate's default
+pub const DEFAULT_PRUNING_BLOCKS: u32 = 256;
+
+/// How much history the node keeps
+///
+/// The mode is recorded in the meta db the first time the database is opened and an existing
+/// database cannot be switched from one mode to the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum StorageMode {
+    /// Keep the state of every block and maintain a flat index of the storage history, so that
+    /// storage can be queried at any block
+    Archive,
+    /// Only keep the state of the last `blocks` blocks, queries against older blocks fail
+    Pruned { blocks: u32 },
+}
+
+impl Default for StorageMode {
+    fn default() -> Self {
+        Self::Pruned { blocks: DEFAULT_PRUNING_BLOCKS }
+    }
+}
+
+impl StorageMode {
+    pub fn is_archive(&self) -> bool {
+        matches!(self, Self::Archive)
+    }
+
+    /// Return true if the state of block `block_number` is still available when the last
+    /// finalized block is `finalized_block_number`
+    ///
+    /// Like Substrate's state pruning, the state of the blocks above the finalized one is always
+    /// kept, only finalized blocks are pruned.
+    pub fn has_state_at(&self, block_number: u64, finalized_block_number: u64) -> bool {
+        match self {
+            Self::Archive => true,
+            Self::Pruned { blocks } => finalized_block_number.saturating_sub(block_number) < u64::from(*blocks),
+        }
+    }
+
+    /// Return true if a database created in `self` mode can be opened in `other` mode
+    ///
+    /// Going from archive to pruned or the other way around is not possible, but the number of
+    /// blocks kept by a pruned node can be changed.
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        matches!((self, other), (Self::Archive, Self::Archive) | (Self::Pruned { .. }, Self::Pruned { .. }))
+    }
+}
+
+impl fmt::Display for StorageMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Archive => write!(f, "archive"),
+            Self::Pruned { blocks } => write!(f, "pruned ({blocks} blocks)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pruned_mode_keeps_the_last_finalized_blocks() {
+        let storage_mode = StorageMode::Pruned { blocks: 256 };
+
+        assert!(storage_mode.has_state_at(1000, 1000));
+        assert!(storage_mode.has_state_at(745, 1000));
+        assert!(!storage_mode.has_state_at(744, 1000));
+        assert!(!storage_mode.has_state_at(0, 1000));
+        // Blocks above the finalized one are not pruned
+        assert!(storage_mode.has_state_at(1010, 1000));
+        assert!(storage_mode.has_state_at(0, 255));
+    }
+
+    #[test]
+    fn archive_mode_keeps_every_block() {
+        assert!(StorageMode::Archive.has_state_at(0, u64::MAX));
+    }
+}
@@ -15,7 +15,7 @@
 use std::fs::File;
 use std::path::PathBuf;
 
-use ethers::types::{Address, H160};
+use ethers::types::{Address, BlockNumber, H160};
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
@@ -23,6 +23,8 @@ use crate::oracle::OracleConfig;
 
 /// Default Anvil local endpoint
 pub const DEFAULT_RPC_ENDPOINT: &str = "http://127.0.0.1:8545";
+/// Default interval between two reads of the core contract state
+pub const DEFAULT_STATE_UPDATE_POLL_MS: u64 = 10_000;
 /// Default Anvil chain ID
 pub const DEFAULT_CHAIN_ID: u64 = 31337;
 /// Default private key derived from starting Anvil as follows:
@@ -40,6 +42,8 @@ pub struct EthereumClientConfig {
     pub contracts: StarknetContracts,
     #[serde(default)]
     pub oracle: OracleConfig,
+    #[serde(default)]
+    pub state_update: StateUpdateConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,6 +77,27 @@ pub struct HttpProviderConfig {
     pub gas_price_poll_ms: Option<u64>,
 }
 
+/// Settings of the [`crate::state_update::StateUpdateWatcher`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateUpdateConfig {
+    #[serde(default = "default_state_update_poll_ms")]
+    pub poll_interval_ms: u64,
+    /// The L1 block the state of the core contract is read at. Anvil finalizes the blocks two
+    /// epochs of 32 blocks behind the latest one.
+    #[serde(default)]
+    pub block_tag: L1BlockTag,
+}
+
+/// An L1 block, relative to the head of the chain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L1BlockTag {
+    Latest,
+    Safe,
+    #[default]
+    Finalized,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalWalletConfig {
     #[serde(default = "default_chain_id")]
@@ -85,6 +110,10 @@ fn default_rpc_endpoint() -> String {
     DEFAULT_RPC_ENDPOINT.into()
 }
 
+fn default_state_update_poll_ms() -> u64 {
+    DEFAULT_STATE_UPDATE_POLL_MS
+}
+
 fn default_chain_id() -> u64 {
     DEFAULT_CHAIN_ID
 }
@@ -119,6 +148,22 @@ impl EthereumProviderConfig {
     }
 }
 
+impl Default for StateUpdateConfig {
+    fn default() -> Self {
+        Self { poll_interval_ms: default_state_update_poll_ms(), block_tag: L1BlockTag::default() }
+    }
+}
+
+impl From<L1BlockTag> for BlockNumber {
+    fn from(tag: L1BlockTag) -> Self {
+        match tag {
+            L1BlockTag::Latest => BlockNumber::Latest,
+            L1BlockTag::Safe => BlockNumber::Safe,
+            L1BlockTag::Finalized => BlockNumber::Finalized,
+        }
+    }
+}
+
 impl Default for LocalWalletConfig {
     fn default() -> Self {
         Self { chain_id: default_chain_id(), private_key: default_private_key() }
@@ -12,4 +12,12 @@ pub enum Error {
     ConfigReadFromFile(#[source] std::io::Error),
     #[error("Failed to decode from JSON: {0}")]
     ConfigDecodeFromJson(#[source] serde_json::Error),
+    #[error("Failed to read the L1 block: {0}")]
+    L1BlockRead(String),
+    #[error("L1 block {0:?} not found")]
+    L1BlockNotFound(ethers::types::BlockNumber),
+    #[error("Failed to call the core contract: {0}")]
+    CoreContractCall(String),
+    #[error("Invalid Starknet block number {0} in the core contract")]
+    InvalidStateBlockNumber(ethers::types::I256),
 }
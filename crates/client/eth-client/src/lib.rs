@@ -10,6 +10,7 @@
 pub mod config;
 pub mod error;
 pub mod oracle;
+pub mod state_update;
 
 use std::time::Duration;
 
@@ -0,0 +1,274 @@
+//! Watcher of the Starknet state accepted on L1.
+//!
+//! Every time a state update is proven, the Starknet core contract updates its `stateBlockNumber`
+//! and `stateBlockHash`. The blocks up to that one are `ACCEPTED_ON_L1`, as long as the local
+//! chain has the same block at that height.
+//!
+//! Both views are read at the same L1 block, so that they belong to the same state update.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::contract::abigen;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, BlockId, BlockNumber, U256};
+
+use crate::config::{EthereumClientConfig, L1BlockTag};
+use crate::error::Error;
+
+abigen!(
+    StarknetCoreContract,
+    r#"[
+        function stateBlockNumber() external view returns (int256)
+        function stateBlockHash() external view returns (uint256)
+    ]"#
+);
+
+/// The last Starknet block accepted on L1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L1StateUpdate {
+    pub block_number: u64,
+    pub block_hash: U256,
+}
+
+/// Polls the state of the core contract.
+pub struct StateUpdateWatcher<M> {
+    contract: StarknetCoreContract<M>,
+    client: Arc<M>,
+    /// The L1 block the state is read at, resolved to a number before each read
+    block_tag: BlockNumber,
+    poll_interval: Duration,
+}
+
+impl StateUpdateWatcher<Provider<Http>> {
+    pub fn from_config(config: &EthereumClientConfig) -> Result<Self, Error> {
+        let address = config.contracts.core_contract()?;
+        let provider: Provider<Http> = config.provider.clone().try_into()?;
+        Ok(Self::new(
+            address,
+            Arc::new(provider),
+            config.state_update.block_tag,
+            Duration::from_millis(config.state_update.poll_interval_ms),
+        ))
+    }
+}
+
+impl<M: Middleware> StateUpdateWatcher<M> {
+    pub fn new(core_contract: Address, client: Arc<M>, block_tag: L1BlockTag, poll_interval: Duration) -> Self {
+        Self {
+            contract: StarknetCoreContract::new(core_contract, client.clone()),
+            client,
+            block_tag: block_tag.into(),
+            poll_interval,
+        }
+    }
+
+    /// The L1 block currently designated by the tag of the watcher.
+    async fn l1_block(&self) -> Result<BlockId, Error> {
+        let block = self.client.get_block(self.block_tag).await.map_err(|e| Error::L1BlockRead(e.to_string()))?;
+        let number = block.and_then(|block| block.number).ok_or(Error::L1BlockNotFound(self.block_tag))?;
+        Ok(number.into())
+    }
+
+    /// The last state update of the core contract, `None` until the first one.
+    pub async fn last_state_update(&self) -> Result<Option<L1StateUpdate>, Error> {
+        // The tag may move to another block between the two calls
+        let l1_block = self.l1_block().await?;
+
+        let block_number = self
+            .contract
+            .state_block_number()
+            .block(l1_block)
+            .call()
+            .await
+            .map_err(|e| Error::CoreContractCall(e.to_string()))?;
+        // The block number is -1 before the first state update
+        if block_number.is_negative() {
+            return Ok(None);
+        }
+        let block_number = u64::try_from(block_number).map_err(|_| Error::InvalidStateBlockNumber(block_number))?;
+
+        let block_hash = self
+            .contract
+            .state_block_hash()
+            .block(l1_block)
+            .call()
+            .await
+            .map_err(|e| Error::CoreContractCall(e.to_string()))?;
+
+        Ok(Some(L1StateUpdate { block_number, block_hash }))
+    }
+
+    /// Poll the core contract forever, calling `on_update` with every new state update.
+    pub async fn run(self, mut on_update: impl FnMut(L1StateUpdate)) {
+        let mut last_update = None;
+        loop {
+            match self.last_state_update().await {
+                Ok(Some(update)) if last_update != Some(update) => {
+                    log::debug!("⟠ Starknet block {} accepted on L1", update.block_number);
+                    on_update(update);
+                    last_update = Some(update);
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("⟠ Failed to read the Starknet state accepted on L1: {e}"),
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::abi::{encode, Token};
+    use ethers::middleware::SignerMiddleware;
+    use ethers::providers::MockProvider;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{Block, Bytes, TransactionRequest, TxHash, I256, U64};
+    use ethers::utils::{self, Anvil};
+
+    use super::*;
+    use crate::config::{EthereumProviderConfig, HttpProviderConfig, StarknetContracts, StateUpdateConfig};
+
+    const CORE_CONTRACT: Address = Address::repeat_byte(0x42);
+
+    fn watcher() -> (StateUpdateWatcher<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        let watcher = StateUpdateWatcher::new(CORE_CONTRACT, Arc::new(provider), L1BlockTag::Finalized, Duration::ZERO);
+        (watcher, mock)
+    }
+
+    /// Queue the responses of the node, which are served last in first out.
+    fn push_responses(mock: &MockProvider, l1_block: u64, views: &[U256]) {
+        for view in views.iter().rev() {
+            mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Uint(*view)]))).unwrap();
+        }
+        mock.push::<Block<TxHash>, _>(Block { number: Some(l1_block.into()), ..Default::default() }).unwrap();
+    }
+
+    fn assert_view_call(mock: &MockProvider, call: &TypedTransaction, l1_block: u64) {
+        let block = BlockId::from(U64::from(l1_block));
+        mock.assert_request("eth_call", [utils::serialize(call), utils::serialize(&block)]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn no_state_update_before_the_first_one() {
+        let (watcher, mock) = watcher();
+        push_responses(&mock, 100, &[U256::MAX]);
+
+        assert_eq!(watcher.last_state_update().await.unwrap(), None);
+
+        mock.assert_request(
+            "eth_getBlockByNumber",
+            [utils::serialize(&BlockNumber::Finalized), utils::serialize(&false)],
+        )
+        .unwrap();
+        assert_view_call(&mock, &watcher.contract.state_block_number().tx, 100);
+    }
+
+    #[tokio::test]
+    async fn state_update_views_are_read_at_the_same_l1_block() {
+        let (watcher, mock) = watcher();
+        push_responses(&mock, 101, &[42.into(), 0xabcd.into()]);
+
+        assert_eq!(
+            watcher.last_state_update().await.unwrap(),
+            Some(L1StateUpdate { block_number: 42, block_hash: 0xabcd.into() })
+        );
+
+        mock.assert_request(
+            "eth_getBlockByNumber",
+            [utils::serialize(&BlockNumber::Finalized), utils::serialize(&false)],
+        )
+        .unwrap();
+        assert_view_call(&mock, &watcher.contract.state_block_number().tx, 101);
+        assert_view_call(&mock, &watcher.contract.state_block_hash().tx, 101);
+    }
+
+    #[tokio::test]
+    async fn missing_l1_block_is_an_error() {
+        let (watcher, mock) = watcher();
+        mock.push::<Option<Block<TxHash>>, _>(None).unwrap();
+
+        assert!(matches!(watcher.last_state_update().await, Err(Error::L1BlockNotFound(BlockNumber::Finalized))));
+    }
+
+    abigen!(
+        FakeStarknetCoreContract,
+        r#"[
+            function setState(int256 blockNumber, uint256 blockHash) external
+        ]"#
+    );
+
+    /// Creation code of a stand-in for the Starknet core contract, serving its `stateBlockNumber`
+    /// and `stateBlockHash` views from the storage slots 0 and 1, and setting them with
+    /// `setState(int256,uint256)`. The block number is initialized to -1, as in the core contract.
+    ///
+    /// ```text
+    /// constructor:  PUSH32 -1 PUSH1 0 SSTORE
+    ///               PUSH1 77 DUP1 PUSH1 47 PUSH1 0 CODECOPY PUSH1 0 RETURN
+    /// runtime:      PUSH1 0 CALLDATALOAD PUSH1 224 SHR
+    ///               DUP1 PUSH4 stateBlockNumber() EQ PUSH1 number JUMPI
+    ///               DUP1 PUSH4 stateBlockHash() EQ PUSH1 hash JUMPI
+    ///               PUSH4 setState(int256,uint256) EQ PUSH1 set JUMPI
+    ///               PUSH1 0 DUP1 REVERT
+    /// number:       JUMPDEST PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+    /// hash:         JUMPDEST PUSH1 1 SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+    /// set:          JUMPDEST PUSH1 4 CALLDATALOAD PUSH1 0 SSTORE PUSH1 36 CALLDATALOAD PUSH1 1 SSTORE STOP
+    /// ```
+    const FAKE_CORE_CONTRACT_CREATION_CODE: &str =
+        "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff600055604d80602f6000396000f360003560e01c806335befa5d146027578063382d83e31460335763706776d014603f57600080fd5b60005460005260206000f35b60015460005260206000f35b60043560005560243560015500";
+
+    /// Anvil finalizes the blocks two epochs of 32 blocks behind the latest one
+    const ANVIL_FINALITY_DEPTH: u64 = 64;
+
+    #[tokio::test]
+    #[ignore = "requires the anvil binary of Foundry"]
+    async fn watcher_picks_up_the_state_updates_of_a_core_contract_on_anvil() {
+        let anvil = Anvil::new().spawn();
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(anvil.chain_id());
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap().interval(Duration::from_millis(10));
+        let client = Arc::new(SignerMiddleware::new(provider, wallet));
+
+        let deployment = TransactionRequest::new().data(Bytes::from_str(FAKE_CORE_CONTRACT_CREATION_CODE).unwrap());
+        let receipt = client.send_transaction(deployment, None).await.unwrap().await.unwrap().unwrap();
+        let core_contract = receipt.contract_address.unwrap();
+
+        let config = |block_tag| EthereumClientConfig {
+            provider: EthereumProviderConfig::Http(HttpProviderConfig {
+                rpc_endpoint: anvil.endpoint(),
+                ..Default::default()
+            }),
+            contracts: StarknetContracts { core_contract: format!("{core_contract:#x}"), ..Default::default() },
+            state_update: StateUpdateConfig { poll_interval_ms: 10, block_tag },
+            ..Default::default()
+        };
+        let latest = StateUpdateWatcher::from_config(&config(L1BlockTag::Latest)).unwrap();
+        let finalized = StateUpdateWatcher::from_config(&config(L1BlockTag::Finalized)).unwrap();
+        let finalize_blocks = || async {
+            client.provider().request::<_, ()>("anvil_mine", [U64::from(ANVIL_FINALITY_DEPTH)]).await.unwrap()
+        };
+
+        // Finalize the deployment, the core contract being read at the finalized block
+        finalize_blocks().await;
+        assert_eq!(latest.last_state_update().await.unwrap(), None);
+        assert_eq!(finalized.last_state_update().await.unwrap(), None);
+
+        FakeStarknetCoreContract::new(core_contract, client.clone())
+            .set_state(I256::from(42), 0xabcd.into())
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+
+        let update = L1StateUpdate { block_number: 42, block_hash: 0xabcd.into() };
+        assert_eq!(latest.last_state_update().await.unwrap(), Some(update));
+        assert_eq!(finalized.last_state_update().await.unwrap(), None);
+        finalize_blocks().await;
+        assert_eq!(finalized.last_state_update().await.unwrap(), Some(update));
+    }
+}
@@ -1,2 +1,5 @@
 pub const GENESIS_ASSETS_DIR: &str = "genesis-assets";
 pub const GENESIS_ASSETS_FILE: &str = "genesis.json";
+pub const GENESIS_SPEC_FILE: &str = "genesis-spec.json";
+/// Chain spec property holding the genesis data, for chain specs embedding it
+pub const GENESIS_CHAIN_SPEC_PROPERTY: &str = "genesis";
@@ -2,23 +2,44 @@ mod constants;
 
 use std::path::PathBuf;
 
-use constants::{GENESIS_ASSETS_DIR, GENESIS_ASSETS_FILE};
+pub use constants::GENESIS_CHAIN_SPEC_PROPERTY;
+use constants::{GENESIS_ASSETS_DIR, GENESIS_ASSETS_FILE, GENESIS_SPEC_FILE};
 use jsonrpsee::types::error::CallError;
-use mp_genesis_config::GenesisData;
+use mp_genesis_config::{GenesisData, GenesisLoader, GenesisSpec, GenesisSpecError, GenesisValidationErrors};
 
 pub trait GenesisProvider {
     type LoadGenesisDataError: std::error::Error;
     fn load_genesis_data(&self) -> Result<GenesisData, LoadGenesisDataError>;
 }
 
+/// Reads the genesis data from `genesis-assets/genesis.json` in the given config directory, or
+/// expands `genesis-assets/genesis-spec.json` when there is one, in which case `genesis.json` is
+/// ignored with a warning
 pub struct OnDiskGenesisConfig(pub PathBuf);
 
+/// Genesis data embedded in the `genesis` property of the chain spec
+pub struct ChainSpecGenesisConfig(GenesisData);
+
+/// Genesis data held in memory, e.g. built with [`GenesisData::builder`]
+pub struct InMemoryGenesisConfig(pub GenesisData);
+
+/// Any of the genesis providers, for nodes picking one at runtime
+pub enum GenesisSource {
+    OnDisk(OnDiskGenesisConfig),
+    ChainSpec(ChainSpecGenesisConfig),
+    InMemory(InMemoryGenesisConfig),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum LoadGenesisDataError {
-    #[error("File cannot be deserialized into a GenesisData struct")]
-    InvalidJson,
-    #[error("Unable to read genesis file: invalid path")]
-    InvalidPath,
+    #[error("Genesis data cannot be deserialized into a GenesisData struct: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("Unable to read genesis file {}: {1}", .0.display())]
+    InvalidPath(PathBuf, #[source] std::io::Error),
+    #[error("Unable to expand the genesis spec: {0}")]
+    InvalidSpec(#[from] GenesisSpecError),
+    #[error(transparent)]
+    Invalid(#[from] GenesisValidationErrors),
 }
 
 impl From<LoadGenesisDataError> for jsonrpsee::core::Error {
@@ -27,16 +48,87 @@ impl From<LoadGenesisDataError> for jsonrpsee::core::Error {
     }
 }
 
+/// Check the genesis data against the class files of the `config_dir` directory
+pub fn validate_genesis_data(config_dir: PathBuf, data: GenesisData) -> Result<GenesisData, LoadGenesisDataError> {
+    let loader = GenesisLoader::new(config_dir, data);
+    loader.validate()?;
+    Ok(loader.data().clone())
+}
+
+impl OnDiskGenesisConfig {
+    /// Load and validate the genesis data
+    pub fn load_and_validate(&self) -> Result<GenesisData, LoadGenesisDataError> {
+        validate_genesis_data(self.0.clone(), self.load_genesis_data()?)
+    }
+}
+
 impl GenesisProvider for OnDiskGenesisConfig {
     type LoadGenesisDataError = LoadGenesisDataError;
 
     fn load_genesis_data(&self) -> Result<GenesisData, Self::LoadGenesisDataError> {
+        let spec_path = self.0.join(GENESIS_ASSETS_DIR).join(GENESIS_SPEC_FILE);
         let genesis_path = self.0.join(GENESIS_ASSETS_DIR).join(GENESIS_ASSETS_FILE);
+        if spec_path.exists() {
+            if genesis_path.exists() {
+                log::warn!(
+                    "Both {} and {} exist, the genesis is expanded from {} and {} is ignored",
+                    spec_path.display(),
+                    genesis_path.display(),
+                    spec_path.display(),
+                    genesis_path.display()
+                );
+            }
+            log::info!("Expanding genesis spec at: {}", spec_path.display());
+
+            let content = std::fs::read_to_string(&spec_path)
+                .map_err(|e| LoadGenesisDataError::InvalidPath(spec_path.clone(), e))?;
+            return Ok(serde_json::from_str::<GenesisSpec>(&content)?.expand()?);
+        }
 
         log::info!("Loading genesis data at: {}", genesis_path.display());
 
-        std::fs::read_to_string(genesis_path.clone()).map_or(Err(LoadGenesisDataError::InvalidPath), |s| {
-            serde_json::from_str::<GenesisData>(&s).map_err(|_| LoadGenesisDataError::InvalidJson)
-        })
+        let content = std::fs::read_to_string(&genesis_path)
+            .map_err(|e| LoadGenesisDataError::InvalidPath(genesis_path.clone(), e))?;
+        Ok(serde_json::from_str::<GenesisData>(&content)?)
+    }
+}
+
+impl ChainSpecGenesisConfig {
+    /// Read the genesis data from the properties of a chain spec, if it embeds it
+    pub fn from_properties(
+        properties: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Option<Self>, LoadGenesisDataError> {
+        properties
+            .get(GENESIS_CHAIN_SPEC_PROPERTY)
+            .map(|genesis| Ok(Self(serde_json::from_value(genesis.clone())?)))
+            .transpose()
+    }
+}
+
+impl GenesisProvider for ChainSpecGenesisConfig {
+    type LoadGenesisDataError = LoadGenesisDataError;
+
+    fn load_genesis_data(&self) -> Result<GenesisData, Self::LoadGenesisDataError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl GenesisProvider for InMemoryGenesisConfig {
+    type LoadGenesisDataError = LoadGenesisDataError;
+
+    fn load_genesis_data(&self) -> Result<GenesisData, Self::LoadGenesisDataError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl GenesisProvider for GenesisSource {
+    type LoadGenesisDataError = LoadGenesisDataError;
+
+    fn load_genesis_data(&self) -> Result<GenesisData, Self::LoadGenesisDataError> {
+        match self {
+            GenesisSource::OnDisk(provider) => provider.load_genesis_data(),
+            GenesisSource::ChainSpec(provider) => provider.load_genesis_data(),
+            GenesisSource::InMemory(provider) => provider.load_genesis_data(),
+        }
     }
 }
@@ -10,6 +10,7 @@ pub struct BlockMetrics {
     pub strk_l1_gas_price_fri: Gauge,
     pub eth_l1_data_gas_price_wei: Gauge,
     pub strk_l1_data_gas_price_fri: Gauge,
+    pub receipts_skipped_blocks: Gauge,
 }
 
 impl BlockMetrics {
@@ -37,6 +38,13 @@ impl BlockMetrics {
                 Gauge::new("madara_l1_data_gas_price_strk", "Gauge for madara l1 data gas price in strk fri")?,
                 registry,
             )?,
+            receipts_skipped_blocks: register(
+                Gauge::new(
+                    "madara_receipts_skipped_blocks",
+                    "Gauge for the number of madara blocks whose receipts could not be indexed",
+                )?,
+                registry,
+            )?,
         })
     }
 }
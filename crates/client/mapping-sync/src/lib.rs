@@ -4,11 +4,20 @@
 //! The `MappingSyncWorker` listen to new Substrate blocks and read their digest to find
 //! `pallet-starknet` logs. Those logs should contain the data necessary to update the Madara
 //! mapping db: a starknet block header.
+//! The receipts of the block transactions are built from the execution outcome recorded by the
+//! runtime at import, and persisted at the same time, so that the receipt RPCs don't have to
+//! re-execute the block.
+//! In archive mode, the storage values written by the block are also recorded, and added to the
+//! historical storage index once the block is finalized.
+//!
+//! The [`repair`] module allows to verify and rebuild the mapping db of a stopped node.
 //!
 //! # Usage
 //! The madara node should spawn a `MappingSyncWorker` among it's services.
 
 mod block_metrics;
+mod receipts;
+pub mod repair;
 mod sync_blocks;
 
 use std::marker::PhantomData;
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::objects::FeeType;
+use blockifier::transaction::transaction_execution::Transaction;
+use mc_db::{FeeUnit, StorageDiff, StoredTransactionReceipt, TransactionReceiptKind};
+use mp_block::commitments::BlockStateDiff;
+use mp_block::Block as StarknetBlock;
+use mp_digest_log::find_starknet_block;
+use mp_transactions::execution::charged_fee;
+use mp_transactions::{compute_message_hash, get_transaction_hash};
+use pallet_starknet_runtime_api::StarknetRuntimeApi;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
+use starknet_api::transaction::Fee;
+
+use crate::block_metrics::BlockMetrics;
+use crate::sync_blocks::block_state_diff;
+
+/// Computes the receipts of the transactions of `starknet_block`.
+///
+/// The receipts are built from the execution outcome recorded by the runtime when the block was
+/// imported. The runtimes predating [`StarknetRuntimeApi::get_tx_execution_resources`] don't record
+/// the execution resources, the transactions of their blocks are re-executed once on top of the
+/// parent block state instead. When `with_storage_diffs` is set, the final value of every storage
+/// slot written by the block is also returned, taken from `state_diff` when it is known.
+pub fn block_receipts<B: BlockT, C>(
+    client: &C,
+    substrate_block_hash: B::Hash,
+    parent_substrate_block_hash: B::Hash,
+    starknet_block: &StarknetBlock,
+    state_diff: Option<&BlockStateDiff>,
+    with_storage_diffs: bool,
+) -> anyhow::Result<(Vec<StoredTransactionReceipt>, Option<Vec<StorageDiff>>)>
+where
+    C: ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+{
+    let transactions = starknet_block.transactions();
+    if transactions.is_empty() {
+        return Ok((Vec::new(), with_storage_diffs.then(Vec::new)));
+    }
+
+    let runtime_api = client.runtime_api();
+    let recorded_outcomes = transactions
+        .iter()
+        .map(|transaction| {
+            runtime_api.get_tx_execution_resources(substrate_block_hash, *get_transaction_hash(transaction)).ok()?
+        })
+        .collect::<Option<Vec<_>>>();
+    let (outcomes, storage_diffs) = match (recorded_outcomes, state_diff) {
+        (Some(outcomes), Some(state_diff)) => (outcomes, with_storage_diffs.then(|| block_storage_diffs(state_diff))),
+        (Some(outcomes), None) if !with_storage_diffs => (outcomes, None),
+        _ => re_execute_block(client, parent_substrate_block_hash, transactions, with_storage_diffs)?,
+    };
+
+    let block_hash = starknet_block.header().hash().into();
+    let block_number = starknet_block.header().block_number;
+
+    let mut first_event_index = 0u32;
+    let mut receipts = Vec::with_capacity(transactions.len());
+    for (transaction_index, (transaction, (actual_fee, execution_resources))) in
+        transactions.iter().zip(outcomes).enumerate()
+    {
+        let transaction_hash = *get_transaction_hash(transaction);
+
+        let events = runtime_api.get_events_for_tx_by_hash(substrate_block_hash, transaction_hash)?;
+        let messages_sent = runtime_api.get_tx_messages_to_l1(substrate_block_hash, transaction_hash)?;
+        let revert_reason = runtime_api
+            .get_tx_execution_outcome(substrate_block_hash, transaction_hash)?
+            .map(|message| String::from_utf8_lossy(&message).into_owned());
+        // The runtimes predating sponsored transactions don't record any paymaster
+        let paymaster = runtime_api.get_tx_paymaster(substrate_block_hash, transaction_hash).ok().flatten();
+
+        let events_count = events.len() as u32;
+        receipts.push(StoredTransactionReceipt {
+            transaction_hash,
+            block_hash,
+            block_number,
+            transaction_index: transaction_index as u32,
+            kind: receipt_kind(transaction),
+            actual_fee,
+            fee_unit: fee_unit(transaction),
+            execution_resources: execution_resources.into_iter().collect(),
+            messages_sent,
+            first_event_index,
+            events,
+            revert_reason,
+            paymaster,
+        });
+        first_event_index += events_count;
+    }
+
+    Ok((receipts, storage_diffs))
+}
+
+/// Re-executes the transactions of a block on top of the state of its parent block
+///
+/// Returns the fee charged and the execution resources used by each transaction, along with the
+/// storage diffs of the block when `with_storage_diffs` is set.
+#[allow(clippy::type_complexity)]
+fn re_execute_block<B: BlockT, C>(
+    client: &C,
+    parent_substrate_block_hash: B::Hash,
+    transactions: &[Transaction],
+    with_storage_diffs: bool,
+) -> anyhow::Result<(Vec<(Fee, BTreeMap<String, u64>)>, Option<Vec<StorageDiff>>)>
+where
+    C: ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+{
+    let execution_infos = client
+        .runtime_api()
+        .re_execute_transactions(parent_substrate_block_hash, Vec::new(), transactions.to_vec(), with_storage_diffs)?
+        .map_err(|e| anyhow::anyhow!("Failed to re-execute the block transactions: {e:?}"))?
+        .map_err(|e| anyhow::anyhow!("One of the block transactions failed during its re-execution: {e:?}"))?;
+    anyhow::ensure!(
+        execution_infos.len() == transactions.len(),
+        "Re-execution returned {} results for {} transactions",
+        execution_infos.len(),
+        transactions.len()
+    );
+
+    let mut outcomes = Vec::with_capacity(execution_infos.len());
+    let mut storage_diffs = BTreeMap::new();
+    for (execution_info, state_diff) in execution_infos {
+        outcomes.push((
+            charged_fee(&execution_info),
+            execution_info
+                .actual_resources
+                .0
+                .into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value as u64))
+                .collect(),
+        ));
+
+        // Later transactions override the values written by the previous ones
+        for (contract_address, updates) in state_diff.into_iter().flat_map(|diff| diff.storage_updates) {
+            for (key, value) in updates {
+                storage_diffs.insert((contract_address, key), value);
+            }
+        }
+    }
+
+    let storage_diffs = with_storage_diffs.then(|| {
+        storage_diffs.into_iter().map(|((contract_address, key), value)| (contract_address, key, value)).collect()
+    });
+
+    Ok((outcomes, storage_diffs))
+}
+
+/// The final value of every storage slot written by the block
+fn block_storage_diffs(state_diff: &BlockStateDiff) -> Vec<StorageDiff> {
+    state_diff
+        .storage_diffs
+        .iter()
+        .flat_map(|(contract_address, updates)| updates.iter().map(|(key, value)| (*contract_address, *key, *value)))
+        .collect()
+}
+
+/// Index up to `limit` of the canonical blocks synced without their receipts, see
+/// [`mc_db::IndexingBackfill`]
+///
+/// Returns `true` if any block was indexed.
+pub fn backfill_indexes<B: BlockT, C>(
+    client: &C,
+    backend: &mc_db::Backend<B>,
+    limit: usize,
+    block_metrics: Option<&BlockMetrics>,
+) -> anyhow::Result<bool>
+where
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+{
+    let mut backfill = match backend.meta().indexing_backfill()? {
+        Some(backfill) if !backfill.is_done() => backfill,
+        _ => return Ok(false),
+    };
+
+    let mut failed_blocks = Vec::new();
+    for _ in 0..limit {
+        let (block_number, is_retry) = match (backfill.retry_blocks.pop_first(), backfill.next_block) {
+            (Some(block_number), _) => (block_number, true),
+            (None, Some(0)) => break,
+            (None, next_block) => {
+                let block_number = next_block
+                    .unwrap_or_else(|| UniqueSaturatedInto::<u64>::unique_saturated_into(client.info().best_number));
+                backfill.next_block = Some(block_number.saturating_sub(1));
+                // The genesis block does not contain any transaction
+                if block_number == 0 {
+                    break;
+                }
+                (block_number, false)
+            }
+        };
+
+        if let Err(e) = index_block(client, backend, block_number) {
+            if is_retry {
+                log::error!(
+                    target: "mapping-sync",
+                    "Failed again to compute the receipts of block #{block_number}, they won't be served: {e:#}"
+                );
+                backfill.skipped_blocks.insert(block_number);
+            } else {
+                log::warn!(
+                    target: "mapping-sync",
+                    "Failed to compute the receipts of block #{block_number}, scheduling a retry: {e:#}"
+                );
+                failed_blocks.push(block_number);
+            }
+        }
+    }
+    backfill.retry_blocks.extend(failed_blocks);
+
+    backend.meta().write_indexing_backfill(&backfill)?;
+    if let Some(block_metrics) = block_metrics {
+        block_metrics.receipts_skipped_blocks.set(backfill.skipped_blocks.len() as f64);
+    }
+    if backfill.is_done() {
+        log::info!(target: "mapping-sync", "Indexed the receipts of all the synced blocks");
+    }
+
+    Ok(true)
+}
+
+/// Compute and write the receipts of the canonical block `block_number`
+fn index_block<B: BlockT, C>(client: &C, backend: &mc_db::Backend<B>, block_number: u64) -> anyhow::Result<()>
+where
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+{
+    let substrate_block_hash = client
+        .hash(block_number.unique_saturated_into())?
+        .ok_or_else(|| anyhow::anyhow!("Block #{block_number} is not in the canonical chain"))?;
+    let header = client
+        .header(substrate_block_hash)?
+        .ok_or_else(|| anyhow::anyhow!("Header of block #{block_number} not found"))?;
+    let starknet_block = find_starknet_block(header.digest())?;
+
+    let state_diff = block_state_diff(client, substrate_block_hash, starknet_block.header());
+    let (receipts, storage_diffs) = block_receipts(
+        client,
+        substrate_block_hash,
+        *header.parent_hash(),
+        &starknet_block,
+        state_diff.as_ref(),
+        backend.storage_mode().is_archive(),
+    )?;
+    backend.mapping().write_receipts(&substrate_block_hash, block_number, &receipts, storage_diffs.as_deref())?;
+
+    Ok(())
+}
+
+fn receipt_kind(transaction: &Transaction) -> TransactionReceiptKind {
+    match transaction {
+        Transaction::AccountTransaction(AccountTransaction::Invoke(_)) => TransactionReceiptKind::Invoke,
+        Transaction::AccountTransaction(AccountTransaction::Declare(_)) => TransactionReceiptKind::Declare,
+        Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) => {
+            TransactionReceiptKind::DeployAccount { contract_address: tx.contract_address }
+        }
+        Transaction::L1HandlerTransaction(tx) => {
+            TransactionReceiptKind::L1Handler { message_hash: compute_message_hash(&tx.tx).to_fixed_bytes() }
+        }
+    }
+}
+
+/// FRI for v3 transactions paying in STRK, WEI otherwise.
+fn fee_unit(transaction: &Transaction) -> FeeUnit {
+    match transaction {
+        Transaction::AccountTransaction(tx) => match tx.fee_type() {
+            FeeType::Strk => FeeUnit::Fri,
+            FeeType::Eth => FeeUnit::Wei,
+        },
+        Transaction::L1HandlerTransaction(_) => FeeUnit::Wei,
+    }
+}
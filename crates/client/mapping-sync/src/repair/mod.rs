@@ -0,0 +1,269 @@
+//! Offline verification and repair of the mapping db
+//!
+//! Those functions are meant to be run by the `madara db` commands on the data directory of a
+//! stopped node, when the mapping db went out of sync with the Substrate backend.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transaction_execution::Transaction;
+use mp_block::Block as StarknetBlock;
+use mp_digest_log::{find_starknet_block, FindLogError};
+use mp_transactions::get_transaction_hash;
+use pallet_starknet_runtime_api::StarknetRuntimeApi;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor, One, UniqueSaturatedInto, Zero};
+use starknet_api::block::BlockHash;
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkHash;
+use starknet_api::state::ContractClass;
+use starknet_api::transaction::DeclareTransaction;
+
+use crate::sync_blocks::{sync_block, sync_genesis_block};
+
+/// Result of the verification of the mapping db against the canonical chain
+#[derive(Debug)]
+pub struct ChainVerification<B: BlockT> {
+    /// Number of blocks whose mapping agrees with their digest
+    pub valid_blocks: u64,
+    /// Number of blocks not synced yet, that the mapping sync worker will reach from a syncing tip
+    pub pending_blocks: u64,
+    /// Blocks not synced that no syncing tip leads to, the mapping sync worker will never sync them
+    pub unreachable_blocks: Vec<NumberFor<B>>,
+    /// Blocks whose mapping disagrees with their digest, with the reason
+    pub invalid_blocks: Vec<(NumberFor<B>, String)>,
+    /// Syncing tips that are not known by the Substrate backend
+    pub unknown_tips: Vec<B::Hash>,
+}
+
+impl<B: BlockT> ChainVerification<B> {
+    pub fn is_valid(&self) -> bool {
+        self.unreachable_blocks.is_empty() && self.invalid_blocks.is_empty() && self.unknown_tips.is_empty()
+    }
+}
+
+/// Walk the canonical chain from its tip down to genesis and cross-check every block digest
+/// against the mapping db
+///
+/// The mapping sync worker syncs blocks from the `CURRENT_SYNCING_TIPS` down to the first synced
+/// one, so a block that is not synced and not below a syncing tip is a hole in the mapping.
+pub fn verify_chain<B: BlockT, C>(client: &C, backend: &mc_db::Backend<B>) -> anyhow::Result<ChainVerification<B>>
+where
+    C: HeaderBackend<B>,
+{
+    let syncing_tips: HashSet<B::Hash> = backend.meta().current_syncing_tips()?.into_iter().collect();
+    let unknown_tips =
+        syncing_tips.iter().filter(|tip| !matches!(client.header(**tip), Ok(Some(_)))).cloned().collect();
+
+    let mut verification = ChainVerification {
+        valid_blocks: 0,
+        pending_blocks: 0,
+        unreachable_blocks: Vec::new(),
+        invalid_blocks: Vec::new(),
+        unknown_tips,
+    };
+
+    // The most recent blocks are reached from the chain leaves, once the syncing tips are exhausted
+    let mut below_syncing_tip = true;
+    let mut number = client.info().best_number;
+    loop {
+        let header = canonical_header(client, number)?;
+        let block_hash = header.hash();
+
+        if syncing_tips.contains(&block_hash) {
+            below_syncing_tip = true;
+        }
+
+        if backend.mapping().is_synced(&block_hash)? {
+            below_syncing_tip = false;
+            match check_block_mapping(backend, &header)? {
+                None => verification.valid_blocks += 1,
+                Some(reason) => verification.invalid_blocks.push((number, reason)),
+            }
+        } else if below_syncing_tip {
+            verification.pending_blocks += 1;
+        } else {
+            verification.unreachable_blocks.push(number);
+        }
+
+        if number.is_zero() {
+            break;
+        }
+        number -= One::one();
+    }
+
+    Ok(verification)
+}
+
+/// Return a description of the first inconsistency between the block digest and the mapping db
+fn check_block_mapping<B: BlockT>(backend: &mc_db::Backend<B>, header: &B::Header) -> anyhow::Result<Option<String>> {
+    let block_hash = header.hash();
+    let starknet_block = match find_starknet_block(header.digest()) {
+        Ok(starknet_block) => starknet_block,
+        Err(FindLogError::NotLog) => return Ok(None),
+        Err(FindLogError::MultipleLogs) => return Ok(Some("multiple Starknet logs in the digest".to_string())),
+    };
+    let starknet_block_hash: StarkHash = starknet_block.header().hash().into();
+
+    let mapped_blocks = backend.mapping().block_hash(BlockHash(starknet_block_hash))?.unwrap_or_default();
+    if !mapped_blocks.contains(&block_hash) {
+        return Ok(Some(format!("Starknet block {starknet_block_hash} is not mapped to this block")));
+    }
+
+    for (transaction_index, transaction) in starknet_block.transactions().iter().enumerate() {
+        let transaction_hash = *get_transaction_hash(transaction);
+        if backend.mapping().block_hash_from_transaction_hash(transaction_hash)? != Some(block_hash) {
+            return Ok(Some(format!("transaction {} is not mapped to this block", transaction_hash.0)));
+        }
+        if let Some(receipt) = backend.receipts().get_receipt(transaction_hash)? {
+            if receipt.block_hash != starknet_block_hash || receipt.transaction_index as usize != transaction_index {
+                return Ok(Some(format!("the receipt of transaction {} points to another block", transaction_hash.0)));
+            }
+        }
+    }
+
+    for class_hash in declared_sierra_classes(&starknet_block) {
+        if backend.sierra_classes().get_sierra_class(class_hash)?.is_none() {
+            return Ok(Some(format!("the Sierra class {} declared by the block is missing", class_hash.0)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Source of the Sierra classes declared on chain
+///
+/// The blocks only hold the compiled classes. The Sierra classes are stored in the Madara db when
+/// their declare transaction is received, so they have to be fetched elsewhere when they are lost.
+pub trait SierraClassSource {
+    /// Return the Sierra class `class_hash`, declared in block `block_number`
+    fn sierra_class(&self, class_hash: ClassHash, block_number: u64) -> anyhow::Result<Option<ContractClass>>;
+}
+
+/// Result of the reindexing of the canonical chain
+#[derive(Debug, Default)]
+pub struct Reindexing {
+    /// Number of reindexed blocks
+    pub reindexed_blocks: u64,
+    /// Blocks whose receipts could not be computed, they are left to the indexing backfill of the
+    /// mapping sync worker
+    pub blocks_without_receipts: Vec<u64>,
+    /// Number of Sierra classes missing from the db and restored from the source
+    pub restored_sierra_classes: u64,
+    /// Sierra classes declared by the reindexed blocks that could not be restored
+    pub missing_sierra_classes: Vec<ClassHash>,
+    /// Number of syncing tips and block mappings removed because their Substrate block is not
+    /// known anymore
+    pub purged_entries: u64,
+}
+
+/// Rebuild the mapping db, the transaction receipts and the L1 handler paid fees of the canonical
+/// blocks from `from` to the chain tip
+///
+/// The blocks imported before the execution outcome of their transactions was recorded are
+/// re-executed to compute their receipts, so the state of their parent block must be available.
+/// The Sierra classes declared by the blocks and missing from the db are restored from
+/// `sierra_classes` when given. The syncing tips, and the mappings of the reindexed Starknet
+/// blocks, pointing to Substrate blocks the backend does not know anymore, after a revert for
+/// instance, are purged.
+pub fn reindex_blocks<B: BlockT, C>(
+    client: &C,
+    backend: &mc_db::Backend<B>,
+    from: NumberFor<B>,
+    sierra_classes: Option<&dyn SierraClassSource>,
+) -> anyhow::Result<Reindexing>
+where
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+{
+    let is_stale = |block_hash: &B::Hash| matches!(client.header(*block_hash), Ok(None));
+    let mut reindexing = Reindexing::default();
+
+    let syncing_tips = backend.meta().current_syncing_tips()?;
+    let known_tips: Vec<_> = syncing_tips.iter().filter(|tip| !is_stale(tip)).cloned().collect();
+    if known_tips.len() != syncing_tips.len() {
+        reindexing.purged_entries += (syncing_tips.len() - known_tips.len()) as u64;
+        backend.meta().write_current_syncing_tips(known_tips)?;
+    }
+
+    let best_number = client.info().best_number;
+    let mut number = from;
+    while number <= best_number {
+        let header = canonical_header(client, number)?;
+        let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(number);
+
+        // The receipts are computed again, a pending retry would be redundant
+        if let Some(mut backfill) = backend.meta().indexing_backfill()? {
+            if backfill.retry_blocks.remove(&block_number) {
+                backend.meta().write_indexing_backfill(&backfill)?;
+            }
+        }
+
+        if number.is_zero() {
+            sync_genesis_block(client, backend, &header)?;
+        } else {
+            sync_block(client, backend, &header, None)?;
+        }
+
+        // Receipts are computed on a best-effort basis by the mapping sync worker
+        if backend.meta().indexing_backfill()?.is_some_and(|backfill| backfill.retry_blocks.contains(&block_number)) {
+            reindexing.blocks_without_receipts.push(block_number);
+        }
+
+        if let Ok(starknet_block) = find_starknet_block(header.digest()) {
+            reindexing.purged_entries +=
+                backend.mapping().purge_block_hashes(BlockHash(starknet_block.header().hash().into()), is_stale)?
+                    as u64;
+
+            for transaction in starknet_block.transactions() {
+                if let Transaction::L1HandlerTransaction(l1_handler) = transaction {
+                    backend
+                        .l1_handler_paid_fee()
+                        .store_fee_paid_for_l1_handler_tx(l1_handler.tx_hash.0, l1_handler.paid_fee_on_l1)?;
+                }
+            }
+
+            for class_hash in declared_sierra_classes(&starknet_block) {
+                if backend.sierra_classes().get_sierra_class(class_hash)?.is_some() {
+                    continue;
+                }
+                match sierra_classes.map(|source| source.sierra_class(class_hash, block_number)).transpose()?.flatten()
+                {
+                    Some(class) => {
+                        backend.sierra_classes().store_sierra_class(class_hash, class)?;
+                        reindexing.restored_sierra_classes += 1;
+                    }
+                    None => reindexing.missing_sierra_classes.push(class_hash),
+                }
+            }
+        }
+
+        reindexing.reindexed_blocks += 1;
+        number += One::one();
+    }
+
+    Ok(reindexing)
+}
+
+/// Hashes of the Sierra classes declared by the transactions of `block`
+fn declared_sierra_classes(block: &StarknetBlock) -> impl Iterator<Item = ClassHash> + '_ {
+    block.transactions().iter().filter_map(|transaction| match transaction {
+        Transaction::AccountTransaction(AccountTransaction::Declare(declare)) => match declare.tx() {
+            DeclareTransaction::V2(_) | DeclareTransaction::V3(_) => Some(declare.class_hash()),
+            DeclareTransaction::V0(_) | DeclareTransaction::V1(_) => None,
+        },
+        _ => None,
+    })
+}
+
+fn canonical_header<B: BlockT, C>(client: &C, number: NumberFor<B>) -> anyhow::Result<B::Header>
+where
+    C: HeaderBackend<B>,
+{
+    let block_hash = client.hash(number)?.ok_or_else(|| anyhow::anyhow!("No canonical block at height {number:?}"))?;
+    client.header(block_hash)?.ok_or_else(|| anyhow::anyhow!("Header of block {block_hash:?} not found"))
+}
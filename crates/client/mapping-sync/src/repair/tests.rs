@@ -0,0 +1,426 @@
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroU128;
+
+use blockifier::blockifier::block::GasPrices;
+use blockifier::context::{BlockContext, FeeTokenAddresses};
+use blockifier::execution::contract_class::{ClassInfo, ContractClass as CompiledClass, ContractClassV1};
+use blockifier::state::cached_state::CommitmentStateDiff;
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use blockifier::transaction::transactions::{DeclareTransaction as BlockifierDeclareTransaction, L1HandlerTransaction};
+use mc_db::{DatabaseOptions, MappingCommitment};
+use mp_block::commitments::BlockStateDiff;
+use mp_block::Header as StarknetHeader;
+use mp_digest_log::{Log, MADARA_ENGINE_ID};
+use mp_felt::Felt252Wrapper;
+use mp_simulations::{
+    FeeEstimate, InternalSubstrateError, ReExecutionResult, SimulationError, SimulationFlags,
+    TransactionSimulationResult,
+};
+use mp_starknet_inherent::L1GasPrices;
+use parity_scale_codec::Encode;
+use sc_client_db::DatabaseSource;
+use sp_api::ApiRef;
+use sp_blockchain::{BlockStatus, Info};
+use sp_runtime::generic::{Block as GenericBlock, Header};
+use sp_runtime::testing::H256;
+use sp_runtime::traits::BlakeTwo256;
+use sp_runtime::{Digest, DigestItem, OpaqueExtrinsic};
+use starknet_api::core::{ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{
+    Calldata, DeclareTransactionV2, Event as StarknetEvent, Fee, MessageToL1, TransactionHash,
+};
+use tempfile::TempDir;
+
+use super::*;
+
+type Block = GenericBlock<Header<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+/// A chain of blocks, all canonical
+struct MockClient {
+    headers: Vec<Header<u32, BlakeTwo256>>,
+    records_execution_resources: bool,
+}
+
+impl MockClient {
+    /// Wrap each of `starknet_blocks` in a Substrate block, the first one being the genesis
+    fn new(starknet_blocks: Vec<StarknetBlock>) -> Self {
+        let mut headers: Vec<Header<u32, BlakeTwo256>> = Vec::new();
+        for (number, starknet_block) in starknet_blocks.into_iter().enumerate() {
+            let parent_hash = headers.last().map(|header| header.hash()).unwrap_or_default();
+            headers.push(substrate_header(number as u32, parent_hash, starknet_block, H256::zero()));
+        }
+        Self { headers, records_execution_resources: false }
+    }
+
+    /// Record the execution resources of the transactions, as the runtime does at import
+    fn recording_execution_resources(self) -> Self {
+        Self { records_execution_resources: true, ..self }
+    }
+
+    fn block_hash(&self, number: u32) -> H256 {
+        self.headers[number as usize].hash()
+    }
+}
+
+impl HeaderBackend<Block> for MockClient {
+    fn header(&self, hash: H256) -> sp_blockchain::Result<Option<Header<u32, BlakeTwo256>>> {
+        Ok(self.headers.iter().find(|header| header.hash() == hash).cloned())
+    }
+
+    fn info(&self) -> Info<Block> {
+        let best = self.headers.last().unwrap();
+        Info {
+            best_hash: best.hash(),
+            best_number: *best.number(),
+            genesis_hash: self.block_hash(0),
+            finalized_hash: best.hash(),
+            finalized_number: *best.number(),
+            finalized_state: None,
+            number_leaves: 1,
+            block_gap: None,
+        }
+    }
+
+    fn status(&self, hash: H256) -> sp_blockchain::Result<BlockStatus> {
+        Ok(if self.header(hash)?.is_some() { BlockStatus::InChain } else { BlockStatus::Unknown })
+    }
+
+    fn number(&self, hash: H256) -> sp_blockchain::Result<Option<u32>> {
+        Ok(self.header(hash)?.map(|header| *header.number()))
+    }
+
+    fn hash(&self, number: u32) -> sp_blockchain::Result<Option<H256>> {
+        Ok(self.headers.get(number as usize).map(|header| header.hash()))
+    }
+}
+
+impl ProvideRuntimeApi<Block> for MockClient {
+    type Api = MockApi;
+
+    fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+        MockApi { records_execution_resources: self.records_execution_resources }.into()
+    }
+}
+
+/// A runtime whose re-execution of transactions always fails
+struct MockApi {
+    records_execution_resources: bool,
+}
+
+sp_api::mock_impl_runtime_apis! {
+    impl StarknetRuntimeApi<Block> for MockApi {
+        fn nonce(_contract_address: ContractAddress) -> Nonce {
+            unimplemented!()
+        }
+        fn get_storage_at(_address: ContractAddress, _key: StorageKey) -> Result<StarkFelt, SimulationError> {
+            unimplemented!()
+        }
+        fn call(
+            _address: ContractAddress,
+            _function_selector: EntryPointSelector,
+            _calldata: Calldata,
+        ) -> Result<Vec<Felt252Wrapper>, SimulationError> {
+            unimplemented!()
+        }
+        fn contract_class_hash_by_address(_address: ContractAddress) -> ClassHash {
+            unimplemented!()
+        }
+        fn contract_class_by_class_hash(_class_hash: ClassHash) -> Option<CompiledClass> {
+            unimplemented!()
+        }
+        fn chain_id() -> Felt252Wrapper {
+            unimplemented!()
+        }
+        fn program_hash() -> Felt252Wrapper {
+            unimplemented!()
+        }
+        fn fee_token_addresses() -> FeeTokenAddresses {
+            unimplemented!()
+        }
+        fn estimate_fee(
+            _transactions: Vec<AccountTransaction>,
+            _simulation_flags: SimulationFlags,
+        ) -> Result<Result<Vec<FeeEstimate>, SimulationError>, InternalSubstrateError> {
+            unimplemented!()
+        }
+        fn estimate_message_fee(
+            _message: L1HandlerTransaction,
+        ) -> Result<Result<FeeEstimate, SimulationError>, InternalSubstrateError> {
+            unimplemented!()
+        }
+        fn simulate_message(
+            _message: L1HandlerTransaction,
+            _simulation_flags: SimulationFlags,
+        ) -> Result<Result<TransactionExecutionInfo, SimulationError>, InternalSubstrateError> {
+            unimplemented!()
+        }
+        fn simulate_transactions(
+            _transactions: Vec<AccountTransaction>,
+            _simulation_flags: SimulationFlags,
+        ) -> Result<Vec<TransactionSimulationResult>, InternalSubstrateError> {
+            unimplemented!()
+        }
+        fn extrinsic_filter(_xts: Vec<OpaqueExtrinsic>) -> Vec<Transaction> {
+            unimplemented!()
+        }
+        fn re_execute_transactions(
+            _transactions_before: Vec<Transaction>,
+            _transactions_to_trace: Vec<Transaction>,
+            _with_state_diff: bool,
+        ) -> Result<ReExecutionResult, InternalSubstrateError> {
+            Err(InternalSubstrateError::FailedToCreateATransactionalStorageExecution)
+        }
+        fn get_transaction_re_execution_state_diff(
+            _transactions_before: Vec<Transaction>,
+            _transactions: Vec<Transaction>,
+        ) -> Result<Result<CommitmentStateDiff, SimulationError>, InternalSubstrateError> {
+            unimplemented!()
+        }
+        fn get_index_and_tx_for_tx_hash(
+            _xts: Vec<OpaqueExtrinsic>,
+            _tx_hash: TransactionHash,
+        ) -> Option<(u32, Transaction)> {
+            unimplemented!()
+        }
+        fn get_events_for_tx_by_hash(_tx_hash: TransactionHash) -> Vec<StarknetEvent> {
+            Vec::new()
+        }
+        fn get_tx_execution_outcome(_tx_hash: TransactionHash) -> Option<Vec<u8>> {
+            None
+        }
+        fn get_block_context() -> BlockContext {
+            unimplemented!()
+        }
+        fn is_transaction_fee_disabled() -> bool {
+            false
+        }
+        fn get_tx_messages_to_l1(_tx_hash: TransactionHash) -> Vec<MessageToL1> {
+            Vec::new()
+        }
+        fn get_tx_paymaster(_tx_hash: TransactionHash) -> Option<ContractAddress> {
+            None
+        }
+        fn get_tx_execution_resources(&self, _tx_hash: TransactionHash) -> Option<(Fee, BTreeMap<String, u64>)> {
+            self.records_execution_resources.then(|| (Fee(1), BTreeMap::from([("n_steps".to_string(), 10)])))
+        }
+        fn l1_nonce_unused(_nonce: Nonce) -> bool {
+            unimplemented!()
+        }
+        fn current_l1_gas_prices() -> L1GasPrices {
+            unimplemented!()
+        }
+        fn sequencer_address_for_slot(_slot: u64) -> Option<ContractAddress> {
+            unimplemented!()
+        }
+        fn last_state_diff() -> BlockStateDiff {
+            BlockStateDiff::default()
+        }
+    }
+}
+
+struct MockSierraClasses(HashMap<ClassHash, ContractClass>);
+
+impl SierraClassSource for MockSierraClasses {
+    fn sierra_class(&self, class_hash: ClassHash, _block_number: u64) -> anyhow::Result<Option<ContractClass>> {
+        Ok(self.0.get(&class_hash).cloned())
+    }
+}
+
+fn starknet_block(block_number: u64, transactions: Vec<Transaction>) -> StarknetBlock {
+    let l1_gas_price = NonZeroU128::new(10).unwrap();
+    StarknetBlock::try_new(
+        StarknetHeader {
+            parent_block_hash: Default::default(),
+            block_number,
+            sequencer_address: Default::default(),
+            block_timestamp: Default::default(),
+            global_state_root: Default::default(),
+            transaction_count: transactions.len() as u128,
+            transaction_commitment: Default::default(),
+            event_count: Default::default(),
+            event_commitment: Default::default(),
+            receipt_commitment: Default::default(),
+            state_diff_commitment: Default::default(),
+            state_diff_length: Default::default(),
+            l1_da_mode: Default::default(),
+            protocol_version: Default::default(),
+            l1_gas_price: GasPrices {
+                eth_l1_gas_price: l1_gas_price,
+                strk_l1_gas_price: l1_gas_price,
+                eth_l1_data_gas_price: l1_gas_price,
+                strk_l1_data_gas_price: l1_gas_price,
+            },
+            extra_data: Default::default(),
+        },
+        transactions,
+    )
+    .unwrap()
+}
+
+fn substrate_header(
+    number: u32,
+    parent_hash: H256,
+    starknet_block: StarknetBlock,
+    state_root: H256,
+) -> Header<u32, BlakeTwo256> {
+    let mut digest = Digest::default();
+    digest.push(DigestItem::Consensus(MADARA_ENGINE_ID, Log::Block(starknet_block).encode()));
+    Header::new(number, Default::default(), state_root, parent_hash, digest)
+}
+
+/// A Cairo 1 declare transaction, whose hash is the class hash
+fn declare_transaction(class_hash: ClassHash) -> Transaction {
+    let compiled_class = CompiledClass::V1(
+        ContractClassV1::try_from_json_string(include_str!(
+            "../../../../../cairo-contracts/build/cairo_1/HelloStarknet.casm.json"
+        ))
+        .unwrap(),
+    );
+    let tx = DeclareTransaction::V2(DeclareTransactionV2 {
+        max_fee: Fee(0),
+        signature: Default::default(),
+        nonce: Default::default(),
+        class_hash,
+        compiled_class_hash: Default::default(),
+        sender_address: Default::default(),
+    });
+
+    Transaction::AccountTransaction(AccountTransaction::Declare(
+        BlockifierDeclareTransaction::new(
+            tx,
+            TransactionHash(class_hash.0),
+            ClassInfo::new(&compiled_class, 1, 0).unwrap(),
+        )
+        .unwrap(),
+    ))
+}
+
+fn open_backend() -> (TempDir, mc_db::Backend<Block>) {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = mc_db::Backend::open(
+        &DatabaseSource::ParityDb { path: Default::default() },
+        dir.path(),
+        None,
+        &DatabaseOptions::default(),
+    )
+    .unwrap();
+    (dir, backend)
+}
+
+fn empty_chain(length: u64) -> MockClient {
+    MockClient::new((0..length).map(|block_number| starknet_block(block_number, Vec::new())).collect())
+}
+
+#[test]
+fn reindexing_rebuilds_a_valid_mapping() {
+    let client = empty_chain(4);
+    let (_dir, backend) = open_backend();
+    backend.meta().write_current_syncing_tips(vec![H256::repeat_byte(0xff)]).unwrap();
+
+    let reindexing = reindex_blocks(&client, &backend, 0, None).unwrap();
+    assert_eq!(reindexing.reindexed_blocks, 4);
+    assert_eq!(reindexing.purged_entries, 1);
+    assert!(reindexing.blocks_without_receipts.is_empty());
+    assert!(backend.meta().current_syncing_tips().unwrap().is_empty());
+
+    let verification = verify_chain(&client, &backend).unwrap();
+    assert_eq!(verification.valid_blocks, 4);
+    assert_eq!(verification.pending_blocks, 0);
+    assert!(verification.is_valid());
+}
+
+#[test]
+fn blocks_no_syncing_tip_leads_to_are_unreachable() {
+    let client = empty_chain(4);
+    let (_dir, backend) = open_backend();
+    reindex_blocks(&client, &backend, 3, None).unwrap();
+
+    let verification = verify_chain(&client, &backend).unwrap();
+    assert_eq!(verification.valid_blocks, 1);
+    assert_eq!(verification.unreachable_blocks, vec![2, 1, 0]);
+    assert!(!verification.is_valid());
+
+    backend.meta().write_current_syncing_tips(vec![client.block_hash(2)]).unwrap();
+    let verification = verify_chain(&client, &backend).unwrap();
+    assert_eq!(verification.pending_blocks, 3);
+    assert!(verification.is_valid());
+
+    let unknown_tip = H256::repeat_byte(0xff);
+    backend.meta().write_current_syncing_tips(vec![client.block_hash(2), unknown_tip]).unwrap();
+    let verification = verify_chain(&client, &backend).unwrap();
+    assert_eq!(verification.unknown_tips, vec![unknown_tip]);
+    assert!(!verification.is_valid());
+}
+
+#[test]
+fn reindexing_purges_the_mappings_of_unknown_blocks() {
+    let client = empty_chain(2);
+    let (_dir, backend) = open_backend();
+
+    // A block of a fork that has since been reverted, wrapping the same Starknet block
+    let block = starknet_block(1, Vec::new());
+    let starknet_block_hash = block.header().hash();
+    let stale_header = substrate_header(1, client.block_hash(0), block, H256::repeat_byte(1));
+    backend
+        .mapping()
+        .write_hashes(MappingCommitment {
+            block_hash: stale_header.hash(),
+            starknet_block_hash: starknet_block_hash.into(),
+            starknet_transaction_hashes: Vec::new(),
+            starknet_transaction_receipts: Vec::new(),
+            starknet_block_number: 1,
+            starknet_storage_diffs: None,
+            starknet_state_diff: None,
+        })
+        .unwrap();
+
+    let reindexing = reindex_blocks(&client, &backend, 0, None).unwrap();
+    assert_eq!(reindexing.purged_entries, 1);
+    assert_eq!(backend.mapping().block_hash(starknet_block_hash.into()).unwrap(), Some(vec![client.block_hash(1)]));
+    assert!(!backend.mapping().is_synced(&stale_header.hash()).unwrap());
+}
+
+#[test]
+fn reindexing_restores_sierra_classes_and_reports_blocks_without_receipts() {
+    let restored_class = ClassHash(StarkFelt::from(1u8));
+    let lost_class = ClassHash(StarkFelt::from(2u8));
+    let client = MockClient::new(vec![
+        starknet_block(0, Vec::new()),
+        starknet_block(1, vec![declare_transaction(restored_class), declare_transaction(lost_class)]),
+    ]);
+    let (_dir, backend) = open_backend();
+    let sierra_classes = MockSierraClasses(HashMap::from([(restored_class, ContractClass::default())]));
+
+    let reindexing = reindex_blocks(&client, &backend, 0, Some(&sierra_classes)).unwrap();
+    assert_eq!(reindexing.reindexed_blocks, 2);
+    assert_eq!(reindexing.blocks_without_receipts, vec![1]);
+    assert_eq!(reindexing.restored_sierra_classes, 1);
+    assert_eq!(reindexing.missing_sierra_classes, vec![lost_class]);
+    assert_eq!(backend.sierra_classes().get_sierra_class(restored_class).unwrap(), Some(ContractClass::default()));
+
+    let verification = verify_chain(&client, &backend).unwrap();
+    assert_eq!(verification.valid_blocks, 1);
+    assert_eq!(verification.invalid_blocks.len(), 1);
+    assert_eq!(verification.invalid_blocks[0].0, 1);
+}
+
+#[test]
+fn reindexing_builds_receipts_from_the_recorded_execution_resources() {
+    let class_hash = ClassHash(StarkFelt::from(1u8));
+    let client =
+        MockClient::new(vec![starknet_block(0, Vec::new()), starknet_block(1, vec![declare_transaction(class_hash)])])
+            .recording_execution_resources();
+    let (_dir, backend) = open_backend();
+    let sierra_classes = MockSierraClasses(HashMap::from([(class_hash, ContractClass::default())]));
+
+    let reindexing = reindex_blocks(&client, &backend, 0, Some(&sierra_classes)).unwrap();
+    assert!(reindexing.blocks_without_receipts.is_empty());
+
+    let receipt = backend.receipts().get_receipt(TransactionHash(class_hash.0)).unwrap().unwrap();
+    assert_eq!(receipt.actual_fee, Fee(1));
+    assert_eq!(receipt.execution_resources, vec![("n_steps".to_string(), 10)]);
+
+    assert!(verify_chain(&client, &backend).unwrap().is_valid());
+}
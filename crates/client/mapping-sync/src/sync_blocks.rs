@@ -1,4 +1,6 @@
 use mc_rpc_core::utils::get_block_by_block_hash;
+use mp_block::commitments::BlockStateDiff;
+use mp_block::Header as StarknetHeader;
 use mp_digest_log::{find_starknet_block, FindLogError};
 use mp_transactions::get_transaction_hash;
 use num_traits::FromPrimitive;
@@ -7,21 +9,20 @@ use prometheus_endpoint::prometheus::core::Number;
 use sc_client_api::backend::{Backend, StorageProvider};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::{Backend as _, HeaderBackend};
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT, Zero};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto, Zero};
 
 use crate::block_metrics::BlockMetrics;
+use crate::receipts::{backfill_indexes, block_receipts};
 
-fn sync_block<B: BlockT, C, BE>(
+pub(crate) fn sync_block<B: BlockT, C>(
     client: &C,
     backend: &mc_db::Backend<B>,
     header: &B::Header,
     block_metrics: Option<&BlockMetrics>,
 ) -> anyhow::Result<()>
 where
-    C: HeaderBackend<B> + StorageProvider<B, BE>,
-    C: ProvideRuntimeApi<B>,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
     C::Api: StarknetRuntimeApi<B>,
-    BE: Backend<B>,
 {
     // Before storing the new block in the Madara backend database, we want to make sure that the
     // wrapped Starknet block it contains is the same that we can find in the storage at this height.
@@ -43,7 +44,30 @@ where
                              db state ({storage_starknet_block_hash:?})"
                         ))
                     } else {
-                        // Success, we write the Starknet to Substate hashes mapping to db
+                        // Success, we compute the transaction receipts and write them to db along with the
+                        // Starknet to Substate hashes mapping
+                        let block_number = digest_starknet_block.header().block_number;
+                        let state_diff = block_state_diff(client, substrate_block_hash, digest_starknet_block.header());
+                        let (receipts, storage_diffs) = match block_receipts(
+                            client,
+                            substrate_block_hash,
+                            *header.parent_hash(),
+                            &digest_starknet_block,
+                            state_diff.as_ref(),
+                            backend.storage_mode().is_archive(),
+                        ) {
+                            Ok(indexes) => indexes,
+                            // The mapping is still written so that the sync goes on, the receipts are
+                            // computed again later on and not served by the RPC in the meantime
+                            Err(e) => {
+                                log::warn!(
+                                    target: "mapping-sync",
+                                    "Failed to compute the receipts of block #{block_number}, scheduling a retry: {e:#}"
+                                );
+                                backend.meta().schedule_indexing_retry(block_number)?;
+                                Default::default()
+                            }
+                        };
                         let mapping_commitment = mc_db::MappingCommitment {
                             block_hash: substrate_block_hash,
                             starknet_block_hash: digest_starknet_block_hash.into(),
@@ -53,6 +77,10 @@ where
                                 .map(get_transaction_hash)
                                 .cloned()
                                 .collect(),
+                            starknet_transaction_receipts: receipts,
+                            starknet_block_number: block_number,
+                            starknet_storage_diffs: storage_diffs,
+                            starknet_state_diff: state_diff,
                         };
 
                         if let Some(block_metrics) = block_metrics {
@@ -98,7 +126,51 @@ where
     }
 }
 
-fn sync_genesis_block<B: BlockT, C>(_client: &C, backend: &mc_db::Backend<B>, header: &B::Header) -> anyhow::Result<()>
+/// The state diff recorded by the runtime for the block, if it is the one committed to by its
+/// header
+///
+/// The runtimes predating [`StarknetRuntimeApi::last_state_diff`] don't record it, and the state of
+/// the block may be pruned, in which case the state diff is not stored.
+pub(crate) fn block_state_diff<B: BlockT, C>(
+    client: &C,
+    substrate_block_hash: B::Hash,
+    header: &StarknetHeader,
+) -> Option<BlockStateDiff>
+where
+    C: ProvideRuntimeApi<B>,
+    C::Api: StarknetRuntimeApi<B>,
+{
+    match client.runtime_api().last_state_diff(substrate_block_hash) {
+        Ok(state_diff)
+            if state_diff.commitment() == header.state_diff_commitment
+                && state_diff.len() == header.state_diff_length =>
+        {
+            Some(state_diff)
+        }
+        Ok(_) => {
+            log::debug!(
+                target: "mapping-sync",
+                "The state diff of block #{} is not recorded by the runtime",
+                header.block_number
+            );
+            None
+        }
+        Err(e) => {
+            log::debug!(
+                target: "mapping-sync",
+                "Failed to read the state diff of block #{}: {e}",
+                header.block_number
+            );
+            None
+        }
+    }
+}
+
+pub(crate) fn sync_genesis_block<B: BlockT, C>(
+    _client: &C,
+    backend: &mc_db::Backend<B>,
+    header: &B::Header,
+) -> anyhow::Result<()>
 where
     C: HeaderBackend<B>,
     B: BlockT,
@@ -117,6 +189,11 @@ where
         block_hash: substrate_block_hash,
         starknet_block_hash: block_hash.into(),
         starknet_transaction_hashes: Vec::new(),
+        starknet_transaction_receipts: Vec::new(),
+        starknet_block_number: block.header().block_number,
+        starknet_storage_diffs: backend.storage_mode().is_archive().then(Vec::new),
+        // The genesis header commits to no state update, the genesis state is not a diff
+        starknet_state_diff: Some(BlockStateDiff::default()),
     };
 
     backend.mapping().write_hashes(mapping_commitment)?;
@@ -170,7 +247,7 @@ where
         madara_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
         Ok(true)
     } else {
-        sync_block::<_, _, _>(client, madara_backend, &operating_header, block_metrics)?;
+        sync_block::<_, _>(client, madara_backend, &operating_header, block_metrics)?;
 
         current_syncing_tips.push(*operating_header.parent_hash());
         madara_backend.meta().write_current_syncing_tips(current_syncing_tips)?;
@@ -199,9 +276,52 @@ where
             || sync_one_block::<_, _, _>(client, substrate_backend, madara_backend, sync_from, block_metrics)?;
     }
 
+    // The blocks synced without their receipts are indexed once the chain tip is reached
+    if !synced_any {
+        synced_any = backfill_indexes::<_, _>(client, madara_backend, limit, block_metrics)?;
+    }
+
+    if madara_backend.storage_mode().is_archive() {
+        synced_any = index_storage_history::<_, _>(client, madara_backend, limit)? || synced_any;
+    }
+
     Ok(synced_any)
 }
 
+/// Add up to `limit` finalized blocks to the historical storage index
+///
+/// Returns `true` if any block was indexed.
+fn index_storage_history<B: BlockT, C>(
+    client: &C,
+    madara_backend: &mc_db::Backend<B>,
+    limit: usize,
+) -> anyhow::Result<bool>
+where
+    C: HeaderBackend<B>,
+{
+    let historical_storage = madara_backend.historical_storage();
+    let finalized_number = UniqueSaturatedInto::<u64>::unique_saturated_into(client.info().finalized_number);
+    let mut next_block = historical_storage.last_indexed_block()?.map_or(0, |block_number| block_number + 1);
+
+    let mut indexed_any = false;
+    for _ in 0..limit {
+        if next_block > finalized_number {
+            break;
+        }
+        let block_hash = client
+            .hash(next_block.unique_saturated_into())?
+            .ok_or_else(|| anyhow::anyhow!("Finalized block #{next_block} not found"))?;
+        // The storage diffs of the block are not computed yet, see `backfill_indexes`
+        if !historical_storage.index_block(next_block, &block_hash)? {
+            break;
+        }
+        indexed_any = true;
+        next_block += 1;
+    }
+
+    Ok(indexed_any)
+}
+
 fn fetch_header<B: BlockT, BE>(
     substrate_backend: &BE,
     madara_backend: &mc_db::Backend<B>,
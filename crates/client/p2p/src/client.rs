@@ -0,0 +1,198 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transaction_execution::Transaction as ExecutionTransaction;
+use mc_rpc_core::utils::{blockifier_to_rpc_contract_class_types, get_block_by_block_hash};
+use mc_storage::OverrideHandle;
+use mp_block::Block as StarknetBlock;
+use mp_felt::Felt252Wrapper;
+use mp_transactions::get_transaction_hash;
+use mp_transactions::to_starknet_core_transaction::to_starknet_core_tx;
+use sc_client_api::{HeaderBackend, StorageProvider};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use starknet_api::block::BlockHash;
+use starknet_api::core::ClassHash;
+use starknet_core::types::ContractClass;
+
+use crate::convert;
+use crate::error::Error;
+use crate::proto::{Class, Event, Hash, Receipt, SignedBlockHeader, Transaction};
+use crate::source::{BlockSource, StateDiff};
+
+/// The classes of the chain which are not kept by the Madara DB, read from the state of the blocks.
+pub trait StateClassSource<B: BlockT>: Send + Sync + 'static {
+    /// The class `class_hash` in the state of the block `substrate_hash`.
+    fn contract_class(&self, substrate_hash: B::Hash, class_hash: ClassHash) -> Option<BlockifierContractClass>;
+}
+
+/// Reads the classes from the storage of the runtime.
+pub struct StorageClasses<B: BlockT, C, BE> {
+    client: Arc<C>,
+    overrides: Arc<OverrideHandle<B>>,
+    _backend: PhantomData<fn() -> BE>,
+}
+
+impl<B: BlockT, C, BE> StorageClasses<B, C, BE> {
+    pub fn new(client: Arc<C>, overrides: Arc<OverrideHandle<B>>) -> Self {
+        Self { client, overrides, _backend: PhantomData }
+    }
+}
+
+impl<B, C, BE> StateClassSource<B> for StorageClasses<B, C, BE>
+where
+    B: BlockT,
+    BE: sc_client_api::Backend<B> + 'static,
+    C: HeaderBackend<B> + StorageProvider<B, BE> + Send + Sync + 'static,
+{
+    fn contract_class(&self, substrate_hash: B::Hash, class_hash: ClassHash) -> Option<BlockifierContractClass> {
+        self.overrides
+            .for_block_hash(self.client.as_ref(), substrate_hash)
+            .contract_class_by_class_hash(substrate_hash, class_hash)
+    }
+}
+
+/// Serves the blocks imported by the node, read from the Substrate client and the Madara DB.
+///
+/// The receipts, events and state diffs of a block are served once the mapping sync worker has
+/// stored them.
+pub struct ClientBlockSource<B: BlockT, C, S> {
+    client: Arc<C>,
+    backend: Arc<mc_db::Backend<B>>,
+    classes: S,
+}
+
+impl<B, C, S> ClientBlockSource<B, C, S>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + Send + Sync + 'static,
+    S: StateClassSource<B>,
+{
+    pub fn new(client: Arc<C>, backend: Arc<mc_db::Backend<B>>, classes: S) -> Self {
+        Self { client, backend, classes }
+    }
+
+    fn substrate_hash(&self, block_number: u64) -> Result<Option<B::Hash>, Error> {
+        Ok(self.client.hash(UniqueSaturatedInto::unique_saturated_into(block_number))?)
+    }
+
+    fn block(&self, block_number: u64) -> Result<Option<(B::Hash, StarknetBlock)>, Error> {
+        let Some(substrate_hash) = self.substrate_hash(block_number)? else {
+            return Ok(None);
+        };
+        let block = get_block_by_block_hash(self.client.as_ref(), substrate_hash)
+            .map_err(|e| Error::IncompleteBlock { block_number, what: format!("its Starknet block: {e}") })?;
+        Ok(Some((substrate_hash, block)))
+    }
+
+    /// The receipts of the transactions of the block, once the mapping sync worker has stored them.
+    fn stored_receipts(&self, block_number: u64) -> Result<Option<Vec<mc_db::StoredTransactionReceipt>>, Error> {
+        let Some((_, block)) = self.block(block_number)? else {
+            return Ok(None);
+        };
+        let mut receipts = Vec::with_capacity(block.transactions().len());
+        for tx in block.transactions() {
+            match self.backend.receipts().get_receipt(*get_transaction_hash(tx))? {
+                Some(receipt) => receipts.push(receipt),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(receipts))
+    }
+
+    fn declared_class(
+        &self,
+        block_number: u64,
+        substrate_hash: B::Hash,
+        class_hash: ClassHash,
+    ) -> Result<Class, Error> {
+        let missing_class = || Error::IncompleteBlock {
+            block_number,
+            what: format!("the class {:#x}", Felt252Wrapper::from(class_hash)),
+        };
+
+        if let Some(sierra_class) = self.backend.sierra_classes().get_sierra_class(class_hash)? {
+            return Ok(convert::cairo1_class(class_hash, sierra_class));
+        }
+
+        let contract_class = self.classes.contract_class(substrate_hash, class_hash).ok_or_else(missing_class)?;
+        match blockifier_to_rpc_contract_class_types(contract_class) {
+            Ok(ContractClass::Legacy(class)) => Ok(convert::cairo0_class(class_hash, class)),
+            Ok(ContractClass::Sierra(_)) => Err(missing_class()),
+            Err(e) => Err(Error::Conversion { block_number, what: "a declared class", reason: e.to_string() }),
+        }
+    }
+}
+
+/// The classes declared by the transactions of a block.
+fn declared_class_hashes(block: &StarknetBlock) -> impl Iterator<Item = ClassHash> + '_ {
+    block.transactions().iter().filter_map(|tx| match tx {
+        ExecutionTransaction::AccountTransaction(AccountTransaction::Declare(declare)) => {
+            Some(declare.tx().class_hash())
+        }
+        _ => None,
+    })
+}
+
+impl<B, C, S> BlockSource for ClientBlockSource<B, C, S>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + Send + Sync + 'static,
+    S: StateClassSource<B>,
+{
+    fn block_number(&self, block_hash: &Hash) -> Result<Option<u64>, Error> {
+        let Ok(block_hash) = Felt252Wrapper::try_from(&block_hash.elements[..]) else {
+            return Ok(None);
+        };
+        let substrate_hashes = self.backend.mapping().block_hash(BlockHash(block_hash.into()))?.unwrap_or_default();
+        for substrate_hash in substrate_hashes {
+            let Some(block_number) = self.client.number(substrate_hash)? else {
+                continue;
+            };
+            let block_number: u64 = UniqueSaturatedInto::unique_saturated_into(block_number);
+            // Only the blocks of the canonical chain are served
+            if self.substrate_hash(block_number)? == Some(substrate_hash) {
+                return Ok(Some(block_number));
+            }
+        }
+        Ok(None)
+    }
+
+    fn header(&self, block_number: u64) -> Result<Option<SignedBlockHeader>, Error> {
+        Ok(self.block(block_number)?.map(|(_, block)| convert::header(block.header())))
+    }
+
+    fn transactions(&self, block_number: u64) -> Result<Option<Vec<Transaction>>, Error> {
+        Ok(self.block(block_number)?.map(|(_, block)| {
+            block.transactions().iter().cloned().map(|tx| convert::transaction(to_starknet_core_tx(tx))).collect()
+        }))
+    }
+
+    fn receipts(&self, block_number: u64) -> Result<Option<Vec<Receipt>>, Error> {
+        Ok(self.stored_receipts(block_number)?.map(|receipts| receipts.iter().map(convert::receipt).collect()))
+    }
+
+    fn events(&self, block_number: u64) -> Result<Option<Vec<Event>>, Error> {
+        Ok(self.stored_receipts(block_number)?.map(|receipts| receipts.iter().flat_map(convert::events).collect()))
+    }
+
+    /// The state diff stored with the block, rather than computed again, which would execute the
+    /// block on the state of its parent, pruned by most nodes.
+    fn state_diff(&self, block_number: u64) -> Result<Option<StateDiff>, Error> {
+        let Some(substrate_hash) = self.substrate_hash(block_number)? else {
+            return Ok(None);
+        };
+        Ok(self.backend.state_diffs().get_state_diff(&substrate_hash)?.map(convert::state_diff))
+    }
+
+    fn classes(&self, block_number: u64) -> Result<Option<Vec<Class>>, Error> {
+        let Some((substrate_hash, block)) = self.block(block_number)? else {
+            return Ok(None);
+        };
+        declared_class_hashes(&block)
+            .map(|class_hash| self.declared_class(block_number, substrate_hash, class_hash))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
@@ -0,0 +1,452 @@
+//! Framing of the sync protocols.
+//!
+//! A request is a single length-delimited [`IterationRequest`]. The response is a stream of
+//! length-delimited messages carrying the data of the requested blocks, ending with [`Fin`]. The
+//! node writes the messages of its responses as their blocks are read, see [`CodecResponse`].
+
+use std::io;
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::request_response::{Codec, ProtocolName};
+use prost::Message;
+use tokio::sync::mpsc;
+
+use crate::error::Error;
+use crate::proto::state_diffs_response::StateDiffMessage;
+use crate::proto::{
+    block_headers_response, classes_response, events_response, receipts_response, transactions_response,
+    BlockHeadersResponse, Class, ClassesResponse, Event, EventsResponse, Fin, Iteration, IterationRequest, Receipt,
+    ReceiptsResponse, SignedBlockHeader, StateDiffsResponse, Transaction, TransactionsResponse,
+};
+
+/// Maximum size of an encoded request.
+pub const MAX_REQUEST_SIZE: usize = 1024;
+/// Maximum size of an encoded response message. Sierra programs make up the largest ones.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Maximum size of all the messages of a response.
+pub const MAX_RESPONSE_SIZE: usize = 128 * 1024 * 1024;
+
+/// The Starknet sync protocols, one for each kind of block data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Headers,
+    Transactions,
+    Receipts,
+    Events,
+    StateDiffs,
+    Classes,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 6] = [
+        Protocol::Headers,
+        Protocol::Transactions,
+        Protocol::Receipts,
+        Protocol::Events,
+        Protocol::StateDiffs,
+        Protocol::Classes,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Headers => "/starknet/headers/0.1.0-rc.0",
+            Protocol::Transactions => "/starknet/transactions/0.1.0-rc.0",
+            Protocol::Receipts => "/starknet/receipts/0.1.0-rc.0",
+            Protocol::Events => "/starknet/events/0.1.0-rc.0",
+            Protocol::StateDiffs => "/starknet/state_diffs/0.1.0-rc.0",
+            Protocol::Classes => "/starknet/classes/0.1.0-rc.0",
+        }
+    }
+}
+
+impl ProtocolName for Protocol {
+    fn protocol_name(&self) -> &[u8] {
+        self.name().as_bytes()
+    }
+}
+
+/// A request for the data of the blocks of `iteration`, on one of the sync protocols.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub protocol: Protocol,
+    pub iteration: Iteration,
+}
+
+/// The data of the requested blocks, in the order of the blocks and without the final [`Fin`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    Headers(Vec<SignedBlockHeader>),
+    Transactions(Vec<Transaction>),
+    Receipts(Vec<Receipt>),
+    Events(Vec<Event>),
+    StateDiffs(Vec<StateDiffMessage>),
+    Classes(Vec<Class>),
+}
+
+impl Response {
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            Response::Headers(_) => Protocol::Headers,
+            Response::Transactions(_) => Protocol::Transactions,
+            Response::Receipts(_) => Protocol::Receipts,
+            Response::Events(_) => Protocol::Events,
+            Response::StateDiffs(_) => Protocol::StateDiffs,
+            Response::Classes(_) => Protocol::Classes,
+        }
+    }
+}
+
+/// The data of the blocks of a response served by the node, one block at a time, or the error
+/// which stopped serving it.
+pub type ServedBlocks = mpsc::Receiver<Result<Response, Error>>;
+
+/// A response as exchanged by [`StarknetCodec`].
+#[derive(Debug)]
+pub enum CodecResponse {
+    /// A response read or written at once, as the responses of the peers are read.
+    Whole(Response),
+    /// A response of the node, whose messages are written as its blocks are read, so that only a
+    /// few of them are held in memory.
+    Streamed(ServedBlocks),
+}
+
+/// [`Codec`] of the sync protocols.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StarknetCodec;
+
+#[async_trait]
+impl Codec for StarknetCodec {
+    type Protocol = Protocol;
+    type Request = Request;
+    type Response = CodecResponse;
+
+    async fn read_request<T>(&mut self, protocol: &Protocol, io: &mut T) -> io::Result<Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let request: IterationRequest =
+            read_message(io, MAX_REQUEST_SIZE).await?.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let iteration = request.iteration.ok_or_else(|| invalid_data("Request without iteration"))?;
+
+        Ok(Request { protocol: *protocol, iteration })
+    }
+
+    async fn read_response<T>(&mut self, protocol: &Protocol, io: &mut T) -> io::Result<CodecResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(CodecResponse::Whole(match protocol {
+            Protocol::Headers => Response::Headers(read_stream::<BlockHeadersResponse, _>(io).await?),
+            Protocol::Transactions => Response::Transactions(read_stream::<TransactionsResponse, _>(io).await?),
+            Protocol::Receipts => Response::Receipts(read_stream::<ReceiptsResponse, _>(io).await?),
+            Protocol::Events => Response::Events(read_stream::<EventsResponse, _>(io).await?),
+            Protocol::StateDiffs => Response::StateDiffs(read_stream::<StateDiffsResponse, _>(io).await?),
+            Protocol::Classes => Response::Classes(read_stream::<ClassesResponse, _>(io).await?),
+        }))
+    }
+
+    async fn write_request<T>(&mut self, _protocol: &Protocol, io: &mut T, request: Request) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_message(io, &IterationRequest { iteration: Some(request.iteration) }).await
+    }
+
+    /// Write the messages of `response` then [`Fin`]. A streamed response stopped by an error is
+    /// not ended with [`Fin`], for the peer not to take it as complete.
+    async fn write_response<T>(&mut self, protocol: &Protocol, io: &mut T, response: CodecResponse) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        match response {
+            CodecResponse::Whole(response) => write_messages(io, response).await?,
+            CodecResponse::Streamed(mut blocks) => {
+                while let Some(block) = blocks.recv().await {
+                    let block = block.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                    write_messages(io, block).await?;
+                }
+            }
+        }
+        write_fin(io, protocol).await
+    }
+}
+
+/// A message of a response stream, carrying either an item or the final [`Fin`].
+trait StreamMessage: Message + Default {
+    type Item;
+
+    fn item(item: Self::Item) -> Self;
+
+    fn fin() -> Self;
+
+    /// The item carried by the message, `None` for [`Fin`].
+    fn into_item(self) -> io::Result<Option<Self::Item>>;
+}
+
+macro_rules! stream_message {
+    ($message:ty, $field:ident, $module:ident:: $oneof:ident, $variant:ident, $item:ty) => {
+        impl StreamMessage for $message {
+            type Item = $item;
+
+            fn item(item: $item) -> Self {
+                Self { $field: Some($module::$oneof::$variant(item)) }
+            }
+
+            fn fin() -> Self {
+                Self { $field: Some($module::$oneof::Fin(Fin {})) }
+            }
+
+            fn into_item(self) -> io::Result<Option<$item>> {
+                match self.$field {
+                    Some($module::$oneof::$variant(item)) => Ok(Some(item)),
+                    Some($module::$oneof::Fin(_)) => Ok(None),
+                    None => Err(invalid_data("Empty response message")),
+                }
+            }
+        }
+    };
+}
+
+stream_message!(BlockHeadersResponse, header_message, block_headers_response::HeaderMessage, Header, SignedBlockHeader);
+stream_message!(
+    TransactionsResponse,
+    transaction_message,
+    transactions_response::TransactionMessage,
+    Transaction,
+    Transaction
+);
+stream_message!(ReceiptsResponse, receipt_message, receipts_response::ReceiptMessage, Receipt, Receipt);
+stream_message!(EventsResponse, event_message, events_response::EventMessage, Event, Event);
+stream_message!(ClassesResponse, class_message, classes_response::ClassMessage, Class, Class);
+
+/// Contract diffs and declared classes share the stream of state diffs.
+impl StreamMessage for StateDiffsResponse {
+    type Item = StateDiffMessage;
+
+    fn item(item: StateDiffMessage) -> Self {
+        Self { state_diff_message: Some(item) }
+    }
+
+    fn fin() -> Self {
+        Self { state_diff_message: Some(StateDiffMessage::Fin(Fin {})) }
+    }
+
+    fn into_item(self) -> io::Result<Option<StateDiffMessage>> {
+        match self.state_diff_message {
+            Some(StateDiffMessage::Fin(_)) => Ok(None),
+            Some(item) => Ok(Some(item)),
+            None => Err(invalid_data("Empty response message")),
+        }
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Read the varint length prefix of a message, `None` if the stream ended before it.
+async fn read_length<T: AsyncRead + Unpin + Send>(io: &mut T) -> io::Result<Option<usize>> {
+    let mut buffer = [0u8; 10];
+    for position in 0..buffer.len() {
+        if io.read(&mut buffer[position..=position]).await? == 0 {
+            return if position == 0 { Ok(None) } else { Err(io::ErrorKind::UnexpectedEof.into()) };
+        }
+        if buffer[position] & 0x80 == 0 {
+            return prost::decode_length_delimiter(&buffer[..=position]).map(Some).map_err(invalid_data);
+        }
+    }
+    Err(invalid_data("Invalid length prefix"))
+}
+
+async fn read_message<M, T>(io: &mut T, max_size: usize) -> io::Result<Option<M>>
+where
+    M: Message + Default,
+    T: AsyncRead + Unpin + Send,
+{
+    let Some(length) = read_length(io).await? else {
+        return Ok(None);
+    };
+    if length > max_size {
+        return Err(invalid_data(format!("Message of {length} bytes, the maximum is {max_size}")));
+    }
+    let mut buffer = vec![0u8; length];
+    io.read_exact(&mut buffer).await?;
+
+    M::decode(&buffer[..]).map(Some).map_err(invalid_data)
+}
+
+async fn write_message<M, T>(io: &mut T, message: &M) -> io::Result<()>
+where
+    M: Message,
+    T: AsyncWrite + Unpin + Send,
+{
+    io.write_all(&message.encode_length_delimited_to_vec()).await
+}
+
+async fn read_stream<M, T>(io: &mut T) -> io::Result<Vec<M::Item>>
+where
+    M: StreamMessage,
+    T: AsyncRead + Unpin + Send,
+{
+    let mut items = Vec::new();
+    let mut size = 0;
+    loop {
+        let message: M = read_message(io, MAX_MESSAGE_SIZE)
+            .await?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Response ended without Fin"))?;
+        size += message.encoded_len();
+        if size > MAX_RESPONSE_SIZE {
+            return Err(invalid_data(format!("Response over {MAX_RESPONSE_SIZE} bytes")));
+        }
+        match message.into_item()? {
+            Some(item) => items.push(item),
+            None => return Ok(items),
+        }
+    }
+}
+
+async fn write_items<M, T>(io: &mut T, items: Vec<M::Item>) -> io::Result<()>
+where
+    M: StreamMessage,
+    T: AsyncWrite + Unpin + Send,
+{
+    for item in items {
+        write_message(io, &M::item(item)).await?;
+    }
+    Ok(())
+}
+
+/// Write the messages of `response`, without the final [`Fin`].
+async fn write_messages<T: AsyncWrite + Unpin + Send>(io: &mut T, response: Response) -> io::Result<()> {
+    match response {
+        Response::Headers(items) => write_items::<BlockHeadersResponse, _>(io, items).await,
+        Response::Transactions(items) => write_items::<TransactionsResponse, _>(io, items).await,
+        Response::Receipts(items) => write_items::<ReceiptsResponse, _>(io, items).await,
+        Response::Events(items) => write_items::<EventsResponse, _>(io, items).await,
+        Response::StateDiffs(items) => write_items::<StateDiffsResponse, _>(io, items).await,
+        Response::Classes(items) => write_items::<ClassesResponse, _>(io, items).await,
+    }
+}
+
+async fn write_fin<T: AsyncWrite + Unpin + Send>(io: &mut T, protocol: &Protocol) -> io::Result<()> {
+    match protocol {
+        Protocol::Headers => write_message(io, &BlockHeadersResponse::fin()).await,
+        Protocol::Transactions => write_message(io, &TransactionsResponse::fin()).await,
+        Protocol::Receipts => write_message(io, &ReceiptsResponse::fin()).await,
+        Protocol::Events => write_message(io, &EventsResponse::fin()).await,
+        Protocol::StateDiffs => write_message(io, &StateDiffsResponse::fin()).await,
+        Protocol::Classes => write_message(io, &ClassesResponse::fin()).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::io::Cursor;
+
+    use super::*;
+    use crate::proto::{iteration, ContractDiff, Hash};
+
+    fn iteration() -> Iteration {
+        Iteration {
+            start: Some(iteration::Start::BlockNumber(3)),
+            direction: iteration::Direction::Backward as i32,
+            limit: 2,
+            step: 1,
+        }
+    }
+
+    async fn read_whole(protocol: Protocol, buffer: &mut Cursor<Vec<u8>>) -> io::Result<Response> {
+        buffer.set_position(0);
+        match StarknetCodec.read_response(&protocol, buffer).await? {
+            CodecResponse::Whole(response) => Ok(response),
+            CodecResponse::Streamed(_) => panic!("expected a whole response"),
+        }
+    }
+
+    async fn roundtrip(response: Response) -> Response {
+        let protocol = response.protocol();
+        let mut buffer = Cursor::new(Vec::new());
+        StarknetCodec.write_response(&protocol, &mut buffer, CodecResponse::Whole(response)).await.unwrap();
+        read_whole(protocol, &mut buffer).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_roundtrip() {
+        let request = Request { protocol: Protocol::Events, iteration: iteration() };
+        let mut buffer = Cursor::new(Vec::new());
+        StarknetCodec.write_request(&Protocol::Events, &mut buffer, request.clone()).await.unwrap();
+        buffer.set_position(0);
+
+        assert_eq!(StarknetCodec.read_request(&Protocol::Events, &mut buffer).await.unwrap(), request);
+    }
+
+    #[tokio::test]
+    async fn responses_roundtrip() {
+        let header = SignedBlockHeader {
+            block_hash: Some(Hash { elements: vec![1; 32] }),
+            number: 3,
+            protocol_version: "0.13.2".into(),
+            ..Default::default()
+        };
+        let headers = Response::Headers(vec![header.clone(), SignedBlockHeader { number: 2, ..header }]);
+        assert_eq!(roundtrip(headers.clone()).await, headers);
+
+        let state_diffs = Response::StateDiffs(vec![StateDiffMessage::ContractDiff(ContractDiff::default())]);
+        assert_eq!(roundtrip(state_diffs.clone()).await, state_diffs);
+
+        assert_eq!(roundtrip(Response::Classes(vec![])).await, Response::Classes(vec![]));
+    }
+
+    #[tokio::test]
+    async fn streamed_responses_are_read_whole() {
+        let (sender, blocks) = mpsc::channel(2);
+        let event = |block_number: u8| Event {
+            transaction_hash: Some(Hash { elements: vec![block_number; 32] }),
+            ..Default::default()
+        };
+        sender.send(Ok(Response::Events(vec![event(1), event(1)]))).await.unwrap();
+        sender.send(Ok(Response::Events(vec![event(2)]))).await.unwrap();
+        drop(sender);
+
+        let mut buffer = Cursor::new(Vec::new());
+        StarknetCodec.write_response(&Protocol::Events, &mut buffer, CodecResponse::Streamed(blocks)).await.unwrap();
+        assert_eq!(
+            read_whole(Protocol::Events, &mut buffer).await.unwrap(),
+            Response::Events(vec![event(1), event(1), event(2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn streamed_responses_stopped_by_an_error_do_not_end_with_fin() {
+        let (sender, blocks) = mpsc::channel(2);
+        sender.send(Ok(Response::Headers(vec![SignedBlockHeader::default()]))).await.unwrap();
+        sender.send(Err(Error::ServiceStopped)).await.unwrap();
+        drop(sender);
+
+        let mut buffer = Cursor::new(Vec::new());
+        let result =
+            StarknetCodec.write_response(&Protocol::Headers, &mut buffer, CodecResponse::Streamed(blocks)).await;
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Other);
+        assert_eq!(read_whole(Protocol::Headers, &mut buffer).await.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn responses_must_end_with_fin() {
+        let mut buffer = Cursor::new(Vec::new());
+        write_message(&mut buffer, &BlockHeadersResponse::item(SignedBlockHeader::default())).await.unwrap();
+
+        let error = read_whole(Protocol::Headers, &mut buffer).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn oversized_requests_are_rejected() {
+        let mut prefix = Vec::new();
+        prost::encode_length_delimiter(MAX_REQUEST_SIZE + 1, &mut prefix).unwrap();
+        let mut buffer = Cursor::new(prefix);
+
+        let error = StarknetCodec.read_request(&Protocol::Headers, &mut buffer).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
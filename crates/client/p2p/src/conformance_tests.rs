@@ -0,0 +1,259 @@
+//! Round trips the messages of `fixtures/`, encoded from the field numbers of the Starknet p2p
+//! specification independently of the [`crate::proto`] definitions.
+//!
+//! Each `<name>.bin` fixture is the encoding of the text format message of `<name>.txtpb`. They can
+//! be encoded again from the specification with
+//! `protoc --encode=<proto-message> p2p/proto/<proto-file> < <name>.txtpb > <name>.bin`.
+
+use std::fmt::Debug;
+
+use prost::Message;
+
+use crate::proto::receipt::execution_resources::BuiltinCounter;
+use crate::proto::receipt::{Common, ExecutionResources};
+use crate::proto::transaction::{InvokeV3, L1HandlerV0, Txn};
+use crate::proto::*;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_bytes!(concat!("../fixtures/", $name, ".bin")).as_slice()
+    };
+}
+
+/// Decode `fixture` into `expected`, and encode it back to the same bytes.
+fn assert_round_trips<M: Message + Default + PartialEq + Debug>(fixture: &[u8], expected: M) {
+    assert_eq!(M::decode(fixture).unwrap(), expected);
+    assert_eq!(expected.encode_to_vec(), fixture);
+}
+
+fn be_bytes(value: u64, len: usize) -> Vec<u8> {
+    [vec![0; len - 8], value.to_be_bytes().to_vec()].concat()
+}
+
+fn felt(value: u64) -> Felt252 {
+    Felt252 { elements: be_bytes(value, 32) }
+}
+
+fn hash(value: u64) -> Hash {
+    Hash { elements: be_bytes(value, 32) }
+}
+
+fn address(value: u64) -> Address {
+    Address { elements: be_bytes(value, 32) }
+}
+
+#[test]
+fn block_headers_requests_conform_to_the_specification() {
+    assert_round_trips(
+        fixture!("block_headers_request"),
+        IterationRequest {
+            iteration: Some(Iteration {
+                start: Some(iteration::Start::BlockNumber(5)),
+                direction: iteration::Direction::Backward as i32,
+                limit: 10,
+                step: 2,
+            }),
+        },
+    );
+    assert_round_trips(
+        fixture!("block_headers_request_by_hash"),
+        IterationRequest {
+            iteration: Some(Iteration {
+                start: Some(iteration::Start::Header(hash(0x11))),
+                direction: iteration::Direction::Forward as i32,
+                limit: 1,
+                step: 0,
+            }),
+        },
+    );
+}
+
+#[test]
+fn block_headers_responses_conform_to_the_specification() {
+    let header = SignedBlockHeader {
+        block_hash: Some(hash(0x105)),
+        parent_hash: Some(hash(0x104)),
+        number: 5,
+        time: 1_700_000_000,
+        sequencer_address: Some(address(0x5e)),
+        state_root: Some(hash(0x5700)),
+        state_diff_commitment: Some(StateDiffCommitment { state_diff_length: 3, root: Some(hash(0x5d)) }),
+        transactions: Some(Patricia { n_leaves: 2, root: Some(hash(0x7a)) }),
+        events: Some(Patricia { n_leaves: 1, root: Some(hash(0xe7)) }),
+        receipts: Some(hash(0x7e)),
+        protocol_version: "0.13.2".into(),
+        gas_price_fri: Some(Uint128 { low: 100, high: 0 }),
+        gas_price_wei: Some(Uint128 { low: 1, high: 2 }),
+        data_gas_price_fri: Some(Uint128 { low: 3, high: 0 }),
+        data_gas_price_wei: Some(Uint128 { low: 4, high: 0 }),
+        l1_data_availability_mode: L1DataAvailabilityMode::Blob as i32,
+        signatures: vec![ConsensusSignature { r: Some(felt(0xa)), s: Some(felt(0xb)) }],
+    };
+    assert_round_trips(
+        fixture!("block_headers_response"),
+        BlockHeadersResponse { header_message: Some(block_headers_response::HeaderMessage::Header(header)) },
+    );
+    assert_round_trips(
+        fixture!("block_headers_fin"),
+        BlockHeadersResponse { header_message: Some(block_headers_response::HeaderMessage::Fin(Fin {})) },
+    );
+}
+
+#[test]
+fn transactions_responses_conform_to_the_specification() {
+    let invoke = InvokeV3 {
+        sender: Some(address(0x5e0)),
+        signature: Some(AccountSignature { parts: vec![felt(0x51), felt(0x52)] }),
+        calldata: vec![felt(0xca1), felt(0xca2)],
+        resource_bounds: Some(ResourceBounds {
+            l1_gas: Some(ResourceLimits { max_amount: Some(felt(0x100)), max_price_per_unit: Some(felt(0x200)) }),
+            l2_gas: Some(ResourceLimits { max_amount: Some(felt(0)), max_price_per_unit: Some(felt(0)) }),
+        }),
+        tip: 7,
+        paymaster_data: vec![felt(0x9a)],
+        account_deployment_data: vec![felt(0xad)],
+        nonce_data_availability_mode: VolitionDomain::L1 as i32,
+        fee_data_availability_mode: VolitionDomain::L2 as i32,
+        nonce: Some(felt(3)),
+    };
+    assert_round_trips(
+        fixture!("transactions_response_invoke_v3"),
+        TransactionsResponse {
+            transaction_message: Some(transactions_response::TransactionMessage::Transaction(Transaction {
+                transaction_hash: Some(hash(0x7a5)),
+                txn: Some(Txn::InvokeV3(invoke)),
+            })),
+        },
+    );
+
+    let l1_handler = L1HandlerV0 {
+        nonce: Some(felt(1)),
+        address: Some(address(0xc0)),
+        entry_point_selector: Some(felt(0x5e1)),
+        calldata: vec![felt(0xe7)],
+    };
+    assert_round_trips(
+        fixture!("transactions_response_l1_handler"),
+        TransactionsResponse {
+            transaction_message: Some(transactions_response::TransactionMessage::Transaction(Transaction {
+                transaction_hash: Some(hash(0x7a6)),
+                txn: Some(Txn::L1Handler(l1_handler)),
+            })),
+        },
+    );
+}
+
+#[test]
+fn receipts_responses_conform_to_the_specification() {
+    let common = Common {
+        actual_fee: Some(felt(0xfee)),
+        price_unit: PriceUnit::Fri as i32,
+        messages_sent: vec![MessageToL1 {
+            from_address: Some(felt(0xc0)),
+            payload: vec![felt(1), felt(2)],
+            to_address: Some(EthereumAddress { elements: be_bytes(0xe7, 20) }),
+        }],
+        execution_resources: Some(ExecutionResources {
+            builtins: Some(BuiltinCounter { pedersen: 2, range_check: 4, ..Default::default() }),
+            steps: 100,
+            memory_holes: 3,
+            l1_gas: Some(felt(0x1a)),
+            l1_data_gas: Some(felt(0x1b)),
+        }),
+        revert_reason: Some("reverted".into()),
+    };
+    assert_round_trips(
+        fixture!("receipts_response"),
+        ReceiptsResponse {
+            receipt_message: Some(receipts_response::ReceiptMessage::Receipt(Receipt {
+                r#type: Some(receipt::Type::Invoke(receipt::Invoke { common: Some(common) })),
+            })),
+        },
+    );
+
+    let common = Common {
+        actual_fee: Some(felt(0)),
+        price_unit: PriceUnit::Wei as i32,
+        messages_sent: Vec::new(),
+        execution_resources: Some(ExecutionResources { steps: 10, ..Default::default() }),
+        revert_reason: None,
+    };
+    assert_round_trips(
+        fixture!("receipts_response_l1_handler"),
+        ReceiptsResponse {
+            receipt_message: Some(receipts_response::ReceiptMessage::Receipt(Receipt {
+                r#type: Some(receipt::Type::L1Handler(receipt::L1Handler {
+                    common: Some(common),
+                    msg_hash: Some(hash(0x3a5)),
+                })),
+            })),
+        },
+    );
+}
+
+#[test]
+fn events_responses_conform_to_the_specification() {
+    assert_round_trips(
+        fixture!("events_response"),
+        EventsResponse {
+            event_message: Some(events_response::EventMessage::Event(Event {
+                transaction_hash: Some(hash(0x7a5)),
+                from_address: Some(felt(0xc0)),
+                keys: vec![felt(0x4e)],
+                data: vec![felt(1), felt(2)],
+            })),
+        },
+    );
+}
+
+#[test]
+fn state_diffs_responses_conform_to_the_specification() {
+    let contract_diff = ContractDiff {
+        address: Some(address(0xc0)),
+        nonce: Some(felt(2)),
+        class_hash: Some(hash(0xc1a)),
+        values: vec![
+            ContractStoredValue { key: Some(felt(1)), value: Some(felt(0x10)) },
+            ContractStoredValue { key: Some(felt(2)), value: Some(felt(0x20)) },
+        ],
+        domain: VolitionDomain::L2 as i32,
+    };
+    assert_round_trips(
+        fixture!("state_diffs_response_contract_diff"),
+        StateDiffsResponse {
+            state_diff_message: Some(state_diffs_response::StateDiffMessage::ContractDiff(contract_diff)),
+        },
+    );
+
+    let declared_class = DeclaredClass { class_hash: Some(hash(0xc1a)), compiled_class_hash: Some(hash(0xca5)) };
+    assert_round_trips(
+        fixture!("state_diffs_response_declared_class"),
+        StateDiffsResponse {
+            state_diff_message: Some(state_diffs_response::StateDiffMessage::DeclaredClass(declared_class)),
+        },
+    );
+}
+
+#[test]
+fn classes_responses_conform_to_the_specification() {
+    let cairo1_class = Cairo1Class {
+        abi: "[]".into(),
+        entry_points: Some(Cairo1EntryPoints {
+            externals: vec![SierraEntryPoint { index: 0, selector: Some(felt(0x5e1)) }],
+            l1_handlers: Vec::new(),
+            constructors: vec![SierraEntryPoint { index: 1, selector: Some(felt(0x5e2)) }],
+        }),
+        program: vec![felt(1), felt(0x2a)],
+        contract_class_version: "0.1.0".into(),
+    };
+    assert_round_trips(
+        fixture!("classes_response"),
+        ClassesResponse {
+            class_message: Some(classes_response::ClassMessage::Class(Class {
+                domain: 0,
+                class_hash: Some(hash(0xc1a)),
+                class: Some(class::Class::Cairo1(cairo1_class)),
+            })),
+        },
+    );
+}
@@ -0,0 +1,407 @@
+//! Conversions of the Madara block data to the messages of the sync protocols.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use mc_db::{FeeUnit, StoredTransactionReceipt, TransactionReceiptKind};
+use mp_block::commitments::BlockStateDiff;
+use mp_block::{L1DataAvailabilityMode, PROTOCOL_VERSION_0_13_2};
+use mp_felt::Felt252Wrapper;
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_api::state::EntryPointType;
+use starknet_core::types::{
+    CompressedLegacyContractClass, DataAvailabilityMode, LegacyContractEntryPoint, ResourceBounds,
+    ResourceBoundsMapping, Transaction as CoreTransaction,
+};
+
+use crate::proto::{self, receipt, transaction};
+use crate::source::StateDiff;
+
+/// Version of the Sierra classes declared so far.
+const SIERRA_CONTRACT_CLASS_VERSION: &str = "0.1.0";
+
+pub fn felt(value: impl Into<Felt252Wrapper>) -> proto::Felt252 {
+    proto::Felt252 { elements: <[u8; 32]>::from(value.into()).to_vec() }
+}
+
+pub fn hash(value: impl Into<Felt252Wrapper>) -> proto::Hash {
+    proto::Hash { elements: <[u8; 32]>::from(value.into()).to_vec() }
+}
+
+pub fn address(value: impl Into<Felt252Wrapper>) -> proto::Address {
+    proto::Address { elements: <[u8; 32]>::from(value.into()).to_vec() }
+}
+
+pub fn uint128(value: u128) -> proto::Uint128 {
+    proto::Uint128 { low: value as u64, high: (value >> 64) as u64 }
+}
+
+fn felts<T: Into<Felt252Wrapper>>(values: impl IntoIterator<Item = T>) -> Vec<proto::Felt252> {
+    values.into_iter().map(felt).collect()
+}
+
+fn signature(parts: Vec<starknet_ff::FieldElement>) -> proto::AccountSignature {
+    proto::AccountSignature { parts: felts(parts) }
+}
+
+/// The Starknet version of the blocks of a Madara protocol version.
+pub fn protocol_version(protocol_version: u8) -> String {
+    if protocol_version >= PROTOCOL_VERSION_0_13_2 { "0.13.2".into() } else { "0.13.1".into() }
+}
+
+/// The header of a block, without signatures as Madara does not sign its blocks.
+pub fn header(header: &mp_block::Header) -> proto::SignedBlockHeader {
+    let gas_prices = &header.l1_gas_price;
+    let l1_data_availability_mode = match header.l1_da_mode {
+        L1DataAvailabilityMode::Calldata => proto::L1DataAvailabilityMode::Calldata,
+        L1DataAvailabilityMode::Blob => proto::L1DataAvailabilityMode::Blob,
+    };
+
+    proto::SignedBlockHeader {
+        block_hash: Some(hash(header.hash())),
+        parent_hash: Some(hash(header.parent_block_hash)),
+        number: header.block_number,
+        time: header.block_timestamp,
+        sequencer_address: Some(address(header.sequencer_address)),
+        state_root: Some(hash(header.global_state_root)),
+        state_diff_commitment: Some(proto::StateDiffCommitment {
+            state_diff_length: header.state_diff_length,
+            root: Some(hash(header.state_diff_commitment)),
+        }),
+        transactions: Some(proto::Patricia {
+            n_leaves: header.transaction_count as u64,
+            root: Some(hash(header.transaction_commitment)),
+        }),
+        events: Some(proto::Patricia {
+            n_leaves: header.event_count as u64,
+            root: Some(hash(header.event_commitment)),
+        }),
+        receipts: Some(hash(header.receipt_commitment)),
+        protocol_version: protocol_version(header.protocol_version),
+        gas_price_fri: Some(uint128(gas_prices.strk_l1_gas_price.get())),
+        gas_price_wei: Some(uint128(gas_prices.eth_l1_gas_price.get())),
+        data_gas_price_fri: Some(uint128(gas_prices.strk_l1_data_gas_price.get())),
+        data_gas_price_wei: Some(uint128(gas_prices.eth_l1_data_gas_price.get())),
+        l1_data_availability_mode: l1_data_availability_mode as i32,
+        signatures: Vec::new(),
+    }
+}
+
+fn volition_domain(mode: DataAvailabilityMode) -> i32 {
+    match mode {
+        DataAvailabilityMode::L1 => proto::VolitionDomain::L1 as i32,
+        DataAvailabilityMode::L2 => proto::VolitionDomain::L2 as i32,
+    }
+}
+
+fn resource_bounds(resource_bounds: ResourceBoundsMapping) -> proto::ResourceBounds {
+    let limits = |bounds: ResourceBounds| proto::ResourceLimits {
+        max_amount: Some(felt(bounds.max_amount)),
+        max_price_per_unit: Some(felt(bounds.max_price_per_unit)),
+    };
+    proto::ResourceBounds { l1_gas: Some(limits(resource_bounds.l1_gas)), l2_gas: Some(limits(resource_bounds.l2_gas)) }
+}
+
+pub fn transaction(tx: CoreTransaction) -> proto::Transaction {
+    use starknet_core::types::{DeclareTransaction, DeployAccountTransaction, InvokeTransaction};
+    use transaction::Txn;
+
+    let (transaction_hash, txn) = match tx {
+        CoreTransaction::Declare(DeclareTransaction::V0(tx)) => (
+            tx.transaction_hash,
+            Txn::DeclareV0(transaction::DeclareV0 {
+                sender: Some(address(tx.sender_address)),
+                max_fee: Some(felt(tx.max_fee)),
+                signature: Some(signature(tx.signature)),
+                class_hash: Some(hash(tx.class_hash)),
+            }),
+        ),
+        CoreTransaction::Declare(DeclareTransaction::V1(tx)) => (
+            tx.transaction_hash,
+            Txn::DeclareV1(transaction::DeclareV1 {
+                sender: Some(address(tx.sender_address)),
+                max_fee: Some(felt(tx.max_fee)),
+                signature: Some(signature(tx.signature)),
+                class_hash: Some(hash(tx.class_hash)),
+                nonce: Some(felt(tx.nonce)),
+            }),
+        ),
+        CoreTransaction::Declare(DeclareTransaction::V2(tx)) => (
+            tx.transaction_hash,
+            Txn::DeclareV2(transaction::DeclareV2 {
+                sender: Some(address(tx.sender_address)),
+                max_fee: Some(felt(tx.max_fee)),
+                signature: Some(signature(tx.signature)),
+                class_hash: Some(hash(tx.class_hash)),
+                nonce: Some(felt(tx.nonce)),
+                compiled_class_hash: Some(hash(tx.compiled_class_hash)),
+            }),
+        ),
+        CoreTransaction::Declare(DeclareTransaction::V3(tx)) => (
+            tx.transaction_hash,
+            Txn::DeclareV3(transaction::DeclareV3 {
+                sender: Some(address(tx.sender_address)),
+                signature: Some(signature(tx.signature)),
+                class_hash: Some(hash(tx.class_hash)),
+                nonce: Some(felt(tx.nonce)),
+                compiled_class_hash: Some(hash(tx.compiled_class_hash)),
+                resource_bounds: Some(resource_bounds(tx.resource_bounds)),
+                tip: tx.tip,
+                paymaster_data: felts(tx.paymaster_data),
+                account_deployment_data: felts(tx.account_deployment_data),
+                nonce_data_availability_mode: volition_domain(tx.nonce_data_availability_mode),
+                fee_data_availability_mode: volition_domain(tx.fee_data_availability_mode),
+            }),
+        ),
+        CoreTransaction::Deploy(tx) => (
+            tx.transaction_hash,
+            Txn::Deploy(transaction::Deploy {
+                class_hash: Some(hash(tx.class_hash)),
+                address_salt: Some(felt(tx.contract_address_salt)),
+                calldata: felts(tx.constructor_calldata),
+                // Legacy deploy transactions only existed in version 0
+                version: 0,
+            }),
+        ),
+        CoreTransaction::DeployAccount(DeployAccountTransaction::V1(tx)) => (
+            tx.transaction_hash,
+            Txn::DeployAccountV1(transaction::DeployAccountV1 {
+                max_fee: Some(felt(tx.max_fee)),
+                signature: Some(signature(tx.signature)),
+                class_hash: Some(hash(tx.class_hash)),
+                nonce: Some(felt(tx.nonce)),
+                address_salt: Some(felt(tx.contract_address_salt)),
+                calldata: felts(tx.constructor_calldata),
+            }),
+        ),
+        CoreTransaction::DeployAccount(DeployAccountTransaction::V3(tx)) => (
+            tx.transaction_hash,
+            Txn::DeployAccountV3(transaction::DeployAccountV3 {
+                signature: Some(signature(tx.signature)),
+                class_hash: Some(hash(tx.class_hash)),
+                nonce: Some(felt(tx.nonce)),
+                address_salt: Some(felt(tx.contract_address_salt)),
+                calldata: felts(tx.constructor_calldata),
+                resource_bounds: Some(resource_bounds(tx.resource_bounds)),
+                tip: tx.tip,
+                paymaster_data: felts(tx.paymaster_data),
+                nonce_data_availability_mode: volition_domain(tx.nonce_data_availability_mode),
+                fee_data_availability_mode: volition_domain(tx.fee_data_availability_mode),
+            }),
+        ),
+        CoreTransaction::Invoke(InvokeTransaction::V0(tx)) => (
+            tx.transaction_hash,
+            Txn::InvokeV0(transaction::InvokeV0 {
+                max_fee: Some(felt(tx.max_fee)),
+                signature: Some(signature(tx.signature)),
+                address: Some(address(tx.contract_address)),
+                entry_point_selector: Some(felt(tx.entry_point_selector)),
+                calldata: felts(tx.calldata),
+            }),
+        ),
+        CoreTransaction::Invoke(InvokeTransaction::V1(tx)) => (
+            tx.transaction_hash,
+            Txn::InvokeV1(transaction::InvokeV1 {
+                sender: Some(address(tx.sender_address)),
+                max_fee: Some(felt(tx.max_fee)),
+                signature: Some(signature(tx.signature)),
+                calldata: felts(tx.calldata),
+                nonce: Some(felt(tx.nonce)),
+            }),
+        ),
+        CoreTransaction::Invoke(InvokeTransaction::V3(tx)) => (
+            tx.transaction_hash,
+            Txn::InvokeV3(transaction::InvokeV3 {
+                sender: Some(address(tx.sender_address)),
+                signature: Some(signature(tx.signature)),
+                calldata: felts(tx.calldata),
+                resource_bounds: Some(resource_bounds(tx.resource_bounds)),
+                tip: tx.tip,
+                paymaster_data: felts(tx.paymaster_data),
+                account_deployment_data: felts(tx.account_deployment_data),
+                nonce_data_availability_mode: volition_domain(tx.nonce_data_availability_mode),
+                fee_data_availability_mode: volition_domain(tx.fee_data_availability_mode),
+                nonce: Some(felt(tx.nonce)),
+            }),
+        ),
+        CoreTransaction::L1Handler(tx) => (
+            tx.transaction_hash,
+            Txn::L1Handler(transaction::L1HandlerV0 {
+                nonce: Some(felt(tx.nonce)),
+                address: Some(address(tx.contract_address)),
+                entry_point_selector: Some(felt(tx.entry_point_selector)),
+                calldata: felts(tx.calldata),
+            }),
+        ),
+    };
+
+    proto::Transaction { transaction_hash: Some(hash(transaction_hash)), txn: Some(txn) }
+}
+
+fn execution_resources(resources: &[(String, u64)]) -> receipt::ExecutionResources {
+    let resource = |name: &str| {
+        resources.iter().find(|(resource, _)| resource.eq_ignore_ascii_case(name)).map_or(0, |(_, value)| *value)
+    };
+    let count = |name: &str| u32::try_from(resource(name)).unwrap_or(u32::MAX);
+
+    receipt::ExecutionResources {
+        builtins: Some(receipt::execution_resources::BuiltinCounter {
+            bitwise: count("bitwise_builtin"),
+            ecdsa: count("ecdsa_builtin"),
+            ec_op: count("ec_op_builtin"),
+            pedersen: count("pedersen_builtin"),
+            range_check: count("range_check_builtin"),
+            poseidon: count("poseidon_builtin"),
+            keccak: count("keccak_builtin"),
+            output: count("output_builtin"),
+        }),
+        steps: count("n_steps"),
+        memory_holes: count("memory_holes"),
+        l1_gas: Some(felt(resource(blockifier::abi::constants::L1_GAS_USAGE))),
+        l1_data_gas: Some(felt(resource(blockifier::abi::constants::BLOB_GAS_USAGE))),
+    }
+}
+
+pub fn receipt(receipt: &StoredTransactionReceipt) -> proto::Receipt {
+    let price_unit = match receipt.fee_unit {
+        FeeUnit::Wei => proto::PriceUnit::Wei,
+        FeeUnit::Fri => proto::PriceUnit::Fri,
+    };
+    let messages_sent = receipt
+        .messages_sent
+        .iter()
+        .map(|message| proto::MessageToL1 {
+            from_address: Some(felt(message.from_address)),
+            payload: felts(message.payload.0.iter().copied()),
+            to_address: Some(proto::EthereumAddress { elements: message.to_address.0.as_bytes().to_vec() }),
+        })
+        .collect();
+    let common = Some(receipt::Common {
+        actual_fee: Some(felt(receipt.actual_fee.0)),
+        price_unit: price_unit as i32,
+        messages_sent,
+        execution_resources: Some(execution_resources(&receipt.execution_resources)),
+        revert_reason: receipt.revert_reason.clone(),
+    });
+
+    let r#type = match &receipt.kind {
+        TransactionReceiptKind::Invoke => receipt::Type::Invoke(receipt::Invoke { common }),
+        TransactionReceiptKind::Declare => receipt::Type::Declare(receipt::Declare { common }),
+        TransactionReceiptKind::DeployAccount { contract_address } => {
+            receipt::Type::DeployAccount(receipt::DeployAccount {
+                common,
+                contract_address: Some(felt(*contract_address)),
+            })
+        }
+        TransactionReceiptKind::L1Handler { message_hash } => receipt::Type::L1Handler(receipt::L1Handler {
+            common,
+            msg_hash: Some(proto::Hash { elements: message_hash.to_vec() }),
+        }),
+    };
+
+    proto::Receipt { r#type: Some(r#type) }
+}
+
+/// The events emitted by the transaction of `receipt`.
+pub fn events(receipt: &StoredTransactionReceipt) -> impl Iterator<Item = proto::Event> + '_ {
+    receipt.events.iter().map(|event| proto::Event {
+        transaction_hash: Some(hash(receipt.transaction_hash)),
+        from_address: Some(felt(event.from_address)),
+        keys: felts(event.content.keys.iter().copied()),
+        data: felts(event.content.data.0.iter().copied()),
+    })
+}
+
+/// The state diff of a block, as stored by the mapping sync worker.
+pub fn state_diff(state_diff: BlockStateDiff) -> StateDiff {
+    let mut contract_diffs: BTreeMap<ContractAddress, proto::ContractDiff> = BTreeMap::new();
+    let mut contract_diff = |contract_address: ContractAddress| {
+        contract_diffs
+            .entry(contract_address)
+            .or_insert_with(|| proto::ContractDiff { address: Some(address(contract_address)), ..Default::default() })
+    };
+
+    for (contract_address, class_hash) in state_diff.deployed_contracts {
+        contract_diff(contract_address).class_hash = Some(hash(class_hash));
+    }
+    for (contract_address, nonce) in state_diff.nonces {
+        contract_diff(contract_address).nonce = Some(felt(nonce));
+    }
+    for (contract_address, storage_updates) in state_diff.storage_diffs {
+        contract_diff(contract_address).values.extend(
+            storage_updates
+                .into_iter()
+                .map(|(key, value)| proto::ContractStoredValue { key: Some(felt(key)), value: Some(felt(value)) }),
+        );
+    }
+
+    let deprecated_declared_classes = state_diff
+        .deprecated_declared_classes
+        .into_iter()
+        .map(|class_hash| proto::DeclaredClass { class_hash: Some(hash(class_hash)), compiled_class_hash: None });
+    let declared_classes =
+        state_diff.declared_classes.into_iter().map(|(class_hash, compiled_class_hash)| proto::DeclaredClass {
+            class_hash: Some(hash(class_hash)),
+            compiled_class_hash: Some(hash(compiled_class_hash)),
+        });
+
+    StateDiff {
+        contract_diffs: contract_diffs.into_values().collect(),
+        declared_classes: deprecated_declared_classes.chain(declared_classes).collect(),
+    }
+}
+
+pub fn cairo0_class(class_hash: ClassHash, class: CompressedLegacyContractClass) -> proto::Class {
+    let entry_points = |entry_points: Vec<LegacyContractEntryPoint>| {
+        entry_points
+            .into_iter()
+            .map(|entry_point| proto::EntryPoint {
+                selector: Some(felt(entry_point.selector)),
+                offset: entry_point.offset,
+            })
+            .collect()
+    };
+
+    proto::Class {
+        domain: 0,
+        class_hash: Some(hash(class_hash)),
+        class: Some(proto::class::Class::Cairo0(proto::Cairo0Class {
+            // The ABI of Cairo 0 classes is not kept by Madara
+            abi: String::new(),
+            externals: entry_points(class.entry_points_by_type.external),
+            l1_handlers: entry_points(class.entry_points_by_type.l1_handler),
+            constructors: entry_points(class.entry_points_by_type.constructor),
+            program: base64::engine::general_purpose::STANDARD.encode(class.program),
+        })),
+    }
+}
+
+pub fn cairo1_class(class_hash: ClassHash, class: starknet_api::state::ContractClass) -> proto::Class {
+    let entry_points = |entry_point_type: EntryPointType| {
+        class
+            .entry_points_by_type
+            .get(&entry_point_type)
+            .into_iter()
+            .flatten()
+            .map(|entry_point| proto::SierraEntryPoint {
+                index: entry_point.function_idx.0 as u64,
+                selector: Some(felt(entry_point.selector)),
+            })
+            .collect()
+    };
+
+    proto::Class {
+        domain: 0,
+        class_hash: Some(hash(class_hash)),
+        class: Some(proto::class::Class::Cairo1(proto::Cairo1Class {
+            entry_points: Some(proto::Cairo1EntryPoints {
+                externals: entry_points(EntryPointType::External),
+                l1_handlers: entry_points(EntryPointType::L1Handler),
+                constructors: entry_points(EntryPointType::Constructor),
+            }),
+            program: felts(class.sierra_program.iter().copied()),
+            abi: class.abi,
+            contract_class_version: SIERRA_CONTRACT_CLASS_VERSION.into(),
+        })),
+    }
+}
@@ -0,0 +1,20 @@
+use libp2p::PeerId;
+use mc_db::DbError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to start the p2p network: {0}")]
+    Network(String),
+    #[error("The p2p service has stopped")]
+    ServiceStopped,
+    #[error("Request to peer {peer} failed: {reason}")]
+    Request { peer: PeerId, reason: String },
+    #[error("Block {block_number} is missing {what}")]
+    IncompleteBlock { block_number: u64, what: String },
+    #[error("Failed to convert {what} of block {block_number}: {reason}")]
+    Conversion { block_number: u64, what: &'static str, reason: String },
+    #[error("Blockchain error: {0}")]
+    Blockchain(#[from] sp_blockchain::Error),
+    #[error("Madara DB error: {0}")]
+    Db(#[from] DbError),
+}
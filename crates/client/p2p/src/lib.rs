@@ -0,0 +1,29 @@
+//! Starknet p2p sync protocols, serving the blocks of a Madara node to other Starknet clients.
+//!
+//! The headers, transactions, receipts, events, state diffs and classes of the blocks are each
+//! served on their own libp2p request-response protocol, with the protobuf messages of the
+//! Starknet p2p specification. A request asks for the blocks of an [`proto::Iteration`], and is
+//! answered with a stream of messages ending with [`proto::Fin`], written as the blocks are read.
+//! The blocks are read from the node and the Madara DB, without executing them again, and only a
+//! bounded number of requests are served at the same time.
+//!
+//! Requests are sent to the peers with a [`NetworkHandle`], which the full nodes use to sync the
+//! chain from a peer, see `mc_sync::peer`. Block propagation between Madara nodes still goes
+//! through the Substrate network.
+
+pub mod client;
+pub mod codec;
+pub mod convert;
+pub mod error;
+mod network;
+pub mod proto;
+pub mod server;
+pub mod source;
+
+#[cfg(test)]
+mod conformance_tests;
+#[cfg(test)]
+mod tests;
+
+pub use libp2p::{Multiaddr, PeerId};
+pub use network::{load_or_generate_keypair, NetworkConfig, NetworkHandle, NetworkService};
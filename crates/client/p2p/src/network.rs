@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::transport::Boxed;
+use libp2p::core::upgrade::Version;
+use libp2p::identity::{ed25519, Keypair};
+use libp2p::request_response::{self, ProtocolSupport, RequestId, ResponseChannel};
+use libp2p::swarm::{NetworkBehaviour, SwarmBuilder, SwarmEvent};
+use libp2p::{noise, tcp, yamux, Multiaddr, PeerId, Swarm, Transport};
+use tokio::sync::{mpsc, oneshot, watch, Semaphore};
+
+use crate::codec::{CodecResponse, Protocol, Request, Response, StarknetCodec};
+use crate::error::Error;
+use crate::server::handle_request;
+use crate::source::BlockSource;
+
+/// Time given to a peer to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum number of requests of the peers served at the same time, the other ones being dropped.
+pub(crate) const MAX_CONCURRENT_INBOUND_REQUESTS: usize = 16;
+/// Number of blocks read ahead of the ones written to the peer, for each request served.
+const SERVED_BLOCKS_AHEAD: usize = 1;
+
+/// Configuration of the p2p network.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkConfig {
+    /// The addresses to listen on, e.g. `/ip4/0.0.0.0/tcp/30334`.
+    pub listen_addresses: Vec<Multiaddr>,
+    /// The peers dialed when the network starts, as addresses ending with `/p2p/<peer id>`.
+    pub bootnodes: Vec<Multiaddr>,
+}
+
+/// Load the ed25519 key identifying the node from `path`, generating it on first use.
+pub fn load_or_generate_keypair(path: &Path) -> Result<Keypair, Error> {
+    let secret = match std::fs::read(path) {
+        Ok(mut bytes) => ed25519::SecretKey::try_from_bytes(&mut bytes)
+            .map_err(|e| Error::Network(format!("Invalid p2p key at {}: {e}", path.display())))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let secret = ed25519::SecretKey::generate();
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| Error::Network(e.to_string()))?;
+            }
+            std::fs::write(path, secret.as_ref()).map_err(|e| Error::Network(e.to_string()))?;
+            secret
+        }
+        Err(e) => return Err(Error::Network(format!("Failed to read the p2p key at {}: {e}", path.display()))),
+    };
+    Ok(ed25519::Keypair::from(secret).into())
+}
+
+/// One request-response behaviour for each sync protocol, so that a request is only ever
+/// negotiated on the protocol it was made for.
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    headers: request_response::Behaviour<StarknetCodec>,
+    transactions: request_response::Behaviour<StarknetCodec>,
+    receipts: request_response::Behaviour<StarknetCodec>,
+    events: request_response::Behaviour<StarknetCodec>,
+    state_diffs: request_response::Behaviour<StarknetCodec>,
+    classes: request_response::Behaviour<StarknetCodec>,
+}
+
+impl Behaviour {
+    fn new() -> Self {
+        let protocol = |protocol: Protocol| {
+            let mut config = request_response::Config::default();
+            config.set_request_timeout(REQUEST_TIMEOUT);
+            request_response::Behaviour::new(StarknetCodec, [(protocol, ProtocolSupport::Full)], config)
+        };
+        Self {
+            headers: protocol(Protocol::Headers),
+            transactions: protocol(Protocol::Transactions),
+            receipts: protocol(Protocol::Receipts),
+            events: protocol(Protocol::Events),
+            state_diffs: protocol(Protocol::StateDiffs),
+            classes: protocol(Protocol::Classes),
+        }
+    }
+
+    fn protocol(&mut self, protocol: Protocol) -> &mut request_response::Behaviour<StarknetCodec> {
+        match protocol {
+            Protocol::Headers => &mut self.headers,
+            Protocol::Transactions => &mut self.transactions,
+            Protocol::Receipts => &mut self.receipts,
+            Protocol::Events => &mut self.events,
+            Protocol::StateDiffs => &mut self.state_diffs,
+            Protocol::Classes => &mut self.classes,
+        }
+    }
+}
+
+impl BehaviourEvent {
+    fn into_protocol_event(self) -> (Protocol, request_response::Event<Request, CodecResponse>) {
+        match self {
+            BehaviourEvent::Headers(event) => (Protocol::Headers, event),
+            BehaviourEvent::Transactions(event) => (Protocol::Transactions, event),
+            BehaviourEvent::Receipts(event) => (Protocol::Receipts, event),
+            BehaviourEvent::Events(event) => (Protocol::Events, event),
+            BehaviourEvent::StateDiffs(event) => (Protocol::StateDiffs, event),
+            BehaviourEvent::Classes(event) => (Protocol::Classes, event),
+        }
+    }
+}
+
+enum Command {
+    AddPeer { peer: PeerId, address: Multiaddr },
+    Request { peer: PeerId, request: Request, result: oneshot::Sender<Result<Response, Error>> },
+}
+
+/// Requests blocks to the peers of a running [`NetworkService`].
+#[derive(Clone)]
+pub struct NetworkHandle {
+    local_peer_id: PeerId,
+    commands: mpsc::UnboundedSender<Command>,
+    listen_addresses: watch::Receiver<Vec<Multiaddr>>,
+}
+
+impl NetworkHandle {
+    pub fn local_peer_id(&self) -> PeerId {
+        self.local_peer_id
+    }
+
+    /// The addresses the node listens on, once the network is running.
+    pub fn listen_addresses(&self) -> watch::Receiver<Vec<Multiaddr>> {
+        self.listen_addresses.clone()
+    }
+
+    /// Record `address` as an address of `peer`, dialed when a request is sent to it.
+    pub fn add_peer(&self, peer: PeerId, address: Multiaddr) -> Result<(), Error> {
+        self.commands.send(Command::AddPeer { peer, address }).map_err(|_| Error::ServiceStopped)
+    }
+
+    /// Send `request` to `peer` and wait for its response.
+    pub async fn request(&self, peer: PeerId, request: Request) -> Result<Response, Error> {
+        let (result, receiver) = oneshot::channel();
+        self.commands.send(Command::Request { peer, request, result }).map_err(|_| Error::ServiceStopped)?;
+        receiver.await.map_err(|_| Error::ServiceStopped)?
+    }
+}
+
+/// Serves the blocks of a [`BlockSource`] on the Starknet sync protocols, and sends the requests
+/// of its [`NetworkHandle`] to the peers.
+pub struct NetworkService<S: ?Sized> {
+    swarm: Swarm<Behaviour>,
+    source: Arc<S>,
+    bootnodes: Vec<(PeerId, Multiaddr)>,
+    commands: mpsc::UnboundedReceiver<Command>,
+    listen_addresses: watch::Sender<Vec<Multiaddr>>,
+    pending_requests: HashMap<(Protocol, RequestId), oneshot::Sender<Result<Response, Error>>>,
+    inbound_requests: Arc<Semaphore>,
+}
+
+fn transport(keypair: &Keypair) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Error> {
+    let noise = noise::Config::new(keypair).map_err(|e| Error::Network(e.to_string()))?;
+    Ok(tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
+        .upgrade(Version::V1)
+        .authenticate(noise)
+        .multiplex(yamux::Config::default())
+        .boxed())
+}
+
+impl<S: BlockSource + ?Sized> NetworkService<S> {
+    /// Listen on the addresses of `config`. The service must be [run](Self::run) to accept
+    /// connections and dial the bootnodes.
+    pub fn new(config: NetworkConfig, keypair: Keypair, source: Arc<S>) -> Result<(Self, NetworkHandle), Error> {
+        let local_peer_id = PeerId::from(keypair.public());
+        let mut swarm =
+            SwarmBuilder::with_tokio_executor(transport(&keypair)?, Behaviour::new(), local_peer_id).build();
+        for address in config.listen_addresses {
+            swarm.listen_on(address.clone()).map_err(|e| Error::Network(format!("Cannot listen on {address}: {e}")))?;
+        }
+
+        let bootnodes = config
+            .bootnodes
+            .into_iter()
+            .map(|address| match PeerId::try_from_multiaddr(&address) {
+                Some(peer) => Ok((peer, address)),
+                None => Err(Error::Network(format!("The bootnode {address} does not end with /p2p/<peer id>"))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let (command_sender, commands) = mpsc::unbounded_channel();
+        let (listen_addresses, listen_addresses_receiver) = watch::channel(Vec::new());
+        let service = Self {
+            swarm,
+            source,
+            bootnodes,
+            commands,
+            listen_addresses,
+            pending_requests: HashMap::new(),
+            inbound_requests: Arc::new(Semaphore::new(MAX_CONCURRENT_INBOUND_REQUESTS)),
+        };
+        let handle =
+            NetworkHandle { local_peer_id, commands: command_sender, listen_addresses: listen_addresses_receiver };
+
+        Ok((service, handle))
+    }
+
+    pub async fn run(mut self) {
+        for (peer, address) in std::mem::take(&mut self.bootnodes) {
+            self.add_peer(peer, address.clone());
+            if let Err(e) = self.swarm.dial(address) {
+                log::warn!("🌐 Failed to dial the bootnode {peer}: {e}");
+            }
+        }
+
+        loop {
+            tokio::select! {
+                event = self.swarm.select_next_some() => self.on_swarm_event(event),
+                Some(command) = self.commands.recv() => self.on_command(command),
+            }
+        }
+    }
+
+    fn on_command(&mut self, command: Command) {
+        match command {
+            Command::AddPeer { peer, address } => self.add_peer(peer, address),
+            Command::Request { peer, request, result } => {
+                let protocol = request.protocol;
+                let request_id = self.swarm.behaviour_mut().protocol(protocol).send_request(&peer, request);
+                self.pending_requests.insert((protocol, request_id), result);
+            }
+        }
+    }
+
+    fn add_peer(&mut self, peer: PeerId, address: Multiaddr) {
+        for protocol in Protocol::ALL {
+            self.swarm.behaviour_mut().protocol(protocol).add_address(&peer, address.clone());
+        }
+    }
+
+    fn on_swarm_event<E>(&mut self, event: SwarmEvent<BehaviourEvent, E>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                log::info!("🌐 Starknet p2p listening on {address}/p2p/{}", self.swarm.local_peer_id());
+                self.listen_addresses.send_modify(|addresses| addresses.push(address));
+            }
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                self.listen_addresses.send_modify(|addresses| addresses.retain(|a| *a != address));
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => log::debug!("🌐 Connected to {peer_id}"),
+            SwarmEvent::Behaviour(event) => {
+                let (protocol, event) = event.into_protocol_event();
+                self.on_request_response_event(protocol, event);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_request_response_event(
+        &mut self,
+        protocol: Protocol,
+        event: request_response::Event<Request, CodecResponse>,
+    ) {
+        match event {
+            request_response::Event::Message { peer, message } => match message {
+                request_response::Message::Request { request, channel, .. } => self.serve(peer, request, channel),
+                request_response::Message::Response { request_id, response } => {
+                    if let Some(result) = self.pending_requests.remove(&(protocol, request_id)) {
+                        let response = match response {
+                            CodecResponse::Whole(response) => response,
+                            CodecResponse::Streamed(_) => unreachable!("The responses of the peers are read whole"),
+                        };
+                        let _ = result.send(Ok(response));
+                    }
+                }
+            },
+            request_response::Event::OutboundFailure { peer, request_id, error } => {
+                if let Some(result) = self.pending_requests.remove(&(protocol, request_id)) {
+                    let _ = result.send(Err(Error::Request { peer, reason: error.to_string() }));
+                }
+            }
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                log::debug!("🌐 Failed to answer the {} request of {peer}: {error}", protocol.name());
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+
+    /// Stream the requested blocks to the peer, reading them on a blocking task as they are
+    /// written.
+    ///
+    /// The requests beyond [`MAX_CONCURRENT_INBOUND_REQUESTS`] are dropped, which the peers see as
+    /// failed requests.
+    fn serve(&mut self, peer: PeerId, request: Request, channel: ResponseChannel<CodecResponse>) {
+        let protocol = request.protocol;
+        let Ok(permit) = self.inbound_requests.clone().try_acquire_owned() else {
+            log::debug!("🌐 Dropped the {} request of {peer}, too many requests are being served", protocol.name());
+            return;
+        };
+        let (sender, blocks) = mpsc::channel(SERVED_BLOCKS_AHEAD);
+        if self
+            .swarm
+            .behaviour_mut()
+            .protocol(protocol)
+            .send_response(channel, CodecResponse::Streamed(blocks))
+            .is_err()
+        {
+            log::debug!("🌐 The peer closed the {} request before its response", protocol.name());
+            return;
+        }
+
+        let source = self.source.clone();
+        tokio::task::spawn_blocking(move || {
+            // Released once the last block is read
+            let _permit = permit;
+            let result = handle_request(source.as_ref(), &request, |block| sender.blocking_send(Ok(block)).is_ok());
+            if let Err(e) = result {
+                log::warn!("🌐 Failed to serve the {} request of {peer}: {e}", protocol.name());
+                let _ = sender.blocking_send(Err(e));
+            }
+        });
+    }
+}
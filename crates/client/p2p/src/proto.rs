@@ -0,0 +1,758 @@
+//! Messages of the Starknet p2p specification, `p2p/proto/*.proto` of
+//! `starknet-io/starknet-p2p-specs`.
+//!
+//! They are written by hand, as `prost-build` would generate them, so that building the node does
+//! not require `protoc`. Field tags must be kept in sync with the specification, the conformance
+//! tests decode messages encoded from it, see `fixtures/`.
+
+#![allow(clippy::derive_partial_eq_without_eq, clippy::large_enum_variant)]
+
+// common.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Felt252 {
+    #[prost(bytes = "vec", tag = "1")]
+    pub elements: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Hash {
+    #[prost(bytes = "vec", tag = "1")]
+    pub elements: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Address {
+    #[prost(bytes = "vec", tag = "1")]
+    pub elements: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthereumAddress {
+    #[prost(bytes = "vec", tag = "1")]
+    pub elements: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Uint128 {
+    #[prost(uint64, tag = "1")]
+    pub low: u64,
+    #[prost(uint64, tag = "2")]
+    pub high: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConsensusSignature {
+    #[prost(message, optional, tag = "1")]
+    pub r: Option<Felt252>,
+    #[prost(message, optional, tag = "2")]
+    pub s: Option<Felt252>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Patricia {
+    #[prost(uint64, tag = "1")]
+    pub n_leaves: u64,
+    #[prost(message, optional, tag = "2")]
+    pub root: Option<Hash>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateDiffCommitment {
+    #[prost(uint64, tag = "1")]
+    pub state_diff_length: u64,
+    #[prost(message, optional, tag = "2")]
+    pub root: Option<Hash>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum L1DataAvailabilityMode {
+    Calldata = 0,
+    Blob = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum VolitionDomain {
+    L1 = 0,
+    L2 = 1,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Iteration {
+    #[prost(enumeration = "iteration::Direction", tag = "3")]
+    pub direction: i32,
+    #[prost(uint64, tag = "4")]
+    pub limit: u64,
+    #[prost(uint64, tag = "5")]
+    pub step: u64,
+    #[prost(oneof = "iteration::Start", tags = "1, 2")]
+    pub start: Option<iteration::Start>,
+}
+
+pub mod iteration {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Direction {
+        Forward = 0,
+        Backward = 1,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Start {
+        #[prost(uint64, tag = "1")]
+        BlockNumber(u64),
+        #[prost(message, tag = "2")]
+        Header(super::Hash),
+    }
+}
+
+/// Marks the end of a response stream.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Fin {}
+
+/// The request of every sync protocol.
+///
+/// `BlockHeadersRequest`, `TransactionsRequest`, `ReceiptsRequest`, `EventsRequest`,
+/// `StateDiffsRequest` and `ClassesRequest` of the specification only differ by their name.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IterationRequest {
+    #[prost(message, optional, tag = "1")]
+    pub iteration: Option<Iteration>,
+}
+
+// header.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SignedBlockHeader {
+    #[prost(message, optional, tag = "1")]
+    pub block_hash: Option<Hash>,
+    #[prost(message, optional, tag = "2")]
+    pub parent_hash: Option<Hash>,
+    #[prost(uint64, tag = "3")]
+    pub number: u64,
+    #[prost(uint64, tag = "4")]
+    pub time: u64,
+    #[prost(message, optional, tag = "5")]
+    pub sequencer_address: Option<Address>,
+    #[prost(message, optional, tag = "6")]
+    pub state_root: Option<Hash>,
+    #[prost(message, optional, tag = "7")]
+    pub state_diff_commitment: Option<StateDiffCommitment>,
+    #[prost(message, optional, tag = "8")]
+    pub transactions: Option<Patricia>,
+    #[prost(message, optional, tag = "9")]
+    pub events: Option<Patricia>,
+    #[prost(message, optional, tag = "10")]
+    pub receipts: Option<Hash>,
+    #[prost(string, tag = "11")]
+    pub protocol_version: String,
+    #[prost(message, optional, tag = "12")]
+    pub gas_price_fri: Option<Uint128>,
+    #[prost(message, optional, tag = "13")]
+    pub gas_price_wei: Option<Uint128>,
+    #[prost(message, optional, tag = "14")]
+    pub data_gas_price_fri: Option<Uint128>,
+    #[prost(message, optional, tag = "15")]
+    pub data_gas_price_wei: Option<Uint128>,
+    #[prost(enumeration = "L1DataAvailabilityMode", tag = "16")]
+    pub l1_data_availability_mode: i32,
+    #[prost(message, repeated, tag = "17")]
+    pub signatures: Vec<ConsensusSignature>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BlockHeadersResponse {
+    #[prost(oneof = "block_headers_response::HeaderMessage", tags = "1, 2")]
+    pub header_message: Option<block_headers_response::HeaderMessage>,
+}
+
+pub mod block_headers_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum HeaderMessage {
+        #[prost(message, tag = "1")]
+        Header(super::SignedBlockHeader),
+        #[prost(message, tag = "2")]
+        Fin(super::Fin),
+    }
+}
+
+// transaction.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AccountSignature {
+    #[prost(message, repeated, tag = "1")]
+    pub parts: Vec<Felt252>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResourceLimits {
+    #[prost(message, optional, tag = "1")]
+    pub max_amount: Option<Felt252>,
+    #[prost(message, optional, tag = "2")]
+    pub max_price_per_unit: Option<Felt252>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResourceBounds {
+    #[prost(message, optional, tag = "1")]
+    pub l1_gas: Option<ResourceLimits>,
+    #[prost(message, optional, tag = "2")]
+    pub l2_gas: Option<ResourceLimits>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Transaction {
+    #[prost(message, optional, tag = "12")]
+    pub transaction_hash: Option<Hash>,
+    #[prost(oneof = "transaction::Txn", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11")]
+    pub txn: Option<transaction::Txn>,
+}
+
+pub mod transaction {
+    use super::{AccountSignature, Address, Felt252, Hash, ResourceBounds, VolitionDomain};
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeclareV0 {
+        #[prost(message, optional, tag = "1")]
+        pub sender: Option<Address>,
+        #[prost(message, optional, tag = "2")]
+        pub max_fee: Option<Felt252>,
+        #[prost(message, optional, tag = "3")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "4")]
+        pub class_hash: Option<Hash>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeclareV1 {
+        #[prost(message, optional, tag = "1")]
+        pub sender: Option<Address>,
+        #[prost(message, optional, tag = "2")]
+        pub max_fee: Option<Felt252>,
+        #[prost(message, optional, tag = "3")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "4")]
+        pub class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "5")]
+        pub nonce: Option<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeclareV2 {
+        #[prost(message, optional, tag = "1")]
+        pub sender: Option<Address>,
+        #[prost(message, optional, tag = "2")]
+        pub max_fee: Option<Felt252>,
+        #[prost(message, optional, tag = "3")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "4")]
+        pub class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "5")]
+        pub nonce: Option<Felt252>,
+        #[prost(message, optional, tag = "6")]
+        pub compiled_class_hash: Option<Hash>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeclareV3 {
+        #[prost(message, optional, tag = "1")]
+        pub sender: Option<Address>,
+        #[prost(message, optional, tag = "2")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "3")]
+        pub class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "4")]
+        pub nonce: Option<Felt252>,
+        #[prost(message, optional, tag = "5")]
+        pub compiled_class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "6")]
+        pub resource_bounds: Option<ResourceBounds>,
+        #[prost(uint64, tag = "7")]
+        pub tip: u64,
+        #[prost(message, repeated, tag = "8")]
+        pub paymaster_data: Vec<Felt252>,
+        #[prost(message, repeated, tag = "9")]
+        pub account_deployment_data: Vec<Felt252>,
+        #[prost(enumeration = "VolitionDomain", tag = "10")]
+        pub nonce_data_availability_mode: i32,
+        #[prost(enumeration = "VolitionDomain", tag = "11")]
+        pub fee_data_availability_mode: i32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Deploy {
+        #[prost(message, optional, tag = "1")]
+        pub class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "2")]
+        pub address_salt: Option<Felt252>,
+        #[prost(message, repeated, tag = "3")]
+        pub calldata: Vec<Felt252>,
+        #[prost(uint32, tag = "4")]
+        pub version: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeployAccountV1 {
+        #[prost(message, optional, tag = "1")]
+        pub max_fee: Option<Felt252>,
+        #[prost(message, optional, tag = "2")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "3")]
+        pub class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "4")]
+        pub nonce: Option<Felt252>,
+        #[prost(message, optional, tag = "5")]
+        pub address_salt: Option<Felt252>,
+        #[prost(message, repeated, tag = "6")]
+        pub calldata: Vec<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeployAccountV3 {
+        #[prost(message, optional, tag = "1")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "2")]
+        pub class_hash: Option<Hash>,
+        #[prost(message, optional, tag = "3")]
+        pub nonce: Option<Felt252>,
+        #[prost(message, optional, tag = "4")]
+        pub address_salt: Option<Felt252>,
+        #[prost(message, repeated, tag = "5")]
+        pub calldata: Vec<Felt252>,
+        #[prost(message, optional, tag = "6")]
+        pub resource_bounds: Option<ResourceBounds>,
+        #[prost(uint64, tag = "7")]
+        pub tip: u64,
+        #[prost(message, repeated, tag = "8")]
+        pub paymaster_data: Vec<Felt252>,
+        #[prost(enumeration = "VolitionDomain", tag = "9")]
+        pub nonce_data_availability_mode: i32,
+        #[prost(enumeration = "VolitionDomain", tag = "10")]
+        pub fee_data_availability_mode: i32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct InvokeV0 {
+        #[prost(message, optional, tag = "1")]
+        pub max_fee: Option<Felt252>,
+        #[prost(message, optional, tag = "2")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, optional, tag = "3")]
+        pub address: Option<Address>,
+        #[prost(message, optional, tag = "4")]
+        pub entry_point_selector: Option<Felt252>,
+        #[prost(message, repeated, tag = "5")]
+        pub calldata: Vec<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct InvokeV1 {
+        #[prost(message, optional, tag = "1")]
+        pub sender: Option<Address>,
+        #[prost(message, optional, tag = "2")]
+        pub max_fee: Option<Felt252>,
+        #[prost(message, optional, tag = "3")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, repeated, tag = "4")]
+        pub calldata: Vec<Felt252>,
+        #[prost(message, optional, tag = "5")]
+        pub nonce: Option<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct InvokeV3 {
+        #[prost(message, optional, tag = "1")]
+        pub sender: Option<Address>,
+        #[prost(message, optional, tag = "2")]
+        pub signature: Option<AccountSignature>,
+        #[prost(message, repeated, tag = "3")]
+        pub calldata: Vec<Felt252>,
+        #[prost(message, optional, tag = "4")]
+        pub resource_bounds: Option<ResourceBounds>,
+        #[prost(uint64, tag = "5")]
+        pub tip: u64,
+        #[prost(message, repeated, tag = "6")]
+        pub paymaster_data: Vec<Felt252>,
+        #[prost(message, repeated, tag = "7")]
+        pub account_deployment_data: Vec<Felt252>,
+        #[prost(enumeration = "VolitionDomain", tag = "8")]
+        pub nonce_data_availability_mode: i32,
+        #[prost(enumeration = "VolitionDomain", tag = "9")]
+        pub fee_data_availability_mode: i32,
+        #[prost(message, optional, tag = "10")]
+        pub nonce: Option<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct L1HandlerV0 {
+        #[prost(message, optional, tag = "1")]
+        pub nonce: Option<Felt252>,
+        #[prost(message, optional, tag = "2")]
+        pub address: Option<Address>,
+        #[prost(message, optional, tag = "3")]
+        pub entry_point_selector: Option<Felt252>,
+        #[prost(message, repeated, tag = "4")]
+        pub calldata: Vec<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Txn {
+        #[prost(message, tag = "1")]
+        DeclareV0(DeclareV0),
+        #[prost(message, tag = "2")]
+        DeclareV1(DeclareV1),
+        #[prost(message, tag = "3")]
+        DeclareV2(DeclareV2),
+        #[prost(message, tag = "4")]
+        DeclareV3(DeclareV3),
+        #[prost(message, tag = "5")]
+        Deploy(Deploy),
+        #[prost(message, tag = "6")]
+        DeployAccountV1(DeployAccountV1),
+        #[prost(message, tag = "7")]
+        DeployAccountV3(DeployAccountV3),
+        #[prost(message, tag = "8")]
+        InvokeV0(InvokeV0),
+        #[prost(message, tag = "9")]
+        InvokeV1(InvokeV1),
+        #[prost(message, tag = "10")]
+        InvokeV3(InvokeV3),
+        #[prost(message, tag = "11")]
+        L1Handler(L1HandlerV0),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransactionsResponse {
+    #[prost(oneof = "transactions_response::TransactionMessage", tags = "1, 2")]
+    pub transaction_message: Option<transactions_response::TransactionMessage>,
+}
+
+pub mod transactions_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum TransactionMessage {
+        #[prost(message, tag = "1")]
+        Transaction(super::Transaction),
+        #[prost(message, tag = "2")]
+        Fin(super::Fin),
+    }
+}
+
+// receipt.proto
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PriceUnit {
+    Wei = 0,
+    Fri = 1,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MessageToL1 {
+    #[prost(message, optional, tag = "1")]
+    pub from_address: Option<Felt252>,
+    #[prost(message, repeated, tag = "2")]
+    pub payload: Vec<Felt252>,
+    #[prost(message, optional, tag = "3")]
+    pub to_address: Option<EthereumAddress>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Receipt {
+    #[prost(oneof = "receipt::Type", tags = "1, 2, 3, 4, 5")]
+    pub r#type: Option<receipt::Type>,
+}
+
+pub mod receipt {
+    use super::{Felt252, Hash, MessageToL1, PriceUnit};
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ExecutionResources {
+        #[prost(message, optional, tag = "1")]
+        pub builtins: Option<execution_resources::BuiltinCounter>,
+        #[prost(uint32, tag = "2")]
+        pub steps: u32,
+        #[prost(uint32, tag = "3")]
+        pub memory_holes: u32,
+        #[prost(message, optional, tag = "4")]
+        pub l1_gas: Option<Felt252>,
+        #[prost(message, optional, tag = "5")]
+        pub l1_data_gas: Option<Felt252>,
+    }
+
+    pub mod execution_resources {
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct BuiltinCounter {
+            #[prost(uint32, tag = "1")]
+            pub bitwise: u32,
+            #[prost(uint32, tag = "2")]
+            pub ecdsa: u32,
+            #[prost(uint32, tag = "3")]
+            pub ec_op: u32,
+            #[prost(uint32, tag = "4")]
+            pub pedersen: u32,
+            #[prost(uint32, tag = "5")]
+            pub range_check: u32,
+            #[prost(uint32, tag = "6")]
+            pub poseidon: u32,
+            #[prost(uint32, tag = "7")]
+            pub keccak: u32,
+            #[prost(uint32, tag = "8")]
+            pub output: u32,
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Common {
+        #[prost(message, optional, tag = "2")]
+        pub actual_fee: Option<Felt252>,
+        #[prost(enumeration = "PriceUnit", tag = "3")]
+        pub price_unit: i32,
+        #[prost(message, repeated, tag = "4")]
+        pub messages_sent: Vec<MessageToL1>,
+        #[prost(message, optional, tag = "5")]
+        pub execution_resources: Option<ExecutionResources>,
+        #[prost(string, optional, tag = "6")]
+        pub revert_reason: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Invoke {
+        #[prost(message, optional, tag = "1")]
+        pub common: Option<Common>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct L1Handler {
+        #[prost(message, optional, tag = "1")]
+        pub common: Option<Common>,
+        #[prost(message, optional, tag = "2")]
+        pub msg_hash: Option<Hash>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Declare {
+        #[prost(message, optional, tag = "1")]
+        pub common: Option<Common>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Deploy {
+        #[prost(message, optional, tag = "1")]
+        pub common: Option<Common>,
+        #[prost(message, optional, tag = "2")]
+        pub contract_address: Option<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeployAccount {
+        #[prost(message, optional, tag = "1")]
+        pub common: Option<Common>,
+        #[prost(message, optional, tag = "2")]
+        pub contract_address: Option<Felt252>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Type {
+        #[prost(message, tag = "1")]
+        Invoke(Invoke),
+        #[prost(message, tag = "2")]
+        L1Handler(L1Handler),
+        #[prost(message, tag = "3")]
+        Declare(Declare),
+        #[prost(message, tag = "4")]
+        DeprecatedDeploy(Deploy),
+        #[prost(message, tag = "5")]
+        DeployAccount(DeployAccount),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReceiptsResponse {
+    #[prost(oneof = "receipts_response::ReceiptMessage", tags = "1, 2")]
+    pub receipt_message: Option<receipts_response::ReceiptMessage>,
+}
+
+pub mod receipts_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum ReceiptMessage {
+        #[prost(message, tag = "1")]
+        Receipt(super::Receipt),
+        #[prost(message, tag = "2")]
+        Fin(super::Fin),
+    }
+}
+
+// event.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Event {
+    #[prost(message, optional, tag = "1")]
+    pub transaction_hash: Option<Hash>,
+    #[prost(message, optional, tag = "3")]
+    pub from_address: Option<Felt252>,
+    #[prost(message, repeated, tag = "4")]
+    pub keys: Vec<Felt252>,
+    #[prost(message, repeated, tag = "5")]
+    pub data: Vec<Felt252>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EventsResponse {
+    #[prost(oneof = "events_response::EventMessage", tags = "1, 2")]
+    pub event_message: Option<events_response::EventMessage>,
+}
+
+pub mod events_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum EventMessage {
+        #[prost(message, tag = "1")]
+        Event(super::Event),
+        #[prost(message, tag = "2")]
+        Fin(super::Fin),
+    }
+}
+
+// state.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContractStoredValue {
+    #[prost(message, optional, tag = "1")]
+    pub key: Option<Felt252>,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<Felt252>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContractDiff {
+    #[prost(message, optional, tag = "1")]
+    pub address: Option<Address>,
+    #[prost(message, optional, tag = "2")]
+    pub nonce: Option<Felt252>,
+    #[prost(message, optional, tag = "3")]
+    pub class_hash: Option<Hash>,
+    #[prost(message, repeated, tag = "4")]
+    pub values: Vec<ContractStoredValue>,
+    #[prost(enumeration = "VolitionDomain", tag = "5")]
+    pub domain: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeclaredClass {
+    #[prost(message, optional, tag = "1")]
+    pub class_hash: Option<Hash>,
+    /// Not set for Cairo 0 classes.
+    #[prost(message, optional, tag = "2")]
+    pub compiled_class_hash: Option<Hash>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateDiffsResponse {
+    #[prost(oneof = "state_diffs_response::StateDiffMessage", tags = "1, 2, 3")]
+    pub state_diff_message: Option<state_diffs_response::StateDiffMessage>,
+}
+
+pub mod state_diffs_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum StateDiffMessage {
+        #[prost(message, tag = "1")]
+        ContractDiff(super::ContractDiff),
+        #[prost(message, tag = "2")]
+        DeclaredClass(super::DeclaredClass),
+        #[prost(message, tag = "3")]
+        Fin(super::Fin),
+    }
+}
+
+// class.proto
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EntryPoint {
+    #[prost(message, optional, tag = "1")]
+    pub selector: Option<Felt252>,
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Cairo0Class {
+    #[prost(string, tag = "1")]
+    pub abi: String,
+    #[prost(message, repeated, tag = "2")]
+    pub externals: Vec<EntryPoint>,
+    #[prost(message, repeated, tag = "3")]
+    pub l1_handlers: Vec<EntryPoint>,
+    #[prost(message, repeated, tag = "4")]
+    pub constructors: Vec<EntryPoint>,
+    /// The gzip compressed program, encoded in base64.
+    #[prost(string, tag = "5")]
+    pub program: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SierraEntryPoint {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(message, optional, tag = "2")]
+    pub selector: Option<Felt252>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Cairo1EntryPoints {
+    #[prost(message, repeated, tag = "1")]
+    pub externals: Vec<SierraEntryPoint>,
+    #[prost(message, repeated, tag = "2")]
+    pub l1_handlers: Vec<SierraEntryPoint>,
+    #[prost(message, repeated, tag = "3")]
+    pub constructors: Vec<SierraEntryPoint>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Cairo1Class {
+    #[prost(string, tag = "1")]
+    pub abi: String,
+    #[prost(message, optional, tag = "2")]
+    pub entry_points: Option<Cairo1EntryPoints>,
+    #[prost(message, repeated, tag = "3")]
+    pub program: Vec<Felt252>,
+    #[prost(string, tag = "4")]
+    pub contract_class_version: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Class {
+    #[prost(uint32, tag = "3")]
+    pub domain: u32,
+    #[prost(message, optional, tag = "4")]
+    pub class_hash: Option<Hash>,
+    #[prost(oneof = "class::Class", tags = "1, 2")]
+    pub class: Option<class::Class>,
+}
+
+pub mod class {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Class {
+        #[prost(message, tag = "1")]
+        Cairo0(super::Cairo0Class),
+        #[prost(message, tag = "2")]
+        Cairo1(super::Cairo1Class),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClassesResponse {
+    #[prost(oneof = "classes_response::ClassMessage", tags = "1, 2")]
+    pub class_message: Option<classes_response::ClassMessage>,
+}
+
+pub mod classes_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum ClassMessage {
+        #[prost(message, tag = "1")]
+        Class(super::Class),
+        #[prost(message, tag = "2")]
+        Fin(super::Fin),
+    }
+}
@@ -0,0 +1,74 @@
+use crate::codec::{Protocol, Request, Response};
+use crate::error::Error;
+use crate::proto::iteration::{Direction, Start};
+use crate::proto::state_diffs_response::StateDiffMessage;
+use crate::proto::Iteration;
+use crate::source::BlockSource;
+
+/// Maximum number of blocks whose data is sent in response to a single request.
+pub const MAX_BLOCKS_PER_REQUEST: u64 = 128;
+
+/// Answer a request with the data of the blocks of its iteration, passed to `send` one block at a
+/// time.
+///
+/// The iteration stops at the first block missing from `source`, so a peer asking for blocks
+/// past the best one only gets those available. It also stops once `send` returns `false`, the
+/// peer having stopped reading the response.
+pub fn handle_request<S, F>(source: &S, request: &Request, mut send: F) -> Result<(), Error>
+where
+    S: BlockSource + ?Sized,
+    F: FnMut(Response) -> bool,
+{
+    let iteration = &request.iteration;
+    let Some(mut block_number) = first_block(source, iteration)? else {
+        return Ok(());
+    };
+    let step = iteration.step.max(1);
+    let backward = iteration.direction == Direction::Backward as i32;
+
+    for _ in 0..iteration.limit.min(MAX_BLOCKS_PER_REQUEST) {
+        let Some(block) = block_data(source, request.protocol, block_number)? else {
+            break;
+        };
+        if !send(block) {
+            break;
+        }
+
+        let next = if backward { block_number.checked_sub(step) } else { block_number.checked_add(step) };
+        match next {
+            Some(next) => block_number = next,
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of the block the iteration starts from, `None` for an unknown block.
+fn first_block<S: BlockSource + ?Sized>(source: &S, iteration: &Iteration) -> Result<Option<u64>, Error> {
+    match &iteration.start {
+        Some(Start::BlockNumber(block_number)) => Ok(Some(*block_number)),
+        Some(Start::Header(block_hash)) => source.block_number(block_hash),
+        None => Ok(None),
+    }
+}
+
+/// The data of block `block_number` served on `protocol`, `None` if the block is missing.
+fn block_data<S: BlockSource + ?Sized>(
+    source: &S,
+    protocol: Protocol,
+    block_number: u64,
+) -> Result<Option<Response>, Error> {
+    Ok(match protocol {
+        Protocol::Headers => source.header(block_number)?.map(|header| Response::Headers(vec![header])),
+        Protocol::Transactions => source.transactions(block_number)?.map(Response::Transactions),
+        Protocol::Receipts => source.receipts(block_number)?.map(Response::Receipts),
+        Protocol::Events => source.events(block_number)?.map(Response::Events),
+        Protocol::StateDiffs => source.state_diff(block_number)?.map(|state_diff| {
+            let contract_diffs = state_diff.contract_diffs.into_iter().map(StateDiffMessage::ContractDiff);
+            let declared_classes = state_diff.declared_classes.into_iter().map(StateDiffMessage::DeclaredClass);
+            Response::StateDiffs(contract_diffs.chain(declared_classes).collect())
+        }),
+        Protocol::Classes => source.classes(block_number)?.map(Response::Classes),
+    })
+}
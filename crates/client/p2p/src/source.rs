@@ -0,0 +1,32 @@
+use crate::error::Error;
+use crate::proto::{Class, ContractDiff, DeclaredClass, Event, Hash, Receipt, SignedBlockHeader, Transaction};
+
+/// The changes of a block to the state, as streamed by the state diffs protocol.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateDiff {
+    pub contract_diffs: Vec<ContractDiff>,
+    pub declared_classes: Vec<DeclaredClass>,
+}
+
+/// The blocks served to the peers.
+///
+/// Each method returns `None` for a block that is not known yet, which ends the response to the
+/// peer.
+pub trait BlockSource: Send + Sync + 'static {
+    /// The number of the block with the given hash, if it is part of the chain.
+    fn block_number(&self, block_hash: &Hash) -> Result<Option<u64>, Error>;
+
+    fn header(&self, block_number: u64) -> Result<Option<SignedBlockHeader>, Error>;
+
+    fn transactions(&self, block_number: u64) -> Result<Option<Vec<Transaction>>, Error>;
+
+    /// The receipts of the transactions of the block, in the same order.
+    fn receipts(&self, block_number: u64) -> Result<Option<Vec<Receipt>>, Error>;
+
+    fn events(&self, block_number: u64) -> Result<Option<Vec<Event>>, Error>;
+
+    fn state_diff(&self, block_number: u64) -> Result<Option<StateDiff>, Error>;
+
+    /// The classes declared in the block.
+    fn classes(&self, block_number: u64) -> Result<Option<Vec<Class>>, Error>;
+}
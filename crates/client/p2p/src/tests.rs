@@ -0,0 +1,307 @@
+use std::sync::{Arc, RwLock};
+
+use assert_matches::assert_matches;
+use libp2p::identity::Keypair;
+use libp2p::{Multiaddr, PeerId};
+
+use crate::codec::{Protocol, Request, Response};
+use crate::error::Error;
+use crate::network::MAX_CONCURRENT_INBOUND_REQUESTS;
+use crate::proto::iteration::{Direction, Start};
+use crate::proto::state_diffs_response::StateDiffMessage;
+use crate::proto::{
+    Class, ContractDiff, DeclaredClass, Event, Hash, Iteration, Receipt, SignedBlockHeader, Transaction,
+};
+use crate::server::{handle_request, MAX_BLOCKS_PER_REQUEST};
+use crate::source::{BlockSource, StateDiff};
+use crate::{load_or_generate_keypair, NetworkConfig, NetworkHandle, NetworkService};
+
+fn block_hash(block_number: u64) -> Hash {
+    Hash { elements: [&[0; 24][..], &(block_number + 1).to_be_bytes()].concat() }
+}
+
+/// A chain of blocks with one event each, held in memory.
+struct MemoryBlockSource {
+    block_count: u64,
+}
+
+impl MemoryBlockSource {
+    fn known(&self, block_number: u64) -> bool {
+        block_number < self.block_count
+    }
+}
+
+impl BlockSource for MemoryBlockSource {
+    fn block_number(&self, hash: &Hash) -> Result<Option<u64>, Error> {
+        Ok((0..self.block_count).find(|block_number| block_hash(*block_number) == *hash))
+    }
+
+    fn header(&self, block_number: u64) -> Result<Option<SignedBlockHeader>, Error> {
+        Ok(self.known(block_number).then(|| SignedBlockHeader {
+            block_hash: Some(block_hash(block_number)),
+            parent_hash: block_number.checked_sub(1).map(block_hash),
+            number: block_number,
+            protocol_version: "0.13.2".into(),
+            ..Default::default()
+        }))
+    }
+
+    fn transactions(&self, block_number: u64) -> Result<Option<Vec<Transaction>>, Error> {
+        Ok(self.known(block_number).then(Vec::new))
+    }
+
+    fn receipts(&self, block_number: u64) -> Result<Option<Vec<Receipt>>, Error> {
+        Ok(self.known(block_number).then(Vec::new))
+    }
+
+    fn events(&self, block_number: u64) -> Result<Option<Vec<Event>>, Error> {
+        Ok(self
+            .known(block_number)
+            .then(|| vec![Event { transaction_hash: Some(block_hash(block_number)), ..Default::default() }]))
+    }
+
+    fn state_diff(&self, block_number: u64) -> Result<Option<StateDiff>, Error> {
+        Ok(self.known(block_number).then(|| StateDiff {
+            contract_diffs: vec![ContractDiff::default(); 2],
+            declared_classes: vec![DeclaredClass {
+                class_hash: Some(block_hash(block_number)),
+                compiled_class_hash: None,
+            }],
+        }))
+    }
+
+    fn classes(&self, block_number: u64) -> Result<Option<Vec<Class>>, Error> {
+        Ok(self.known(block_number).then(Vec::new))
+    }
+}
+
+/// A chain whose headers are only read once its gate is open.
+struct GatedBlockSource {
+    chain: MemoryBlockSource,
+    gate: Arc<RwLock<()>>,
+}
+
+impl BlockSource for GatedBlockSource {
+    fn block_number(&self, hash: &Hash) -> Result<Option<u64>, Error> {
+        self.chain.block_number(hash)
+    }
+
+    fn header(&self, block_number: u64) -> Result<Option<SignedBlockHeader>, Error> {
+        let _open = self.gate.read().unwrap();
+        self.chain.header(block_number)
+    }
+
+    fn transactions(&self, block_number: u64) -> Result<Option<Vec<Transaction>>, Error> {
+        self.chain.transactions(block_number)
+    }
+
+    fn receipts(&self, block_number: u64) -> Result<Option<Vec<Receipt>>, Error> {
+        self.chain.receipts(block_number)
+    }
+
+    fn events(&self, block_number: u64) -> Result<Option<Vec<Event>>, Error> {
+        self.chain.events(block_number)
+    }
+
+    fn state_diff(&self, block_number: u64) -> Result<Option<StateDiff>, Error> {
+        self.chain.state_diff(block_number)
+    }
+
+    fn classes(&self, block_number: u64) -> Result<Option<Vec<Class>>, Error> {
+        self.chain.classes(block_number)
+    }
+}
+
+fn request(protocol: Protocol, start: Start, direction: Direction, limit: u64, step: u64) -> Request {
+    Request { protocol, iteration: Iteration { start: Some(start), direction: direction as i32, limit, step } }
+}
+
+fn header_numbers(response: Response) -> Vec<u64> {
+    assert_matches!(response, Response::Headers(headers) => headers.iter().map(|header| header.number).collect())
+}
+
+/// The data of each block served in answer to `request`.
+fn served(source: &MemoryBlockSource, request: &Request) -> Vec<Response> {
+    let mut blocks = Vec::new();
+    handle_request(source, request, |block| {
+        blocks.push(block);
+        true
+    })
+    .unwrap();
+    blocks
+}
+
+fn served_headers(source: &MemoryBlockSource, start: Start, direction: Direction, limit: u64, step: u64) -> Vec<u64> {
+    served(source, &request(Protocol::Headers, start, direction, limit, step))
+        .into_iter()
+        .flat_map(header_numbers)
+        .collect()
+}
+
+#[test]
+fn iterations_stop_at_the_first_missing_block() {
+    let source = MemoryBlockSource { block_count: 5 };
+
+    assert_eq!(served_headers(&source, Start::BlockNumber(3), Direction::Forward, 10, 1), vec![3, 4]);
+    assert_eq!(served_headers(&source, Start::BlockNumber(5), Direction::Forward, 10, 1), Vec::<u64>::new());
+}
+
+#[test]
+fn iterations_follow_their_direction_and_step() {
+    let source = MemoryBlockSource { block_count: 5 };
+
+    assert_eq!(served_headers(&source, Start::BlockNumber(4), Direction::Backward, 10, 2), vec![4, 2, 0]);
+    assert_eq!(served_headers(&source, Start::BlockNumber(0), Direction::Forward, 2, 3), vec![0, 3]);
+    // A step of 0 is taken as 1
+    assert_eq!(served_headers(&source, Start::BlockNumber(1), Direction::Forward, 2, 0), vec![1, 2]);
+}
+
+#[test]
+fn iterations_can_start_from_a_block_hash() {
+    let source = MemoryBlockSource { block_count: 5 };
+
+    assert_eq!(served_headers(&source, Start::Header(block_hash(2)), Direction::Forward, 2, 1), vec![2, 3]);
+    assert_eq!(served_headers(&source, Start::Header(block_hash(7)), Direction::Forward, 2, 1), Vec::<u64>::new());
+}
+
+#[test]
+fn iterations_are_capped() {
+    let source = MemoryBlockSource { block_count: MAX_BLOCKS_PER_REQUEST + 10 };
+
+    let headers = served_headers(&source, Start::BlockNumber(0), Direction::Forward, u64::MAX, 1);
+    assert_eq!(headers.len() as u64, MAX_BLOCKS_PER_REQUEST);
+}
+
+#[test]
+fn iterations_stop_once_the_peer_stops_reading() {
+    let source = MemoryBlockSource { block_count: 5 };
+
+    let mut headers = Vec::new();
+    handle_request(&source, &request(Protocol::Headers, Start::BlockNumber(0), Direction::Forward, 5, 1), |block| {
+        headers.extend(header_numbers(block));
+        headers.len() < 2
+    })
+    .unwrap();
+    assert_eq!(headers, vec![0, 1]);
+}
+
+#[test]
+fn state_diffs_stream_contract_diffs_then_declared_classes() {
+    let source = MemoryBlockSource { block_count: 2 };
+
+    let blocks = served(&source, &request(Protocol::StateDiffs, Start::BlockNumber(0), Direction::Forward, 2, 1));
+    let messages: Vec<_> = blocks
+        .into_iter()
+        .flat_map(|block| assert_matches!(block, Response::StateDiffs(messages) => messages))
+        .collect();
+    assert_eq!(messages.len(), 6);
+    assert_matches!(messages[1], StateDiffMessage::ContractDiff(_));
+    assert_matches!(&messages[2], StateDiffMessage::DeclaredClass(class) => {
+        assert_eq!(class.class_hash, Some(block_hash(0)));
+    });
+    assert_matches!(messages[3], StateDiffMessage::ContractDiff(_));
+}
+
+#[test]
+fn keypairs_are_kept_across_restarts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("p2p").join("secret_ed25519");
+
+    let keypair = load_or_generate_keypair(&path).unwrap();
+    assert_eq!(PeerId::from(load_or_generate_keypair(&path).unwrap().public()), PeerId::from(keypair.public()));
+}
+
+/// The address of a running node, ending with its peer id.
+async fn node_address(node: &NetworkHandle) -> Multiaddr {
+    let mut listen_addresses = node.listen_addresses();
+    let address = listen_addresses.wait_for(|addresses| !addresses.is_empty()).await.unwrap()[0].clone();
+    format!("{address}/p2p/{}", node.local_peer_id()).parse().unwrap()
+}
+
+/// Start a node listening on a local port.
+async fn start_node<S: BlockSource>(source: S, bootnodes: Vec<Multiaddr>) -> (NetworkHandle, Multiaddr) {
+    let config = NetworkConfig { listen_addresses: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()], bootnodes };
+    let (service, handle) = NetworkService::new(config, Keypair::generate_ed25519(), Arc::new(source)).unwrap();
+    tokio::spawn(service.run());
+
+    let address = node_address(&handle).await;
+    (handle, address)
+}
+
+#[tokio::test]
+async fn blocks_are_requested_from_peers() {
+    let (server, server_address) = start_node(MemoryBlockSource { block_count: 3 }, vec![]).await;
+    let (client, _) = start_node(MemoryBlockSource { block_count: 0 }, vec![]).await;
+    client.add_peer(server.local_peer_id(), server_address).unwrap();
+
+    let headers = client
+        .request(server.local_peer_id(), request(Protocol::Headers, Start::BlockNumber(0), Direction::Forward, 10, 1));
+    assert_eq!(header_numbers(headers.await.unwrap()), vec![0, 1, 2]);
+
+    let events = client
+        .request(server.local_peer_id(), request(Protocol::Events, Start::BlockNumber(2), Direction::Backward, 2, 1));
+    assert_matches!(events.await.unwrap(), Response::Events(events) => {
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].transaction_hash, Some(block_hash(2)));
+    });
+}
+
+#[tokio::test]
+async fn bootnodes_are_peers_of_the_node() {
+    let (server, server_address) = start_node(MemoryBlockSource { block_count: 2 }, vec![]).await;
+    let (client, _) = start_node(MemoryBlockSource { block_count: 0 }, vec![server_address]).await;
+
+    let state_diffs = client
+        .request(server.local_peer_id(), request(Protocol::StateDiffs, Start::BlockNumber(1), Direction::Forward, 1, 1))
+        .await
+        .unwrap();
+    assert_matches!(state_diffs, Response::StateDiffs(messages) => assert_eq!(messages.len(), 3));
+}
+
+#[tokio::test]
+async fn bootnodes_must_have_a_peer_id() {
+    let config =
+        NetworkConfig { listen_addresses: vec![], bootnodes: vec!["/ip4/127.0.0.1/tcp/30333".parse().unwrap()] };
+    let source = Arc::new(MemoryBlockSource { block_count: 0 });
+
+    let result = NetworkService::new(config, Keypair::generate_ed25519(), source).map(|_| ());
+    assert_matches!(result, Err(Error::Network(_)));
+}
+
+#[tokio::test]
+async fn requests_to_unknown_peers_fail() {
+    let (client, _) = start_node(MemoryBlockSource { block_count: 0 }, vec![]).await;
+    let unknown_peer = PeerId::from(Keypair::generate_ed25519().public());
+
+    let result =
+        client.request(unknown_peer, request(Protocol::Headers, Start::BlockNumber(0), Direction::Forward, 1, 1)).await;
+    assert_matches!(result, Err(Error::Request { peer, .. }) => assert_eq!(peer, unknown_peer));
+}
+
+#[tokio::test]
+async fn requests_beyond_the_limit_are_dropped() {
+    let gate = Arc::new(RwLock::new(()));
+    let closed = gate.write().unwrap();
+    let source = GatedBlockSource { chain: MemoryBlockSource { block_count: 1 }, gate: gate.clone() };
+    let (server, server_address) = start_node(source, vec![]).await;
+    let (client, _) = start_node(MemoryBlockSource { block_count: 0 }, vec![]).await;
+    client.add_peer(server.local_peer_id(), server_address).unwrap();
+
+    let peer = server.local_peer_id();
+    let requests: Vec<_> = (0..=MAX_CONCURRENT_INBOUND_REQUESTS)
+        .map(|_| {
+            let client = client.clone();
+            let headers = request(Protocol::Headers, Start::BlockNumber(0), Direction::Forward, 1, 1);
+            tokio::spawn(async move { client.request(peer, headers).await })
+        })
+        .collect();
+
+    // The request over the limit fails while the other ones wait for the gate
+    let (dropped, _, pending) = futures::future::select_all(requests).await;
+    assert_matches!(dropped.unwrap(), Err(Error::Request { .. }));
+
+    drop(closed);
+    for response in futures::future::join_all(pending).await {
+        assert_eq!(header_numbers(response.unwrap().unwrap()), vec![0]);
+    }
+}
@@ -0,0 +1,260 @@
+//! Optimistic parallel execution of the transactions of a block, in the style of Block-STM.
+//!
+//! Transactions are executed in waves. In each wave, the pending transactions are executed in
+//! parallel against a [`MultiVersionState`] that is frozen for the duration of the wave, each of
+//! them reading the values written by the transactions before it and recording the [`Version`] of
+//! everything it read through the blockifier [`StateReader`]. The writes of the wave are then
+//! applied and the transactions are validated in block order:
+//! - the valid transactions following the committed ones are committed,
+//! - the transactions whose reads are outdated are executed again in the next wave, unless they
+//!   read a key written by a transaction executed again in that wave, in which case they would most
+//!   likely be invalidated again.
+//!
+//! The first transaction that isn't committed only reads committed values once executed again, so
+//! every wave commits at least one transaction. As the state is frozen during a wave, the result
+//! doesn't depend on the scheduling of the threads and is the same as the one of
+//! [`execute_sequentially`].
+//!
+//! Transactions all writing a common key, such as the balance of the sequencer when fees are
+//! charged, end up being executed one at a time.
+//!
+//! The executor is not used to produce blocks. Substrate builds a block by applying its extrinsics
+//! one after the other through the runtime, and every node importing the block executes them again
+//! the same way, so the runtime can't take the state written by the executor. Running the executor
+//! before sealing could only add to the time it takes to produce a block. Using it requires the
+//! runtime to apply a block from its committed writes, which changes what importing nodes verify.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use blockifier::execution::contract_class::ContractClass;
+use blockifier::state::cached_state::{CachedState, GlobalContractCache};
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::StateReader;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+pub mod starknet;
+mod versioned_state;
+
+pub use versioned_state::{Incarnation, MultiVersionState, StateKey, TxIndex, Version, VersionedState, WriteSet};
+
+#[cfg(test)]
+mod tests;
+
+/// The state updates of a block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub storage: BTreeMap<(ContractAddress, StorageKey), StarkFelt>,
+    pub nonces: BTreeMap<ContractAddress, Nonce>,
+    pub class_hashes: BTreeMap<ContractAddress, ClassHash>,
+    pub compiled_class_hashes: BTreeMap<ClassHash, CompiledClassHash>,
+    /// Classes declared in the block, see [`BlockExecution::contract_classes`].
+    pub declared_classes: BTreeSet<ClassHash>,
+}
+
+/// The outcome of the execution of a block.
+pub struct BlockExecution<R, E> {
+    /// The result of each transaction. Failed transactions don't update the state.
+    pub results: Vec<Result<R, E>>,
+    pub state_diff: StateDiff,
+    pub contract_classes: HashMap<ClassHash, ContractClass>,
+    /// Number of waves of parallel executions.
+    pub waves: usize,
+    /// Number of transaction executions, re-executions included.
+    pub executions: usize,
+}
+
+impl<R, E> BlockExecution<R, E> {
+    fn new(executions: Vec<Execution<R, E>>, waves: usize, executions_count: usize) -> Self {
+        let mut state_diff = StateDiff::default();
+        let mut contract_classes = HashMap::new();
+        let mut results = Vec::with_capacity(executions.len());
+
+        for Execution { result, writes, .. } in executions {
+            state_diff.storage.extend(writes.storage);
+            state_diff.nonces.extend(writes.nonces);
+            state_diff.class_hashes.extend(writes.class_hashes);
+            state_diff.compiled_class_hashes.extend(writes.compiled_class_hashes);
+            state_diff.declared_classes.extend(writes.contract_classes.keys().copied());
+            contract_classes.extend(writes.contract_classes);
+            results.push(result);
+        }
+
+        Self { results, state_diff, contract_classes, waves, executions: executions_count }
+    }
+}
+
+/// The last execution of a transaction.
+struct Execution<R, E> {
+    incarnation: Incarnation,
+    result: Result<R, E>,
+    reads: Vec<(StateKey, Version)>,
+    writes: WriteSet,
+}
+
+/// Execute `tx` against the state it sees in `versions`, discarding its writes if it fails.
+fn execute_transaction<S, T, R, E, F>(
+    base: &S,
+    versions: &MultiVersionState,
+    transactions: &[T],
+    tx: TxIndex,
+    incarnation: Incarnation,
+    execute: &F,
+) -> Execution<R, E>
+where
+    S: StateReader,
+    E: From<StateError>,
+    F: for<'a> Fn(&T, &mut CachedState<VersionedState<'a, S>>) -> Result<R, E>,
+{
+    let mut state = CachedState::new(VersionedState::new(base, versions, tx), GlobalContractCache::new(1));
+    let result = execute(&transactions[tx], &mut state).and_then(|output| {
+        let writes = WriteSet::from_cached_state(&mut state)?;
+        Ok((output, writes))
+    });
+    let reads = state.state.take_reads();
+
+    match result {
+        Ok((output, writes)) => Execution { incarnation, result: Ok(output), reads, writes },
+        Err(e) => Execution { incarnation, result: Err(e), reads, writes: WriteSet::default() },
+    }
+}
+
+/// Execute `transactions` one after the other on top of `base`.
+pub fn execute_sequentially<S, T, R, E, F>(base: &S, transactions: &[T], execute: F) -> BlockExecution<R, E>
+where
+    S: StateReader,
+    E: From<StateError>,
+    F: for<'a> Fn(&T, &mut CachedState<VersionedState<'a, S>>) -> Result<R, E>,
+{
+    let mut versions = MultiVersionState::default();
+    let executions = (0..transactions.len())
+        .map(|tx| {
+            let execution = execute_transaction(base, &versions, transactions, tx, 0, &execute);
+            versions.apply(tx, 0, &execution.writes);
+            execution
+        })
+        .collect();
+
+    BlockExecution::new(executions, transactions.len(), transactions.len())
+}
+
+/// Executes the transactions of a block on several threads.
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelExecutor {
+    workers: NonZeroUsize,
+}
+
+impl Default for ParallelExecutor {
+    /// One worker per available core.
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN))
+    }
+}
+
+impl ParallelExecutor {
+    pub fn new(workers: NonZeroUsize) -> Self {
+        Self { workers }
+    }
+
+    /// Execute `transactions` on top of `base`, with the same outcome as
+    /// [`execute_sequentially`].
+    ///
+    /// `execute` runs a transaction against the state it is given. The state updates of a failing
+    /// transaction are discarded.
+    pub fn execute<S, T, R, E, F>(&self, base: &S, transactions: &[T], execute: F) -> BlockExecution<R, E>
+    where
+        S: StateReader + Sync,
+        T: Sync,
+        R: Send,
+        E: From<StateError> + Send,
+        F: for<'a> Fn(&T, &mut CachedState<VersionedState<'a, S>>) -> Result<R, E> + Sync,
+    {
+        let mut versions = MultiVersionState::default();
+        let mut executions: Vec<Option<Execution<R, E>>> = transactions.iter().map(|_| None).collect();
+        let mut pending: Vec<TxIndex> = (0..transactions.len()).collect();
+        let mut committed = 0;
+        let mut waves = 0;
+        let mut executions_count = 0;
+
+        while committed < transactions.len() {
+            waves += 1;
+            executions_count += pending.len();
+            let incarnation = |tx: TxIndex| executions[tx].as_ref().map_or(0, |execution| execution.incarnation + 1);
+            let wave = pending.iter().map(|&tx| (tx, incarnation(tx))).collect::<Vec<_>>();
+
+            for (tx, execution) in self.execute_wave(base, &versions, transactions, &wave, &execute) {
+                versions.apply(tx, execution.incarnation, &execution.writes);
+                executions[tx] = Some(execution);
+            }
+
+            pending.clear();
+            let mut rescheduled = HashSet::new();
+            for tx in committed..transactions.len() {
+                let reads = &executions[tx].as_ref().expect("every transaction is executed in the first wave").reads;
+                let valid = versions.validate(tx, reads);
+                if valid && committed == tx {
+                    committed += 1;
+                } else if !valid {
+                    let depends_on_rescheduled = reads.iter().any(|(key, _)| {
+                        matches!(versions.version(key, tx), Version::Tx(writer, _) if rescheduled.contains(&writer))
+                    });
+                    if !depends_on_rescheduled {
+                        pending.push(tx);
+                        rescheduled.insert(tx);
+                    }
+                }
+            }
+            log::trace!("Wave {waves}: {committed} transactions committed, {} to execute again", pending.len());
+        }
+
+        let executions = executions.into_iter().map(|execution| execution.expect("all transactions are committed"));
+        BlockExecution::new(executions.collect(), waves, executions_count)
+    }
+
+    /// Execute the transactions of `wave` in parallel, the state being frozen.
+    fn execute_wave<S, T, R, E, F>(
+        &self,
+        base: &S,
+        versions: &MultiVersionState,
+        transactions: &[T],
+        wave: &[(TxIndex, Incarnation)],
+        execute: &F,
+    ) -> Vec<(TxIndex, Execution<R, E>)>
+    where
+        S: StateReader + Sync,
+        T: Sync,
+        R: Send,
+        E: From<StateError> + Send,
+        F: for<'a> Fn(&T, &mut CachedState<VersionedState<'a, S>>) -> Result<R, E> + Sync,
+    {
+        let run = |&(tx, incarnation): &(TxIndex, Incarnation)| {
+            (tx, execute_transaction(base, versions, transactions, tx, incarnation, execute))
+        };
+        let workers = self.workers.get().min(wave.len());
+        if workers <= 1 {
+            return wave.iter().map(run).collect();
+        }
+
+        let next = AtomicUsize::new(0);
+        let executed = Mutex::new(Vec::with_capacity(wave.len()));
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    let mut local = Vec::new();
+                    while let Some(job) = wave.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        local.push(run(job));
+                    }
+                    executed.lock().expect("poisoned lock").extend(local);
+                });
+            }
+        });
+
+        let mut executed = executed.into_inner().expect("poisoned lock");
+        executed.sort_unstable_by_key(|(tx, _)| *tx);
+        executed
+    }
+}
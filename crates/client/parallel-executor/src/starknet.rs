@@ -0,0 +1,166 @@
+//! Execution of Starknet transactions, the way the runtime dispatches them.
+
+use std::collections::HashSet;
+
+use blockifier::context::BlockContext;
+use blockifier::execution::contract_class::ContractClass;
+use blockifier::state::cached_state::CachedState;
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{State, StateReader, StateResult};
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::objects::{TransactionExecutionInfo, TransactionExecutionResult};
+use blockifier::transaction::transaction_execution::Transaction;
+use mp_transactions::execution::{
+    execute_l1_handler_transaction, run_non_revertible_transaction, run_revertible_transaction, PaymasterConfig,
+    SetArbitraryNonce,
+};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::TransactionVersion;
+
+use crate::VersionedState;
+
+/// The runtime configuration the transactions of a block are executed with.
+#[derive(Clone, Debug)]
+pub struct ExecutionSettings {
+    pub block_context: BlockContext,
+    pub charge_fee: bool,
+    pub disable_nonce_validation: bool,
+    pub paymaster_config: Option<PaymasterConfig>,
+}
+
+/// Execute `transaction` as the runtime does when it is dispatched, its validation included.
+///
+/// To be given to [`ParallelExecutor::execute`](crate::ParallelExecutor::execute) and
+/// [`execute_sequentially`](crate::execute_sequentially).
+pub fn execute_transaction<S: StateReader>(
+    transaction: &Transaction,
+    state: &mut CachedState<VersionedState<'_, S>>,
+    settings: &ExecutionSettings,
+) -> TransactionExecutionResult<TransactionExecutionInfo> {
+    let state = &mut TransactionState(state);
+    let ExecutionSettings { block_context, charge_fee, disable_nonce_validation, paymaster_config } = settings;
+    let paymaster_config = paymaster_config.as_ref();
+
+    match transaction {
+        Transaction::AccountTransaction(AccountTransaction::Declare(tx)) => run_non_revertible_transaction(
+            tx,
+            state,
+            block_context,
+            true,
+            *charge_fee,
+            *disable_nonce_validation,
+            paymaster_config,
+        ),
+        Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) => run_non_revertible_transaction(
+            tx,
+            state,
+            block_context,
+            true,
+            *charge_fee,
+            *disable_nonce_validation,
+            paymaster_config,
+        ),
+        Transaction::AccountTransaction(AccountTransaction::Invoke(tx))
+            if tx.tx.version() == TransactionVersion::ZERO =>
+        {
+            run_non_revertible_transaction(
+                tx,
+                state,
+                block_context,
+                true,
+                *charge_fee,
+                *disable_nonce_validation,
+                paymaster_config,
+            )
+        }
+        Transaction::AccountTransaction(AccountTransaction::Invoke(tx)) => run_revertible_transaction(
+            tx,
+            state,
+            block_context,
+            true,
+            *charge_fee,
+            *disable_nonce_validation,
+            paymaster_config,
+        ),
+        Transaction::L1HandlerTransaction(tx) => execute_l1_handler_transaction(tx, state, block_context),
+    }
+}
+
+/// The state of a transaction, which can set nonces like the state of the runtime.
+struct TransactionState<'s, 'a, S: StateReader>(&'s mut CachedState<VersionedState<'a, S>>);
+
+impl<S: StateReader> StateReader for TransactionState<'_, '_, S> {
+    fn get_storage_at(&self, contract_address: ContractAddress, key: StorageKey) -> StateResult<StarkFelt> {
+        self.0.get_storage_at(contract_address, key)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.0.get_nonce_at(contract_address)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.0.get_class_hash_at(contract_address)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.0.get_compiled_contract_class(class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.0.get_compiled_class_hash(class_hash)
+    }
+}
+
+impl<S: StateReader> State for TransactionState<'_, '_, S> {
+    fn set_storage_at(
+        &mut self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        value: StarkFelt,
+    ) -> StateResult<()> {
+        self.0.set_storage_at(contract_address, key, value)
+    }
+
+    fn increment_nonce(&mut self, contract_address: ContractAddress) -> StateResult<()> {
+        self.0.increment_nonce(contract_address)
+    }
+
+    fn set_class_hash_at(&mut self, contract_address: ContractAddress, class_hash: ClassHash) -> StateResult<()> {
+        self.0.set_class_hash_at(contract_address, class_hash)
+    }
+
+    fn set_contract_class(&mut self, class_hash: ClassHash, contract_class: ContractClass) -> StateResult<()> {
+        self.0.set_contract_class(class_hash, contract_class)
+    }
+
+    fn set_compiled_class_hash(
+        &mut self,
+        class_hash: ClassHash,
+        compiled_class_hash: CompiledClassHash,
+    ) -> StateResult<()> {
+        self.0.set_compiled_class_hash(class_hash, compiled_class_hash)
+    }
+
+    fn add_visited_pcs(&mut self, class_hash: ClassHash, pcs: &HashSet<usize>) {
+        self.0.add_visited_pcs(class_hash, pcs)
+    }
+}
+
+impl<S: StateReader> SetArbitraryNonce for TransactionState<'_, '_, S> {
+    fn set_nonce_at(&mut self, contract_address: ContractAddress, nonce: Nonce) -> StateResult<()> {
+        let mut current_nonce = self.get_nonce_at(contract_address)?;
+        if current_nonce > nonce {
+            return Err(StateError::StateReadError("Impossible to decrease a nonce".to_string()));
+        }
+
+        // `CachedState` only lets nonces be incremented
+        while current_nonce != nonce {
+            self.increment_nonce(contract_address)?;
+            current_nonce = self.get_nonce_at(contract_address)?;
+        }
+
+        Ok(())
+    }
+}
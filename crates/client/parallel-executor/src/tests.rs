@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::num::{NonZeroU128, NonZeroUsize};
+use std::path::Path;
+use std::sync::Arc;
+
+use blockifier::abi::abi_utils::{get_storage_var_address, selector_from_name};
+use blockifier::blockifier::block::{BlockInfo, GasPrices};
+use blockifier::context::{BlockContext, ChainInfo, FeeTokenAddresses};
+use blockifier::execution::contract_class::{ContractClass, ContractClassV0};
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{State, StateReader, StateResult};
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::errors::TransactionExecutionError;
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use blockifier::transaction::transaction_execution::Transaction;
+use blockifier::transaction::transactions::InvokeTransaction;
+use blockifier::versioned_constants::VersionedConstants;
+use starknet_api::block::{BlockNumber, BlockTimestamp};
+use starknet_api::core::{ChainId, ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{Calldata, Fee, InvokeTransactionV1, TransactionHash, TransactionSignature};
+
+use super::starknet::ExecutionSettings;
+use super::*;
+
+/// In-memory state the blocks are executed on.
+#[derive(Default)]
+struct BaseState {
+    storage: HashMap<(ContractAddress, StorageKey), StarkFelt>,
+    nonces: HashMap<ContractAddress, Nonce>,
+    class_hashes: HashMap<ContractAddress, ClassHash>,
+    classes: HashMap<ClassHash, ContractClass>,
+}
+
+impl StateReader for BaseState {
+    fn get_storage_at(&self, contract_address: ContractAddress, key: StorageKey) -> StateResult<StarkFelt> {
+        Ok(self.storage.get(&(contract_address, key)).copied().unwrap_or_default())
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        Ok(self.nonces.get(&contract_address).copied().unwrap_or_default())
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        Ok(self.class_hashes.get(&contract_address).copied().unwrap_or_default())
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.classes.get(&class_hash).cloned().ok_or(StateError::UndeclaredClassHash(class_hash))
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        Err(StateError::UndeclaredClassHash(class_hash))
+    }
+}
+
+/// A slot of the storage, `(contract, key)`.
+type Slot = (u64, u64);
+
+/// A transaction made of storage operations on small integer values.
+#[derive(Clone, Debug)]
+enum Op {
+    /// Add one to a slot.
+    Increment(Slot),
+    /// Move an amount from a slot to another, failing if the first one holds less.
+    Transfer { from: Slot, to: Slot, amount: u64 },
+    /// Copy the value of a slot into another.
+    Copy { from: Slot, to: Slot },
+    /// Increment the nonce of a contract.
+    BumpNonce(u64),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TestError {
+    InsufficientBalance,
+    State(String),
+}
+
+impl From<StateError> for TestError {
+    fn from(e: StateError) -> Self {
+        Self::State(e.to_string())
+    }
+}
+
+fn address(contract: u64) -> ContractAddress {
+    ContractAddress(PatriciaKey::try_from(StarkFelt::from(contract)).unwrap())
+}
+
+fn storage_key((contract, key): Slot) -> (ContractAddress, StorageKey) {
+    (address(contract), StorageKey(PatriciaKey::try_from(StarkFelt::from(key)).unwrap()))
+}
+
+fn to_u64(felt: StarkFelt) -> u64 {
+    u64::from_be_bytes(felt.bytes()[24..].try_into().unwrap())
+}
+
+fn read(state: &mut impl State, slot: Slot) -> StateResult<u64> {
+    let (contract, key) = storage_key(slot);
+    state.get_storage_at(contract, key).map(to_u64)
+}
+
+fn write(state: &mut impl State, slot: Slot, value: u64) -> StateResult<()> {
+    let (contract, key) = storage_key(slot);
+    state.set_storage_at(contract, key, StarkFelt::from(value))
+}
+
+/// Run the operations of a transaction, returning the last value written.
+fn execute(ops: &[Op], state: &mut impl State) -> Result<u64, TestError> {
+    let mut last = 0;
+    for op in ops {
+        last = match *op {
+            Op::Increment(slot) => {
+                let value = read(state, slot)? + 1;
+                write(state, slot, value)?;
+                value
+            }
+            Op::Transfer { from, to, amount } => {
+                let balance = read(state, from)?;
+                if balance < amount {
+                    return Err(TestError::InsufficientBalance);
+                }
+                write(state, from, balance - amount)?;
+                let value = read(state, to)? + amount;
+                write(state, to, value)?;
+                value
+            }
+            Op::Copy { from, to } => {
+                let value = read(state, from)?;
+                write(state, to, value)?;
+                value
+            }
+            Op::BumpNonce(contract) => {
+                state.increment_nonce(address(contract))?;
+                to_u64(state.get_nonce_at(address(contract))?.0)
+            }
+        };
+    }
+    Ok(last)
+}
+
+/// Execute the transactions on a plain map, the reference of the executors.
+fn execute_naively(base: &BaseState, transactions: &[Vec<Op>]) -> (Vec<Result<u64, TestError>>, HashMap<Slot, u64>) {
+    let mut storage = HashMap::new();
+    let value = |storage: &HashMap<Slot, u64>, slot: Slot| {
+        let (contract, key) = storage_key(slot);
+        storage.get(&slot).copied().unwrap_or_else(|| to_u64(base.get_storage_at(contract, key).unwrap()))
+    };
+
+    let results = transactions
+        .iter()
+        .map(|ops| {
+            let mut updates = storage.clone();
+            let mut last = 0;
+            for op in ops {
+                last = match *op {
+                    Op::Increment(slot) => {
+                        let value = value(&updates, slot) + 1;
+                        updates.insert(slot, value);
+                        value
+                    }
+                    Op::Transfer { from, to, amount } => {
+                        let balance = value(&updates, from);
+                        if balance < amount {
+                            return Err(TestError::InsufficientBalance);
+                        }
+                        updates.insert(from, balance - amount);
+                        let value = value(&updates, to) + amount;
+                        updates.insert(to, value);
+                        value
+                    }
+                    Op::Copy { from, to } => {
+                        let value = value(&updates, from);
+                        updates.insert(to, value);
+                        value
+                    }
+                    Op::BumpNonce(_) => unreachable!("nonces are not used by the naive execution"),
+                };
+            }
+            storage = updates;
+            Ok(last)
+        })
+        .collect();
+
+    (results, storage)
+}
+
+fn executor(workers: usize) -> ParallelExecutor {
+    ParallelExecutor::new(NonZeroUsize::new(workers).unwrap())
+}
+
+/// A deterministic pseudo random generator, to build reproducible workloads.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+/// Transactions on `slots` slots of 4 contracts, the fewer the slots the more conflicts.
+fn random_workload(seed: u64, transactions: usize, slots: u64) -> Vec<Vec<Op>> {
+    let mut rng = Lcg(seed);
+    let slot = |rng: &mut Lcg| (rng.next(4), rng.next(slots));
+    (0..transactions)
+        .map(|_| {
+            (0..1 + rng.next(3))
+                .map(|_| match rng.next(3) {
+                    0 => Op::Increment(slot(&mut rng)),
+                    1 => Op::Transfer { from: slot(&mut rng), to: slot(&mut rng), amount: rng.next(20) },
+                    _ => Op::Copy { from: slot(&mut rng), to: slot(&mut rng) },
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn funded_base_state(slots: u64) -> BaseState {
+    let mut base = BaseState::default();
+    for contract in 0..4 {
+        for key in 0..slots {
+            base.storage.insert(storage_key((contract, key)), StarkFelt::from(10_u64));
+        }
+    }
+    base
+}
+
+#[test]
+fn parallel_execution_is_deterministic() {
+    for (seed, slots) in [(1, 2), (2, 8), (3, 64), (4, 1_000)] {
+        let base = funded_base_state(slots);
+        let transactions = random_workload(seed, 200, slots);
+
+        let sequential = execute_sequentially(&base, &transactions, |ops, state| execute(ops, state));
+        let (expected_results, expected_storage) = execute_naively(&base, &transactions);
+        assert_eq!(sequential.results, expected_results);
+        for (slot, value) in &expected_storage {
+            let expected = StarkFelt::from(*value);
+            let key = storage_key(*slot);
+            assert_eq!(sequential.state_diff.storage.get(&key).copied().unwrap_or(base.storage[&key]), expected);
+        }
+
+        for workers in [1, 2, 4, 8] {
+            let parallel = executor(workers).execute(&base, &transactions, |ops, state| execute(ops, state));
+            assert_eq!(parallel.results, sequential.results, "seed {seed}, {workers} workers");
+            assert_eq!(parallel.state_diff, sequential.state_diff, "seed {seed}, {workers} workers");
+        }
+    }
+}
+
+#[test]
+fn parallel_execution_does_not_depend_on_the_number_of_workers() {
+    let base = funded_base_state(8);
+    let transactions = random_workload(42, 300, 8);
+
+    let reference = executor(1).execute(&base, &transactions, |ops, state| execute(ops, state));
+    for workers in [2, 3, 16] {
+        let parallel = executor(workers).execute(&base, &transactions, |ops, state| execute(ops, state));
+        assert_eq!(parallel.results, reference.results);
+        assert_eq!(parallel.state_diff, reference.state_diff);
+        assert_eq!((parallel.waves, parallel.executions), (reference.waves, reference.executions));
+    }
+}
+
+#[test]
+fn independent_transactions_are_executed_once() {
+    let transactions: Vec<_> = (0..100).map(|i| vec![Op::Increment((0, i)), Op::BumpNonce(i)]).collect();
+
+    let execution = executor(4).execute(&BaseState::default(), &transactions, |ops, state| execute(ops, state));
+
+    assert_eq!(execution.waves, 1);
+    assert_eq!(execution.executions, 100);
+    assert!(execution.results.iter().all(|result| *result == Ok(1)));
+    assert_eq!(execution.state_diff.storage.len(), 100);
+    assert_eq!(execution.state_diff.nonces.len(), 100);
+}
+
+#[test]
+fn conflicting_transactions_are_executed_again() {
+    let transactions = vec![vec![Op::Increment((0, 0))]; 50];
+
+    let execution = executor(4).execute(&BaseState::default(), &transactions, |ops, state| execute(ops, state));
+
+    assert_eq!(execution.results, (1..=50).map(Ok).collect::<Vec<_>>());
+    assert_eq!(execution.state_diff.storage[&storage_key((0, 0))], StarkFelt::from(50_u64));
+    // Each transaction reads the counter written by the previous one, so after the first wave they
+    // are executed again one at a time.
+    assert_eq!(execution.waves, 50);
+    assert_eq!(execution.executions, 50 + 49);
+}
+
+#[test]
+fn failed_transactions_do_not_update_the_state() {
+    let transactions = vec![
+        vec![Op::Transfer { from: (0, 0), to: (0, 1), amount: 5 }],
+        vec![Op::Increment((0, 2)), Op::Transfer { from: (0, 0), to: (0, 1), amount: 6 }],
+        vec![Op::Copy { from: (0, 2), to: (0, 3) }],
+    ];
+    let mut base = BaseState::default();
+    base.storage.insert(storage_key((0, 0)), StarkFelt::from(10_u64));
+
+    let execution = executor(3).execute(&base, &transactions, |ops, state| execute(ops, state));
+
+    assert_eq!(execution.results, vec![Ok(5), Err(TestError::InsufficientBalance), Ok(0)]);
+    assert_eq!(execution.state_diff.storage.get(&storage_key((0, 2))), None);
+    assert_eq!(execution.state_diff.storage[&storage_key((0, 0))], StarkFelt::from(5_u64));
+}
+
+const ACCOUNT_CLASS_HASH: u64 = 0x100;
+const COUNTER_CLASS_HASH: u64 = 0x200;
+/// Counter incremented by every account.
+const SHARED_COUNTER: u64 = 0x1000;
+
+fn cairo_0_class(name: &str) -> ContractClass {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../cairo-contracts/build").join(name);
+    let raw_class = std::fs::read_to_string(path).unwrap();
+    ContractClass::V0(ContractClassV0::try_from_json_string(&raw_class).unwrap())
+}
+
+fn account(index: u64) -> u64 {
+    0x2000 + index
+}
+
+/// Counter only incremented by the account `index`.
+fn own_counter(index: u64) -> u64 {
+    0x3000 + index
+}
+
+/// The shared counter and `accounts` accounts, each of them with its own counter.
+fn counters_state(accounts: u64) -> BaseState {
+    let mut base = BaseState::default();
+    base.classes.insert(ClassHash(StarkFelt::from(ACCOUNT_CLASS_HASH)), cairo_0_class("NoValidateAccount.json"));
+    base.classes.insert(ClassHash(StarkFelt::from(COUNTER_CLASS_HASH)), cairo_0_class("counter.json"));
+    base.class_hashes.insert(address(SHARED_COUNTER), ClassHash(StarkFelt::from(COUNTER_CLASS_HASH)));
+    for index in 0..accounts {
+        base.class_hashes.insert(address(account(index)), ClassHash(StarkFelt::from(ACCOUNT_CLASS_HASH)));
+        base.class_hashes.insert(address(own_counter(index)), ClassHash(StarkFelt::from(COUNTER_CLASS_HASH)));
+    }
+    base
+}
+
+/// The account `index` increasing the balance of `counter` by `amount`.
+fn increase_balance(index: u64, nonce: u64, counter: u64, amount: u64) -> Transaction {
+    let tx = starknet_api::transaction::InvokeTransaction::V1(InvokeTransactionV1 {
+        max_fee: Fee(0),
+        signature: TransactionSignature::default(),
+        nonce: Nonce(StarkFelt::from(nonce)),
+        sender_address: address(account(index)),
+        calldata: Calldata(Arc::new(vec![
+            *address(counter).0.key(),
+            selector_from_name("increase_balance").0,
+            StarkFelt::from(1_u64),
+            StarkFelt::from(amount),
+        ])),
+    });
+    let tx_hash = TransactionHash(StarkFelt::from(account(index) << 32 | nonce));
+    Transaction::AccountTransaction(AccountTransaction::Invoke(InvokeTransaction { tx, tx_hash, only_query: false }))
+}
+
+fn execution_settings() -> ExecutionSettings {
+    let block_context = BlockContext::new_unchecked(
+        &BlockInfo {
+            block_number: BlockNumber(1),
+            block_timestamp: BlockTimestamp(1_700_000_000),
+            sequencer_address: address(0x42),
+            gas_prices: GasPrices {
+                eth_l1_gas_price: NonZeroU128::MIN,
+                strk_l1_gas_price: NonZeroU128::MIN,
+                eth_l1_data_gas_price: NonZeroU128::MIN,
+                strk_l1_data_gas_price: NonZeroU128::MIN,
+            },
+            use_kzg_da: true,
+        },
+        &ChainInfo { chain_id: ChainId("MADARA".to_string()), fee_token_addresses: FeeTokenAddresses::default() },
+        VersionedConstants::latest_constants(),
+    );
+    ExecutionSettings { block_context, charge_fee: false, disable_nonce_validation: false, paymaster_config: None }
+}
+
+/// Increments of the shared counter and of the own counters of 8 accounts, along a transaction
+/// with a nonce from the future and one calling a contract which isn't deployed.
+fn counters_workload() -> Vec<Transaction> {
+    let mut transactions = Vec::new();
+    for nonce in 0..3 {
+        for index in 0..8 {
+            let counter = if (index + nonce) % 2 == 0 { SHARED_COUNTER } else { own_counter(index) };
+            transactions.push(increase_balance(index, nonce, counter, index + 1));
+        }
+    }
+    transactions.push(increase_balance(0, 10, SHARED_COUNTER, 1));
+    transactions.push(increase_balance(1, 3, 0xdead, 1));
+    transactions
+}
+
+/// The receipts of the transactions, errors being compared by their message.
+fn receipts<'a>(
+    execution: &'a BlockExecution<TransactionExecutionInfo, TransactionExecutionError>,
+) -> Vec<Result<&'a TransactionExecutionInfo, String>> {
+    execution.results.iter().map(|result| result.as_ref().map_err(ToString::to_string)).collect()
+}
+
+#[test]
+fn starknet_transactions_have_the_same_outcome_in_parallel() {
+    let base = counters_state(8);
+    let transactions = counters_workload();
+    let settings = execution_settings();
+
+    let sequential =
+        execute_sequentially(&base, &transactions, |tx, state| starknet::execute_transaction(tx, state, &settings));
+    for workers in [1, 2, 4, 8] {
+        let parallel = executor(workers)
+            .execute(&base, &transactions, |tx, state| starknet::execute_transaction(tx, state, &settings));
+        assert_eq!(receipts(&parallel), receipts(&sequential), "{workers} workers");
+        assert_eq!(parallel.state_diff, sequential.state_diff, "{workers} workers");
+    }
+
+    // The transaction with a nonce from the future fails, the one calling nothing is reverted
+    let (failed, reverted) = (&sequential.results[24], &sequential.results[25]);
+    assert!(failed.is_err());
+    assert!(reverted.as_ref().unwrap().revert_error.is_some());
+    assert!(sequential.results[..24].iter().all(|result| result.as_ref().unwrap().revert_error.is_none()));
+
+    let balance = get_storage_var_address("balance", &[]);
+    let shared_increments: u64 =
+        (0..3).flat_map(|nonce| (0..8).filter(move |index| (index + nonce) % 2 == 0)).map(|index| index + 1).sum();
+    assert_eq!(sequential.state_diff.storage[&(address(SHARED_COUNTER), balance)], StarkFelt::from(shared_increments));
+    assert_eq!(sequential.state_diff.nonces[&address(account(0))], Nonce(StarkFelt::from(3_u64)));
+    // Reverted transactions still increment the nonce
+    assert_eq!(sequential.state_diff.nonces[&address(account(1))], Nonce(StarkFelt::from(4_u64)));
+}
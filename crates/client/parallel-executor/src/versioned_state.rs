@@ -0,0 +1,253 @@
+//! Multi-version state of the block being executed.
+//!
+//! Every key maps to the values written by each transaction of the block. A transaction reads the
+//! value written by the closest transaction before it, or the base state if there is none, and
+//! remembers the [`Version`] it read to be validated later on.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+
+use blockifier::execution::contract_class::ContractClass;
+use blockifier::state::cached_state::CachedState;
+use blockifier::state::state_api::{StateReader, StateResult};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+/// Index of a transaction in the block.
+pub type TxIndex = usize;
+/// Number of previous executions of a transaction.
+pub type Incarnation = usize;
+
+/// Where a value has been read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Version {
+    /// The state the block is built on.
+    Base,
+    /// The given execution of a transaction of the block.
+    Tx(TxIndex, Incarnation),
+}
+
+/// A key of the Starknet state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StateKey {
+    Storage(ContractAddress, StorageKey),
+    Nonce(ContractAddress),
+    ClassHash(ContractAddress),
+    CompiledClassHash(ClassHash),
+    ContractClass(ClassHash),
+}
+
+/// The values written to a key by each transaction.
+struct VersionedMap<K, V>(HashMap<K, BTreeMap<TxIndex, (Incarnation, V)>>);
+
+impl<K: Eq + Hash, V> Default for VersionedMap<K, V> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<K: Eq + Hash, V> VersionedMap<K, V> {
+    /// The last value written to `key` by a transaction before `tx`.
+    fn read(&self, key: &K, tx: TxIndex) -> Option<(Version, &V)> {
+        let (&writer, (incarnation, value)) = self.0.get(key)?.range(..tx).next_back()?;
+        Some((Version::Tx(writer, *incarnation), value))
+    }
+
+    fn write(&mut self, key: K, tx: TxIndex, incarnation: Incarnation, value: V) {
+        self.0.entry(key).or_default().insert(tx, (incarnation, value));
+    }
+
+    fn remove(&mut self, key: &K, tx: TxIndex) {
+        if let Some(versions) = self.0.get_mut(key) {
+            versions.remove(&tx);
+        }
+    }
+}
+
+/// The state updates of a transaction.
+#[derive(Clone, Debug, Default)]
+pub struct WriteSet {
+    pub storage: HashMap<(ContractAddress, StorageKey), StarkFelt>,
+    pub nonces: HashMap<ContractAddress, Nonce>,
+    pub class_hashes: HashMap<ContractAddress, ClassHash>,
+    pub compiled_class_hashes: HashMap<ClassHash, CompiledClassHash>,
+    pub contract_classes: HashMap<ClassHash, ContractClass>,
+}
+
+impl WriteSet {
+    /// Take the updates of a transaction executed on a [`VersionedState`].
+    pub(crate) fn from_cached_state<S: StateReader>(
+        state: &mut CachedState<VersionedState<'_, S>>,
+    ) -> StateResult<Self> {
+        let changes = state.get_actual_state_changes()?;
+        // The classes read by the transaction are cached along the declared ones
+        let read_classes = state.state.read_classes.take();
+        let contract_classes = state
+            .class_hash_to_class
+            .take()
+            .into_iter()
+            .filter(|(class_hash, _)| !read_classes.contains(class_hash))
+            .collect();
+
+        Ok(Self {
+            storage: changes.storage_updates.into_iter().collect(),
+            nonces: changes.nonce_updates.into_iter().collect(),
+            class_hashes: changes.class_hash_updates.into_iter().collect(),
+            compiled_class_hashes: changes.compiled_class_hash_updates.into_iter().collect(),
+            contract_classes,
+        })
+    }
+
+    fn keys(&self) -> impl Iterator<Item = StateKey> + '_ {
+        let storage = self.storage.keys().map(|&(address, key)| StateKey::Storage(address, key));
+        let nonces = self.nonces.keys().map(|&address| StateKey::Nonce(address));
+        let class_hashes = self.class_hashes.keys().map(|&address| StateKey::ClassHash(address));
+        let compiled_class_hashes =
+            self.compiled_class_hashes.keys().map(|&class_hash| StateKey::CompiledClassHash(class_hash));
+        let contract_classes = self.contract_classes.keys().map(|&class_hash| StateKey::ContractClass(class_hash));
+        storage.chain(nonces).chain(class_hashes).chain(compiled_class_hashes).chain(contract_classes)
+    }
+}
+
+/// The values written by the transactions of the block.
+#[derive(Default)]
+pub struct MultiVersionState {
+    storage: VersionedMap<(ContractAddress, StorageKey), StarkFelt>,
+    nonces: VersionedMap<ContractAddress, Nonce>,
+    class_hashes: VersionedMap<ContractAddress, ClassHash>,
+    compiled_class_hashes: VersionedMap<ClassHash, CompiledClassHash>,
+    contract_classes: VersionedMap<ClassHash, ContractClass>,
+    /// Keys written by the last execution of each transaction.
+    written_keys: HashMap<TxIndex, Vec<StateKey>>,
+}
+
+impl MultiVersionState {
+    /// Replace the writes of the previous execution of `tx` with `writes`.
+    pub fn apply(&mut self, tx: TxIndex, incarnation: Incarnation, writes: &WriteSet) {
+        for key in self.written_keys.remove(&tx).unwrap_or_default() {
+            match key {
+                StateKey::Storage(address, key) => self.storage.remove(&(address, key), tx),
+                StateKey::Nonce(address) => self.nonces.remove(&address, tx),
+                StateKey::ClassHash(address) => self.class_hashes.remove(&address, tx),
+                StateKey::CompiledClassHash(class_hash) => self.compiled_class_hashes.remove(&class_hash, tx),
+                StateKey::ContractClass(class_hash) => self.contract_classes.remove(&class_hash, tx),
+            }
+        }
+
+        for (&key, &value) in &writes.storage {
+            self.storage.write(key, tx, incarnation, value);
+        }
+        for (&address, &nonce) in &writes.nonces {
+            self.nonces.write(address, tx, incarnation, nonce);
+        }
+        for (&address, &class_hash) in &writes.class_hashes {
+            self.class_hashes.write(address, tx, incarnation, class_hash);
+        }
+        for (&class_hash, &compiled_class_hash) in &writes.compiled_class_hashes {
+            self.compiled_class_hashes.write(class_hash, tx, incarnation, compiled_class_hash);
+        }
+        for (&class_hash, contract_class) in &writes.contract_classes {
+            self.contract_classes.write(class_hash, tx, incarnation, contract_class.clone());
+        }
+        self.written_keys.insert(tx, writes.keys().collect());
+    }
+
+    /// The version of `key` that `tx` reads.
+    pub fn version(&self, key: &StateKey, tx: TxIndex) -> Version {
+        let version = match key {
+            StateKey::Storage(address, key) => self.storage.read(&(*address, *key), tx).map(|(version, _)| version),
+            StateKey::Nonce(address) => self.nonces.read(address, tx).map(|(version, _)| version),
+            StateKey::ClassHash(address) => self.class_hashes.read(address, tx).map(|(version, _)| version),
+            StateKey::CompiledClassHash(class_hash) => {
+                self.compiled_class_hashes.read(class_hash, tx).map(|(version, _)| version)
+            }
+            StateKey::ContractClass(class_hash) => {
+                self.contract_classes.read(class_hash, tx).map(|(version, _)| version)
+            }
+        };
+        version.unwrap_or(Version::Base)
+    }
+
+    /// Returns `true` if `tx` would still read the same versions.
+    pub fn validate(&self, tx: TxIndex, reads: &[(StateKey, Version)]) -> bool {
+        reads.iter().all(|(key, version)| self.version(key, tx) == *version)
+    }
+}
+
+/// The state as seen by a transaction of the block, recording its reads.
+pub struct VersionedState<'a, S> {
+    base: &'a S,
+    versions: &'a MultiVersionState,
+    tx: TxIndex,
+    reads: RefCell<Vec<(StateKey, Version)>>,
+    read_classes: RefCell<HashSet<ClassHash>>,
+}
+
+impl<'a, S: StateReader> VersionedState<'a, S> {
+    pub(crate) fn new(base: &'a S, versions: &'a MultiVersionState, tx: TxIndex) -> Self {
+        Self { base, versions, tx, reads: Default::default(), read_classes: Default::default() }
+    }
+
+    /// The keys read by the transaction, along the version read.
+    pub(crate) fn take_reads(&self) -> Vec<(StateKey, Version)> {
+        self.reads.take()
+    }
+
+    fn read<V: Clone>(
+        &self,
+        key: StateKey,
+        versioned: Option<(Version, &V)>,
+        base: impl FnOnce() -> StateResult<V>,
+    ) -> StateResult<V> {
+        let (version, value) = match versioned {
+            Some((version, value)) => (version, Ok(value.clone())),
+            None => (Version::Base, base()),
+        };
+        self.reads.borrow_mut().push((key, version));
+        value
+    }
+}
+
+impl<S: StateReader> StateReader for VersionedState<'_, S> {
+    fn get_storage_at(&self, contract_address: ContractAddress, key: StorageKey) -> StateResult<StarkFelt> {
+        self.read(
+            StateKey::Storage(contract_address, key),
+            self.versions.storage.read(&(contract_address, key), self.tx),
+            || self.base.get_storage_at(contract_address, key),
+        )
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.read(StateKey::Nonce(contract_address), self.versions.nonces.read(&contract_address, self.tx), || {
+            self.base.get_nonce_at(contract_address)
+        })
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.read(
+            StateKey::ClassHash(contract_address),
+            self.versions.class_hashes.read(&contract_address, self.tx),
+            || self.base.get_class_hash_at(contract_address),
+        )
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        let contract_class = self.read(
+            StateKey::ContractClass(class_hash),
+            self.versions.contract_classes.read(&class_hash, self.tx),
+            || self.base.get_compiled_contract_class(class_hash),
+        )?;
+        self.read_classes.borrow_mut().insert(class_hash);
+        Ok(contract_class)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.read(
+            StateKey::CompiledClassHash(class_hash),
+            self.versions.compiled_class_hashes.read(&class_hash, self.tx),
+            || self.base.get_compiled_class_hash(class_hash),
+        )
+    }
+}
@@ -22,9 +22,9 @@ use starknet_core::types::{
     BlockHashAndNumber, BlockId, BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
     BroadcastedInvokeTransaction, BroadcastedTransaction, ContractClass, DeclareTransactionResult,
     DeployAccountTransactionResult, EventFilterWithPage, EventsPage, FeeEstimate, FieldElement, FunctionCall,
-    InvokeTransactionResult, MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingStateUpdate,
-    MaybePendingTransactionReceipt, MsgFromL1, SimulatedTransaction, SimulationFlag, SimulationFlagForEstimateFee,
-    SyncStatusType, Transaction, TransactionTrace, TransactionTraceWithHash,
+    FunctionInvocation, InvokeTransactionResult, MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs,
+    MaybePendingStateUpdate, MaybePendingTransactionReceipt, MsgFromL1, SimulatedTransaction, SimulationFlag,
+    SimulationFlagForEstimateFee, SyncStatusType, Transaction, TransactionTrace, TransactionTraceWithHash,
 };
 
 #[serde_as]
@@ -37,6 +37,29 @@ pub struct PredeployedAccountWithBalance {
     pub balance: FieldElement,
 }
 
+/// The paymaster which sponsored a transaction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaymasterInfo {
+    /// Address of the paymaster, charged the fee instead of the sender
+    pub paymaster_address: FieldElement,
+    /// Call to the paymaster validation entry point, only part of simulations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validate_invocation: Option<FunctionInvocation>,
+}
+
+/// A Starknet RPC result, extended with the paymaster of a sponsored transaction.
+///
+/// Only returned by the `madara_*` methods, the `starknet_*` ones stick to the specification. The
+/// paymaster is serialized along the fields of the result, and left out for the transactions paid
+/// by their sender.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WithPaymaster<T> {
+    #[serde(flatten)]
+    pub result: T,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<PaymasterInfo>,
+}
+
 /// Madara rpc interface for additional features.
 #[rpc(server, namespace = "madara")]
 pub trait MadaraRpcApi: StarknetReadRpcApi {
@@ -49,6 +72,29 @@ pub trait MadaraRpcApi: StarknetReadRpcApi {
         &self,
         params: BroadcastedDeclareTransactionV0,
     ) -> RpcResult<DeclareTransactionResult>;
+
+    /// Same as `starknet_estimateFee`, along with the paymaster of the sponsored transactions.
+    #[method(name = "estimateFeeWithPaymaster")]
+    async fn estimate_fee_with_paymaster(
+        &self,
+        request: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlagForEstimateFee>,
+        block_id: BlockId,
+    ) -> RpcResult<Vec<WithPaymaster<FeeEstimate>>>;
+
+    /// Same as `starknet_simulateTransactions`, along with the paymaster of the sponsored
+    /// transactions and the call to its validation entry point.
+    #[method(name = "simulateTransactionsWithPaymaster")]
+    async fn simulate_transactions_with_paymaster(
+        &self,
+        block_id: BlockId,
+        transactions: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> RpcResult<Vec<WithPaymaster<SimulatedTransaction>>>;
+
+    /// Returns the paymaster which sponsored a transaction, `null` if it was paid by its sender.
+    #[method(name = "getTransactionPaymaster")]
+    fn get_transaction_paymaster(&self, transaction_hash: FieldElement) -> RpcResult<Option<PaymasterInfo>>;
 }
 
 /// Starknet write rpc interface.
@@ -1,4 +1,4 @@
-use starknet_core::types::BlockTag;
+use starknet_core::types::{BlockTag, PriceUnit};
 
 use super::*;
 
@@ -38,3 +38,33 @@ fn block_id_deserialization() {
         BlockId::Number(42)
     );
 }
+
+#[test]
+fn paymaster_is_serialized_along_the_result() {
+    let fee_estimate = FeeEstimate {
+        gas_consumed: FieldElement::from(2u8),
+        gas_price: FieldElement::from(3u8),
+        overall_fee: FieldElement::from(6u8),
+        unit: PriceUnit::Fri,
+    };
+    let sponsored = WithPaymaster {
+        result: fee_estimate.clone(),
+        paymaster: Some(PaymasterInfo {
+            paymaster_address: FieldElement::from_hex_be("0x42").unwrap(),
+            validate_invocation: None,
+        }),
+    };
+    assert_eq!(
+        serde_json::to_value(&sponsored).unwrap(),
+        serde_json::json!({
+            "gas_consumed": "0x2",
+            "gas_price": "0x3",
+            "overall_fee": "0x6",
+            "unit": "FRI",
+            "paymaster": { "paymaster_address": "0x42" }
+        })
+    );
+
+    let not_sponsored = WithPaymaster { result: fee_estimate.clone(), paymaster: None };
+    assert_eq!(serde_json::to_value(&not_sponsored).unwrap(), serde_json::to_value(&fee_estimate).unwrap());
+}
@@ -38,6 +38,9 @@ pub enum StarknetRpcApiError {
     ClassAlreadyDeclared,
     #[error("Account validation failed")]
     ValidationFailure,
+    /// Rejected by the transaction filters of the chain, not part of the spec.
+    #[error("Transaction rejected: {0}")]
+    TransactionRejected(String),
     #[error("The transaction version is not supported")]
     UnsupportedTxVersion,
     #[error("Internal server error")]
@@ -76,6 +79,7 @@ impl From<StarknetRpcApiError> for jsonrpsee::core::Error {
             StarknetRpcApiError::InternalServerError => 500,
             StarknetRpcApiError::UnimplementedMethod => 501,
             StarknetRpcApiError::ProofLimitExceeded => 10000,
+            StarknetRpcApiError::TransactionRejected(_) => 10001,
         };
 
         let data = match &err {
@@ -112,6 +116,7 @@ impl From<SimulationError> for StarknetRpcApiError {
             SimulationError::TransactionExecutionFailed(e) => StarknetRpcApiError::ContractError(e.into()),
             SimulationError::MissingL1GasUsage | SimulationError::StateDiff => StarknetRpcApiError::InternalServerError,
             SimulationError::EstimateFeeFailed(_) => StarknetRpcApiError::InternalServerError,
+            SimulationError::TransactionRejectedByFilter(reason) => StarknetRpcApiError::TransactionRejected(reason),
         }
     }
 }
@@ -17,16 +17,17 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 
 use blockifier::transaction::account_transaction::AccountTransaction;
-use blockifier::transaction::objects::{ResourcesMapping, TransactionExecutionInfo};
+use blockifier::transaction::objects::{FeeType, HasRelatedFeeType, ResourcesMapping, TransactionExecutionInfo};
 use blockifier::transaction::transactions::{DeclareTransaction, L1HandlerTransaction};
 use errors::StarknetRpcApiError;
 use jsonrpsee::core::{async_trait, RpcResult};
-use log::error;
+use log::{error, warn};
+use mc_db::{FeeUnit, StoredTransactionReceipt, TransactionReceiptKind};
 use mc_genesis_data_provider::GenesisProvider;
 pub use mc_rpc_core::utils::*;
 pub use mc_rpc_core::{
-    Felt, MadaraRpcApiServer, PredeployedAccountWithBalance, StarknetReadRpcApiServer, StarknetTraceRpcApiServer,
-    StarknetWriteRpcApiServer,
+    Felt, MadaraRpcApiServer, PaymasterInfo, PredeployedAccountWithBalance, StarknetReadRpcApiServer,
+    StarknetTraceRpcApiServer, StarknetWriteRpcApiServer, WithPaymaster,
 };
 use mc_storage::OverrideHandle;
 use mp_block::BlockTransactions;
@@ -34,6 +35,7 @@ use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use mp_simulations::SimulationFlags;
 use mp_transactions::compute_hash::ComputeTransactionHash;
+use mp_transactions::execution::{Paymaster, TransactionRejection};
 use mp_transactions::from_broadcasted_transactions::{
     try_account_tx_from_broadcasted_tx, try_declare_tx_from_broadcasted_declare_tx,
     try_deploy_tx_from_broadcasted_deploy_tx, try_invoke_tx_from_broadcasted_invoke_tx,
@@ -53,6 +55,7 @@ use sp_blockchain::HeaderBackend;
 use sp_core::H256;
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use sp_runtime::transaction_validity::InvalidTransaction;
+use starknet_api::block::BlockHash;
 use starknet_api::core::{ClassHash, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::transaction::{Calldata, Fee, TransactionHash, TransactionVersion};
@@ -182,6 +185,29 @@ where
         .ok_or(StarknetRpcApiError::BlockNotFound)
     }
 
+    /// Returns the substrate block hash corresponding to the given Starknet block id, making sure
+    /// the state of this block has not been pruned
+    fn substrate_block_hash_with_state(&self, block_id: BlockId) -> Result<B::Hash, StarknetRpcApiError> {
+        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id)?;
+
+        let block_number = self
+            .client
+            .number(substrate_block_hash)
+            .map_err(|_| StarknetRpcApiError::BlockNotFound)?
+            .ok_or(StarknetRpcApiError::BlockNotFound)?;
+        let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(block_number);
+        let finalized_block_number =
+            UniqueSaturatedInto::<u64>::unique_saturated_into(self.client.info().finalized_number);
+
+        let storage_mode = self.backend.storage_mode();
+        if !storage_mode.has_state_at(block_number, finalized_block_number) {
+            error!("The state of block {block_number} has been pruned, the node is running in {storage_mode} mode");
+            return Err(StarknetRpcApiError::BlockNotFound);
+        }
+
+        Ok(substrate_block_hash)
+    }
+
     /// Helper function to get the substrate block number from a Starknet block id
     ///
     /// # Arguments
@@ -289,10 +315,14 @@ where
         &self,
         declare_transaction: BroadcastedDeclareTransaction,
     ) -> RpcResult<DeclareTransactionResult> {
-        let opt_sierra_contract_class = if let BroadcastedDeclareTransaction::V2(ref tx) = declare_transaction {
-            Some(flattened_sierra_to_sierra_contract_class(tx.contract_class.clone()))
-        } else {
-            None
+        let opt_sierra_contract_class = match declare_transaction {
+            BroadcastedDeclareTransaction::V1(_) => None,
+            BroadcastedDeclareTransaction::V2(ref tx) => {
+                Some(flattened_sierra_to_sierra_contract_class(tx.contract_class.clone()))
+            }
+            BroadcastedDeclareTransaction::V3(ref tx) => {
+                Some(flattened_sierra_to_sierra_contract_class(tx.contract_class.clone()))
+            }
         };
 
         let chain_id = Felt252Wrapper(self.chain_id()?.0);
@@ -505,6 +535,9 @@ where
             })?
             .ok_or(StarknetRpcApiError::TxnHashNotFound)?;
 
+        let starknet_block = get_block_by_block_hash(self.client.as_ref(), substrate_block_hash)?;
+        let finality_status = self.finality_status(starknet_block.header().block_number)?;
+
         let execution_status = {
             let revert_error = self.get_tx_execution_outcome(substrate_block_hash, transaction_hash)?;
 
@@ -515,7 +548,7 @@ where
             }
         };
 
-        Ok(TransactionStatus { finality_status: TransactionFinalityStatus::AcceptedOnL2, execution_status })
+        Ok(TransactionStatus { finality_status, execution_status })
     }
 
     /// Get the value of the storage at the given address and key.
@@ -548,7 +581,7 @@ where
     /// * `STORAGE_KEY_NOT_FOUND` - If the specified storage key does not exist within the given
     ///   contract.
     fn get_storage_at(&self, contract_address: FieldElement, key: FieldElement, block_id: BlockId) -> RpcResult<Felt> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+        let substrate_block_hash = self.substrate_block_hash_with_state(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
         })?;
@@ -556,6 +589,28 @@ where
         let contract_address = Felt252Wrapper(contract_address).into();
         let key = Felt252Wrapper(key).into();
 
+        // In archive mode, the storage history of the finalized blocks is indexed, up to the first
+        // block whose storage diffs are still to be computed by the indexing backfill
+        let canonical_block_number = self
+            .client
+            .number(substrate_block_hash)
+            .ok()
+            .flatten()
+            .filter(|block_number| self.client.hash(*block_number).ok().flatten() == Some(substrate_block_hash));
+        if let (true, Some(block_number)) = (self.backend.storage_mode().is_archive(), canonical_block_number) {
+            let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(block_number);
+            let value =
+                self.backend.historical_storage().get_storage_at(&contract_address, &key, block_number).map_err(
+                    |e| {
+                        error!("Failed to read storage history from db backend: {e}");
+                        StarknetRpcApiError::InternalServerError
+                    },
+                )?;
+            if let Some(value) = value {
+                return Ok(Felt(Felt252Wrapper::from(value).into()));
+            }
+        }
+
         let value = self
             .overrides
             .for_block_hash(self.client.as_ref(), substrate_block_hash)
@@ -590,7 +645,7 @@ where
     /// * `CONTRACT_ERROR` - If there is an error with the contract or the function call.
     /// * `BLOCK_NOT_FOUND` - If the specified block does not exist in the blockchain.
     fn call(&self, request: FunctionCall, block_id: BlockId) -> RpcResult<Vec<String>> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+        let substrate_block_hash = self.substrate_block_hash_with_state(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
         })?;
@@ -626,7 +681,7 @@ where
     /// * `BLOCK_NOT_FOUND` - If the specified block does not exist in the blockchain.
     /// * `CONTRACT_NOT_FOUND` - If the specified contract address does not exist.
     fn get_class_at(&self, block_id: BlockId, contract_address: FieldElement) -> RpcResult<ContractClass> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+        let substrate_block_hash = self.substrate_block_hash_with_state(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
         })?;
@@ -660,7 +715,7 @@ where
     ///
     /// * `class_hash` - The class hash of the given contract
     fn get_class_hash_at(&self, block_id: BlockId, contract_address: FieldElement) -> RpcResult<Felt> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+        let substrate_block_hash = self.substrate_block_hash_with_state(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
         })?;
@@ -763,7 +818,7 @@ where
     /// Returns the contract class definition if found. In case of an error, returns a
     /// `StarknetRpcApiError` indicating either `BlockNotFound` or `ClassHashNotFound`.
     fn get_class(&self, block_id: BlockId, class_hash: FieldElement) -> RpcResult<ContractClass> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+        let substrate_block_hash = self.substrate_block_hash_with_state(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
         })?;
@@ -814,19 +869,7 @@ where
 
         let transaction_hashes =
             starknet_block.transactions_hashes().map(|txh| Felt252Wrapper::from(*txh).into()).collect();
-        let block_status = match self.backend.messaging().last_synced_l1_block_with_event() {
-            Ok(l1_block) => {
-                if l1_block.block_number >= starknet_block.header().block_number {
-                    BlockStatus::AcceptedOnL1
-                } else {
-                    BlockStatus::AcceptedOnL2
-                }
-            }
-            Err(e) => {
-                error!("Failed to get last synced l1 block, error: {e}");
-                Err(StarknetRpcApiError::InternalServerError)?
-            }
-        };
+        let block_status = self.block_status(starknet_block.header().block_number)?;
 
         let parent_blockhash = starknet_block.header().parent_block_hash;
         let block_with_tx_hashes = BlockWithTxHashes {
@@ -835,7 +878,7 @@ where
             block_hash: block_hash.into(),
             parent_hash: Felt252Wrapper::from(parent_blockhash).into(),
             block_number: starknet_block.header().block_number,
-            new_root: Felt252Wrapper::from(self.backend.temporary_global_state_root_getter()).into(),
+            new_root: Felt252Wrapper::from(starknet_block.header().global_state_root).into(),
             timestamp: starknet_block.header().block_timestamp,
             sequencer_address: Felt252Wrapper::from(starknet_block.header().sequencer_address).into(),
             l1_gas_price: self.get_current_resource_price()?,
@@ -862,7 +905,7 @@ where
     /// `BLOCK_NOT_FOUND` or `CONTRACT_NOT_FOUND`, returns a `StarknetRpcApiError` indicating the
     /// specific issue.
     fn get_nonce(&self, block_id: BlockId, contract_address: FieldElement) -> RpcResult<Felt> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+        let substrate_block_hash = self.substrate_block_hash_with_state(block_id).map_err(|e| {
             error!("'{e}'");
             StarknetRpcApiError::BlockNotFound
         })?;
@@ -918,25 +961,9 @@ where
         simulation_flags: Vec<SimulationFlagForEstimateFee>,
         block_id: BlockId,
     ) -> RpcResult<Vec<FeeEstimate>> {
-        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
-            error!("'{e}'");
-            StarknetRpcApiError::BlockNotFound
-        })?;
-        let chain_id = Felt252Wrapper(self.chain_id()?.0);
+        let fee_estimates = self.broadcasted_fee_estimates(request, simulation_flags, block_id)?;
 
-        let transactions = request
-            .into_iter()
-            .map(|tx| try_account_tx_from_broadcasted_tx(tx, chain_id))
-            .collect::<Result<Vec<AccountTransaction>, _>>()
-            .map_err(|e| {
-                error!("Failed to convert BroadcastedTransaction to AccountTransaction: {e}");
-                StarknetRpcApiError::InternalServerError
-            })?;
-
-        let fee_estimates =
-            self.estimate_fee(substrate_block_hash, transactions, SimulationFlags::from(simulation_flags))?;
-
-        Ok(fee_estimates)
+        Ok(fee_estimates.into_iter().map(|estimate| estimate.result).collect())
     }
 
     /// Estimate the L2 fee of a message sent on L1
@@ -1057,12 +1084,11 @@ where
         let transactions = starknet_block.transactions().iter().map(|tx| to_starknet_core_tx(tx.clone())).collect();
 
         let block_with_txs = BlockWithTxs {
-            // TODO: Get status from block
-            status: BlockStatus::AcceptedOnL2,
+            status: self.block_status(starknet_block.header().block_number)?,
             block_hash: block_hash.into(),
             parent_hash: Felt252Wrapper::from(starknet_block.header().parent_block_hash).into(),
             block_number: starknet_block.header().block_number,
-            new_root: Felt252Wrapper::from(self.backend.temporary_global_state_root_getter()).into(),
+            new_root: Felt252Wrapper::from(starknet_block.header().global_state_root).into(),
             timestamp: starknet_block.header().block_timestamp,
             sequencer_address: Felt252Wrapper::from(starknet_block.header().sequencer_address).into(),
             transactions,
@@ -1102,7 +1128,8 @@ where
                 nonces: Vec::new(),
             };
 
-            let old_root = Felt252Wrapper::from(self.backend.temporary_global_state_root_getter()).into();
+            let latest_block = get_block_by_block_hash(self.client.as_ref(), self.client.info().best_hash)?;
+            let old_root = Felt252Wrapper::from(latest_block.header().global_state_root).into();
             let pending_state_update = PendingStateUpdate { old_root, state_diff };
 
             return Ok(MaybePendingStateUpdate::PendingUpdate(pending_state_update));
@@ -1115,16 +1142,17 @@ where
 
         let starknet_block = get_block_by_block_hash(self.client.as_ref(), substrate_block_hash)?;
 
+        let block_transactions = starknet_block.transactions();
+
+        let previous_block_substrate_hash = get_previous_block_substrate_hash(self, substrate_block_hash)?;
+
         let old_root = if starknet_block.header().block_number > 0 {
-            Felt252Wrapper::from(self.backend.temporary_global_state_root_getter()).into()
+            let previous_block = get_block_by_block_hash(self.client.as_ref(), previous_block_substrate_hash)?;
+            Felt252Wrapper::from(previous_block.header().global_state_root).into()
         } else {
             FieldElement::default()
         };
 
-        let block_transactions = starknet_block.transactions();
-
-        let previous_block_substrate_hash = get_previous_block_substrate_hash(self, substrate_block_hash)?;
-
         let state_diff = self.get_transaction_re_execution_state_diff(
             previous_block_substrate_hash,
             vec![],
@@ -1133,7 +1161,7 @@ where
 
         let state_update = StateUpdate {
             block_hash: starknet_block.header().hash().into(),
-            new_root: Felt252Wrapper::from(self.backend.temporary_global_state_root_getter()).into(),
+            new_root: Felt252Wrapper::from(starknet_block.header().global_state_root).into(),
             old_root,
             state_diff,
         };
@@ -1257,11 +1285,20 @@ where
         let starknet_block = get_block_by_block_hash(self.client.as_ref(), substrate_block_hash)?;
 
         let searched_tx_hash: TransactionHash = Felt252Wrapper::from(transaction_hash).into();
-        let find_tx = starknet_block
-            .transactions()
-            .iter()
-            .find(|tx| get_transaction_hash(tx) == &searched_tx_hash)
-            .map(|tx| to_starknet_core_tx(tx.clone()));
+        let transaction_index = self
+            .backend
+            .receipts()
+            .get_receipt(searched_tx_hash)
+            .map_err(|e| {
+                error!("Failed to read transaction receipt from db backend: {e}");
+                StarknetRpcApiError::InternalServerError
+            })?
+            .map(|receipt| receipt.transaction_index as usize);
+        let find_tx = match transaction_index {
+            Some(index) => starknet_block.transactions().get(index),
+            None => starknet_block.transactions().iter().find(|tx| get_transaction_hash(tx) == &searched_tx_hash),
+        }
+        .map(|tx| to_starknet_core_tx(tx.clone()));
 
         find_tx.ok_or(StarknetRpcApiError::TxnHashNotFound.into())
     }
@@ -1294,18 +1331,33 @@ where
     ) -> RpcResult<MaybePendingTransactionReceipt> {
         let transaction_hash = Felt252Wrapper::from(transaction_hash).into();
 
-        let receipt = match self.backend.mapping().block_hash_from_transaction_hash(transaction_hash).map_err(|e| {
-            error!("Failed to interact with db backend error: {e}");
+        let stored_receipt = self.backend.receipts().get_receipt(transaction_hash).map_err(|e| {
+            error!("Failed to read transaction receipt from db backend: {e}");
             StarknetRpcApiError::InternalServerError
-        })? {
-            Some(substrate_block_hash) => self.prepare_tx_receipt(transaction_hash, substrate_block_hash).await?,
+        })?;
+        let Some(stored_receipt) = stored_receipt else {
+            let is_synced = self.backend.mapping().block_hash_from_transaction_hash(transaction_hash).map_err(|e| {
+                error!("Failed to interact with db backend error: {e}");
+                StarknetRpcApiError::InternalServerError
+            })?;
+            if is_synced.is_some() {
+                // The receipts of the blocks synced before they were persisted are indexed by the
+                // mapping sync worker, see `mc_db::IndexingBackfill`
+                error!("The receipt of transaction {transaction_hash} is not indexed yet");
+                return Err(StarknetRpcApiError::InternalServerError.into());
+            }
             // Try to find pending Tx
-            None => self.get_pending_transaction_receipt(transaction_hash).await.map_err(|e| {
+            return Ok(self.get_pending_transaction_receipt(transaction_hash).await.map_err(|e| {
                 error!("Failed to find pending tx with hash: {transaction_hash}: {e}");
                 StarknetRpcApiError::TxnHashNotFound
-            })?,
+            })?);
         };
-        Ok(receipt)
+
+        let finality_status = self.finality_status(stored_receipt.block_number)?;
+        Ok(MaybePendingTransactionReceipt::Receipt(stored_receipt_to_transaction_receipt(
+            stored_receipt,
+            finality_status,
+        )))
     }
 }
 
@@ -1322,6 +1374,120 @@ where
     G: GenesisProvider + Send + Sync + 'static,
     H: HasherT + Send + Sync + 'static,
 {
+    /// Estimates the fee of the `request` transactions, along with the paymaster of the sponsored
+    /// ones
+    fn broadcasted_fee_estimates(
+        &self,
+        request: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlagForEstimateFee>,
+        block_id: BlockId,
+    ) -> RpcResult<Vec<WithPaymaster<FeeEstimate>>> {
+        let substrate_block_hash = self.substrate_block_hash_from_starknet_block(block_id).map_err(|e| {
+            error!("'{e}'");
+            StarknetRpcApiError::BlockNotFound
+        })?;
+        let chain_id = Felt252Wrapper(self.chain_id()?.0);
+
+        let transactions = request
+            .into_iter()
+            .map(|tx| try_account_tx_from_broadcasted_tx(tx, chain_id))
+            .collect::<Result<Vec<AccountTransaction>, _>>()
+            .map_err(|e| {
+                error!("Failed to convert BroadcastedTransaction to AccountTransaction: {e}");
+                StarknetRpcApiError::InternalServerError
+            })?;
+
+        let fee_estimates =
+            self.estimate_fee(substrate_block_hash, transactions, SimulationFlags::from(simulation_flags))?;
+
+        Ok(fee_estimates)
+    }
+
+    /// The paymaster which sponsored `transaction_hash`, as recorded in its receipt when it is part
+    /// of a block, or the one it would be executed with when it is still pending.
+    fn transaction_paymaster(&self, transaction_hash: TransactionHash) -> RpcResult<Option<PaymasterInfo>> {
+        let stored_receipt = self.backend.receipts().get_receipt(transaction_hash).map_err(|e| {
+            error!("Failed to read transaction receipt from db backend: {e}");
+            StarknetRpcApiError::InternalServerError
+        })?;
+        if let Some(stored_receipt) = stored_receipt {
+            return Ok(stored_receipt.paymaster.map(|address| PaymasterInfo {
+                paymaster_address: Felt252Wrapper::from(address).into(),
+                validate_invocation: None,
+            }));
+        }
+
+        let is_synced = self.backend.mapping().block_hash_from_transaction_hash(transaction_hash).map_err(|e| {
+            error!("Failed to interact with db backend error: {e}");
+            StarknetRpcApiError::InternalServerError
+        })?;
+        if is_synced.is_some() {
+            error!("The receipt of transaction {transaction_hash} is not indexed yet");
+            return Err(StarknetRpcApiError::InternalServerError.into());
+        }
+
+        let pending_tx = self.find_pending_tx(transaction_hash)?.ok_or(StarknetRpcApiError::TxnHashNotFound)?;
+        let blockifier::transaction::transaction_execution::Transaction::AccountTransaction(account_tx) = pending_tx
+        else {
+            return Ok(None);
+        };
+        let config = self.paymaster_config(self.get_best_block_hash())?;
+
+        Ok(Paymaster::from_account_transaction(&account_tx, config.as_ref()).ok().flatten().map(|paymaster| {
+            PaymasterInfo {
+                paymaster_address: Felt252Wrapper::from(paymaster.address).into(),
+                validate_invocation: None,
+            }
+        }))
+    }
+
+    /// Whether the Starknet block `block_number` is at or below the last block accepted on L1, this
+    /// one being the block the local chain has at its height.
+    fn is_accepted_on_l1(&self, block_number: u64) -> Result<bool, StarknetRpcApiError> {
+        let last_accepted = self.backend.meta().last_l1_accepted_block().map_err(|e| {
+            error!("Failed to get the last block accepted on L1: {e}");
+            StarknetRpcApiError::InternalServerError
+        })?;
+        let Some(last_accepted) = last_accepted.filter(|last_accepted| block_number <= last_accepted.block_number)
+        else {
+            return Ok(false);
+        };
+
+        // The blocks of a chain which diverged from the one settled on L1 aren't accepted, nor the
+        // ones of a node which hasn't synced the accepted block yet. The mapping tells whether the
+        // accepted block is the canonical one at its height, without reading the block.
+        let Ok(substrate_block_hash) =
+            self.substrate_block_hash_from_starknet_block(BlockId::Number(last_accepted.block_number))
+        else {
+            return Ok(false);
+        };
+        let mapped_blocks = self.backend.mapping().block_hash(BlockHash(last_accepted.block_hash)).map_err(|e| {
+            error!("Failed to interact with db backend error: {e}");
+            StarknetRpcApiError::InternalServerError
+        })?;
+        if !mapped_blocks.is_some_and(|mapped_blocks| mapped_blocks.contains(&substrate_block_hash)) {
+            warn!(
+                "The Starknet block {} accepted on L1, with hash {}, is not the one of the local chain",
+                last_accepted.block_number, last_accepted.block_hash
+            );
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    fn block_status(&self, block_number: u64) -> Result<BlockStatus, StarknetRpcApiError> {
+        Ok(if self.is_accepted_on_l1(block_number)? { BlockStatus::AcceptedOnL1 } else { BlockStatus::AcceptedOnL2 })
+    }
+
+    fn finality_status(&self, block_number: u64) -> Result<TransactionFinalityStatus, StarknetRpcApiError> {
+        Ok(if self.is_accepted_on_l1(block_number)? {
+            TransactionFinalityStatus::AcceptedOnL1
+        } else {
+            TransactionFinalityStatus::AcceptedOnL2
+        })
+    }
+
     fn prepare_pending_block_with_tx_hashes(&self) -> Result<PendingBlockWithTxHashes, StarknetRpcApiError> {
         let parent_hash = self.get_best_block_hash();
         let latest_block = get_block_by_block_hash(self.client.as_ref(), parent_hash)
@@ -1380,144 +1546,6 @@ where
         self.filter_extrinsics(latest_block, pending_transactions)
     }
 
-    async fn prepare_tx_receipt(
-        &self,
-        transaction_hash: TransactionHash,
-        substrate_block_hash: B::Hash,
-    ) -> Result<MaybePendingTransactionReceipt, StarknetRpcApiError> {
-        let starknet_block: mp_block::Block = get_block_by_block_hash(self.client.as_ref(), substrate_block_hash)
-            .map_err(|_e| StarknetRpcApiError::BlockNotFound)?;
-        let block_header = starknet_block.header();
-        let block_hash = block_header.hash().into();
-        let block_number = block_header.block_number;
-
-        let transaction =
-            starknet_block.transactions().iter().find(|tx| get_transaction_hash(tx) == &transaction_hash).ok_or_else(
-                || {
-                    error!(
-                        "Failed to find transaction hash in block. Substrate block hash: {substrate_block_hash}, \
-                         transaction hash: {transaction_hash}"
-                    );
-                    StarknetRpcApiError::InternalServerError
-                },
-            )?;
-
-        let events = self.get_events_for_tx_by_hash(substrate_block_hash, transaction_hash)?;
-
-        let execution_result = {
-            let revert_error = self.get_tx_execution_outcome(substrate_block_hash, transaction_hash)?;
-
-            // This is safe because the message is a Vec<u8> build from a String
-            revert_error_to_execution_result(
-                revert_error.map(|message| unsafe { String::from_utf8_unchecked(message) }),
-            )
-        };
-
-        let events_converted: Vec<starknet_core::types::Event> =
-            events.clone().into_iter().map(starknet_api_to_starknet_core_event).collect();
-
-        let fee_disabled = self.is_transaction_fee_disabled(substrate_block_hash)?;
-        let actual_fee = FeePayment {
-            amount: if fee_disabled {
-                FieldElement::ZERO
-            } else {
-                // Event {
-                //     from_address: fee_token_address,
-                //     keys: [selector("Transfer")],
-                //     data: [
-                //         send_from_address,       // account_contract_address
-                //         send_to_address,         // to (sequencer address)
-                //         expected_fee_value_low,  // transfer amount (fee)
-                //         expected_fee_value_high,
-                //     ]},
-                // fee transfer must be the last event, except enabled disable-transaction-fee feature
-                events_converted.last().unwrap().data[2]
-            },
-            unit: PriceUnit::Wei,
-        };
-
-        let messages = self.get_tx_messages_to_l1(substrate_block_hash, transaction_hash)?;
-
-        let messages_sent = messages.into_iter().map(starknet_api_to_starknet_core_message_to_l1).collect();
-
-        let parent_block_hash = self
-            .substrate_block_hash_from_starknet_block(BlockId::Hash(
-                Felt252Wrapper::from(block_header.parent_block_hash).into(),
-            ))
-            .map_err(|e| {
-                error!("Parent Block not found: {e}");
-                StarknetRpcApiError::BlockNotFound
-            })?;
-        let execution_info =
-            self.get_transaction_execution_info(parent_block_hash, starknet_block.transactions(), transaction_hash)?;
-        let execution_resources = actual_resources_to_execution_resources(execution_info.actual_resources);
-        let transaction_hash = Felt252Wrapper::from(transaction_hash).into();
-
-        let receipt = match transaction {
-            blockifier::transaction::transaction_execution::Transaction::AccountTransaction(account_tx) => {
-                match account_tx {
-                    blockifier::transaction::account_transaction::AccountTransaction::Declare(_) => {
-                        TransactionReceipt::Declare(DeclareTransactionReceipt {
-                            transaction_hash,
-                            actual_fee,
-                            finality_status: TransactionFinalityStatus::AcceptedOnL2,
-                            block_hash,
-                            block_number,
-                            messages_sent,
-                            events: events_converted,
-                            execution_result,
-                            execution_resources,
-                        })
-                    }
-                    blockifier::transaction::account_transaction::AccountTransaction::DeployAccount(tx) => {
-                        TransactionReceipt::DeployAccount(DeployAccountTransactionReceipt {
-                            transaction_hash,
-                            actual_fee,
-                            finality_status: TransactionFinalityStatus::AcceptedOnL2,
-                            block_hash,
-                            block_number,
-                            messages_sent,
-                            events: events_converted,
-                            contract_address: Felt252Wrapper::from(tx.contract_address).into(),
-                            execution_result,
-                            execution_resources,
-                        })
-                    }
-                    blockifier::transaction::account_transaction::AccountTransaction::Invoke(_) => {
-                        TransactionReceipt::Invoke(InvokeTransactionReceipt {
-                            transaction_hash,
-                            actual_fee,
-                            finality_status: TransactionFinalityStatus::AcceptedOnL2,
-                            block_hash,
-                            block_number,
-                            messages_sent,
-                            events: events_converted,
-                            execution_result,
-                            execution_resources,
-                        })
-                    }
-                }
-            }
-            blockifier::transaction::transaction_execution::Transaction::L1HandlerTransaction(l1_handler_tx) => {
-                let message_hash = compute_message_hash(&l1_handler_tx.tx);
-                TransactionReceipt::L1Handler(L1HandlerTransactionReceipt {
-                    message_hash: Hash256::from_bytes(message_hash.to_fixed_bytes()),
-                    transaction_hash,
-                    actual_fee,
-                    finality_status: TransactionFinalityStatus::AcceptedOnL2,
-                    block_hash,
-                    block_number,
-                    messages_sent,
-                    events: events_converted,
-                    execution_result,
-                    execution_resources,
-                })
-            }
-        };
-
-        Ok(MaybePendingTransactionReceipt::Receipt(receipt))
-    }
-
     fn get_transaction_execution_info(
         &self,
         parent_substrate_block_hash: B::Hash,
@@ -1554,15 +1582,6 @@ where
         Ok(execution_info.0)
     }
 
-    fn get_events_for_tx_by_hash(
-        &self,
-        substrate_block_hash: B::Hash,
-        tx_hash: TransactionHash,
-    ) -> Result<Vec<starknet_api::transaction::Event>, StarknetRpcApiError> {
-        let events = self.do_get_events_for_tx_by_hash(substrate_block_hash, tx_hash)?;
-        Ok(events)
-    }
-
     fn get_tx_execution_outcome(
         &self,
         substrate_block_hash: B::Hash,
@@ -1602,8 +1621,10 @@ where
         let pending_txs = self.get_pending_txs(parent_substrate_block_hash)?;
         let simulation =
             self.get_transaction_execution_info(parent_substrate_block_hash, &pending_txs, transaction_hash)?;
-        let actual_fee =
-            FeePayment { amount: Felt252Wrapper::from(simulation.actual_fee.0).into(), unit: PriceUnit::Wei };
+        let actual_fee = FeePayment {
+            amount: Felt252Wrapper::from(simulation.actual_fee.0).into(),
+            unit: transaction_fee_unit(&pending_tx),
+        };
         let execution_result = revert_error_to_execution_result(simulation.revert_error);
         let execution_resources = actual_resources_to_execution_resources(simulation.actual_resources);
         let transaction_hash = Felt252Wrapper::from(transaction_hash).into();
@@ -1681,6 +1702,12 @@ where
         error!("Failed to submit extrinsic: {:?}", e);
         match e.into_pool_error() {
             Ok(PoolError::InvalidTransaction(InvalidTransaction::BadProof)) => StarknetRpcApiError::ValidationFailure,
+            Ok(PoolError::InvalidTransaction(InvalidTransaction::Custom(code))) => {
+                match TransactionRejection::from_code(code) {
+                    Some(reason) => StarknetRpcApiError::TransactionRejected(reason.to_string()),
+                    None => StarknetRpcApiError::InternalServerError,
+                }
+            }
             _ => StarknetRpcApiError::InternalServerError,
         }
     })
@@ -1716,6 +1743,18 @@ fn starknet_api_to_starknet_core_message_to_l1(
     }
 }
 
+/// Returns the unit of the fee paid by a transaction: FRI for v3 transactions paying in STRK, WEI
+/// otherwise.
+fn transaction_fee_unit(transaction: &blockifier::transaction::transaction_execution::Transaction) -> PriceUnit {
+    match transaction {
+        blockifier::transaction::transaction_execution::Transaction::AccountTransaction(tx) => match tx.fee_type() {
+            FeeType::Strk => PriceUnit::Fri,
+            FeeType::Eth => PriceUnit::Wei,
+        },
+        blockifier::transaction::transaction_execution::Transaction::L1HandlerTransaction(_) => PriceUnit::Wei,
+    }
+}
+
 fn revert_error_to_execution_result(revert_error: Option<String>) -> ExecutionResult {
     match revert_error {
         None => ExecutionResult::Succeeded,
@@ -1723,9 +1762,84 @@ fn revert_error_to_execution_result(revert_error: Option<String>) -> ExecutionRe
     }
 }
 
+fn stored_receipt_to_transaction_receipt(
+    receipt: StoredTransactionReceipt,
+    finality_status: TransactionFinalityStatus,
+) -> TransactionReceipt {
+    let transaction_hash = Felt252Wrapper::from(receipt.transaction_hash).into();
+    let actual_fee = FeePayment {
+        amount: Felt252Wrapper::from(receipt.actual_fee.0).into(),
+        unit: match receipt.fee_unit {
+            FeeUnit::Wei => PriceUnit::Wei,
+            FeeUnit::Fri => PriceUnit::Fri,
+        },
+    };
+    let block_hash = Felt252Wrapper::from(receipt.block_hash).into();
+    let block_number = receipt.block_number;
+    let messages_sent = receipt.messages_sent.into_iter().map(starknet_api_to_starknet_core_message_to_l1).collect();
+    let events = receipt.events.into_iter().map(starknet_api_to_starknet_core_event).collect();
+    let execution_result = revert_error_to_execution_result(receipt.revert_reason);
+    let execution_resources = resources_to_execution_resources(receipt.execution_resources.into_iter().collect());
+
+    match receipt.kind {
+        TransactionReceiptKind::Invoke => TransactionReceipt::Invoke(InvokeTransactionReceipt {
+            transaction_hash,
+            actual_fee,
+            finality_status,
+            block_hash,
+            block_number,
+            messages_sent,
+            events,
+            execution_result,
+            execution_resources,
+        }),
+        TransactionReceiptKind::Declare => TransactionReceipt::Declare(DeclareTransactionReceipt {
+            transaction_hash,
+            actual_fee,
+            finality_status,
+            block_hash,
+            block_number,
+            messages_sent,
+            events,
+            execution_result,
+            execution_resources,
+        }),
+        TransactionReceiptKind::DeployAccount { contract_address } => {
+            TransactionReceipt::DeployAccount(DeployAccountTransactionReceipt {
+                transaction_hash,
+                actual_fee,
+                finality_status,
+                block_hash,
+                block_number,
+                messages_sent,
+                events,
+                contract_address: Felt252Wrapper::from(contract_address).into(),
+                execution_result,
+                execution_resources,
+            })
+        }
+        TransactionReceiptKind::L1Handler { message_hash } => {
+            TransactionReceipt::L1Handler(L1HandlerTransactionReceipt {
+                message_hash: Hash256::from_bytes(message_hash),
+                transaction_hash,
+                actual_fee,
+                finality_status,
+                block_hash,
+                block_number,
+                messages_sent,
+                events,
+                execution_result,
+                execution_resources,
+            })
+        }
+    }
+}
+
 fn actual_resources_to_execution_resources(resources: ResourcesMapping) -> ExecutionResources {
-    let resources =
-        resources.0.into_iter().map(|(k, v)| (k.to_lowercase(), v as u64)).collect::<HashMap<String, u64>>();
+    resources_to_execution_resources(resources.0.into_iter().map(|(k, v)| (k.to_lowercase(), v as u64)).collect())
+}
+
+fn resources_to_execution_resources(resources: HashMap<String, u64>) -> ExecutionResources {
     // Based on `VM_RESOURCE_FEE_COSTS`
     // in crates/primitives/fee/src/lib.rs
     ExecutionResources {
@@ -2,8 +2,8 @@ use jsonrpsee::core::{async_trait, RpcResult};
 use log::error;
 use mc_genesis_data_provider::GenesisProvider;
 pub use mc_rpc_core::{
-    Felt, MadaraRpcApiServer, PredeployedAccountWithBalance, StarknetReadRpcApiServer, StarknetTraceRpcApiServer,
-    StarknetWriteRpcApiServer,
+    Felt, MadaraRpcApiServer, PaymasterInfo, PredeployedAccountWithBalance, StarknetReadRpcApiServer,
+    StarknetTraceRpcApiServer, StarknetWriteRpcApiServer, WithPaymaster,
 };
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
@@ -17,7 +17,10 @@ use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::traits::Block as BlockT;
-use starknet_core::types::{BlockId, BlockTag, DeclareTransactionResult, FieldElement, FunctionCall};
+use starknet_core::types::{
+    BlockId, BlockTag, BroadcastedTransaction, DeclareTransactionResult, FeeEstimate, FieldElement, FunctionCall,
+    SimulatedTransaction, SimulationFlag, SimulationFlagForEstimateFee,
+};
 use starknet_core::utils::get_selector_from_name;
 
 use crate::errors::StarknetRpcApiError;
@@ -83,4 +86,26 @@ where
             class_hash: Felt252Wrapper::from(class_hash).into(),
         })
     }
+
+    async fn estimate_fee_with_paymaster(
+        &self,
+        request: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlagForEstimateFee>,
+        block_id: BlockId,
+    ) -> RpcResult<Vec<WithPaymaster<FeeEstimate>>> {
+        self.broadcasted_fee_estimates(request, simulation_flags, block_id)
+    }
+
+    async fn simulate_transactions_with_paymaster(
+        &self,
+        block_id: BlockId,
+        transactions: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> RpcResult<Vec<WithPaymaster<SimulatedTransaction>>> {
+        self.simulate_broadcasted_transactions(block_id, transactions, simulation_flags)
+    }
+
+    fn get_transaction_paymaster(&self, transaction_hash: FieldElement) -> RpcResult<Option<PaymasterInfo>> {
+        self.transaction_paymaster(Felt252Wrapper::from(transaction_hash).into())
+    }
 }
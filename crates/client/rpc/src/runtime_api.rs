@@ -8,9 +8,11 @@ pub use mc_rpc_core::{
     Felt, MadaraRpcApiServer, PredeployedAccountWithBalance, StarknetReadRpcApiServer, StarknetTraceRpcApiServer,
     StarknetWriteRpcApiServer,
 };
+use mc_rpc_core::{PaymasterInfo, WithPaymaster};
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use mp_simulations::SimulationFlags;
+use mp_transactions::execution::PaymasterConfig;
 use pallet_starknet_runtime_api::{ConvertTransactionRuntimeApi, StarknetRuntimeApi};
 use sc_client_api::backend::Backend;
 use sc_transaction_pool::ChainApi;
@@ -106,7 +108,7 @@ where
         block_hash: B::Hash,
         transactions: Vec<AccountTransaction>,
         simulation_flags: SimulationFlags,
-    ) -> RpcApiResult<Vec<FeeEstimate>> {
+    ) -> RpcApiResult<Vec<WithPaymaster<FeeEstimate>>> {
         let fee_estimates = self
             .client
             .runtime_api()
@@ -116,7 +118,13 @@ where
                 StarknetRpcApiError::InternalServerError
             })???
             .iter()
-            .map(|estimate| estimate.into())
+            .map(|estimate| WithPaymaster {
+                result: estimate.into(),
+                paymaster: estimate.paymaster.map(|address| PaymasterInfo {
+                    paymaster_address: Felt252Wrapper::from(address).into(),
+                    validate_invocation: None,
+                }),
+            })
             .collect();
         Ok(fee_estimates)
     }
@@ -152,6 +160,13 @@ where
         })
     }
 
+    pub fn paymaster_config(&self, substrate_block_hash: B::Hash) -> RpcApiResult<Option<PaymasterConfig>> {
+        self.client.runtime_api().paymaster_config(substrate_block_hash).map_err(|e| {
+            error!("Failed to get the paymaster config. Substrate block hash: {substrate_block_hash}, error: {e}");
+            StarknetRpcApiError::InternalServerError
+        })
+    }
+
     pub fn is_transaction_fee_disabled(&self, substrate_block_hash: B::Hash) -> RpcApiResult<bool> {
         self.client.runtime_api().is_transaction_fee_disabled(substrate_block_hash).map_err(|e| {
             error!("Failed to get check fee disabled. Substrate block hash: {substrate_block_hash}, error: {e}");
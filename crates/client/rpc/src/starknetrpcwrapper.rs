@@ -3,8 +3,8 @@ use std::sync::Arc;
 use jsonrpsee::core::{async_trait, RpcResult};
 use mc_genesis_data_provider::GenesisProvider;
 pub use mc_rpc_core::{
-    Felt, MadaraRpcApiServer, PredeployedAccountWithBalance, StarknetReadRpcApiServer, StarknetTraceRpcApiServer,
-    StarknetWriteRpcApiServer,
+    Felt, MadaraRpcApiServer, PaymasterInfo, PredeployedAccountWithBalance, StarknetReadRpcApiServer,
+    StarknetTraceRpcApiServer, StarknetWriteRpcApiServer, WithPaymaster,
 };
 use mp_hashers::HasherT;
 use mp_transactions::{BroadcastedDeclareTransactionV0, TransactionStatus};
@@ -59,6 +59,28 @@ where
     ) -> RpcResult<DeclareTransactionResult> {
         self.0.add_declare_transaction_v0(params).await
     }
+
+    async fn estimate_fee_with_paymaster(
+        &self,
+        request: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlagForEstimateFee>,
+        block_id: BlockId,
+    ) -> RpcResult<Vec<WithPaymaster<FeeEstimate>>> {
+        self.0.estimate_fee_with_paymaster(request, simulation_flags, block_id).await
+    }
+
+    async fn simulate_transactions_with_paymaster(
+        &self,
+        block_id: BlockId,
+        transactions: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> RpcResult<Vec<WithPaymaster<SimulatedTransaction>>> {
+        self.0.simulate_transactions_with_paymaster(block_id, transactions, simulation_flags).await
+    }
+
+    fn get_transaction_paymaster(&self, transaction_hash: FieldElement) -> RpcResult<Option<PaymasterInfo>> {
+        self.0.get_transaction_paymaster(transaction_hash)
+    }
 }
 
 #[async_trait]
@@ -7,7 +7,7 @@ use jsonrpsee::core::{async_trait, RpcResult};
 use log::error;
 use mc_genesis_data_provider::GenesisProvider;
 use mc_rpc_core::utils::{blockifier_to_rpc_state_diff_types, get_block_by_block_hash};
-use mc_rpc_core::{StarknetReadRpcApiServer, StarknetTraceRpcApiServer};
+use mc_rpc_core::{PaymasterInfo, StarknetReadRpcApiServer, StarknetTraceRpcApiServer, WithPaymaster};
 use mp_felt::Felt252Wrapper;
 use mp_hashers::HasherT;
 use mp_simulations::SimulationFlags;
@@ -53,75 +53,10 @@ where
         transactions: Vec<BroadcastedTransaction>,
         simulation_flags: Vec<SimulationFlag>,
     ) -> RpcResult<Vec<SimulatedTransaction>> {
-        let substrate_block_hash =
-            self.substrate_block_hash_from_starknet_block(block_id).map_err(|_| StarknetRpcApiError::BlockNotFound)?;
-        let chain_id = Felt252Wrapper(self.chain_id()?.0);
+        let simulated_transactions =
+            self.simulate_broadcasted_transactions(block_id, transactions, simulation_flags)?;
 
-        let mut tx_types = Vec::with_capacity(transactions.len());
-        let mut account_transactions = Vec::with_capacity(transactions.len());
-
-        let tx_type_and_tx_iterator = transactions.into_iter().map(|tx| match tx {
-            BroadcastedTransaction::Invoke(invoke_tx) => (
-                TxType::Invoke,
-                try_invoke_tx_from_broadcasted_invoke_tx(invoke_tx, chain_id).map(AccountTransaction::Invoke),
-            ),
-            BroadcastedTransaction::Declare(declare_tx) => (
-                TxType::Declare,
-                try_declare_tx_from_broadcasted_declare_tx(declare_tx, chain_id).map(AccountTransaction::Declare),
-            ),
-            BroadcastedTransaction::DeployAccount(deploy_account_tx) => (
-                TxType::DeployAccount,
-                try_deploy_tx_from_broadcasted_deploy_tx(deploy_account_tx, chain_id)
-                    .map(AccountTransaction::DeployAccount),
-            ),
-        });
-
-        for (tx_type, account_tx) in tx_type_and_tx_iterator {
-            let tx = account_tx.map_err(|e| {
-                error!("Failed to convert BroadcastedTransaction to AccountTransaction: {e}");
-                StarknetRpcApiError::InternalServerError
-            })?;
-            account_transactions.push(tx);
-            tx_types.push(tx_type);
-        }
-
-        let simulation_flags = SimulationFlags::from(simulation_flags);
-
-        let res = self
-            .client
-            .runtime_api()
-            .simulate_transactions(substrate_block_hash, account_transactions, simulation_flags)
-            .map_err(|e| {
-                error!("Request parameters error: {e}");
-                StarknetRpcApiError::InternalServerError
-            })?
-            .map_err(|e| {
-                error!("Failed to call function: {:#?}", e);
-                StarknetRpcApiError::from(e)
-            })?;
-
-        let mut simulated_transactions = vec![];
-        for (tx_type, simulation_result) in tx_types.into_iter().zip(res.into_iter()) {
-            match simulation_result {
-                Ok(simulation) => {
-                    let state_diff = blockifier_to_rpc_state_diff_types(simulation.state_diff)
-                        .map_err(|_| StarknetRpcApiError::InternalServerError)?;
-
-                    let transaction_trace =
-                        tx_execution_infos_to_tx_trace(tx_type, &simulation.execution_info, Some(state_diff))?;
-
-                    simulated_transactions.push(SimulatedTransaction {
-                        transaction_trace,
-                        fee_estimation: simulation.fee_estimate.into(),
-                    });
-                }
-                Err(e) => {
-                    return Err(StarknetRpcApiError::from(e).into());
-                }
-            }
-        }
-
-        Ok(simulated_transactions)
+        Ok(simulated_transactions.into_iter().map(|simulation| simulation.result).collect())
     }
 
     async fn trace_block_transactions(&self, block_id: BlockId) -> RpcResult<Vec<TransactionTraceWithHash>> {
@@ -207,6 +142,101 @@ where
     P: TransactionPool<Block = B> + 'static,
     H: HasherT + Send + Sync + 'static,
 {
+    /// Simulates the `transactions`, along with the paymaster of the sponsored ones
+    pub(crate) fn simulate_broadcasted_transactions(
+        &self,
+        block_id: BlockId,
+        transactions: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> RpcResult<Vec<WithPaymaster<SimulatedTransaction>>> {
+        let substrate_block_hash =
+            self.substrate_block_hash_from_starknet_block(block_id).map_err(|_| StarknetRpcApiError::BlockNotFound)?;
+        let chain_id = Felt252Wrapper(self.chain_id()?.0);
+
+        let mut tx_types = Vec::with_capacity(transactions.len());
+        let mut account_transactions = Vec::with_capacity(transactions.len());
+
+        let tx_type_and_tx_iterator = transactions.into_iter().map(|tx| match tx {
+            BroadcastedTransaction::Invoke(invoke_tx) => (
+                TxType::Invoke,
+                try_invoke_tx_from_broadcasted_invoke_tx(invoke_tx, chain_id).map(AccountTransaction::Invoke),
+            ),
+            BroadcastedTransaction::Declare(declare_tx) => (
+                TxType::Declare,
+                try_declare_tx_from_broadcasted_declare_tx(declare_tx, chain_id).map(AccountTransaction::Declare),
+            ),
+            BroadcastedTransaction::DeployAccount(deploy_account_tx) => (
+                TxType::DeployAccount,
+                try_deploy_tx_from_broadcasted_deploy_tx(deploy_account_tx, chain_id)
+                    .map(AccountTransaction::DeployAccount),
+            ),
+        });
+
+        for (tx_type, account_tx) in tx_type_and_tx_iterator {
+            let tx = account_tx.map_err(|e| {
+                error!("Failed to convert BroadcastedTransaction to AccountTransaction: {e}");
+                StarknetRpcApiError::InternalServerError
+            })?;
+            account_transactions.push(tx);
+            tx_types.push(tx_type);
+        }
+
+        let simulation_flags = SimulationFlags::from(simulation_flags);
+
+        let res = self
+            .client
+            .runtime_api()
+            .simulate_transactions(substrate_block_hash, account_transactions, simulation_flags)
+            .map_err(|e| {
+                error!("Request parameters error: {e}");
+                StarknetRpcApiError::InternalServerError
+            })?
+            .map_err(|e| {
+                error!("Failed to call function: {:#?}", e);
+                StarknetRpcApiError::from(e)
+            })?;
+
+        let mut simulated_transactions = vec![];
+        for (tx_type, simulation_result) in tx_types.into_iter().zip(res.into_iter()) {
+            match simulation_result {
+                Ok(simulation) => {
+                    let state_diff = blockifier_to_rpc_state_diff_types(simulation.state_diff)
+                        .map_err(|_| StarknetRpcApiError::InternalServerError)?;
+
+                    let transaction_trace =
+                        tx_execution_infos_to_tx_trace(tx_type, &simulation.execution_info, Some(state_diff))?;
+
+                    let paymaster = simulation
+                        .paymaster
+                        .map(|paymaster| {
+                            Ok::<_, StarknetRpcApiError>(PaymasterInfo {
+                                paymaster_address: Felt252Wrapper::from(paymaster.address).into(),
+                                validate_invocation: paymaster
+                                    .validate_call_info
+                                    .as_ref()
+                                    .map(try_get_function_invocation_from_call_info)
+                                    .transpose()?,
+                            })
+                        })
+                        .transpose()?;
+
+                    simulated_transactions.push(WithPaymaster {
+                        result: SimulatedTransaction {
+                            transaction_trace,
+                            fee_estimation: simulation.fee_estimate.into(),
+                        },
+                        paymaster,
+                    });
+                }
+                Err(e) => {
+                    return Err(StarknetRpcApiError::from(e).into());
+                }
+            }
+        }
+
+        Ok(simulated_transactions)
+    }
+
     pub fn re_execute_transactions(
         &self,
         previous_block_substrate_hash: B::Hash,
@@ -0,0 +1,187 @@
+//! Cache of the contract classes decoded from the storage.
+//!
+//! Decoding a class is expensive and most transactions use the same few account and token
+//! classes. The cache is owned by the [`OverrideHandle`](crate::OverrideHandle), so the decoded
+//! classes are reused by every reader of the storage overrides, whatever the block they read.
+//!
+//! A Sierra class hash can be compiled to different CASM classes by different forks, so the classes
+//! are cached by class hash and compiled class hash. Cairo 0 classes have no compiled class hash.
+
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use blockifier::execution::contract_class::ContractClass;
+use lru::LruCache;
+use starknet_api::core::{ClassHash, CompiledClassHash};
+
+/// Number of classes kept by the cache of the storage overrides.
+pub const DEFAULT_CAPACITY: usize = 512;
+
+/// The key of a cached class.
+pub type ContractClassKey = (ClassHash, Option<CompiledClassHash>);
+
+/// Counters of a [`ContractClassCache`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContractClassCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Number of classes currently cached.
+    pub len: usize,
+}
+
+impl ContractClassCacheStats {
+    /// Share of the lookups served by the cache, `0` if there were none.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+/// A bounded, least recently used cache of decoded contract classes.
+///
+/// Clones share the same cache.
+#[derive(Clone, Debug)]
+pub struct ContractClassCache(Arc<Mutex<Inner>>);
+
+#[derive(Debug)]
+struct Inner {
+    /// `None` when the cache is disabled.
+    classes: Option<LruCache<ContractClassKey, ContractClass>>,
+    stats: ContractClassCacheStats,
+}
+
+impl ContractClassCache {
+    /// A cache holding at most `capacity` classes, `0` disabling it.
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            classes: NonZeroUsize::new(capacity).map(LruCache::new),
+            stats: ContractClassCacheStats::default(),
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // The cache is left consistent by every operation, even the interrupted ones
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The class cached for `key`, or the one returned by `load` which is then cached.
+    ///
+    /// `load` is called without holding the lock, so concurrent lookups aren't blocked by the
+    /// decoding.
+    pub fn get_or_load(
+        &self,
+        key: ContractClassKey,
+        load: impl FnOnce() -> Option<ContractClass>,
+    ) -> Option<ContractClass> {
+        {
+            let mut inner = self.lock();
+            if let Some(class) = inner.classes.as_mut().and_then(|classes| classes.get(&key)).cloned() {
+                inner.stats.hits += 1;
+                return Some(class);
+            }
+            inner.stats.misses += 1;
+        }
+
+        let class = load()?;
+        self.lock().insert(key, class.clone());
+        Some(class)
+    }
+
+    /// Remove all the classes, the counters are kept.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        if let Some(classes) = inner.classes.as_mut() {
+            classes.clear();
+        }
+        inner.stats.len = 0;
+    }
+
+    pub fn stats(&self) -> ContractClassCacheStats {
+        self.lock().stats
+    }
+}
+
+impl Inner {
+    fn insert(&mut self, key: ContractClassKey, class: ContractClass) {
+        let Some(classes) = self.classes.as_mut() else {
+            return;
+        };
+        // The class may have been loaded concurrently, replacing it isn't an eviction
+        if let Some((evicted, _)) = classes.push(key, class) {
+            if evicted != key {
+                self.stats.evictions += 1;
+            }
+        }
+        self.stats.len = classes.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use blockifier::execution::contract_class::ContractClassV0;
+    use starknet_api::hash::StarkFelt;
+
+    use super::*;
+
+    fn class_hash(n: u8) -> ClassHash {
+        ClassHash(StarkFelt::from(n))
+    }
+
+    fn compiled_class_hash(n: u8) -> CompiledClassHash {
+        CompiledClassHash(StarkFelt::from(n))
+    }
+
+    fn contract_class(name: &str) -> ContractClass {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../cairo-contracts/build").join(name);
+        let raw_class = std::fs::read_to_string(path).unwrap();
+        ContractClass::V0(ContractClassV0::try_from_json_string(&raw_class).unwrap())
+    }
+
+    #[test]
+    fn least_recently_used_classes_are_evicted() {
+        let cache = ContractClassCache::new(2);
+        let class = contract_class("ERC20.json");
+        let load = || Some(class.clone());
+
+        cache.get_or_load((class_hash(1), None), load);
+        cache.get_or_load((class_hash(2), None), load);
+        cache.get_or_load((class_hash(1), None), load);
+        cache.get_or_load((class_hash(3), None), load);
+
+        assert_eq!(cache.get_or_load((class_hash(1), None), || None), Some(class.clone()));
+        assert_eq!(cache.get_or_load((class_hash(2), None), || None), None);
+        assert_eq!(cache.stats(), ContractClassCacheStats { hits: 2, misses: 4, evictions: 1, len: 2 });
+        assert_eq!(cache.stats().hit_rate(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn classes_are_cached_per_compiled_class_hash() {
+        let cache = ContractClassCache::new(2);
+        let class = contract_class("ERC20.json");
+        let other_class = contract_class("NoValidateAccount.json");
+
+        cache.get_or_load((class_hash(1), Some(compiled_class_hash(1))), || Some(class.clone()));
+
+        assert_eq!(cache.get_or_load((class_hash(1), Some(compiled_class_hash(2))), || None), None);
+        assert_eq!(
+            cache.get_or_load((class_hash(1), Some(compiled_class_hash(2))), || Some(other_class.clone())),
+            Some(other_class.clone())
+        );
+        assert_eq!(cache.get_or_load((class_hash(1), Some(compiled_class_hash(1))), || None), Some(class));
+        assert_eq!(cache.get_or_load((class_hash(1), Some(compiled_class_hash(2))), || None), Some(other_class));
+    }
+
+    #[test]
+    fn disabled_cache_always_loads() {
+        let cache = ContractClassCache::new(0);
+        let class = contract_class("ERC20.json");
+
+        cache.get_or_load((class_hash(1), None), || Some(class.clone()));
+
+        assert_eq!(cache.get_or_load((class_hash(1), None), || None), None);
+        assert_eq!(cache.stats(), ContractClassCacheStats { hits: 0, misses: 2, evictions: 0, len: 0 });
+    }
+}
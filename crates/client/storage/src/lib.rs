@@ -6,20 +6,24 @@
 //! first one as a fallback.
 //! It can also support multiple versions of the pallet storage.
 
+pub mod contract_class_cache;
 mod overrides;
 
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use contract_class_cache::ContractClassCache;
+use futures::StreamExt;
 use mp_storage::{StarknetStorageSchemaVersion, PALLET_STARKNET_SCHEMA};
 pub use overrides::*;
 use pallet_starknet_runtime_api::StarknetRuntimeApi;
 use parity_scale_codec::Decode;
 use sc_client_api::backend::{Backend, StorageProvider};
+use sc_client_api::client::BlockchainEvents;
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::traits::Block as BlockT;
-use sp_storage::StorageKey;
+use sp_storage::{well_known_keys, StorageKey};
 
 /// Create and return a handle of the starknet schema overrides
 pub fn overrides_handle<B, C, BE>(client: Arc<C>) -> Arc<OverrideHandle<B>>
@@ -30,18 +34,42 @@ where
     C: HeaderBackend<B> + StorageProvider<B, BE> + 'static,
     BE: Backend<B> + 'static,
 {
+    let contract_classes = ContractClassCache::new(contract_class_cache::DEFAULT_CAPACITY);
     let mut overrides_map = BTreeMap::new();
     overrides_map.insert(
         StarknetStorageSchemaVersion::V1,
-        Box::new(SchemaV1Override::new(client.clone())) as Box<dyn StorageOverride<_>>,
+        Box::new(SchemaV1Override::new(client.clone(), contract_classes.clone())) as Box<dyn StorageOverride<_>>,
     );
 
     Arc::new(OverrideHandle {
         schemas: overrides_map,
         fallback: Box::new(RuntimeApiStorageOverride::<B, C>::new(client)),
+        contract_classes,
     })
 }
 
+/// Clear the classes decoded by the storage overrides whenever an imported block upgrades the
+/// runtime, as the upgrade may migrate the stored classes.
+pub async fn clear_contract_classes_on_runtime_upgrade<B, C>(client: Arc<C>, contract_classes: ContractClassCache)
+where
+    B: BlockT,
+    C: BlockchainEvents<B>,
+{
+    let code_key = StorageKey(well_known_keys::CODE.to_vec());
+    let mut code_changes = match client.storage_changes_notification_stream(Some(&[code_key]), None) {
+        Ok(code_changes) => code_changes,
+        Err(e) => {
+            log::error!("Failed to watch the runtime upgrades, the contract class cache won't be cleared: {e}");
+            return;
+        }
+    };
+
+    while let Some(notification) = code_changes.next().await {
+        log::info!("Runtime upgraded at block {}, clearing the contract class cache", notification.block);
+        contract_classes.clear();
+    }
+}
+
 /// Retrieve the current `pallet-starknet` storage schema version
 pub fn onchain_storage_schema<B, C, BE>(client: &C, hash: B::Hash) -> StarknetStorageSchemaVersion
 where
@@ -10,7 +10,7 @@ use sc_client_api::{Backend, HeaderBackend, StorageProvider};
 use sp_api::ProvideRuntimeApi;
 use sp_io::hashing::twox_128;
 use sp_runtime::traits::Block as BlockT;
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::{Event as StarknetEvent, TransactionHash};
@@ -18,6 +18,7 @@ use starknet_api::transaction::{Event as StarknetEvent, TransactionHash};
 mod schema_v1_override;
 
 pub use self::schema_v1_override::SchemaV1Override;
+use crate::contract_class_cache::ContractClassCache;
 use crate::onchain_storage_schema;
 
 /// A handle containing multiple entities implementing `StorageOverride`
@@ -26,6 +27,8 @@ pub struct OverrideHandle<B: BlockT> {
     pub schemas: BTreeMap<StarknetStorageSchemaVersion, Box<dyn StorageOverride<B>>>,
     /// A non-failing way to retrieve the storage data
     pub fallback: Box<dyn StorageOverride<B>>,
+    /// The classes decoded by the schema overrides
+    pub contract_classes: ContractClassCache,
 }
 
 #[allow(clippy::borrowed_box)]
@@ -74,6 +77,8 @@ pub trait StorageOverride<B: BlockT>: Send + Sync {
         block_hash: B::Hash,
         contract_class_hash: ClassHash,
     ) -> Option<ContractClass>;
+    /// Return the compiled class hash of a Sierra class hash for a provided block hash.
+    fn compiled_class_hash(&self, block_hash: B::Hash, class_hash: ClassHash) -> Option<CompiledClassHash>;
     /// Returns the nonce for a provided contract address and block hash.
     fn nonce(&self, block_hash: B::Hash, address: ContractAddress) -> Option<Nonce>;
 
@@ -175,6 +180,19 @@ where
         self.client.runtime_api().contract_class_by_class_hash(block_hash, contract_class_hash).ok()?
     }
 
+    /// Return the compiled class hash of a Sierra class hash for a provided block hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_hash` - The block hash
+    /// * `class_hash` - The Sierra class hash to fetch the compiled class hash for
+    ///
+    /// # Returns
+    /// * `Some(compiled_class_hash)` - The compiled class hash, if the class was declared
+    fn compiled_class_hash(&self, block_hash: <B as BlockT>::Hash, class_hash: ClassHash) -> Option<CompiledClassHash> {
+        self.client.runtime_api().compiled_class_hash_by_class_hash(block_hash, class_hash).ok()?
+    }
+
     /// Return the nonce for a provided contract address and block hash.
     ///
     /// # Arguments
@@ -3,8 +3,8 @@ use std::sync::Arc;
 
 use blockifier::execution::contract_class::ContractClass;
 use mp_storage::{
-    PALLET_STARKNET, STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH, STARKNET_NONCE, STARKNET_STORAGE,
-    STARKNET_TX_EVENTS,
+    PALLET_STARKNET, STARKNET_COMPILED_CLASS_HASH, STARKNET_CONTRACT_CLASS, STARKNET_CONTRACT_CLASS_HASH,
+    STARKNET_NONCE, STARKNET_STORAGE, STARKNET_TX_EVENTS,
 };
 use parity_scale_codec::{Decode, Encode};
 // Substrate
@@ -12,22 +12,25 @@ use sc_client_api::backend::{Backend, StorageProvider};
 use sp_blockchain::HeaderBackend;
 use sp_runtime::traits::Block as BlockT;
 use sp_storage::StorageKey;
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey as StarknetStorageKey;
 use starknet_api::transaction::{Event as StarknetEvent, TransactionHash};
 
 use super::{storage_key_build, storage_prefix_build, StorageOverride};
+use crate::contract_class_cache::ContractClassCache;
 
 /// An override for runtimes that use Schema V1
 pub struct SchemaV1Override<B: BlockT, C, BE> {
     client: Arc<C>,
+    /// The decoded classes, shared by the blocks of every fork
+    contract_classes: ContractClassCache,
     _marker: PhantomData<(B, BE)>,
 }
 
 impl<B: BlockT, C, BE> SchemaV1Override<B, C, BE> {
-    pub fn new(client: Arc<C>) -> Self {
-        Self { client, _marker: PhantomData }
+    pub fn new(client: Arc<C>, contract_classes: ContractClassCache) -> Self {
+        Self { client, contract_classes, _marker: PhantomData }
     }
 }
 
@@ -109,12 +112,25 @@ where
         contract_class_hash: ClassHash,
     ) -> Option<ContractClass> {
         let storage_contract_class_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_CONTRACT_CLASS);
-        self.query_storage::<ContractClass>(
+        let key = StorageKey(storage_key_build(
+            storage_contract_class_prefix,
+            &self.encode_storage_key(&contract_class_hash),
+        ));
+
+        // The cache is shared with other blocks, which may have declared classes this one doesn't
+        if !matches!(self.client.storage_hash(block_hash, &key), Ok(Some(_))) {
+            return None;
+        }
+        let compiled_class_hash = self.compiled_class_hash(block_hash, contract_class_hash);
+        self.contract_classes
+            .get_or_load((contract_class_hash, compiled_class_hash), || self.query_storage(block_hash, &key))
+    }
+
+    fn compiled_class_hash(&self, block_hash: <B as BlockT>::Hash, class_hash: ClassHash) -> Option<CompiledClassHash> {
+        let storage_compiled_class_hash_prefix = storage_prefix_build(PALLET_STARKNET, STARKNET_COMPILED_CLASS_HASH);
+        self.query_storage::<CompiledClassHash>(
             block_hash,
-            &StorageKey(storage_key_build(
-                storage_contract_class_prefix,
-                &self.encode_storage_key(&contract_class_hash),
-            )),
+            &StorageKey(storage_key_build(storage_compiled_class_hash_prefix, &self.encode_storage_key(&class_hash))),
         )
     }
 
@@ -0,0 +1,149 @@
+//! The local chain the upstream blocks are imported into.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use blockifier::transaction::transaction_execution::Transaction as ExecutionTransaction;
+use mp_block::Header;
+use mp_digest_log::find_starknet_block;
+use mp_felt::Felt252Wrapper;
+use mp_starknet_inherent::{InherentDataProvider as StarknetInherentDataProvider, StarknetInherentData};
+use pallet_starknet_runtime_api::{ConvertTransactionRuntimeApi, StarknetRuntimeApi};
+use sc_block_builder::{BlockBuilderProvider, BuiltBlock, RecordProof};
+use sc_client_api::HeaderBackend;
+use sc_consensus::{
+    BlockImport, BlockImportParams, BoxBlockImport, ForkChoiceStrategy, ImportResult, StateAction, StorageChanges,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_block_builder::BlockBuilder as BlockBuilderApi;
+use sp_consensus::BlockOrigin;
+use sp_inherents::{InherentData, InherentDataProvider};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use sp_runtime::Digest;
+use sp_timestamp::Timestamp;
+
+use crate::error::Error;
+
+/// The inherents of a block built from an upstream block.
+#[derive(Clone, Debug)]
+pub struct BlockInherents {
+    /// Timestamp of the block, in seconds.
+    pub timestamp: u64,
+    pub starknet: StarknetInherentData,
+}
+
+/// The chain the blocks synced from upstream are built and imported into.
+#[async_trait]
+pub trait LocalChain: Send + Sync {
+    /// A block built on top of the best block, not imported yet.
+    type BuiltBlock: Send;
+
+    /// The Starknet header of the best block.
+    fn best_header(&self) -> Result<Header, Error>;
+
+    /// The chain id the transactions are hashed with.
+    fn chain_id(&self) -> Result<Felt252Wrapper, Error>;
+
+    /// Build a block executing `transactions` on top of the best block, returned with its Starknet
+    /// header.
+    async fn build_block(
+        &self,
+        inherents: BlockInherents,
+        transactions: Vec<ExecutionTransaction>,
+    ) -> Result<(Header, Self::BuiltBlock), Error>;
+
+    /// Import a block built by [`LocalChain::build_block`] as the new finalized best block.
+    async fn import_block(&mut self, block: Self::BuiltBlock) -> Result<(), Error>;
+}
+
+/// The Substrate chain of the node, whose blocks are imported through `block_import`.
+pub struct SubstrateChain<B: BlockT, C, BE> {
+    client: Arc<C>,
+    block_import: BoxBlockImport<B>,
+    _backend: PhantomData<fn() -> BE>,
+}
+
+impl<B: BlockT, C, BE> SubstrateChain<B, C, BE> {
+    pub fn new(client: Arc<C>, block_import: BoxBlockImport<B>) -> Self {
+        Self { client, block_import, _backend: PhantomData }
+    }
+}
+
+#[async_trait]
+impl<B, C, BE> LocalChain for SubstrateChain<B, C, BE>
+where
+    B: BlockT,
+    BE: sc_client_api::Backend<B>,
+    C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBuilderProvider<BE, B, C> + Send + Sync,
+    C::Api: BlockBuilderApi<B> + StarknetRuntimeApi<B> + ConvertTransactionRuntimeApi<B>,
+{
+    type BuiltBlock = BuiltBlock<B>;
+
+    fn best_header(&self) -> Result<Header, Error> {
+        let best_hash = self.client.info().best_hash;
+        let best_header = self.client.header(best_hash)?.ok_or(Error::MissingStarknetBlock)?;
+        let best_block = find_starknet_block(best_header.digest()).map_err(|_| Error::MissingStarknetBlock)?;
+        Ok(best_block.header().clone())
+    }
+
+    fn chain_id(&self) -> Result<Felt252Wrapper, Error> {
+        Ok(self.client.runtime_api().chain_id(self.client.info().best_hash)?)
+    }
+
+    async fn build_block(
+        &self,
+        inherents: BlockInherents,
+        transactions: Vec<ExecutionTransaction>,
+    ) -> Result<(Header, BuiltBlock<B>), Error> {
+        let inherent_data = inherent_data(inherents).await?;
+
+        // The runtime API is not kept across the awaits, as it is not `Send`
+        let parent_hash = self.client.info().best_hash;
+        let mut extrinsics = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            extrinsics.push(match transaction {
+                ExecutionTransaction::AccountTransaction(tx) => {
+                    self.client.runtime_api().convert_account_transaction(parent_hash, tx)?
+                }
+                ExecutionTransaction::L1HandlerTransaction(tx) => {
+                    self.client.runtime_api().convert_l1_transaction(parent_hash, tx)?
+                }
+            });
+        }
+
+        let mut block_builder = self.client.new_block_at(parent_hash, Digest::default(), RecordProof::No)?;
+        for inherent in block_builder.create_inherents(inherent_data)? {
+            block_builder.push(inherent)?;
+        }
+        for extrinsic in extrinsics {
+            block_builder.push(extrinsic)?;
+        }
+        let built = block_builder.build()?;
+
+        let starknet_block =
+            find_starknet_block(built.block.header().digest()).map_err(|_| Error::MissingStarknetBlock)?;
+        Ok((starknet_block.header().clone(), built))
+    }
+
+    async fn import_block(&mut self, block: BuiltBlock<B>) -> Result<(), Error> {
+        let (header, body) = block.block.deconstruct();
+        let mut import_params = BlockImportParams::new(BlockOrigin::NetworkInitialSync, header);
+        import_params.body = Some(body);
+        import_params.state_action = StateAction::ApplyChanges(StorageChanges::Changes(block.storage_changes));
+        import_params.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+        import_params.finalized = true;
+        match self.block_import.import_block(import_params).await {
+            Ok(ImportResult::Imported(_)) => Ok(()),
+            Ok(result) => Err(Error::Import(format!("{result:?}"))),
+            Err(e) => Err(Error::Import(e.to_string())),
+        }
+    }
+}
+
+async fn inherent_data(inherents: BlockInherents) -> Result<InherentData, Error> {
+    let timestamp = sp_timestamp::InherentDataProvider::new(Timestamp::new(inherents.timestamp * 1000));
+    let starknet = StarknetInherentDataProvider::new(inherents.starknet);
+
+    Ok((timestamp, starknet).create_inherent_data().await?)
+}
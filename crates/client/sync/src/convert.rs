@@ -0,0 +1,663 @@
+//! Conversion of the feeder gateway transactions into the transactions executed by Madara, and of
+//! the blocks of Madara into the objects served by its feeder gateway.
+//!
+//! Every converted transaction is hashed again with the chain id of this node, a different hash
+//! meaning the transaction would not execute the same way as upstream.
+
+use std::num::NonZeroU128;
+use std::sync::Arc;
+
+use blockifier::transaction::transaction_execution::Transaction as ExecutionTransaction;
+use blockifier::transaction::transactions::{
+    DeclareTransaction, DeployAccountTransaction, InvokeTransaction, L1HandlerTransaction,
+};
+use mc_rpc_core::utils::flattened_sierra_to_sierra_contract_class;
+use mp_block::commitments::BlockStateDiff;
+use mp_block::{Block as StarknetBlock, Header};
+use mp_felt::Felt252Wrapper;
+use mp_transactions::compute_hash::ComputeTransactionHash;
+use mp_transactions::from_broadcasted_transactions::{
+    try_declare_tx_from_broadcasted_declare_tx, try_declare_tx_from_broadcasted_declare_tx_v0,
+    try_deploy_tx_from_broadcasted_deploy_tx, try_invoke_tx_from_broadcasted_invoke_tx,
+    BroadcastedTransactionConversionError,
+};
+use mp_transactions::to_starknet_core_transaction::to_starknet_core_tx;
+use mp_transactions::BroadcastedDeclareTransactionV0;
+use starknet_api::core::EntryPointSelector;
+use starknet_api::hash::StarkHash;
+use starknet_api::state::EntryPointType;
+use starknet_api::transaction::{Calldata, Fee, TransactionHash, TransactionSignature, TransactionVersion};
+use starknet_core::types::{
+    BroadcastedDeclareTransaction, BroadcastedDeclareTransactionV1, BroadcastedDeclareTransactionV2,
+    BroadcastedDeclareTransactionV3, BroadcastedDeployAccountTransaction, BroadcastedDeployAccountTransactionV1,
+    BroadcastedDeployAccountTransactionV3, BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV1,
+    BroadcastedInvokeTransactionV3, EntryPointsByType, FlattenedSierraClass, SierraEntryPoint,
+};
+use starknet_ff::FieldElement;
+
+use crate::error::Error;
+use crate::types;
+
+/// Version of the Sierra classes served by the feeder gateway of Madara.
+const SIERRA_CONTRACT_CLASS_VERSION: &str = "0.1.0";
+
+pub fn invoke_transaction(tx: types::InvokeTransaction, chain_id: Felt252Wrapper) -> Result<InvokeTransaction, Error> {
+    let tx_hash = tx.transaction_hash;
+    let converted = match version(tx.version) {
+        Some(0) => {
+            let entry_point_selector =
+                tx.entry_point_selector.ok_or_else(|| invalid(tx_hash, "missing entry point selector"))?;
+            let tx = starknet_api::transaction::InvokeTransaction::V0(starknet_api::transaction::InvokeTransactionV0 {
+                max_fee: fee(tx_hash, tx.max_fee)?,
+                signature: signature(tx.signature),
+                contract_address: Felt252Wrapper::from(tx.sender_address).into(),
+                entry_point_selector: EntryPointSelector(Felt252Wrapper::from(entry_point_selector).into()),
+                calldata: calldata(tx.calldata),
+            });
+            let computed_hash = tx.compute_hash(chain_id, false);
+            InvokeTransaction { tx, tx_hash: computed_hash, only_query: false }
+        }
+        Some(1) => try_invoke_tx_from_broadcasted_invoke_tx(
+            BroadcastedInvokeTransaction::V1(BroadcastedInvokeTransactionV1 {
+                sender_address: tx.sender_address,
+                calldata: tx.calldata,
+                max_fee: tx.max_fee,
+                signature: tx.signature,
+                nonce: tx.nonce,
+                is_query: false,
+            }),
+            chain_id,
+        )
+        .map_err(conversion(tx_hash))?,
+        Some(3) => try_invoke_tx_from_broadcasted_invoke_tx(
+            BroadcastedInvokeTransaction::V3(BroadcastedInvokeTransactionV3 {
+                sender_address: tx.sender_address,
+                calldata: tx.calldata,
+                signature: tx.signature,
+                nonce: tx.nonce,
+                resource_bounds: resource_bounds(tx_hash, tx.fee_market.resource_bounds)?,
+                tip: tip(tx_hash, tx.fee_market.tip)?,
+                paymaster_data: tx.fee_market.paymaster_data,
+                account_deployment_data: tx.account_deployment_data,
+                nonce_data_availability_mode: tx.fee_market.nonce_data_availability_mode.into(),
+                fee_data_availability_mode: tx.fee_market.fee_data_availability_mode.into(),
+                is_query: false,
+            }),
+            chain_id,
+        )
+        .map_err(conversion(tx_hash))?,
+        _ => return Err(Error::UnsupportedTransaction { kind: "invoke", tx_hash }),
+    };
+
+    check_hash(tx_hash, converted.tx_hash)?;
+    Ok(converted)
+}
+
+/// Convert a declare transaction along the class it declares, fetched from the gateway.
+///
+/// The Sierra class is returned as well for Cairo 1 classes, to be stored by the node.
+pub fn declare_transaction(
+    tx: types::DeclareTransaction,
+    class: types::ContractClass,
+    chain_id: Felt252Wrapper,
+) -> Result<(DeclareTransaction, Option<starknet_api::state::ContractClass>), Error> {
+    let tx_hash = tx.transaction_hash;
+    let (converted, sierra_class) = match (version(tx.version), class) {
+        (Some(0), types::ContractClass::Legacy(class)) => {
+            let converted = try_declare_tx_from_broadcasted_declare_tx_v0(
+                BroadcastedDeclareTransactionV0 {
+                    sender_address: tx.sender_address,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    contract_class: Arc::new(
+                        class.compress().map_err(|e| invalid_class(tx.class_hash, e.to_string()))?,
+                    ),
+                    is_query: false,
+                },
+                chain_id,
+            )
+            .map_err(conversion(tx_hash))?;
+            (converted, None)
+        }
+        (Some(1), types::ContractClass::Legacy(class)) => {
+            let converted = try_declare_tx_from_broadcasted_declare_tx(
+                BroadcastedDeclareTransaction::V1(BroadcastedDeclareTransactionV1 {
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    contract_class: Arc::new(
+                        class.compress().map_err(|e| invalid_class(tx.class_hash, e.to_string()))?,
+                    ),
+                    sender_address: tx.sender_address,
+                    is_query: false,
+                }),
+                chain_id,
+            )
+            .map_err(conversion(tx_hash))?;
+            (converted, None)
+        }
+        (Some(2), types::ContractClass::Sierra(class)) => {
+            let class = Arc::new(flattened_sierra_class(class));
+            let compiled_class_hash =
+                tx.compiled_class_hash.ok_or_else(|| invalid(tx_hash, "missing compiled class hash"))?;
+            let converted = try_declare_tx_from_broadcasted_declare_tx(
+                BroadcastedDeclareTransaction::V2(BroadcastedDeclareTransactionV2 {
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    contract_class: class.clone(),
+                    sender_address: tx.sender_address,
+                    compiled_class_hash,
+                    is_query: false,
+                }),
+                chain_id,
+            )
+            .map_err(conversion(tx_hash))?;
+            (converted, Some(flattened_sierra_to_sierra_contract_class(class)))
+        }
+        (Some(3), types::ContractClass::Sierra(class)) => {
+            let class = Arc::new(flattened_sierra_class(class));
+            let compiled_class_hash =
+                tx.compiled_class_hash.ok_or_else(|| invalid(tx_hash, "missing compiled class hash"))?;
+            let converted = try_declare_tx_from_broadcasted_declare_tx(
+                BroadcastedDeclareTransaction::V3(BroadcastedDeclareTransactionV3 {
+                    sender_address: tx.sender_address,
+                    compiled_class_hash,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    contract_class: class.clone(),
+                    resource_bounds: resource_bounds(tx_hash, tx.fee_market.resource_bounds)?,
+                    tip: tip(tx_hash, tx.fee_market.tip)?,
+                    paymaster_data: tx.fee_market.paymaster_data,
+                    account_deployment_data: tx.account_deployment_data,
+                    nonce_data_availability_mode: tx.fee_market.nonce_data_availability_mode.into(),
+                    fee_data_availability_mode: tx.fee_market.fee_data_availability_mode.into(),
+                    is_query: false,
+                }),
+                chain_id,
+            )
+            .map_err(conversion(tx_hash))?;
+            (converted, Some(flattened_sierra_to_sierra_contract_class(class)))
+        }
+        (Some(0..=1), types::ContractClass::Sierra(_)) | (Some(2..=3), types::ContractClass::Legacy(_)) => {
+            return Err(invalid_class(tx.class_hash, "the class is not of the declared Cairo version".into()));
+        }
+        _ => return Err(Error::UnsupportedTransaction { kind: "declare", tx_hash }),
+    };
+
+    check_hash(tx_hash, converted.tx_hash())?;
+    Ok((converted, sierra_class))
+}
+
+/// The Sierra class stored by the node for a class fetched from the gateway, `None` for the
+/// classes of Cairo 0.
+pub fn sierra_class(class: types::ContractClass) -> Option<starknet_api::state::ContractClass> {
+    match class {
+        types::ContractClass::Sierra(class) => {
+            Some(flattened_sierra_to_sierra_contract_class(Arc::new(flattened_sierra_class(class))))
+        }
+        types::ContractClass::Legacy(_) => None,
+    }
+}
+
+pub fn deploy_account_transaction(
+    tx: types::DeployAccountTransaction,
+    chain_id: Felt252Wrapper,
+) -> Result<DeployAccountTransaction, Error> {
+    let tx_hash = tx.transaction_hash;
+    let broadcasted = match version(tx.version) {
+        Some(1) => BroadcastedDeployAccountTransaction::V1(BroadcastedDeployAccountTransactionV1 {
+            max_fee: tx.max_fee,
+            signature: tx.signature,
+            nonce: tx.nonce,
+            contract_address_salt: tx.contract_address_salt,
+            constructor_calldata: tx.constructor_calldata,
+            class_hash: tx.class_hash,
+            is_query: false,
+        }),
+        Some(3) => BroadcastedDeployAccountTransaction::V3(BroadcastedDeployAccountTransactionV3 {
+            signature: tx.signature,
+            nonce: tx.nonce,
+            contract_address_salt: tx.contract_address_salt,
+            constructor_calldata: tx.constructor_calldata,
+            class_hash: tx.class_hash,
+            resource_bounds: resource_bounds(tx_hash, tx.fee_market.resource_bounds)?,
+            tip: tip(tx_hash, tx.fee_market.tip)?,
+            paymaster_data: tx.fee_market.paymaster_data,
+            nonce_data_availability_mode: tx.fee_market.nonce_data_availability_mode.into(),
+            fee_data_availability_mode: tx.fee_market.fee_data_availability_mode.into(),
+            is_query: false,
+        }),
+        _ => return Err(Error::UnsupportedTransaction { kind: "deploy account", tx_hash }),
+    };
+    let converted = try_deploy_tx_from_broadcasted_deploy_tx(broadcasted, chain_id).map_err(conversion(tx_hash))?;
+
+    check_hash(tx_hash, converted.tx_hash)?;
+    Ok(converted)
+}
+
+/// Convert an L1 handler transaction, the fee paid on L1 being served apart, see
+/// [`paid_fee_on_l1`].
+pub fn l1_handler_transaction(
+    tx: types::L1HandlerTransaction,
+    paid_fee_on_l1: Fee,
+    chain_id: Felt252Wrapper,
+) -> Result<L1HandlerTransaction, Error> {
+    let tx_hash = tx.transaction_hash;
+    let converted = starknet_api::transaction::L1HandlerTransaction {
+        version: TransactionVersion(Felt252Wrapper::from(tx.version).into()),
+        nonce: Felt252Wrapper::from(tx.nonce).into(),
+        contract_address: Felt252Wrapper::from(tx.contract_address).into(),
+        entry_point_selector: EntryPointSelector(Felt252Wrapper::from(tx.entry_point_selector).into()),
+        calldata: calldata(tx.calldata),
+    };
+    let computed_hash = converted.compute_hash(chain_id, false);
+
+    check_hash(tx_hash, computed_hash)?;
+    Ok(L1HandlerTransaction { tx: converted, tx_hash: computed_hash, paid_fee_on_l1 })
+}
+
+/// The fee paid on L1 for an L1 handler, `actual_fee` being the fee charged for its execution.
+///
+/// Only the feeder gateway of Madara serves the fee paid on L1. For other gateways it is
+/// approximated by the fee charged upstream, which the fee paid on L1 covered, and is at least 1 as
+/// the L1 handlers paying no fee are rejected. Neither the block hash nor the receipt commit to
+/// this fee, so the approximation is only visible through this node.
+pub fn paid_fee_on_l1(tx: &types::L1HandlerTransaction, actual_fee: FieldElement) -> Result<Fee, Error> {
+    if let Some(paid_fee_on_l1) = tx.paid_fee_on_l1 {
+        return fee(tx.transaction_hash, paid_fee_on_l1);
+    }
+    match u128::try_from(Felt252Wrapper::from(actual_fee)) {
+        Ok(0) | Err(_) => Ok(Fee(1)),
+        Ok(fee) => Ok(Fee(fee)),
+    }
+}
+
+/// The gateway block of a block of this node, `actual_fees` being the fee charged for each of its
+/// transactions.
+///
+/// The Starknet version is left out, as the protocol version of Madara is not one.
+pub fn gateway_block(block: &StarknetBlock, actual_fees: &[Fee]) -> types::Block {
+    let header = block.header();
+    let price = |price: NonZeroU128| FieldElement::from(price.get());
+
+    types::Block {
+        block_hash: header.hash().into(),
+        block_number: header.block_number,
+        parent_block_hash: felt(header.parent_block_hash),
+        state_root: Some(felt(header.global_state_root)),
+        timestamp: header.block_timestamp,
+        sequencer_address: Some(felt(header.sequencer_address)),
+        l1_gas_price: types::ResourcePrice {
+            price_in_wei: price(header.l1_gas_price.eth_l1_gas_price),
+            price_in_fri: price(header.l1_gas_price.strk_l1_gas_price),
+        },
+        l1_data_gas_price: types::ResourcePrice {
+            price_in_wei: price(header.l1_gas_price.eth_l1_data_gas_price),
+            price_in_fri: price(header.l1_gas_price.strk_l1_data_gas_price),
+        },
+        transactions: block.transactions().iter().cloned().map(gateway_transaction).collect(),
+        transaction_receipts: block
+            .transactions_hashes()
+            .zip(actual_fees)
+            .map(|(transaction_hash, actual_fee)| types::TransactionReceipt {
+                transaction_hash: felt(*transaction_hash),
+                actual_fee: FieldElement::from(actual_fee.0),
+            })
+            .collect(),
+        starknet_version: None,
+    }
+}
+
+/// The gateway transaction of a transaction of this node.
+pub fn gateway_transaction(tx: ExecutionTransaction) -> types::Transaction {
+    use starknet_core::types::{DeclareTransaction, DeployAccountTransaction, InvokeTransaction, Transaction};
+
+    let tx = match tx {
+        // Converted from the executed transaction, which keeps the fee paid on L1
+        ExecutionTransaction::L1HandlerTransaction(tx) => {
+            return types::Transaction::L1Handler(types::L1HandlerTransaction {
+                transaction_hash: felt(tx.tx_hash),
+                version: felt(tx.tx.version),
+                contract_address: felt(tx.tx.contract_address),
+                entry_point_selector: felt(tx.tx.entry_point_selector),
+                nonce: felt(tx.tx.nonce),
+                calldata: tx.tx.calldata.0.iter().map(|value| felt(*value)).collect(),
+                paid_fee_on_l1: Some(FieldElement::from(tx.paid_fee_on_l1.0)),
+            });
+        }
+        tx @ ExecutionTransaction::AccountTransaction(_) => to_starknet_core_tx(tx),
+    };
+
+    match tx {
+        Transaction::Invoke(InvokeTransaction::V0(tx)) => types::Transaction::Invoke(types::InvokeTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::ZERO,
+            sender_address: tx.contract_address,
+            entry_point_selector: Some(tx.entry_point_selector),
+            calldata: tx.calldata,
+            signature: tx.signature,
+            max_fee: tx.max_fee,
+            nonce: FieldElement::ZERO,
+            account_deployment_data: Vec::new(),
+            fee_market: types::FeeMarketFields::default(),
+        }),
+        Transaction::Invoke(InvokeTransaction::V1(tx)) => types::Transaction::Invoke(types::InvokeTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::ONE,
+            sender_address: tx.sender_address,
+            entry_point_selector: None,
+            calldata: tx.calldata,
+            signature: tx.signature,
+            max_fee: tx.max_fee,
+            nonce: tx.nonce,
+            account_deployment_data: Vec::new(),
+            fee_market: types::FeeMarketFields::default(),
+        }),
+        Transaction::Invoke(InvokeTransaction::V3(tx)) => types::Transaction::Invoke(types::InvokeTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::THREE,
+            sender_address: tx.sender_address,
+            entry_point_selector: None,
+            calldata: tx.calldata,
+            signature: tx.signature,
+            max_fee: FieldElement::ZERO,
+            nonce: tx.nonce,
+            account_deployment_data: tx.account_deployment_data,
+            fee_market: fee_market(
+                tx.resource_bounds,
+                tx.tip,
+                tx.paymaster_data,
+                tx.nonce_data_availability_mode,
+                tx.fee_data_availability_mode,
+            ),
+        }),
+        Transaction::Declare(DeclareTransaction::V0(tx)) => types::Transaction::Declare(types::DeclareTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::ZERO,
+            class_hash: tx.class_hash,
+            compiled_class_hash: None,
+            sender_address: tx.sender_address,
+            signature: tx.signature,
+            max_fee: tx.max_fee,
+            nonce: FieldElement::ZERO,
+            account_deployment_data: Vec::new(),
+            fee_market: types::FeeMarketFields::default(),
+        }),
+        Transaction::Declare(DeclareTransaction::V1(tx)) => types::Transaction::Declare(types::DeclareTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::ONE,
+            class_hash: tx.class_hash,
+            compiled_class_hash: None,
+            sender_address: tx.sender_address,
+            signature: tx.signature,
+            max_fee: tx.max_fee,
+            nonce: tx.nonce,
+            account_deployment_data: Vec::new(),
+            fee_market: types::FeeMarketFields::default(),
+        }),
+        Transaction::Declare(DeclareTransaction::V2(tx)) => types::Transaction::Declare(types::DeclareTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::TWO,
+            class_hash: tx.class_hash,
+            compiled_class_hash: Some(tx.compiled_class_hash),
+            sender_address: tx.sender_address,
+            signature: tx.signature,
+            max_fee: tx.max_fee,
+            nonce: tx.nonce,
+            account_deployment_data: Vec::new(),
+            fee_market: types::FeeMarketFields::default(),
+        }),
+        Transaction::Declare(DeclareTransaction::V3(tx)) => types::Transaction::Declare(types::DeclareTransaction {
+            transaction_hash: tx.transaction_hash,
+            version: FieldElement::THREE,
+            class_hash: tx.class_hash,
+            compiled_class_hash: Some(tx.compiled_class_hash),
+            sender_address: tx.sender_address,
+            signature: tx.signature,
+            max_fee: FieldElement::ZERO,
+            nonce: tx.nonce,
+            account_deployment_data: tx.account_deployment_data,
+            fee_market: fee_market(
+                tx.resource_bounds,
+                tx.tip,
+                tx.paymaster_data,
+                tx.nonce_data_availability_mode,
+                tx.fee_data_availability_mode,
+            ),
+        }),
+        Transaction::DeployAccount(DeployAccountTransaction::V1(tx)) => {
+            types::Transaction::DeployAccount(types::DeployAccountTransaction {
+                transaction_hash: tx.transaction_hash,
+                version: FieldElement::ONE,
+                class_hash: tx.class_hash,
+                contract_address_salt: tx.contract_address_salt,
+                constructor_calldata: tx.constructor_calldata,
+                signature: tx.signature,
+                max_fee: tx.max_fee,
+                nonce: tx.nonce,
+                fee_market: types::FeeMarketFields::default(),
+            })
+        }
+        Transaction::DeployAccount(DeployAccountTransaction::V3(tx)) => {
+            types::Transaction::DeployAccount(types::DeployAccountTransaction {
+                transaction_hash: tx.transaction_hash,
+                version: FieldElement::THREE,
+                class_hash: tx.class_hash,
+                contract_address_salt: tx.contract_address_salt,
+                constructor_calldata: tx.constructor_calldata,
+                signature: tx.signature,
+                max_fee: FieldElement::ZERO,
+                nonce: tx.nonce,
+                fee_market: fee_market(
+                    tx.resource_bounds,
+                    tx.tip,
+                    tx.paymaster_data,
+                    tx.nonce_data_availability_mode,
+                    tx.fee_data_availability_mode,
+                ),
+            })
+        }
+        Transaction::L1Handler(_) | Transaction::Deploy(_) => {
+            unreachable!("Account transactions are not converted to L1 handler or deploy transactions")
+        }
+    }
+}
+
+/// The gateway state update of a block of this node, `old_root` being the state root of its parent.
+///
+/// Replaced classes are served with the deployed contracts, as the state diff of the block does not
+/// tell them apart.
+pub fn gateway_state_update(header: &Header, old_root: StarkHash, state_diff: &BlockStateDiff) -> types::StateUpdate {
+    types::StateUpdate {
+        block_hash: header.hash().into(),
+        new_root: felt(header.global_state_root),
+        old_root: felt(old_root),
+        state_diff: types::StateDiff {
+            storage_diffs: state_diff
+                .storage_diffs
+                .iter()
+                .map(|(address, updates)| {
+                    let entries = updates
+                        .iter()
+                        .map(|(key, value)| types::StorageEntry { key: felt(*key), value: felt(*value) })
+                        .collect();
+                    (felt(*address), entries)
+                })
+                .collect(),
+            nonces: state_diff.nonces.iter().map(|(address, nonce)| (felt(*address), felt(*nonce))).collect(),
+            deployed_contracts: state_diff
+                .deployed_contracts
+                .iter()
+                .map(|(address, class_hash)| types::DeployedContract {
+                    address: felt(*address),
+                    class_hash: felt(*class_hash),
+                })
+                .collect(),
+            replaced_classes: Vec::new(),
+            old_declared_contracts: state_diff
+                .deprecated_declared_classes
+                .iter()
+                .map(|class_hash| felt(*class_hash))
+                .collect(),
+            declared_classes: state_diff
+                .declared_classes
+                .iter()
+                .map(|(class_hash, compiled_class_hash)| types::DeclaredClass {
+                    class_hash: felt(*class_hash),
+                    compiled_class_hash: felt(*compiled_class_hash),
+                })
+                .collect(),
+        },
+    }
+}
+
+/// The gateway class of a Sierra class stored by this node.
+///
+/// The contract class version is not stored, every Sierra class declared so far is of version
+/// `0.1.0`.
+pub fn gateway_sierra_class(class: starknet_api::state::ContractClass) -> types::SierraClass {
+    let entry_points = |entry_point_type| {
+        class
+            .entry_points_by_type
+            .get(&entry_point_type)
+            .into_iter()
+            .flatten()
+            .map(|entry_point| SierraEntryPoint {
+                selector: felt(entry_point.selector),
+                function_idx: entry_point.function_idx.0,
+            })
+            .collect()
+    };
+
+    types::SierraClass {
+        sierra_program: class.sierra_program.iter().map(|value| felt(*value)).collect(),
+        contract_class_version: SIERRA_CONTRACT_CLASS_VERSION.to_string(),
+        entry_points_by_type: types::SierraEntryPoints {
+            constructor: entry_points(EntryPointType::Constructor),
+            external: entry_points(EntryPointType::External),
+            l1_handler: entry_points(EntryPointType::L1Handler),
+        },
+        abi: class.abi,
+    }
+}
+
+fn fee_market(
+    resource_bounds: starknet_core::types::ResourceBoundsMapping,
+    tip: u64,
+    paymaster_data: Vec<FieldElement>,
+    nonce_data_availability_mode: starknet_core::types::DataAvailabilityMode,
+    fee_data_availability_mode: starknet_core::types::DataAvailabilityMode,
+) -> types::FeeMarketFields {
+    let bounds = |bounds: starknet_core::types::ResourceBounds| types::ResourceBounds {
+        max_amount: FieldElement::from(bounds.max_amount),
+        max_price_per_unit: FieldElement::from(bounds.max_price_per_unit),
+    };
+
+    types::FeeMarketFields {
+        resource_bounds: Some(types::ResourceBoundsMapping {
+            l1_gas: bounds(resource_bounds.l1_gas),
+            l2_gas: bounds(resource_bounds.l2_gas),
+        }),
+        tip: FieldElement::from(tip),
+        paymaster_data,
+        nonce_data_availability_mode: nonce_data_availability_mode.into(),
+        fee_data_availability_mode: fee_data_availability_mode.into(),
+    }
+}
+
+fn flattened_sierra_class(class: types::SierraClass) -> FlattenedSierraClass {
+    FlattenedSierraClass {
+        sierra_program: class.sierra_program,
+        contract_class_version: class.contract_class_version,
+        entry_points_by_type: EntryPointsByType {
+            constructor: class.entry_points_by_type.constructor,
+            external: class.entry_points_by_type.external,
+            l1_handler: class.entry_points_by_type.l1_handler,
+        },
+        abi: class.abi,
+    }
+}
+
+impl From<types::DataAvailabilityMode> for starknet_core::types::DataAvailabilityMode {
+    fn from(mode: types::DataAvailabilityMode) -> Self {
+        match mode {
+            types::DataAvailabilityMode::L1 => Self::L1,
+            types::DataAvailabilityMode::L2 => Self::L2,
+        }
+    }
+}
+
+impl From<starknet_core::types::DataAvailabilityMode> for types::DataAvailabilityMode {
+    fn from(mode: starknet_core::types::DataAvailabilityMode) -> Self {
+        match mode {
+            starknet_core::types::DataAvailabilityMode::L1 => Self::L1,
+            starknet_core::types::DataAvailabilityMode::L2 => Self::L2,
+        }
+    }
+}
+
+fn resource_bounds(
+    tx_hash: FieldElement,
+    resource_bounds: Option<types::ResourceBoundsMapping>,
+) -> Result<starknet_core::types::ResourceBoundsMapping, Error> {
+    let resource_bounds = resource_bounds.ok_or_else(|| invalid(tx_hash, "missing resource bounds"))?;
+    let convert = |bounds: types::ResourceBounds| -> Result<_, Error> {
+        Ok(starknet_core::types::ResourceBounds {
+            max_amount: Felt252Wrapper::from(bounds.max_amount)
+                .try_into()
+                .map_err(|_| invalid(tx_hash, "max amount too large"))?,
+            max_price_per_unit: Felt252Wrapper::from(bounds.max_price_per_unit)
+                .try_into()
+                .map_err(|_| invalid(tx_hash, "max price per unit too large"))?,
+        })
+    };
+    Ok(starknet_core::types::ResourceBoundsMapping {
+        l1_gas: convert(resource_bounds.l1_gas)?,
+        l2_gas: convert(resource_bounds.l2_gas)?,
+    })
+}
+
+fn version(version: FieldElement) -> Option<u64> {
+    Felt252Wrapper::from(version).try_into().ok()
+}
+
+fn tip(tx_hash: FieldElement, tip: FieldElement) -> Result<u64, Error> {
+    Felt252Wrapper::from(tip).try_into().map_err(|_| invalid(tx_hash, "tip too large"))
+}
+
+fn fee(tx_hash: FieldElement, max_fee: FieldElement) -> Result<Fee, Error> {
+    Ok(Fee(Felt252Wrapper::from(max_fee).try_into().map_err(|_| invalid(tx_hash, "max fee too large"))?))
+}
+
+fn signature(signature: Vec<FieldElement>) -> TransactionSignature {
+    TransactionSignature(signature.into_iter().map(|v| Felt252Wrapper::from(v).into()).collect())
+}
+
+fn calldata(calldata: Vec<FieldElement>) -> Calldata {
+    Calldata(Arc::new(calldata.into_iter().map(|v| Felt252Wrapper::from(v).into()).collect()))
+}
+
+fn felt(value: impl Into<Felt252Wrapper>) -> FieldElement {
+    value.into().into()
+}
+
+fn check_hash(expected: FieldElement, computed: TransactionHash) -> Result<(), Error> {
+    let computed = Felt252Wrapper::from(computed.0).into();
+    if computed != expected {
+        return Err(Error::TransactionHashMismatch { expected, computed });
+    }
+    Ok(())
+}
+
+fn invalid(tx_hash: FieldElement, reason: &str) -> Error {
+    Error::InvalidTransaction { tx_hash, reason: reason.into() }
+}
+
+fn invalid_class(class_hash: FieldElement, reason: String) -> Error {
+    Error::InvalidClass { class_hash, reason }
+}
+
+fn conversion(tx_hash: FieldElement) -> impl FnOnce(BroadcastedTransactionConversionError) -> Error {
+    move |source| Error::TransactionConversion { tx_hash, source }
+}
@@ -0,0 +1,78 @@
+use mc_db::DbError;
+use mp_transactions::from_broadcasted_transactions::BroadcastedTransactionConversionError;
+use sp_api::ApiError;
+use starknet_ff::FieldElement;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to query the feeder gateway: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Invalid feeder gateway url: {0}")]
+    InvalidUrl(String),
+    #[error("Feeder gateway error {code}: {message}")]
+    Gateway { code: String, message: String },
+    #[error("Failed to deserialize the feeder gateway response: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("Block {0} is not served by the upstream sequencer")]
+    MissingBlock(u64),
+    #[error("Failed to query the upstream peer: {0}")]
+    Peer(#[from] mc_p2p::error::Error),
+    #[error("Invalid response of the upstream peer: {0}")]
+    InvalidPeerResponse(String),
+    #[error("Unsupported {kind} transaction {tx_hash:#x}")]
+    UnsupportedTransaction { kind: &'static str, tx_hash: FieldElement },
+    #[error("Invalid transaction {tx_hash:#x}: {reason}")]
+    InvalidTransaction { tx_hash: FieldElement, reason: String },
+    #[error("Failed to convert transaction {tx_hash:#x}: {source}")]
+    TransactionConversion {
+        tx_hash: FieldElement,
+        #[source]
+        source: BroadcastedTransactionConversionError,
+    },
+    #[error("Transaction {expected:#x} is hashed to {computed:#x} by this node")]
+    TransactionHashMismatch { expected: FieldElement, computed: FieldElement },
+    #[error("Failed to convert the declared class {class_hash:#x}: {reason}")]
+    InvalidClass { class_hash: FieldElement, reason: String },
+    #[error("Block {block_number} has parent {found:#x}, expected {expected:#x}")]
+    ParentMismatch { block_number: u64, expected: FieldElement, found: FieldElement },
+    #[error("Block {block_number} has hash {expected:#x} upstream but {computed:#x} once imported")]
+    BlockHashMismatch { block_number: u64, expected: FieldElement, computed: FieldElement },
+    #[error("Block {block_number} has state root {expected:#x} upstream but {computed:#x} once imported")]
+    StateRootMismatch { block_number: u64, expected: FieldElement, computed: FieldElement },
+    #[error("Block {block_number} is served without its state root")]
+    MissingStateRoot { block_number: u64 },
+    #[error("The state diff of block {block_number} differs from the upstream state update")]
+    StateDiffMismatch { block_number: u64 },
+    #[error(
+        "The local genesis {local:#x} is not the upstream block 0 {upstream:#x}, only a Madara sequencer using the \
+         same chain spec can be synced from"
+    )]
+    GenesisMismatch { local: FieldElement, upstream: FieldElement },
+    #[error("The best block {best} does not follow the last block synced from upstream {last_synced:?}")]
+    LocalChainMismatch { best: u64, last_synced: Option<u64> },
+    #[error("No Starknet block in the digest of a local block")]
+    MissingStarknetBlock,
+    #[error("Failed to use Runtime API: {0}")]
+    RuntimeApi(#[from] ApiError),
+    #[error("Blockchain error: {0}")]
+    Blockchain(#[from] sp_blockchain::Error),
+    #[error("Failed to create the inherents: {0}")]
+    Inherents(#[from] sp_inherents::Error),
+    #[error("Failed to import the block: {0}")]
+    Import(String),
+    #[error("Madara DB error: {0}")]
+    Db(#[from] DbError),
+}
+
+impl Error {
+    /// Whether syncing can be retried later, the other errors require an operator.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Http(_)
+                | Self::Gateway { .. }
+                | Self::MissingBlock(_)
+                | Self::Peer(mc_p2p::error::Error::Request { .. })
+        )
+    }
+}
@@ -0,0 +1,72 @@
+//! Client of the feeder gateway of a Starknet sequencer.
+
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
+use starknet_ff::FieldElement;
+
+use crate::error::Error;
+use crate::types::{Block, ContractClass, GatewayError, StateUpdate};
+
+/// Code returned for the blocks the sequencer has not produced yet.
+pub(crate) const BLOCK_NOT_FOUND: &str = "StarknetErrorCode.BLOCK_NOT_FOUND";
+
+#[derive(Clone, Debug)]
+pub struct FeederGateway {
+    client: reqwest::Client,
+    /// The url the `feeder_gateway/` endpoints are relative to.
+    base_url: Url,
+}
+
+impl FeederGateway {
+    /// A client of the feeder gateway served at `<base_url>/feeder_gateway`.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let mut base_url = Url::parse(base_url).map_err(|e| Error::InvalidUrl(format!("{base_url}: {e}")))?;
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Ok(Self { client: reqwest::Client::new(), base_url })
+    }
+
+    /// The block `block_number`, `None` if the sequencer hasn't produced it yet.
+    pub async fn block(&self, block_number: u64) -> Result<Option<Block>, Error> {
+        self.get("get_block", &[("blockNumber", block_number.to_string())]).await
+    }
+
+    pub async fn state_update(&self, block_number: u64) -> Result<StateUpdate, Error> {
+        self.get("get_state_update", &[("blockNumber", block_number.to_string())])
+            .await?
+            .ok_or(Error::MissingBlock(block_number))
+    }
+
+    /// The class `class_hash`, as declared at block `block_number`.
+    pub async fn class_by_hash(&self, class_hash: FieldElement, block_number: u64) -> Result<ContractClass, Error> {
+        self.get(
+            "get_class_by_hash",
+            &[("classHash", format!("{class_hash:#x}")), ("blockNumber", block_number.to_string())],
+        )
+        .await?
+        .ok_or(Error::MissingBlock(block_number))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, method: &str, query: &[(&str, String)]) -> Result<Option<T>, Error> {
+        let url = self
+            .base_url
+            .join(&format!("feeder_gateway/{method}"))
+            .map_err(|e| Error::InvalidUrl(format!("{}: {e}", self.base_url)))?;
+        let response = self.client.get(url).query(query).send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        if status == StatusCode::OK {
+            return Ok(Some(serde_json::from_slice(&body)?));
+        }
+        match serde_json::from_slice::<GatewayError>(&body) {
+            Ok(error) if error.code == BLOCK_NOT_FOUND => Ok(None),
+            Ok(GatewayError { code, message }) => Err(Error::Gateway { code, message }),
+            Err(_) => {
+                Err(Error::Gateway { code: status.to_string(), message: String::from_utf8_lossy(&body).into_owned() })
+            }
+        }
+    }
+}
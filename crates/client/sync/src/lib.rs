@@ -0,0 +1,33 @@
+//! Sync of a full node from an upstream Starknet sequencer, through its feeder gateway or from a
+//! peer serving the p2p sync protocols, see [`peer`].
+//!
+//! The blocks served upstream are executed again by the node, and imported only if the resulting
+//! Starknet block, state diff and block hash are the upstream ones. The upstream sequencer is a
+//! Madara node serving its feeder gateway, see [`server`], or its blocks over p2p, and using the
+//! same chain spec: the local genesis must be the upstream block 0, so the public Starknet
+//! networks, whose genesis state is not the one of a Madara chain, can't be synced from: the worker
+//! checks the upstream genesis before syncing anything and stops, with the node, if it is another
+//! one. Syncing this way has some other limitations:
+//! - Madara only serves the Sierra classes, and the peers serve the Cairo 0 classes without their
+//!   ABI, so the blocks declaring a Cairo 0 class can't be synced;
+//! - the fee paid on L1 by the L1 handlers is only served by the feeder gateway of Madara, it is
+//!   approximated for the other gateways and the peers, see [`convert::paid_fee_on_l1`];
+//! - legacy `DEPLOY` transactions are not supported;
+//! - upstream blocks closer than the minimum period of `pallet_timestamp` can't be imported.
+
+pub mod chain;
+pub mod convert;
+pub mod error;
+pub mod gateway;
+pub mod peer;
+pub mod server;
+pub mod types;
+pub mod upstream;
+pub mod verify;
+mod worker;
+
+#[cfg(test)]
+mod tests;
+
+pub use upstream::Upstream;
+pub use worker::GatewaySyncWorker;
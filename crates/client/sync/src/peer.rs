@@ -0,0 +1,488 @@
+//! Sync from a peer serving its blocks on the Starknet p2p sync protocols, see [`mc_p2p`].
+//!
+//! The messages of the peer are converted to the objects of the feeder gateway, so that the blocks
+//! are executed and checked the same way. Each request asks for the data of a single block, or of a
+//! block and its parent for the headers of a state update.
+
+use async_trait::async_trait;
+use mc_p2p::codec::{Protocol, Request, Response};
+use mc_p2p::proto::iteration::{Direction, Start};
+use mc_p2p::proto::state_diffs_response::StateDiffMessage;
+use mc_p2p::proto::{self, Iteration};
+use mc_p2p::{NetworkHandle, PeerId};
+use mp_felt::Felt252Wrapper;
+use starknet_core::types::SierraEntryPoint;
+use starknet_ff::FieldElement;
+
+use crate::error::Error;
+use crate::types::{
+    Block, ContractClass, DataAvailabilityMode, DeclareTransaction, DeclaredClass, DeployAccountTransaction,
+    DeployTransaction, DeployedContract, FeeMarketFields, InvokeTransaction, L1HandlerTransaction, ResourceBounds,
+    ResourceBoundsMapping, ResourcePrice, SierraClass, SierraEntryPoints, StateDiff, StateUpdate, StorageEntry,
+    Transaction, TransactionReceipt,
+};
+use crate::upstream::Upstream;
+
+/// Serves the blocks of a peer, which must have been added to the network of the node.
+pub struct PeerUpstream {
+    network: NetworkHandle,
+    peer: PeerId,
+}
+
+impl PeerUpstream {
+    pub fn new(network: NetworkHandle, peer: PeerId) -> Self {
+        Self { network, peer }
+    }
+
+    /// The data served on `protocol` for block `block_number` and the `limit - 1` blocks before it.
+    async fn request(&self, protocol: Protocol, block_number: u64, limit: u64) -> Result<Response, Error> {
+        let iteration = Iteration {
+            direction: Direction::Backward as i32,
+            limit,
+            step: 1,
+            start: Some(Start::BlockNumber(block_number)),
+        };
+        Ok(self.network.request(self.peer, Request { protocol, iteration }).await?)
+    }
+
+    /// The headers of block `block_number` and of the `limit - 1` blocks before it, those the peer
+    /// has.
+    async fn headers(&self, block_number: u64, limit: u64) -> Result<Vec<proto::SignedBlockHeader>, Error> {
+        let Response::Headers(headers) = self.request(Protocol::Headers, block_number, limit).await? else {
+            unreachable!("The responses are read for the protocol of their request");
+        };
+        if let Some(header) = headers.first() {
+            if header.number != block_number {
+                return Err(invalid(format!("Header of block {} served for block {block_number}", header.number)));
+            }
+        }
+        Ok(headers)
+    }
+}
+
+#[async_trait]
+impl Upstream for PeerUpstream {
+    /// The block, once the peer has indexed its receipts.
+    async fn block(&self, block_number: u64) -> Result<Option<Block>, Error> {
+        let Some(header) = self.headers(block_number, 1).await?.into_iter().next() else {
+            return Ok(None);
+        };
+        let Response::Transactions(transactions) = self.request(Protocol::Transactions, block_number, 1).await? else {
+            unreachable!("The responses are read for the protocol of their request");
+        };
+        let Response::Receipts(receipts) = self.request(Protocol::Receipts, block_number, 1).await? else {
+            unreachable!("The responses are read for the protocol of their request");
+        };
+
+        let transaction_count = header.transactions.as_ref().map_or(0, |transactions| transactions.n_leaves);
+        if transactions.len() as u64 != transaction_count {
+            return Err(invalid(format!(
+                "{} transactions served for block {block_number}, {transaction_count} in its header",
+                transactions.len()
+            )));
+        }
+        if receipts.len() != transactions.len() {
+            return Ok(None);
+        }
+
+        block(header, transactions, receipts).map(Some)
+    }
+
+    async fn state_update(&self, block_number: u64) -> Result<StateUpdate, Error> {
+        let mut headers = self.headers(block_number, 2).await?.into_iter();
+        let header = headers.next().ok_or(Error::MissingBlock(block_number))?;
+        let old_root = match headers.next() {
+            Some(parent) => felt(parent.state_root, "state root")?,
+            None => FieldElement::ZERO,
+        };
+        let Response::StateDiffs(messages) = self.request(Protocol::StateDiffs, block_number, 1).await? else {
+            unreachable!("The responses are read for the protocol of their request");
+        };
+
+        // An empty response is also served for a state diff the peer hasn't stored yet
+        let state_diff_length =
+            header.state_diff_commitment.as_ref().map_or(0, |commitment| commitment.state_diff_length);
+        if messages.is_empty() && state_diff_length != 0 {
+            return Err(Error::MissingBlock(block_number));
+        }
+
+        Ok(StateUpdate {
+            block_hash: felt(header.block_hash, "block hash")?,
+            new_root: felt(header.state_root, "state root")?,
+            old_root,
+            state_diff: state_diff(messages)?,
+        })
+    }
+
+    /// The class, if it is a Sierra one: the peers don't serve the ABI of the Cairo 0 classes,
+    /// which is part of their hash.
+    async fn class_by_hash(&self, class_hash: FieldElement, block_number: u64) -> Result<ContractClass, Error> {
+        let Response::Classes(classes) = self.request(Protocol::Classes, block_number, 1).await? else {
+            unreachable!("The responses are read for the protocol of their request");
+        };
+
+        for class in classes {
+            if felt(class.class_hash, "class hash")? != class_hash {
+                continue;
+            }
+            return match class.class {
+                Some(proto::class::Class::Cairo1(class)) => Ok(ContractClass::Sierra(sierra_class(class)?)),
+                Some(proto::class::Class::Cairo0(_)) => Err(Error::InvalidClass {
+                    class_hash,
+                    reason: "Cairo 0 classes are served without their ABI".into(),
+                }),
+                None => Err(invalid(format!("Empty class {class_hash:#x}"))),
+            };
+        }
+
+        Err(invalid(format!("Class {class_hash:#x} not served with block {block_number}")))
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidPeerResponse(reason)
+}
+
+/// The messages holding a felt as big-endian bytes.
+trait Bytes {
+    fn bytes(&self) -> &[u8];
+}
+
+impl Bytes for proto::Felt252 {
+    fn bytes(&self) -> &[u8] {
+        &self.elements
+    }
+}
+
+impl Bytes for proto::Hash {
+    fn bytes(&self) -> &[u8] {
+        &self.elements
+    }
+}
+
+impl Bytes for proto::Address {
+    fn bytes(&self) -> &[u8] {
+        &self.elements
+    }
+}
+
+/// The felt of `field`, which must be set.
+fn felt<T: Bytes>(value: Option<T>, field: &str) -> Result<FieldElement, Error> {
+    let value = value.ok_or_else(|| invalid(format!("Missing {field}")))?;
+    FieldElement::from_byte_slice_be(value.bytes()).map_err(|_| invalid(format!("Invalid {field}")))
+}
+
+fn felts<T: Bytes>(values: Vec<T>) -> Result<Vec<FieldElement>, Error> {
+    values.into_iter().map(|value| felt(Some(value), "felt")).collect()
+}
+
+fn signature(signature: Option<proto::AccountSignature>) -> Result<Vec<FieldElement>, Error> {
+    felts(signature.map(|signature| signature.parts).unwrap_or_default())
+}
+
+fn uint128(value: Option<proto::Uint128>, field: &str) -> Result<FieldElement, Error> {
+    let value = value.ok_or_else(|| invalid(format!("Missing {field}")))?;
+    Ok(Felt252Wrapper::from(((value.high as u128) << 64) | value.low as u128).into())
+}
+
+fn block(
+    header: proto::SignedBlockHeader,
+    transactions: Vec<proto::Transaction>,
+    receipts: Vec<proto::Receipt>,
+) -> Result<Block, Error> {
+    let transactions = transactions.into_iter().map(transaction).collect::<Result<Vec<_>, _>>()?;
+    let transaction_receipts = transactions
+        .iter()
+        .zip(receipts)
+        .map(|(tx, receipt)| {
+            Ok(TransactionReceipt { transaction_hash: tx.transaction_hash(), actual_fee: actual_fee(receipt)? })
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(Block {
+        block_hash: felt(header.block_hash, "block hash")?,
+        block_number: header.number,
+        parent_block_hash: felt(header.parent_hash, "parent hash")?,
+        state_root: Some(felt(header.state_root, "state root")?),
+        timestamp: header.time,
+        sequencer_address: Some(felt(header.sequencer_address, "sequencer address")?),
+        l1_gas_price: ResourcePrice {
+            price_in_wei: uint128(header.gas_price_wei, "gas price in wei")?,
+            price_in_fri: uint128(header.gas_price_fri, "gas price in fri")?,
+        },
+        l1_data_gas_price: ResourcePrice {
+            price_in_wei: uint128(header.data_gas_price_wei, "data gas price in wei")?,
+            price_in_fri: uint128(header.data_gas_price_fri, "data gas price in fri")?,
+        },
+        transactions,
+        transaction_receipts,
+        starknet_version: Some(header.protocol_version),
+    })
+}
+
+fn actual_fee(receipt: proto::Receipt) -> Result<FieldElement, Error> {
+    use proto::receipt::Type;
+
+    let common = match receipt.r#type {
+        Some(Type::Invoke(receipt)) => receipt.common,
+        Some(Type::L1Handler(receipt)) => receipt.common,
+        Some(Type::Declare(receipt)) => receipt.common,
+        Some(Type::DeprecatedDeploy(receipt)) => receipt.common,
+        Some(Type::DeployAccount(receipt)) => receipt.common,
+        None => None,
+    };
+    felt(common.and_then(|common| common.actual_fee), "actual fee")
+}
+
+fn data_availability_mode(domain: i32) -> Result<DataAvailabilityMode, Error> {
+    match domain {
+        domain if domain == proto::VolitionDomain::L1 as i32 => Ok(DataAvailabilityMode::L1),
+        domain if domain == proto::VolitionDomain::L2 as i32 => Ok(DataAvailabilityMode::L2),
+        domain => Err(invalid(format!("Unknown volition domain {domain}"))),
+    }
+}
+
+fn fee_market(
+    resource_bounds: Option<proto::ResourceBounds>,
+    tip: u64,
+    paymaster_data: Vec<proto::Felt252>,
+    nonce_data_availability_mode: i32,
+    fee_data_availability_mode: i32,
+) -> Result<FeeMarketFields, Error> {
+    let resource_bounds = resource_bounds.ok_or_else(|| invalid("Missing resource bounds".into()))?;
+    let limits = |limits: Option<proto::ResourceLimits>| {
+        let limits = limits.ok_or_else(|| invalid("Missing resource limits".into()))?;
+        Ok::<_, Error>(ResourceBounds {
+            max_amount: felt(limits.max_amount, "max amount")?,
+            max_price_per_unit: felt(limits.max_price_per_unit, "max price per unit")?,
+        })
+    };
+
+    Ok(FeeMarketFields {
+        resource_bounds: Some(ResourceBoundsMapping {
+            l1_gas: limits(resource_bounds.l1_gas)?,
+            l2_gas: limits(resource_bounds.l2_gas)?,
+        }),
+        tip: Felt252Wrapper::from(tip).into(),
+        paymaster_data: felts(paymaster_data)?,
+        nonce_data_availability_mode: data_availability_mode(nonce_data_availability_mode)?,
+        fee_data_availability_mode: data_availability_mode(fee_data_availability_mode)?,
+    })
+}
+
+fn transaction(tx: proto::Transaction) -> Result<Transaction, Error> {
+    use proto::transaction::Txn;
+
+    let transaction_hash = felt(tx.transaction_hash, "transaction hash")?;
+    let txn = tx.txn.ok_or_else(|| invalid(format!("Empty transaction {transaction_hash:#x}")))?;
+
+    Ok(match txn {
+        Txn::DeclareV0(tx) => Transaction::Declare(DeclareTransaction {
+            transaction_hash,
+            version: FieldElement::ZERO,
+            class_hash: felt(tx.class_hash, "class hash")?,
+            compiled_class_hash: None,
+            sender_address: felt(tx.sender, "sender address")?,
+            signature: signature(tx.signature)?,
+            max_fee: felt(tx.max_fee, "max fee")?,
+            nonce: FieldElement::ZERO,
+            account_deployment_data: Vec::new(),
+            fee_market: FeeMarketFields::default(),
+        }),
+        Txn::DeclareV1(tx) => Transaction::Declare(DeclareTransaction {
+            transaction_hash,
+            version: FieldElement::ONE,
+            class_hash: felt(tx.class_hash, "class hash")?,
+            compiled_class_hash: None,
+            sender_address: felt(tx.sender, "sender address")?,
+            signature: signature(tx.signature)?,
+            max_fee: felt(tx.max_fee, "max fee")?,
+            nonce: felt(tx.nonce, "nonce")?,
+            account_deployment_data: Vec::new(),
+            fee_market: FeeMarketFields::default(),
+        }),
+        Txn::DeclareV2(tx) => Transaction::Declare(DeclareTransaction {
+            transaction_hash,
+            version: FieldElement::TWO,
+            class_hash: felt(tx.class_hash, "class hash")?,
+            compiled_class_hash: Some(felt(tx.compiled_class_hash, "compiled class hash")?),
+            sender_address: felt(tx.sender, "sender address")?,
+            signature: signature(tx.signature)?,
+            max_fee: felt(tx.max_fee, "max fee")?,
+            nonce: felt(tx.nonce, "nonce")?,
+            account_deployment_data: Vec::new(),
+            fee_market: FeeMarketFields::default(),
+        }),
+        Txn::DeclareV3(tx) => Transaction::Declare(DeclareTransaction {
+            transaction_hash,
+            version: FieldElement::THREE,
+            class_hash: felt(tx.class_hash, "class hash")?,
+            compiled_class_hash: Some(felt(tx.compiled_class_hash, "compiled class hash")?),
+            sender_address: felt(tx.sender, "sender address")?,
+            signature: signature(tx.signature)?,
+            max_fee: FieldElement::ZERO,
+            nonce: felt(tx.nonce, "nonce")?,
+            account_deployment_data: felts(tx.account_deployment_data)?,
+            fee_market: fee_market(
+                tx.resource_bounds,
+                tx.tip,
+                tx.paymaster_data,
+                tx.nonce_data_availability_mode,
+                tx.fee_data_availability_mode,
+            )?,
+        }),
+        Txn::Deploy(_) => Transaction::Deploy(DeployTransaction { transaction_hash }),
+        Txn::DeployAccountV1(tx) => Transaction::DeployAccount(DeployAccountTransaction {
+            transaction_hash,
+            version: FieldElement::ONE,
+            class_hash: felt(tx.class_hash, "class hash")?,
+            contract_address_salt: felt(tx.address_salt, "contract address salt")?,
+            constructor_calldata: felts(tx.calldata)?,
+            signature: signature(tx.signature)?,
+            max_fee: felt(tx.max_fee, "max fee")?,
+            nonce: felt(tx.nonce, "nonce")?,
+            fee_market: FeeMarketFields::default(),
+        }),
+        Txn::DeployAccountV3(tx) => Transaction::DeployAccount(DeployAccountTransaction {
+            transaction_hash,
+            version: FieldElement::THREE,
+            class_hash: felt(tx.class_hash, "class hash")?,
+            contract_address_salt: felt(tx.address_salt, "contract address salt")?,
+            constructor_calldata: felts(tx.calldata)?,
+            signature: signature(tx.signature)?,
+            max_fee: FieldElement::ZERO,
+            nonce: felt(tx.nonce, "nonce")?,
+            fee_market: fee_market(
+                tx.resource_bounds,
+                tx.tip,
+                tx.paymaster_data,
+                tx.nonce_data_availability_mode,
+                tx.fee_data_availability_mode,
+            )?,
+        }),
+        Txn::InvokeV0(tx) => Transaction::Invoke(InvokeTransaction {
+            transaction_hash,
+            version: FieldElement::ZERO,
+            sender_address: felt(tx.address, "contract address")?,
+            entry_point_selector: Some(felt(tx.entry_point_selector, "entry point selector")?),
+            calldata: felts(tx.calldata)?,
+            signature: signature(tx.signature)?,
+            max_fee: felt(tx.max_fee, "max fee")?,
+            nonce: FieldElement::ZERO,
+            account_deployment_data: Vec::new(),
+            fee_market: FeeMarketFields::default(),
+        }),
+        Txn::InvokeV1(tx) => Transaction::Invoke(InvokeTransaction {
+            transaction_hash,
+            version: FieldElement::ONE,
+            sender_address: felt(tx.sender, "sender address")?,
+            entry_point_selector: None,
+            calldata: felts(tx.calldata)?,
+            signature: signature(tx.signature)?,
+            max_fee: felt(tx.max_fee, "max fee")?,
+            nonce: felt(tx.nonce, "nonce")?,
+            account_deployment_data: Vec::new(),
+            fee_market: FeeMarketFields::default(),
+        }),
+        Txn::InvokeV3(tx) => Transaction::Invoke(InvokeTransaction {
+            transaction_hash,
+            version: FieldElement::THREE,
+            sender_address: felt(tx.sender, "sender address")?,
+            entry_point_selector: None,
+            calldata: felts(tx.calldata)?,
+            signature: signature(tx.signature)?,
+            max_fee: FieldElement::ZERO,
+            nonce: felt(tx.nonce, "nonce")?,
+            account_deployment_data: felts(tx.account_deployment_data)?,
+            fee_market: fee_market(
+                tx.resource_bounds,
+                tx.tip,
+                tx.paymaster_data,
+                tx.nonce_data_availability_mode,
+                tx.fee_data_availability_mode,
+            )?,
+        }),
+        Txn::L1Handler(tx) => Transaction::L1Handler(L1HandlerTransaction {
+            transaction_hash,
+            version: FieldElement::ZERO,
+            contract_address: felt(tx.address, "contract address")?,
+            entry_point_selector: felt(tx.entry_point_selector, "entry point selector")?,
+            nonce: felt(tx.nonce, "nonce")?,
+            calldata: felts(tx.calldata)?,
+            // Not served by the peers, approximated as for the other gateways than Madara's
+            paid_fee_on_l1: None,
+        }),
+    })
+}
+
+fn state_diff(messages: Vec<StateDiffMessage>) -> Result<StateDiff, Error> {
+    let mut state_diff = StateDiff::default();
+
+    for message in messages {
+        match message {
+            StateDiffMessage::ContractDiff(diff) => {
+                let address = felt(diff.address, "contract address")?;
+                if let Some(class_hash) = diff.class_hash {
+                    let class_hash = felt(Some(class_hash), "class hash")?;
+                    state_diff.deployed_contracts.push(DeployedContract { address, class_hash });
+                }
+                if let Some(nonce) = diff.nonce {
+                    state_diff.nonces.insert(address, felt(Some(nonce), "nonce")?);
+                }
+                if !diff.values.is_empty() {
+                    let entries = diff
+                        .values
+                        .into_iter()
+                        .map(|value| {
+                            Ok(StorageEntry {
+                                key: felt(value.key, "storage key")?,
+                                value: felt(value.value, "storage value")?,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    state_diff.storage_diffs.entry(address).or_default().extend(entries);
+                }
+            }
+            StateDiffMessage::DeclaredClass(class) => {
+                let class_hash = felt(class.class_hash, "class hash")?;
+                match class.compiled_class_hash {
+                    Some(compiled_class_hash) => state_diff.declared_classes.push(DeclaredClass {
+                        class_hash,
+                        compiled_class_hash: felt(Some(compiled_class_hash), "compiled class hash")?,
+                    }),
+                    None => state_diff.old_declared_contracts.push(class_hash),
+                }
+            }
+            // Read by the codec, never part of a response
+            StateDiffMessage::Fin(_) => {}
+        }
+    }
+
+    Ok(state_diff)
+}
+
+fn sierra_class(class: proto::Cairo1Class) -> Result<SierraClass, Error> {
+    let entry_points = class.entry_points.unwrap_or_default();
+    let sierra_entry_points = |entry_points: Vec<proto::SierraEntryPoint>| {
+        entry_points
+            .into_iter()
+            .map(|entry_point| {
+                Ok(SierraEntryPoint {
+                    selector: felt(entry_point.selector, "entry point selector")?,
+                    function_idx: entry_point.index,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    };
+
+    Ok(SierraClass {
+        sierra_program: felts(class.program)?,
+        contract_class_version: class.contract_class_version,
+        entry_points_by_type: SierraEntryPoints {
+            constructor: sierra_entry_points(entry_points.constructors)?,
+            external: sierra_entry_points(entry_points.externals)?,
+            l1_handler: sierra_entry_points(entry_points.l1_handlers)?,
+        },
+        abi: class.abi,
+    })
+}
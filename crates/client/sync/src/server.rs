@@ -0,0 +1,219 @@
+//! Feeder gateway of Madara, serving its finalized blocks to the full nodes syncing from it.
+//!
+//! The `get_block`, `get_state_update` and `get_class_by_hash` endpoints used by
+//! [`crate::gateway::FeederGateway`] are served, with the objects of [`crate::types`]. Blocks are
+//! served once finalized and indexed by the mapping sync worker, which stores their receipts and
+//! state diff. Only Sierra classes are served: Madara does not keep the Cairo 0 classes as they
+//! were declared, so the blocks declaring one can't be synced from Madara.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use mp_digest_log::find_starknet_block;
+use mp_felt::Felt252Wrapper;
+use sc_client_api::HeaderBackend;
+use serde::Serialize;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
+use starknet_ff::FieldElement;
+
+use crate::convert;
+use crate::gateway::BLOCK_NOT_FOUND;
+use crate::types::GatewayError;
+
+/// Code returned for the classes not served.
+const UNDECLARED_CLASS: &str = "StarknetErrorCode.UNDECLARED_CLASS";
+/// Code returned for malformed queries.
+const MALFORMED_REQUEST: &str = "StarknetErrorCode.MALFORMED_REQUEST";
+/// Code returned for the blocks imported before their state diff was stored.
+const STATE_DIFF_NOT_STORED: &str = "MadaraErrorCode.STATE_DIFF_NOT_STORED";
+/// Code returned when the node fails to read a block.
+const INTERNAL_ERROR: &str = "StarknetErrorCode.INTERNAL_ERROR";
+
+/// Serves the feeder gateway endpoints used to sync from Madara.
+pub struct FeederGatewayServer<B: BlockT, C> {
+    client: Arc<C>,
+    backend: Arc<mc_db::Backend<B>>,
+}
+
+/// A response of the gateway, the errors being served with their status code.
+type GatewayResult = Result<Response<Body>, (StatusCode, GatewayError)>;
+
+impl<B, C> FeederGatewayServer<B, C>
+where
+    B: BlockT,
+    C: HeaderBackend<B> + 'static,
+{
+    pub fn new(client: Arc<C>, backend: Arc<mc_db::Backend<B>>) -> Self {
+        Self { client, backend }
+    }
+
+    /// Bind the gateway to `addr`, returning the address bound and the future serving it.
+    pub fn bind(self, addr: &SocketAddr) -> Result<(SocketAddr, impl Future<Output = ()>), hyper::Error> {
+        let server = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let server = server.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.respond(&request)) }
+                }))
+            }
+        });
+
+        let server = Server::try_bind(addr)?.serve(make_service);
+        let local_addr = server.local_addr();
+        Ok((local_addr, async move {
+            if let Err(e) = server.await {
+                log::error!("The feeder gateway stopped: {e}");
+            }
+        }))
+    }
+
+    fn respond(&self, request: &Request<Body>) -> Response<Body> {
+        let query = query(request.uri().query().unwrap_or_default());
+        let result = match (request.method(), request.uri().path().trim_end_matches('/')) {
+            (&Method::GET, "/feeder_gateway/get_block") => block_number(&query).and_then(|n| self.block(n)),
+            (&Method::GET, "/feeder_gateway/get_state_update") => {
+                block_number(&query).and_then(|n| self.state_update(n))
+            }
+            (&Method::GET, "/feeder_gateway/get_class_by_hash") => class_hash(&query).and_then(|h| self.class(h)),
+            _ => Err(error(StatusCode::NOT_FOUND, MALFORMED_REQUEST, "Unknown endpoint".to_string())),
+        };
+
+        result.unwrap_or_else(|(status, error)| json(status, &error))
+    }
+
+    fn block(&self, block_number: u64) -> GatewayResult {
+        let (_, block) = self.finalized_block(block_number)?;
+
+        let mut actual_fees = Vec::with_capacity(block.transactions().len());
+        for transaction_hash in block.transactions_hashes() {
+            match self.backend.receipts().get_receipt(*transaction_hash).map_err(internal)? {
+                Some(receipt) => actual_fees.push(receipt.actual_fee),
+                None => return Err(not_indexed(block_number)),
+            }
+        }
+
+        Ok(json(StatusCode::OK, &convert::gateway_block(&block, &actual_fees)))
+    }
+
+    fn state_update(&self, block_number: u64) -> GatewayResult {
+        let (substrate_hash, block) = self.finalized_block(block_number)?;
+        let state_diff =
+            self.backend.state_diffs().get_state_diff(&substrate_hash).map_err(internal)?.ok_or_else(|| {
+                error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    STATE_DIFF_NOT_STORED,
+                    format!("Block {block_number} was imported before the state diffs were stored"),
+                )
+            })?;
+        let old_root = match block_number.checked_sub(1) {
+            Some(parent_number) => self.finalized_block(parent_number)?.1.header().global_state_root,
+            None => Default::default(),
+        };
+
+        Ok(json(StatusCode::OK, &convert::gateway_state_update(block.header(), old_root, &state_diff)))
+    }
+
+    /// The class `class_hash`, whatever the block it is requested at, as it can only be requested
+    /// by the nodes syncing a block declaring it.
+    fn class(&self, class_hash: FieldElement) -> GatewayResult {
+        match self
+            .backend
+            .sierra_classes()
+            .get_sierra_class(Felt252Wrapper::from(class_hash).into())
+            .map_err(internal)?
+        {
+            Some(class) => Ok(json(StatusCode::OK, &convert::gateway_sierra_class(class))),
+            None => Err(error(
+                StatusCode::BAD_REQUEST,
+                UNDECLARED_CLASS,
+                format!("Class {class_hash:#x} is not declared, or is a Cairo 0 class, which are not served"),
+            )),
+        }
+    }
+
+    /// The finalized and indexed block `block_number`, with the hash of its Substrate block.
+    fn finalized_block(&self, block_number: u64) -> Result<(B::Hash, mp_block::Block), (StatusCode, GatewayError)> {
+        let finalized_number: u64 = self.client.info().finalized_number.unique_saturated_into();
+        if block_number > finalized_number {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                BLOCK_NOT_FOUND,
+                format!("Block {block_number} is not finalized yet"),
+            ));
+        }
+
+        let substrate_hash = self
+            .client
+            .hash(block_number.unique_saturated_into())
+            .map_err(internal)?
+            .ok_or_else(|| not_indexed(block_number))?;
+        if !self.backend.mapping().is_synced(&substrate_hash).map_err(internal)? {
+            return Err(not_indexed(block_number));
+        }
+        let header = self.client.header(substrate_hash).map_err(internal)?.ok_or_else(|| not_indexed(block_number))?;
+        let block = find_starknet_block(header.digest()).map_err(|e| internal(format!("{e:?}")))?;
+
+        Ok((substrate_hash, block))
+    }
+}
+
+/// The query parameters of a request, the ones served being neither encoded nor repeated.
+fn query(query: &str) -> Vec<(&str, &str)> {
+    query.split('&').filter_map(|parameter| parameter.split_once('=')).collect()
+}
+
+fn parameter<'a>(query: &[(&str, &'a str)], name: &str) -> Result<&'a str, (StatusCode, GatewayError)> {
+    query
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| error(StatusCode::BAD_REQUEST, MALFORMED_REQUEST, format!("Missing {name} parameter")))
+}
+
+fn block_number(query: &[(&str, &str)]) -> Result<u64, (StatusCode, GatewayError)> {
+    let value = parameter(query, "blockNumber")?;
+    value
+        .parse()
+        .map_err(|_| error(StatusCode::BAD_REQUEST, MALFORMED_REQUEST, format!("Invalid block number {value}")))
+}
+
+fn class_hash(query: &[(&str, &str)]) -> Result<FieldElement, (StatusCode, GatewayError)> {
+    let value = parameter(query, "classHash")?;
+    FieldElement::from_hex_be(value)
+        .map_err(|_| error(StatusCode::BAD_REQUEST, MALFORMED_REQUEST, format!("Invalid class hash {value}")))
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    match serde_json::to_vec(body) {
+        Ok(body) => Response::builder()
+            .status(status)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .expect("The status and header are valid"),
+        Err(e) => {
+            log::error!("Failed to serialize a feeder gateway response: {e}");
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            response
+        }
+    }
+}
+
+fn error(status: StatusCode, code: &str, message: String) -> (StatusCode, GatewayError) {
+    (status, GatewayError { code: code.to_string(), message })
+}
+
+/// A block finalized but not indexed yet, which the syncing nodes request again later.
+fn not_indexed(block_number: u64) -> (StatusCode, GatewayError) {
+    error(StatusCode::BAD_REQUEST, BLOCK_NOT_FOUND, format!("Block {block_number} is not indexed yet"))
+}
+
+fn internal(e: impl ToString) -> (StatusCode, GatewayError) {
+    error(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL_ERROR, e.to_string())
+}
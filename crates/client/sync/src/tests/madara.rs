@@ -0,0 +1,358 @@
+//! A Madara chain serving its feeder gateway or its blocks over p2p.
+//!
+//! Its blocks are built from the transactions of the gateway fixtures and the declaration of a
+//! Sierra class, then served by the feeder gateway of Madara or by its p2p sync protocols, so that
+//! the syncing node is tested against the objects Madara serves.
+
+use std::num::NonZeroU128;
+use std::path::Path;
+use std::sync::Arc;
+
+use blockifier::blockifier::block::GasPrices;
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transaction_execution::Transaction as ExecutionTransaction;
+use mc_db::{DatabaseOptions, FeeUnit, MappingCommitment, StoredTransactionReceipt, TransactionReceiptKind};
+use mc_p2p::client::{ClientBlockSource, StateClassSource};
+use mc_p2p::{load_or_generate_keypair, NetworkConfig, NetworkHandle, NetworkService};
+use mc_rpc_core::utils::flattened_sierra_to_sierra_contract_class;
+use mp_block::commitments::BlockStateDiff;
+use mp_block::{Block as StarknetBlock, Header, L1DataAvailabilityMode, PROTOCOL_VERSION_0_13_2};
+use mp_digest_log::{Log, MADARA_ENGINE_ID};
+use mp_felt::Felt252Wrapper;
+use mp_starknet_inherent::{L1GasPrices, StarknetInherentData};
+use mp_transactions::from_broadcasted_transactions::try_declare_tx_from_broadcasted_declare_tx;
+use mp_transactions::get_transaction_hash;
+use parity_scale_codec::Encode;
+use sc_client_api::HeaderBackend;
+use sc_client_db::DatabaseSource;
+use sp_blockchain::{BlockStatus, Info};
+use sp_runtime::generic::{Block as GenericBlock, Header as SubstrateHeader};
+use sp_runtime::testing::H256;
+use sp_runtime::traits::{BlakeTwo256, Header as HeaderT};
+use sp_runtime::{Digest, DigestItem, OpaqueExtrinsic};
+use starknet_api::block::BlockHash;
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::ContractClass;
+use starknet_api::transaction::Fee;
+use starknet_core::types::contract::SierraClass;
+use starknet_core::types::{BroadcastedDeclareTransaction, BroadcastedDeclareTransactionV2};
+use starknet_ff::FieldElement;
+use tempfile::TempDir;
+
+use super::{block_1, chain_id, felt, STATE_UPDATE_1};
+use crate::chain::BlockInherents;
+use crate::convert;
+use crate::gateway::FeederGateway;
+use crate::peer::PeerUpstream;
+use crate::server::FeederGatewayServer;
+use crate::types::{StateUpdate, Transaction};
+use crate::verify::block_state_diff;
+
+pub(super) type Block = GenericBlock<SubstrateHeader<u32, BlakeTwo256>, OpaqueExtrinsic>;
+
+/// Fee paid on L1 by the L1 handler of block 1.
+pub(super) const PAID_FEE_ON_L1: Fee = Fee(5);
+const HELLO_STARKNET: &str = include_str!("../../../../../cairo-contracts/build/cairo_1/HelloStarknet.sierra.json");
+const HELLO_STARKNET_COMPILED_CLASS_HASH: &str = "0xdf4d3042eec107abe704619f13d92bbe01a58029311b7a1886b23dcbb4ea87";
+
+/// The blocks of a Madara chain, with what the mapping sync worker stores for them.
+pub(super) struct MadaraChain {
+    pub blocks: Vec<StarknetBlock>,
+    pub state_diffs: Vec<BlockStateDiff>,
+    /// Fee charged for each transaction, by block.
+    pub actual_fees: Vec<Vec<Fee>>,
+    /// The Sierra class declared in block 1.
+    pub sierra_class: (ClassHash, ContractClass),
+}
+
+impl MadaraChain {
+    /// The genesis, then a block declaring a Sierra class, invoking a contract and handling an L1
+    /// message.
+    pub fn new() -> Self {
+        let sierra_class: SierraClass = serde_json::from_str(HELLO_STARKNET).unwrap();
+        let sierra_class = Arc::new(sierra_class.flatten().unwrap());
+        let declare = try_declare_tx_from_broadcasted_declare_tx(
+            BroadcastedDeclareTransaction::V2(BroadcastedDeclareTransactionV2 {
+                max_fee: FieldElement::ONE,
+                signature: Vec::new(),
+                nonce: FieldElement::ONE,
+                contract_class: sierra_class.clone(),
+                sender_address: felt("0x12fd537"),
+                compiled_class_hash: felt(HELLO_STARKNET_COMPILED_CLASS_HASH),
+                is_query: false,
+            }),
+            chain_id(),
+        )
+        .unwrap();
+        let class_hash = Felt252Wrapper::from(sierra_class.class_hash()).into();
+
+        let mut upstream = block_1().transactions.into_iter();
+        let Some(Transaction::Invoke(invoke)) = upstream.next() else { panic!("expected an invoke transaction") };
+        let Some(Transaction::L1Handler(l1_handler)) = upstream.next() else { panic!("expected an L1 handler") };
+        let transactions = vec![
+            ExecutionTransaction::AccountTransaction(AccountTransaction::Declare(declare)),
+            ExecutionTransaction::AccountTransaction(AccountTransaction::Invoke(
+                convert::invoke_transaction(invoke, chain_id()).unwrap(),
+            )),
+            ExecutionTransaction::L1HandlerTransaction(
+                convert::l1_handler_transaction(l1_handler, PAID_FEE_ON_L1, chain_id()).unwrap(),
+            ),
+        ];
+
+        let state_update: StateUpdate = serde_json::from_str(STATE_UPDATE_1).unwrap();
+        let mut state_diff = block_state_diff(&state_update.state_diff);
+        state_diff
+            .declared_classes
+            .insert(class_hash, Felt252Wrapper::from(felt(HELLO_STARKNET_COMPILED_CLASS_HASH)).into());
+
+        let genesis = genesis_header();
+        let header = built_header(&genesis, &block_1_inherents(), &transactions, &state_diff);
+
+        Self {
+            blocks: vec![
+                StarknetBlock::try_new(genesis, Vec::new()).unwrap(),
+                StarknetBlock::try_new(header, transactions).unwrap(),
+            ],
+            state_diffs: vec![BlockStateDiff::default(), state_diff],
+            actual_fees: vec![Vec::new(), vec![Fee(3), Fee(1), Fee(2)]],
+            sierra_class: (class_hash, flattened_sierra_to_sierra_contract_class(sierra_class)),
+        }
+    }
+}
+
+/// The genesis shared by the chains of the tests.
+pub(super) fn genesis_header() -> Header {
+    Header {
+        parent_block_hash: StarkHash::ZERO,
+        block_number: 0,
+        sequencer_address: Default::default(),
+        block_timestamp: 0,
+        global_state_root: StarkHash::ZERO,
+        transaction_count: 0,
+        transaction_commitment: StarkHash::ZERO,
+        event_count: 0,
+        event_commitment: StarkHash::ZERO,
+        receipt_commitment: StarkHash::ZERO,
+        state_diff_commitment: StarkHash::ZERO,
+        state_diff_length: 0,
+        l1_da_mode: L1DataAvailabilityMode::Calldata,
+        protocol_version: PROTOCOL_VERSION_0_13_2,
+        l1_gas_price: GasPrices {
+            eth_l1_gas_price: NonZeroU128::MIN,
+            strk_l1_gas_price: NonZeroU128::MIN,
+            eth_l1_data_gas_price: NonZeroU128::MIN,
+            strk_l1_data_gas_price: NonZeroU128::MIN,
+        },
+        extra_data: None,
+    }
+}
+
+/// The inherents block 1 is produced with.
+pub(super) fn block_1_inherents() -> BlockInherents {
+    BlockInherents {
+        timestamp: 1_700_000_000,
+        starknet: StarknetInherentData {
+            sequencer_address: Felt252Wrapper::from(felt("0xdead")).into(),
+            l1_gas_price: L1GasPrices {
+                eth_l1_gas_price: NonZeroU128::new(1_000_000_000).unwrap(),
+                strk_l1_gas_price: NonZeroU128::new(100_000_000).unwrap(),
+                eth_l1_data_gas_price: NonZeroU128::MIN,
+                strk_l1_data_gas_price: NonZeroU128::MIN,
+                last_update_timestamp: 1_700_000_000,
+            },
+        },
+    }
+}
+
+/// The header of the block built on top of `parent`, whose execution results in `state_diff`.
+///
+/// The transaction, event and receipt commitments are not computed, as the transactions are not
+/// executed.
+pub(super) fn built_header(
+    parent: &Header,
+    inherents: &BlockInherents,
+    transactions: &[ExecutionTransaction],
+    state_diff: &BlockStateDiff,
+) -> Header {
+    let sequencer_address = StarkFelt::new(inherents.starknet.sequencer_address).unwrap();
+    let l1_gas_price = &inherents.starknet.l1_gas_price;
+    Header {
+        parent_block_hash: parent.hash().into(),
+        block_number: parent.block_number + 1,
+        sequencer_address: ContractAddress::try_from(sequencer_address).unwrap(),
+        block_timestamp: inherents.timestamp,
+        transaction_count: transactions.len() as u128,
+        state_diff_commitment: state_diff.commitment(),
+        state_diff_length: state_diff.len(),
+        l1_gas_price: GasPrices {
+            eth_l1_gas_price: l1_gas_price.eth_l1_gas_price,
+            strk_l1_gas_price: l1_gas_price.strk_l1_gas_price,
+            eth_l1_data_gas_price: l1_gas_price.eth_l1_data_gas_price,
+            strk_l1_data_gas_price: l1_gas_price.strk_l1_data_gas_price,
+        },
+        ..genesis_header()
+    }
+}
+
+/// Substrate blocks wrapping the Starknet blocks of a chain, the first one being the genesis.
+struct MockClient {
+    headers: Vec<SubstrateHeader<u32, BlakeTwo256>>,
+    finalized_number: u32,
+}
+
+impl MockClient {
+    fn new(blocks: &[StarknetBlock], finalized_number: u32) -> Self {
+        let mut headers: Vec<SubstrateHeader<u32, BlakeTwo256>> = Vec::new();
+        for (number, block) in blocks.iter().enumerate() {
+            let parent_hash = headers.last().map(|header| header.hash()).unwrap_or_default();
+            let mut digest = Digest::default();
+            digest.push(DigestItem::Consensus(MADARA_ENGINE_ID, Log::Block(block.clone()).encode()));
+            headers.push(SubstrateHeader::new(number as u32, Default::default(), H256::zero(), parent_hash, digest));
+        }
+        Self { headers, finalized_number }
+    }
+}
+
+impl HeaderBackend<Block> for MockClient {
+    fn header(&self, hash: H256) -> sp_blockchain::Result<Option<SubstrateHeader<u32, BlakeTwo256>>> {
+        Ok(self.headers.iter().find(|header| header.hash() == hash).cloned())
+    }
+
+    fn info(&self) -> Info<Block> {
+        let best = self.headers.last().unwrap();
+        let finalized = &self.headers[self.finalized_number as usize];
+        Info {
+            best_hash: best.hash(),
+            best_number: *best.number(),
+            genesis_hash: self.headers[0].hash(),
+            finalized_hash: finalized.hash(),
+            finalized_number: self.finalized_number,
+            finalized_state: None,
+            number_leaves: 1,
+            block_gap: None,
+        }
+    }
+
+    fn status(&self, hash: H256) -> sp_blockchain::Result<BlockStatus> {
+        Ok(if self.header(hash)?.is_some() { BlockStatus::InChain } else { BlockStatus::Unknown })
+    }
+
+    fn number(&self, hash: H256) -> sp_blockchain::Result<Option<u32>> {
+        Ok(self.header(hash)?.map(|header| *header.number()))
+    }
+
+    fn hash(&self, number: u32) -> sp_blockchain::Result<Option<H256>> {
+        Ok(self.headers.get(number as usize).map(|header| header.hash()))
+    }
+}
+
+pub(super) fn open_backend() -> (TempDir, Arc<mc_db::Backend<Block>>) {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = mc_db::Backend::open(
+        &DatabaseSource::ParityDb { path: Default::default() },
+        dir.path(),
+        None,
+        &DatabaseOptions::default(),
+    )
+    .unwrap();
+    (dir, Arc::new(backend))
+}
+
+/// The client and Madara DB of a node importing `chain`, whose blocks are finalized up to
+/// `finalized_number` and indexed by the mapping sync worker up to `indexed_number`.
+fn index(
+    chain: &MadaraChain,
+    finalized_number: u32,
+    indexed_number: u32,
+) -> (TempDir, Arc<MockClient>, Arc<mc_db::Backend<Block>>) {
+    let client = Arc::new(MockClient::new(&chain.blocks, finalized_number));
+    let (dir, backend) = open_backend();
+
+    for (number, block) in chain.blocks.iter().enumerate().take(indexed_number as usize + 1) {
+        let header = block.header();
+        let starknet_block_hash: StarkHash = header.hash().into();
+        let receipts = block
+            .transactions()
+            .iter()
+            .zip(&chain.actual_fees[number])
+            .enumerate()
+            .map(|(index, (transaction, actual_fee))| StoredTransactionReceipt {
+                transaction_hash: *get_transaction_hash(transaction),
+                block_hash: starknet_block_hash,
+                block_number: header.block_number,
+                transaction_index: index as u32,
+                kind: TransactionReceiptKind::Invoke,
+                actual_fee: *actual_fee,
+                fee_unit: FeeUnit::Wei,
+                execution_resources: Vec::new(),
+                messages_sent: Vec::new(),
+                first_event_index: 0,
+                events: Vec::new(),
+                revert_reason: None,
+                paymaster: None,
+            })
+            .collect();
+        backend
+            .mapping()
+            .write_hashes(MappingCommitment {
+                block_hash: client.headers[number].hash(),
+                starknet_block_hash: BlockHash(starknet_block_hash),
+                starknet_transaction_hashes: block.transactions_hashes().copied().collect(),
+                starknet_transaction_receipts: receipts,
+                starknet_block_number: header.block_number,
+                starknet_storage_diffs: None,
+                starknet_state_diff: Some(chain.state_diffs[number].clone()),
+            })
+            .unwrap();
+    }
+    let (class_hash, class) = chain.sierra_class.clone();
+    backend.sierra_classes().store_sierra_class(class_hash, class).unwrap();
+
+    (dir, client, backend)
+}
+
+/// Serve the feeder gateway of `chain`, whose blocks are finalized up to `finalized_number` and
+/// indexed up to `indexed_number`.
+pub(super) async fn serve(chain: &MadaraChain, finalized_number: u32, indexed_number: u32) -> (TempDir, FeederGateway) {
+    let (dir, client, backend) = index(chain, finalized_number, indexed_number);
+
+    let (address, server) = FeederGatewayServer::new(client, backend).bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    tokio::spawn(server);
+
+    (dir, FeederGateway::new(&format!("http://{address}")).unwrap())
+}
+
+/// The Cairo 0 classes, which are not part of the chain.
+struct NoStateClasses;
+
+impl StateClassSource<Block> for NoStateClasses {
+    fn contract_class(&self, _substrate_hash: H256, _class_hash: ClassHash) -> Option<BlockifierContractClass> {
+        None
+    }
+}
+
+/// Start a node serving `source`, listening on a local port.
+fn start_node(source: Arc<ClientBlockSource<Block, MockClient, NoStateClasses>>, key: &Path) -> NetworkHandle {
+    let config = NetworkConfig { listen_addresses: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()], bootnodes: vec![] };
+    let (service, handle) = NetworkService::new(config, load_or_generate_keypair(key).unwrap(), source).unwrap();
+    tokio::spawn(service.run());
+    handle
+}
+
+/// Serve the blocks of `chain` indexed up to `indexed_number` over p2p, to the node of the returned
+/// upstream.
+pub(super) async fn serve_p2p(chain: &MadaraChain, indexed_number: u32) -> (TempDir, PeerUpstream) {
+    let (dir, client, backend) = index(chain, chain.blocks.len() as u32 - 1, indexed_number);
+    // The syncing node serves the same blocks, which are never requested
+    let source = Arc::new(ClientBlockSource::new(client, backend, NoStateClasses));
+    let server = start_node(source.clone(), &dir.path().join("server_key"));
+    let node = start_node(source, &dir.path().join("node_key"));
+
+    let mut listen_addresses = server.listen_addresses();
+    let address = listen_addresses.wait_for(|addresses| !addresses.is_empty()).await.unwrap()[0].clone();
+    node.add_peer(server.local_peer_id(), address).unwrap();
+
+    (dir, PeerUpstream::new(node, server.local_peer_id()))
+}
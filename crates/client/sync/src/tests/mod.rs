@@ -0,0 +1,292 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::num::NonZeroU128;
+
+use assert_matches::assert_matches;
+use blockifier::blockifier::block::GasPrices;
+use mp_block::{Header, L1DataAvailabilityMode, PROTOCOL_VERSION_0_13_2};
+use mp_felt::Felt252Wrapper;
+use starknet_api::hash::StarkHash;
+use starknet_api::transaction::Fee;
+use starknet_ff::FieldElement;
+
+use crate::error::Error;
+use crate::gateway::FeederGateway;
+use crate::types::{Block, ContractClass, StateUpdate, Transaction};
+use crate::verify::{block_state_diff, verify_block};
+use crate::{convert, types};
+
+mod madara;
+mod peer;
+mod server;
+mod worker;
+
+const BLOCK_0: &str = include_str!("../../resources/feeder_gateway/get_block/0.json");
+const BLOCK_1: &str = include_str!("../../resources/feeder_gateway/get_block/1.json");
+const STATE_UPDATE_1: &str = include_str!("../../resources/feeder_gateway/get_state_update/1.json");
+const LEGACY_CLASS: &str = include_str!("../../../../../cairo-contracts/build/test.json");
+const INVOKE_HASH: &str = "0x62633b1f3d64708df3d0d44706b388f841ed4534346be6ad60336c8eb2f4b3e";
+const BLOCK_NOT_FOUND: &str = r#"{"code": "StarknetErrorCode.BLOCK_NOT_FOUND", "message": "Block not found"}"#;
+
+/// Serve the fixtures as a feeder gateway would, answering `BLOCK_NOT_FOUND` to other requests.
+fn mock_gateway() -> FeederGateway {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request_line = String::new();
+            {
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+            }
+
+            let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+            let (status, body) = match target {
+                "/feeder_gateway/get_block?blockNumber=0" => ("200 OK", BLOCK_0),
+                "/feeder_gateway/get_block?blockNumber=1" => ("200 OK", BLOCK_1),
+                "/feeder_gateway/get_state_update?blockNumber=1" => ("200 OK", STATE_UPDATE_1),
+                "/feeder_gateway/get_class_by_hash?classHash=0x1234&blockNumber=1" => ("200 OK", LEGACY_CLASS),
+                "/feeder_gateway/get_class_by_hash?classHash=0x1234&blockNumber=2" => {
+                    ("500 Internal Server Error", r#"{"code": "StarknetErrorCode.UNDECLARED_CLASS", "message": ""}"#)
+                }
+                _ => ("400 Bad Request", BLOCK_NOT_FOUND),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: \
+                 close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+
+    FeederGateway::new(&format!("http://{address}")).unwrap()
+}
+
+fn chain_id() -> Felt252Wrapper {
+    Felt252Wrapper(FieldElement::from_byte_slice_be(b"SN_GOERLI").unwrap())
+}
+
+fn felt(value: &str) -> FieldElement {
+    FieldElement::from_hex_be(value).unwrap()
+}
+
+fn block_1() -> Block {
+    serde_json::from_str(BLOCK_1).unwrap()
+}
+
+/// The header of the block built from `block`, as it would be by the pallet.
+fn header(block: &Block, state_update: &StateUpdate) -> Header {
+    let state_diff = block_state_diff(&state_update.state_diff);
+    Header {
+        parent_block_hash: Felt252Wrapper::from(block.parent_block_hash).into(),
+        block_number: block.block_number,
+        sequencer_address: Felt252Wrapper::from(block.sequencer_address.unwrap()).into(),
+        block_timestamp: block.timestamp,
+        global_state_root: StarkHash::ZERO,
+        transaction_count: block.transactions.len() as u128,
+        transaction_commitment: StarkHash::from(1u128),
+        event_count: 0,
+        event_commitment: StarkHash::ZERO,
+        receipt_commitment: StarkHash::from(2u128),
+        state_diff_commitment: state_diff.commitment(),
+        state_diff_length: state_diff.len(),
+        l1_da_mode: L1DataAvailabilityMode::Calldata,
+        protocol_version: PROTOCOL_VERSION_0_13_2,
+        l1_gas_price: GasPrices {
+            eth_l1_gas_price: NonZeroU128::new(1_000_000_000).unwrap(),
+            strk_l1_gas_price: NonZeroU128::new(100_000_000).unwrap(),
+            eth_l1_data_gas_price: NonZeroU128::MIN,
+            strk_l1_data_gas_price: NonZeroU128::MIN,
+        },
+        extra_data: None,
+    }
+}
+
+#[tokio::test]
+async fn gateway_serves_blocks_and_state_updates() {
+    let gateway = mock_gateway();
+
+    let genesis = gateway.block(0).await.unwrap().unwrap();
+    assert_eq!(genesis.block_number, 0);
+    assert!(genesis.transactions.is_empty());
+
+    let block = gateway.block(1).await.unwrap().unwrap();
+    assert_eq!(block.parent_block_hash, genesis.block_hash);
+    assert_eq!(block.sequencer_address, Some(felt("0xdead")));
+    assert_eq!(block.l1_gas_price.price_in_wei, felt("0x3b9aca00"));
+    assert_matches!(&block.transactions[..], [Transaction::Invoke(_), Transaction::L1Handler(_)]);
+    assert_eq!(block.transactions[1].transaction_hash(), block.transaction_receipts[1].transaction_hash);
+
+    let state_update = gateway.state_update(1).await.unwrap();
+    assert_eq!(state_update.block_hash, block.block_hash);
+    assert_eq!(state_update.state_diff.storage_diffs[&felt("0x12fd537")].len(), 2);
+}
+
+#[tokio::test]
+async fn gateway_reports_blocks_not_produced_yet() {
+    let gateway = mock_gateway();
+
+    assert_matches!(gateway.block(2).await, Ok(None));
+    assert_matches!(gateway.state_update(2).await, Err(Error::MissingBlock(2)));
+}
+
+#[tokio::test]
+async fn gateway_serves_classes() {
+    let gateway = mock_gateway();
+
+    assert_matches!(gateway.class_by_hash(felt("0x1234"), 1).await, Ok(ContractClass::Legacy(_)));
+    assert_matches!(
+        gateway.class_by_hash(felt("0x1234"), 2).await,
+        Err(Error::Gateway { code, .. }) if code == "StarknetErrorCode.UNDECLARED_CLASS"
+    );
+}
+
+#[test]
+fn gateway_url_must_be_valid() {
+    assert_matches!(FeederGateway::new("not a url"), Err(Error::InvalidUrl(_)));
+}
+
+#[test]
+fn convert_transactions_of_the_upstream_block() {
+    let mut transactions = block_1().transactions.into_iter();
+
+    let Some(Transaction::Invoke(invoke)) = transactions.next() else { panic!("expected an invoke transaction") };
+    let tx_hash = invoke.transaction_hash;
+    let converted = convert::invoke_transaction(invoke, chain_id()).unwrap();
+    assert_eq!(FieldElement::from(Felt252Wrapper::from(converted.tx_hash)), tx_hash);
+
+    let Some(Transaction::L1Handler(l1_handler)) = transactions.next() else { panic!("expected an L1 handler") };
+    let converted = convert::l1_handler_transaction(l1_handler, Fee(1), chain_id()).unwrap();
+    assert_eq!(converted.paid_fee_on_l1, Fee(1));
+}
+
+#[test]
+fn convert_approximates_the_fee_paid_on_l1_when_not_served() {
+    let Some(Transaction::L1Handler(l1_handler)) = block_1().transactions.into_iter().nth(1) else {
+        panic!("expected an L1 handler")
+    };
+    assert_eq!(l1_handler.paid_fee_on_l1, None);
+
+    assert_eq!(convert::paid_fee_on_l1(&l1_handler, FieldElement::from(7u8)).unwrap(), Fee(7));
+    assert_eq!(convert::paid_fee_on_l1(&l1_handler, FieldElement::ZERO).unwrap(), Fee(1));
+}
+
+#[test]
+fn convert_deploy_account_transaction() {
+    let tx: types::DeployAccountTransaction = serde_json::from_value(serde_json::json!({
+        "transaction_hash": "0x4cf7bf97d4f8ef73eb83d2e6fb8e5354c04f2121b9bd38510220eff3a07e9df",
+        "version": "0x1",
+        "class_hash": "0x3",
+        "contract_address_salt": "0x0",
+        "constructor_calldata": ["0x1", "0x2", "0x3"],
+        "signature": [],
+        "max_fee": "0x1",
+        "nonce": "0x0"
+    }))
+    .unwrap();
+
+    assert!(convert::deploy_account_transaction(tx, chain_id()).is_ok());
+}
+
+#[test]
+fn convert_rejects_transactions_hashed_differently() {
+    let Some(Transaction::Invoke(mut invoke)) = block_1().transactions.into_iter().next() else {
+        panic!("expected an invoke transaction")
+    };
+    invoke.max_fee = FieldElement::TWO;
+
+    assert_matches!(
+        convert::invoke_transaction(invoke, chain_id()),
+        Err(Error::TransactionHashMismatch { expected, .. }) if expected == felt(INVOKE_HASH)
+    );
+}
+
+#[test]
+fn convert_rejects_unsupported_transactions() {
+    let Some(Transaction::Invoke(mut invoke)) = block_1().transactions.into_iter().next() else {
+        panic!("expected an invoke transaction")
+    };
+    invoke.version = FieldElement::TWO;
+    assert_matches!(
+        convert::invoke_transaction(invoke, chain_id()),
+        Err(Error::UnsupportedTransaction { kind: "invoke", .. })
+    );
+
+    let declare: types::DeclareTransaction = serde_json::from_value(serde_json::json!({
+        "transaction_hash": "0x1",
+        "version": "0x2",
+        "class_hash": "0x1234",
+        "compiled_class_hash": "0x1",
+        "sender_address": "0x12fd537",
+        "max_fee": "0x1",
+        "nonce": "0x0"
+    }))
+    .unwrap();
+    let class: ContractClass = serde_json::from_str(LEGACY_CLASS).unwrap();
+    assert_matches!(
+        convert::declare_transaction(declare, class, chain_id()),
+        Err(Error::InvalidClass { class_hash, .. }) if class_hash == felt("0x1234")
+    );
+}
+
+#[test]
+fn verify_block_built_from_the_upstream_block() {
+    let mut block = block_1();
+    let state_update: StateUpdate = serde_json::from_str(STATE_UPDATE_1).unwrap();
+    let header = header(&block, &state_update);
+    block.block_hash = header.hash().into();
+
+    assert_matches!(verify_block(&block, &state_update.state_diff, &header), Ok(()));
+}
+
+#[test]
+fn verify_block_detects_differences() {
+    let mut block = block_1();
+    let state_update: StateUpdate = serde_json::from_str(STATE_UPDATE_1).unwrap();
+    let header = header(&block, &state_update);
+
+    assert_matches!(
+        verify_block(&block, &state_update.state_diff, &header),
+        Err(Error::BlockHashMismatch { block_number: 1, .. })
+    );
+
+    block.block_hash = header.hash().into();
+    let mut state_diff = state_update.state_diff.clone();
+    state_diff.nonces.insert(felt("0x12fd537"), FieldElement::TWO);
+    assert_matches!(verify_block(&block, &state_diff, &header), Err(Error::StateDiffMismatch { block_number: 1 }));
+
+    let mut state_diff = state_update.state_diff.clone();
+    state_diff.replaced_classes.push(types::DeployedContract { address: felt("0x1"), class_hash: felt("0x2") });
+    assert_matches!(verify_block(&block, &state_diff, &header), Err(Error::StateDiffMismatch { block_number: 1 }));
+}
+
+#[test]
+fn verify_block_checks_the_state_root() {
+    let mut block = block_1();
+    let state_update: StateUpdate = serde_json::from_str(STATE_UPDATE_1).unwrap();
+    let header = header(&block, &state_update);
+    block.block_hash = header.hash().into();
+
+    // A zero local root is checked like any other
+    block.state_root = Some(FieldElement::ONE);
+    assert_matches!(
+        verify_block(&block, &state_update.state_diff, &header),
+        Err(Error::StateRootMismatch { block_number: 1, expected, computed })
+            if expected == FieldElement::ONE && computed == FieldElement::ZERO
+    );
+
+    block.state_root = None;
+    assert_matches!(
+        verify_block(&block, &state_update.state_diff, &header),
+        Err(Error::MissingStateRoot { block_number: 1 })
+    );
+}
@@ -0,0 +1,90 @@
+use assert_matches::assert_matches;
+use mp_felt::Felt252Wrapper;
+use starknet_ff::FieldElement;
+
+use super::chain_id;
+use super::madara::{serve, serve_p2p, MadaraChain};
+use crate::convert;
+use crate::error::Error;
+use crate::types::{ContractClass, Transaction};
+use crate::upstream::Upstream;
+use crate::verify::{block_state_diff, verify_block};
+
+#[tokio::test]
+async fn peer_serves_the_blocks_of_the_gateway() {
+    let chain = MadaraChain::new();
+    let (_gateway_dir, gateway) = serve(&chain, 1, 1).await;
+    let (_peer_dir, peer) = serve_p2p(&chain, 1).await;
+
+    for block_number in [0, 1] {
+        let expected = gateway.block(block_number).await.unwrap().unwrap();
+        let block = peer.block(block_number).await.unwrap().unwrap();
+        assert_eq!(block.block_hash, expected.block_hash);
+        assert_eq!(block.parent_block_hash, expected.parent_block_hash);
+        assert_eq!(block.timestamp, expected.timestamp);
+        assert_eq!(block.sequencer_address, expected.sequencer_address);
+        assert_eq!(block.l1_gas_price.price_in_wei, expected.l1_gas_price.price_in_wei);
+        assert_eq!(block.l1_gas_price.price_in_fri, expected.l1_gas_price.price_in_fri);
+        let hashes: Vec<_> = block.transactions.iter().map(Transaction::transaction_hash).collect();
+        let expected_hashes: Vec<_> = expected.transactions.iter().map(Transaction::transaction_hash).collect();
+        assert_eq!(hashes, expected_hashes);
+        let actual_fees: Vec<_> = block.transaction_receipts.iter().map(|receipt| receipt.actual_fee).collect();
+        let expected_fees: Vec<_> = expected.transaction_receipts.iter().map(|receipt| receipt.actual_fee).collect();
+        assert_eq!(actual_fees, expected_fees);
+    }
+
+    let block = peer.block(1).await.unwrap().unwrap();
+    let state_update = peer.state_update(1).await.unwrap();
+    assert_eq!(state_update.block_hash, block.block_hash);
+    assert_eq!(state_update.old_root, FieldElement::ZERO);
+    assert_eq!(block_state_diff(&state_update.state_diff), chain.state_diffs[1]);
+    assert_matches!(verify_block(&block, &state_update.state_diff, chain.blocks[1].header()), Ok(()));
+}
+
+#[tokio::test]
+async fn peer_transactions_convert_back_to_the_executed_ones() {
+    let chain = MadaraChain::new();
+    let (_dir, peer) = serve_p2p(&chain, 1).await;
+
+    let block = peer.block(1).await.unwrap().unwrap();
+    let mut transactions = block.transactions.into_iter();
+
+    let Some(Transaction::Declare(declare)) = transactions.next() else { panic!("expected a declare transaction") };
+    let class = peer.class_by_hash(declare.class_hash, 1).await.unwrap();
+    let (_, sierra_class) = convert::declare_transaction(declare, class, chain_id()).unwrap();
+    assert_eq!(sierra_class, Some(chain.sierra_class.1));
+
+    let Some(Transaction::Invoke(invoke)) = transactions.next() else { panic!("expected an invoke transaction") };
+    assert!(convert::invoke_transaction(invoke, chain_id()).is_ok());
+
+    // The fee paid on L1 is not served by the peers
+    let Some(Transaction::L1Handler(l1_handler)) = transactions.next() else { panic!("expected an L1 handler") };
+    assert_eq!(l1_handler.paid_fee_on_l1, None);
+    let tx_hash = l1_handler.transaction_hash;
+    let paid_fee_on_l1 = convert::paid_fee_on_l1(&l1_handler, FieldElement::TWO).unwrap();
+    let converted = convert::l1_handler_transaction(l1_handler, paid_fee_on_l1, chain_id()).unwrap();
+    assert_eq!(FieldElement::from(Felt252Wrapper::from(converted.tx_hash)), tx_hash);
+}
+
+#[tokio::test]
+async fn peer_only_serves_indexed_blocks() {
+    let chain = MadaraChain::new();
+    let (_dir, peer) = serve_p2p(&chain, 0).await;
+
+    assert_matches!(peer.block(0).await, Ok(Some(_)));
+    assert_matches!(peer.block(1).await, Ok(None));
+    assert_matches!(peer.state_update(1).await, Err(Error::MissingBlock(1)));
+    assert_matches!(peer.block(2).await, Ok(None));
+    assert_matches!(peer.state_update(2).await, Err(Error::MissingBlock(2)));
+}
+
+#[tokio::test]
+async fn peer_does_not_serve_undeclared_classes() {
+    let chain = MadaraChain::new();
+    let (_dir, peer) = serve_p2p(&chain, 1).await;
+
+    assert_matches!(peer.class_by_hash(FieldElement::from(0x1234u64), 1).await, Err(Error::InvalidPeerResponse(_)));
+    let class_hash = FieldElement::from(Felt252Wrapper::from(chain.sierra_class.0));
+    assert_matches!(peer.class_by_hash(class_hash, 1).await, Ok(ContractClass::Sierra(_)));
+    assert_matches!(peer.class_by_hash(class_hash, 0).await, Err(Error::InvalidPeerResponse(_)));
+}
@@ -0,0 +1,83 @@
+use assert_matches::assert_matches;
+use mp_felt::Felt252Wrapper;
+use starknet_ff::FieldElement;
+
+use super::chain_id;
+use super::madara::{serve, MadaraChain, PAID_FEE_ON_L1};
+use crate::convert;
+use crate::error::Error;
+use crate::types::{ContractClass, Transaction};
+use crate::verify::{block_state_diff, verify_block};
+
+#[tokio::test]
+async fn server_serves_finalized_blocks() {
+    let chain = MadaraChain::new();
+    let (_dir, gateway) = serve(&chain, 1, 1).await;
+
+    let genesis = gateway.block(0).await.unwrap().unwrap();
+    assert_eq!(genesis.block_hash, FieldElement::from(chain.blocks[0].header().hash()));
+
+    let block = gateway.block(1).await.unwrap().unwrap();
+    let header = chain.blocks[1].header();
+    assert_eq!(block.block_hash, FieldElement::from(header.hash()));
+    assert_eq!(block.parent_block_hash, genesis.block_hash);
+    assert_eq!(block.timestamp, header.block_timestamp);
+    assert_eq!(block.l1_gas_price.price_in_fri, FieldElement::from(100_000_000u128));
+    let actual_fees: Vec<_> = block.transaction_receipts.iter().map(|receipt| receipt.actual_fee).collect();
+    assert_eq!(actual_fees, [FieldElement::THREE, FieldElement::ONE, FieldElement::TWO]);
+
+    let state_update = gateway.state_update(1).await.unwrap();
+    assert_eq!(state_update.block_hash, block.block_hash);
+    assert_eq!(block_state_diff(&state_update.state_diff), chain.state_diffs[1]);
+    assert_matches!(verify_block(&block, &state_update.state_diff, header), Ok(()));
+}
+
+#[tokio::test]
+async fn server_transactions_convert_back_to_the_executed_ones() {
+    let chain = MadaraChain::new();
+    let (_dir, gateway) = serve(&chain, 1, 1).await;
+
+    let block = gateway.block(1).await.unwrap().unwrap();
+    let mut transactions = block.transactions.into_iter();
+
+    let Some(Transaction::Declare(declare)) = transactions.next() else { panic!("expected a declare transaction") };
+    let class = gateway.class_by_hash(declare.class_hash, 1).await.unwrap();
+    let (_, sierra_class) = convert::declare_transaction(declare, class, chain_id()).unwrap();
+    assert_eq!(sierra_class, Some(chain.sierra_class.1));
+
+    let Some(Transaction::Invoke(invoke)) = transactions.next() else { panic!("expected an invoke transaction") };
+    assert!(convert::invoke_transaction(invoke, chain_id()).is_ok());
+
+    let Some(Transaction::L1Handler(l1_handler)) = transactions.next() else { panic!("expected an L1 handler") };
+    let paid_fee_on_l1 = convert::paid_fee_on_l1(&l1_handler, FieldElement::TWO).unwrap();
+    assert_eq!(paid_fee_on_l1, PAID_FEE_ON_L1);
+    let tx_hash = l1_handler.transaction_hash;
+    let converted = convert::l1_handler_transaction(l1_handler, paid_fee_on_l1, chain_id()).unwrap();
+    assert_eq!(FieldElement::from(Felt252Wrapper::from(converted.tx_hash)), tx_hash);
+}
+
+#[tokio::test]
+async fn server_only_serves_finalized_and_indexed_blocks() {
+    let chain = MadaraChain::new();
+
+    let (_dir, gateway) = serve(&chain, 0, 1).await;
+    assert_matches!(gateway.block(1).await, Ok(None));
+    assert_matches!(gateway.state_update(1).await, Err(Error::MissingBlock(1)));
+
+    let (_dir, gateway) = serve(&chain, 1, 0).await;
+    assert_matches!(gateway.block(1).await, Ok(None));
+    assert_matches!(gateway.block(0).await, Ok(Some(_)));
+}
+
+#[tokio::test]
+async fn server_does_not_serve_unknown_classes() {
+    let chain = MadaraChain::new();
+    let (_dir, gateway) = serve(&chain, 1, 1).await;
+
+    assert_matches!(
+        gateway.class_by_hash(FieldElement::from(0x1234u64), 1).await,
+        Err(Error::Gateway { code, .. }) if code == "StarknetErrorCode.UNDECLARED_CLASS"
+    );
+    let class_hash = FieldElement::from(Felt252Wrapper::from(chain.sierra_class.0));
+    assert_matches!(gateway.class_by_hash(class_hash, 1).await, Ok(ContractClass::Sierra(_)));
+}
@@ -0,0 +1,242 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use async_trait::async_trait;
+use blockifier::transaction::transaction_execution::Transaction as ExecutionTransaction;
+use mc_db::GatewaySyncedBlock;
+use mp_block::commitments::BlockStateDiff;
+use mp_block::Header;
+use mp_felt::Felt252Wrapper;
+use starknet_api::core::Nonce;
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{Fee, TransactionHash};
+
+use super::chain_id;
+use super::madara::{genesis_header, open_backend, serve, serve_p2p, Block, MadaraChain, PAID_FEE_ON_L1};
+use crate::chain::{BlockInherents, LocalChain};
+use crate::error::Error;
+use crate::peer::PeerUpstream;
+use crate::GatewaySyncWorker;
+
+/// A chain whose runtime is faked, the execution of the blocks resulting in the state diff set by
+/// the test.
+#[derive(Clone)]
+struct FakeChain {
+    headers: Arc<Mutex<Vec<Header>>>,
+    /// State diff of the blocks built.
+    state_diff: BlockStateDiff,
+    /// Whether the import of the blocks fails.
+    import_fails: bool,
+}
+
+impl FakeChain {
+    fn new(headers: Vec<Header>, state_diff: BlockStateDiff) -> Self {
+        Self { headers: Arc::new(Mutex::new(headers)), state_diff, import_fails: false }
+    }
+
+    fn best_number(&self) -> u64 {
+        self.headers.lock().unwrap().last().unwrap().block_number
+    }
+}
+
+#[async_trait]
+impl LocalChain for FakeChain {
+    type BuiltBlock = Header;
+
+    fn best_header(&self) -> Result<Header, Error> {
+        Ok(self.headers.lock().unwrap().last().unwrap().clone())
+    }
+
+    fn chain_id(&self) -> Result<Felt252Wrapper, Error> {
+        Ok(chain_id())
+    }
+
+    async fn build_block(
+        &self,
+        inherents: BlockInherents,
+        transactions: Vec<ExecutionTransaction>,
+    ) -> Result<(Header, Header), Error> {
+        let header = super::madara::built_header(&self.best_header()?, &inherents, &transactions, &self.state_diff);
+        Ok((header.clone(), header))
+    }
+
+    async fn import_block(&mut self, block: Header) -> Result<(), Error> {
+        if self.import_fails {
+            return Err(Error::Import("the block import failed".to_string()));
+        }
+        self.headers.lock().unwrap().push(block);
+        Ok(())
+    }
+}
+
+type Worker = GatewaySyncWorker<Block, FakeChain>;
+
+fn synced(header: &Header) -> GatewaySyncedBlock {
+    GatewaySyncedBlock { block_number: header.block_number, block_hash: header.hash().into() }
+}
+
+fn l1_handler_hash(chain: &MadaraChain) -> StarkFelt {
+    let hashes: Vec<&TransactionHash> = chain.blocks[1].transactions_hashes().collect();
+    hashes[2].0
+}
+
+#[tokio::test]
+async fn worker_resumes_from_the_upstream_genesis() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+
+    let local = FakeChain::new(vec![genesis_header()], BlockStateDiff::default());
+    let worker = Worker::new(gateway.clone(), local, backend.clone(), Duration::ZERO);
+    assert_eq!(worker.resume().await.unwrap(), synced(&genesis_header()));
+    assert_eq!(backend.meta().last_gateway_synced_block().unwrap(), Some(synced(&genesis_header())));
+
+    // A chain whose genesis differs
+    let mut genesis = genesis_header();
+    genesis.block_timestamp = 1;
+    let (_dir, backend) = open_backend();
+    let worker =
+        Worker::new(gateway, FakeChain::new(vec![genesis], BlockStateDiff::default()), backend, Duration::ZERO);
+    assert_matches!(worker.resume().await, Err(Error::GenesisMismatch { .. }));
+}
+
+#[tokio::test]
+async fn worker_imports_the_upstream_blocks() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+    let local = FakeChain::new(vec![genesis_header()], chain.state_diffs[1].clone());
+    let mut worker = Worker::new(gateway, local.clone(), backend.clone(), Duration::ZERO);
+
+    let genesis = worker.resume().await.unwrap();
+    let block_1 = worker.sync_next_block(&genesis).await.unwrap().unwrap();
+    assert_eq!(block_1, synced(chain.blocks[1].header()));
+    assert_eq!(local.best_number(), 1);
+
+    // The class, the fee paid on L1 and the block are stored once the block is imported
+    let (class_hash, class) = &chain.sierra_class;
+    assert_eq!(backend.sierra_classes().get_sierra_class(*class_hash).unwrap().as_ref(), Some(class));
+    assert_eq!(
+        backend.l1_handler_paid_fee().get_fee_paid_for_l1_handler_tx(l1_handler_hash(&chain)).unwrap(),
+        PAID_FEE_ON_L1
+    );
+    assert_eq!(backend.meta().last_gateway_synced_block().unwrap(), Some(block_1));
+
+    // Block 2 is not produced yet
+    assert_matches!(worker.sync_next_block(&block_1).await, Ok(None));
+}
+
+#[tokio::test]
+async fn worker_imports_the_blocks_of_a_peer() {
+    let chain = MadaraChain::new();
+    let (_upstream, peer) = serve_p2p(&chain, 1).await;
+    let (_dir, backend) = open_backend();
+    let local = FakeChain::new(vec![genesis_header()], chain.state_diffs[1].clone());
+    let mut worker =
+        GatewaySyncWorker::<Block, _, PeerUpstream>::new(peer, local.clone(), backend.clone(), Duration::ZERO);
+
+    let genesis = worker.resume().await.unwrap();
+    let block_1 = worker.sync_next_block(&genesis).await.unwrap().unwrap();
+    assert_eq!(block_1, synced(chain.blocks[1].header()));
+    assert_eq!(local.best_number(), 1);
+
+    // The fee paid on L1, not served by the peers, is the one charged for the L1 handler
+    let (class_hash, class) = &chain.sierra_class;
+    assert_eq!(backend.sierra_classes().get_sierra_class(*class_hash).unwrap().as_ref(), Some(class));
+    assert_eq!(backend.l1_handler_paid_fee().get_fee_paid_for_l1_handler_tx(l1_handler_hash(&chain)).unwrap(), Fee(2));
+
+    assert_matches!(worker.sync_next_block(&block_1).await, Ok(None));
+}
+
+#[tokio::test]
+async fn worker_catches_up_after_an_unrecorded_import() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+    backend.meta().write_last_gateway_synced_block(&synced(&genesis_header())).unwrap();
+
+    // Block 1 was imported, but the node stopped before recording it
+    let local = FakeChain::new(vec![genesis_header(), chain.blocks[1].header().clone()], BlockStateDiff::default());
+    let worker = Worker::new(gateway, local, backend.clone(), Duration::ZERO);
+
+    assert_eq!(worker.resume().await.unwrap(), synced(chain.blocks[1].header()));
+    assert_eq!(backend.meta().last_gateway_synced_block().unwrap(), Some(synced(chain.blocks[1].header())));
+    assert_eq!(
+        backend.l1_handler_paid_fee().get_fee_paid_for_l1_handler_tx(l1_handler_hash(&chain)).unwrap(),
+        PAID_FEE_ON_L1
+    );
+}
+
+#[tokio::test]
+async fn worker_does_not_resume_a_diverging_chain() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+    backend.meta().write_last_gateway_synced_block(&synced(&genesis_header())).unwrap();
+
+    // A local block 1 which is not the upstream one
+    let mut block_1 = chain.blocks[1].header().clone();
+    block_1.block_timestamp += 1;
+    let local = FakeChain::new(vec![genesis_header(), block_1], BlockStateDiff::default());
+    let worker = Worker::new(gateway.clone(), local, backend.clone(), Duration::ZERO);
+    assert_matches!(worker.resume().await, Err(Error::BlockHashMismatch { block_number: 1, .. }));
+
+    // A best block not following the last block synced
+    let local = FakeChain::new(vec![genesis_header()], BlockStateDiff::default());
+    backend.meta().write_last_gateway_synced_block(&synced(chain.blocks[1].header())).unwrap();
+    let worker = Worker::new(gateway, local, backend, Duration::ZERO);
+    assert_matches!(worker.resume().await, Err(Error::LocalChainMismatch { best: 0, last_synced: Some(1) }));
+}
+
+#[tokio::test]
+async fn worker_rejects_a_block_with_another_parent() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+    let mut worker = Worker::new(
+        gateway,
+        FakeChain::new(vec![genesis_header()], BlockStateDiff::default()),
+        backend,
+        Duration::ZERO,
+    );
+
+    let last_synced = GatewaySyncedBlock { block_number: 0, block_hash: StarkFelt::ONE };
+    assert_matches!(worker.sync_next_block(&last_synced).await, Err(Error::ParentMismatch { block_number: 1, .. }));
+}
+
+#[tokio::test]
+async fn worker_stores_nothing_for_a_block_failing_verification() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+
+    // The local execution results in another state diff
+    let mut state_diff = chain.state_diffs[1].clone();
+    state_diff.nonces.insert(Felt252Wrapper::from(0x12fd537u64).into(), Nonce(StarkFelt::TWO));
+    let local = FakeChain::new(vec![genesis_header()], state_diff);
+    let mut worker = Worker::new(gateway, local.clone(), backend.clone(), Duration::ZERO);
+
+    let genesis = worker.resume().await.unwrap();
+    assert_matches!(worker.sync_next_block(&genesis).await, Err(Error::StateDiffMismatch { block_number: 1 }));
+    assert_eq!(local.best_number(), 0);
+    assert_eq!(backend.sierra_classes().get_sierra_class(chain.sierra_class.0).unwrap(), None);
+    assert!(backend.l1_handler_paid_fee().get_fee_paid_for_l1_handler_tx(l1_handler_hash(&chain)).is_err());
+    assert_eq!(backend.meta().last_gateway_synced_block().unwrap(), Some(genesis));
+}
+
+#[tokio::test]
+async fn worker_releases_the_classes_of_a_block_failing_import() {
+    let chain = MadaraChain::new();
+    let (_upstream, gateway) = serve(&chain, 1, 1).await;
+    let (_dir, backend) = open_backend();
+    let mut local = FakeChain::new(vec![genesis_header()], chain.state_diffs[1].clone());
+    local.import_fails = true;
+    let mut worker = Worker::new(gateway, local, backend.clone(), Duration::ZERO);
+
+    let genesis = worker.resume().await.unwrap();
+    assert_matches!(worker.sync_next_block(&genesis).await, Err(Error::Import(_)));
+    assert_eq!(backend.sierra_classes().get_sierra_class(chain.sierra_class.0).unwrap(), None);
+    assert!(backend.l1_handler_paid_fee().get_fee_paid_for_l1_handler_tx(l1_handler_hash(&chain)).is_err());
+    assert_eq!(backend.meta().last_gateway_synced_block().unwrap(), Some(genesis));
+}
@@ -0,0 +1,287 @@
+//! Objects returned by the feeder gateway.
+//!
+//! Only the fields needed to rebuild and check the blocks are part of these objects, which are also
+//! the ones served by the feeder gateway of Madara, see [`crate::server`]. Felts are hex strings,
+//! and the data availability modes are integers.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use starknet_core::types::contract::legacy::LegacyContractClass;
+use starknet_core::types::SierraEntryPoint;
+use starknet_ff::FieldElement;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Block {
+    pub block_hash: FieldElement,
+    pub block_number: u64,
+    pub parent_block_hash: FieldElement,
+    #[serde(default)]
+    pub state_root: Option<FieldElement>,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub sequencer_address: Option<FieldElement>,
+    #[serde(default)]
+    pub l1_gas_price: ResourcePrice,
+    #[serde(default)]
+    pub l1_data_gas_price: ResourcePrice,
+    pub transactions: Vec<Transaction>,
+    pub transaction_receipts: Vec<TransactionReceipt>,
+    #[serde(default)]
+    pub starknet_version: Option<String>,
+}
+
+/// A gas price in wei and in fri.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct ResourcePrice {
+    pub price_in_wei: FieldElement,
+    pub price_in_fri: FieldElement,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransactionReceipt {
+    pub transaction_hash: FieldElement,
+    #[serde(default)]
+    pub actual_fee: FieldElement,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum Transaction {
+    #[serde(rename = "INVOKE_FUNCTION")]
+    Invoke(InvokeTransaction),
+    #[serde(rename = "DECLARE")]
+    Declare(DeclareTransaction),
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount(DeployAccountTransaction),
+    #[serde(rename = "L1_HANDLER")]
+    L1Handler(L1HandlerTransaction),
+    /// Legacy deployment, not supported by Madara.
+    #[serde(rename = "DEPLOY")]
+    Deploy(DeployTransaction),
+}
+
+impl Transaction {
+    pub fn transaction_hash(&self) -> FieldElement {
+        match self {
+            Self::Invoke(tx) => tx.transaction_hash,
+            Self::Declare(tx) => tx.transaction_hash,
+            Self::DeployAccount(tx) => tx.transaction_hash,
+            Self::L1Handler(tx) => tx.transaction_hash,
+            Self::Deploy(tx) => tx.transaction_hash,
+        }
+    }
+}
+
+/// Fields introduced by the version 3 of the transactions.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FeeMarketFields {
+    #[serde(default)]
+    pub resource_bounds: Option<ResourceBoundsMapping>,
+    #[serde(default)]
+    pub tip: FieldElement,
+    #[serde(default)]
+    pub paymaster_data: Vec<FieldElement>,
+    #[serde(default)]
+    pub nonce_data_availability_mode: DataAvailabilityMode,
+    #[serde(default)]
+    pub fee_data_availability_mode: DataAvailabilityMode,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ResourceBoundsMapping {
+    #[serde(rename = "L1_GAS")]
+    pub l1_gas: ResourceBounds,
+    #[serde(rename = "L2_GAS")]
+    pub l2_gas: ResourceBounds,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ResourceBounds {
+    pub max_amount: FieldElement,
+    pub max_price_per_unit: FieldElement,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum DataAvailabilityMode {
+    #[default]
+    L1,
+    L2,
+}
+
+impl TryFrom<u8> for DataAvailabilityMode {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::L1),
+            1 => Ok(Self::L2),
+            _ => Err(format!("Unknown data availability mode {value}")),
+        }
+    }
+}
+
+impl From<DataAvailabilityMode> for u8 {
+    fn from(mode: DataAvailabilityMode) -> Self {
+        match mode {
+            DataAvailabilityMode::L1 => 0,
+            DataAvailabilityMode::L2 => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InvokeTransaction {
+    pub transaction_hash: FieldElement,
+    pub version: FieldElement,
+    /// Named `contract_address` in the version 0.
+    #[serde(alias = "contract_address")]
+    pub sender_address: FieldElement,
+    #[serde(default)]
+    pub entry_point_selector: Option<FieldElement>,
+    pub calldata: Vec<FieldElement>,
+    #[serde(default)]
+    pub signature: Vec<FieldElement>,
+    #[serde(default)]
+    pub max_fee: FieldElement,
+    #[serde(default)]
+    pub nonce: FieldElement,
+    #[serde(default)]
+    pub account_deployment_data: Vec<FieldElement>,
+    #[serde(flatten)]
+    pub fee_market: FeeMarketFields,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeclareTransaction {
+    pub transaction_hash: FieldElement,
+    pub version: FieldElement,
+    pub class_hash: FieldElement,
+    #[serde(default)]
+    pub compiled_class_hash: Option<FieldElement>,
+    pub sender_address: FieldElement,
+    #[serde(default)]
+    pub signature: Vec<FieldElement>,
+    #[serde(default)]
+    pub max_fee: FieldElement,
+    #[serde(default)]
+    pub nonce: FieldElement,
+    #[serde(default)]
+    pub account_deployment_data: Vec<FieldElement>,
+    #[serde(flatten)]
+    pub fee_market: FeeMarketFields,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeployAccountTransaction {
+    pub transaction_hash: FieldElement,
+    pub version: FieldElement,
+    pub class_hash: FieldElement,
+    pub contract_address_salt: FieldElement,
+    pub constructor_calldata: Vec<FieldElement>,
+    #[serde(default)]
+    pub signature: Vec<FieldElement>,
+    #[serde(default)]
+    pub max_fee: FieldElement,
+    #[serde(default)]
+    pub nonce: FieldElement,
+    #[serde(flatten)]
+    pub fee_market: FeeMarketFields,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct L1HandlerTransaction {
+    pub transaction_hash: FieldElement,
+    #[serde(default)]
+    pub version: FieldElement,
+    pub contract_address: FieldElement,
+    pub entry_point_selector: FieldElement,
+    #[serde(default)]
+    pub nonce: FieldElement,
+    pub calldata: Vec<FieldElement>,
+    /// Fee paid on L1 for the message, only served by the feeder gateway of Madara.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paid_fee_on_l1: Option<FieldElement>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeployTransaction {
+    pub transaction_hash: FieldElement,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StateUpdate {
+    pub block_hash: FieldElement,
+    pub new_root: FieldElement,
+    pub old_root: FieldElement,
+    pub state_diff: StateDiff,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StateDiff {
+    /// Storage updates by contract address.
+    pub storage_diffs: HashMap<FieldElement, Vec<StorageEntry>>,
+    pub nonces: HashMap<FieldElement, FieldElement>,
+    pub deployed_contracts: Vec<DeployedContract>,
+    #[serde(default)]
+    pub replaced_classes: Vec<DeployedContract>,
+    /// Cairo 0 classes.
+    pub old_declared_contracts: Vec<FieldElement>,
+    /// Cairo 1 classes.
+    pub declared_classes: Vec<DeclaredClass>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct StorageEntry {
+    pub key: FieldElement,
+    pub value: FieldElement,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct DeployedContract {
+    pub address: FieldElement,
+    pub class_hash: FieldElement,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct DeclaredClass {
+    pub class_hash: FieldElement,
+    pub compiled_class_hash: FieldElement,
+}
+
+/// A class returned by `get_class_by_hash`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum ContractClass {
+    Sierra(SierraClass),
+    Legacy(LegacyContractClass),
+}
+
+/// A Sierra class, whose ABI is returned as a string.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SierraClass {
+    pub sierra_program: Vec<FieldElement>,
+    pub contract_class_version: String,
+    pub entry_points_by_type: SierraEntryPoints,
+    #[serde(default)]
+    pub abi: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SierraEntryPoints {
+    #[serde(rename = "CONSTRUCTOR")]
+    pub constructor: Vec<SierraEntryPoint>,
+    #[serde(rename = "EXTERNAL")]
+    pub external: Vec<SierraEntryPoint>,
+    #[serde(rename = "L1_HANDLER")]
+    pub l1_handler: Vec<SierraEntryPoint>,
+}
+
+/// The error body of the feeder gateway.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GatewayError {
+    pub code: String,
+    pub message: String,
+}
@@ -0,0 +1,51 @@
+//! The upstream sequencer the blocks are synced from.
+
+use async_trait::async_trait;
+use starknet_ff::FieldElement;
+
+use crate::error::Error;
+use crate::gateway::FeederGateway;
+use crate::types::{Block, ContractClass, StateUpdate};
+
+/// Serves the blocks of the upstream sequencer, as objects of its feeder gateway.
+#[async_trait]
+pub trait Upstream: Send + Sync {
+    /// The block `block_number`, `None` if the sequencer hasn't produced it yet.
+    async fn block(&self, block_number: u64) -> Result<Option<Block>, Error>;
+
+    async fn state_update(&self, block_number: u64) -> Result<StateUpdate, Error>;
+
+    /// The class `class_hash`, as declared at block `block_number`.
+    async fn class_by_hash(&self, class_hash: FieldElement, block_number: u64) -> Result<ContractClass, Error>;
+}
+
+#[async_trait]
+impl Upstream for FeederGateway {
+    async fn block(&self, block_number: u64) -> Result<Option<Block>, Error> {
+        FeederGateway::block(self, block_number).await
+    }
+
+    async fn state_update(&self, block_number: u64) -> Result<StateUpdate, Error> {
+        FeederGateway::state_update(self, block_number).await
+    }
+
+    async fn class_by_hash(&self, class_hash: FieldElement, block_number: u64) -> Result<ContractClass, Error> {
+        FeederGateway::class_by_hash(self, class_hash, block_number).await
+    }
+}
+
+/// The upstream chosen when the node starts.
+#[async_trait]
+impl<U: Upstream + ?Sized> Upstream for Box<U> {
+    async fn block(&self, block_number: u64) -> Result<Option<Block>, Error> {
+        (**self).block(block_number).await
+    }
+
+    async fn state_update(&self, block_number: u64) -> Result<StateUpdate, Error> {
+        (**self).state_update(block_number).await
+    }
+
+    async fn class_by_hash(&self, class_hash: FieldElement, block_number: u64) -> Result<ContractClass, Error> {
+        (**self).class_by_hash(class_hash, block_number).await
+    }
+}
@@ -0,0 +1,66 @@
+//! Checks of the blocks built locally against the upstream ones.
+
+use mp_block::commitments::BlockStateDiff;
+use mp_block::Header;
+use mp_felt::Felt252Wrapper;
+use starknet_api::hash::StarkFelt;
+use starknet_ff::FieldElement;
+
+use crate::error::Error;
+use crate::types::{Block, StateDiff};
+
+/// Check that `local`, the header of the block built from `upstream`, is the upstream block.
+///
+/// The state diff and the state root are checked against the upstream ones first, to report a more
+/// precise error than a block hash mismatch.
+pub fn verify_block(upstream: &Block, state_diff: &StateDiff, local: &Header) -> Result<(), Error> {
+    let block_number = upstream.block_number;
+
+    let upstream_diff = block_state_diff(state_diff);
+    if upstream_diff.commitment() != local.state_diff_commitment || upstream_diff.len() != local.state_diff_length {
+        return Err(Error::StateDiffMismatch { block_number });
+    }
+
+    let expected = upstream.state_root.ok_or(Error::MissingStateRoot { block_number })?;
+    let computed: FieldElement = Felt252Wrapper::from(local.global_state_root).into();
+    if computed != expected {
+        return Err(Error::StateRootMismatch { block_number, expected, computed });
+    }
+
+    let computed: FieldElement = local.hash().into();
+    if computed != upstream.block_hash {
+        return Err(Error::BlockHashMismatch { block_number, expected: upstream.block_hash, computed });
+    }
+
+    Ok(())
+}
+
+/// The state diff of a gateway state update, as committed to by the block headers.
+///
+/// Replaced classes are counted with the deployed contracts.
+pub fn block_state_diff(state_diff: &StateDiff) -> BlockStateDiff {
+    let mut diff = BlockStateDiff::default();
+
+    for contract in state_diff.deployed_contracts.iter().chain(&state_diff.replaced_classes) {
+        diff.deployed_contracts.insert(felt(contract.address).into(), felt(contract.class_hash).into());
+    }
+    for class in &state_diff.declared_classes {
+        diff.declared_classes.insert(felt(class.class_hash).into(), felt(class.compiled_class_hash).into());
+    }
+    diff.deprecated_declared_classes.extend(state_diff.old_declared_contracts.iter().map(|v| felt(*v).into()));
+    for (address, entries) in &state_diff.storage_diffs {
+        let updates = diff.storage_diffs.entry(felt(*address).into()).or_default();
+        for entry in entries {
+            updates.insert(felt(entry.key).into(), StarkFelt::from(felt(entry.value)));
+        }
+    }
+    for (address, nonce) in &state_diff.nonces {
+        diff.nonces.insert(felt(*address).into(), felt(*nonce).into());
+    }
+
+    diff
+}
+
+fn felt(value: FieldElement) -> Felt252Wrapper {
+    value.into()
+}
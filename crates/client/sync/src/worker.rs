@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::num::NonZeroU128;
+use std::sync::Arc;
+use std::time::Duration;
+
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transaction_execution::Transaction as ExecutionTransaction;
+use futures_timer::Delay;
+use mc_db::GatewaySyncedBlock;
+use mp_felt::Felt252Wrapper;
+use mp_starknet_inherent::{L1GasPrices, StarknetInherentData, DEFAULT_SEQUENCER_ADDRESS};
+use sp_runtime::traits::Block as BlockT;
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::ContractClass;
+use starknet_api::transaction::Fee;
+use starknet_ff::FieldElement;
+
+use crate::chain::{BlockInherents, LocalChain};
+use crate::error::Error;
+use crate::gateway::FeederGateway;
+use crate::types::{Block, Transaction};
+use crate::upstream::Upstream;
+use crate::{convert, verify};
+
+/// Imports the blocks of an upstream sequencer, polling its feeder gateway, or a peer serving its
+/// blocks on the Starknet p2p network, for new blocks.
+///
+/// Each upstream block is executed again on top of the best block, and imported only if the
+/// Starknet block built locally is the upstream one. The last block synced is recorded in the
+/// Madara DB to resume syncing after a restart.
+pub struct GatewaySyncWorker<B: BlockT, L, U = FeederGateway> {
+    upstream: U,
+    chain: L,
+    backend: Arc<mc_db::Backend<B>>,
+    poll_interval: Duration,
+}
+
+/// The transactions of an upstream block, with what the node stores along the block.
+struct BlockTransactions {
+    transactions: Vec<ExecutionTransaction>,
+    /// Sierra classes declared in the block, read by the block import.
+    sierra_classes: Vec<(ClassHash, ContractClass)>,
+    /// Fees paid on L1 by the L1 handlers, by transaction hash.
+    l1_handler_fees: Vec<(StarkFelt, Fee)>,
+}
+
+impl<B: BlockT, L: LocalChain, U: Upstream> GatewaySyncWorker<B, L, U> {
+    pub fn new(upstream: U, chain: L, backend: Arc<mc_db::Backend<B>>, poll_interval: Duration) -> Self {
+        Self { upstream, chain, backend, poll_interval }
+    }
+
+    /// Sync until an error that can't be recovered from by retrying.
+    pub async fn run(mut self) {
+        let mut last_synced = loop {
+            match self.resume().await {
+                Ok(block) => break block,
+                Err(e) if e.is_transient() => {
+                    log::warn!("Failed to reach the upstream sequencer, retrying: {e}");
+                    Delay::new(self.poll_interval).await;
+                }
+                Err(e) => {
+                    log::error!("Cannot sync from the upstream sequencer: {e}");
+                    return;
+                }
+            }
+        };
+        log::info!("🔄 Syncing from the upstream sequencer after block {}", last_synced.block_number);
+
+        loop {
+            match self.sync_next_block(&last_synced).await {
+                Ok(Some(block)) => last_synced = block,
+                Ok(None) => Delay::new(self.poll_interval).await,
+                Err(e) if e.is_transient() => {
+                    log::warn!(
+                        "Failed to sync block {} from the upstream sequencer, retrying: {e}",
+                        last_synced.block_number + 1
+                    );
+                    Delay::new(self.poll_interval).await;
+                }
+                Err(e) => {
+                    log::error!(
+                        "Stopped syncing from the upstream sequencer at block {}: {e}",
+                        last_synced.block_number + 1
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    /// The last block synced, once checked that the local chain follows it.
+    pub(crate) async fn resume(&self) -> Result<GatewaySyncedBlock, Error> {
+        let best_header = self.chain.best_header()?;
+        let best = GatewaySyncedBlock { block_number: best_header.block_number, block_hash: best_header.hash().into() };
+
+        match self.backend.meta().last_gateway_synced_block()? {
+            Some(last_synced) if last_synced == best => Ok(best),
+            // The best block was imported but not recorded, nor were the fees of its L1 handlers
+            Some(last_synced)
+                if best.block_number == last_synced.block_number + 1
+                    && best_header.parent_block_hash == last_synced.block_hash =>
+            {
+                let block =
+                    self.upstream.block(best.block_number).await?.ok_or(Error::MissingBlock(best.block_number))?;
+                let computed: FieldElement = Felt252Wrapper::from(best.block_hash).into();
+                if block.block_hash != computed {
+                    return Err(Error::BlockHashMismatch {
+                        block_number: best.block_number,
+                        expected: block.block_hash,
+                        computed,
+                    });
+                }
+                for (tx_hash, fee) in l1_handler_fees(&block)? {
+                    self.backend.l1_handler_paid_fee().store_fee_paid_for_l1_handler_tx(tx_hash, fee)?;
+                }
+                self.backend.meta().write_last_gateway_synced_block(&best)?;
+                Ok(best)
+            }
+            None if best.block_number == 0 => {
+                let genesis = self.upstream.block(0).await?.ok_or(Error::MissingBlock(0))?;
+                let local: FieldElement = Felt252Wrapper::from(best.block_hash).into();
+                if genesis.block_hash != local {
+                    return Err(Error::GenesisMismatch { local, upstream: genesis.block_hash });
+                }
+                self.backend.meta().write_last_gateway_synced_block(&best)?;
+                Ok(best)
+            }
+            last_synced => Err(Error::LocalChainMismatch {
+                best: best.block_number,
+                last_synced: last_synced.map(|block| block.block_number),
+            }),
+        }
+    }
+
+    /// Sync the block following `last_synced`, `None` if the sequencer hasn't produced it yet.
+    ///
+    /// Nothing is stored for a block that fails verification. The Sierra classes it declares are
+    /// stored just before its import, which reads them, and released if the import fails.
+    pub(crate) async fn sync_next_block(
+        &mut self,
+        last_synced: &GatewaySyncedBlock,
+    ) -> Result<Option<GatewaySyncedBlock>, Error> {
+        let block_number = last_synced.block_number + 1;
+        let Some(block) = self.upstream.block(block_number).await? else {
+            return Ok(None);
+        };
+        let expected_parent: FieldElement = Felt252Wrapper::from(last_synced.block_hash).into();
+        if block.parent_block_hash != expected_parent {
+            return Err(Error::ParentMismatch {
+                block_number,
+                expected: expected_parent,
+                found: block.parent_block_hash,
+            });
+        }
+        let state_update = self.upstream.state_update(block_number).await?;
+
+        let BlockTransactions { transactions, sierra_classes, l1_handler_fees } = self.transactions(&block).await?;
+        let (header, built) = self.chain.build_block(inherents(&block), transactions).await?;
+        verify::verify_block(&block, &state_update.state_diff, &header)?;
+
+        for (class_hash, class) in &sierra_classes {
+            self.backend.sierra_classes().store_sierra_class(*class_hash, class.clone())?;
+        }
+        if let Err(e) = self.chain.import_block(built).await {
+            for (class_hash, _) in &sierra_classes {
+                if let Err(e) = self.backend.sierra_classes().release_sierra_class(*class_hash) {
+                    log::warn!("Failed to release the Sierra class {class_hash:?} of a block not imported: {e}");
+                }
+            }
+            return Err(e);
+        }
+        for (tx_hash, fee) in l1_handler_fees {
+            self.backend.l1_handler_paid_fee().store_fee_paid_for_l1_handler_tx(tx_hash, fee)?;
+        }
+
+        let synced = GatewaySyncedBlock { block_number, block_hash: Felt252Wrapper::from(block.block_hash).into() };
+        self.backend.meta().write_last_gateway_synced_block(&synced)?;
+        log::info!("✨ Imported block #{block_number} ({:#x}) from the upstream sequencer", block.block_hash);
+
+        Ok(Some(synced))
+    }
+
+    /// The transactions of `block`, fetching the classes it declares.
+    async fn transactions(&self, block: &Block) -> Result<BlockTransactions, Error> {
+        let chain_id = self.chain.chain_id()?;
+        let actual_fees = actual_fees(block);
+
+        let mut transactions = Vec::with_capacity(block.transactions.len());
+        let mut sierra_classes = Vec::new();
+        let mut l1_handler_fees = Vec::new();
+        for transaction in block.transactions.iter().cloned() {
+            transactions.push(match transaction {
+                Transaction::Invoke(tx) => ExecutionTransaction::AccountTransaction(AccountTransaction::Invoke(
+                    convert::invoke_transaction(tx, chain_id)?,
+                )),
+                Transaction::Declare(tx) => {
+                    let class_hash = Felt252Wrapper::from(tx.class_hash).into();
+                    let class = self.upstream.class_by_hash(tx.class_hash, block.block_number).await?;
+                    let (tx, sierra_class) = convert::declare_transaction(tx, class, chain_id)?;
+                    sierra_classes.extend(sierra_class.map(|class| (class_hash, class)));
+                    ExecutionTransaction::AccountTransaction(AccountTransaction::Declare(tx))
+                }
+                Transaction::DeployAccount(tx) => ExecutionTransaction::AccountTransaction(
+                    AccountTransaction::DeployAccount(convert::deploy_account_transaction(tx, chain_id)?),
+                ),
+                Transaction::L1Handler(tx) => {
+                    let actual_fee = actual_fees.get(&tx.transaction_hash).copied().unwrap_or_default();
+                    let paid_fee_on_l1 = convert::paid_fee_on_l1(&tx, actual_fee)?;
+                    let tx = convert::l1_handler_transaction(tx, paid_fee_on_l1, chain_id)?;
+                    l1_handler_fees.push((tx.tx_hash.0, paid_fee_on_l1));
+                    ExecutionTransaction::L1HandlerTransaction(tx)
+                }
+                Transaction::Deploy(tx) => {
+                    return Err(Error::UnsupportedTransaction { kind: "deploy", tx_hash: tx.transaction_hash });
+                }
+            });
+        }
+
+        Ok(BlockTransactions { transactions, sierra_classes, l1_handler_fees })
+    }
+}
+
+/// The fee charged for each transaction of `block`, by transaction hash.
+fn actual_fees(block: &Block) -> HashMap<FieldElement, FieldElement> {
+    block.transaction_receipts.iter().map(|receipt| (receipt.transaction_hash, receipt.actual_fee)).collect()
+}
+
+/// The fees paid on L1 by the L1 handlers of `block`, by transaction hash.
+fn l1_handler_fees(block: &Block) -> Result<Vec<(StarkFelt, Fee)>, Error> {
+    let actual_fees = actual_fees(block);
+    block
+        .transactions
+        .iter()
+        .filter_map(|transaction| match transaction {
+            Transaction::L1Handler(tx) => Some(tx),
+            _ => None,
+        })
+        .map(|tx| {
+            let actual_fee = actual_fees.get(&tx.transaction_hash).copied().unwrap_or_default();
+            Ok((Felt252Wrapper::from(tx.transaction_hash).into(), convert::paid_fee_on_l1(tx, actual_fee)?))
+        })
+        .collect()
+}
+
+/// The timestamp, sequencer address and gas prices of `block`.
+fn inherents(block: &Block) -> BlockInherents {
+    let sequencer_address = block
+        .sequencer_address
+        .map(|address| Felt252Wrapper::from(address).into())
+        .unwrap_or(DEFAULT_SEQUENCER_ADDRESS);
+    let l1_gas_price = L1GasPrices {
+        eth_l1_gas_price: gas_price(block.l1_gas_price.price_in_wei),
+        strk_l1_gas_price: gas_price(block.l1_gas_price.price_in_fri),
+        eth_l1_data_gas_price: gas_price(block.l1_data_gas_price.price_in_wei),
+        strk_l1_data_gas_price: gas_price(block.l1_data_gas_price.price_in_fri),
+        last_update_timestamp: block.timestamp.into(),
+    };
+
+    BlockInherents { timestamp: block.timestamp, starknet: StarknetInherentData { sequencer_address, l1_gas_price } }
+}
+
+/// Gas prices are non-zero for Madara, the blocks produced with a zero price won't match.
+fn gas_price(price: FieldElement) -> NonZeroU128 {
+    u128::try_from(Felt252Wrapper::from(price)).ok().and_then(NonZeroU128::new).unwrap_or(NonZeroU128::MIN)
+}
@@ -1,14 +1,18 @@
 use std::path::PathBuf;
 
-use madara_runtime::{AuraConfig, GrandpaConfig, RuntimeGenesisConfig, SealingMode, SystemConfig, WASM_BINARY};
+use madara_runtime::{
+    AccountId, AuraConfig, GrandpaConfig, RuntimeGenesisConfig, SealingMode, SystemConfig, WASM_BINARY,
+};
+use mc_genesis_data_provider::{OnDiskGenesisConfig, GENESIS_CHAIN_SPEC_PROPERTY};
 use mp_felt::Felt252Wrapper;
+use mp_genesis_config::DevAccounts;
 use pallet_starknet::genesis_loader::{GenesisData, GenesisLoader, HexFelt};
-use sc_service::{BasePath, ChainType};
+use sc_service::{BasePath, ChainType, Properties};
 use serde::{Deserialize, Serialize};
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_consensus_grandpa::AuthorityId as GrandpaId;
 use sp_core::storage::Storage;
-use sp_core::{Pair, Public};
+use sp_core::{sr25519, Pair, Public};
 use sp_state_machine::BasicExternalities;
 
 use crate::constants::DEV_CHAIN_ID;
@@ -50,14 +54,39 @@ pub fn get_from_seed<TPublic: Public>(seed: &str) -> <TPublic::Pair as Pair>::Pu
     TPublic::Pair::from_string(&format!("//{seed}"), None).expect("static values are valid; qed").public()
 }
 
+/// Generate an account id from seed.
+pub fn get_account_id_from_seed(seed: &str) -> AccountId {
+    AccountId::from(get_from_seed::<sr25519::Public>(seed))
+}
+
 /// Generate an Aura authority key.
 pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
     (get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
 }
 
-pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<DevChainSpec, String> {
+/// Create the dev chain spec.
+///
+/// The genesis is loaded and validated right away, with the `dev_accounts` added to it, and
+/// embedded in the chain spec properties. The genesis storage is built from that same data, so
+/// that the chain spec, the genesis state and `madara_predeployedAccounts` always agree.
+pub fn development_config(
+    sealing: SealingMode,
+    base_path: BasePath,
+    dev_accounts: Option<DevAccounts>,
+) -> Result<DevChainSpec, String> {
     let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
     let chain_id = DEV_CHAIN_ID;
+    let config_dir = base_path.config_dir(chain_id);
+
+    let mut genesis_data = load_genesis(config_dir.clone())?;
+    let with_dev_accounts = dev_accounts.is_some();
+    if let Some(dev_accounts) = dev_accounts {
+        if !genesis_data.contract_classes.iter().any(|(class_hash, _)| *class_hash == dev_accounts.class_hash) {
+            return Err(format!("The dev account class {:#x} is not declared in the genesis", dev_accounts.class_hash));
+        }
+        genesis_data.add_dev_accounts(&dev_accounts);
+    }
+    let properties = genesis_properties(&genesis_data)?;
 
     Ok(DevChainSpec::from_genesis(
         // Name
@@ -66,10 +95,10 @@ pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<D
         chain_id,
         ChainType::Development,
         move || {
-            let genesis_loader = load_genesis(base_path.config_dir(chain_id));
+            let genesis_loader = GenesisLoader::new(config_dir.clone(), genesis_data.clone());
 
             // Logging the development account
-            print_development_accounts(&genesis_loader);
+            print_development_accounts(&genesis_loader, with_dev_accounts);
 
             DevGenesisExt {
                 genesis_config: testnet_genesis(
@@ -77,6 +106,8 @@ pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<D
                     wasm_binary,
                     // Initial PoA authorities
                     vec![authority_keys_from_seed("Alice")],
+                    // Accounts administering the chain parameters
+                    vec![get_account_id_from_seed("Alice")],
                     true,
                 ),
                 sealing: sealing.clone(),
@@ -90,7 +121,7 @@ pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<D
         None,
         None,
         // Properties
-        None,
+        Some(properties),
         // Extensions
         None,
     ))
@@ -99,7 +130,17 @@ pub fn development_config(sealing: SealingMode, base_path: BasePath) -> Result<D
 // helper to print development accounts info
 // accounts with addresses 0x1 and 0x4 are NO VALIDATE accounts (don't require PK)
 // accounts with addresses 0x2 and 0x3 have the same PK
-pub fn print_development_accounts(genesis_loader: &GenesisLoader) {
+pub fn print_development_accounts(genesis_loader: &GenesisLoader, with_dev_accounts: bool) {
+    if with_dev_accounts {
+        log::info!("🧪 Using the following development accounts:");
+        for account in genesis_loader.data().predeployed_accounts.iter().filter(|account| account.private_key.is_some())
+        {
+            let private_key: String = account.private_key.iter().flatten().map(|byte| format!("{byte:02x}")).collect();
+            log::info!("🧪 {} with address: {:#x} and pk: 0x{private_key}", account.name, account.contract_address);
+        }
+        return;
+    }
+
     // TODO: this is only true by luck. It's not enforced by anything
     let no_validate_account_address = genesis_loader.data().contracts[0].0.0;
     let argent_account_address = genesis_loader.data().contracts[1].0.0;
@@ -117,10 +158,13 @@ pub fn print_development_accounts(genesis_loader: &GenesisLoader) {
     log::info!("🧪 CAIRO 1 with address: {cairo_1_no_validate_account_address:#x} and no pk");
 }
 
+/// Create the local testnet chain spec, embedding its genesis like [`development_config`]
 pub fn local_testnet_config(base_path: BasePath, chain_id: &str) -> Result<ChainSpec, String> {
     let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
 
-    let owned_chain_id = chain_id.to_owned();
+    let config_dir = base_path.config_dir(chain_id);
+    let genesis_data = load_genesis(config_dir.clone())?;
+    let properties = genesis_properties(&genesis_data)?;
 
     Ok(ChainSpec::from_genesis(
         // Name
@@ -130,11 +174,13 @@ pub fn local_testnet_config(base_path: BasePath, chain_id: &str) -> Result<Chain
         ChainType::Local,
         move || {
             testnet_genesis(
-                load_genesis(base_path.config_dir(&owned_chain_id)),
+                GenesisLoader::new(config_dir.clone(), genesis_data.clone()),
                 wasm_binary,
                 // Initial PoA authorities
                 // Intended to be only 2
                 vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")],
+                // Accounts administering the chain parameters
+                vec![get_account_id_from_seed("Alice")],
                 true,
             )
         },
@@ -145,21 +191,26 @@ pub fn local_testnet_config(base_path: BasePath, chain_id: &str) -> Result<Chain
         // Protocol ID
         None,
         // Properties
-        None,
+        Some(properties),
         None,
         // Extensions
         None,
     ))
 }
 
-fn load_genesis(data_path: PathBuf) -> GenesisLoader {
-    let genesis_path = data_path.join(GENESIS_ASSETS_DIR).join(GENESIS_ASSETS_FILE);
+fn load_genesis(data_path: PathBuf) -> Result<GenesisData, String> {
+    log::debug!("🧪 Loading genesis data from : {}", data_path.join(GENESIS_ASSETS_DIR).display());
+    OnDiskGenesisConfig(data_path)
+        .load_and_validate()
+        .map_err(|e| format!("Failed loading genesis. Please run `madara setup` before opening an issue. {e}"))
+}
 
-    log::debug!("🧪 Loading genesis data at : {}", genesis_path.display());
-    let genesis_file_content = std::fs::read_to_string(genesis_path)
-        .expect("Failed to read genesis file. Please run `madara setup` before opening an issue.");
-    let genesis_data: GenesisData = serde_json::from_str(&genesis_file_content).expect("Failed loading genesis");
-    GenesisLoader::new(data_path, genesis_data)
+/// Chain spec properties embedding the genesis data, read back by [`ChainSpecGenesisConfig`]
+///
+/// [`ChainSpecGenesisConfig`]: mc_genesis_data_provider::ChainSpecGenesisConfig
+fn genesis_properties(genesis_data: &GenesisData) -> Result<Properties, String> {
+    let genesis = serde_json::to_value(genesis_data).map_err(|e| e.to_string())?;
+    Ok(Properties::from_iter([(GENESIS_CHAIN_SPEC_PROPERTY.to_string(), genesis)]))
 }
 
 /// Configure initial storage state for FRAME modules.
@@ -167,9 +218,12 @@ fn testnet_genesis(
     genesis_loader: GenesisLoader,
     wasm_binary: &[u8],
     initial_authorities: Vec<(AuraId, GrandpaId)>,
+    control_accounts: Vec<AccountId>,
     _enable_println: bool,
 ) -> RuntimeGenesisConfig {
-    let starknet_genesis_config: madara_runtime::pallet_starknet::GenesisConfig<_> = genesis_loader.into();
+    let mut starknet_genesis_config: madara_runtime::pallet_starknet::GenesisConfig<_> = genesis_loader.into();
+    starknet_genesis_config.disable_transaction_fee = cfg!(feature = "disable-transaction-fee");
+    starknet_genesis_config.control_accounts = control_accounts;
 
     RuntimeGenesisConfig {
         system: SystemConfig {
@@ -1,4 +1,4 @@
-use crate::commands::{ExtendedRunCmd, SetupCmd};
+use crate::commands::{DbCmd, ExtendedRunCmd, SetupCmd};
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -22,6 +22,10 @@ pub enum Subcommand {
     /// Db meta columns information.
     ChainInfo(sc_cli::ChainInfoCmd),
 
+    /// Sub-commands concerned with the Madara database.
+    #[command(subcommand)]
+    Db(DbCmd),
+
     /// Validate blocks.
     CheckBlock(sc_cli::CheckBlockCmd),
 
@@ -6,7 +6,7 @@ use sc_cli::{ChainSpec, SubstrateCli};
 
 use crate::benchmarking::{inherent_benchmark_data, RemarkBuilder};
 use crate::cli::{Cli, Subcommand};
-use crate::commands::run_node;
+use crate::commands::{run_node, DbCmd};
 use crate::constants::DEV_CHAIN_ID;
 #[cfg(feature = "sharingan")]
 use crate::constants::SHARINGAN_CHAIN_ID;
@@ -40,9 +40,13 @@ impl SubstrateCli for Cli {
     fn load_spec(&self, id: &str) -> Result<Box<dyn ChainSpec>, String> {
         Ok(match id {
             DEV_CHAIN_ID => {
-                let sealing = self.run.sealing.map(Into::into).unwrap_or_default();
+                let sealing = self.run.sealing_mode().map_err(|e| e.to_string())?;
                 let base_path = self.run.base_path().map_err(|e| e.to_string())?;
-                Box::new(chain_spec::development_config(sealing, base_path)?)
+                let dev_accounts = self.run.dev_accounts().map_err(|e| e.to_string())?;
+                Box::new(chain_spec::development_config(sealing, base_path, dev_accounts)?)
+            }
+            _ if self.run.dev_accounts.is_some() => {
+                return Err("`--dev-accounts` can only be used with the dev chain".into());
             }
             #[cfg(feature = "sharingan")]
             SHARINGAN_CHAIN_ID => Box::new(chain_spec::ChainSpec::from_json_bytes(
@@ -179,6 +183,22 @@ pub fn run() -> sc_cli::Result<()> {
             let runner = cli.create_runner(cmd)?;
             runner.sync_run(|config| cmd.run::<Block>(&config))
         }
+        Some(Subcommand::Db(DbCmd::Migrate(ref cmd))) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.sync_run(|config| cmd.run(config))
+        }
+        Some(Subcommand::Db(DbCmd::Inspect(ref cmd))) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.sync_run(|config| cmd.run(config))
+        }
+        Some(Subcommand::Db(DbCmd::Verify(ref cmd))) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.sync_run(|config| cmd.run(config))
+        }
+        Some(Subcommand::Db(DbCmd::Reindex(ref cmd))) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.sync_run(|config| cmd.run(config))
+        }
         Some(Subcommand::Setup(ref cmd)) => cmd.run(),
         None => run_node(cli),
     }
@@ -0,0 +1,287 @@
+use madara_runtime::opaque::Block;
+use mc_mapping_sync::repair::SierraClassSource;
+use mc_sync::gateway::FeederGateway;
+use mp_felt::Felt252Wrapper;
+use sc_cli::{CliConfiguration, DatabaseParams, Result, SharedParams};
+use sc_service::Configuration;
+use sp_runtime::traits::NumberFor;
+use starknet_api::core::ClassHash;
+use starknet_api::state::ContractClass;
+
+use crate::service;
+use crate::starknet::db_config_dir;
+
+/// Sub-commands operating on the Madara database.
+#[derive(Debug, clap::Subcommand)]
+pub enum DbCmd {
+    /// Upgrade the database to the schema of this version of the node.
+    Migrate(MigrateCmd),
+
+    /// Display the content of the database and look up Starknet blocks and transactions.
+    Inspect(InspectCmd),
+
+    /// Check the database against the chain.
+    Verify(VerifyCmd),
+
+    /// Rebuild the database from the chain.
+    Reindex(ReindexCmd),
+}
+
+fn parse_felt(value: &str) -> std::result::Result<Felt252Wrapper, String> {
+    Felt252Wrapper::from_hex_be(value).map_err(|e| format!("{e:?}"))
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct MigrateCmd {
+    /// Only list the migrations that would be run, without modifying the database.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl MigrateCmd {
+    pub fn run(&self, config: Configuration) -> Result<()> {
+        let db_config_dir = db_config_dir(&config);
+
+        if self.dry_run {
+            let (schema_version, pending) = mc_db::pending_migrations(&config.database, &db_config_dir)?;
+            println!(
+                "Database schema version: {schema_version}, latest version: {}",
+                mc_db::migrations::CURRENT_SCHEMA_VERSION
+            );
+            if pending.is_empty() {
+                println!("The database is up to date");
+            }
+            for migration in pending {
+                println!("Would migrate from version {}: {}", migration.from_version, migration.description);
+            }
+            return Ok(());
+        }
+
+        let applied = mc_db::migrate(&config.database, &db_config_dir)?;
+        if applied.is_empty() {
+            println!("The database is up to date");
+        }
+        for migration in applied {
+            println!("Migrated from version {}: {}", migration.from_version, migration.description);
+        }
+
+        Ok(())
+    }
+}
+
+impl CliConfiguration for MigrateCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct InspectCmd {
+    /// Look up the Substrate blocks wrapping the Starknet block with this hash.
+    #[arg(long, value_parser = parse_felt)]
+    pub block_hash: Option<Felt252Wrapper>,
+
+    /// Look up the block and the receipt of the Starknet transaction with this hash.
+    #[arg(long, value_parser = parse_felt)]
+    pub transaction_hash: Option<Felt252Wrapper>,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl InspectCmd {
+    pub fn run(&self, mut config: Configuration) -> Result<()> {
+        // Must run before the database is opened by the node components
+        let column_stats = mc_db::column_stats(&config.database, &db_config_dir(&config))?;
+        println!("{:<4} {:<24} {:>12} {:>16}", "id", "column", "entries", "size (bytes)");
+        for stats in column_stats {
+            println!("{:<4} {:<24} {:>12} {:>16}", stats.column, stats.name, stats.entries, stats.values_size);
+        }
+
+        let (_, _, _, _, madara_backend) = service::new_chain_ops_without_migrating(&mut config)?;
+        println!("Storage mode: {}", madara_backend.storage_mode());
+        println!("Syncing tips: {:?}", madara_backend.meta().current_syncing_tips().map_err(|e| e.to_string())?);
+
+        if let Some(block_hash) = self.block_hash {
+            match madara_backend.mapping().block_hash(block_hash.into()).map_err(|e| e.to_string())? {
+                Some(substrate_block_hashes) => {
+                    println!("Starknet block {:#x} is wrapped in {substrate_block_hashes:?}", block_hash.0)
+                }
+                None => println!("Starknet block {:#x} is not in the mapping", block_hash.0),
+            }
+        }
+
+        if let Some(transaction_hash) = self.transaction_hash {
+            match madara_backend
+                .mapping()
+                .block_hash_from_transaction_hash(transaction_hash.into())
+                .map_err(|e| e.to_string())?
+            {
+                Some(substrate_block_hash) => {
+                    println!("Starknet transaction {:#x} is in block {substrate_block_hash:?}", transaction_hash.0)
+                }
+                None => println!("Starknet transaction {:#x} is not in the mapping", transaction_hash.0),
+            }
+            match madara_backend.receipts().get_receipt(transaction_hash.into()).map_err(|e| e.to_string())? {
+                Some(receipt) => println!("Receipt: {receipt:#?}"),
+                None => println!("No receipt stored for transaction {:#x}", transaction_hash.0),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CliConfiguration for InspectCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct VerifyCmd {
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl VerifyCmd {
+    pub fn run(&self, mut config: Configuration) -> Result<()> {
+        let (client, _, _, _, madara_backend) = service::new_chain_ops_without_migrating(&mut config)?;
+
+        let verification = mc_mapping_sync::repair::verify_chain(client.as_ref(), &madara_backend)
+            .map_err(|e| format!("Failed to verify the database: {e}"))?;
+
+        println!("Valid blocks: {}", verification.valid_blocks);
+        println!("Blocks waiting to be synced: {}", verification.pending_blocks);
+        for tip in &verification.unknown_tips {
+            println!("Unknown syncing tip: {tip:?}");
+        }
+        for number in &verification.unreachable_blocks {
+            println!("Block {number} is not synced and will never be");
+        }
+        for (number, reason) in &verification.invalid_blocks {
+            println!("Block {number} is invalid: {reason}");
+        }
+
+        if !verification.is_valid() {
+            return Err("The database is out of sync with the chain, run `madara db reindex` to repair it".into());
+        }
+        println!("The database is consistent with the chain");
+
+        Ok(())
+    }
+}
+
+impl CliConfiguration for VerifyCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct ReindexCmd {
+    /// Number of the first block to reindex.
+    #[arg(long, default_value_t = 0)]
+    pub from: NumberFor<Block>,
+
+    /// Url of a sequencer to fetch the Sierra classes missing from the database from, its feeder
+    /// gateway being served at `<url>/feeder_gateway`.
+    #[arg(long, value_hint = clap::ValueHint::Url)]
+    pub gateway: Option<String>,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+/// Fetches the Sierra classes from the feeder gateway of a sequencer
+struct GatewaySierraClasses {
+    gateway: FeederGateway,
+    tokio_handle: tokio::runtime::Handle,
+}
+
+impl SierraClassSource for GatewaySierraClasses {
+    fn sierra_class(&self, class_hash: ClassHash, block_number: u64) -> anyhow::Result<Option<ContractClass>> {
+        let class = self
+            .tokio_handle
+            .block_on(self.gateway.class_by_hash(Felt252Wrapper::from(class_hash).into(), block_number))?;
+        Ok(mc_sync::convert::sierra_class(class))
+    }
+}
+
+impl ReindexCmd {
+    pub fn run(&self, mut config: Configuration) -> Result<()> {
+        let sierra_classes = match &self.gateway {
+            Some(url) => Some(GatewaySierraClasses {
+                gateway: FeederGateway::new(url).map_err(|e| e.to_string())?,
+                tokio_handle: config.tokio_handle.clone(),
+            }),
+            None => None,
+        };
+        let (client, _, _, _, madara_backend) = service::new_chain_ops(&mut config)?;
+
+        let reindexing = mc_mapping_sync::repair::reindex_blocks(
+            client.as_ref(),
+            &madara_backend,
+            self.from,
+            sierra_classes.as_ref().map(|source| source as &dyn SierraClassSource),
+        )
+        .map_err(|e| format!("Failed to reindex the database: {e}"))?;
+
+        println!("Reindexed {} blocks from block {}", reindexing.reindexed_blocks, self.from);
+        println!("Removed {} entries pointing to unknown blocks", reindexing.purged_entries);
+        println!("Restored {} Sierra classes", reindexing.restored_sierra_classes);
+        for number in &reindexing.blocks_without_receipts {
+            println!("The receipts of block {number} could not be computed, the node will retry on its next start");
+        }
+        for class_hash in &reindexing.missing_sierra_classes {
+            println!("The Sierra class {} is missing, rerun with `--gateway` to fetch it", class_hash.0);
+        }
+
+        Ok(())
+    }
+}
+
+impl CliConfiguration for ReindexCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
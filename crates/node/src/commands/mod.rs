@@ -1,5 +1,7 @@
+mod db;
 mod run;
 mod setup;
 
+pub use db::*;
 pub use run::*;
 pub use setup::*;
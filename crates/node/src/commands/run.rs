@@ -1,16 +1,26 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use clap::ValueHint::FilePath;
 use madara_runtime::SealingMode;
-use sc_cli::{Result, RpcMethods, RunCmd, SubstrateCli};
+use mc_db::{DatabaseOptions, DEFAULT_PRUNING_BLOCKS};
+use mc_p2p::{Multiaddr, NetworkConfig};
+use mp_felt::Felt252Wrapper;
+use mp_genesis_config::{DevAccounts, DEFAULT_DEV_ACCOUNT_BALANCE};
+use sc_cli::{DatabasePruningMode, Result, RpcMethods, RunCmd, SubstrateCli};
 use sc_service::BasePath;
 use serde::{Deserialize, Serialize};
 
 use crate::cli::Cli;
 use crate::service;
 
+const DEFAULT_DEV_SEED: &str = "madara";
+/// The cairo 0 OpenZeppelin account of the default genesis
+const DEFAULT_DEV_ACCOUNT_CLASS_HASH: &str = "0x006280083f8c2a2db9f737320d5e3029b380e0e820fe24b8d312a6a34fdba0cd";
+
 /// Available Sealing methods.
-#[derive(Debug, Copy, Clone, clap::ValueEnum, Default, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum Sealing {
     /// Seal using rpc method.
     #[default]
@@ -20,18 +30,51 @@ pub enum Sealing {
     Instant,
     /// Seal when transaction is executed with finalization.
     InstantFinality,
+    /// Seal a block every given number of milliseconds, `interval=<ms>`.
+    Interval(u64),
+    /// Seal a block every given number of milliseconds, or sooner when enough transactions are
+    /// pending or they would fill a block, `hybrid=<ms>`. See `--sealing-max-pending-transactions`.
+    Hybrid(u64),
 }
 
-impl From<Sealing> for SealingMode {
-    fn from(value: Sealing) -> Self {
-        match value {
-            Sealing::Manual => SealingMode::Manual,
-            Sealing::Instant => SealingMode::Instant { finalize: false },
-            Sealing::InstantFinality => SealingMode::Instant { finalize: true },
+impl FromStr for Sealing {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let value = value.to_lowercase();
+        let block_time = |ms: &str| {
+            ms.parse::<u64>()
+                .ok()
+                .filter(|ms| *ms > 0)
+                .ok_or_else(|| format!("Invalid block time {ms:?}, expected a positive number of milliseconds"))
+        };
+        match value.split_once('=') {
+            None => match value.as_str() {
+                "manual" => Ok(Sealing::Manual),
+                "instant" => Ok(Sealing::Instant),
+                "instant-finality" => Ok(Sealing::InstantFinality),
+                _ => Err(format!(
+                    "Unknown sealing {value:?}, expected manual, instant, instant-finality, interval=<ms> or \
+                     hybrid=<ms>"
+                )),
+            },
+            Some(("interval", ms)) => Ok(Sealing::Interval(block_time(ms)?)),
+            Some(("hybrid", ms)) => Ok(Sealing::Hybrid(block_time(ms)?)),
+            Some((kind, _)) => Err(format!("Unknown sealing {kind:?}, expected interval=<ms> or hybrid=<ms>")),
         }
     }
 }
 
+/// How much history the node keeps.
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum StorageMode {
+    /// Keep the state of every block and index the storage history, so that it can be queried at
+    /// any block.
+    Archive,
+    /// Only keep the state of the last `--pruning-blocks` blocks.
+    Pruned,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
 pub enum SettlementLayer {
     /// Use Ethereum core contract
@@ -43,10 +86,31 @@ pub struct ExtendedRunCmd {
     #[clap(flatten)]
     pub base: RunCmd,
 
-    /// Choose sealing method.
-    #[clap(long, value_enum, ignore_case = true)]
+    /// Choose sealing method: manual, instant, instant-finality, interval=<ms> or hybrid=<ms>.
+    #[clap(long)]
     pub sealing: Option<Sealing>,
 
+    /// With hybrid sealing, seal a block as soon as this many transactions are ready
+    #[clap(long, value_name = "COUNT")]
+    pub sealing_max_pending_transactions: Option<u32>,
+
+    /// With hybrid sealing, seal a block as soon as the ready transactions weigh this many bytes
+    #[clap(long, value_name = "BYTES")]
+    pub sealing_max_pending_bytes: Option<u32>,
+
+    /// With interval or hybrid sealing, do not seal blocks without transactions
+    #[clap(long)]
+    pub sealing_skip_empty: bool,
+
+    /// With interval or hybrid sealing, finalize the sealed blocks, like `instant-finality`
+    #[clap(long)]
+    pub sealing_finalize: bool,
+
+    /// Make the timestamps of sealed blocks start at 0 and grow by the slot duration each block,
+    /// instead of following the system clock. Requires `--sealing`.
+    #[clap(long)]
+    pub deterministic_timestamps: bool,
+
     /// Choose a supported settlement layer
     #[clap(long, ignore_case = true, requires = "settlement_conf")]
     pub settlement: Option<SettlementLayer>,
@@ -58,9 +122,203 @@ pub struct ExtendedRunCmd {
     /// returned.
     #[clap(long, value_hint = FilePath, requires = "settlement")]
     pub settlement_conf: Option<PathBuf>,
+
+    /// Sync the chain from the feeder gateway of an upstream sequencer, e.g.
+    /// `https://sequencer.example.com`, instead of producing blocks
+    ///
+    /// The blocks are executed again and only imported if they hash to the upstream ones. The
+    /// upstream sequencer is a Madara node serving its feeder gateway with the same chain spec,
+    /// see `--feeder-gateway-addr`, as the genesis of the chain must be its block 0: the node
+    /// stops at startup otherwise, so the public Starknet networks can't be synced from.
+    #[clap(long, value_name = "URL", conflicts_with = "sealing")]
+    pub sync_from_gateway: Option<String>,
+
+    /// Sync the chain from a peer of the Starknet p2p network, e.g.
+    /// `/ip4/127.0.0.1/tcp/30334/p2p/<peer id>`, instead of producing blocks
+    ///
+    /// The blocks are checked as with `--sync-from-gateway`, the peer being a Madara node serving
+    /// its blocks with the same chain spec, see `--starknet-p2p-listen-addr`. The blocks declaring
+    /// Cairo 0 classes can't be synced this way, as their ABI is not served.
+    #[clap(
+        long,
+        value_name = "MULTIADDR",
+        conflicts_with_all = ["sealing", "sync_from_gateway"],
+        requires = "starknet_p2p_listen_addresses"
+    )]
+    pub sync_from_peer: Option<Multiaddr>,
+
+    /// Serve the finalized blocks on the feeder gateway endpoints, listening on this address, e.g.
+    /// `0.0.0.0:9545`
+    ///
+    /// Full nodes using the same chain spec can then sync the chain from the node with
+    /// `--sync-from-gateway`. The blocks declaring Cairo 0 classes can't be synced this way.
+    #[clap(long, value_name = "SOCKET_ADDR")]
+    pub feeder_gateway_addr: Option<SocketAddr>,
+
+    /// Serve the blocks on the Starknet p2p sync protocols, listening on this address, e.g.
+    /// `/ip4/0.0.0.0/tcp/30334`
+    ///
+    /// Other Starknet clients can then sync the chain from the node. The key identifying the node
+    /// on the Starknet p2p network is kept in the `starknet-p2p` directory of the chain.
+    #[clap(long = "starknet-p2p-listen-addr", value_name = "MULTIADDR")]
+    pub starknet_p2p_listen_addresses: Vec<Multiaddr>,
+
+    /// Peer to connect to on the Starknet p2p network, e.g.
+    /// `/ip4/127.0.0.1/tcp/30334/p2p/<peer id>`
+    #[clap(long = "starknet-p2p-bootnode", value_name = "MULTIADDR", requires = "starknet_p2p_listen_addresses")]
+    pub starknet_p2p_bootnodes: Vec<Multiaddr>,
+
+    /// Choose how much history the node keeps
+    ///
+    /// The mode is recorded in the database when it is created and cannot be changed afterwards.
+    /// If not specified, the mode of the existing database is used, or `pruned` for a new one.
+    #[clap(long, value_enum, ignore_case = true, conflicts_with = "state_pruning")]
+    pub storage_mode: Option<StorageMode>,
+
+    /// Number of blocks whose state is kept when running in `pruned` storage mode
+    #[clap(long, value_name = "COUNT")]
+    pub pruning_blocks: Option<u32>,
+
+    /// Size of the block cache of the Starknet database, in MiB
+    ///
+    /// Overrides the cache size of `--starknet-db-config`.
+    #[clap(long, value_name = "MiB")]
+    pub starknet_db_cache: Option<usize>,
+
+    /// Path to a JSON file containing the options of the Starknet database
+    ///
+    /// The file can set the cache size and the options of each column, by column name, e.g.
+    /// `{ "cache_size": 512, "columns": { "sierra_contract_classes": { "compression": "zstd" } }
+    /// }`. The columns not listed keep their default options. Only used by the RocksDB backend.
+    #[clap(long, value_hint = FilePath)]
+    pub starknet_db_config: Option<PathBuf>,
+
+    /// Number of funded accounts to add to the genesis of the dev chain
+    ///
+    /// Their keys are derived from `--dev-seed`, so the same seed always gives the same accounts.
+    /// They are returned along their private key by `madara_predeployedAccounts`.
+    #[clap(long, value_name = "COUNT")]
+    pub dev_accounts: Option<u32>,
+
+    /// Seed the keys of the dev accounts are derived from
+    #[clap(long, requires = "dev_accounts", default_value = DEFAULT_DEV_SEED)]
+    pub dev_seed: String,
+
+    /// Class hash of the dev accounts
+    ///
+    /// The class must be declared in the genesis, take the public key as only constructor
+    /// argument and store it in `Account_public_key`. Defaults to the OpenZeppelin account.
+    #[clap(
+        long,
+        requires = "dev_accounts",
+        value_name = "CLASS_HASH",
+        default_value = DEFAULT_DEV_ACCOUNT_CLASS_HASH
+    )]
+    pub dev_account_class: String,
 }
 
 impl ExtendedRunCmd {
+    /// The sealing mode, from `--sealing` and the `--sealing-*` options
+    pub fn sealing_mode(&self) -> Result<SealingMode> {
+        let Some(sealing) = self.sealing else {
+            if self.sealing_max_pending_transactions.is_some()
+                || self.sealing_max_pending_bytes.is_some()
+                || self.sealing_skip_empty
+                || self.sealing_finalize
+                || self.deterministic_timestamps
+            {
+                return Err("The `--sealing-*` options and `--deterministic-timestamps` require `--sealing`".into());
+            }
+            return Ok(SealingMode::default());
+        };
+
+        let is_hybrid = matches!(sealing, Sealing::Hybrid(_));
+        if !is_hybrid && (self.sealing_max_pending_transactions.is_some() || self.sealing_max_pending_bytes.is_some()) {
+            return Err("`--sealing-max-pending-*` options require `--sealing hybrid=<ms>`".into());
+        }
+        if !matches!(sealing, Sealing::Interval(_) | Sealing::Hybrid(_))
+            && (self.sealing_skip_empty || self.sealing_finalize)
+        {
+            return Err("`--sealing-skip-empty` and `--sealing-finalize` require interval or hybrid sealing".into());
+        }
+
+        Ok(match sealing {
+            Sealing::Manual => SealingMode::Manual,
+            Sealing::Instant => SealingMode::Instant { finalize: false },
+            Sealing::InstantFinality => SealingMode::Instant { finalize: true },
+            Sealing::Interval(block_time) => SealingMode::Interval {
+                block_time,
+                finalize: self.sealing_finalize,
+                skip_empty: self.sealing_skip_empty,
+            },
+            // Without `--sealing-max-pending-*`, blocks are sealed early only once the ready
+            // transactions would exceed the `BlockResourceLimits`
+            Sealing::Hybrid(block_time) => SealingMode::Hybrid {
+                block_time,
+                max_pending_transactions: self.sealing_max_pending_transactions,
+                max_pending_bytes: self.sealing_max_pending_bytes,
+                finalize: self.sealing_finalize,
+                skip_empty: self.sealing_skip_empty,
+            },
+        })
+    }
+
+    /// The options of the Starknet database, from `--starknet-db-config` and `--starknet-db-cache`
+    pub fn starknet_db_options(&self) -> Result<DatabaseOptions> {
+        let mut options = DatabaseOptions::default();
+        if let Some(path) = &self.starknet_db_config {
+            let file = std::fs::File::open(path)?;
+            let overrides: DatabaseOptions = serde_json::from_reader(file)
+                .map_err(|e| format!("Invalid Starknet database configuration {}: {e}", path.display()))?;
+            overrides.validate()?;
+            options = options.with_overrides(overrides);
+        }
+        if let Some(cache_size) = self.starknet_db_cache {
+            options.cache_size = cache_size;
+        }
+
+        Ok(options)
+    }
+
+    /// The Starknet p2p network to serve the blocks on, if the node listens on any address
+    pub fn starknet_p2p_config(&self) -> Option<NetworkConfig> {
+        (!self.starknet_p2p_listen_addresses.is_empty()).then(|| NetworkConfig {
+            listen_addresses: self.starknet_p2p_listen_addresses.clone(),
+            bootnodes: self.starknet_p2p_bootnodes.clone(),
+        })
+    }
+
+    /// The state pruning to configure Substrate with, if a storage mode was requested
+    pub fn state_pruning(&self) -> Result<Option<DatabasePruningMode>> {
+        match (self.storage_mode, self.pruning_blocks) {
+            (Some(StorageMode::Archive), Some(_)) => {
+                Err("`--pruning-blocks` can only be used with `--storage-mode pruned`".into())
+            }
+            (Some(StorageMode::Archive), None) => Ok(Some(DatabasePruningMode::Archive)),
+            (Some(StorageMode::Pruned), blocks) => {
+                Ok(Some(DatabasePruningMode::Custom(blocks.unwrap_or(DEFAULT_PRUNING_BLOCKS))))
+            }
+            (None, Some(_)) => Err("`--pruning-blocks` requires `--storage-mode pruned`".into()),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// The dev accounts to add to the genesis, from `--dev-accounts`
+    pub fn dev_accounts(&self) -> Result<Option<DevAccounts>> {
+        let Some(count) = self.dev_accounts else {
+            return Ok(None);
+        };
+        let class_hash = Felt252Wrapper::from_hex_be(&self.dev_account_class)
+            .map_err(|e| format!("Invalid dev account class hash {}: {e}", self.dev_account_class))?;
+
+        Ok(Some(DevAccounts {
+            count,
+            seed: self.dev_seed.clone(),
+            class_hash: class_hash.into(),
+            balance: DEFAULT_DEV_ACCOUNT_BALANCE,
+        }))
+    }
+
     /// The substrate base directory on your machine
     ///
     /// Will be different depending on your OS
@@ -77,6 +335,9 @@ pub fn run_node(mut cli: Cli) -> Result<()> {
     if cli.run.base.shared_params.dev {
         override_dev_environment(&mut cli.run);
     }
+    if let Some(state_pruning) = cli.run.state_pruning()? {
+        cli.run.base.import_params.pruning_params.state_pruning = Some(state_pruning);
+    }
     let runner = cli.create_runner(&cli.run.base)?;
 
     let settlement_config: Option<(SettlementLayer, PathBuf)> = match cli.run.settlement {
@@ -96,9 +357,23 @@ pub fn run_node(mut cli: Cli) -> Result<()> {
         }
     };
 
+    let starknet_db_options = cli.run.starknet_db_options()?;
+    let sealing = cli.run.sealing_mode()?;
+    let starknet_p2p_config = cli.run.starknet_p2p_config();
+
     runner.run_node_until_exit(|config| async move {
-        let sealing = cli.run.sealing.map(Into::into).unwrap_or_default();
-        service::new_full(config, sealing, settlement_config).map_err(sc_cli::Error::Service)
+        service::new_full(
+            config,
+            sealing,
+            settlement_config,
+            starknet_db_options,
+            cli.run.deterministic_timestamps,
+            cli.run.sync_from_gateway,
+            cli.run.sync_from_peer,
+            cli.run.feeder_gateway_addr,
+            starknet_p2p_config,
+        )
+        .map_err(sc_cli::Error::Service)
     })
 }
 
@@ -120,3 +395,65 @@ fn override_dev_environment(cmd: &mut ExtendedRunCmd) {
     cmd.base.rpc_external = true;
     cmd.base.rpc_methods = RpcMethods::Unsafe;
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn sealing_is_parsed_case_insensitively() {
+        assert_eq!(Sealing::from_str("manual"), Ok(Sealing::Manual));
+        assert_eq!(Sealing::from_str("Instant"), Ok(Sealing::Instant));
+        assert_eq!(Sealing::from_str("INSTANT-FINALITY"), Ok(Sealing::InstantFinality));
+        assert_eq!(Sealing::from_str("interval=2000"), Ok(Sealing::Interval(2_000)));
+        assert_eq!(Sealing::from_str("Hybrid=6000"), Ok(Sealing::Hybrid(6_000)));
+    }
+
+    #[test]
+    fn block_time_must_be_a_positive_number_of_milliseconds() {
+        assert!(Sealing::from_str("interval=0").is_err());
+        assert!(Sealing::from_str("interval=-1").is_err());
+        assert!(Sealing::from_str("hybrid=").is_err());
+        assert!(Sealing::from_str("hybrid=6s").is_err());
+    }
+
+    fn sealing_mode(args: &[&str]) -> Result<SealingMode> {
+        let cli = Cli::try_parse_from([&["madara"][..], args].concat()).unwrap();
+        cli.run.sealing_mode()
+    }
+
+    #[test]
+    fn hybrid_sealing_does_not_require_pending_limits() {
+        assert_eq!(
+            sealing_mode(&["--sealing=hybrid=6000"]).unwrap(),
+            SealingMode::Hybrid {
+                block_time: 6_000,
+                max_pending_transactions: None,
+                max_pending_bytes: None,
+                finalize: false,
+                skip_empty: false,
+            }
+        );
+        assert_eq!(
+            sealing_mode(&["--sealing=hybrid=6000", "--sealing-max-pending-transactions=100"]).unwrap(),
+            SealingMode::Hybrid {
+                block_time: 6_000,
+                max_pending_transactions: Some(100),
+                max_pending_bytes: None,
+                finalize: false,
+                skip_empty: false,
+            }
+        );
+        assert!(sealing_mode(&["--sealing=interval=6000", "--sealing-max-pending-bytes=1024"]).is_err());
+    }
+
+    #[test]
+    fn unknown_sealing_is_rejected() {
+        assert!(Sealing::from_str("").is_err());
+        assert!(Sealing::from_str("aura").is_err());
+        assert!(Sealing::from_str("manual=1000").is_err());
+        assert!(Sealing::from_str("interval").is_err());
+    }
+}
@@ -1,11 +1,13 @@
 use std::path::{Path, PathBuf};
 
+use mc_genesis_data_provider::validate_genesis_data;
+use mp_genesis_config::GenesisSpec;
 use sc_cli::{Error, Result, SubstrateCli};
 use sc_service::BasePath;
 use sha3::{Digest, Sha3_256};
 use url::Url;
 
-use crate::chain_spec::GENESIS_ASSETS_DIR;
+use crate::chain_spec::{GENESIS_ASSETS_DIR, GENESIS_ASSETS_FILE};
 use crate::cli::Cli;
 use crate::configs::FileInfos;
 use crate::{configs, constants};
@@ -46,6 +48,11 @@ pub struct SetupCmd {
 
     #[clap(flatten)]
     pub source: SetupSource,
+
+    /// Generate the genesis from a genesis spec, once the assets are copied, instead of using the
+    /// genesis.json of the source. Class paths are relative to the config directory.
+    #[clap(long, value_name = "PATH")]
+    pub genesis_spec: Option<PathBuf>,
 }
 
 impl SetupCmd {
@@ -197,10 +204,29 @@ impl SetupCmd {
             write_content_to_disk(&asset_file_content, &asset_dest_path)?;
         }
 
+        if let Some(genesis_spec_path) = &self.genesis_spec {
+            generate_genesis(genesis_spec_path, &dest_config_dir_path)?;
+        }
+
         Ok(())
     }
 }
 
+/// Expand a genesis spec into the genesis.json of `config_dir`, checking it against the classes
+/// of `config_dir`
+fn generate_genesis(genesis_spec_path: &Path, config_dir: &Path) -> Result<()> {
+    let spec: GenesisSpec = serde_json::from_slice(&std::fs::read(genesis_spec_path)?)
+        .map_err(|e| Error::Input(format!("Invalid genesis spec '{}': {}", genesis_spec_path.display(), e)))?;
+    let genesis_data = spec.expand().map_err(|e| Error::Input(format!("Invalid genesis spec: {}", e)))?;
+    let genesis_data =
+        validate_genesis_data(config_dir.to_path_buf(), genesis_data).map_err(|e| Error::Application(Box::new(e)))?;
+
+    let genesis_path = config_dir.join(GENESIS_ASSETS_DIR).join(GENESIS_ASSETS_FILE);
+    println!("Writing the genesis generated from '{}' to '{}'", genesis_spec_path.display(), genesis_path.display());
+    let content = serde_json::to_vec_pretty(&genesis_data).map_err(|e| Error::Application(Box::new(e)))?;
+    write_content_to_disk(content, &genesis_path)
+}
+
 fn write_content_to_disk<T: AsRef<[u8]>>(config_content: T, dest_config_file_path: &Path) -> Result<()> {
     std::fs::create_dir_all(
         dest_config_file_path.parent().expect("dest_config_file_path should be the path to a file, not a directory"),
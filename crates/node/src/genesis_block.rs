@@ -77,8 +77,15 @@ fn construct_genesis_block<Block: BlockT>(state_root: Block::Hash, state_version
             block_number: Default::default(),
             sequencer_address: Default::default(),
             block_timestamp: Default::default(),
+            global_state_root: Default::default(),
             transaction_count: Default::default(),
+            transaction_commitment: Default::default(),
             event_count: Default::default(),
+            event_commitment: Default::default(),
+            receipt_commitment: Default::default(),
+            state_diff_commitment: Default::default(),
+            state_diff_length: Default::default(),
+            l1_da_mode: Default::default(),
             protocol_version: Default::default(),
             extra_data: Default::default(),
         },
@@ -12,7 +12,9 @@ mod configs;
 mod constants;
 mod genesis_block;
 mod import_queue;
+mod metrics;
 mod rpc;
+mod sealing;
 mod starknet;
 
 fn main() -> sc_cli::Result<()> {
@@ -0,0 +1,72 @@
+//! Prometheus metrics of the client caches.
+
+use std::time::Duration;
+
+use futures_timer::Delay;
+use mc_storage::contract_class_cache::{ContractClassCache, ContractClassCacheStats};
+use prometheus_endpoint::prometheus::{Counter, Gauge};
+use prometheus_endpoint::{register, PrometheusError, Registry};
+
+/// How often the statistics of the caches are exported.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct ContractClassCacheMetrics {
+    pub hits: Counter,
+    pub misses: Counter,
+    pub evictions: Counter,
+    pub size: Gauge,
+    pub hit_rate: Gauge,
+}
+
+impl ContractClassCacheMetrics {
+    pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(Self {
+            hits: register(
+                Counter::new("madara_contract_class_cache_hits", "Counter for contract classes found in the cache")?,
+                registry,
+            )?,
+            misses: register(
+                Counter::new(
+                    "madara_contract_class_cache_misses",
+                    "Counter for contract classes decoded from storage",
+                )?,
+                registry,
+            )?,
+            evictions: register(
+                Counter::new(
+                    "madara_contract_class_cache_evictions",
+                    "Counter for contract classes evicted from the cache",
+                )?,
+                registry,
+            )?,
+            size: register(
+                Gauge::new("madara_contract_class_cache_size", "Gauge for the number of cached contract classes")?,
+                registry,
+            )?,
+            hit_rate: register(
+                Gauge::new(
+                    "madara_contract_class_cache_hit_rate",
+                    "Gauge for the share of class lookups served by the cache",
+                )?,
+                registry,
+            )?,
+        })
+    }
+
+    /// Export the statistics of the contract class cache of the storage overrides periodically.
+    pub async fn run(self, cache: ContractClassCache) {
+        let mut reported = ContractClassCacheStats::default();
+        loop {
+            Delay::new(REPORT_INTERVAL).await;
+
+            let stats = cache.stats();
+            self.hits.inc_by(stats.hits.saturating_sub(reported.hits) as f64);
+            self.misses.inc_by(stats.misses.saturating_sub(reported.misses) as f64);
+            self.evictions.inc_by(stats.evictions.saturating_sub(reported.evictions) as f64);
+            self.size.set(stats.len as f64);
+            self.hit_rate.set(stats.hit_rate());
+            reported = stats;
+        }
+    }
+}
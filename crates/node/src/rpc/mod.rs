@@ -5,6 +5,7 @@
 
 #![warn(missing_docs)]
 
+mod sealing;
 mod starknet;
 use std::sync::Arc;
 
@@ -24,6 +25,9 @@ use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 pub use starknet::StarknetDeps;
 
+use self::sealing::{SealingClockApiServer, SealingClockRpc};
+use crate::sealing::SealingClock;
+
 /// Full client dependencies.
 pub struct FullDeps<A: ChainApi, C, G: GenesisProvider, P> {
     /// The client instance to use.
@@ -36,6 +40,8 @@ pub struct FullDeps<A: ChainApi, C, G: GenesisProvider, P> {
     pub deny_unsafe: DenyUnsafe,
     /// Manual seal command sink
     pub command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+    /// Clock of the blocks sealed by manual sealing
+    pub sealing_clock: Option<Arc<SealingClock>>,
     /// Starknet dependencies
     pub starknet: StarknetDeps<C, G, Block>,
 }
@@ -68,7 +74,8 @@ where
     use substrate_frame_rpc_system::{System, SystemApiServer};
 
     let mut module = RpcModule::new(());
-    let FullDeps { client, pool, deny_unsafe, starknet: starknet_params, command_sink, graph, .. } = deps;
+    let FullDeps { client, pool, deny_unsafe, starknet: starknet_params, command_sink, sealing_clock, graph, .. } =
+        deps;
 
     module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
 
@@ -97,5 +104,9 @@ where
         )?;
     }
 
+    if let Some(sealing_clock) = sealing_clock {
+        module.merge(SealingClockRpc::new(sealing_clock, deny_unsafe).into_rpc())?;
+    }
+
     Ok(module)
 }
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::proc_macros::rpc;
+use sc_rpc_api::DenyUnsafe;
+
+use crate::sealing::SealingClock;
+
+/// Control of the timestamps of the blocks sealed by manual sealing
+#[rpc(server, namespace = "madara")]
+pub trait SealingClockApi {
+    /// Move the clock `seconds` forward, the next block being at least that much after the last
+    /// one. Returns the total number of seconds the clock has been moved forward by.
+    ///
+    /// Blocks stay at least one slot apart, see [`SealingClock`].
+    #[method(name = "increaseTime")]
+    fn increase_time(&self, seconds: u64) -> RpcResult<u64>;
+}
+
+/// Implementation of [`SealingClockApiServer`]
+pub struct SealingClockRpc {
+    clock: Arc<SealingClock>,
+    deny_unsafe: DenyUnsafe,
+}
+
+impl SealingClockRpc {
+    pub fn new(clock: Arc<SealingClock>, deny_unsafe: DenyUnsafe) -> Self {
+        Self { clock, deny_unsafe }
+    }
+}
+
+impl SealingClockApiServer for SealingClockRpc {
+    fn increase_time(&self, seconds: u64) -> RpcResult<u64> {
+        self.deny_unsafe.check_if_safe()?;
+        Ok(self.clock.warp(seconds.saturating_mul(1000)) / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increase_time_is_unsafe() {
+        let rpc = SealingClockRpc::new(Arc::new(SealingClock::new(true, 0)), DenyUnsafe::Yes);
+
+        assert!(rpc.increase_time(60).is_err());
+    }
+
+    #[test]
+    fn increase_time_returns_the_total_increase_in_seconds() {
+        let clock = Arc::new(SealingClock::new(true, 0));
+        let rpc = SealingClockRpc::new(clock.clone(), DenyUnsafe::No);
+
+        assert_eq!(rpc.increase_time(60).unwrap(), 60);
+        assert_eq!(rpc.increase_time(30).unwrap(), 90);
+        assert_eq!(clock.next_timestamp(), 90_000);
+        // Saturates instead of overflowing
+        assert!(rpc.increase_time(u64::MAX).is_ok());
+    }
+}
@@ -0,0 +1,408 @@
+//! Seal triggers and timestamps of the sealing modes built on manual sealing.
+//!
+//! The interval and hybrid modes are built on manual sealing: a task sends `SealNewBlock`
+//! commands to the manual-seal worker, next to the ones sent through the engine RPC.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use blockifier::transaction::transaction_execution::Transaction;
+use futures::channel::{mpsc, oneshot};
+use futures::prelude::*;
+use futures_timer::Delay;
+use madara_runtime::opaque::{Block, UncheckedExtrinsic};
+use madara_runtime::{BlockResourceLimits, Hash, SealingMode, SLOT_DURATION};
+use pallet_starknet::bouncer::{self, BlockResources};
+use pallet_starknet_runtime_api::StarknetRuntimeApi;
+use sc_consensus_manual_seal::rpc::EngineCommand;
+use sc_transaction_pool_api::{InPoolTransaction, PoolStatus, TransactionPool};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+
+/// When to seal the next block
+#[derive(Debug, Clone)]
+pub struct SealTriggers {
+    block_time: Duration,
+    max_pending_transactions: Option<u32>,
+    max_pending_bytes: Option<u32>,
+    /// The Starknet resources of a block, the hybrid mode sealing once the ready transactions
+    /// would use more
+    block_resource_limits: Option<BlockResources>,
+    finalize: bool,
+    skip_empty: bool,
+}
+
+impl SealTriggers {
+    /// The triggers of the interval and hybrid sealing modes, `None` for the other modes
+    pub fn from_sealing_mode(sealing: &SealingMode) -> Option<Self> {
+        match *sealing {
+            SealingMode::Interval { block_time, finalize, skip_empty } => Some(Self {
+                block_time: Duration::from_millis(block_time),
+                max_pending_transactions: None,
+                max_pending_bytes: None,
+                block_resource_limits: None,
+                finalize,
+                skip_empty,
+            }),
+            SealingMode::Hybrid { block_time, max_pending_transactions, max_pending_bytes, finalize, skip_empty } => {
+                Some(Self {
+                    block_time: Duration::from_millis(block_time),
+                    max_pending_transactions,
+                    max_pending_bytes,
+                    block_resource_limits: Some(BlockResourceLimits::get())
+                        .filter(|limits| *limits != BlockResources::MAX),
+                    finalize,
+                    skip_empty,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the ready transactions exhaust the budget of the hybrid mode, `resources` being
+    /// the Starknet resources they are estimated to use
+    fn is_budget_reached(&self, status: &PoolStatus, resources: &BlockResources) -> bool {
+        self.max_pending_transactions.is_some_and(|max| status.ready >= max as usize)
+            || self.max_pending_bytes.is_some_and(|max| status.ready_bytes >= max as usize)
+            || self.block_resource_limits.as_ref().is_some_and(|limits| !resources.fits_in(limits))
+    }
+
+    fn skips_block(&self, status: &PoolStatus) -> bool {
+        self.skip_empty && status.ready == 0
+    }
+
+    /// Send a `SealNewBlock` command to `sink` at the end of every interval, or as soon as the
+    /// ready transactions exhaust the budget of the hybrid mode
+    ///
+    /// The next interval starts once the block is sealed.
+    pub async fn run<C, P>(self, client: Arc<C>, pool: Arc<P>, mut sink: mpsc::Sender<EngineCommand<Hash>>)
+    where
+        C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+        C::Api: StarknetRuntimeApi<Block>,
+        P: TransactionPool<Block = Block>,
+    {
+        let mut imports = pool.import_notification_stream().fuse();
+        loop {
+            let mut interval = Delay::new(self.block_time).fuse();
+            let mut resources = BlockResources::default();
+            loop {
+                futures::select! {
+                    () = interval => break,
+                    imported = imports.next() => match imported {
+                        Some(hash) => {
+                            if self.block_resource_limits.is_some() {
+                                let estimate = pool
+                                    .ready_transaction(&hash)
+                                    .and_then(|transaction| estimate_resources(&*client, transaction.data().clone()));
+                                resources = resources.saturating_add(&estimate.unwrap_or_default());
+                            }
+                            if self.is_budget_reached(&pool.status(), &resources) {
+                                break;
+                            }
+                        }
+                        None => return,
+                    },
+                }
+            }
+
+            if self.skips_block(&pool.status()) {
+                log::debug!("No transaction to seal, skipping the block");
+                continue;
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let command = EngineCommand::SealNewBlock {
+                create_empty: true,
+                finalize: self.finalize,
+                parent_hash: None,
+                sender: Some(sender),
+            };
+            if sink.send(command).await.is_err() {
+                return;
+            }
+            match receiver.await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => log::warn!("Failed to seal a block: {e}"),
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+/// The Starknet resources a transaction uses when executed alone on top of the best block
+///
+/// The pending transactions are not executed before it, so this is only an estimate of what it
+/// will use in the next block. `None` if the transaction fails to execute.
+fn estimate_resources<C>(client: &C, extrinsic: UncheckedExtrinsic) -> Option<BlockResources>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: StarknetRuntimeApi<Block>,
+{
+    let best = client.info().best_hash;
+    let api = client.runtime_api();
+
+    let transaction = api.extrinsic_filter(best, vec![extrinsic]).ok()?.pop()?;
+    let l1_handler_payload_size = match &transaction {
+        Transaction::L1HandlerTransaction(transaction) => Some(transaction.payload_size()),
+        Transaction::AccountTransaction(_) => None,
+    };
+    let executed = api
+        .re_execute_transactions(best, vec![], vec![transaction], true)
+        .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| format!("{e:?}")))
+        .and_then(|result| result.map_err(|e| format!("{e:?}")));
+    let (execution_info, state_diff) = match executed {
+        Ok(mut executed) => executed.pop()?,
+        Err(e) => {
+            log::debug!("Unable to estimate the resources of a ready transaction: {e}");
+            return None;
+        }
+    };
+    let state_diff_size = state_diff.as_ref().map(bouncer::state_diff_size).unwrap_or_default();
+
+    Some(BlockResources::from_execution_info(&execution_info, state_diff_size, l1_handler_payload_size))
+}
+
+#[derive(Debug, Default)]
+struct ClockState {
+    /// Milliseconds added to the clock by time warps
+    offset: u64,
+    /// Timestamp of the last sealed block
+    last: u64,
+}
+
+/// Timestamps of the blocks sealed by manual sealing
+///
+/// Timestamps follow the system clock, or start at 0 in deterministic mode, and can be warped
+/// forward.
+///
+/// Each block is at least `SLOT_DURATION` after its parent, even with manual sealing where the
+/// runtime skips the Aura checks: `pallet_timestamp` rejects blocks less than half a slot after
+/// their parent, and keeping one block per slot leaves the chain valid for Aura if it later runs
+/// with the default sealing. Sealing faster than once per slot therefore makes the chain time run
+/// ahead of the system clock, until blocks are sealed slowly enough for the clock to catch up. In
+/// deterministic mode, timestamps simply grow by `SLOT_DURATION` each block.
+#[derive(Debug)]
+pub struct SealingClock {
+    deterministic: bool,
+    state: Mutex<ClockState>,
+}
+
+impl SealingClock {
+    /// `last` is the timestamp of the best block, in milliseconds
+    pub fn new(deterministic: bool, last: u64) -> Self {
+        Self { deterministic, state: Mutex::new(ClockState { offset: 0, last }) }
+    }
+
+    fn now(&self) -> u64 {
+        if self.deterministic {
+            return 0;
+        }
+        SystemTime::now().duration_since(UNIX_EPOCH).expect("the system clock is after the epoch").as_millis() as u64
+    }
+
+    /// The timestamp of the next block, in milliseconds
+    pub fn next_timestamp(&self) -> u64 {
+        let mut state = self.state.lock().expect("poisoned lock");
+        let timestamp = self.now().saturating_add(state.offset).max(state.last.saturating_add(SLOT_DURATION));
+        state.last = timestamp;
+        timestamp
+    }
+
+    /// Move the clock `milliseconds` forward, the next block being at least that much after the
+    /// last one. Returns the total offset of the clock, in milliseconds.
+    ///
+    /// The offset includes the time the chain ran ahead of the clock, so that the warp starts
+    /// from the last block rather than from the system clock.
+    pub fn warp(&self, milliseconds: u64) -> u64 {
+        let mut state = self.state.lock().expect("poisoned lock");
+        let now = self.now();
+        state.offset =
+            state.offset.saturating_add(milliseconds).max(state.last.saturating_add(milliseconds).saturating_sub(now));
+        state.offset
+    }
+}
+
+/// Timestamp inherent of the blocks sealed by manual sealing, see [`SealingClock`]
+pub struct SealingClockInherentDataProvider(pub Arc<SealingClock>);
+
+#[async_trait::async_trait]
+impl sp_inherents::InherentDataProvider for SealingClockInherentDataProvider {
+    async fn provide_inherent_data(
+        &self,
+        inherent_data: &mut sp_inherents::InherentData,
+    ) -> Result<(), sp_inherents::Error> {
+        inherent_data.put_data(sp_timestamp::INHERENT_IDENTIFIER, &self.0.next_timestamp())
+    }
+
+    async fn try_handle_error(
+        &self,
+        _identifier: &sp_inherents::InherentIdentifier,
+        _error: &[u8],
+    ) -> Option<Result<(), sp_inherents::Error>> {
+        // The pallet never reports error.
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(ready: usize, ready_bytes: usize) -> PoolStatus {
+        PoolStatus { ready, ready_bytes, future: 0, future_bytes: 0 }
+    }
+
+    fn hybrid(max_pending_transactions: Option<u32>, max_pending_bytes: Option<u32>) -> SealTriggers {
+        SealTriggers::from_sealing_mode(&SealingMode::Hybrid {
+            block_time: 6_000,
+            max_pending_transactions,
+            max_pending_bytes,
+            finalize: false,
+            skip_empty: false,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn only_interval_and_hybrid_modes_have_triggers() {
+        assert!(SealTriggers::from_sealing_mode(&SealingMode::Default).is_none());
+        assert!(SealTriggers::from_sealing_mode(&SealingMode::Manual).is_none());
+        assert!(SealTriggers::from_sealing_mode(&SealingMode::Instant { finalize: true }).is_none());
+
+        let triggers = SealTriggers::from_sealing_mode(&SealingMode::Interval {
+            block_time: 2_000,
+            finalize: true,
+            skip_empty: false,
+        })
+        .unwrap();
+        assert_eq!(triggers.block_time, Duration::from_millis(2_000));
+        assert!(triggers.finalize);
+    }
+
+    #[test]
+    fn interval_mode_has_no_budget() {
+        let triggers = SealTriggers::from_sealing_mode(&SealingMode::Interval {
+            block_time: 2_000,
+            finalize: false,
+            skip_empty: false,
+        })
+        .unwrap();
+
+        assert!(!triggers.is_budget_reached(&status(usize::MAX, usize::MAX), &BlockResources::MAX));
+    }
+
+    #[test]
+    fn hybrid_mode_seals_once_enough_transactions_are_ready() {
+        let triggers = hybrid(Some(3), None);
+
+        assert!(!triggers.is_budget_reached(&status(2, 1_000_000), &BlockResources::default()));
+        assert!(triggers.is_budget_reached(&status(3, 0), &BlockResources::default()));
+    }
+
+    #[test]
+    fn hybrid_mode_seals_once_enough_bytes_are_ready() {
+        let triggers = hybrid(None, Some(1_024));
+
+        assert!(!triggers.is_budget_reached(&status(100, 1_023), &BlockResources::default()));
+        assert!(triggers.is_budget_reached(&status(1, 1_024), &BlockResources::default()));
+    }
+
+    #[test]
+    fn hybrid_mode_seals_once_the_ready_transactions_fill_a_block() {
+        let triggers = hybrid(None, None);
+        let limits = BlockResourceLimits::get();
+
+        assert_eq!(triggers.block_resource_limits, Some(limits));
+        assert!(!triggers.is_budget_reached(&status(1, 0), &limits));
+        let steps = BlockResources { n_steps: limits.n_steps + 1, ..Default::default() };
+        assert!(triggers.is_budget_reached(&status(1, 0), &steps));
+        let l1_gas = BlockResources { l1_gas: limits.l1_gas + 1, ..Default::default() };
+        assert!(triggers.is_budget_reached(&status(1, 0), &l1_gas));
+        let mut builtins = BlockResources::default();
+        builtins.builtins.poseidon = limits.builtins.poseidon + 1;
+        assert!(triggers.is_budget_reached(&status(1, 0), &builtins));
+    }
+
+    fn system_time() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+    }
+
+    #[test]
+    fn deterministic_clock_grows_by_one_slot_per_block() {
+        let clock = SealingClock::new(true, 0);
+
+        assert_eq!(clock.next_timestamp(), SLOT_DURATION);
+        assert_eq!(clock.next_timestamp(), 2 * SLOT_DURATION);
+    }
+
+    #[test]
+    fn clock_starts_from_the_best_block() {
+        let clock = SealingClock::new(true, 60_000);
+        assert_eq!(clock.next_timestamp(), 60_000 + SLOT_DURATION);
+
+        let before = system_time();
+        let clock = SealingClock::new(false, 60_000);
+        let timestamp = clock.next_timestamp();
+        assert!(timestamp >= before && timestamp <= system_time());
+    }
+
+    #[test]
+    fn increasing_the_time_moves_the_next_block_forward() {
+        let clock = SealingClock::new(true, 0);
+        let last = clock.next_timestamp();
+
+        assert_eq!(clock.warp(3_600_000), last + 3_600_000);
+        assert_eq!(clock.next_timestamp(), last + 3_600_000);
+        // The offset is kept for the blocks after
+        assert_eq!(clock.next_timestamp(), last + 3_600_000 + SLOT_DURATION);
+
+        let clock = SealingClock::new(false, 0);
+        let before = system_time();
+        assert!(clock.warp(3_600_000) >= 3_600_000);
+        assert!(clock.next_timestamp() >= before + 3_600_000);
+    }
+
+    #[test]
+    fn increases_add_up() {
+        let clock = SealingClock::new(true, 0);
+        let last = clock.next_timestamp();
+
+        clock.warp(10_000);
+        clock.warp(20_000);
+
+        assert_eq!(clock.next_timestamp(), last + 30_000);
+    }
+
+    #[test]
+    fn timestamps_are_monotonic() {
+        // A best block ahead of the system clock, e.g. after a warp before a restart
+        let ahead = system_time() + 3_600_000;
+        let clock = SealingClock::new(false, ahead);
+
+        let mut last = ahead;
+        for _ in 0..10 {
+            let timestamp = clock.next_timestamp();
+            assert!(timestamp >= last + SLOT_DURATION);
+            last = timestamp;
+        }
+
+        clock.warp(0);
+        assert!(clock.next_timestamp() >= last + SLOT_DURATION);
+    }
+
+    #[test]
+    fn empty_blocks_are_only_skipped_when_asked() {
+        let skip_empty = SealTriggers::from_sealing_mode(&SealingMode::Interval {
+            block_time: 2_000,
+            finalize: false,
+            skip_empty: true,
+        })
+        .unwrap();
+        let keep_empty = hybrid(None, None);
+
+        assert!(skip_empty.skips_block(&status(0, 0)));
+        assert!(!skip_empty.skips_block(&status(1, 0)));
+        assert!(!keep_empty.skips_block(&status(0, 0)));
+    }
+}
@@ -1,6 +1,6 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
-use std::cell::RefCell;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -12,10 +12,20 @@ use futures::lock::Mutex;
 use futures::prelude::*;
 use madara_runtime::opaque::Block;
 use madara_runtime::{self, Hash, RuntimeApi, SealingMode, StarknetHasher};
+use mc_db::{DatabaseOptions, L1AcceptedBlock};
 use mc_eth_client::config::EthereumClientConfig;
-use mc_genesis_data_provider::OnDiskGenesisConfig;
+use mc_eth_client::state_update::StateUpdateWatcher;
+use mc_genesis_data_provider::{ChainSpecGenesisConfig, GenesisSource, OnDiskGenesisConfig};
 use mc_mapping_sync::MappingSyncWorker;
-use mc_storage::overrides_handle;
+use mc_p2p::client::{ClientBlockSource, StorageClasses};
+use mc_p2p::{Multiaddr, NetworkConfig as StarknetP2pConfig, NetworkService as StarknetP2pService, PeerId};
+use mc_storage::{clear_contract_classes_on_runtime_upgrade, overrides_handle};
+use mc_sync::chain::SubstrateChain;
+use mc_sync::gateway::FeederGateway;
+use mc_sync::peer::PeerUpstream;
+use mc_sync::server::FeederGatewayServer;
+use mc_sync::{GatewaySyncWorker, Upstream};
+use mp_felt::Felt252Wrapper;
 use mp_starknet_inherent::{
     InherentDataProvider as StarknetInherentDataProvider, InherentError as StarknetInherentError, L1GasPrices,
     StarknetInherentData, DEFAULT_SEQUENCER_ADDRESS, SEQ_ADDR_STORAGE_KEY,
@@ -37,6 +47,7 @@ use sp_api::offchain::OffchainStorage;
 use sp_api::{ConstructRuntimeApi, ProvideRuntimeApi};
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 use sp_offchain::STORAGE_PREFIX;
+use sp_runtime::traits::Header as HeaderT;
 
 use crate::commands::SettlementLayer;
 use crate::genesis_block::MadaraGenesisBlockBuilder;
@@ -44,12 +55,16 @@ use crate::import_queue::{
     build_aura_queue_grandpa_pipeline, build_manual_seal_queue_pipeline, BlockImportPipeline,
     GRANDPA_JUSTIFICATION_PERIOD,
 };
+use crate::metrics::ContractClassCacheMetrics;
 use crate::rpc::StarknetDeps;
-use crate::starknet::{db_config_dir, MadaraBackend};
+use crate::sealing::{SealTriggers, SealingClock, SealingClockInherentDataProvider};
+use crate::starknet::{db_config_dir, storage_mode, MadaraBackend};
 // Our native executor instance.
 pub struct ExecutorDispatch;
 
 const MADARA_TASK_GROUP: &str = "madara";
+/// How often the feeder gateway is polled for new blocks once synced.
+const GATEWAY_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
     /// Only enable the benchmarking host functions when we actually want to benchmark.
@@ -79,6 +94,8 @@ type BoxBlockImport = sc_consensus::BoxBlockImport<Block>;
 pub fn new_partial(
     config: &Configuration,
     manual_sealing: bool,
+    starknet_db_options: &DatabaseOptions,
+    migrate_starknet_db: bool,
 ) -> Result<
     sc_service::PartialComponents<
         FullClient,
@@ -147,7 +164,11 @@ where
         client.clone(),
     );
 
-    let madara_backend = Arc::new(MadaraBackend::open(&config.database, &db_config_dir(config))?);
+    let madara_backend = Arc::new(if migrate_starknet_db {
+        MadaraBackend::open(&config.database, &db_config_dir(config), storage_mode(config), starknet_db_options)?
+    } else {
+        MadaraBackend::open_without_migrating(&config.database, &db_config_dir(config))?
+    });
 
     let (import_queue, import_pipeline) = if manual_sealing {
         build_manual_seal_queue_pipeline(client.clone(), config, &task_manager, madara_backend.clone())
@@ -179,11 +200,38 @@ where
 /// # Arguments
 ///
 /// - `cache`: whether more information should be cached when storing the block in the database.
+/// - `sync_from_gateway`: the url of the upstream sequencer to import the blocks from, instead of
+///   producing them.
+/// - `sync_from_peer`: the address of the peer of the Starknet p2p network to import the blocks
+///   from, instead of producing them.
+/// - `feeder_gateway_addr`: the address the finalized blocks are served on for the full nodes
+///   syncing from this one, if any.
+/// - `starknet_p2p`: the configuration of the Starknet p2p network the blocks are served on, if
+///   any.
+#[allow(clippy::too_many_arguments)]
 pub fn new_full(
     config: Configuration,
     sealing: SealingMode,
     settlement_config: Option<(SettlementLayer, PathBuf)>,
+    starknet_db_options: DatabaseOptions,
+    deterministic_timestamps: bool,
+    sync_from_gateway: Option<String>,
+    sync_from_peer: Option<Multiaddr>,
+    feeder_gateway_addr: Option<SocketAddr>,
+    starknet_p2p: Option<StarknetP2pConfig>,
 ) -> Result<TaskManager, ServiceError> {
+    let gateway = sync_from_gateway
+        .map(|url| FeederGateway::new(&url))
+        .transpose()
+        .map_err(|e| ServiceError::Other(e.to_string()))?;
+    let sync_peer = sync_from_peer
+        .map(|address| match PeerId::try_from_multiaddr(&address) {
+            Some(peer) => Ok((peer, address)),
+            None => Err(ServiceError::Other(format!("The peer {address} does not end with /p2p/<peer id>"))),
+        })
+        .transpose()?;
+    let syncs = gateway.is_some() || sync_peer.is_some();
+
     let sc_service::PartialComponents {
         client,
         backend,
@@ -193,7 +241,7 @@ pub fn new_full(
         select_chain,
         transaction_pool,
         other: (madara_backend, BlockImportPipeline { block_import, grandpa_link }, mut telemetry),
-    } = new_partial(&config, !sealing.is_default())?;
+    } = new_partial(&config, !sealing.is_default() || syncs, &starknet_db_options, true)?;
 
     let mut net_config = sc_network::config::FullNetworkConfiguration::new(&config.network);
 
@@ -256,20 +304,39 @@ pub fn new_full(
 
     // Channel for the rpc handler to communicate with the authorship task.
     let (command_sink, commands_stream) = match sealing {
-        SealingMode::Manual => {
+        SealingMode::Manual | SealingMode::Interval { .. } | SealingMode::Hybrid { .. } => {
             let (sender, receiver) = mpsc::channel(1000);
             (Some(sender), Some(receiver))
         }
         _ => (None, None),
     };
 
+    // Timestamps of the blocks sealed by manual sealing, continuing from the best block
+    let sealing_clock = (!sealing.is_default()).then(|| {
+        let best_timestamp = client
+            .header(client.info().best_hash)
+            .ok()
+            .flatten()
+            .and_then(|header| mp_digest_log::find_starknet_block(header.digest()).ok())
+            .map(|block| block.header().block_timestamp * 1000)
+            .unwrap_or_default();
+        Arc::new(SealingClock::new(deterministic_timestamps, best_timestamp))
+    });
+
     let overrides = overrides_handle(client.clone());
+    let starknet_p2p_key_path = db_config_dir(&config).join("starknet-p2p").join("secret_ed25519");
     let config_dir: PathBuf = config.data_path.clone();
-    let genesis_data = OnDiskGenesisConfig(config_dir);
+    // Chain specs embedding the genesis data take precedence over the genesis file
+    let genesis_data = match ChainSpecGenesisConfig::from_properties(&config.chain_spec.properties())
+        .map_err(|e| ServiceError::Other(e.to_string()))?
+    {
+        Some(genesis) => GenesisSource::ChainSpec(genesis),
+        None => GenesisSource::OnDisk(OnDiskGenesisConfig(config_dir)),
+    };
     let starknet_rpc_params = StarknetDeps {
         client: client.clone(),
         madara_backend: madara_backend.clone(),
-        overrides,
+        overrides: overrides.clone(),
         sync_service: sync_service.clone(),
         starting_block,
         genesis_provider: genesis_data.into(),
@@ -288,6 +355,7 @@ pub fn new_full(
                 deny_unsafe,
                 starknet: starknet_rpc_params.clone(),
                 command_sink: command_sink.clone(),
+                sealing_clock: sealing_clock.clone(),
             };
             crate::rpc::create_full(deps).map_err(Into::into)
         })
@@ -324,6 +392,103 @@ pub fn new_full(
         .for_each(|()| future::ready(())),
     );
 
+    task_manager.spawn_handle().spawn(
+        "contract-class-cache-invalidation",
+        Some(MADARA_TASK_GROUP),
+        clear_contract_classes_on_runtime_upgrade(client.clone(), overrides.contract_classes.clone()),
+    );
+
+    if let Some(registry) = prometheus_registry.as_ref() {
+        match ContractClassCacheMetrics::register(registry) {
+            Ok(metrics) => task_manager.spawn_handle().spawn(
+                "contract-class-cache-metrics",
+                Some(MADARA_TASK_GROUP),
+                metrics.run(overrides.contract_classes.clone()),
+            ),
+            Err(e) => log::warn!("Failed to register the contract class cache metrics: {e}"),
+        }
+    }
+
+    // Track the blocks accepted on L1, whatever the role of the node
+    if let Some((SettlementLayer::Ethereum, config_path)) = &settlement_config {
+        let ethereum_conf =
+            EthereumClientConfig::from_json_file(config_path).map_err(|e| ServiceError::Other(e.to_string()))?;
+        let watcher =
+            StateUpdateWatcher::from_config(&ethereum_conf).map_err(|e| ServiceError::Other(e.to_string()))?;
+        let madara_backend = madara_backend.clone();
+
+        task_manager.spawn_handle().spawn(
+            "settlement-worker-state-updates",
+            Some(MADARA_TASK_GROUP),
+            watcher.run(move |update| {
+                let mut block_hash = [0u8; 32];
+                update.block_hash.to_big_endian(&mut block_hash);
+                let Ok(block_hash) = Felt252Wrapper::try_from(&block_hash) else {
+                    log::error!("⟠ Invalid hash of the Starknet block {} accepted on L1", update.block_number);
+                    return;
+                };
+                let block = L1AcceptedBlock { block_number: update.block_number, block_hash: block_hash.into() };
+                if let Err(e) = madara_backend.meta().write_last_l1_accepted_block(&block) {
+                    log::error!("⟠ Failed to save the last block accepted on L1: {e}");
+                }
+            }),
+        );
+    }
+
+    // Serve the blocks to the other Starknet clients, whatever the role of the node
+    let starknet_p2p_network = match starknet_p2p {
+        Some(p2p_config) => {
+            let keypair = mc_p2p::load_or_generate_keypair(&starknet_p2p_key_path)
+                .map_err(|e| ServiceError::Other(e.to_string()))?;
+            let source = Arc::new(ClientBlockSource::new(
+                client.clone(),
+                madara_backend.clone(),
+                StorageClasses::<_, _, FullBackend>::new(client.clone(), overrides.clone()),
+            ));
+            let (p2p_service, p2p_network) =
+                StarknetP2pService::new(p2p_config, keypair, source).map_err(|e| ServiceError::Other(e.to_string()))?;
+            task_manager.spawn_essential_handle().spawn("starknet-p2p", Some(MADARA_TASK_GROUP), p2p_service.run());
+            Some(p2p_network)
+        }
+        None => None,
+    };
+
+    // Serve the finalized blocks to the full nodes syncing from this one, whatever the role of the
+    // node
+    if let Some(addr) = feeder_gateway_addr {
+        let (addr, server) = FeederGatewayServer::new(client.clone(), madara_backend.clone())
+            .bind(&addr)
+            .map_err(|e| ServiceError::Other(format!("Failed to serve the feeder gateway on {addr}: {e}")))?;
+        log::info!("🌐 Serving the feeder gateway on {addr}");
+        task_manager.spawn_essential_handle().spawn("feeder-gateway", Some(MADARA_TASK_GROUP), server);
+    }
+
+    // Follow the upstream sequencer instead of producing blocks. Its blocks are built locally, so
+    // they are imported through the manual-seal pipeline
+    let upstream: Option<Box<dyn Upstream>> = match (gateway, sync_peer) {
+        (Some(gateway), _) => Some(Box::new(gateway)),
+        (None, Some((peer, address))) => {
+            let network = starknet_p2p_network
+                .ok_or_else(|| ServiceError::Other("Syncing from a peer requires the Starknet p2p network".into()))?;
+            network.add_peer(peer, address).map_err(|e| ServiceError::Other(e.to_string()))?;
+            Some(Box::new(PeerUpstream::new(network, peer)))
+        }
+        (None, None) => None,
+    };
+    if let Some(upstream) = upstream {
+        log::info!("🔄 Syncing from the upstream sequencer, block production disabled");
+        let worker = GatewaySyncWorker::new(
+            upstream,
+            SubstrateChain::<_, _, FullBackend>::new(client.clone(), block_import),
+            madara_backend.clone(),
+            GATEWAY_POLL_INTERVAL,
+        );
+        task_manager.spawn_essential_handle().spawn("gateway-sync", Some(MADARA_TASK_GROUP), worker.run());
+
+        network_starter.start_network();
+        return Ok(task_manager);
+    }
+
     if role.is_authority() {
         let l1_gas_price = Arc::new(Mutex::new(L1GasPrices::default()));
 
@@ -383,6 +548,7 @@ pub fn new_full(
                 prometheus_registry.as_ref(),
                 commands_stream,
                 telemetry,
+                sealing_clock.expect("manual-seal modes have a clock"),
             )?;
 
             network_starter.start_network();
@@ -406,9 +572,10 @@ pub fn new_full(
             select_chain,
             block_import,
             proposer_factory,
-            create_inherent_data_providers: move |_, ()| {
+            create_inherent_data_providers: move |parent_hash, ()| {
                 let offchain_storage = backend.offchain_storage();
                 let l1_gas_price = l1_gas_price.clone();
+                let client = client.clone();
                 async move {
                     let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
 
@@ -417,11 +584,23 @@ pub fn new_full(
                         slot_duration,
                     );
 
+                    // The address registered on-chain by the slot author takes precedence over the
+                    // one configured in the offchain storage of this node.
+                    let registered_sequencer_address = client
+                        .runtime_api()
+                        .sequencer_address_for_slot(parent_hash, u64::from(*slot))
+                        .unwrap_or_else(|e| {
+                            log::warn!("Failed to read the registered sequencer address: {e}");
+                            None
+                        });
+
                     let ocw_storage = offchain_storage.clone();
                     let prefix = &STORAGE_PREFIX;
                     let key = SEQ_ADDR_STORAGE_KEY;
 
-                    let sequencer_address: [u8; 32] = if let Some(storage) = ocw_storage {
+                    let sequencer_address: [u8; 32] = if let Some(address) = registered_sequencer_address {
+                        address.0.0.bytes().try_into().map_err(|_| StarknetInherentError::WrongAddressFormat)?
+                    } else if let Some(storage) = ocw_storage {
                         storage
                             .get(prefix, key)
                             .unwrap_or(DEFAULT_SEQUENCER_ADDRESS.to_vec())
@@ -514,6 +693,7 @@ fn run_manual_seal_authorship(
     prometheus_registry: Option<&Registry>,
     commands_stream: Option<mpsc::Receiver<sc_consensus_manual_seal::rpc::EngineCommand<Hash>>>,
     telemetry: Option<Telemetry>,
+    sealing_clock: Arc<SealingClock>,
 ) -> Result<(), ServiceError>
 where
     RuntimeApi: ConstructRuntimeApi<Block, FullClient>,
@@ -527,37 +707,9 @@ where
         telemetry.as_ref().map(|x| x.handle()),
     );
 
-    thread_local!(static TIMESTAMP: RefCell<u64> = RefCell::new(0));
-
-    /// Provide a mock duration starting at 0 in millisecond for timestamp inherent.
-    /// Each call will increment timestamp by slot_duration making Aura think time has passed.
-    struct MockTimestampInherentDataProvider;
-
-    #[async_trait::async_trait]
-    impl sp_inherents::InherentDataProvider for MockTimestampInherentDataProvider {
-        async fn provide_inherent_data(
-            &self,
-            inherent_data: &mut sp_inherents::InherentData,
-        ) -> Result<(), sp_inherents::Error> {
-            TIMESTAMP.with(|x| {
-                *x.borrow_mut() += madara_runtime::SLOT_DURATION;
-                inherent_data.put_data(sp_timestamp::INHERENT_IDENTIFIER, &*x.borrow())
-            })
-        }
-
-        async fn try_handle_error(
-            &self,
-            _identifier: &sp_inherents::InherentIdentifier,
-            _error: &[u8],
-        ) -> Option<Result<(), sp_inherents::Error>> {
-            // The pallet never reports error.
-            None
-        }
-    }
-
-    let create_inherent_data_providers = move |_, ()| async move {
-        let timestamp = MockTimestampInherentDataProvider;
-        Ok(timestamp)
+    let create_inherent_data_providers = move |_, ()| {
+        let timestamp = SealingClockInherentDataProvider(sealing_clock.clone());
+        async move { Ok(timestamp) }
     };
 
     let manual_seal: BoxFuture<_> = match sealing {
@@ -573,6 +725,29 @@ where
                 create_inherent_data_providers,
             }))
         }
+        SealingMode::Interval { .. } | SealingMode::Hybrid { .. } => {
+            let triggers = SealTriggers::from_sealing_mode(&sealing).expect("interval and hybrid modes have triggers");
+            let (trigger_sink, trigger_stream) = mpsc::channel(1);
+            task_manager.spawn_essential_handle().spawn(
+                "seal-triggers",
+                None,
+                triggers.run(client.clone(), transaction_pool.clone(), trigger_sink),
+            );
+
+            // Blocks can still be sealed through the RPC in between
+            let commands_stream =
+                stream::select(commands_stream.expect("Interval sealing requires a channel from RPC."), trigger_stream);
+            Box::pin(sc_consensus_manual_seal::run_manual_seal(sc_consensus_manual_seal::ManualSealParams {
+                block_import,
+                env: proposer_factory,
+                client,
+                pool: transaction_pool,
+                commands_stream,
+                select_chain,
+                consensus_data_provider: None,
+                create_inherent_data_providers,
+            }))
+        }
         SealingMode::Instant { finalize } => {
             let instant_seal_params = sc_consensus_manual_seal::InstantSealParams {
                 block_import,
@@ -601,8 +776,19 @@ type ChainOpsResult =
     Result<(Arc<FullClient>, Arc<FullBackend>, BasicQueue<Block>, TaskManager, Arc<MadaraBackend>), ServiceError>;
 
 pub fn new_chain_ops(config: &mut Configuration) -> ChainOpsResult {
+    chain_ops(config, true)
+}
+
+/// Same as [`new_chain_ops`], for the commands that must not modify the Starknet database
+///
+/// Fails if the Starknet database has pending schema migrations.
+pub fn new_chain_ops_without_migrating(config: &mut Configuration) -> ChainOpsResult {
+    chain_ops(config, false)
+}
+
+fn chain_ops(config: &mut Configuration, migrate_starknet_db: bool) -> ChainOpsResult {
     config.keystore = sc_service::config::KeystoreConfig::InMemory;
     let sc_service::PartialComponents { client, backend, import_queue, task_manager, other, .. } =
-        new_partial(config, false)?;
+        new_partial(config, false, &DatabaseOptions::default(), migrate_starknet_db)?;
     Ok((client, backend, import_queue, task_manager, other.0))
 }
@@ -1,7 +1,8 @@
 use std::path::PathBuf;
 
 use madara_runtime::opaque::Block;
-use sc_service::Configuration;
+use mc_db::StorageMode;
+use sc_service::{Configuration, PruningMode};
 
 pub type MadaraBackend = mc_db::Backend<Block>;
 
@@ -9,3 +10,15 @@ pub type MadaraBackend = mc_db::Backend<Block>;
 pub fn db_config_dir(config: &Configuration) -> PathBuf {
     config.base_path.config_dir(config.chain_spec.id())
 }
+
+/// Returns the storage mode matching the state pruning the node was configured with.
+///
+/// `None` means that the mode recorded in the database should be used.
+pub fn storage_mode(config: &Configuration) -> Option<StorageMode> {
+    config.state_pruning.as_ref().map(|state_pruning| match state_pruning {
+        PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => StorageMode::Archive,
+        PruningMode::Constrained(constraints) => {
+            StorageMode::Pruned { blocks: constraints.max_blocks.unwrap_or(mc_db::DEFAULT_PRUNING_BLOCKS) }
+        }
+    })
+}
@@ -12,8 +12,11 @@ use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::objects::TransactionExecutionInfo;
 use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transactions::L1HandlerTransaction;
+use mp_block::commitments::BlockStateDiff;
 use mp_felt::Felt252Wrapper;
 pub extern crate alloc;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use mp_simulations::{
@@ -21,11 +24,12 @@ use mp_simulations::{
     TransactionSimulationResult,
 };
 use mp_starknet_inherent::L1GasPrices;
+use mp_transactions::execution::PaymasterConfig;
 use sp_api::BlockT;
-use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, EntryPointSelector, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
-use starknet_api::transaction::{Calldata, Event as StarknetEvent, MessageToL1, TransactionHash};
+use starknet_api::transaction::{Calldata, Event as StarknetEvent, Fee, MessageToL1, TransactionHash};
 
 sp_api::decl_runtime_apis! {
     pub trait StarknetRuntimeApi {
@@ -39,6 +43,8 @@ sp_api::decl_runtime_apis! {
         fn contract_class_hash_by_address(address: ContractAddress) -> ClassHash;
         /// Returns the contract class for the given class hash.
         fn contract_class_by_class_hash(class_hash: ClassHash) -> Option<ContractClass>;
+        /// Returns the compiled class hash of the given Sierra class hash.
+        fn compiled_class_hash_by_class_hash(class_hash: ClassHash) -> Option<CompiledClassHash>;
         /// Returns the chain id.
         fn chain_id() -> Felt252Wrapper;
         /// Returns the Starknet OS Cairo program hash.
@@ -86,12 +92,24 @@ sp_api::decl_runtime_apis! {
         fn get_block_context() -> BlockContext;
         /// Return is fee disabled in state
         fn is_transaction_fee_disabled() -> bool;
+        /// Return is nonce validation disabled in state
+        fn is_nonce_validation_disabled() -> bool;
+        /// Returns the configuration of the paymasters, if transactions can be sponsored
+        fn paymaster_config() -> Option<PaymasterConfig>;
         /// Return messages sent to L1 during tx execution
         fn get_tx_messages_to_l1(tx_hash: TransactionHash) -> Vec<MessageToL1>;
+        /// Returns the paymaster charged the fee of the tx instead of its sender, if it was sponsored
+        fn get_tx_paymaster(tx_hash: TransactionHash) -> Option<ContractAddress>;
+        /// Returns the fee charged and the execution resources used by the tx, if they were recorded when its block was imported
+        fn get_tx_execution_resources(tx_hash: TransactionHash) -> Option<(Fee, BTreeMap<String, u64>)>;
         /// Check if L1 Message Nonce has not been used
         fn l1_nonce_unused(nonce: Nonce) -> bool;
         /// Get current L1 gas prices
         fn current_l1_gas_prices() -> L1GasPrices;
+        /// Returns the sequencer address registered by the authority expected to author `slot`
+        fn sequencer_address_for_slot(slot: u64) -> Option<ContractAddress>;
+        /// Returns the state diff of the block, as committed to by its header
+        fn last_state_diff() -> BlockStateDiff;
     }
 
     pub trait ConvertTransactionRuntimeApi {
@@ -1,10 +1,13 @@
 use core::marker::PhantomData;
 use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
 
 use blockifier::execution::contract_class::ContractClass;
 use blockifier::state::cached_state::{CachedState, GlobalContractCache};
 use blockifier::state::errors::StateError;
 use blockifier::state::state_api::{State, StateReader, StateResult};
+use lru::LruCache;
 use mp_felt::Felt252Wrapper;
 use mp_transactions::execution::SetArbitraryNonce;
 use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
@@ -15,19 +18,71 @@ use starknet_crypto::FieldElement;
 use crate::types::ContractStorageKey;
 use crate::{Config, Pallet};
 
+/// Number of classes kept by [`DECODED_CONTRACT_CLASSES`].
+const DECODED_CONTRACT_CLASSES_CAPACITY: usize = 128;
+
+/// The classes last decoded from `ContractClasses`, most transactions using the same few account
+/// and token classes. Natively they are shared by every block executed by the process; under Wasm,
+/// by the transactions of a runtime call, e.g. of a block.
+///
+/// The state adapters are created for each transaction, and runtime storage only holds encoded
+/// values, so a process wide static is the only place the decoded classes can outlive them. It
+/// never changes the outcome of an execution: an entry is the decoding of the class stored under
+/// its key, which a class hash and a compiled class hash identify. The cache is cleared on runtime
+/// upgrade, the only time the stored classes may be migrated.
+///
+/// A Sierra class hash can be compiled to different CASM classes by different forks, so the classes
+/// are cached by class hash and compiled class hash. Cairo 0 classes have no compiled class hash.
+static DECODED_CONTRACT_CLASSES: Mutex<Option<DecodedContractClasses>> = Mutex::new(None);
+
+type DecodedContractClasses = LruCache<(ClassHash, Option<CompiledClassHash>), ContractClass>;
+
+fn with_decoded_contract_classes<R>(f: impl FnOnce(&mut DecodedContractClasses) -> R) -> R {
+    let mut decoded = DECODED_CONTRACT_CLASSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(decoded.get_or_insert_with(|| {
+        LruCache::new(NonZeroUsize::new(DECODED_CONTRACT_CLASSES_CAPACITY).expect("the capacity is not zero"))
+    }))
+}
+
+/// Remove the classes decoded by the state adapters, as a runtime upgrade may migrate the stored
+/// classes.
+pub(crate) fn clear_decoded_contract_classes() {
+    let mut decoded = DECODED_CONTRACT_CLASSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *decoded = None;
+}
+
 /// Empty struct that implements the traits needed by the blockifier/starknet in rust.
 ///
 /// We feed this struct when executing a transaction so that we directly use the substrate storage
 /// and not an extra layer that would add overhead.
+/// The state updates are also recorded in the pending state diff of the block, which is committed
+/// to in the block header.
 /// We don't implement those traits directly on the pallet to avoid compilation problems.
 pub struct BlockifierStateAdapter<T: Config> {
     visited_pcs: HashMap<ClassHash, HashSet<usize>>,
+    updated_entries: HashSet<StateEntry>,
     _phantom: PhantomData<T>,
 }
 
+/// An entry of the state diff.
+#[derive(PartialEq, Eq, Hash)]
+enum StateEntry {
+    Storage(ContractAddress, StorageKey),
+    Nonce(ContractAddress),
+    ClassHash(ContractAddress),
+    DeclaredClass(ClassHash),
+}
+
 impl<T: Config> Default for BlockifierStateAdapter<T> {
     fn default() -> Self {
-        Self { visited_pcs: Default::default(), _phantom: PhantomData }
+        Self { visited_pcs: Default::default(), updated_entries: Default::default(), _phantom: PhantomData }
+    }
+}
+
+impl<T: Config> BlockifierStateAdapter<T> {
+    /// Number of state entries updated through this adapter.
+    pub fn state_diff_size(&self) -> u64 {
+        self.updated_entries.len() as u64
     }
 }
 
@@ -46,7 +101,21 @@ impl<T: Config> StateReader for BlockifierStateAdapter<T> {
     }
 
     fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
-        Pallet::<T>::contract_class_by_class_hash(class_hash.0).ok_or(StateError::UndeclaredClassHash(class_hash))
+        // The cache is shared with other blocks, which may have declared classes this one doesn't
+        if !crate::ContractClasses::<T>::contains_key(class_hash.0) {
+            return Err(StateError::UndeclaredClassHash(class_hash));
+        }
+        let key = (class_hash, Pallet::<T>::compiled_class_hash_by_class_hash(class_hash.0));
+
+        if let Some(class) = with_decoded_contract_classes(|classes| classes.get(&key).cloned()) {
+            return Ok(class);
+        }
+
+        // Decoded without holding the lock, so concurrent executions aren't blocked by the decoding
+        let class = Pallet::<T>::contract_class_by_class_hash(class_hash.0)
+            .ok_or(StateError::UndeclaredClassHash(class_hash))?;
+        with_decoded_contract_classes(|classes| classes.put(key, class.clone()));
+        Ok(class)
     }
 
     fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
@@ -64,6 +133,8 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
         let contract_storage_key: ContractStorageKey = (contract_address, key);
 
         crate::StorageView::<T>::insert(contract_storage_key, value);
+        crate::PendingStorageChanges::<T>::append(contract_address, (key, Felt252Wrapper::from(value)));
+        self.updated_entries.insert(StateEntry::Storage(contract_address, key));
 
         Ok(())
     }
@@ -74,17 +145,25 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
         let new_nonce: Nonce = Felt252Wrapper(current_nonce + FieldElement::ONE).into();
 
         crate::Nonces::<T>::insert(contract_address, new_nonce);
+        crate::PendingNonces::<T>::insert(contract_address, new_nonce);
+        self.updated_entries.insert(StateEntry::Nonce(contract_address));
 
         Ok(())
     }
 
     fn set_class_hash_at(&mut self, contract_address: ContractAddress, class_hash: ClassHash) -> StateResult<()> {
         crate::ContractClassHashes::<T>::insert(contract_address, class_hash.0);
+        crate::PendingContractClassHashes::<T>::insert(contract_address, class_hash);
+        self.updated_entries.insert(StateEntry::ClassHash(contract_address));
 
         Ok(())
     }
 
     fn set_contract_class(&mut self, class_hash: ClassHash, contract_class: ContractClass) -> StateResult<()> {
+        if matches!(contract_class, ContractClass::V0(_)) {
+            crate::PendingDeprecatedDeclaredClasses::<T>::mutate(|classes| classes.insert(class_hash));
+            self.updated_entries.insert(StateEntry::DeclaredClass(class_hash));
+        }
         crate::ContractClasses::<T>::insert(class_hash.0, contract_class);
 
         Ok(())
@@ -96,6 +175,8 @@ impl<T: Config> State for BlockifierStateAdapter<T> {
         compiled_class_hash: CompiledClassHash,
     ) -> StateResult<()> {
         crate::CompiledClassHashes::<T>::insert(class_hash.0, compiled_class_hash);
+        crate::PendingDeclaredClasses::<T>::insert(class_hash, compiled_class_hash);
+        self.updated_entries.insert(StateEntry::DeclaredClass(class_hash));
 
         Ok(())
     }
@@ -179,6 +260,8 @@ where
 impl<T: Config> SetArbitraryNonce for BlockifierStateAdapter<T> {
     fn set_nonce_at(&mut self, contract_address: ContractAddress, nonce: Nonce) -> StateResult<()> {
         crate::Nonces::<T>::insert(contract_address, nonce);
+        crate::PendingNonces::<T>::insert(contract_address, nonce);
+        self.updated_entries.insert(StateEntry::Nonce(contract_address));
 
         Ok(())
     }
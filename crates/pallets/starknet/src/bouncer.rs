@@ -0,0 +1,244 @@
+//! Starknet resources consumed by the block being built.
+//!
+//! Substrate only bounds blocks by weight and length, which tell nothing about the cost of proving
+//! a block with SNOS or about the size of its data availability. The pallet sums the resources
+//! used by each transaction of the block and defers the transactions that would exceed the
+//! runtime `BlockResourceLimits` to the next block, see `Pallet::check_block_resources`. The
+//! resources are those of the execution kept in the block: transactions are executed once, before
+//! their dispatch.
+
+use blockifier::abi::constants::{L1_GAS_USAGE, N_STEPS_RESOURCE};
+use blockifier::execution::call_info::CallInfo;
+use blockifier::state::cached_state::CommitmentStateDiff;
+use blockifier::transaction::objects::TransactionExecutionInfo;
+
+/// Number of felts of the header of an L2 to L1 message in the OS output: the sender, the
+/// recipient and the payload size.
+pub const L2_TO_L1_MSG_HEADER_SIZE: u64 = 3;
+/// Number of felts of the header of an L1 to L2 message in the OS output: the sender, the
+/// recipient, the nonce, the selector and the payload size.
+pub const L1_TO_L2_MSG_HEADER_SIZE: u64 = 5;
+
+/// Instances of the builtins used by the Cairo execution.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    parity_scale_codec::Encode,
+    parity_scale_codec::Decode,
+    scale_info::TypeInfo,
+)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuiltinCount {
+    pub pedersen: u64,
+    pub range_check: u64,
+    pub ecdsa: u64,
+    pub bitwise: u64,
+    pub ec_op: u64,
+    pub keccak: u64,
+    pub poseidon: u64,
+    pub segment_arena: u64,
+}
+
+impl BuiltinCount {
+    pub const MAX: Self = Self {
+        pedersen: u64::MAX,
+        range_check: u64::MAX,
+        ecdsa: u64::MAX,
+        bitwise: u64::MAX,
+        ec_op: u64::MAX,
+        keccak: u64::MAX,
+        poseidon: u64::MAX,
+        segment_arena: u64::MAX,
+    };
+
+    /// Read the builtin counters of blockifier resources, indexed by builtin runner name.
+    fn from_resources<'a>(resources: impl Iterator<Item = (&'a String, &'a u128)>) -> Self {
+        let mut count = Self::default();
+        for (name, &instances) in resources {
+            let counter = match name.as_str() {
+                "pedersen_builtin" => &mut count.pedersen,
+                "range_check_builtin" => &mut count.range_check,
+                "ecdsa_builtin" => &mut count.ecdsa,
+                "bitwise_builtin" => &mut count.bitwise,
+                "ec_op_builtin" => &mut count.ec_op,
+                "keccak_builtin" => &mut count.keccak,
+                "poseidon_builtin" => &mut count.poseidon,
+                "segment_arena_builtin" => &mut count.segment_arena,
+                _ => continue,
+            };
+            *counter = saturating_u64(instances);
+        }
+        count
+    }
+
+    fn as_array(&self) -> [u64; 8] {
+        [
+            self.pedersen,
+            self.range_check,
+            self.ecdsa,
+            self.bitwise,
+            self.ec_op,
+            self.keccak,
+            self.poseidon,
+            self.segment_arena,
+        ]
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        Self {
+            pedersen: f(self.pedersen, other.pedersen),
+            range_check: f(self.range_check, other.range_check),
+            ecdsa: f(self.ecdsa, other.ecdsa),
+            bitwise: f(self.bitwise, other.bitwise),
+            ec_op: f(self.ec_op, other.ec_op),
+            keccak: f(self.keccak, other.keccak),
+            poseidon: f(self.poseidon, other.poseidon),
+            segment_arena: f(self.segment_arena, other.segment_arena),
+        }
+    }
+}
+
+/// Starknet resources used by transactions, or the maximum a block can use.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    parity_scale_codec::Encode,
+    parity_scale_codec::Decode,
+    scale_info::TypeInfo,
+)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockResources {
+    /// Cairo steps, memory holes included.
+    pub n_steps: u64,
+    pub builtins: BuiltinCount,
+    /// L1 gas, data availability gas excluded.
+    pub l1_gas: u64,
+    /// Felts of the L1 <> L2 messages in the OS output.
+    pub message_segment_length: u64,
+    /// Number of state updates: storage slots, nonces, deployed contracts and declared classes.
+    ///
+    /// The updates are counted per transaction, so an entry updated by several transactions is
+    /// counted several times.
+    pub state_diff_size: u64,
+}
+
+impl BlockResources {
+    /// No limit.
+    pub const MAX: Self = Self {
+        n_steps: u64::MAX,
+        builtins: BuiltinCount::MAX,
+        l1_gas: u64::MAX,
+        message_segment_length: u64::MAX,
+        state_diff_size: u64::MAX,
+    };
+
+    /// The resources used by an executed transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `execution_info` - The execution info of the transaction.
+    /// * `state_diff_size` - The number of state entries updated by the transaction.
+    /// * `l1_handler_payload_size` - The payload length of the L1 message consumed by an L1 handler
+    ///   transaction.
+    pub fn from_execution_info(
+        execution_info: &TransactionExecutionInfo,
+        state_diff_size: u64,
+        l1_handler_payload_size: Option<usize>,
+    ) -> Self {
+        let resources = &execution_info.bouncer_resources.0;
+        let resource = |name: &str| resources.get(name).copied().map(saturating_u64).unwrap_or_default();
+
+        let l2_to_l1_messages_length: u64 = [
+            &execution_info.validate_call_info,
+            &execution_info.execute_call_info,
+            &execution_info.fee_transfer_call_info,
+        ]
+        .into_iter()
+        .flatten()
+        .map(l2_to_l1_messages_length)
+        .sum();
+        let l1_to_l2_message_length =
+            l1_handler_payload_size.map_or(0, |payload_size| L1_TO_L2_MSG_HEADER_SIZE + payload_size as u64);
+
+        Self {
+            n_steps: resource(N_STEPS_RESOURCE),
+            builtins: BuiltinCount::from_resources(resources.iter()),
+            l1_gas: resource(L1_GAS_USAGE),
+            message_segment_length: l2_to_l1_messages_length + l1_to_l2_message_length,
+            state_diff_size,
+        }
+    }
+
+    pub fn saturating_add(&self, other: &Self) -> Self {
+        self.zip_with(other, u64::saturating_add)
+    }
+
+    pub fn saturating_sub(&self, other: &Self) -> Self {
+        self.zip_with(other, u64::saturating_sub)
+    }
+
+    /// Returns `true` if none of the resources exceeds its limit.
+    pub fn fits_in(&self, limits: &Self) -> bool {
+        self.as_array().into_iter().zip(limits.as_array()).all(|(used, limit)| used <= limit)
+    }
+
+    fn as_array(&self) -> [u64; 12] {
+        let [pedersen, range_check, ecdsa, bitwise, ec_op, keccak, poseidon, segment_arena] = self.builtins.as_array();
+        [
+            self.n_steps,
+            pedersen,
+            range_check,
+            ecdsa,
+            bitwise,
+            ec_op,
+            keccak,
+            poseidon,
+            segment_arena,
+            self.l1_gas,
+            self.message_segment_length,
+            self.state_diff_size,
+        ]
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(u64, u64) -> u64 + Copy) -> Self {
+        Self {
+            n_steps: f(self.n_steps, other.n_steps),
+            builtins: self.builtins.zip_with(&other.builtins, f),
+            l1_gas: f(self.l1_gas, other.l1_gas),
+            message_segment_length: f(self.message_segment_length, other.message_segment_length),
+            state_diff_size: f(self.state_diff_size, other.state_diff_size),
+        }
+    }
+}
+
+/// Number of state updates of a state diff, counted like [`BlockResources::state_diff_size`].
+pub fn state_diff_size(state_diff: &CommitmentStateDiff) -> u64 {
+    let storage_updates: usize = state_diff.storage_updates.values().map(|updates| updates.len()).sum();
+    (storage_updates
+        + state_diff.address_to_class_hash.len()
+        + state_diff.address_to_nonce.len()
+        + state_diff.class_hash_to_compiled_class_hash.len()) as u64
+}
+
+/// Length in the OS output of the L2 to L1 messages sent by a call and its inner calls.
+fn l2_to_l1_messages_length(call_info: &CallInfo) -> u64 {
+    let own_messages: u64 = call_info
+        .execution
+        .l2_to_l1_messages
+        .iter()
+        .map(|ordered_message| L2_TO_L1_MSG_HEADER_SIZE + ordered_message.message.payload.0.len() as u64)
+        .sum();
+    own_messages + call_info.inner_calls.iter().map(l2_to_l1_messages_length).sum::<u64>()
+}
+
+fn saturating_u64(value: u128) -> u64 {
+    value.try_into().unwrap_or(u64::MAX)
+}
@@ -0,0 +1,25 @@
+//! Accounts administering the chain parameters stored in the pallet.
+//!
+//! [`ControlAccountsOf`] exposes the accounts set in the genesis or through
+//! `set_control_accounts` as [`SortedMembers`], so that a runtime can accept their signed
+//! extrinsics as its `ControlOrigin` with `frame_system::EnsureSignedBy`.
+use frame_support::traits::SortedMembers;
+use sp_std::marker::PhantomData;
+use sp_std::vec::Vec;
+
+use crate::{Config, Pallet};
+
+/// The control accounts of the pallet, sorted.
+pub struct ControlAccountsOf<T>(PhantomData<T>);
+
+impl<T: Config> SortedMembers<T::AccountId> for ControlAccountsOf<T> {
+    fn sorted_members() -> Vec<T::AccountId> {
+        let mut accounts = Pallet::<T>::control_accounts();
+        accounts.sort();
+        accounts
+    }
+
+    fn contains(account: &T::AccountId) -> bool {
+        Pallet::<T>::control_accounts().contains(account)
+    }
+}
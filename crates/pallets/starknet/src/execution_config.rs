@@ -1,8 +1,7 @@
 use mp_simulations::SimulationFlags;
 use mp_transactions::execution::ExecutionConfig;
-use sp_core::Get;
 
-use crate::Config;
+use crate::{Config, Pallet};
 
 /// Builder pattern for [`ExecutionConfig`]. Combines the
 /// execution configuration from the runtime with the possible
@@ -15,8 +14,8 @@ impl RuntimeExecutionConfigBuilder {
             is_query: false,
             disable_fee_charge: false,
             disable_validation: false,
-            disable_nonce_validation: T::DisableNonceValidation::get(),
-            disable_transaction_fee: T::DisableTransactionFee::get(),
+            disable_nonce_validation: Pallet::<T>::is_nonce_validation_disabled(),
+            disable_transaction_fee: Pallet::<T>::is_transaction_fee_disabled(),
             offset_version: false,
         })
     }
@@ -17,7 +17,7 @@ impl<T: crate::Config> From<GenesisLoader> for GenesisConfig<T> {
             .map(|(hash, class)| {
                 let hash = Felt252Wrapper(hash.0).into();
                 match class {
-                    ContractClass::Path { path, version } => (
+                    ContractClass::Path { path, version, .. } => (
                         hash,
                         read_contract_class_from_json(
                             &std::fs::read_to_string(loader.base_path().join(path)).expect(
@@ -129,7 +129,8 @@ mod tests {
     #[test]
     fn test_serialize_loader() {
         // Given
-        let class: ContractClass = ContractClass::Path { path: "cairo-contracts/ERC20.json".into(), version: 0 };
+        let class: ContractClass =
+            ContractClass::Path { path: "cairo-contracts/ERC20.json".into(), version: 0, sierra_path: None };
 
         let class_hash = FieldElement::from(1u8).into();
         let contract_address = FieldElement::from(2u8).into();
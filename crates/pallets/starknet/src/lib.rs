@@ -32,7 +32,7 @@
 // Ensure we're `no_std` when compiling for Wasm.
 #![allow(clippy::large_enum_variant)]
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Starknet pallet.
 /// Definition of the pallet's runtime storage items, events, errors, and dispatchable
@@ -42,10 +42,18 @@ use std::sync::Arc;
 pub use pallet::*;
 /// An adapter for the blockifier state related traits
 pub mod blockifier_state_adapter;
+/// Starknet resources of the block being built.
+pub mod bouncer;
+/// Accounts administering the chain parameters stored in the pallet.
+pub mod control_accounts;
 #[cfg(feature = "genesis-loader")]
 pub mod genesis_loader;
 /// Simulation, estimations and execution trace logic.
 pub mod simulations;
+/// Tries committing to the Starknet state.
+pub mod state_trie;
+/// Transaction filters configurable through the pallet storage.
+pub mod transaction_filters;
 /// Transaction validation logic.
 pub mod transaction_validation;
 /// The Starknet pallet's runtime custom types.
@@ -54,7 +62,7 @@ pub mod types;
 #[cfg(test)]
 mod tests;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Deref;
 use std::str::from_utf8_unchecked;
 
@@ -65,26 +73,35 @@ use blockifier::execution::contract_class::ContractClass;
 use blockifier::execution::entry_point::{CallEntryPoint, CallType, EntryPointExecutionContext};
 use blockifier::state::cached_state::{CachedState, GlobalContractCache};
 use blockifier::transaction::account_transaction::AccountTransaction;
-use blockifier::transaction::objects::{DeprecatedTransactionInfo, TransactionInfo};
+use blockifier::transaction::objects::{DeprecatedTransactionInfo, TransactionExecutionInfo, TransactionInfo};
 use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transactions::{
     DeclareTransaction, DeployAccountTransaction, InvokeTransaction, L1HandlerTransaction,
 };
 use blockifier::versioned_constants::VersionedConstants;
 use blockifier_state_adapter::BlockifierStateAdapter;
+use bouncer::BlockResources;
 use frame_support::pallet_prelude::*;
-use frame_support::traits::Time;
+use frame_support::storage;
+use frame_support::traits::{Time, UnfilteredDispatchable};
 use frame_system::pallet_prelude::*;
-use mp_block::{Block as StarknetBlock, Header as StarknetHeader};
+use mp_block::commitments::{
+    calculate_event_commitment, calculate_receipt_commitment, calculate_transaction_commitment, BlockHashVersion,
+    BlockStateDiff, ReceiptCommitmentData, ReceiptResources,
+};
+use mp_block::{Block as StarknetBlock, Header as StarknetHeader, L1DataAvailabilityMode};
 use mp_chain_id::MADARA_CHAIN_ID;
 use mp_digest_log::MADARA_ENGINE_ID;
 use mp_felt::Felt252Wrapper;
 use mp_starknet_inherent::{InherentError, InherentType, L1GasPrices, STARKNET_INHERENT_IDENTIFIER};
 use mp_storage::{StarknetStorageSchemaVersion, PALLET_STARKNET_SCHEMA};
 use mp_transactions::execution::{
-    execute_l1_handler_transaction, run_non_revertible_transaction, run_revertible_transaction, TransactionFilter,
+    execute_l1_handler_transaction, run_non_revertible_transaction, run_revertible_transaction, PaymasterConfig,
+    TransactionFilter, TransactionRejection,
+};
+use mp_transactions::{
+    get_transaction_hash, get_transaction_nonce, get_transaction_sender_address, get_transaction_signature,
 };
-use mp_transactions::{get_transaction_nonce, get_transaction_sender_address};
 use sp_runtime::traits::UniqueSaturatedInto;
 use sp_runtime::DigestItem;
 use starknet_api::block::{BlockNumber, BlockTimestamp};
@@ -97,7 +114,10 @@ use starknet_api::transaction::{
 };
 use starknet_crypto::FieldElement;
 
-use crate::types::{CasmClassHash, ContractStorageKey, SierraClassHash, SierraOrCasmClassHash, StorageSlot};
+use crate::state_trie::{NodePosition, TrieId, TrieNode};
+use crate::types::{
+    CasmClassHash, ContractStorageKey, SierraClassHash, SierraOrCasmClassHash, StorageSlot, TransactionFilterRules,
+};
 pub(crate) const LOG_TARGET: &str = "runtime::starknet";
 
 pub const ETHEREUM_EXECUTION_RPC: &[u8] = b"starknet::ETHEREUM_EXECUTION_RPC";
@@ -105,6 +125,16 @@ pub const ETHEREUM_CONSENSUS_RPC: &[u8] = b"starknet::ETHEREUM_CONSENSUS_RPC";
 
 pub const SN_OS_CONFIG_HASH_VERSION: &str = "StarknetOsConfig1";
 
+/// The last execution constants decoded from `ExecutionConstantsOverride`, along with their JSON.
+/// Parsing them is much more expensive than comparing the stored JSON, and they are read by every
+/// transaction. Natively the constants are decoded once per process; under Wasm, once per runtime
+/// call, e.g. per block.
+///
+/// Runtime storage can only hold the JSON, hence the static. The decoded constants are only served
+/// for the exact JSON they were parsed from, so a stale entry is never used, and they are dropped
+/// on runtime upgrade since a new runtime may parse the same JSON differently.
+static DECODED_EXECUTION_CONSTANTS: Mutex<Option<(Vec<u8>, Arc<VersionedConstants>)>> = Mutex::new(None);
+
 // syntactic sugar for logging.
 #[macro_export]
 macro_rules! log {
@@ -121,7 +151,11 @@ pub mod pallet {
 
     use super::*;
 
+    /// The storage version 1 maintains the state tries, see [`Pallet::migrate_to_state_tries`].
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
@@ -129,6 +163,14 @@ pub mod pallet {
     /// mechanism and comply with starknet which uses an ER20 as fee token
     #[pallet::config]
     pub trait Config: frame_system::Config {
+        /// The overarching event type.
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        /// The origin allowed to update the chain parameters stored in the pallet.
+        type ControlOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+        /// Identifier of a block author, e.g. an Aura authority id.
+        type AuthorityId: Parameter + Member + MaybeSerializeDeserialize + Ord;
+        /// The origin of a block author managing its sequencer address.
+        type AuthorityOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = Self::AuthorityId>;
         /// The block time
         type TimestampProvider: Time;
         /// Custom transaction filter for Invoke txs
@@ -149,17 +191,16 @@ pub mod pallet {
         /// set how long transactions are kept in the mempool.
         #[pallet::constant]
         type TransactionLongevity: Get<TransactionLongevity>;
-        /// A bool to disable transaction fees and make all transactions free
-        #[pallet::constant]
-        type DisableTransactionFee: Get<bool>;
-        /// A bool to disable Nonce validation
-        type DisableNonceValidation: Get<bool>;
         #[pallet::constant]
         type ProtocolVersion: Get<u8>;
         #[pallet::constant]
         type ProgramHash: Get<Felt252Wrapper>;
+        /// The execution constants used when no override has been set in the storage.
         #[pallet::constant]
         type ExecutionConstants: Get<Arc<VersionedConstants>>;
+        /// The Starknet resources a block can use, see the [`crate::bouncer`] module.
+        #[pallet::constant]
+        type BlockResourceLimits: Get<BlockResources>;
     }
 
     /// The Starknet pallet hooks.
@@ -185,7 +226,10 @@ pub mod pallet {
 
         /// Perform a module upgrade.
         fn on_runtime_upgrade() -> Weight {
-            Weight::zero()
+            crate::blockifier_state_adapter::clear_decoded_contract_classes();
+            Self::clear_decoded_execution_constants();
+
+            Self::migrate_to_state_tries()
         }
     }
 
@@ -218,6 +262,39 @@ pub mod pallet {
     #[pallet::unbounded]
     #[pallet::getter(fn tx_revert_error)]
     pub(super) type TxRevertError<T: Config> = StorageMap<_, Identity, TransactionHash, String, OptionQuery>;
+
+    /// Fee and gas consumed by each transaction, committed to in the block receipt commitment.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn tx_receipt_resources)]
+    pub(super) type TxReceiptResources<T: Config> =
+        StorageMap<_, Identity, TransactionHash, ReceiptResources, ValueQuery>;
+
+    /// Execution resources used by each transaction, reported in its receipt.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn tx_execution_resources)]
+    pub(super) type TxExecutionResources<T: Config> =
+        StorageMap<_, Identity, TransactionHash, BTreeMap<String, u64>, OptionQuery>;
+
+    /// Paymaster charged the fee of each sponsored transaction instead of its sender.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn tx_paymaster)]
+    pub(super) type TxPaymaster<T: Config> = StorageMap<_, Identity, TransactionHash, ContractAddress, OptionQuery>;
+
+    /// Starknet resources used by the transactions of the current building block.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn pending_block_resources)]
+    pub(super) type PendingBlockResources<T: Config> = StorageValue<_, BlockResources, ValueQuery>;
+
+    /// Transaction executed by `pre_dispatch` to check its block resources, which its dispatch
+    /// keeps instead of executing it again.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type PreDispatchedTransaction<T: Config> = StorageValue<_, TransactionHash, OptionQuery>;
+
     /// The Starknet pallet storage items.
     /// STORAGE
     /// Mapping of contract address to state root.
@@ -227,6 +304,17 @@ pub mod pallet {
     pub(super) type ContractsStateRoots<T: Config> =
         StorageMap<_, Identity, ContractAddress, Felt252Wrapper, OptionQuery>;
 
+    /// Nodes of the Starknet state tries, see the [`crate::state_trie`] module.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type StateTrieNodes<T: Config> =
+        StorageDoubleMap<_, Identity, TrieId, Identity, NodePosition, TrieNode, OptionQuery>;
+
+    /// Position of the highest node of each non-empty Starknet state trie.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type StateTrieRoots<T: Config> = StorageMap<_, Identity, TrieId, NodePosition, OptionQuery>;
+
     /// Pending storage slot updates
     /// STORAGE
     /// Mapping storage key to storage value.
@@ -236,6 +324,35 @@ pub mod pallet {
     pub(super) type PendingStorageChanges<T: Config> =
         StorageMap<_, Identity, ContractAddress, Vec<StorageSlot>, ValueQuery>;
 
+    /// Pending nonce updates
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type PendingNonces<T: Config> = StorageMap<_, Identity, ContractAddress, Nonce, OptionQuery>;
+
+    /// Pending deployed or replaced contracts
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type PendingContractClassHashes<T: Config> =
+        StorageMap<_, Identity, ContractAddress, ClassHash, OptionQuery>;
+
+    /// Pending Cairo 1 class declarations
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type PendingDeclaredClasses<T: Config> =
+        StorageMap<_, Identity, ClassHash, CompiledClassHash, OptionQuery>;
+
+    /// Pending Cairo 0 class declarations
+    #[pallet::storage]
+    #[pallet::unbounded]
+    pub(super) type PendingDeprecatedDeclaredClasses<T: Config> = StorageValue<_, BTreeSet<ClassHash>, ValueQuery>;
+
+    /// State diff of the last block, overwritten by each block.
+    /// Read by the client once the block is imported, to store it along the block.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn last_state_diff)]
+    pub(super) type LastStateDiff<T: Config> = StorageValue<_, BlockStateDiff, ValueQuery>;
+
     /// Mapping for block number and hashes.
     /// Safe to use `Identity` as the key is already a hash.
     #[pallet::storage]
@@ -303,7 +420,23 @@ pub mod pallet {
     #[pallet::getter(fn sequencer_address)]
     pub type SequencerAddress<T: Config> = StorageValue<_, ContractAddress, ValueQuery>;
 
-    /// Current sequencer address.
+    /// Sequencer address set through `set_sequencer_address`.
+    /// When set, it takes precedence over the address provided by the block author's inherent.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn sequencer_address_override)]
+    pub type SequencerAddressOverride<T: Config> = StorageValue<_, ContractAddress, OptionQuery>;
+
+    /// Sequencer address registered by each block author.
+    /// Read by the node when building the inherent data of the blocks it authors, so that fees are
+    /// paid to the author of the block.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn authority_sequencer_address)]
+    pub type AuthoritySequencerAddresses<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AuthorityId, ContractAddress, OptionQuery>;
+
+    /// Current L1 gas prices.
     #[pallet::storage]
     #[pallet::unbounded]
     #[pallet::getter(fn current_l1_gas_prices)]
@@ -315,6 +448,44 @@ pub mod pallet {
     #[pallet::getter(fn inherent_update)]
     pub type InherentUpdate<T: Config> = StorageValue<_, bool, ValueQuery>;
 
+    /// Parameters of the storage-backed transaction filters.
+    /// Updated through `set_transaction_filter_rules`.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn transaction_filter_rules)]
+    pub type FilterRules<T: Config> = StorageValue<_, TransactionFilterRules, ValueQuery>;
+
+    /// Accounts allowed to update the chain parameters when the runtime accepts them as its
+    /// `ControlOrigin`, see [`crate::control_accounts::ControlAccountsOf`].
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn control_accounts)]
+    pub type ControlAccounts<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+
+    /// If true, transaction fees are neither checked nor charged.
+    #[pallet::storage]
+    #[pallet::getter(fn is_transaction_fee_disabled)]
+    pub type TransactionFeeDisabled<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// If true, the nonce of incoming transactions is not checked against the account nonce.
+    #[pallet::storage]
+    #[pallet::getter(fn is_nonce_validation_disabled)]
+    pub type NonceValidationDisabled<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// JSON encoded `VersionedConstants` replacing the runtime `ExecutionConstants`.
+    /// Stored as JSON because `VersionedConstants` can only be deserialized from it.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn execution_constants_override)]
+    pub type ExecutionConstantsOverride<T: Config> = StorageValue<_, Vec<u8>, OptionQuery>;
+
+    /// Interface of the paymaster contracts sponsoring transactions.
+    /// Transactions cannot be sponsored when `None`, their `paymaster_data` is then ignored.
+    #[pallet::storage]
+    #[pallet::unbounded]
+    #[pallet::getter(fn paymaster_config)]
+    pub type PaymasterConfiguration<T: Config> = StorageValue<_, PaymasterConfig, OptionQuery>;
+
     /// Information about processed L1 Messages
     /// Based on Nonce value.
     #[pallet::storage]
@@ -361,6 +532,18 @@ pub mod pallet {
         pub strk_fee_token_address: ContractAddress,
         /// Must be set to the address of a fee token ERC20 contract.
         pub eth_fee_token_address: ContractAddress,
+        /// Initial parameters of the storage-backed transaction filters.
+        pub transaction_filter_rules: TransactionFilterRules,
+        /// Accounts allowed to update the chain parameters, see [`ControlAccounts`].
+        pub control_accounts: Vec<T::AccountId>,
+        /// Make all transactions free.
+        pub disable_transaction_fee: bool,
+        /// Accept transactions regardless of their nonce.
+        pub disable_nonce_validation: bool,
+        /// Sequencer address used instead of the one provided by the block author.
+        pub sequencer_address: Option<ContractAddress>,
+        /// Sequencer addresses registered by the initial block authors.
+        pub authority_sequencer_addresses: Vec<(T::AuthorityId, ContractAddress)>,
         pub _phantom: PhantomData<T>,
     }
 
@@ -375,6 +558,12 @@ pub mod pallet {
                 chain_id: DefaultChainId::get(),
                 strk_fee_token_address: Default::default(),
                 eth_fee_token_address: Default::default(),
+                transaction_filter_rules: Default::default(),
+                control_accounts: vec![],
+                disable_transaction_fee: false,
+                disable_nonce_validation: false,
+                sequencer_address: None,
+                authority_sequencer_addresses: vec![],
                 _phantom: PhantomData,
             }
         }
@@ -382,8 +571,6 @@ pub mod pallet {
     #[pallet::genesis_build]
     impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
         fn build(&self) {
-            <Pallet<T>>::store_block(0);
-
             frame_support::storage::unhashed::put::<StarknetStorageSchemaVersion>(
                 PALLET_STARKNET_SCHEMA,
                 &StarknetStorageSchemaVersion::V1,
@@ -391,6 +578,9 @@ pub mod pallet {
 
             for (class_hash, contract_class) in self.contract_classes.iter() {
                 ContractClasses::<T>::insert(class_hash.0, contract_class);
+                if let ContractClass::V0(_) = contract_class {
+                    PendingDeprecatedDeclaredClasses::<T>::mutate(|classes| classes.insert(*class_hash));
+                }
             }
 
             for (sierra_class_hash, casm_class_hash) in self.sierra_to_casm_class_hash.iter() {
@@ -400,6 +590,7 @@ pub mod pallet {
                     sierra_class_hash,
                 );
                 CompiledClassHashes::<T>::insert(sierra_class_hash.0, casm_class_hash);
+                PendingDeclaredClasses::<T>::insert(sierra_class_hash, casm_class_hash);
             }
 
             for (address, class_hash) in self.contracts.iter() {
@@ -410,10 +601,12 @@ pub mod pallet {
                 );
 
                 ContractClassHashes::<T>::insert(address, class_hash.0);
+                PendingContractClassHashes::<T>::insert(address, class_hash);
             }
 
             for (key, value) in self.storage.iter() {
                 StorageView::<T>::insert(key, value);
+                PendingStorageChanges::<T>::append(key.0, (key.1, Felt252Wrapper::from(*value)));
             }
 
             LastKnownEthBlock::<T>::set(None);
@@ -423,11 +616,55 @@ pub mod pallet {
                 eth_fee_token_address: self.eth_fee_token_address,
             });
             InherentUpdate::<T>::put(true);
+            FilterRules::<T>::put(self.transaction_filter_rules.clone());
+            ControlAccounts::<T>::put(self.control_accounts.clone());
+            TransactionFeeDisabled::<T>::put(self.disable_transaction_fee);
+            NonceValidationDisabled::<T>::put(self.disable_nonce_validation);
+            SequencerAddressOverride::<T>::set(self.sequencer_address);
+            if let Some(address) = self.sequencer_address {
+                SequencerAddress::<T>::put(address);
+            }
+            for (authority, address) in self.authority_sequencer_addresses.iter() {
+                AuthoritySequencerAddresses::<T>::insert(authority, address);
+            }
 
-            ChainIdStorage::<T>::put(self.chain_id)
+            ChainIdStorage::<T>::put(self.chain_id);
+
+            // The genesis state is the state diff of the genesis block
+            <Pallet<T>>::store_block(0);
         }
     }
 
+    /// The Starknet pallet events.
+    /// EVENTS
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// The transaction filter rules have been updated.
+        TransactionFilterRulesUpdated { rules: TransactionFilterRules },
+        /// Transaction fees have been enabled or disabled.
+        TransactionFeeDisabledUpdated { disabled: bool },
+        /// Nonce validation has been enabled or disabled.
+        NonceValidationDisabledUpdated { disabled: bool },
+        /// The execution constants have been replaced, or reset to the runtime default if
+        /// `overridden` is false.
+        ExecutionConstantsUpdated { overridden: bool },
+        /// The sequencer address override has been set or removed.
+        SequencerAddressUpdated { address: Option<ContractAddress> },
+        /// A block author registered a sequencer address, replacing `previous` if any.
+        SequencerAddressRegistered {
+            authority: T::AuthorityId,
+            address: ContractAddress,
+            previous: Option<ContractAddress>,
+        },
+        /// The sequencer address of a block author has been removed.
+        SequencerAddressUnregistered { authority: T::AuthorityId },
+        /// Transaction sponsoring has been configured, or disabled if `config` is `None`.
+        PaymasterConfigUpdated { config: Option<PaymasterConfig> },
+        /// The accounts allowed to update the chain parameters have been replaced.
+        ControlAccountsUpdated { accounts: Vec<T::AccountId> },
+    }
+
     /// The Starknet pallet custom errors.
     /// ERRORS
     #[pallet::error]
@@ -456,6 +693,9 @@ pub mod pallet {
         L1MessageAlreadyExecuted,
         MissingL1GasUsage,
         QueryTransactionCannotBeExecuted,
+        TransactionRejectedByFilter,
+        InvalidExecutionConstants,
+        SequencerAddressNotRegistered,
     }
 
     /// The Starknet pallet external functions.
@@ -484,7 +724,7 @@ pub mod pallet {
 
             let addr = StarkFelt::new(data.sequencer_address).map_err(|_| Error::<T>::SequencerAddressNotValid)?;
             let addr = ContractAddress(addr.try_into().map_err(|_| Error::<T>::SequencerAddressNotValid)?);
-            SequencerAddress::<T>::put(addr);
+            SequencerAddress::<T>::put(Self::sequencer_address_override().unwrap_or(addr));
             CurrentL1GasPrice::<T>::put(data.l1_gas_price);
 
             InherentUpdate::<T>::put(true);
@@ -508,27 +748,40 @@ pub mod pallet {
             ensure!(!transaction.only_query, Error::<T>::QueryTransactionCannotBeExecuted);
             // This ensures that the function can only be called via unsigned transaction.
             ensure_none(origin)?;
+            if Self::take_pre_dispatched(transaction.tx_hash) {
+                return Ok(());
+            }
+            T::InvokeTransactionFilter::check(&transaction).map_err(|e| {
+                log!(debug, "Invoke transaction rejected by filter: {}", e);
+                Error::<T>::TransactionRejectedByFilter
+            })?;
 
             // Init caches
             let mut state = BlockifierStateAdapter::<T>::default();
             let block_context = Self::get_block_context();
-            let charge_fee = !<T as Config>::DisableTransactionFee::get();
+            let charge_fee = !Self::is_transaction_fee_disabled();
+            let disable_nonce_validation = Self::is_nonce_validation_disabled();
+            let paymaster_config = Self::paymaster_config();
 
             // Execute
             let tx_execution_infos = match transaction.tx.version() {
-                TransactionVersion::ZERO => run_non_revertible_transaction::<_, _, T::InvokeTransactionFilter>(
+                TransactionVersion::ZERO => run_non_revertible_transaction(
                     &transaction,
                     &mut state,
                     &block_context,
                     true,
                     charge_fee,
+                    disable_nonce_validation,
+                    paymaster_config.as_ref(),
                 ),
-                _ => run_revertible_transaction::<_, _, T::InvokeTransactionFilter>(
+                _ => run_revertible_transaction(
                     &transaction,
                     &mut state,
                     &block_context,
                     true,
                     charge_fee,
+                    disable_nonce_validation,
+                    paymaster_config.as_ref(),
                 ),
             }
             .map_err(|e| {
@@ -544,7 +797,8 @@ pub mod pallet {
             Self::store_transaction(
                 transaction.tx_hash,
                 Transaction::AccountTransaction(AccountTransaction::Invoke(transaction)),
-                tx_execution_infos.revert_error,
+                &tx_execution_infos,
+                BlockResources::from_execution_info(&tx_execution_infos, state.state_diff_size(), None),
             );
 
             Ok(())
@@ -567,17 +821,26 @@ pub mod pallet {
             ensure!(!transaction.only_query(), Error::<T>::QueryTransactionCannotBeExecuted);
             // This ensures that the function can only be called via unsigned transaction.
             ensure_none(origin)?;
+            if Self::take_pre_dispatched(transaction.tx_hash()) {
+                return Ok(());
+            }
+            T::DeclareTransactionFilter::check(&transaction).map_err(|e| {
+                log!(debug, "Declare transaction rejected by filter: {}", e);
+                Error::<T>::TransactionRejectedByFilter
+            })?;
 
             let mut state = BlockifierStateAdapter::<T>::default();
-            let charge_fee = !<T as Config>::DisableTransactionFee::get();
+            let charge_fee = !Self::is_transaction_fee_disabled();
 
             // Execute
-            let tx_execution_infos = run_non_revertible_transaction::<_, _, T::DeclareTransactionFilter>(
+            let tx_execution_infos = run_non_revertible_transaction(
                 &transaction,
                 &mut state,
                 &Self::get_block_context(),
                 true,
                 charge_fee,
+                Self::is_nonce_validation_disabled(),
+                Self::paymaster_config().as_ref(),
             )
             .map_err(|_| Error::<T>::TransactionExecutionFailed)?;
 
@@ -589,7 +852,8 @@ pub mod pallet {
             Self::store_transaction(
                 transaction.tx_hash(),
                 Transaction::AccountTransaction(AccountTransaction::Declare(transaction.clone())),
-                tx_execution_infos.revert_error,
+                &tx_execution_infos,
+                BlockResources::from_execution_info(&tx_execution_infos, state.state_diff_size(), None),
             );
 
             Ok(())
@@ -613,17 +877,26 @@ pub mod pallet {
             ensure!(!transaction.only_query, Error::<T>::QueryTransactionCannotBeExecuted);
             // This ensures that the function can only be called via unsigned transaction.
             ensure_none(origin)?;
+            if Self::take_pre_dispatched(transaction.tx_hash) {
+                return Ok(());
+            }
+            T::DeployAccountTransactionFilter::check(&transaction).map_err(|e| {
+                log!(debug, "Deploy account transaction rejected by filter: {}", e);
+                Error::<T>::TransactionRejectedByFilter
+            })?;
 
             let mut state = BlockifierStateAdapter::<T>::default();
-            let charge_fee = !<T as Config>::DisableTransactionFee::get();
+            let charge_fee = !Self::is_transaction_fee_disabled();
 
             // Execute
-            let tx_execution_infos = run_non_revertible_transaction::<_, _, T::DeployAccountTransactionFilter>(
+            let tx_execution_infos = run_non_revertible_transaction(
                 &transaction,
                 &mut state,
                 &Self::get_block_context(),
                 true,
                 charge_fee,
+                Self::is_nonce_validation_disabled(),
+                Self::paymaster_config().as_ref(),
             )
             .map_err(|_| Error::<T>::TransactionExecutionFailed)?;
 
@@ -635,7 +908,8 @@ pub mod pallet {
             Self::store_transaction(
                 transaction.tx_hash,
                 Transaction::AccountTransaction(AccountTransaction::DeployAccount(transaction)),
-                tx_execution_infos.revert_error,
+                &tx_execution_infos,
+                BlockResources::from_execution_info(&tx_execution_infos, state.state_diff_size(), None),
             );
 
             Ok(())
@@ -659,6 +933,9 @@ pub mod pallet {
         pub fn consume_l1_message(origin: OriginFor<T>, transaction: L1HandlerTransaction) -> DispatchResult {
             // This ensures that the function can only be called via unsigned transaction.
             ensure_none(origin)?;
+            if Self::take_pre_dispatched(transaction.tx_hash) {
+                return Ok(());
+            }
 
             let nonce = transaction.tx.nonce;
 
@@ -685,14 +962,225 @@ pub mod pallet {
                 &tx_execution_infos.execute_call_info,
                 &tx_execution_infos.fee_transfer_call_info,
             );
+            let resources = BlockResources::from_execution_info(
+                &tx_execution_infos,
+                state.state_diff_size(),
+                Some(transaction.payload_size()),
+            );
             Self::store_transaction(
                 transaction.tx_hash,
                 Transaction::L1HandlerTransaction(transaction),
-                tx_execution_infos.revert_error,
+                &tx_execution_infos,
+                resources,
             );
 
             Ok(())
         }
+
+        /// Replace the parameters of the storage-backed transaction filters.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `rules` - The new filter rules.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(5)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_transaction_filter_rules(origin: OriginFor<T>, rules: TransactionFilterRules) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            FilterRules::<T>::put(rules.clone());
+            Self::deposit_event(Event::TransactionFilterRulesUpdated { rules });
+
+            Ok(())
+        }
+
+        /// Enable or disable transaction fees.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `disabled` - If true, transactions are executed without charging fees.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_transaction_fee_disabled(origin: OriginFor<T>, disabled: bool) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            TransactionFeeDisabled::<T>::put(disabled);
+            Self::deposit_event(Event::TransactionFeeDisabledUpdated { disabled });
+
+            Ok(())
+        }
+
+        /// Enable or disable the validation of transaction nonces.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `disabled` - If true, transactions are accepted regardless of their nonce.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_nonce_validation_disabled(origin: OriginFor<T>, disabled: bool) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            NonceValidationDisabled::<T>::put(disabled);
+            Self::deposit_event(Event::NonceValidationDisabledUpdated { disabled });
+
+            Ok(())
+        }
+
+        /// Replace the execution constants used by the blockifier.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `constants` - The JSON encoded `VersionedConstants`, or `None` to go back to the
+        /// runtime `ExecutionConstants`.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_execution_constants(origin: OriginFor<T>, constants: Option<Vec<u8>>) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            if let Some(constants) = &constants {
+                serde_json::from_slice::<VersionedConstants>(constants).map_err(|e| {
+                    log!(debug, "Failed to deserialize execution constants: {}", e);
+                    Error::<T>::InvalidExecutionConstants
+                })?;
+            }
+
+            let overridden = constants.is_some();
+            ExecutionConstantsOverride::<T>::set(constants);
+            Self::deposit_event(Event::ExecutionConstantsUpdated { overridden });
+
+            Ok(())
+        }
+
+        /// Set the sequencer address used for the next blocks, regardless of the block author.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `address` - The sequencer address, or `None` to use the one provided by the block
+        /// author.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_sequencer_address(origin: OriginFor<T>, address: Option<ContractAddress>) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            SequencerAddressOverride::<T>::set(address);
+            Self::deposit_event(Event::SequencerAddressUpdated { address });
+
+            Ok(())
+        }
+
+        /// Register or rotate the sequencer address of the calling block author.
+        ///
+        /// Fees of the blocks authored by the caller are paid to this address.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `AuthorityOrigin` of the runtime.
+        /// * `address` - The Starknet address receiving the fees.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the registration.
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn register_sequencer_address(origin: OriginFor<T>, address: ContractAddress) -> DispatchResult {
+            let authority = T::AuthorityOrigin::ensure_origin(origin)?;
+
+            let previous = AuthoritySequencerAddresses::<T>::mutate(&authority, |stored| stored.replace(address));
+            Self::deposit_event(Event::SequencerAddressRegistered { authority, address, previous });
+
+            Ok(())
+        }
+
+        /// Remove the sequencer address of a block author.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `AuthorityOrigin` for `authority`, or the `ControlOrigin`.
+        /// * `authority` - The block author whose sequencer address is removed.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the removal.
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn unregister_sequencer_address(origin: OriginFor<T>, authority: T::AuthorityId) -> DispatchResult {
+            match T::AuthorityOrigin::try_origin(origin) {
+                Ok(caller) => ensure!(caller == authority, DispatchError::BadOrigin),
+                Err(origin) => T::ControlOrigin::ensure_origin(origin).map(|_| ())?,
+            }
+
+            AuthoritySequencerAddresses::<T>::take(&authority).ok_or(Error::<T>::SequencerAddressNotRegistered)?;
+            Self::deposit_event(Event::SequencerAddressUnregistered { authority });
+
+            Ok(())
+        }
+
+        /// Configure the paymaster contracts allowed to pay for the transactions they sponsor.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `config` - The paymaster interface, or `None` to disable transaction sponsoring.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_paymaster_config(origin: OriginFor<T>, config: Option<PaymasterConfig>) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            PaymasterConfiguration::<T>::set(config.clone());
+            Self::deposit_event(Event::PaymasterConfigUpdated { config });
+
+            Ok(())
+        }
+
+        /// Replace the accounts allowed to update the chain parameters.
+        ///
+        /// # Arguments
+        ///
+        /// * `origin` - Must be the `ControlOrigin` of the runtime.
+        /// * `accounts` - The new control accounts, empty to only accept the other origins of the
+        /// `ControlOrigin`.
+        ///
+        /// # Returns
+        ///
+        /// * `DispatchResult` - The result of the update.
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_control_accounts(origin: OriginFor<T>, accounts: Vec<T::AccountId>) -> DispatchResult {
+            T::ControlOrigin::ensure_origin(origin)?;
+
+            ControlAccounts::<T>::put(accounts.clone());
+            Self::deposit_event(Event::ControlAccountsUpdated { accounts });
+
+            Ok(())
+        }
     }
 
     #[pallet::inherent]
@@ -737,6 +1225,13 @@ pub mod pallet {
             let transaction = Self::convert_runtime_calls_to_starknet_transaction(call.clone())
                 .map_err(|_| InvalidTransaction::Call)?;
 
+            if let Transaction::AccountTransaction(account_transaction) = &transaction {
+                Self::check_transaction_filters(account_transaction).map_err(|e| {
+                    log!(debug, "Transaction rejected by filter: {}", e);
+                    InvalidTransaction::Custom(e.code())
+                })?;
+            }
+
             // Version 0 transaction does not have any nonce or validation rules.
             match transaction {
                 Transaction::AccountTransaction(AccountTransaction::Declare(DeclareTransaction { tx, .. }))
@@ -823,11 +1318,12 @@ pub mod pallet {
         /// In the default implementation of pre_dispatch for the ValidateUnsigned trait,
         /// this function calls the validate_unsigned function in order to verify validity
         /// before dispatch. In our case, since transaction was already validated in
-        /// `validate_unsigned` we can just return Ok.
-        fn pre_dispatch(_call: &Self::Call) -> Result<(), TransactionValidityError> {
+        /// `validate_unsigned` we only check that it fits in the block resources, which executes
+        /// it: the dispatch that follows keeps that execution.
+        fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
             // TODO: run the full validation: pre_validation and validation, to avoid including failing tx in
             // the runtime
-            Ok(())
+            Self::check_block_resources(call)
         }
     }
 }
@@ -883,7 +1379,7 @@ impl<T: Config> Pallet<T> {
                 use_kzg_da: true,
             },
             &ChainInfo { chain_id, fee_token_addresses },
-            T::ExecutionConstants::get().deref(),
+            Self::execution_constants().deref(),
         )
     }
 
@@ -951,7 +1447,7 @@ impl<T: Config> Pallet<T> {
             storage_address: address,
             caller_address: ContractAddress::default(),
             call_type: CallType::Call,
-            initial_gas: T::ExecutionConstants::get().tx_initial_gas(),
+            initial_gas: Self::execution_constants().tx_initial_gas(),
         };
 
         let mut resources = cairo_vm::vm::runners::cairo_runner::ExecutionResources::default();
@@ -1010,7 +1506,10 @@ impl<T: Config> Pallet<T> {
         let transaction_count = transactions.len();
 
         let parent_block_hash = Self::parent_block_hash(&block_number);
-        let events_count = transaction_hashes.iter().map(|tx_hash| TxEvents::<T>::get(tx_hash).len() as u128).sum();
+        let events = transaction_hashes
+            .iter()
+            .flat_map(|tx_hash| TxEvents::<T>::get(tx_hash).into_iter().map(|event| (*tx_hash, event)))
+            .collect::<Vec<_>>();
 
         let sequencer_address = Self::sequencer_address();
         let block_timestamp = Self::block_timestamp();
@@ -1019,6 +1518,32 @@ impl<T: Config> Pallet<T> {
         let extra_data = None;
 
         let l1_gas_price = Self::current_l1_gas_prices().into();
+        // Blocks are executed with KZG data availability, see `get_block_context`
+        let l1_da_mode = L1DataAvailabilityMode::Blob;
+
+        let hash_version = BlockHashVersion::from_protocol_version(protocol_version);
+        let transaction_commitment = calculate_transaction_commitment(
+            &transaction_hashes
+                .iter()
+                .zip(transactions.iter())
+                .map(|(tx_hash, tx)| (*tx_hash, get_transaction_signature(tx)))
+                .collect::<Vec<_>>(),
+            hash_version,
+        );
+        let event_commitment = calculate_event_commitment(&events, hash_version);
+        let receipt_commitment = calculate_receipt_commitment(
+            &transaction_hashes
+                .iter()
+                .map(|tx_hash| ReceiptCommitmentData {
+                    transaction_hash: *tx_hash,
+                    resources: TxReceiptResources::<T>::get(tx_hash),
+                    messages_sent: TxMessages::<T>::get(tx_hash),
+                    revert_reason: TxRevertError::<T>::get(tx_hash),
+                })
+                .collect::<Vec<_>>(),
+        );
+        let state_diff = Self::take_pending_state_diff();
+        let global_state_root = Self::update_state_tries(&state_diff);
 
         let block = StarknetBlock::try_new(
             StarknetHeader::new(
@@ -1026,8 +1551,15 @@ impl<T: Config> Pallet<T> {
                 block_number,
                 sequencer_address,
                 block_timestamp,
+                global_state_root,
                 transaction_count as u128,
-                events_count,
+                transaction_commitment,
+                events.len() as u128,
+                event_commitment,
+                receipt_commitment,
+                state_diff.commitment(),
+                state_diff.len(),
+                l1_da_mode,
                 protocol_version,
                 l1_gas_price,
                 extra_data,
@@ -1040,14 +1572,36 @@ impl<T: Config> Pallet<T> {
         let blockhash = block.header().hash();
         BlockHash::<T>::insert(block_number, blockhash);
 
+        LastStateDiff::<T>::put(state_diff);
+
         // Kill pending storage.
         Pending::<T>::kill();
         PendingHashes::<T>::kill();
+        PendingBlockResources::<T>::kill();
 
         let digest = DigestItem::Consensus(MADARA_ENGINE_ID, mp_digest_log::Log::Block(block).encode());
         frame_system::Pallet::<T>::deposit_log(digest);
     }
 
+    /// Drains the state updates recorded since the last block.
+    fn take_pending_state_diff() -> BlockStateDiff {
+        let storage_diffs = PendingStorageChanges::<T>::drain()
+            .map(|(contract_address, slots)| {
+                // Only the last value written to a slot is part of the diff
+                let updates = slots.into_iter().map(|(key, value)| (key, value.into())).collect::<BTreeMap<_, _>>();
+                (contract_address, updates)
+            })
+            .collect();
+
+        BlockStateDiff {
+            deployed_contracts: PendingContractClassHashes::<T>::drain().collect(),
+            declared_classes: PendingDeclaredClasses::<T>::drain().collect(),
+            deprecated_declared_classes: PendingDeprecatedDeclaredClasses::<T>::take(),
+            storage_diffs,
+            nonces: PendingNonces::<T>::drain().collect(),
+        }
+    }
+
     /// Aggregate L2 > L1 messages from the call info.
     ///
     /// # Arguments
@@ -1158,18 +1712,122 @@ impl<T: Config> Pallet<T> {
         }
     }
 
-    fn store_transaction(tx_hash: TransactionHash, tx: Transaction, revert_reason: Option<String>) {
+    fn store_transaction(
+        tx_hash: TransactionHash,
+        tx: Transaction,
+        execution_info: &TransactionExecutionInfo,
+        resources: BlockResources,
+    ) {
+        if let Transaction::AccountTransaction(tx) = &tx {
+            if let Some(paymaster) = Self::transaction_paymaster(tx) {
+                TxPaymaster::<T>::insert(tx_hash, paymaster.address);
+            }
+        }
         Pending::<T>::append(tx);
         PendingHashes::<T>::append(tx_hash);
-        TxRevertError::<T>::set(tx_hash, revert_reason);
+        TxRevertError::<T>::set(tx_hash, execution_info.revert_error.clone());
+        TxReceiptResources::<T>::insert(tx_hash, ReceiptResources::from(execution_info));
+        // Sorted by name so that the storage does not depend on the order of the blockifier map
+        TxExecutionResources::<T>::insert(
+            tx_hash,
+            execution_info
+                .actual_resources
+                .0
+                .iter()
+                .map(|(name, value)| (name.to_lowercase(), *value as u64))
+                .collect::<BTreeMap<_, _>>(),
+        );
+        PendingBlockResources::<T>::mutate(|block_resources| {
+            *block_resources = block_resources.saturating_add(&resources)
+        });
+    }
+
+    /// Defer a transaction to a later block if executing it would exceed the block resource
+    /// limits.
+    ///
+    /// The resources used by the transaction are only known once it is executed, so the call is
+    /// dispatched here, in a storage layer that is only kept if the transaction fits in the block.
+    /// The dispatch that follows then keeps this execution instead of running the transaction
+    /// again, see [`Self::take_pre_dispatched`]. Transactions which don't fit in an empty block
+    /// are invalid. Calls failing to execute are left to the dispatch to reject.
+    fn check_block_resources(call: &Call<T>) -> Result<(), TransactionValidityError> {
+        let limits = T::BlockResourceLimits::get();
+        if limits == BlockResources::MAX {
+            return Ok(());
+        }
+        let Ok(transaction) = Self::convert_runtime_calls_to_starknet_transaction(call.clone()) else {
+            return Ok(());
+        };
+        let tx_hash = *get_transaction_hash(&transaction);
+
+        let before = Self::pending_block_resources();
+        storage::transactional::with_transaction(|| {
+            if call.clone().dispatch_bypass_filter(frame_system::RawOrigin::None.into()).is_err() {
+                return storage::TransactionOutcome::Rollback(Ok(Ok(())));
+            }
+
+            let after = Self::pending_block_resources();
+            if !after.saturating_sub(&before).fits_in(&limits) {
+                log!(debug, "Transaction exceeds the block resource limits");
+                return storage::TransactionOutcome::Rollback(Ok(Err(InvalidTransaction::Custom(
+                    TransactionRejection::ExceedsBlockResourceLimits.code(),
+                )
+                .into())));
+            }
+            if !after.fits_in(&limits) {
+                return storage::TransactionOutcome::Rollback(Ok(Err(InvalidTransaction::ExhaustsResources.into())));
+            }
+
+            PreDispatchedTransaction::<T>::put(tx_hash);
+            storage::TransactionOutcome::Commit(Ok(Ok(())))
+        })
+        .map_err(|e: DispatchError| {
+            log!(error, "Failed to create a transactional storage to check the block resources: {:?}", e);
+            TransactionValidityError::from(InvalidTransaction::ExhaustsResources)
+        })?
+    }
+
+    /// Returns `true` if `pre_dispatch` already executed the transaction, in which case its
+    /// dispatch has nothing left to do.
+    fn take_pre_dispatched(tx_hash: TransactionHash) -> bool {
+        PreDispatchedTransaction::<T>::take() == Some(tx_hash)
     }
 
     pub fn program_hash() -> Felt252Wrapper {
         T::ProgramHash::get()
     }
 
-    pub fn is_transaction_fee_disabled() -> bool {
-        T::DisableTransactionFee::get()
+    /// The execution constants set through `set_execution_constants`, or the runtime
+    /// `ExecutionConstants` if none were set.
+    pub fn execution_constants() -> Arc<VersionedConstants> {
+        let Some(json) = Self::execution_constants_override() else {
+            return T::ExecutionConstants::get();
+        };
+
+        let mut decoded = DECODED_EXECUTION_CONSTANTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((decoded_json, constants)) = decoded.as_ref() {
+            if *decoded_json == json {
+                return constants.clone();
+            }
+        }
+
+        match serde_json::from_slice(&json) {
+            Ok(constants) => {
+                let constants = Arc::new(constants);
+                *decoded = Some((json, constants.clone()));
+                constants
+            }
+            Err(e) => {
+                log!(error, "Failed to deserialize the stored execution constants: {}", e);
+                T::ExecutionConstants::get()
+            }
+        }
+    }
+
+    /// Drop the constants decoded by [`Self::execution_constants`].
+    fn clear_decoded_execution_constants() {
+        let mut decoded = DECODED_EXECUTION_CONSTANTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *decoded = None;
     }
 
     fn init_cached_state() -> CachedState<BlockifierStateAdapter<T>> {
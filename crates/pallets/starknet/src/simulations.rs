@@ -9,12 +9,12 @@ use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transactions::{ExecutableTransaction, L1HandlerTransaction};
 use frame_support::storage;
 use mp_simulations::{
-    FeeEstimate, InternalSubstrateError, ReExecutionResult, SimulationError, SimulationFlags, TransactionSimulation,
-    TransactionSimulationResult,
+    FeeEstimate, InternalSubstrateError, PaymasterValidation, ReExecutionResult, SimulationError, SimulationFlags,
+    TransactionSimulation, TransactionSimulationResult,
 };
 use mp_transactions::execution::{
     commit_transactional_state, execute_l1_handler_transaction, run_non_revertible_transaction,
-    run_revertible_transaction, CheckFeeBounds, MutRefState, SetArbitraryNonce,
+    run_revertible_transaction, CheckFeeBounds, MutRefState, Paymaster, SetArbitraryNonce,
 };
 use sp_runtime::DispatchError;
 use starknet_api::transaction::TransactionVersion;
@@ -48,6 +48,8 @@ impl<T: Config> Pallet<T> {
         let mut state = BlockifierStateAdapter::<T>::default();
 
         let fee_res_iterator = transactions.into_iter().map(|tx| {
+            Self::check_transaction_filters(&tx)
+                .map_err(|e| SimulationError::TransactionRejectedByFilter(e.to_string()))?;
             match Self::execute_account_transaction(&tx, &mut state, &block_context, simulation_flags) {
                 Ok(mut execution_info) => {
                     if !execution_info.is_reverted() {
@@ -105,6 +107,9 @@ impl<T: Config> Pallet<T> {
         let tx_execution_results = transactions
             .into_iter()
             .map(|tx| {
+                Self::check_transaction_filters(&tx)
+                    .map_err(|e| SimulationError::TransactionRejectedByFilter(e.to_string()))?;
+
                 // In order to produce a state diff for this specific tx we execute on a transactional state
                 let mut transactional_state =
                     CachedState::new(MutRefState::new(&mut state), GlobalContractCache::new(1));
@@ -136,7 +141,13 @@ impl<T: Config> Pallet<T> {
                     Ok(fee_estimate) => fee_estimate,
                     Err(e) => return Err(e),
                 };
-                Ok(TransactionSimulation { fee_estimate, execution_info: exec_info, state_diff })
+                let paymaster = Self::transaction_paymaster(&tx).map(|paymaster| PaymasterValidation {
+                    address: paymaster.address,
+                    validate_call_info: paymaster
+                        .find_validate_call_info(exec_info.validate_call_info.as_ref())
+                        .cloned(),
+                });
+                Ok(TransactionSimulation { fee_estimate, execution_info: exec_info, state_diff, paymaster })
             })
             .collect();
 
@@ -350,38 +361,47 @@ impl<T: Config> Pallet<T> {
         block_context: &BlockContext,
         simulation_flags: &SimulationFlags,
     ) -> Result<TransactionExecutionInfo, TransactionExecutionError> {
+        let disable_nonce_validation = Self::is_nonce_validation_disabled();
+        let paymaster_config = Self::paymaster_config();
+
         match transaction {
-            AccountTransaction::Declare(tx) => run_non_revertible_transaction::<_, _, T::DeclareTransactionFilter>(
+            AccountTransaction::Declare(tx) => run_non_revertible_transaction(
                 tx,
                 state,
                 block_context,
                 simulation_flags.validate,
                 simulation_flags.charge_fee,
+                disable_nonce_validation,
+                paymaster_config.as_ref(),
+            ),
+            AccountTransaction::DeployAccount(tx) => run_non_revertible_transaction(
+                tx,
+                state,
+                block_context,
+                simulation_flags.validate,
+                simulation_flags.charge_fee,
+                disable_nonce_validation,
+                paymaster_config.as_ref(),
             ),
-            AccountTransaction::DeployAccount(tx) => {
-                run_non_revertible_transaction::<_, _, T::DeployAccountTransactionFilter>(
-                    tx,
-                    state,
-                    block_context,
-                    simulation_flags.validate,
-                    simulation_flags.charge_fee,
-                )
-            }
             AccountTransaction::Invoke(tx) if tx.tx.version() == TransactionVersion::ZERO => {
-                run_non_revertible_transaction::<_, _, T::InvokeTransactionFilter>(
+                run_non_revertible_transaction(
                     tx,
                     state,
                     block_context,
                     simulation_flags.validate,
                     simulation_flags.charge_fee,
+                    disable_nonce_validation,
+                    paymaster_config.as_ref(),
                 )
             }
-            AccountTransaction::Invoke(tx) => run_revertible_transaction::<_, _, T::InvokeTransactionFilter>(
+            AccountTransaction::Invoke(tx) => run_revertible_transaction(
                 tx,
                 state,
                 block_context,
                 simulation_flags.validate,
                 simulation_flags.charge_fee,
+                disable_nonce_validation,
+                paymaster_config.as_ref(),
             ),
         }
     }
@@ -398,13 +418,22 @@ impl<T: Config> Pallet<T> {
             AccountTransaction::Invoke(tx) => tx.estimate_minimal_gas_vector(&tx_context)?,
         };
         let current_l1_gas_price: GasPrices = Self::current_l1_gas_prices().into();
-        Self::from_tx_info_and_gas_price(
+        let mut fee_estimate = Self::from_tx_info_and_gas_price(
             execution_info,
             &current_l1_gas_price,
             transaction.fee_type(),
             Some(gas_vector),
             block_context,
-        )
+        )?;
+        fee_estimate.paymaster = Self::transaction_paymaster(transaction).map(|paymaster| paymaster.address);
+
+        Ok(fee_estimate)
+    }
+
+    /// Returns the paymaster sponsoring `transaction`, if sponsoring is enabled.
+    pub fn transaction_paymaster(transaction: &AccountTransaction) -> Option<Paymaster> {
+        // An invalid paymaster address fails the execution of the transaction before this is called
+        Paymaster::from_account_transaction(transaction, Self::paymaster_config().as_ref()).ok().flatten()
     }
 
     fn execute_message<S: State>(
@@ -462,6 +491,14 @@ impl<T: Config> Pallet<T> {
         let overall_fee =
             gas_consumed.saturating_mul(gas_price).saturating_add(data_gas_consumed.saturating_mul(data_gas_price));
 
-        Ok(FeeEstimate { gas_consumed, gas_price, data_gas_consumed, data_gas_price, overall_fee, fee_type })
+        Ok(FeeEstimate {
+            gas_consumed,
+            gas_price,
+            data_gas_consumed,
+            data_gas_price,
+            overall_fee,
+            fee_type,
+            paymaster: None,
+        })
     }
 }
@@ -0,0 +1,381 @@
+//! Tries committing to the Starknet state, whose roots make up the global state root of the block
+//! headers.
+//!
+//! See [the Starknet protocol specification](https://docs.starknet.io/documentation/architecture_and_concepts/Network_Architecture/starknet-state/).
+//!
+//! The tries are binary Merkle-Patricia trees of height 251 updated in place at the end of each
+//! block. Only the leaves and the binary nodes are stored, the edges between them being derived
+//! from their positions, so an update touches at most one node per binary node above the leaf.
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+use std::ops::AddAssign;
+
+use frame_support::traits::{Get, GetStorageVersion, StorageVersion};
+use frame_support::weights::Weight;
+use mp_block::commitments::{
+    calculate_class_commitment_leaf, calculate_contract_state_hash, calculate_global_state_root, BlockStateDiff,
+};
+use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
+use mp_hashers::HasherT;
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_core::U256;
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_crypto::FieldElement;
+
+use crate::{
+    CompiledClassHashes, Config, ContractClassHashes, ContractsStateRoots, Nonces, Pallet, StateTrieNodes,
+    StateTrieRoots, StorageView,
+};
+
+/// Height of the state tries.
+pub const STATE_TRIE_HEIGHT: u8 = 251;
+
+/// Identifier of a state trie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum TrieId {
+    /// Trie of the contract states, keyed by contract address.
+    Contracts,
+    /// Trie of the Cairo 1 classes, keyed by class hash.
+    Classes,
+    /// Storage trie of a contract, keyed by storage key.
+    Storage(ContractAddress),
+}
+
+/// Position of a node in a trie: its height and the bits above that height of the keys of the
+/// leaves below it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct NodePosition {
+    pub height: u8,
+    pub path: U256,
+}
+
+impl NodePosition {
+    fn leaf(key: U256) -> Self {
+        Self { height: 0, path: key }
+    }
+
+    fn contains(&self, key: U256) -> bool {
+        key >> usize::from(self.height) == self.path
+    }
+}
+
+/// A stored node of a trie.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum TrieNode {
+    /// A non-zero leaf.
+    Leaf(Felt252Wrapper),
+    /// A node with two non-empty subtrees, along with its hash.
+    Binary { hash: Felt252Wrapper, children: [NodePosition; 2] },
+}
+
+impl TrieNode {
+    fn hash(&self) -> FieldElement {
+        match self {
+            Self::Leaf(value) => value.0,
+            Self::Binary { hash, .. } => hash.0,
+        }
+    }
+}
+
+/// Storage of the nodes of a trie.
+pub trait TrieStorage {
+    /// Returns the position of the highest stored node, `None` for an empty trie.
+    fn root(&self) -> Option<NodePosition>;
+    fn set_root(&mut self, root: Option<NodePosition>);
+    /// Returns the node at `position`, which must be stored.
+    fn node(&self, position: &NodePosition) -> TrieNode;
+    fn insert_node(&mut self, position: NodePosition, node: TrieNode);
+    fn remove_node(&mut self, position: &NodePosition);
+}
+
+/// Number of storage reads and writes made while updating the state tries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StorageAccesses {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+impl StorageAccesses {
+    fn read(accesses: &Cell<Self>, count: u64) {
+        let mut current = accesses.get();
+        current.reads += count;
+        accesses.set(current);
+    }
+
+    fn write(accesses: &Cell<Self>, count: u64) {
+        let mut current = accesses.get();
+        current.writes += count;
+        accesses.set(current);
+    }
+
+    /// The weight of those accesses to the storage of the runtime.
+    pub fn weight<T: Config>(&self) -> Weight {
+        T::DbWeight::get().reads_writes(self.reads, self.writes)
+    }
+}
+
+impl AddAssign for StorageAccesses {
+    fn add_assign(&mut self, other: Self) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+    }
+}
+
+/// A state trie stored in the pallet, counting its accesses to the storage.
+pub struct PalletTrie<'a, T> {
+    id: TrieId,
+    accesses: &'a Cell<StorageAccesses>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, T: Config> PalletTrie<'a, T> {
+    pub fn new(id: TrieId, accesses: &'a Cell<StorageAccesses>) -> Self {
+        Self { id, accesses, _phantom: PhantomData }
+    }
+}
+
+impl<T: Config> TrieStorage for PalletTrie<'_, T> {
+    fn root(&self) -> Option<NodePosition> {
+        StorageAccesses::read(self.accesses, 1);
+        StateTrieRoots::<T>::get(self.id)
+    }
+
+    fn set_root(&mut self, root: Option<NodePosition>) {
+        StorageAccesses::write(self.accesses, 1);
+        StateTrieRoots::<T>::set(self.id, root)
+    }
+
+    fn node(&self, position: &NodePosition) -> TrieNode {
+        StorageAccesses::read(self.accesses, 1);
+        StateTrieNodes::<T>::get(self.id, position).expect("The nodes below the root of a trie are stored")
+    }
+
+    fn insert_node(&mut self, position: NodePosition, node: TrieNode) {
+        StorageAccesses::write(self.accesses, 1);
+        StateTrieNodes::<T>::insert(self.id, position, node)
+    }
+
+    fn remove_node(&mut self, position: &NodePosition) {
+        StorageAccesses::write(self.accesses, 1);
+        StateTrieNodes::<T>::remove(self.id, position)
+    }
+}
+
+/// Sets the leaf at `key` to `value`, removing it if `value` is zero.
+pub fn update<H: HasherT, S: TrieStorage>(storage: &mut S, key: U256, value: FieldElement) {
+    let root = update_subtree::<H, S>(storage, storage.root(), key, value);
+    storage.set_root(root);
+}
+
+/// Returns the root hash of the trie, zero if it is empty.
+pub fn root_hash<H: HasherT, S: TrieStorage>(storage: &S) -> FieldElement {
+    match storage.root() {
+        Some(position) => subtree_hash::<H, S>(storage, &position, STATE_TRIE_HEIGHT),
+        None => FieldElement::ZERO,
+    }
+}
+
+/// Updates the subtree whose highest stored node is at `position` and returns the new position of
+/// that node.
+fn update_subtree<H: HasherT, S: TrieStorage>(
+    storage: &mut S,
+    position: Option<NodePosition>,
+    key: U256,
+    value: FieldElement,
+) -> Option<NodePosition> {
+    let Some(position) = position else {
+        if value == FieldElement::ZERO {
+            return None;
+        }
+        let leaf = NodePosition::leaf(key);
+        storage.insert_node(leaf, TrieNode::Leaf(value.into()));
+        return Some(leaf);
+    };
+
+    if !position.contains(key) {
+        if value == FieldElement::ZERO {
+            return Some(position);
+        }
+        // The new leaf and the subtree meet at the highest bit where their paths differ
+        let height = position.height + ((key >> usize::from(position.height)) ^ position.path).bits() as u8;
+        let leaf = NodePosition::leaf(key);
+        storage.insert_node(leaf, TrieNode::Leaf(value.into()));
+        let children = if key.bit(usize::from(height - 1)) { [position, leaf] } else { [leaf, position] };
+        let node = NodePosition { height, path: key >> usize::from(height) };
+        insert_binary_node::<H, S>(storage, node, children);
+        return Some(node);
+    }
+
+    match storage.node(&position) {
+        TrieNode::Leaf(_) if value == FieldElement::ZERO => {
+            storage.remove_node(&position);
+            None
+        }
+        TrieNode::Leaf(_) => {
+            storage.insert_node(position, TrieNode::Leaf(value.into()));
+            Some(position)
+        }
+        TrieNode::Binary { mut children, .. } => {
+            let side = usize::from(key.bit(usize::from(position.height - 1)));
+            match update_subtree::<H, S>(storage, Some(children[side]), key, value) {
+                Some(child) => {
+                    children[side] = child;
+                    insert_binary_node::<H, S>(storage, position, children);
+                    Some(position)
+                }
+                // A binary node with a single child is replaced by an edge to that child
+                None => {
+                    storage.remove_node(&position);
+                    Some(children[1 - side])
+                }
+            }
+        }
+    }
+}
+
+fn insert_binary_node<H: HasherT, S: TrieStorage>(
+    storage: &mut S,
+    position: NodePosition,
+    children: [NodePosition; 2],
+) {
+    let hash = H::hash_elements(
+        subtree_hash::<H, S>(storage, &children[0], position.height - 1),
+        subtree_hash::<H, S>(storage, &children[1], position.height - 1),
+    );
+    storage.insert_node(position, TrieNode::Binary { hash: hash.into(), children });
+}
+
+/// Returns the hash of the subtree of the given `height` whose highest stored node is at
+/// `position`, i.e. the hash of the edge leading to that node.
+fn subtree_hash<H: HasherT, S: TrieStorage>(storage: &S, position: &NodePosition, height: u8) -> FieldElement {
+    let node_hash = storage.node(position).hash();
+    let length = height - position.height;
+    if length == 0 {
+        return node_hash;
+    }
+    let path = position.path & ((U256::one() << usize::from(length)) - 1);
+    H::hash_elements(node_hash, u256_to_felt(path)) + FieldElement::from(length)
+}
+
+fn u256_to_felt(value: U256) -> FieldElement {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    // Safe because the keys of the tries are felts
+    FieldElement::from_bytes_be(&bytes).unwrap()
+}
+
+fn felt_to_u256(value: &StarkFelt) -> U256 {
+    U256::from_big_endian(value.bytes())
+}
+
+impl<T: Config> Pallet<T> {
+    /// Applies the state diff of a block to the state tries and returns the new global state root.
+    pub(crate) fn update_state_tries(state_diff: &BlockStateDiff) -> StarkHash {
+        Self::update_state_tries_counting_accesses(state_diff, &Cell::default())
+    }
+
+    fn update_state_tries_counting_accesses(
+        state_diff: &BlockStateDiff,
+        accesses: &Cell<StorageAccesses>,
+    ) -> StarkHash {
+        let mut updated_contracts = BTreeSet::new();
+        for (contract_address, updates) in &state_diff.storage_diffs {
+            let mut storage_trie = PalletTrie::<T>::new(TrieId::Storage(*contract_address), accesses);
+            for (key, value) in updates {
+                update::<PedersenHasher, _>(
+                    &mut storage_trie,
+                    felt_to_u256(key.0.key()),
+                    Felt252Wrapper::from(*value).0,
+                );
+            }
+            ContractsStateRoots::<T>::insert(
+                contract_address,
+                Felt252Wrapper(root_hash::<PedersenHasher, _>(&storage_trie)),
+            );
+            StorageAccesses::write(accesses, 1);
+            updated_contracts.insert(*contract_address);
+        }
+        updated_contracts.extend(state_diff.deployed_contracts.keys().copied());
+        updated_contracts.extend(state_diff.nonces.keys().copied());
+
+        let mut contracts_trie = PalletTrie::<T>::new(TrieId::Contracts, accesses);
+        for contract_address in updated_contracts {
+            StorageAccesses::read(accesses, 3);
+            let class_hash = ClassHash(ContractClassHashes::<T>::get(contract_address));
+            let storage_root = ContractsStateRoots::<T>::get(contract_address).unwrap_or_default();
+            let nonce: Nonce = Nonces::<T>::get(contract_address);
+            update::<PedersenHasher, _>(
+                &mut contracts_trie,
+                felt_to_u256(contract_address.0.key()),
+                calculate_contract_state_hash(&class_hash, storage_root.0, &nonce),
+            );
+        }
+
+        let mut classes_trie = PalletTrie::<T>::new(TrieId::Classes, accesses);
+        for (class_hash, compiled_class_hash) in &state_diff.declared_classes {
+            update::<PoseidonHasher, _>(
+                &mut classes_trie,
+                felt_to_u256(&class_hash.0),
+                calculate_class_commitment_leaf(compiled_class_hash),
+            );
+        }
+
+        calculate_global_state_root(
+            root_hash::<PedersenHasher, _>(&contracts_trie),
+            root_hash::<PoseidonHasher, _>(&classes_trie),
+        )
+    }
+
+    /// Builds the state tries of the chains started before they were maintained, the migration to
+    /// the storage version 1 of the pallet.
+    ///
+    /// Returns the weight of the migration, which reads the whole state when the tries have to be
+    /// built.
+    pub(crate) fn migrate_to_state_tries() -> Weight {
+        let mut accesses = StorageAccesses { reads: 1, writes: 0 };
+        if Self::on_chain_storage_version() >= 1 {
+            return accesses.weight::<T>();
+        }
+
+        accesses.reads += 1;
+        if StateTrieRoots::<T>::iter_keys().next().is_none() {
+            let (global_state_root, build_accesses) = Self::build_state_tries();
+            accesses += build_accesses;
+            log::info!(target: crate::LOG_TARGET, "Built the state tries, global state root: {global_state_root:?}");
+        }
+        StorageVersion::new(1).put::<Self>();
+        accesses.writes += 1;
+
+        accesses.weight::<T>()
+    }
+
+    /// Builds the state tries from the whole state, returning the global state root and the
+    /// accesses to the storage it took.
+    pub(crate) fn build_state_tries() -> (StarkHash, StorageAccesses) {
+        let mut state = BlockStateDiff::default();
+        for ((contract_address, key), value) in StorageView::<T>::iter() {
+            state.storage_diffs.entry(contract_address).or_default().insert(key, value);
+        }
+        state.deployed_contracts = ContractClassHashes::<T>::iter()
+            .map(|(contract_address, class_hash)| (contract_address, ClassHash(class_hash)))
+            .collect();
+        state.declared_classes = CompiledClassHashes::<T>::iter()
+            .map(|(class_hash, compiled_class_hash)| (ClassHash(class_hash), compiled_class_hash))
+            .collect();
+        state.nonces = Nonces::<T>::iter().collect();
+
+        let state_entries = state.storage_diffs.values().map(|updates| updates.len()).sum::<usize>()
+            + state.deployed_contracts.len()
+            + state.declared_classes.len()
+            + state.nonces.len();
+        let accesses = Cell::new(StorageAccesses { reads: state_entries as u64, writes: 0 });
+        let global_state_root = Self::update_state_tries_counting_accesses(&state, &accesses);
+
+        (global_state_root, accesses.get())
+    }
+}
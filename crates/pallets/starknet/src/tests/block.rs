@@ -4,11 +4,12 @@ use assert_matches::assert_matches;
 use blockifier::blockifier::block::GasPrices;
 use blockifier::transaction::objects::FeeType;
 use frame_support::assert_ok;
+use mp_block::L1DataAvailabilityMode;
 use mp_digest_log::{ensure_log, find_starknet_block};
 use mp_starknet_inherent::DEFAULT_SEQUENCER_ADDRESS;
 use starknet_api::block::{BlockNumber, BlockTimestamp};
 use starknet_api::core::{ChainId, ContractAddress, Nonce, PatriciaKey};
-use starknet_api::hash::StarkFelt;
+use starknet_api::hash::{StarkFelt, StarkHash};
 
 use super::mock::default_mock::*;
 use super::mock::*;
@@ -85,6 +86,47 @@ fn store_block_with_pending_transactions_works() {
     });
 }
 
+#[test]
+fn store_block_commits_to_transactions_events_and_state_diff() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let chain_id = Starknet::chain_id();
+        let header = System::finalize();
+        const BLOCK_NUMBER: u64 = 1;
+        System::initialize(&BLOCK_NUMBER, &header.hash(), &Default::default());
+
+        let transaction = get_invoke_dummy(chain_id, Nonce(StarkFelt::ZERO));
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        Starknet::store_block(BLOCK_NUMBER);
+        let digest = frame_system::Pallet::<MockRuntime>::digest();
+        let block = find_starknet_block(&digest).unwrap();
+        let header = block.header();
+
+        assert_ne!(header.transaction_commitment, StarkHash::ZERO);
+        assert_ne!(header.event_commitment, StarkHash::ZERO);
+        assert_ne!(header.receipt_commitment, StarkHash::ZERO);
+        // At least the sender nonce and the fee transfer balances
+        assert!(header.state_diff_length >= 3);
+        assert_eq!(header.l1_da_mode, L1DataAvailabilityMode::Blob);
+        // The state diff is kept until the next block, for the client to store it
+        let state_diff = Starknet::last_state_diff();
+        assert_eq!(state_diff.commitment(), header.state_diff_commitment);
+        assert_eq!(state_diff.len(), header.state_diff_length);
+
+        // The pending state diff is reset for the next block
+        let header = System::finalize();
+        System::initialize(&(BLOCK_NUMBER + 1), &header.hash(), &Default::default());
+        Starknet::store_block(BLOCK_NUMBER + 1);
+        let digest = frame_system::Pallet::<MockRuntime>::digest();
+        let block = find_starknet_block(&digest).unwrap();
+
+        assert_eq!(block.header().transaction_commitment, StarkHash::ZERO);
+        assert_eq!(block.header().state_diff_length, 0);
+        assert!(Starknet::last_state_diff().is_empty());
+        assert_eq!(block.header().parent_block_hash, Starknet::block_hash(BLOCK_NUMBER).into());
+    });
+}
+
 #[test]
 fn get_block_context_works() {
     new_test_ext::<MockRuntime>().execute_with(|| {
@@ -0,0 +1,149 @@
+use frame_support::assert_ok;
+use frame_support::traits::UnfilteredDispatchable;
+use mp_transactions::execution::TransactionRejection;
+use sp_runtime::traits::ValidateUnsigned;
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionValidityError};
+use starknet_api::core::{ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::bouncer::{BlockResources, BuiltinCount};
+use crate::tests::get_invoke_dummy;
+use crate::{Call, PendingBlockResources};
+
+const NONCE_ZERO: Nonce = Nonce(StarkFelt::ZERO);
+
+fn invoke_call() -> Call<MockRuntime> {
+    Call::invoke { transaction: get_invoke_dummy(Starknet::chain_id(), NONCE_ZERO) }
+}
+
+/// The resources used by the dummy invoke transaction.
+fn invoke_resources() -> BlockResources {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), get_invoke_dummy(Starknet::chain_id(), NONCE_ZERO)));
+        Starknet::pending_block_resources()
+    })
+}
+
+#[test]
+fn block_resources_are_summed_and_compared_to_limits() {
+    let resources = BlockResources {
+        n_steps: 10,
+        builtins: BuiltinCount { range_check: 2, ..Default::default() },
+        state_diff_size: 3,
+        ..Default::default()
+    };
+
+    let total = resources.saturating_add(&resources);
+    assert_eq!(total.n_steps, 20);
+    assert_eq!(total.builtins.range_check, 4);
+    assert_eq!(total.state_diff_size, 6);
+    assert_eq!(total.saturating_sub(&resources), resources);
+    assert_eq!(BlockResources::MAX.saturating_add(&resources), BlockResources::MAX);
+
+    assert!(resources.fits_in(&resources));
+    assert!(total.fits_in(&BlockResources::MAX));
+    assert!(!total.fits_in(&resources));
+    assert!(
+        !BlockResources { builtins: BuiltinCount { range_check: 3, ..Default::default() }, ..resources }
+            .fits_in(&resources)
+    );
+}
+
+#[test]
+fn executed_transactions_use_block_resources() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        assert_eq!(Starknet::pending_block_resources(), BlockResources::default());
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), get_invoke_dummy(Starknet::chain_id(), NONCE_ZERO)));
+
+        let resources = Starknet::pending_block_resources();
+        assert!(resources.n_steps > 0);
+        assert!(resources.builtins.range_check > 0);
+        assert!(resources.state_diff_size > 0);
+
+        run_to_block(3);
+        assert_eq!(Starknet::pending_block_resources(), BlockResources::default());
+    });
+}
+
+#[test]
+fn transaction_exceeding_the_block_resources_left_is_deferred() {
+    let resources = invoke_resources();
+    BlockResourceLimits::set(resources);
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let call = invoke_call();
+        let used = BlockResources { n_steps: 1, ..Default::default() };
+
+        PendingBlockResources::<MockRuntime>::put(used);
+        assert_eq!(
+            Starknet::pre_dispatch(&call),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources))
+        );
+        // The execution is rolled back
+        assert_eq!(Starknet::pending_block_resources(), used);
+        assert!(Starknet::pending().is_empty());
+
+        run_to_block(3);
+        assert_ok!(Starknet::pre_dispatch(&call));
+        assert_eq!(Starknet::pending_block_resources(), resources);
+    });
+}
+
+#[test]
+fn pre_dispatched_transaction_is_executed_once() {
+    let resources = invoke_resources();
+    BlockResourceLimits::set(resources);
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let call = invoke_call();
+
+        assert_ok!(Starknet::pre_dispatch(&call));
+        assert_eq!(Starknet::pending().len(), 1);
+
+        // The dispatch keeps the execution of `pre_dispatch`, executing the transaction again
+        // would fail on its nonce
+        assert_ok!(call.dispatch_bypass_filter(RuntimeOrigin::none()));
+        assert_eq!(Starknet::pending().len(), 1);
+        assert_eq!(Starknet::pending_block_resources(), resources);
+    });
+}
+
+#[test]
+fn transaction_exceeding_the_block_resource_limits_is_invalid() {
+    let resources = invoke_resources();
+    BlockResourceLimits::set(BlockResources { state_diff_size: resources.state_diff_size - 1, ..resources });
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_eq!(
+            Starknet::pre_dispatch(&invoke_call()),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Custom(
+                TransactionRejection::ExceedsBlockResourceLimits.code()
+            )))
+        );
+    });
+}
+
+#[test]
+fn failing_transaction_is_left_to_the_dispatch() {
+    BlockResourceLimits::set(BlockResources::default());
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let mut transaction = get_invoke_dummy(Starknet::chain_id(), NONCE_ZERO);
+        if let starknet_api::transaction::InvokeTransaction::V1(tx) = &mut transaction.tx {
+            tx.sender_address = ContractAddress(PatriciaKey(StarkFelt::from(0xdead_u128)));
+        };
+
+        assert_ok!(Starknet::pre_dispatch(&Call::invoke { transaction }));
+    });
+}
@@ -4,6 +4,7 @@ use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress};
 
 use super::mock::default_mock;
 use super::utils::get_contract_class;
+use crate::types::TransactionFilterRules;
 use crate::GenesisConfig;
 
 #[test]
@@ -83,3 +84,17 @@ fn check_genesis_storage() {
     assert_eq!(t.top, t_2.top, "Storage top are not equal");
     assert_eq!(t.children_default, t_2.children_default, "Storage children are not equal");
 }
+
+#[test]
+fn genesis_sets_the_chain_parameters() {
+    let mut t = frame_system::GenesisConfig::<default_mock::MockRuntime>::default().build_storage().unwrap();
+    let rules = TransactionFilterRules { max_calldata_length: Some(1), ..Default::default() };
+    let genesis: GenesisConfig<default_mock::MockRuntime> =
+        GenesisConfig { transaction_filter_rules: rules.clone(), control_accounts: vec![3], ..Default::default() };
+    genesis.assimilate_storage(&mut t).unwrap();
+
+    sp_io::TestExternalities::from(t).execute_with(|| {
+        assert_eq!(default_mock::Starknet::transaction_filter_rules(), rules);
+        assert_eq!(default_mock::Starknet::control_accounts(), vec![3]);
+    });
+}
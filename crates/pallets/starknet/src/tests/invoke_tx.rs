@@ -102,6 +102,22 @@ fn given_hardcoded_contract_run_invoke_tx_then_it_works() {
     });
 }
 
+#[test]
+fn given_hardcoded_contract_run_invoke_tx_then_its_execution_resources_are_recorded() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let transaction = get_invoke_dummy(Starknet::chain_id(), NONCE_ZERO);
+        let tx_hash = transaction.tx_hash;
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        let execution_resources = Starknet::tx_execution_resources(tx_hash).unwrap();
+        assert!(execution_resources.get("n_steps").is_some_and(|n_steps| *n_steps > 0));
+        assert_eq!(Starknet::tx_receipt_resources(tx_hash).actual_fee, Fee(0x2f8));
+    });
+}
+
 #[test]
 fn given_hardcoded_contract_run_invoke_tx_then_event_is_emitted() {
     new_test_ext::<MockRuntime>().execute_with(|| {
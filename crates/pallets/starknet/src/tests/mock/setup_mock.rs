@@ -8,11 +8,14 @@ macro_rules! mock_runtime {
     ($mock_runtime:ident, $disable_transaction_fee:expr, $disable_nonce_validation: expr) => {
 		pub mod $mock_runtime {
 			use frame_support::parameter_types;
-			use frame_support::traits::{ConstU16, ConstU64};
+			use frame_support::traits::{ConstU16, ConstU64, EitherOfDiverse, SortedMembers};
 			use sp_core::H256;
 			use sp_runtime::traits::{BlakeTwo256, IdentityLookup};
 			use {crate as pallet_starknet, frame_system as system};
-			use crate::{ InherentUpdate, SequencerAddress};
+			use crate::{InherentUpdate, NonceValidationDisabled, SequencerAddress, TransactionFeeDisabled};
+			use crate::bouncer::BlockResources;
+			use crate::control_accounts::ControlAccountsOf;
+			use crate::transaction_filters::{AllowDeclarers, AllowDeployAccountClassHashes, DenySenders, MaxCalldataLength};
 			use frame_support::traits::Hooks;
 			use mp_starknet_inherent::DEFAULT_SEQUENCER_ADDRESS;
             use mp_felt::Felt252Wrapper;
@@ -70,26 +73,36 @@ macro_rules! mock_runtime {
 			parameter_types! {
 				pub const UnsignedPriority: u64 = 1 << 20;
 				pub const TransactionLongevity: u64 = u64::MAX;
-				pub const DisableTransactionFee: bool = $disable_transaction_fee;
-                pub const DisableNonceValidation: bool = $disable_nonce_validation;
-				pub const ProtocolVersion: u8 = 0;
+				pub const ProtocolVersion: u8 = mp_block::PROTOCOL_VERSION_0_13_2;
 				pub const ProgramHash: Felt252Wrapper = mp_program_hash::SN_OS_PROGRAM_HASH;
 				pub const L1GasPrices: GasPrices = GasPrices { eth_l1_gas_price: unsafe { NonZeroU128::new_unchecked(10) }, strk_l1_gas_price: unsafe { NonZeroU128::new_unchecked(10) }, eth_l1_data_gas_price: unsafe { NonZeroU128::new_unchecked(10) }, strk_l1_data_gas_price: unsafe { NonZeroU128::new_unchecked(10) } };
 				pub ExecutionConstants: Arc<VersionedConstants> = Arc::new(VersionedConstants::latest_constants().clone());
+				pub static BlockResourceLimits: BlockResources = BlockResources::MAX;
             }
 
 			impl pallet_starknet::Config for MockRuntime {
+				type RuntimeEvent = RuntimeEvent;
+				type ControlOrigin = EitherOfDiverse<frame_system::EnsureRoot<u64>, frame_system::EnsureSignedBy<ControlAccountsOf<Self>, u64>>;
+				type AuthorityId = u64;
+				type AuthorityOrigin = frame_system::EnsureSignedBy<MockAuthorities, u64>;
 				type TimestampProvider = Timestamp;
 				type UnsignedPriority = UnsignedPriority;
 				type TransactionLongevity = TransactionLongevity;
-				type DisableTransactionFee = DisableTransactionFee;
-                type DisableNonceValidation = DisableNonceValidation;
 				type ProtocolVersion = ProtocolVersion;
 				type ProgramHash = ProgramHash;
 				type ExecutionConstants = ExecutionConstants;
-				type InvokeTransactionFilter = ();
-				type DeclareTransactionFilter = ();
-				type DeployAccountTransactionFilter = ();
+				type BlockResourceLimits = BlockResourceLimits;
+				type InvokeTransactionFilter = (DenySenders<Self>, MaxCalldataLength<Self>);
+				type DeclareTransactionFilter = (DenySenders<Self>, AllowDeclarers<Self>);
+				type DeployAccountTransactionFilter = (DenySenders<Self>, AllowDeployAccountClassHashes<Self>, MaxCalldataLength<Self>);
+			}
+
+			/// Accounts allowed to register a sequencer address.
+			pub struct MockAuthorities;
+			impl SortedMembers<u64> for MockAuthorities {
+				fn sorted_members() -> Vec<u64> {
+					vec![1, 2]
+				}
 			}
 
 			/// Run to block n.
@@ -112,6 +125,8 @@ macro_rules! mock_runtime {
 				InherentUpdate::<MockRuntime>::put(true);
 				let default_addr = ContractAddress(PatriciaKey(StarkFelt::new(DEFAULT_SEQUENCER_ADDRESS).unwrap()));
 				SequencerAddress::<MockRuntime>::put(default_addr);
+				TransactionFeeDisabled::<MockRuntime>::put($disable_transaction_fee);
+				NonceValidationDisabled::<MockRuntime>::put($disable_nonce_validation);
 				System::set_block_number(0);
 				run_to_block(n);
 			}
@@ -25,6 +25,7 @@ use crate::tests::utils::sign_message_hash;
 use crate::{Config, Nonces};
 
 mod account_helper;
+mod bouncer;
 mod build_genesis_config;
 mod call_contract;
 mod declare_tx;
@@ -36,10 +37,16 @@ mod genesis_block;
 mod invoke_tx;
 mod l1_handler_validation;
 mod l1_message;
+mod paymaster;
 mod query_tx;
 mod re_execute_transactions;
+mod runtime_parameters;
 mod send_message;
+mod sequencer_addresses;
 mod starknet_inherent;
+mod state_adapter;
+mod state_trie;
+mod transaction_filters;
 
 mod block;
 mod constants;
@@ -0,0 +1,412 @@
+use std::sync::Arc;
+
+use blockifier::abi::abi_utils::selector_from_name;
+use blockifier::execution::contract_class::ClassInfo;
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transactions::{DeclareTransaction, InvokeTransaction};
+use frame_support::{assert_err, assert_noop, assert_ok};
+use mp_felt::Felt252Wrapper;
+use mp_simulations::SimulationFlags;
+use mp_transactions::compute_hash::ComputeTransactionHash;
+use mp_transactions::execution::PaymasterConfig;
+use sp_runtime::traits::BadOrigin;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{Calldata, ContractAddressSalt, Fee, PaymasterData, TransactionSignature};
+use starknet_core::utils::{get_udc_deployed_address, UdcUniqueSettings, UdcUniqueness};
+use starknet_crypto::FieldElement;
+
+use super::constants::{
+    BLOCKIFIER_ACCOUNT_ADDRESS, ETH_FEE_TOKEN_ADDRESS, STRK_FEE_TOKEN_ADDRESS, UDC_ADDRESS, UDC_SELECTOR,
+};
+use super::mock::default_mock::*;
+use super::mock::*;
+use super::utils::{
+    get_balance_contract_call, get_contract_class, set_account_erc20_balance_to_zero, sign_message_hash,
+};
+use crate::tests::{get_invoke_v3_dummy, set_infinite_tokens};
+use crate::{Error, Event};
+
+/// Class hash of the sample paymaster of `cairo-contracts/src/cairo_1/paymaster`.
+const PAYMASTER_CLASS_HASH: &str = "0x05873f461940883b8a069c52d2095d7551ae6115eaecad94d68ce36605851d40";
+const PAYMASTER_COMPILED_CLASS_HASH: &str = "0x0329fb7397de172016c6dbe8e20527e4550e72fdcc9e05eacf8eafc94d2ffb87";
+
+fn sender_address() -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::try_from(BLOCKIFIER_ACCOUNT_ADDRESS).unwrap()))
+}
+
+fn strk_fee_token_address() -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::try_from(STRK_FEE_TOKEN_ADDRESS).unwrap()))
+}
+
+/// The account declaring, deploying and administering the paymaster.
+fn owner_address() -> ContractAddress {
+    get_account_address(None, AccountType::V1(AccountTypeV1Inner::NoValidate))
+}
+
+fn invoke_from_owner(nonce: u128, calldata: Vec<StarkFelt>) -> InvokeTransaction {
+    let mut tx = starknet_api::transaction::InvokeTransactionV1 {
+        sender_address: owner_address(),
+        signature: TransactionSignature(vec![]),
+        nonce: Nonce(StarkFelt::from(nonce)),
+        calldata: Calldata(Arc::new(calldata)),
+        max_fee: Fee(u128::MAX),
+    };
+    let tx_hash = tx.compute_hash(Starknet::chain_id(), false);
+    tx.signature = sign_message_hash(tx_hash);
+
+    InvokeTransaction { tx: starknet_api::transaction::InvokeTransaction::V1(tx), tx_hash, only_query: false }
+}
+
+/// Declares the sample paymaster and deploys it with the UDC, using the nonces 0 and 1 of its
+/// owner. The paymaster is given enough fee tokens to pay for the transactions it sponsors.
+fn deploy_paymaster() -> ContractAddress {
+    let class_hash = ClassHash(StarkFelt::try_from(PAYMASTER_CLASS_HASH).unwrap());
+    let paymaster_class = get_contract_class("Paymaster.casm.json", 1);
+
+    let mut declare_tx = starknet_api::transaction::DeclareTransactionV2 {
+        sender_address: owner_address(),
+        class_hash,
+        compiled_class_hash: CompiledClassHash(StarkFelt::try_from(PAYMASTER_COMPILED_CLASS_HASH).unwrap()),
+        nonce: Nonce(StarkFelt::ZERO),
+        max_fee: Fee(u128::MAX),
+        signature: TransactionSignature(vec![]),
+    };
+    let tx_hash = declare_tx.compute_hash(Starknet::chain_id(), false);
+    declare_tx.signature = sign_message_hash(tx_hash);
+    let transaction = DeclareTransaction::new(
+        starknet_api::transaction::DeclareTransaction::V2(declare_tx),
+        tx_hash,
+        ClassInfo::new(&paymaster_class, 1, 1).unwrap(),
+    )
+    .unwrap();
+    assert_ok!(Starknet::declare(RuntimeOrigin::none(), transaction));
+
+    let salt = ContractAddressSalt(StarkFelt::ZERO);
+    let transaction = invoke_from_owner(
+        1,
+        vec![
+            StarkFelt::ONE,
+            StarkFelt::try_from(UDC_ADDRESS).unwrap(),  // udc address
+            StarkFelt::try_from(UDC_SELECTOR).unwrap(), // deployContract selector
+            StarkFelt::from(5u128),                     // calldata len
+            class_hash.0,                               // contract class hash
+            salt.0,                                     // salt
+            StarkFelt::ONE,                             // unique
+            StarkFelt::ONE,                             // constructor calldata len
+            *owner_address().0.key(),                   // owner
+        ],
+    );
+    assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+    let address = get_udc_deployed_address(
+        Felt252Wrapper::from(salt).into(),
+        Felt252Wrapper::from(class_hash).into(),
+        &UdcUniqueness::Unique(UdcUniqueSettings {
+            deployer_address: Felt252Wrapper::from(owner_address()).into(),
+            udc_contract_address: FieldElement::from_hex_be(UDC_ADDRESS).unwrap(),
+        }),
+        &[Felt252Wrapper::from(owner_address()).into()],
+    );
+    let address: ContractAddress = Felt252Wrapper::from(address).into();
+    assert_eq!(Starknet::contract_class_hash_by_address(address), class_hash);
+
+    set_infinite_tokens::<MockRuntime>(&address);
+
+    address
+}
+
+/// Returns the calldata of a multicall to `set_sponsored` on the paymaster.
+fn set_sponsored_calldata(paymaster: ContractAddress, account: ContractAddress, sponsored: bool) -> Vec<StarkFelt> {
+    vec![
+        StarkFelt::ONE,
+        *paymaster.0.key(),
+        selector_from_name("set_sponsored").0,
+        StarkFelt::TWO,
+        *account.0.key(),
+        if sponsored { StarkFelt::ONE } else { StarkFelt::ZERO },
+    ]
+}
+
+fn is_sponsored(paymaster: ContractAddress, account: ContractAddress) -> bool {
+    let retdata = Starknet::call_contract(
+        paymaster,
+        selector_from_name("is_sponsored"),
+        Calldata(Arc::new(vec![*account.0.key()])),
+    )
+    .unwrap();
+
+    retdata == vec![Felt252Wrapper::ONE]
+}
+
+/// Returns a v3 invoke transaction sponsored by `paymaster`, sent by an account without funds.
+fn get_sponsored_invoke(paymaster: ContractAddress) -> InvokeTransaction {
+    let chain_id = Starknet::chain_id();
+    let mut transaction = get_invoke_v3_dummy(chain_id, Nonce(StarkFelt::ZERO));
+    if let starknet_api::transaction::InvokeTransaction::V3(tx) = &mut transaction.tx {
+        tx.paymaster_data = PaymasterData(vec![*paymaster.0.key()]);
+    }
+    transaction.tx_hash = transaction.tx.compute_hash(chain_id, false);
+
+    set_account_erc20_balance_to_zero(sender_address(), strk_fee_token_address());
+    set_account_erc20_balance_to_zero(
+        sender_address(),
+        ContractAddress(PatriciaKey(StarkFelt::try_from(ETH_FEE_TOKEN_ADDRESS).unwrap())),
+    );
+
+    transaction
+}
+
+/// Deploys the paymaster, sponsors the sender and enables sponsoring.
+fn setup_sponsoring() -> ContractAddress {
+    let paymaster = deploy_paymaster();
+    assert_ok!(Starknet::invoke(
+        RuntimeOrigin::none(),
+        invoke_from_owner(2, set_sponsored_calldata(paymaster, sender_address(), true))
+    ));
+    assert!(is_sponsored(paymaster, sender_address()));
+    assert_ok!(Starknet::set_paymaster_config(RuntimeOrigin::root(), Some(PaymasterConfig::default())));
+
+    paymaster
+}
+
+#[test]
+fn set_paymaster_config_requires_control_origin() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let config = PaymasterConfig::default();
+
+        assert_noop!(Starknet::set_paymaster_config(RuntimeOrigin::signed(1), Some(config.clone())), BadOrigin);
+        assert_noop!(Starknet::set_paymaster_config(RuntimeOrigin::none(), None), BadOrigin);
+
+        assert_ok!(Starknet::set_paymaster_config(RuntimeOrigin::root(), Some(config.clone())));
+        assert_eq!(Starknet::paymaster_config(), Some(config.clone()));
+        System::assert_last_event(Event::PaymasterConfigUpdated { config: Some(config) }.into());
+
+        assert_ok!(Starknet::set_paymaster_config(RuntimeOrigin::root(), None));
+        assert_eq!(Starknet::paymaster_config(), None);
+    });
+}
+
+#[test]
+fn only_the_owner_of_the_paymaster_sponsors_accounts() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = deploy_paymaster();
+        assert!(!is_sponsored(paymaster, sender_address()));
+
+        // The call of another account reverts
+        let mut transaction = get_invoke_v3_dummy(Starknet::chain_id(), Nonce(StarkFelt::ZERO));
+        if let starknet_api::transaction::InvokeTransaction::V3(tx) = &mut transaction.tx {
+            tx.calldata = Calldata(Arc::new(set_sponsored_calldata(paymaster, sender_address(), true)[1..].to_vec()));
+        }
+        transaction.tx_hash = transaction.tx.compute_hash(Starknet::chain_id(), false);
+        let tx_hash = transaction.tx_hash;
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+        assert!(Starknet::tx_revert_error(tx_hash).is_some());
+        assert!(!is_sponsored(paymaster, sender_address()));
+
+        assert_ok!(Starknet::invoke(
+            RuntimeOrigin::none(),
+            invoke_from_owner(2, set_sponsored_calldata(paymaster, sender_address(), true))
+        ));
+        assert!(is_sponsored(paymaster, sender_address()));
+
+        assert_ok!(Starknet::invoke(
+            RuntimeOrigin::none(),
+            invoke_from_owner(3, set_sponsored_calldata(paymaster, sender_address(), false))
+        ));
+        assert!(!is_sponsored(paymaster, sender_address()));
+    });
+}
+
+#[test]
+fn paymaster_data_is_ignored_when_sponsoring_is_disabled() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = setup_sponsoring();
+        assert_ok!(Starknet::set_paymaster_config(RuntimeOrigin::root(), None));
+
+        let transaction = get_sponsored_invoke(paymaster);
+
+        // The sender has to pay and cannot
+        assert_err!(
+            Starknet::invoke(RuntimeOrigin::none(), transaction),
+            Error::<MockRuntime>::TransactionExecutionFailed
+        );
+    });
+}
+
+#[test]
+fn sponsored_transaction_is_paid_by_the_paymaster() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = setup_sponsoring();
+
+        let transaction = get_sponsored_invoke(paymaster);
+        let tx_hash = transaction.tx_hash;
+        let paymaster_initial_balance = get_balance_contract_call(paymaster, strk_fee_token_address());
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        let paymaster_final_balance = get_balance_contract_call(paymaster, strk_fee_token_address());
+        assert!(paymaster_final_balance[0] < paymaster_initial_balance[0]);
+        assert_eq!(
+            get_balance_contract_call(sender_address(), strk_fee_token_address()),
+            vec![Felt252Wrapper::ZERO, Felt252Wrapper::ZERO]
+        );
+        // The fee transfer is the last event: `Transfer(from, to, amount)`
+        let fee_transfer = Starknet::tx_events(tx_hash).pop().unwrap();
+        assert_eq!(fee_transfer.from_address, strk_fee_token_address());
+        assert_eq!(fee_transfer.content.data.0[0], *paymaster.0.key());
+        // The sender nonce is still incremented
+        assert_eq!(Starknet::nonce(sender_address()), Nonce(StarkFelt::ONE));
+    });
+}
+
+#[test]
+fn transaction_is_rejected_when_the_paymaster_does_not_sponsor_the_sender() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = setup_sponsoring();
+        assert_ok!(Starknet::invoke(
+            RuntimeOrigin::none(),
+            invoke_from_owner(3, set_sponsored_calldata(paymaster, sender_address(), false))
+        ));
+
+        let transaction = get_sponsored_invoke(paymaster);
+
+        assert_err!(
+            Starknet::invoke(RuntimeOrigin::none(), transaction.clone()),
+            Error::<MockRuntime>::TransactionExecutionFailed
+        );
+        // Skipping the validation does not skip the paymaster one
+        let transaction = AccountTransaction::Invoke(transaction);
+        let skip_validate = SimulationFlags { validate: false, charge_fee: false };
+        assert!(Starknet::estimate_fee(vec![transaction.clone()], &skip_validate).unwrap().is_err());
+        assert!(Starknet::simulate_transactions(vec![transaction], &skip_validate).unwrap().pop().unwrap().is_err());
+    });
+}
+
+#[test]
+fn sponsored_transaction_fails_when_the_paymaster_is_not_deployed() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        assert_ok!(Starknet::set_paymaster_config(RuntimeOrigin::root(), Some(PaymasterConfig::default())));
+
+        let transaction = get_sponsored_invoke(ContractAddress(PatriciaKey(StarkFelt::from(0xdeadu128))));
+
+        assert_err!(
+            Starknet::invoke(RuntimeOrigin::none(), transaction),
+            Error::<MockRuntime>::TransactionExecutionFailed
+        );
+    });
+}
+
+#[test]
+fn estimation_and_simulation_of_sponsored_transaction_match_its_execution() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = setup_sponsoring();
+
+        let transaction = get_sponsored_invoke(paymaster);
+        let tx_hash = transaction.tx_hash;
+        let account_transaction = AccountTransaction::Invoke(transaction.clone());
+
+        let fee_estimate = Starknet::estimate_fee(vec![account_transaction.clone()], &Default::default())
+            .unwrap()
+            .unwrap()
+            .pop()
+            .unwrap();
+        let simulation = Starknet::simulate_transactions(vec![account_transaction.clone()], &Default::default())
+            .unwrap()
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert_eq!(simulation.fee_estimate.overall_fee, fee_estimate.overall_fee);
+
+        let execution_info = simulation.execution_info;
+        let paymaster_validation = execution_info.validate_call_info.unwrap().inner_calls.pop().unwrap();
+        assert_eq!(paymaster_validation.call.storage_address, paymaster);
+        assert_eq!(execution_info.fee_transfer_call_info.unwrap().call.caller_address, paymaster);
+
+        // Without the account validation, the paymaster validation is still run
+        let skip_validate = SimulationFlags { validate: false, charge_fee: true };
+        let simulation =
+            Starknet::simulate_transactions(vec![account_transaction], &skip_validate).unwrap().pop().unwrap().unwrap();
+        assert_eq!(simulation.execution_info.validate_call_info.unwrap().call.storage_address, paymaster);
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        let fee_transfer = Starknet::tx_events(tx_hash).pop().unwrap();
+        assert_eq!(fee_transfer.content.data.0[2], StarkFelt::from(execution_info.actual_fee.0));
+    });
+}
+
+#[test]
+fn paymaster_and_its_validation_are_reported_for_sponsored_transactions() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = setup_sponsoring();
+
+        let transaction = get_sponsored_invoke(paymaster);
+        let tx_hash = transaction.tx_hash;
+        let account_transaction = AccountTransaction::Invoke(transaction.clone());
+
+        let fee_estimate = Starknet::estimate_fee(vec![account_transaction.clone()], &Default::default())
+            .unwrap()
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(fee_estimate.paymaster, Some(paymaster));
+
+        let simulation = Starknet::simulate_transactions(vec![account_transaction.clone()], &Default::default())
+            .unwrap()
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert_eq!(simulation.fee_estimate.paymaster, Some(paymaster));
+        let paymaster_validation = simulation.paymaster.unwrap();
+        assert_eq!(paymaster_validation.address, paymaster);
+        let validate_call_info = paymaster_validation.validate_call_info.unwrap();
+        assert_eq!(validate_call_info.call.storage_address, paymaster);
+        assert!(!validate_call_info.execution.failed);
+
+        // The paymaster validation is found as well when it is the only validation run
+        let skip_validate = SimulationFlags { validate: false, charge_fee: true };
+        let simulation =
+            Starknet::simulate_transactions(vec![account_transaction], &skip_validate).unwrap().pop().unwrap().unwrap();
+        assert_eq!(simulation.paymaster.unwrap().validate_call_info.unwrap().call.storage_address, paymaster);
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+        assert_eq!(Starknet::tx_paymaster(tx_hash), Some(paymaster));
+    });
+}
+
+#[test]
+fn no_paymaster_is_reported_for_transactions_paid_by_their_sender() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        let paymaster = setup_sponsoring();
+
+        let transaction = invoke_from_owner(3, set_sponsored_calldata(paymaster, sender_address(), false));
+        let tx_hash = transaction.tx_hash;
+        let account_transaction = AccountTransaction::Invoke(transaction.clone());
+
+        let fee_estimate = Starknet::estimate_fee(vec![account_transaction.clone()], &Default::default())
+            .unwrap()
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(fee_estimate.paymaster, None);
+        let simulation = Starknet::simulate_transactions(vec![account_transaction], &Default::default())
+            .unwrap()
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert!(simulation.paymaster.is_none());
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+        assert_eq!(Starknet::tx_paymaster(tx_hash), None);
+    });
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use frame_support::traits::{Get, Hooks};
+use frame_support::{assert_err, assert_noop, assert_ok};
+use mp_starknet_inherent::StarknetInherentData;
+use sp_runtime::traits::BadOrigin;
+use starknet_api::core::{ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::tests::constants::BLOCKIFIER_ACCOUNT_ADDRESS;
+use crate::tests::get_invoke_dummy;
+use crate::{Error, Event};
+
+const VERSIONED_CONSTANTS: &str = include_str!("../../../../runtime/resources/versioned_constants.json");
+
+#[test]
+fn set_runtime_parameters_requires_control_origin() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let address = ContractAddress(PatriciaKey(StarkFelt::ONE));
+
+        assert_noop!(Starknet::set_transaction_fee_disabled(RuntimeOrigin::signed(1), true), BadOrigin);
+        assert_noop!(Starknet::set_nonce_validation_disabled(RuntimeOrigin::signed(1), true), BadOrigin);
+        assert_noop!(Starknet::set_execution_constants(RuntimeOrigin::signed(1), None), BadOrigin);
+        assert_noop!(Starknet::set_sequencer_address(RuntimeOrigin::none(), Some(address)), BadOrigin);
+    });
+}
+
+#[test]
+fn set_transaction_fee_disabled_updates_storage() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+        assert!(!Starknet::is_transaction_fee_disabled());
+
+        assert_ok!(Starknet::set_transaction_fee_disabled(RuntimeOrigin::root(), true));
+
+        assert!(Starknet::is_transaction_fee_disabled());
+        System::assert_last_event(Event::TransactionFeeDisabledUpdated { disabled: true }.into());
+    });
+}
+
+#[test]
+fn set_nonce_validation_disabled_accepts_any_nonce() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let sender_address = ContractAddress(PatriciaKey(StarkFelt::try_from(BLOCKIFIER_ACCOUNT_ADDRESS).unwrap()));
+        let transaction = get_invoke_dummy(Starknet::chain_id(), Nonce(StarkFelt::from(5u128)));
+
+        assert_err!(
+            Starknet::invoke(RuntimeOrigin::none(), transaction.clone()),
+            Error::<MockRuntime>::TransactionExecutionFailed
+        );
+
+        assert_ok!(Starknet::set_nonce_validation_disabled(RuntimeOrigin::root(), true));
+        System::assert_last_event(Event::NonceValidationDisabledUpdated { disabled: true }.into());
+
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+        // The account nonce is still incremented
+        assert_eq!(Starknet::nonce(sender_address), Nonce(StarkFelt::ONE));
+    });
+}
+
+#[test]
+fn set_execution_constants_rejects_invalid_json() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_noop!(
+            Starknet::set_execution_constants(RuntimeOrigin::root(), Some(b"not json".to_vec())),
+            Error::<MockRuntime>::InvalidExecutionConstants
+        );
+    });
+}
+
+#[test]
+fn set_execution_constants_overrides_runtime_constants() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let mut constants: serde_json::Value = serde_json::from_str(VERSIONED_CONSTANTS).unwrap();
+        constants["invoke_tx_max_n_steps"] = 42.into();
+
+        let json = serde_json::to_vec(&constants).unwrap();
+        assert_ok!(Starknet::set_execution_constants(RuntimeOrigin::root(), Some(json)));
+        System::assert_last_event(Event::ExecutionConstantsUpdated { overridden: true }.into());
+        assert_eq!(Starknet::execution_constants().invoke_tx_max_n_steps, 42);
+        // The stored constants are only decoded once
+        assert!(Arc::ptr_eq(&Starknet::execution_constants(), &Starknet::execution_constants()));
+
+        constants["invoke_tx_max_n_steps"] = 43.into();
+        let json = serde_json::to_vec(&constants).unwrap();
+        assert_ok!(Starknet::set_execution_constants(RuntimeOrigin::root(), Some(json)));
+        assert_eq!(Starknet::execution_constants().invoke_tx_max_n_steps, 43);
+
+        assert_ok!(Starknet::set_execution_constants(RuntimeOrigin::root(), None));
+        System::assert_last_event(Event::ExecutionConstantsUpdated { overridden: false }.into());
+        assert_eq!(
+            Starknet::execution_constants().invoke_tx_max_n_steps,
+            ExecutionConstants::get().invoke_tx_max_n_steps
+        );
+    });
+}
+
+#[test]
+fn decoded_execution_constants_are_cleared_on_runtime_upgrade() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let mut constants: serde_json::Value = serde_json::from_str(VERSIONED_CONSTANTS).unwrap();
+        constants["invoke_tx_max_n_steps"] = 44.into();
+        let json = serde_json::to_vec(&constants).unwrap();
+        assert_ok!(Starknet::set_execution_constants(RuntimeOrigin::root(), Some(json)));
+        let decoded = Starknet::execution_constants();
+
+        Starknet::on_runtime_upgrade();
+        let decoded_after_upgrade = Starknet::execution_constants();
+        assert!(!Arc::ptr_eq(&decoded, &decoded_after_upgrade));
+        assert_eq!(decoded_after_upgrade.invoke_tx_max_n_steps, 44);
+    });
+}
+
+#[test]
+fn sequencer_address_override_takes_precedence_over_inherent() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let address = ContractAddress(PatriciaKey(StarkFelt::ONE));
+        assert_ok!(Starknet::set_sequencer_address(RuntimeOrigin::root(), Some(address)));
+        System::assert_last_event(Event::SequencerAddressUpdated { address: Some(address) }.into());
+
+        System::set_block_number(3);
+        assert_ok!(Starknet::set_starknet_inherent_data(RuntimeOrigin::none(), StarknetInherentData::default()));
+        assert_eq!(Starknet::sequencer_address(), address);
+    });
+}
+
+#[test]
+fn control_accounts_can_update_runtime_parameters() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_noop!(Starknet::set_control_accounts(RuntimeOrigin::signed(3), vec![3]), BadOrigin);
+
+        assert_ok!(Starknet::set_control_accounts(RuntimeOrigin::root(), vec![3]));
+        System::assert_last_event(Event::ControlAccountsUpdated { accounts: vec![3] }.into());
+
+        assert_ok!(Starknet::set_transaction_fee_disabled(RuntimeOrigin::signed(3), true));
+        assert!(Starknet::is_transaction_fee_disabled());
+        assert_noop!(Starknet::set_transaction_fee_disabled(RuntimeOrigin::signed(4), false), BadOrigin);
+
+        // A control account can hand over the control of the chain
+        assert_ok!(Starknet::set_control_accounts(RuntimeOrigin::signed(3), vec![4]));
+        assert_noop!(Starknet::set_transaction_fee_disabled(RuntimeOrigin::signed(3), false), BadOrigin);
+        assert_ok!(Starknet::set_transaction_fee_disabled(RuntimeOrigin::signed(4), false));
+    });
+}
@@ -0,0 +1,77 @@
+use frame_support::{assert_noop, assert_ok};
+use sp_runtime::traits::BadOrigin;
+use starknet_api::core::{ContractAddress, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::{Error, Event};
+
+fn address(value: u128) -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::from(value)))
+}
+
+#[test]
+fn register_sequencer_address_requires_authority_origin() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_noop!(Starknet::register_sequencer_address(RuntimeOrigin::signed(3), address(1)), BadOrigin);
+        assert_noop!(Starknet::register_sequencer_address(RuntimeOrigin::root(), address(1)), BadOrigin);
+        assert_noop!(Starknet::register_sequencer_address(RuntimeOrigin::none(), address(1)), BadOrigin);
+    });
+}
+
+#[test]
+fn authority_can_register_and_rotate_its_sequencer_address() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_ok!(Starknet::register_sequencer_address(RuntimeOrigin::signed(1), address(1)));
+        assert_eq!(Starknet::authority_sequencer_address(1), Some(address(1)));
+        System::assert_last_event(
+            Event::SequencerAddressRegistered { authority: 1, address: address(1), previous: None }.into(),
+        );
+
+        assert_ok!(Starknet::register_sequencer_address(RuntimeOrigin::signed(1), address(2)));
+        assert_eq!(Starknet::authority_sequencer_address(1), Some(address(2)));
+        System::assert_last_event(
+            Event::SequencerAddressRegistered { authority: 1, address: address(2), previous: Some(address(1)) }.into(),
+        );
+
+        // Other authorities are not affected
+        assert_eq!(Starknet::authority_sequencer_address(2), None);
+    });
+}
+
+#[test]
+fn unregister_sequencer_address_requires_same_authority_or_control_origin() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_ok!(Starknet::register_sequencer_address(RuntimeOrigin::signed(1), address(1)));
+        assert_ok!(Starknet::register_sequencer_address(RuntimeOrigin::signed(2), address(2)));
+
+        assert_noop!(Starknet::unregister_sequencer_address(RuntimeOrigin::signed(2), 1), BadOrigin);
+        assert_noop!(Starknet::unregister_sequencer_address(RuntimeOrigin::signed(3), 1), BadOrigin);
+
+        assert_ok!(Starknet::unregister_sequencer_address(RuntimeOrigin::signed(1), 1));
+        assert_eq!(Starknet::authority_sequencer_address(1), None);
+        System::assert_last_event(Event::SequencerAddressUnregistered { authority: 1 }.into());
+
+        assert_ok!(Starknet::unregister_sequencer_address(RuntimeOrigin::root(), 2));
+        assert_eq!(Starknet::authority_sequencer_address(2), None);
+    });
+}
+
+#[test]
+fn unregister_unknown_sequencer_address_fails() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        assert_noop!(
+            Starknet::unregister_sequencer_address(RuntimeOrigin::signed(1), 1),
+            Error::<MockRuntime>::SequencerAddressNotRegistered
+        );
+    });
+}
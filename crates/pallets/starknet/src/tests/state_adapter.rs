@@ -0,0 +1,54 @@
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::StateReader;
+use frame_support::traits::Hooks;
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use super::utils::get_contract_class;
+use crate::blockifier_state_adapter::BlockifierStateAdapter;
+use crate::ContractClasses;
+
+// The decoded classes are shared by the tests running concurrently, so each test uses its own class
+// hashes
+
+#[test]
+fn decoded_classes_are_only_served_to_the_blocks_declaring_them() {
+    let class_hash = ClassHash(StarkFelt::from(0xdec0de01u128));
+    let class = get_contract_class("ERC20.json", 0);
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        ContractClasses::<MockRuntime>::insert(class_hash.0, class.clone());
+        let state = BlockifierStateAdapter::<MockRuntime>::default();
+        assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), class);
+    });
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let state = BlockifierStateAdapter::<MockRuntime>::default();
+        assert!(matches!(
+            state.get_compiled_contract_class(class_hash),
+            Err(StateError::UndeclaredClassHash(undeclared)) if undeclared == class_hash
+        ));
+    });
+}
+
+#[test]
+fn decoded_classes_are_cleared_on_runtime_upgrade() {
+    let class_hash = ClassHash(StarkFelt::from(0xdec0de02u128));
+    let class = get_contract_class("ERC20.json", 0);
+    let migrated_class = get_contract_class("NoValidateAccount.json", 0);
+
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        ContractClasses::<MockRuntime>::insert(class_hash.0, class.clone());
+        let state = BlockifierStateAdapter::<MockRuntime>::default();
+        assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), class);
+
+        // A migration rewriting the class isn't seen until the runtime upgrade is over
+        ContractClasses::<MockRuntime>::insert(class_hash.0, migrated_class.clone());
+        assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), class);
+
+        Starknet::on_runtime_upgrade();
+        assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), migrated_class);
+    });
+}
@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use frame_support::assert_ok;
+use frame_support::traits::{GetStorageVersion, Hooks, StorageVersion};
+use mp_block::commitments::{
+    calculate_class_commitment_leaf, calculate_contract_state_hash, calculate_global_state_root,
+};
+use mp_digest_log::find_starknet_block;
+use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
+use mp_hashers::HasherT;
+use sp_core::U256;
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_crypto::FieldElement;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::state_trie::{root_hash, update, NodePosition, TrieNode, TrieStorage, STATE_TRIE_HEIGHT};
+use crate::tests::get_invoke_dummy;
+use crate::{
+    CompiledClassHashes, ContractClassHashes, ContractsStateRoots, Nonces, StateTrieNodes, StateTrieRoots, StorageView,
+};
+
+/// A trie kept in memory.
+#[derive(Default)]
+struct MemoryTrie {
+    root: Option<NodePosition>,
+    nodes: BTreeMap<(u8, U256), TrieNode>,
+}
+
+impl TrieStorage for MemoryTrie {
+    fn root(&self) -> Option<NodePosition> {
+        self.root
+    }
+
+    fn set_root(&mut self, root: Option<NodePosition>) {
+        self.root = root;
+    }
+
+    fn node(&self, position: &NodePosition) -> TrieNode {
+        self.nodes[&(position.height, position.path)].clone()
+    }
+
+    fn insert_node(&mut self, position: NodePosition, node: TrieNode) {
+        self.nodes.insert((position.height, position.path), node);
+    }
+
+    fn remove_node(&mut self, position: &NodePosition) {
+        self.nodes.remove(&(position.height, position.path));
+    }
+}
+
+/// Computes the root of the trie holding `leaves` from scratch.
+fn full_root<H: HasherT>(leaves: &BTreeMap<U256, FieldElement>) -> FieldElement {
+    let leaves = leaves.iter().filter(|(_, value)| **value != FieldElement::ZERO).collect::<Vec<_>>();
+    if leaves.is_empty() {
+        return FieldElement::ZERO;
+    }
+    let (node_hash, path, length) = full_subtree::<H>(&leaves, STATE_TRIE_HEIGHT);
+    edge_hash::<H>(node_hash, path, length)
+}
+
+/// Returns the hash of the highest binary node or leaf of the subtree, along with the path and
+/// length of the edge leading to it.
+fn full_subtree<H: HasherT>(leaves: &[(&U256, &FieldElement)], height: u8) -> (FieldElement, U256, u8) {
+    if height == 0 {
+        return (*leaves[0].1, U256::zero(), 0);
+    }
+    let (left, right) = leaves.split_at(leaves.partition_point(|(key, _)| !key.bit(usize::from(height - 1))));
+    if left.is_empty() || right.is_empty() {
+        let (node_hash, path, length) = full_subtree::<H>(leaves, height - 1);
+        let direction = if left.is_empty() { U256::one() } else { U256::zero() };
+        return (node_hash, (direction << usize::from(length)) | path, length + 1);
+    }
+    let (left_hash, left_path, left_length) = full_subtree::<H>(left, height - 1);
+    let (right_hash, right_path, right_length) = full_subtree::<H>(right, height - 1);
+    let node_hash = H::hash_elements(
+        edge_hash::<H>(left_hash, left_path, left_length),
+        edge_hash::<H>(right_hash, right_path, right_length),
+    );
+    (node_hash, U256::zero(), 0)
+}
+
+fn edge_hash<H: HasherT>(node_hash: FieldElement, path: U256, length: u8) -> FieldElement {
+    if length == 0 {
+        return node_hash;
+    }
+    H::hash_elements(node_hash, felt(path)) + FieldElement::from(length)
+}
+
+fn felt(value: U256) -> FieldElement {
+    Felt252Wrapper::try_from(value).unwrap().0
+}
+
+fn key(value: &StarkFelt) -> U256 {
+    U256::from_big_endian(value.bytes())
+}
+
+#[test]
+fn single_leaf_trie_root_is_an_edge_to_the_leaf() {
+    let mut trie = MemoryTrie::default();
+    update::<PedersenHasher, _>(&mut trie, U256::from(5), FieldElement::from(7u8));
+
+    let expected =
+        PedersenHasher::hash_elements(FieldElement::from(7u8), FieldElement::from(5u8)) + FieldElement::from(251u8);
+    assert_eq!(root_hash::<PedersenHasher, _>(&trie), expected);
+}
+
+#[test]
+fn two_leaves_trie_root_is_an_edge_to_their_binary_node() {
+    let mut trie = MemoryTrie::default();
+    update::<PedersenHasher, _>(&mut trie, U256::from(1), FieldElement::from(2u8));
+    update::<PedersenHasher, _>(&mut trie, U256::from(0), FieldElement::from(1u8));
+
+    let binary_node = PedersenHasher::hash_elements(FieldElement::from(1u8), FieldElement::from(2u8));
+    let expected = PedersenHasher::hash_elements(binary_node, FieldElement::ZERO) + FieldElement::from(250u8);
+    assert_eq!(root_hash::<PedersenHasher, _>(&trie), expected);
+}
+
+#[test]
+fn incremental_updates_match_a_full_rebuild() {
+    let mut trie = MemoryTrie::default();
+    let mut leaves = BTreeMap::new();
+    // Keys drawn from a small set spread over the whole key space, so that leaves are updated,
+    // removed and inserted again
+    let keys = (0..24u64)
+        .map(|i| {
+            (U256::from(i.wrapping_mul(0x9e37_79b9_7f4a_7c15)) << (i as usize * 7 % 187)) & ((U256::one() << 251) - 1)
+        })
+        .collect::<Vec<_>>();
+    let mut seed = 42u64;
+    for step in 0..500u64 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let key = keys[(seed >> 33) as usize % keys.len()];
+        // One update out of four removes the leaf
+        let value = if (seed >> 20) % 4 == 0 { FieldElement::ZERO } else { FieldElement::from(step + 1) };
+
+        update::<PoseidonHasher, _>(&mut trie, key, value);
+        leaves.insert(key, value);
+
+        assert_eq!(root_hash::<PoseidonHasher, _>(&trie), full_root::<PoseidonHasher>(&leaves), "step {step}");
+    }
+
+    for key in keys {
+        update::<PoseidonHasher, _>(&mut trie, key, FieldElement::ZERO);
+    }
+    assert_eq!(root_hash::<PoseidonHasher, _>(&trie), FieldElement::ZERO);
+    assert!(trie.root.is_none());
+    assert!(trie.nodes.is_empty());
+}
+
+#[test]
+fn block_state_root_commits_to_the_whole_state() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let chain_id = Starknet::chain_id();
+        let header = System::finalize();
+        const BLOCK_NUMBER: u64 = 1;
+        System::initialize(&BLOCK_NUMBER, &header.hash(), &Default::default());
+
+        let transaction = get_invoke_dummy(chain_id, Nonce(StarkFelt::ZERO));
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+
+        Starknet::store_block(BLOCK_NUMBER);
+        let digest = frame_system::Pallet::<MockRuntime>::digest();
+        let block = find_starknet_block(&digest).unwrap();
+
+        // Rebuild the tries from the genesis state and the block updates
+        let mut storages = BTreeMap::<ContractAddress, BTreeMap<U256, FieldElement>>::new();
+        for ((contract_address, storage_key), value) in StorageView::<MockRuntime>::iter() {
+            storages
+                .entry(contract_address)
+                .or_default()
+                .insert(key(storage_key.0.key()), Felt252Wrapper::from(value).0);
+        }
+        let contract_addresses = storages
+            .keys()
+            .copied()
+            .chain(ContractClassHashes::<MockRuntime>::iter_keys())
+            .chain(Nonces::<MockRuntime>::iter_keys())
+            .collect::<BTreeSet<_>>();
+        let contracts = contract_addresses
+            .into_iter()
+            .map(|contract_address| {
+                let storage_root =
+                    storages.get(&contract_address).map(full_root::<PedersenHasher>).unwrap_or(FieldElement::ZERO);
+                let leaf = calculate_contract_state_hash(
+                    &ClassHash(ContractClassHashes::<MockRuntime>::get(contract_address)),
+                    storage_root,
+                    &Nonces::<MockRuntime>::get(contract_address),
+                );
+                (key(contract_address.0.key()), leaf)
+            })
+            .collect();
+        let classes = CompiledClassHashes::<MockRuntime>::iter()
+            .map(|(class_hash, compiled_class_hash)| {
+                (key(&class_hash), calculate_class_commitment_leaf(&compiled_class_hash))
+            })
+            .collect();
+        let expected =
+            calculate_global_state_root(full_root::<PedersenHasher>(&contracts), full_root::<PoseidonHasher>(&classes));
+
+        assert_ne!(block.header().global_state_root, StarkHash::ZERO);
+        assert_eq!(block.header().global_state_root, expected);
+    });
+}
+
+#[test]
+fn state_tries_built_from_the_whole_state_match_the_maintained_ones() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let maintained_root = Starknet::update_state_tries(&Default::default());
+
+        let _ = StateTrieNodes::<MockRuntime>::clear(u32::MAX, None);
+        let _ = StateTrieRoots::<MockRuntime>::clear(u32::MAX, None);
+        let _ = ContractsStateRoots::<MockRuntime>::clear(u32::MAX, None);
+
+        assert_ne!(maintained_root, StarkHash::ZERO);
+        assert_eq!(Starknet::build_state_tries().0, maintained_root);
+    });
+}
+
+#[test]
+fn state_tries_are_built_once_by_the_storage_migration() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        let maintained_root = Starknet::update_state_tries(&Default::default());
+        let state_entries = StorageView::<MockRuntime>::iter().count()
+            + ContractClassHashes::<MockRuntime>::iter().count()
+            + CompiledClassHashes::<MockRuntime>::iter().count()
+            + Nonces::<MockRuntime>::iter().count();
+
+        // A chain started before the state tries were maintained
+        let _ = StateTrieNodes::<MockRuntime>::clear(u32::MAX, None);
+        let _ = StateTrieRoots::<MockRuntime>::clear(u32::MAX, None);
+        StorageVersion::new(0).put::<Starknet>();
+
+        let (_, accesses) = Starknet::build_state_tries();
+        assert!(accesses.reads >= state_entries as u64);
+        assert!(accesses.writes >= StateTrieNodes::<MockRuntime>::iter().count() as u64);
+
+        let _ = StateTrieNodes::<MockRuntime>::clear(u32::MAX, None);
+        let _ = StateTrieRoots::<MockRuntime>::clear(u32::MAX, None);
+        Starknet::on_runtime_upgrade();
+        assert_eq!(Starknet::on_chain_storage_version(), 1);
+        assert_eq!(Starknet::update_state_tries(&Default::default()), maintained_root);
+
+        // The tries are not built again by the next upgrades
+        let _ = StateTrieRoots::<MockRuntime>::clear(u32::MAX, None);
+        Starknet::on_runtime_upgrade();
+        assert!(StateTrieRoots::<MockRuntime>::iter_keys().next().is_none());
+    });
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeSet;
+
+use frame_support::{assert_err, assert_noop, assert_ok};
+use mp_transactions::execution::TransactionRejection;
+use sp_runtime::traits::{BadOrigin, ValidateUnsigned};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionSource, TransactionValidity, TransactionValidityError,
+};
+use starknet_api::core::{ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+
+use super::mock::default_mock::*;
+use super::mock::*;
+use crate::tests::constants::{BLOCKIFIER_ACCOUNT_ADDRESS, SALT};
+use crate::tests::{get_declare_dummy, get_deploy_account_dummy, get_invoke_dummy, set_infinite_tokens};
+use crate::types::TransactionFilterRules;
+use crate::{Call, Error, Event};
+
+fn blockifier_account_address() -> ContractAddress {
+    ContractAddress(PatriciaKey(StarkFelt::try_from(BLOCKIFIER_ACCOUNT_ADDRESS).unwrap()))
+}
+
+fn rejected(reason: TransactionRejection) -> TransactionValidity {
+    Err(TransactionValidityError::Invalid(InvalidTransaction::Custom(reason.code())))
+}
+
+#[test]
+fn set_transaction_filter_rules_requires_control_origin() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let rules = TransactionFilterRules { max_calldata_length: Some(1), ..Default::default() };
+
+        assert_noop!(Starknet::set_transaction_filter_rules(RuntimeOrigin::none(), rules.clone()), BadOrigin);
+        assert_noop!(Starknet::set_transaction_filter_rules(RuntimeOrigin::signed(1), rules.clone()), BadOrigin);
+
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules.clone()));
+        assert_eq!(Starknet::transaction_filter_rules(), rules);
+        System::assert_last_event(Event::TransactionFilterRulesUpdated { rules }.into());
+    });
+}
+
+#[test]
+fn set_transaction_filter_rules_is_not_a_valid_unsigned_transaction() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let call = Call::set_transaction_filter_rules { rules: TransactionFilterRules::default() };
+
+        assert_eq!(
+            Starknet::validate_unsigned(TransactionSource::External, &call),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Call))
+        );
+    });
+}
+
+#[test]
+fn default_rules_accept_transactions() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let transaction = get_invoke_dummy(Starknet::chain_id(), Nonce(StarkFelt::ZERO));
+
+        assert!(Starknet::validate_unsigned(TransactionSource::External, &Call::invoke { transaction }).is_ok());
+    });
+}
+
+#[test]
+fn denied_sender_is_rejected() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let rules = TransactionFilterRules {
+            denied_senders: BTreeSet::from([blockifier_account_address()]),
+            ..Default::default()
+        };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+
+        let transaction = get_invoke_dummy(Starknet::chain_id(), Nonce(StarkFelt::ZERO));
+
+        assert_eq!(
+            Starknet::validate_unsigned(
+                TransactionSource::External,
+                &Call::invoke { transaction: transaction.clone() }
+            ),
+            rejected(TransactionRejection::SenderDenied)
+        );
+        assert_err!(
+            Starknet::invoke(RuntimeOrigin::none(), transaction),
+            Error::<MockRuntime>::TransactionRejectedByFilter
+        );
+    });
+}
+
+#[test]
+fn calldata_longer_than_max_is_rejected() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let transaction = get_invoke_dummy(Starknet::chain_id(), Nonce(StarkFelt::ZERO));
+        let calldata_len = transaction.tx.calldata().0.len() as u32;
+
+        let rules = TransactionFilterRules { max_calldata_length: Some(calldata_len - 1), ..Default::default() };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+        assert_eq!(
+            Starknet::validate_unsigned(
+                TransactionSource::External,
+                &Call::invoke { transaction: transaction.clone() }
+            ),
+            rejected(TransactionRejection::CalldataTooLarge)
+        );
+
+        let rules = TransactionFilterRules { max_calldata_length: Some(calldata_len), ..Default::default() };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+        assert_ok!(Starknet::invoke(RuntimeOrigin::none(), transaction));
+    });
+}
+
+#[test]
+fn declarer_not_in_allowlist_is_rejected() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let account_type = AccountType::V0(AccountTypeV0Inner::NoValidate);
+        let transaction = get_declare_dummy(Starknet::chain_id(), Nonce(StarkFelt::ZERO), account_type);
+        let declarer = transaction.tx.sender_address();
+
+        let rules = TransactionFilterRules { allowed_declarers: Some(BTreeSet::new()), ..Default::default() };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+        assert_eq!(
+            Starknet::validate_unsigned(
+                TransactionSource::External,
+                &Call::declare { transaction: transaction.clone() }
+            ),
+            rejected(TransactionRejection::DeclarerNotAllowed)
+        );
+
+        let rules =
+            TransactionFilterRules { allowed_declarers: Some(BTreeSet::from([declarer])), ..Default::default() };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+        assert!(Starknet::validate_unsigned(TransactionSource::External, &Call::declare { transaction }).is_ok());
+    });
+}
+
+#[test]
+fn deploy_account_class_hash_not_in_allowlist_is_rejected() {
+    new_test_ext::<MockRuntime>().execute_with(|| {
+        basic_test_setup(2);
+
+        let account_type = AccountType::V0(AccountTypeV0Inner::NoValidate);
+        let transaction = get_deploy_account_dummy(Starknet::chain_id(), Nonce(StarkFelt::ZERO), *SALT, account_type);
+        let class_hash = transaction.tx.class_hash();
+        set_infinite_tokens::<MockRuntime>(&transaction.contract_address);
+
+        let rules =
+            TransactionFilterRules { allowed_deploy_account_class_hashes: Some(BTreeSet::new()), ..Default::default() };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+        assert_err!(
+            Starknet::deploy_account(RuntimeOrigin::none(), transaction.clone()),
+            Error::<MockRuntime>::TransactionRejectedByFilter
+        );
+
+        let rules = TransactionFilterRules {
+            allowed_deploy_account_class_hashes: Some(BTreeSet::from([class_hash])),
+            ..Default::default()
+        };
+        assert_ok!(Starknet::set_transaction_filter_rules(RuntimeOrigin::root(), rules));
+        assert_ok!(Starknet::deploy_account(RuntimeOrigin::none(), transaction));
+    });
+}
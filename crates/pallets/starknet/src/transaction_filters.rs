@@ -0,0 +1,142 @@
+//! Transaction filters whose parameters live in the pallet storage.
+//!
+//! Each filter reads the [`crate::types::TransactionFilterRules`] stored by the pallet, so the
+//! policy can be updated on a live chain through `set_transaction_filter_rules`. Filters can be
+//! combined by grouping them in a tuple, e.g. `(DenySenders<Runtime>, MaxCalldataLength<Runtime>)`.
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transactions::{DeclareTransaction, DeployAccountTransaction, InvokeTransaction};
+use mp_transactions::execution::{GetCalldataLen, TransactionFilter, TransactionRejection};
+use sp_std::marker::PhantomData;
+use starknet_api::core::ContractAddress;
+
+use crate::{Config, Pallet};
+
+/// Rejects transactions sent by an address of `denied_senders`.
+///
+/// For deploy account transactions, the sender is the address of the deployed account.
+pub struct DenySenders<T>(PhantomData<T>);
+
+impl<T: Config> DenySenders<T> {
+    fn check_sender(sender_address: &ContractAddress) -> Result<(), TransactionRejection> {
+        if Pallet::<T>::transaction_filter_rules().denied_senders.contains(sender_address) {
+            Err(TransactionRejection::SenderDenied)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: Config> TransactionFilter<InvokeTransaction> for DenySenders<T> {
+    fn is_valid(transaction: &InvokeTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &InvokeTransaction) -> Result<(), TransactionRejection> {
+        Self::check_sender(&transaction.tx.sender_address())
+    }
+}
+
+impl<T: Config> TransactionFilter<DeclareTransaction> for DenySenders<T> {
+    fn is_valid(transaction: &DeclareTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &DeclareTransaction) -> Result<(), TransactionRejection> {
+        Self::check_sender(&transaction.tx.sender_address())
+    }
+}
+
+impl<T: Config> TransactionFilter<DeployAccountTransaction> for DenySenders<T> {
+    fn is_valid(transaction: &DeployAccountTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &DeployAccountTransaction) -> Result<(), TransactionRejection> {
+        Self::check_sender(&transaction.contract_address)
+    }
+}
+
+/// Only accepts declare transactions sent by an address of `allowed_declarers`.
+/// Anyone can declare when it is `None`.
+pub struct AllowDeclarers<T>(PhantomData<T>);
+
+impl<T: Config> TransactionFilter<DeclareTransaction> for AllowDeclarers<T> {
+    fn is_valid(transaction: &DeclareTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &DeclareTransaction) -> Result<(), TransactionRejection> {
+        match Pallet::<T>::transaction_filter_rules().allowed_declarers {
+            Some(allowed) if !allowed.contains(&transaction.tx.sender_address()) => {
+                Err(TransactionRejection::DeclarerNotAllowed)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Only accepts deploy account transactions for a class hash of
+/// `allowed_deploy_account_class_hashes`. Any class can be deployed when it is `None`.
+pub struct AllowDeployAccountClassHashes<T>(PhantomData<T>);
+
+impl<T: Config> TransactionFilter<DeployAccountTransaction> for AllowDeployAccountClassHashes<T> {
+    fn is_valid(transaction: &DeployAccountTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &DeployAccountTransaction) -> Result<(), TransactionRejection> {
+        match Pallet::<T>::transaction_filter_rules().allowed_deploy_account_class_hashes {
+            Some(allowed) if !allowed.contains(&transaction.tx.class_hash()) => {
+                Err(TransactionRejection::ClassHashNotAllowed)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Rejects transactions whose calldata (constructor calldata for deploy account transactions)
+/// is longer than `max_calldata_length`.
+pub struct MaxCalldataLength<T>(PhantomData<T>);
+
+impl<T: Config> MaxCalldataLength<T> {
+    fn check_length(calldata_len: usize) -> Result<(), TransactionRejection> {
+        match Pallet::<T>::transaction_filter_rules().max_calldata_length {
+            Some(max) if calldata_len > max as usize => Err(TransactionRejection::CalldataTooLarge),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T: Config> TransactionFilter<InvokeTransaction> for MaxCalldataLength<T> {
+    fn is_valid(transaction: &InvokeTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &InvokeTransaction) -> Result<(), TransactionRejection> {
+        Self::check_length(transaction.get_calldata_len())
+    }
+}
+
+impl<T: Config> TransactionFilter<DeployAccountTransaction> for MaxCalldataLength<T> {
+    fn is_valid(transaction: &DeployAccountTransaction) -> bool {
+        Self::check(transaction).is_ok()
+    }
+
+    fn check(transaction: &DeployAccountTransaction) -> Result<(), TransactionRejection> {
+        Self::check_length(transaction.get_calldata_len())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Runs the transaction filters configured in the runtime against `transaction`.
+    ///
+    /// Filters are enforced when a transaction enters the pool, when it is dispatched and when it
+    /// is simulated, but not when re-executing transactions already included in a block.
+    pub fn check_transaction_filters(transaction: &AccountTransaction) -> Result<(), TransactionRejection> {
+        match transaction {
+            AccountTransaction::Declare(tx) => T::DeclareTransactionFilter::check(tx),
+            AccountTransaction::DeployAccount(tx) => T::DeployAccountTransactionFilter::check(tx),
+            AccountTransaction::Invoke(tx) => T::InvokeTransactionFilter::check(tx),
+        }
+    }
+}
@@ -4,7 +4,7 @@ use blockifier::transaction::errors::{TransactionExecutionError, TransactionPreV
 use blockifier::transaction::transaction_execution::Transaction;
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use frame_support::traits::EnsureOrigin;
-use mp_transactions::execution::Validate;
+use mp_transactions::execution::{NonceValidation, Paymaster, Validate};
 
 use super::*;
 
@@ -64,20 +64,46 @@ impl<T: Config> Pallet<T> {
             Transaction::AccountTransaction(transaction) => {
                 let mut state = BlockifierStateAdapter::<T>::default();
                 let block_context = Self::get_block_context();
-                let charge_fee = !<T as Config>::DisableTransactionFee::get();
+                let charge_fee = !Self::is_transaction_fee_disabled();
                 let tx_context = Arc::new(block_context.to_tx_context(transaction));
-                let string_nonce_checking = false;
+                let paymaster = Paymaster::from_tx_info(&tx_context.tx_info, Self::paymaster_config().as_ref())
+                    .map_err(|e| {
+                        log!(debug, "Invalid paymaster in pre_validate_unsigned_tx: {:?}", e);
+                        InvalidTransaction::BadProof
+                    })?;
+                let nonce_validation = if Self::is_nonce_validation_disabled() {
+                    NonceValidation::Disabled
+                } else {
+                    NonceValidation::Lenient
+                };
+
+                let paymaster = paymaster.as_ref();
 
                 match transaction {
-                    AccountTransaction::Declare(transaction) => {
-                        Validate::perform_pre_validation_stage::<T::DeclareTransactionFilter>(transaction, &mut state, tx_context, string_nonce_checking, charge_fee)
-                    }
-                    AccountTransaction::DeployAccount(transaction) => {
-                        Validate::perform_pre_validation_stage::<T::DeployAccountTransactionFilter>(transaction, &mut state, tx_context, string_nonce_checking, charge_fee)
-                    }
-                    AccountTransaction::Invoke(transaction) => {
-                        Validate::perform_pre_validation_stage::<T::InvokeTransactionFilter>(transaction, &mut state, tx_context, string_nonce_checking, charge_fee)
-                    }
+                    AccountTransaction::Declare(transaction) => Validate::perform_pre_validation_stage(
+                        transaction,
+                        &mut state,
+                        tx_context,
+                        nonce_validation,
+                        charge_fee,
+                        paymaster,
+                    ),
+                    AccountTransaction::DeployAccount(transaction) => Validate::perform_pre_validation_stage(
+                        transaction,
+                        &mut state,
+                        tx_context,
+                        nonce_validation,
+                        charge_fee,
+                        paymaster,
+                    ),
+                    AccountTransaction::Invoke(transaction) => Validate::perform_pre_validation_stage(
+                        transaction,
+                        &mut state,
+                        tx_context,
+                        nonce_validation,
+                        charge_fee,
+                        paymaster,
+                    ),
                 }
                 // TODO: have more granular error mapping
                 .map_err(|e| {
@@ -99,17 +125,26 @@ impl<T: Config> Pallet<T> {
                 let mut inital_gas = block_context.versioned_constants().tx_initial_gas();
                 let mut resources = ExecutionResources::default();
 
+                let tx_context = Arc::new(block_context.to_tx_context(transaction));
+
                 let validation_result = match transaction {
                     AccountTransaction::Declare(tx) => {
-                        let tx_context = Arc::new(block_context.to_tx_context(tx));
-                        tx.run_validate_entrypoint(&mut state, tx_context, &mut resources, &mut inital_gas, true)
+                        tx.run_validate_entrypoint(&mut state, tx_context.clone(), &mut resources, &mut inital_gas, true)
                     }
                     AccountTransaction::DeployAccount(_) => return Ok(()),
                     AccountTransaction::Invoke(tx) => {
-                        let tx_context = Arc::new(block_context.to_tx_context(tx));
-                        tx.run_validate_entrypoint(&mut state, tx_context, &mut resources, &mut inital_gas, true)
+                        tx.run_validate_entrypoint(&mut state, tx_context.clone(), &mut resources, &mut inital_gas, true)
                     }
-                };
+                }
+                // The paymaster of a sponsored transaction must also accept to pay for it
+                .and_then(|call_info| {
+                    match Paymaster::from_tx_info(&tx_context.tx_info, Self::paymaster_config().as_ref())? {
+                        Some(paymaster) => paymaster
+                            .run_validate_entrypoint(&mut state, tx_context, &mut resources, &mut inital_gas, true)
+                            .map(|_| call_info),
+                        None => Ok(call_info),
+                    }
+                });
 
                 // handle the case where we the user sent both its deploy and first tx at the same time
                 // we assume that the deploy tx is also in the pool and will therefore be executed before
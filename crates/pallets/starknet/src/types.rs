@@ -1,5 +1,5 @@
 //! Starknet pallet custom types.
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use blockifier::execution::contract_class::ContractClass;
 use mp_felt::Felt252Wrapper;
@@ -43,3 +43,21 @@ pub struct TransactionOutput {
     pub messages_sent: Vec<MessageToL1>,
     pub events: Vec<Event>,
 }
+
+/// Parameters of the storage-backed transaction filters.
+/// See the [`crate::transaction_filters`] module.
+#[derive(
+    Clone, Debug, Default, PartialEq, Eq, parity_scale_codec::Encode, parity_scale_codec::Decode, scale_info::TypeInfo,
+)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransactionFilterRules {
+    /// Addresses allowed to send declare transactions. Anyone can declare when `None`.
+    pub allowed_declarers: Option<BTreeSet<ContractAddress>>,
+    /// Addresses not allowed to send any transaction.
+    pub denied_senders: BTreeSet<ContractAddress>,
+    /// Account class hashes that can be deployed with a deploy account transaction.
+    /// Any class can be deployed when `None`.
+    pub allowed_deploy_account_class_hashes: Option<BTreeSet<ClassHash>>,
+    /// Maximum length of the (constructor) calldata of a transaction. Unlimited when `None`.
+    pub max_calldata_length: Option<u32>,
+}
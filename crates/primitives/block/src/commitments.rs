@@ -0,0 +1,371 @@
+//! Commitments of the block header.
+//!
+//! See [the Starknet protocol specification](https://docs.starknet.io/documentation/architecture_and_concepts/Network_Architecture/header/).
+use std::collections::{BTreeMap, BTreeSet};
+
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use mp_felt::Felt252Wrapper;
+use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
+use mp_hashers::HasherT;
+use mp_transactions::execution::charged_fee;
+use sp_core::hashing::keccak_256;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{Event, Fee, MessageToL1, TransactionHash, TransactionSignature};
+use starknet_ff::FieldElement;
+
+use crate::{PROTOCOL_VERSION_0_13_1, PROTOCOL_VERSION_0_13_2};
+
+/// Height of the Patricia trees the transaction, event and receipt commitments are the root of.
+const COMMITMENT_TREE_HEIGHT: u32 = 64;
+
+/// Prefix of the state diff commitment preimage.
+const STATE_DIFF_COMMITMENT_PREFIX: &[u8] = b"STARKNET_STATE_DIFF0";
+
+/// Version prefixed to the roots of the state tries in the global state root preimage.
+const GLOBAL_STATE_VERSION: &[u8] = b"STARKNET_STATE_V0";
+
+/// Version prefixed to the compiled class hashes in the leaves of the classes trie.
+const CONTRACT_CLASS_LEAF_VERSION: &[u8] = b"CONTRACT_CLASS_LEAF_V0";
+
+/// Hash functions used to compute the block hash and its commitments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockHashVersion {
+    /// Block hash of the Madara versions predating the block commitments.
+    /// It does not commit to the state root, the transactions nor the events, and is only kept so
+    /// that the hashes of the blocks built by those versions can still be computed.
+    Legacy,
+    /// Pedersen based block hash used before Starknet 0.13.2.
+    Pedersen,
+    /// Poseidon based block hash introduced with Starknet 0.13.2.
+    Poseidon,
+}
+
+impl BlockHashVersion {
+    /// Returns the hash functions used for blocks of the given protocol version.
+    pub fn from_protocol_version(protocol_version: u8) -> Self {
+        if protocol_version >= PROTOCOL_VERSION_0_13_2 {
+            Self::Poseidon
+        } else if protocol_version >= PROTOCOL_VERSION_0_13_1 {
+            Self::Pedersen
+        } else {
+            Self::Legacy
+        }
+    }
+}
+
+/// Resources consumed by a transaction that are committed to in its receipt.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct ReceiptResources {
+    /// The fee charged for the transaction.
+    pub actual_fee: Fee,
+    /// The L1 gas consumed by the transaction.
+    pub l1_gas: u128,
+    /// The L1 data gas consumed by the transaction.
+    pub l1_data_gas: u128,
+}
+
+impl From<&TransactionExecutionInfo> for ReceiptResources {
+    fn from(execution_info: &TransactionExecutionInfo) -> Self {
+        let resources = &execution_info.actual_resources.0;
+        Self {
+            actual_fee: charged_fee(execution_info),
+            l1_gas: resources.get(blockifier::abi::constants::L1_GAS_USAGE).copied().unwrap_or_default(),
+            l1_data_gas: resources.get(blockifier::abi::constants::BLOB_GAS_USAGE).copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// The receipt fields a receipt commitment leaf is computed from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReceiptCommitmentData {
+    pub transaction_hash: TransactionHash,
+    pub resources: ReceiptResources,
+    pub messages_sent: Vec<MessageToL1>,
+    pub revert_reason: Option<String>,
+}
+
+/// State changes of a block, as committed to by the state diff commitment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct BlockStateDiff {
+    /// Class hashes of the contracts deployed or replaced in the block.
+    pub deployed_contracts: BTreeMap<ContractAddress, ClassHash>,
+    /// Cairo 1 classes declared in the block.
+    pub declared_classes: BTreeMap<ClassHash, CompiledClassHash>,
+    /// Cairo 0 classes declared in the block.
+    pub deprecated_declared_classes: BTreeSet<ClassHash>,
+    /// Storage updates of the block, by contract.
+    pub storage_diffs: BTreeMap<ContractAddress, BTreeMap<StorageKey, StarkFelt>>,
+    /// Nonces updated in the block.
+    pub nonces: BTreeMap<ContractAddress, Nonce>,
+}
+
+impl BlockStateDiff {
+    /// Returns the number of state updates in the diff.
+    pub fn len(&self) -> u64 {
+        let storage_updates: usize = self.storage_diffs.values().map(BTreeMap::len).sum();
+        (self.deployed_contracts.len()
+            + self.declared_classes.len()
+            + self.deprecated_declared_classes.len()
+            + storage_updates
+            + self.nonces.len()) as u64
+    }
+
+    /// Returns `true` if the diff does not contain any update.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Computes the Poseidon hash committing to the state diff.
+    pub fn commitment(&self) -> StarkHash {
+        let mut data = vec![FieldElement::from_byte_slice_be(STATE_DIFF_COMMITMENT_PREFIX).unwrap()];
+
+        data.push(FieldElement::from(self.deployed_contracts.len() as u64));
+        for (address, class_hash) in &self.deployed_contracts {
+            data.extend([felt(address.0.key()), felt(&class_hash.0)]);
+        }
+
+        data.push(FieldElement::from(self.declared_classes.len() as u64));
+        for (class_hash, compiled_class_hash) in &self.declared_classes {
+            data.extend([felt(&class_hash.0), felt(&compiled_class_hash.0)]);
+        }
+
+        data.push(FieldElement::from(self.deprecated_declared_classes.len() as u64));
+        data.extend(self.deprecated_declared_classes.iter().map(|class_hash| felt(&class_hash.0)));
+
+        // Placeholder for the data availability of the updates, which all go to L1
+        data.extend([FieldElement::ONE, FieldElement::ZERO]);
+
+        let storage_diffs = self.storage_diffs.iter().filter(|(_, updates)| !updates.is_empty()).collect::<Vec<_>>();
+        data.push(FieldElement::from(storage_diffs.len() as u64));
+        for (address, updates) in storage_diffs {
+            data.extend([felt(address.0.key()), FieldElement::from(updates.len() as u64)]);
+            for (key, value) in updates {
+                data.extend([felt(key.0.key()), felt(value)]);
+            }
+        }
+
+        data.push(FieldElement::from(self.nonces.len() as u64));
+        for (address, nonce) in &self.nonces {
+            data.extend([felt(address.0.key()), felt(&nonce.0)]);
+        }
+
+        Felt252Wrapper(PoseidonHasher::compute_hash_on_elements(&data)).into()
+    }
+}
+
+/// Computes the transaction commitment of a block from the hashes and signatures of its
+/// transactions.
+///
+/// The signature is `None` for the transaction types that do not have one, such as L1 handlers.
+pub fn calculate_transaction_commitment(
+    transactions: &[(TransactionHash, Option<TransactionSignature>)],
+    version: BlockHashVersion,
+) -> StarkHash {
+    match version {
+        BlockHashVersion::Legacy | BlockHashVersion::Pedersen => {
+            let leaves = transactions
+                .iter()
+                .map(|(hash, signature)| {
+                    let signature =
+                        signature.iter().flat_map(|signature| signature.0.iter()).map(felt).collect::<Vec<_>>();
+                    PedersenHasher::hash_elements(felt(&hash.0), PedersenHasher::compute_hash_on_elements(&signature))
+                })
+                .collect::<Vec<_>>();
+            calculate_commitment::<PedersenHasher>(&leaves)
+        }
+        BlockHashVersion::Poseidon => {
+            let leaves = transactions
+                .iter()
+                .map(|(hash, signature)| {
+                    let mut data = vec![felt(&hash.0)];
+                    match signature {
+                        Some(signature) => data.extend(signature.0.iter().map(felt)),
+                        None => data.push(FieldElement::ZERO),
+                    }
+                    PoseidonHasher::compute_hash_on_elements(&data)
+                })
+                .collect::<Vec<_>>();
+            calculate_commitment::<PoseidonHasher>(&leaves)
+        }
+    }
+}
+
+/// Computes the event commitment of a block from its events and the hashes of the transactions
+/// that emitted them.
+pub fn calculate_event_commitment(events: &[(TransactionHash, Event)], version: BlockHashVersion) -> StarkHash {
+    match version {
+        BlockHashVersion::Legacy | BlockHashVersion::Pedersen => {
+            let leaves = events
+                .iter()
+                .map(|(_, event)| {
+                    let keys = event.content.keys.iter().map(|key| felt(&key.0)).collect::<Vec<_>>();
+                    let data = event.content.data.0.iter().map(felt).collect::<Vec<_>>();
+                    PedersenHasher::compute_hash_on_elements(&[
+                        felt(event.from_address.0.key()),
+                        PedersenHasher::compute_hash_on_elements(&keys),
+                        PedersenHasher::compute_hash_on_elements(&data),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            calculate_commitment::<PedersenHasher>(&leaves)
+        }
+        BlockHashVersion::Poseidon => {
+            let leaves = events
+                .iter()
+                .map(|(tx_hash, event)| {
+                    let mut data = vec![felt(event.from_address.0.key()), felt(&tx_hash.0)];
+                    data.push(FieldElement::from(event.content.keys.len() as u64));
+                    data.extend(event.content.keys.iter().map(|key| felt(&key.0)));
+                    data.push(FieldElement::from(event.content.data.0.len() as u64));
+                    data.extend(event.content.data.0.iter().map(felt));
+                    PoseidonHasher::compute_hash_on_elements(&data)
+                })
+                .collect::<Vec<_>>();
+            calculate_commitment::<PoseidonHasher>(&leaves)
+        }
+    }
+}
+
+/// Computes the receipt commitment of a block.
+///
+/// Receipts were introduced in the block hash by Starknet 0.13.2, so they are always committed to
+/// with Poseidon.
+pub fn calculate_receipt_commitment(receipts: &[ReceiptCommitmentData]) -> StarkHash {
+    let leaves = receipts
+        .iter()
+        .map(|receipt| {
+            PoseidonHasher::compute_hash_on_elements(&[
+                felt(&receipt.transaction_hash.0),
+                Felt252Wrapper::from(receipt.resources.actual_fee.0).0,
+                messages_hash(&receipt.messages_sent),
+                receipt.revert_reason.as_deref().map(starknet_keccak).unwrap_or(FieldElement::ZERO),
+                // L2 gas is not charged yet
+                FieldElement::ZERO,
+                Felt252Wrapper::from(receipt.resources.l1_gas).0,
+                Felt252Wrapper::from(receipt.resources.l1_data_gas).0,
+            ])
+        })
+        .collect::<Vec<_>>();
+    calculate_commitment::<PoseidonHasher>(&leaves)
+}
+
+/// Computes the root of the height 64 binary Merkle-Patricia tree whose leaf `i` is `leaves[i]`.
+pub fn calculate_commitment<H: HasherT>(leaves: &[FieldElement]) -> StarkHash {
+    let leaves = leaves.iter().enumerate().map(|(index, leaf)| (index as u64, *leaf)).collect::<Vec<_>>();
+
+    if leaves.is_empty() {
+        return StarkHash::ZERO;
+    }
+
+    Felt252Wrapper(subtree::<H>(&leaves, COMMITMENT_TREE_HEIGHT).hash::<H>()).into()
+}
+
+/// Computes the global state root from the roots of the contracts and classes tries.
+///
+/// See [the Starknet protocol specification](https://docs.starknet.io/documentation/architecture_and_concepts/Network_Architecture/starknet-state/).
+pub fn calculate_global_state_root(contracts_trie_root: FieldElement, classes_trie_root: FieldElement) -> StarkHash {
+    // The classes trie was introduced in Starknet 0.11, the root is unchanged for older states
+    let root = if classes_trie_root == FieldElement::ZERO {
+        contracts_trie_root
+    } else {
+        PoseidonHasher::compute_hash_on_elements(&[
+            FieldElement::from_byte_slice_be(GLOBAL_STATE_VERSION).unwrap(),
+            contracts_trie_root,
+            classes_trie_root,
+        ])
+    };
+    Felt252Wrapper(root).into()
+}
+
+/// Computes the leaf of a contract in the contracts trie.
+pub fn calculate_contract_state_hash(
+    class_hash: &ClassHash,
+    storage_root: FieldElement,
+    nonce: &Nonce,
+) -> FieldElement {
+    let hash = PedersenHasher::hash_elements(felt(&class_hash.0), storage_root);
+    let hash = PedersenHasher::hash_elements(hash, felt(&nonce.0));
+    // The last element is the contract state version
+    PedersenHasher::hash_elements(hash, FieldElement::ZERO)
+}
+
+/// Computes the leaf of a Cairo 1 class in the classes trie.
+pub fn calculate_class_commitment_leaf(compiled_class_hash: &CompiledClassHash) -> FieldElement {
+    PoseidonHasher::hash_elements(
+        FieldElement::from_byte_slice_be(CONTRACT_CLASS_LEAF_VERSION).unwrap(),
+        felt(&compiled_class_hash.0),
+    )
+}
+
+/// A node of the tree, along with the edge leading to it from the closest binary node above.
+struct Subtree {
+    node_hash: FieldElement,
+    path: u64,
+    path_length: u32,
+}
+
+impl Subtree {
+    fn hash<H: HasherT>(&self) -> FieldElement {
+        if self.path_length == 0 {
+            self.node_hash
+        } else {
+            H::hash_elements(self.node_hash, FieldElement::from(self.path)) + FieldElement::from(self.path_length)
+        }
+    }
+}
+
+/// Builds the subtree of the given `height` containing `leaves`, which must be sorted by index and
+/// share the same bits above `height`.
+fn subtree<H: HasherT>(leaves: &[(u64, FieldElement)], height: u32) -> Subtree {
+    if height == 0 {
+        return Subtree { node_hash: leaves[0].1, path: 0, path_length: 0 };
+    }
+
+    let bit = 1u64 << (height - 1);
+    let (left, right) = leaves.split_at(leaves.partition_point(|(index, _)| index & bit == 0));
+
+    if left.is_empty() || right.is_empty() {
+        let child = subtree::<H>(leaves, height - 1);
+        let direction = if left.is_empty() { 1u64 } else { 0 };
+        return Subtree {
+            node_hash: child.node_hash,
+            path: (direction << child.path_length) | child.path,
+            path_length: child.path_length + 1,
+        };
+    }
+
+    let node_hash =
+        H::hash_elements(subtree::<H>(left, height - 1).hash::<H>(), subtree::<H>(right, height - 1).hash::<H>());
+    Subtree { node_hash, path: 0, path_length: 0 }
+}
+
+fn messages_hash(messages: &[MessageToL1]) -> FieldElement {
+    let mut data = vec![FieldElement::from(messages.len() as u64)];
+    for message in messages {
+        data.push(felt(message.from_address.0.key()));
+        data.push(FieldElement::from_byte_slice_be(message.to_address.0.as_bytes()).unwrap());
+        data.push(FieldElement::from(message.payload.0.len() as u64));
+        data.extend(message.payload.0.iter().map(felt));
+    }
+    PoseidonHasher::compute_hash_on_elements(&data)
+}
+
+/// Keccak256 of `value` truncated to 250 bits.
+fn starknet_keccak(value: &str) -> FieldElement {
+    let mut hash = keccak_256(value.as_bytes());
+    hash[0] &= 0x03;
+    // Safe because the hash is truncated to 250 bits
+    FieldElement::from_bytes_be(&hash).unwrap()
+}
+
+fn felt(value: &StarkFelt) -> FieldElement {
+    Felt252Wrapper::from(*value).into()
+}
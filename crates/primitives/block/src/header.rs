@@ -1,10 +1,41 @@
 use blockifier::blockifier::block::GasPrices;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
 use mp_hashers::HasherT;
 use sp_core::U256;
 use starknet_api::core::ContractAddress;
 use starknet_api::hash::StarkHash;
+use starknet_ff::FieldElement;
+
+use crate::commitments::BlockHashVersion;
+
+/// First protocol version whose blocks are hashed with the Pedersen based hash specified before
+/// Starknet 0.13.2, which commits to the state root, the transactions and the events.
+/// Blocks of protocol version 0 keep the hash of the Madara versions predating these commitments.
+pub const PROTOCOL_VERSION_0_13_1: u8 = 1;
+
+/// First protocol version whose blocks are hashed as specified by Starknet 0.13.2.
+pub const PROTOCOL_VERSION_0_13_2: u8 = 2;
+
+/// Prefix of the Starknet 0.13.2 block hash preimage.
+const BLOCK_HASH_PREFIX: &[u8] = b"STARKNET_BLOCK_HASH0";
+
+/// Starknet version committed to by the Poseidon based block hash.
+const STARKNET_VERSION_0_13_2: &str = "0.13.2";
+
+/// Mode used to publish the block state diff on L1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub enum L1DataAvailabilityMode {
+    /// The state diff is sent as calldata.
+    #[default]
+    Calldata,
+    /// The state diff is sent as blobs (EIP-4844).
+    Blob,
+}
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -20,10 +51,24 @@ pub struct Header {
     pub sequencer_address: ContractAddress,
     /// The time the sequencer created this block before executing transactions
     pub block_timestamp: u64,
+    /// The state commitment after this block.
+    pub global_state_root: StarkHash,
     /// The number of transactions in a block
     pub transaction_count: u128,
+    /// The root of the Patricia tree of the block transactions
+    pub transaction_commitment: StarkHash,
     /// The number of events
     pub event_count: u128,
+    /// The root of the Patricia tree of the block events
+    pub event_commitment: StarkHash,
+    /// The root of the Patricia tree of the block transaction receipts
+    pub receipt_commitment: StarkHash,
+    /// The hash of the block state diff
+    pub state_diff_commitment: StarkHash,
+    /// The number of state updates in the block state diff
+    pub state_diff_length: u64,
+    /// How the block state diff is published on L1
+    pub l1_da_mode: L1DataAvailabilityMode,
     /// The version of the Starknet protocol used when creating this block
     pub protocol_version: u8,
     /// Gas prices for this block
@@ -41,8 +86,15 @@ impl Header {
         block_number: u64,
         sequencer_address: ContractAddress,
         block_timestamp: u64,
+        global_state_root: StarkHash,
         transaction_count: u128,
+        transaction_commitment: StarkHash,
         event_count: u128,
+        event_commitment: StarkHash,
+        receipt_commitment: StarkHash,
+        state_diff_commitment: StarkHash,
+        state_diff_length: u64,
+        l1_da_mode: L1DataAvailabilityMode,
         protocol_version: u8,
         gas_prices: GasPrices,
         extra_data: Option<U256>,
@@ -52,16 +104,39 @@ impl Header {
             block_number,
             sequencer_address,
             block_timestamp,
+            global_state_root,
             transaction_count,
+            transaction_commitment,
             event_count,
+            event_commitment,
+            receipt_commitment,
+            state_diff_commitment,
+            state_diff_length,
+            l1_da_mode,
             protocol_version,
             l1_gas_price: gas_prices,
             extra_data,
         }
     }
 
-    /// Compute the hash using the Pedersen hasher according to [the Starknet protocol specification](https://docs.starknet.io/documentation/architecture_and_concepts/Network_Architecture/header/#block_hash).  
+    /// Returns the hash functions used for this block, based on its protocol version.
+    pub fn hash_version(&self) -> BlockHashVersion {
+        BlockHashVersion::from_protocol_version(self.protocol_version)
+    }
+
+    /// Compute the block hash according to [the Starknet protocol specification](https://docs.starknet.io/documentation/architecture_and_concepts/Network_Architecture/header/#block_hash).
+    ///
+    /// The Pedersen based hash is used from [`PROTOCOL_VERSION_0_13_1`] on, and the Poseidon based
+    /// hash of Starknet 0.13.2 from [`PROTOCOL_VERSION_0_13_2`] on.
     pub fn hash(&self) -> Felt252Wrapper {
+        match self.hash_version() {
+            BlockHashVersion::Legacy => self.legacy_hash(),
+            BlockHashVersion::Pedersen => self.pedersen_hash(),
+            BlockHashVersion::Poseidon => self.poseidon_hash(STARKNET_VERSION_0_13_2),
+        }
+    }
+
+    fn legacy_hash(&self) -> Felt252Wrapper {
         let data: &[Felt252Wrapper] = &[
             self.block_number.into(),
             self.sequencer_address.0.0.into(),
@@ -75,4 +150,60 @@ impl Header {
 
         PedersenHasher::compute_hash_on_wrappers(data)
     }
+
+    fn pedersen_hash(&self) -> Felt252Wrapper {
+        let data: &[Felt252Wrapper] = &[
+            self.block_number.into(),
+            self.global_state_root.into(),
+            self.sequencer_address.0.0.into(),
+            self.block_timestamp.into(),
+            self.transaction_count.into(),
+            self.transaction_commitment.into(),
+            self.event_count.into(),
+            self.event_commitment.into(),
+            Felt252Wrapper::ZERO,
+            Felt252Wrapper::ZERO,
+            self.parent_block_hash.into(),
+        ];
+
+        PedersenHasher::compute_hash_on_wrappers(data)
+    }
+
+    pub(crate) fn poseidon_hash(&self, starknet_version: &str) -> Felt252Wrapper {
+        let data: &[Felt252Wrapper] = &[
+            Felt252Wrapper(FieldElement::from_byte_slice_be(BLOCK_HASH_PREFIX).unwrap()),
+            self.block_number.into(),
+            self.global_state_root.into(),
+            self.sequencer_address.0.0.into(),
+            self.block_timestamp.into(),
+            self.concatenated_counts(),
+            self.state_diff_commitment.into(),
+            self.transaction_commitment.into(),
+            self.event_commitment.into(),
+            self.receipt_commitment.into(),
+            self.l1_gas_price.eth_l1_gas_price.get().into(),
+            self.l1_gas_price.strk_l1_gas_price.get().into(),
+            self.l1_gas_price.eth_l1_data_gas_price.get().into(),
+            self.l1_gas_price.strk_l1_data_gas_price.get().into(),
+            Felt252Wrapper(FieldElement::from_byte_slice_be(starknet_version.as_bytes()).unwrap()),
+            Felt252Wrapper::ZERO,
+            self.parent_block_hash.into(),
+        ];
+
+        PoseidonHasher::compute_hash_on_wrappers(data)
+    }
+
+    /// Packs the transaction, event and state diff counts along with the data availability mode
+    /// into a single felt, each count using 64 bits.
+    fn concatenated_counts(&self) -> Felt252Wrapper {
+        let mut bytes = [0u8; 32];
+        bytes[0..8].copy_from_slice(&(self.transaction_count as u64).to_be_bytes());
+        bytes[8..16].copy_from_slice(&(self.event_count as u64).to_be_bytes());
+        bytes[16..24].copy_from_slice(&self.state_diff_length.to_be_bytes());
+        if self.l1_da_mode == L1DataAvailabilityMode::Blob {
+            bytes[24] = 0b1000_0000;
+        }
+        // Safe as long as the block holds less than 2^59 transactions
+        Felt252Wrapper::try_from(&bytes).unwrap()
+    }
 }
@@ -0,0 +1,61 @@
+//! Blocks built by the Madara versions predating the block commitments.
+//!
+//! Their header did not hold the state root nor the commitments, so its SCALE encoding differs
+//! from the one of [`Header`]. These types are only used to decode the blocks stored in the
+//! existing databases, which are then handled as [`Block`]s of protocol version 0.
+use blockifier::blockifier::block::GasPrices;
+use sp_core::U256;
+use starknet_api::core::ContractAddress;
+use starknet_api::hash::StarkHash;
+
+use crate::{Block, BlockTransactions, Header, L1DataAvailabilityMode};
+
+/// Header of the blocks built before the block commitments.
+#[derive(Clone, Debug, parity_scale_codec::Encode, parity_scale_codec::Decode)]
+pub struct LegacyHeader {
+    pub parent_block_hash: StarkHash,
+    pub block_number: u64,
+    pub sequencer_address: ContractAddress,
+    pub block_timestamp: u64,
+    pub transaction_count: u128,
+    pub event_count: u128,
+    pub protocol_version: u8,
+    pub l1_gas_price: GasPrices,
+    pub extra_data: Option<U256>,
+}
+
+impl From<LegacyHeader> for Header {
+    fn from(header: LegacyHeader) -> Self {
+        Self {
+            parent_block_hash: header.parent_block_hash,
+            block_number: header.block_number,
+            sequencer_address: header.sequencer_address,
+            block_timestamp: header.block_timestamp,
+            global_state_root: StarkHash::ZERO,
+            transaction_count: header.transaction_count,
+            transaction_commitment: StarkHash::ZERO,
+            event_count: header.event_count,
+            event_commitment: StarkHash::ZERO,
+            receipt_commitment: StarkHash::ZERO,
+            state_diff_commitment: StarkHash::ZERO,
+            state_diff_length: 0,
+            l1_da_mode: L1DataAvailabilityMode::Calldata,
+            protocol_version: header.protocol_version,
+            l1_gas_price: header.l1_gas_price,
+            extra_data: header.extra_data,
+        }
+    }
+}
+
+/// Block built before the block commitments.
+#[derive(Clone, Debug, parity_scale_codec::Encode, parity_scale_codec::Decode)]
+pub struct LegacyBlock {
+    pub header: LegacyHeader,
+    pub transactions: BlockTransactions,
+}
+
+impl From<LegacyBlock> for Block {
+    fn from(block: LegacyBlock) -> Self {
+        Self { header: block.header.into(), transactions: block.transactions }
+    }
+}
@@ -1,8 +1,11 @@
 //! Starknet block primitives.
+pub mod commitments;
 mod header;
+#[cfg(feature = "parity-scale-codec")]
+pub mod legacy;
 
 use blockifier::transaction::transaction_execution::Transaction;
-pub use header::Header;
+pub use header::{Header, L1DataAvailabilityMode, PROTOCOL_VERSION_0_13_1, PROTOCOL_VERSION_0_13_2};
 use mp_felt::Felt252Wrapper;
 use mp_transactions::get_transaction_hash;
 use starknet_api::transaction::TransactionHash;
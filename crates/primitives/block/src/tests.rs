@@ -1,10 +1,26 @@
 use core::convert::TryFrom;
+use std::num::NonZeroU128;
 
+use blockifier::blockifier::block::GasPrices;
+use blockifier::execution::call_info::CallInfo;
+use blockifier::transaction::objects::TransactionExecutionInfo;
 use mp_felt::Felt252Wrapper;
 use mp_hashers::pedersen::PedersenHasher;
+use mp_hashers::poseidon::PoseidonHasher;
 use mp_hashers::HasherT;
-use starknet_api::core::{ContractAddress, PatriciaKey};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, EthAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{
+    Event, EventContent, EventData, EventKey, Fee, L2ToL1Payload, MessageToL1, TransactionHash, TransactionSignature,
+};
+use starknet_ff::FieldElement;
+
+use crate::commitments::{
+    calculate_commitment, calculate_event_commitment, calculate_receipt_commitment, calculate_transaction_commitment,
+    BlockHashVersion, BlockStateDiff, ReceiptCommitmentData, ReceiptResources,
+};
+use crate::{Header, L1DataAvailabilityMode, PROTOCOL_VERSION_0_13_1, PROTOCOL_VERSION_0_13_2};
 
 fn generate_dummy_header() -> Vec<Felt252Wrapper> {
     vec![
@@ -72,3 +88,364 @@ fn test_real_header_hash() {
 
     assert_eq!(hash, expected_hash);
 }
+
+fn generate_header(protocol_version: u8) -> Header {
+    Header {
+        parent_block_hash: StarkHash::from(1u128),
+        block_number: 2,
+        sequencer_address: ContractAddress(PatriciaKey(StarkFelt::from(3u128))),
+        block_timestamp: 4,
+        global_state_root: StarkHash::ZERO,
+        transaction_count: 5,
+        transaction_commitment: StarkHash::from(6u128),
+        event_count: 7,
+        event_commitment: StarkHash::from(8u128),
+        receipt_commitment: StarkHash::from(9u128),
+        state_diff_commitment: StarkHash::from(10u128),
+        state_diff_length: 11,
+        l1_da_mode: L1DataAvailabilityMode::Blob,
+        protocol_version,
+        l1_gas_price: GasPrices {
+            eth_l1_gas_price: NonZeroU128::new(12).unwrap(),
+            strk_l1_gas_price: NonZeroU128::new(13).unwrap(),
+            eth_l1_data_gas_price: NonZeroU128::new(14).unwrap(),
+            strk_l1_data_gas_price: NonZeroU128::new(15).unwrap(),
+        },
+        extra_data: None,
+    }
+}
+
+#[test]
+fn test_real_header_hash_from_header() {
+    // Values taken from alpha-mainnet block 86000, see `test_real_header_hash`
+    let header = Header {
+        parent_block_hash: StarkHash::try_from("0x045543088ce763aba7db8f6bfb33e33cc50af5c2ed5a26d38d5071c352a49c1d")
+            .unwrap(),
+        block_number: 86000,
+        sequencer_address: ContractAddress(PatriciaKey(
+            StarkFelt::try_from("0x1176a1bd84444c89232ec27754698e5d2e7e1a7f1539f12027f28b23ec9f3d8").unwrap(),
+        )),
+        block_timestamp: 1687235884,
+        global_state_root: StarkHash::try_from("0x006727a7aae8c38618a179aeebccd6302c67ad5f8528894d1dde794e9ae0bbfa")
+            .unwrap(),
+        transaction_count: 197,
+        transaction_commitment: StarkFelt::try_from(
+            "0x70369cef825889dc005916dba67332b71f270b7af563d0433cee3342dda527d",
+        )
+        .unwrap(),
+        event_count: 1430,
+        event_commitment: StarkFelt::try_from("0x2043ba1ef46882ce1dbb17b501fffa4b71f87f618e8f394e9605959d92efdf6")
+            .unwrap(),
+        protocol_version: PROTOCOL_VERSION_0_13_1,
+        ..generate_header(PROTOCOL_VERSION_0_13_1)
+    };
+
+    assert_eq!(header.hash_version(), BlockHashVersion::Pedersen);
+    assert_eq!(
+        header.hash(),
+        Felt252Wrapper::from_hex_be("0x001d126ca058c7e546d59cf4e10728e4b023ca0fb368e8abcabf0b5335f4487a").unwrap()
+    );
+}
+
+#[test]
+fn test_poseidon_header_hash() {
+    let header = generate_header(PROTOCOL_VERSION_0_13_2);
+    assert_eq!(header.hash_version(), BlockHashVersion::Poseidon);
+
+    // transaction count, event count and state diff length on 64 bits each, then the DA mode
+    let concatenated_counts =
+        Felt252Wrapper::from_hex_be("0x00000000000000050000000000000007000000000000000b8000000000000000").unwrap();
+    let data: &[Felt252Wrapper] = &[
+        Felt252Wrapper::from_hex_be("0x535441524b4e45545f424c4f434b5f4841534830").unwrap(), // STARKNET_BLOCK_HASH0
+        2u64.into(),
+        Felt252Wrapper::ZERO,
+        3u64.into(),
+        4u64.into(),
+        concatenated_counts,
+        10u64.into(),
+        6u64.into(),
+        8u64.into(),
+        9u64.into(),
+        12u64.into(),
+        13u64.into(),
+        14u64.into(),
+        15u64.into(),
+        Felt252Wrapper::from_hex_be("0x302e31332e32").unwrap(), // 0.13.2
+        Felt252Wrapper::ZERO,
+        1u64.into(),
+    ];
+
+    assert_eq!(header.hash(), <PoseidonHasher as HasherT>::compute_hash_on_wrappers(data));
+    assert_ne!(header.hash(), generate_header(PROTOCOL_VERSION_0_13_1).hash());
+}
+
+#[test]
+fn test_legacy_header_hash() {
+    let header = generate_header(0);
+    assert_eq!(header.hash_version(), BlockHashVersion::Legacy);
+
+    // The hash of the blocks built before the commitments were added to the header
+    let data: &[Felt252Wrapper] = &[
+        2u64.into(),
+        3u64.into(),
+        4u64.into(),
+        5u64.into(),
+        7u64.into(),
+        Felt252Wrapper::ZERO,
+        Felt252Wrapper::ZERO,
+        1u64.into(),
+    ];
+
+    assert_eq!(header.hash(), <PedersenHasher as HasherT>::compute_hash_on_wrappers(data));
+}
+
+#[test]
+fn test_empty_commitment_is_zero() {
+    assert_eq!(calculate_commitment::<PedersenHasher>(&[]), StarkHash::ZERO);
+    assert_eq!(calculate_transaction_commitment(&[], BlockHashVersion::Poseidon), StarkHash::ZERO);
+    assert_eq!(calculate_event_commitment(&[], BlockHashVersion::Pedersen), StarkHash::ZERO);
+    assert_eq!(calculate_receipt_commitment(&[]), StarkHash::ZERO);
+}
+
+#[test]
+fn test_commitment_of_a_single_leaf() {
+    let leaf = FieldElement::from(42u64);
+
+    // The root is an edge node of length 64 leading to the leaf at index 0
+    let expected = PedersenHasher::hash_elements(leaf, FieldElement::ZERO) + FieldElement::from(64u64);
+
+    assert_eq!(calculate_commitment::<PedersenHasher>(&[leaf]), Felt252Wrapper(expected).into());
+}
+
+#[test]
+fn test_commitment_of_several_leaves() {
+    let leaves = [FieldElement::from(1u64), FieldElement::from(2u64), FieldElement::from(3u64)];
+
+    // Leaves 0 and 1 are the children of a binary node, leaf 2 is reached through an edge of length 1
+    let bottom_left = PoseidonHasher::hash_elements(leaves[0], leaves[1]);
+    let bottom_right = PoseidonHasher::hash_elements(leaves[2], FieldElement::ZERO) + FieldElement::ONE;
+    let binary = PoseidonHasher::hash_elements(bottom_left, bottom_right);
+    let expected = PoseidonHasher::hash_elements(binary, FieldElement::ZERO) + FieldElement::from(62u64);
+
+    assert_eq!(calculate_commitment::<PoseidonHasher>(&leaves), Felt252Wrapper(expected).into());
+}
+
+#[test]
+fn test_transaction_commitment_depends_on_signatures() {
+    let tx_hash = TransactionHash(StarkFelt::from(1u128));
+    let unsigned = [(tx_hash, None)];
+    let signed = [(tx_hash, Some(TransactionSignature(vec![StarkFelt::from(2u128)])))];
+
+    for version in [BlockHashVersion::Pedersen, BlockHashVersion::Poseidon] {
+        assert_ne!(
+            calculate_transaction_commitment(&unsigned, version),
+            calculate_transaction_commitment(&signed, version)
+        );
+    }
+
+    // Transactions without signature are hashed with a zero signature from 0.13.2 on
+    let leaf = PoseidonHasher::compute_hash_on_elements(&[FieldElement::ONE, FieldElement::ZERO]);
+    assert_eq!(
+        calculate_transaction_commitment(&unsigned, BlockHashVersion::Poseidon),
+        calculate_commitment::<PoseidonHasher>(&[leaf])
+    );
+    // but not the ones with an empty signature
+    let leaf = PoseidonHasher::compute_hash_on_elements(&[FieldElement::ONE]);
+    assert_eq!(
+        calculate_transaction_commitment(&[(tx_hash, Some(TransactionSignature(vec![])))], BlockHashVersion::Poseidon),
+        calculate_commitment::<PoseidonHasher>(&[leaf])
+    );
+}
+
+#[test]
+fn test_state_diff_length_and_commitment() {
+    let address = ContractAddress(PatriciaKey(StarkFelt::from(1u128)));
+    let class_hash = ClassHash(StarkFelt::from(2u128));
+
+    let mut state_diff = BlockStateDiff::default();
+    assert!(state_diff.is_empty());
+    let empty_commitment = state_diff.commitment();
+
+    state_diff.deployed_contracts.insert(address, class_hash);
+    state_diff.deprecated_declared_classes.insert(class_hash);
+    state_diff.nonces.insert(address, Nonce(StarkFelt::ONE));
+    state_diff
+        .storage_diffs
+        .entry(address)
+        .or_default()
+        .insert(StorageKey(PatriciaKey(StarkFelt::ONE)), StarkFelt::ONE);
+    state_diff
+        .storage_diffs
+        .entry(address)
+        .or_default()
+        .insert(StorageKey(PatriciaKey(StarkFelt::from(2u128))), StarkFelt::ONE);
+
+    assert_eq!(state_diff.len(), 5);
+    assert_ne!(state_diff.commitment(), empty_commitment);
+}
+
+// The following vectors are the regression tests of the `block_hash` module of the Starknet
+// sequencer (starknet_api 0.13.0), which computes the commitments and hashes of the Starknet
+// 0.13.2 blocks.
+
+fn felt(value: u64) -> StarkFelt {
+    StarkFelt::from(value as u128)
+}
+
+fn address(value: u64) -> ContractAddress {
+    ContractAddress(PatriciaKey(felt(value)))
+}
+
+fn hex(value: &str) -> StarkHash {
+    StarkHash::try_from(value).unwrap()
+}
+
+fn reference_message_to_l1(seed: u64) -> MessageToL1 {
+    MessageToL1 {
+        from_address: address(seed),
+        to_address: EthAddress::try_from(felt(seed + 1)).unwrap(),
+        payload: L2ToL1Payload(vec![felt(seed + 2), felt(seed + 3)]),
+    }
+}
+
+fn reference_receipt(transaction_hash: u64) -> ReceiptCommitmentData {
+    ReceiptCommitmentData {
+        transaction_hash: TransactionHash(felt(transaction_hash)),
+        resources: ReceiptResources { actual_fee: Fee(99804), l1_gas: 16580, l1_data_gas: 32 },
+        messages_sent: vec![reference_message_to_l1(34), reference_message_to_l1(56)],
+        revert_reason: Some("aborted".into()),
+    }
+}
+
+fn reference_event(seed: u64) -> (TransactionHash, Event) {
+    let event = Event {
+        from_address: address(seed + 8),
+        content: EventContent {
+            keys: vec![EventKey(felt(seed)), EventKey(felt(seed + 1))],
+            data: EventData(vec![felt(seed + 2), felt(seed + 3), felt(seed + 4)]),
+        },
+    };
+    (TransactionHash(felt(0x1234)), event)
+}
+
+fn reference_state_diff() -> BlockStateDiff {
+    let mut state_diff = BlockStateDiff::default();
+    // Deployed contracts, then replaced classes
+    for (contract, class_hash) in [(0, 1), (2, 3), (19, 20)] {
+        state_diff.deployed_contracts.insert(address(contract), ClassHash(felt(class_hash)));
+    }
+    for (contract, key, value) in [(4, 5, 6), (4, 7, 8), (9, 10, 11)] {
+        state_diff
+            .storage_diffs
+            .entry(address(contract))
+            .or_default()
+            .insert(StorageKey(PatriciaKey(felt(key))), felt(value));
+    }
+    for (class_hash, compiled_class_hash) in [(12, 13), (14, 15)] {
+        state_diff.declared_classes.insert(ClassHash(felt(class_hash)), CompiledClassHash(felt(compiled_class_hash)));
+    }
+    state_diff.deprecated_declared_classes.insert(ClassHash(felt(16)));
+    state_diff.nonces.insert(address(17), Nonce(felt(18)));
+    state_diff
+}
+
+#[test]
+fn test_reference_patricia_roots() {
+    let leaves = [1u64, 2, 3].map(FieldElement::from);
+
+    assert_eq!(
+        calculate_commitment::<PoseidonHasher>(&leaves[..1]),
+        hex("0x7752582c54a42fe0fa35c40f07293bb7d8efe90e21d8d2c06a7db52d7d9b7e1")
+    );
+    assert_eq!(
+        calculate_commitment::<PoseidonHasher>(&leaves[..2]),
+        hex("0x1c1ba983ee0a0de87d87d67ea3cbee7023aa65f6b7bcf71259f122ea3af80bf")
+    );
+    assert_eq!(
+        calculate_commitment::<PoseidonHasher>(&leaves),
+        hex("0x3b5cc7f1292eb3847c3f902d048a7e5dc7702d1c191ccd17c2d33f797e6fc32")
+    );
+}
+
+#[test]
+fn test_reference_transaction_commitment() {
+    let transaction = (TransactionHash(felt(1)), Some(TransactionSignature(vec![felt(2), felt(3)])));
+
+    assert_eq!(
+        calculate_transaction_commitment(&[transaction.clone(), transaction], BlockHashVersion::Poseidon),
+        hex("0x0282b635972328bd1cfa86496fe920d20bd9440cd78ee8dc90ae2b383d664dcf")
+    );
+}
+
+#[test]
+fn test_reference_event_commitment() {
+    let events = [reference_event(0), reference_event(1), reference_event(2)];
+
+    assert_eq!(
+        calculate_event_commitment(&events, BlockHashVersion::Poseidon),
+        hex("0x069bb140ddbbeb01d81c7201ecfb933031306e45dab9c77ff9f9ba3cd4c2b9c3")
+    );
+}
+
+#[test]
+fn test_reference_receipt_commitment() {
+    assert_eq!(
+        calculate_receipt_commitment(&[reference_receipt(1234)]),
+        hex("0x31963cb891ebb825e83514deb748c89b6967b5368cbc48a9b56193a1464ca87")
+    );
+}
+
+#[test]
+fn test_receipt_resources_commit_to_the_charged_fee() {
+    let mut execution_info = TransactionExecutionInfo { actual_fee: Fee(99804), ..Default::default() };
+    // Executed while fees were disabled, nothing was transferred to the sequencer
+    assert_eq!(ReceiptResources::from(&execution_info).actual_fee, Fee(0));
+
+    execution_info.fee_transfer_call_info = Some(CallInfo::default());
+    assert_eq!(ReceiptResources::from(&execution_info).actual_fee, Fee(99804));
+}
+
+#[test]
+fn test_reference_state_diff_commitment() {
+    let state_diff = reference_state_diff();
+
+    assert_eq!(state_diff.len(), 10);
+    assert_eq!(state_diff.commitment(), hex("0x0281f5966e49ad7dad9323826d53d1d27c0c4e6ebe5525e2e2fbca549bfa0a67"));
+}
+
+#[test]
+fn test_reference_block_hash() {
+    let state_diff = reference_state_diff();
+    let header = Header {
+        parent_block_hash: felt(11),
+        block_number: 1,
+        sequencer_address: address(3),
+        block_timestamp: 4,
+        global_state_root: felt(2),
+        transaction_count: 1,
+        transaction_commitment: calculate_transaction_commitment(
+            &[(TransactionHash(felt(1)), Some(TransactionSignature(vec![felt(2), felt(3)])))],
+            BlockHashVersion::Poseidon,
+        ),
+        event_count: 0,
+        event_commitment: calculate_event_commitment(&[], BlockHashVersion::Poseidon),
+        receipt_commitment: calculate_receipt_commitment(&[reference_receipt(1)]),
+        state_diff_commitment: state_diff.commitment(),
+        state_diff_length: state_diff.len(),
+        l1_da_mode: L1DataAvailabilityMode::Blob,
+        protocol_version: PROTOCOL_VERSION_0_13_2,
+        l1_gas_price: GasPrices {
+            eth_l1_gas_price: NonZeroU128::new(7).unwrap(),
+            strk_l1_gas_price: NonZeroU128::new(6).unwrap(),
+            eth_l1_data_gas_price: NonZeroU128::new(9).unwrap(),
+            strk_l1_data_gas_price: NonZeroU128::new(10).unwrap(),
+        },
+        extra_data: None,
+    };
+
+    // The reference block commits to the Starknet version "10"
+    assert_eq!(
+        header.poseidon_hash("10"),
+        Felt252Wrapper::from_hex_be("0x061e4998d51a248f1d0288d7e17f6287757b0e5e6c5e1e58ddf740616e312134").unwrap()
+    );
+}
@@ -18,23 +18,42 @@ mod error;
 mod tests;
 
 pub use error::FindLogError;
+use mp_block::legacy::LegacyBlock;
 use mp_block::Block as StarknetBlock;
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::{Decode, Encode, Error, Input};
 use sp_runtime::generic::{Digest, OpaqueDigestItemId};
 use sp_runtime::ConsensusEngineId;
 
 pub const MADARA_ENGINE_ID: ConsensusEngineId = [b'm', b'a', b'd', b'a'];
 
+/// Index of the logs holding a block built before the block commitments, see [`LegacyBlock`].
+const LEGACY_BLOCK_LOG_INDEX: u8 = 0;
+/// Index of the logs holding a block.
+const BLOCK_LOG_INDEX: u8 = 1;
+
 /// A Madara log
 ///
 /// Right now we only expect Madara to log the Starknet block,
 /// but other usecases may appears later on.
-#[derive(Debug, Clone, Encode, Decode)]
+///
+/// The blocks logged by the Madara versions predating the block commitments are decoded as
+/// [`Log::Block`] too.
+#[derive(Debug, Clone, Encode)]
 pub enum Log {
-    #[codec(index = 0)]
+    #[codec(index = 1)]
     Block(StarknetBlock),
 }
 
+impl Decode for Log {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        match input.read_byte()? {
+            LEGACY_BLOCK_LOG_INDEX => Ok(Log::Block(LegacyBlock::decode(input)?.into())),
+            BLOCK_LOG_INDEX => Ok(Log::Block(StarknetBlock::decode(input)?)),
+            _ => Err("Invalid Madara log index".into()),
+        }
+    }
+}
+
 /// Return the wrapped [StarknetBlock] contained in a given [Digest]
 pub fn find_starknet_block(digest: &Digest) -> Result<StarknetBlock, FindLogError> {
     find_log(digest).map(|log| match log {
@@ -2,6 +2,8 @@ use std::num::NonZeroU128;
 
 use assert_matches::assert_matches;
 use blockifier::blockifier::block::GasPrices;
+use mp_block::commitments::BlockHashVersion;
+use mp_block::legacy::{LegacyBlock, LegacyHeader};
 use mp_block::Header;
 use sp_runtime::{Digest, DigestItem};
 
@@ -14,8 +16,15 @@ fn create_empty_block() -> StarknetBlock {
             block_number: Default::default(),
             sequencer_address: Default::default(),
             block_timestamp: Default::default(),
+            global_state_root: Default::default(),
             transaction_count: Default::default(),
+            transaction_commitment: Default::default(),
             event_count: Default::default(),
+            event_commitment: Default::default(),
+            receipt_commitment: Default::default(),
+            state_diff_commitment: Default::default(),
+            state_diff_length: Default::default(),
+            l1_da_mode: Default::default(),
             protocol_version: Default::default(),
             l1_gas_price: unsafe {
                 GasPrices {
@@ -75,3 +84,41 @@ fn other_consensus_engine_id() {
     assert_matches!(find_log(&digest), Err(FindLogError::NotLog));
     assert_matches!(find_starknet_block(&digest), Err(FindLogError::NotLog));
 }
+
+#[test]
+fn legacy_block_is_decoded() {
+    let mut digest = Digest::default();
+    let block = create_empty_block();
+    let header = block.header();
+    let legacy_block = LegacyBlock {
+        header: LegacyHeader {
+            parent_block_hash: header.parent_block_hash,
+            block_number: 42,
+            sequencer_address: header.sequencer_address,
+            block_timestamp: 1700000000,
+            transaction_count: 0,
+            event_count: 0,
+            protocol_version: 0,
+            l1_gas_price: header.l1_gas_price,
+            extra_data: None,
+        },
+        transactions: vec![],
+    };
+
+    digest.push(DigestItem::Consensus(MADARA_ENGINE_ID, (LEGACY_BLOCK_LOG_INDEX, legacy_block).encode()));
+
+    let block = find_starknet_block(&digest).unwrap();
+    assert_eq!(block.header().block_number, 42);
+    assert_eq!(block.header().block_timestamp, 1700000000);
+    assert_eq!(block.header().hash_version(), BlockHashVersion::Legacy);
+}
+
+#[test]
+fn block_log_round_trips() {
+    let block = create_empty_block();
+    let encoded = Log::Block(block.clone()).encode();
+
+    assert_eq!(encoded[0], BLOCK_LOG_INDEX);
+    let Log::Block(decoded) = Log::decode(&mut &encoded[..]).unwrap();
+    assert_eq!(decoded.header().hash(), block.header().hash());
+}
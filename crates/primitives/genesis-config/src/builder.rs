@@ -0,0 +1,112 @@
+//! Programmatic construction of [`GenesisData`], mostly useful in tests.
+
+use std::string::String;
+
+use crate::{
+    ClassHash, ContractAddress, ContractClass, GenesisData, HexFelt, PredeployedAccount, StorageKey, StorageValue,
+};
+
+/// Builds a [`GenesisData`] entry by entry
+///
+/// ```ignore
+/// let genesis = GenesisData::builder("MADARA")
+///     .class(class_hash, ContractClass::Path { path: "genesis-assets/ERC20.json".into(), version: 0, sierra_path: None })
+///     .contract(fee_token_address, class_hash)
+///     .fee_tokens(fee_token_address, fee_token_address)
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct GenesisDataBuilder {
+    data: GenesisData,
+}
+
+impl GenesisData {
+    pub fn builder(chain_id: impl Into<String>) -> GenesisDataBuilder {
+        GenesisDataBuilder {
+            data: GenesisData {
+                contract_classes: Vec::new(),
+                sierra_class_hash_to_casm_class_hash: Vec::new(),
+                contracts: Vec::new(),
+                predeployed_accounts: Vec::new(),
+                storage: Vec::new(),
+                chain_id: chain_id.into(),
+                strk_fee_token_address: HexFelt(Default::default()),
+                eth_fee_token_address: HexFelt(Default::default()),
+            },
+        }
+    }
+}
+
+impl GenesisDataBuilder {
+    /// Declare a class
+    pub fn class(mut self, class_hash: impl Into<ClassHash>, class: ContractClass) -> Self {
+        self.data.contract_classes.push((class_hash.into(), class));
+        self
+    }
+
+    /// Declare a version 1 class from its CASM class, along its compiled class hash
+    pub fn casm_class(
+        mut self,
+        sierra_class_hash: impl Into<ClassHash>,
+        compiled_class_hash: impl Into<ClassHash>,
+        path: impl Into<String>,
+    ) -> Self {
+        let sierra_class_hash = sierra_class_hash.into();
+        self.data
+            .contract_classes
+            .push((sierra_class_hash, ContractClass::Path { path: path.into(), version: 1, sierra_path: None }));
+        self.data.sierra_class_hash_to_casm_class_hash.push((sierra_class_hash, compiled_class_hash.into()));
+        self
+    }
+
+    /// Declare a version 1 class from its CASM class, with the sierra class its class hash is
+    /// checked against
+    pub fn sierra_class(
+        mut self,
+        sierra_class_hash: impl Into<ClassHash>,
+        compiled_class_hash: impl Into<ClassHash>,
+        sierra_path: impl Into<String>,
+        casm_path: impl Into<String>,
+    ) -> Self {
+        let sierra_class_hash = sierra_class_hash.into();
+        let class = ContractClass::Path { path: casm_path.into(), version: 1, sierra_path: Some(sierra_path.into()) };
+        self.data.contract_classes.push((sierra_class_hash, class));
+        self.data.sierra_class_hash_to_casm_class_hash.push((sierra_class_hash, compiled_class_hash.into()));
+        self
+    }
+
+    /// Deploy an instance of `class_hash` at `address`
+    pub fn contract(mut self, address: impl Into<ContractAddress>, class_hash: impl Into<ClassHash>) -> Self {
+        self.data.contracts.push((address.into(), class_hash.into()));
+        self
+    }
+
+    /// Deploy an account and list it among the predeployed accounts
+    pub fn predeployed_account(mut self, account: PredeployedAccount) -> Self {
+        self.data.contracts.push((account.contract_address, account.class_hash));
+        self.data.predeployed_accounts.push(account);
+        self
+    }
+
+    /// Set a storage slot of a contract
+    pub fn storage(
+        mut self,
+        address: impl Into<ContractAddress>,
+        key: impl Into<StorageKey>,
+        value: impl Into<StorageValue>,
+    ) -> Self {
+        self.data.storage.push(((address.into(), key.into()), value.into()));
+        self
+    }
+
+    /// Set the addresses of the ETH and STRK fee tokens
+    pub fn fee_tokens(mut self, eth: impl Into<ContractAddress>, strk: impl Into<ContractAddress>) -> Self {
+        self.data.eth_fee_token_address = eth.into();
+        self.data.strk_fee_token_address = strk.into();
+        self
+    }
+
+    pub fn build(self) -> GenesisData {
+        self.data
+    }
+}
@@ -0,0 +1,165 @@
+//! Funded development accounts derived from a seed.
+
+use std::string::String;
+
+use starknet_core::utils::{get_contract_address, starknet_keccak};
+use starknet_crypto::{get_public_key, FieldElement};
+
+use crate::spec::{storage_var_address, DEFAULT_PUBLIC_KEY_STORAGE_VAR, ERC20_BALANCES, ERC20_TOTAL_SUPPLY};
+use crate::{ClassHash, ContractAddress, GenesisData, PredeployedAccount};
+
+/// Balance of each development account, in both fee tokens: 10 000 ETH or STRK
+pub const DEFAULT_DEV_ACCOUNT_BALANCE: u128 = 10_000 * 10u128.pow(18);
+
+/// Development accounts to add to a genesis
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DevAccounts {
+    pub count: u32,
+    pub seed: String,
+    /// The class must store its public key in `Account_public_key` and take it as only constructor
+    /// argument, as the OpenZeppelin accounts do
+    pub class_hash: ClassHash,
+    /// Balance of each account, in both fee tokens
+    pub balance: u128,
+}
+
+/// Derive the key pair of the `index`-th development account of `seed`
+///
+/// The private key is `sn_keccak(seed || index)`, `index` being big endian encoded on 4 bytes.
+/// `sn_keccak` keeps 250 bits, so the key is always below the order of the curve.
+pub fn derive_dev_account_keys(seed: &str, index: u32) -> (FieldElement, FieldElement) {
+    let mut preimage = seed.as_bytes().to_vec();
+    preimage.extend_from_slice(&index.to_be_bytes());
+    let private_key = starknet_keccak(&preimage);
+    (private_key, get_public_key(&private_key))
+}
+
+impl GenesisData {
+    /// Deploy and fund the development accounts
+    ///
+    /// The accounts are deployed at the address a `DEPLOY_ACCOUNT` transaction with their public
+    /// key as salt would deploy them to, and listed among the predeployed accounts along their
+    /// private key.
+    pub fn add_dev_accounts(&mut self, accounts: &DevAccounts) {
+        let fee_tokens = [self.eth_fee_token_address, self.strk_fee_token_address];
+        for index in 0..accounts.count {
+            let (private_key, public_key) = derive_dev_account_keys(&accounts.seed, index);
+            let address: ContractAddress =
+                get_contract_address(public_key, accounts.class_hash.0, &[public_key], FieldElement::ZERO).into();
+
+            self.contracts.push((address, accounts.class_hash));
+            self.set_storage(address, storage_var_address(DEFAULT_PUBLIC_KEY_STORAGE_VAR, &[]), public_key);
+            self.predeployed_accounts.push(PredeployedAccount {
+                contract_address: address,
+                class_hash: accounts.class_hash,
+                name: format!("Dev account #{index}"),
+                private_key: Some(private_key.to_bytes_be().to_vec()),
+                public_key: public_key.into(),
+            });
+
+            for token in fee_tokens {
+                self.add_u256(token, storage_var_address(ERC20_BALANCES, &[address.0]), accounts.balance);
+                self.add_u256(token, storage_var_address(ERC20_TOTAL_SUPPLY, &[]), accounts.balance);
+            }
+        }
+    }
+
+    fn storage_value(&self, address: ContractAddress, key: FieldElement) -> FieldElement {
+        self.storage
+            .iter()
+            .find(|((contract, storage_key), _)| *contract == address && storage_key.0 == key)
+            .map(|(_, value)| value.0)
+            .unwrap_or(FieldElement::ZERO)
+    }
+
+    fn set_storage(&mut self, address: ContractAddress, key: FieldElement, value: FieldElement) {
+        match self.storage.iter_mut().find(|((contract, storage_key), _)| *contract == address && storage_key.0 == key)
+        {
+            Some((_, stored)) => *stored = value.into(),
+            None => self.storage.push(((address, key.into()), value.into())),
+        }
+    }
+
+    /// Add `amount` to the u256 stored at `key` and `key + 1`, saturating at the maximum u256
+    fn add_u256(&mut self, address: ContractAddress, key: FieldElement, amount: u128) {
+        let high_key = key + FieldElement::ONE;
+        let low = felt_to_u128(self.storage_value(address, key));
+        let high = felt_to_u128(self.storage_value(address, high_key));
+
+        let (low, high) = match low.overflowing_add(amount) {
+            (low, false) => (low, high),
+            (low, true) => match high.checked_add(1) {
+                Some(high) => (low, high),
+                None => (u128::MAX, u128::MAX),
+            },
+        };
+
+        self.set_storage(address, key, low.into());
+        self.set_storage(address, high_key, high.into());
+    }
+}
+
+/// The low 128 bits of a felt, u256 limbs always fitting in them
+fn felt_to_u128(felt: FieldElement) -> u128 {
+    let bytes = felt.to_bytes_be();
+    u128::from_be_bytes(bytes[16..].try_into().expect("the slice is 16 bytes long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genesis() -> GenesisData {
+        GenesisData::builder("MADARA").fee_tokens(FieldElement::ONE, FieldElement::TWO).build()
+    }
+
+    fn dev_accounts(count: u32, seed: &str) -> DevAccounts {
+        DevAccounts { count, seed: seed.into(), class_hash: FieldElement::THREE.into(), balance: 100 }
+    }
+
+    #[test]
+    fn keys_are_derived_from_the_seed() {
+        assert_eq!(derive_dev_account_keys("seed", 0), derive_dev_account_keys("seed", 0));
+        assert_ne!(derive_dev_account_keys("seed", 0), derive_dev_account_keys("seed", 1));
+        assert_ne!(derive_dev_account_keys("seed", 0), derive_dev_account_keys("other seed", 0));
+
+        let (private_key, public_key) = derive_dev_account_keys("seed", 0);
+        assert_eq!(get_public_key(&private_key), public_key);
+    }
+
+    #[test]
+    fn accounts_are_deployed_and_funded() {
+        let mut data = genesis();
+        data.add_dev_accounts(&dev_accounts(3, "seed"));
+
+        assert_eq!(data.predeployed_accounts.len(), 3);
+        assert_eq!(data.contracts.len(), 3);
+        let account = &data.predeployed_accounts[2];
+        let (private_key, public_key) = derive_dev_account_keys("seed", 2);
+        assert_eq!(account.private_key, Some(private_key.to_bytes_be().to_vec()));
+        assert_eq!(account.public_key.0, public_key);
+
+        let balance_key = storage_var_address(ERC20_BALANCES, &[account.contract_address.0]);
+        let total_supply_key = storage_var_address(ERC20_TOTAL_SUPPLY, &[]);
+        for token in [FieldElement::ONE.into(), FieldElement::TWO.into()] {
+            assert_eq!(data.storage_value(token, balance_key), FieldElement::from(100u8));
+            assert_eq!(data.storage_value(token, total_supply_key), FieldElement::from(300u16));
+        }
+    }
+
+    #[test]
+    fn total_supply_saturates() {
+        let mut data = genesis();
+        let total_supply_key = storage_var_address(ERC20_TOTAL_SUPPLY, &[]);
+        data.set_storage(FieldElement::ONE.into(), total_supply_key, u128::MAX.into());
+        data.set_storage(FieldElement::ONE.into(), total_supply_key + FieldElement::ONE, u128::MAX.into());
+
+        data.add_dev_accounts(&dev_accounts(1, "seed"));
+
+        assert_eq!(data.storage_value(FieldElement::ONE.into(), total_supply_key), u128::MAX.into());
+        assert_eq!(
+            data.storage_value(FieldElement::ONE.into(), total_supply_key + FieldElement::ONE),
+            u128::MAX.into()
+        );
+    }
+}
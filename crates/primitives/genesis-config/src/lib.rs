@@ -1,3 +1,8 @@
+mod builder;
+mod dev_accounts;
+mod spec;
+mod validation;
+
 use std::fmt;
 use std::path::PathBuf;
 use std::string::String;
@@ -10,6 +15,11 @@ use serde_with::serde_as;
 use starknet_core::serde::unsigned_field_element::UfeHex;
 use starknet_crypto::FieldElement;
 
+pub use crate::builder::GenesisDataBuilder;
+pub use crate::dev_accounts::{derive_dev_account_keys, DevAccounts, DEFAULT_DEV_ACCOUNT_BALANCE};
+pub use crate::spec::{AccountsSpec, ClassSpec, ContractSpec, Erc20Spec, FeeTokensSpec, GenesisSpec, GenesisSpecError};
+pub use crate::validation::{GenesisValidationError, GenesisValidationErrors};
+
 /// A wrapper for FieldElement that implements serde's Serialize and Deserialize for hex strings.
 #[serde_as]
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
@@ -81,7 +91,13 @@ impl GenesisLoader {
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum ContractClass {
-    Path { path: String, version: u8 },
+    Path {
+        path: String,
+        version: u8,
+        /// Sierra class of a version 1 class, checked against its declared class hash
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sierra_path: Option<String>,
+    },
 }
 
 /// A struct containing predeployed accounts info.
@@ -0,0 +1,378 @@
+//! A compact genesis format, expanded into the [`GenesisData`] the node consumes.
+//!
+//! Instead of listing raw storage slots, a [`GenesisSpec`] describes what the genesis should
+//! contain: ERC20 tokens with their metadata, accounts funded with fee tokens, the UDC... The
+//! storage layout of the OpenZeppelin cairo 0 ERC20 and account contracts is used to write it.
+
+use std::collections::HashMap;
+use std::string::String;
+
+use serde::{Deserialize, Serialize};
+use starknet_core::utils::{cairo_short_string_to_felt, get_contract_address, get_storage_var_address};
+use starknet_crypto::FieldElement;
+
+use crate::{ClassHash, ContractAddress, ContractClass, GenesisData, HexFelt, PredeployedAccount, StorageKey};
+
+const ERC20_NAME: &str = "ERC20_name";
+const ERC20_SYMBOL: &str = "ERC20_symbol";
+const ERC20_DECIMALS: &str = "ERC20_decimals";
+pub(crate) const ERC20_TOTAL_SUPPLY: &str = "ERC20_total_supply";
+pub(crate) const ERC20_BALANCES: &str = "ERC20_balances";
+pub(crate) const DEFAULT_PUBLIC_KEY_STORAGE_VAR: &str = "Account_public_key";
+
+/// A class of the genesis, referred to by its name in the rest of the spec
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ClassSpec {
+    pub name: String,
+    /// Class hash, the sierra class hash for version 1 classes
+    pub class_hash: ClassHash,
+    /// Path of the class from the config directory, the CASM class for version 1 classes
+    pub path: String,
+    pub version: u8,
+    /// Required for version 1 classes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled_class_hash: Option<ClassHash>,
+    /// Path of the sierra class of a version 1 class, used to check its class hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sierra_path: Option<String>,
+}
+
+/// An ERC20 contract, with its metadata and initial supply
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Erc20Spec {
+    pub address: ContractAddress,
+    /// Name of the ERC20 class in [`GenesisSpec::classes`]
+    pub class: String,
+    /// Cairo short string, at most 31 ASCII characters
+    pub name: String,
+    /// Cairo short string, at most 31 ASCII characters
+    pub symbol: String,
+    pub decimals: u8,
+    /// Supply minted to `recipient`, on top of the balances of the funded accounts
+    #[serde(default)]
+    pub initial_supply: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<ContractAddress>,
+}
+
+/// The fee tokens of the chain
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeeTokensSpec {
+    pub eth: Erc20Spec,
+    pub strk: Erc20Spec,
+}
+
+/// Accounts of the same class, one per public key
+///
+/// Each account is deployed at the address a `DEPLOY_ACCOUNT` transaction would deploy it to,
+/// using its public key as salt and as only constructor argument.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AccountsSpec {
+    /// Name of the account class in [`GenesisSpec::classes`]
+    pub class: String,
+    pub public_keys: Vec<HexFelt>,
+    /// Storage variable holding the public key of the account
+    #[serde(default = "default_public_key_storage_var")]
+    pub public_key_storage_var: String,
+    /// ETH balance of each account, in wei
+    #[serde(default)]
+    pub eth_balance: u128,
+    /// STRK balance of each account, in fri
+    #[serde(default)]
+    pub strk_balance: u128,
+}
+
+fn default_public_key_storage_var() -> String {
+    DEFAULT_PUBLIC_KEY_STORAGE_VAR.into()
+}
+
+/// A contract deployed without any storage, such as the UDC
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ContractSpec {
+    pub address: ContractAddress,
+    /// Name of the contract class in [`GenesisSpec::classes`]
+    pub class: String,
+}
+
+/// A high level description of the genesis state
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct GenesisSpec {
+    pub chain_id: String,
+    pub classes: Vec<ClassSpec>,
+    pub fee_tokens: FeeTokensSpec,
+    #[serde(default)]
+    pub erc20_tokens: Vec<Erc20Spec>,
+    #[serde(default)]
+    pub accounts: Vec<AccountsSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udc: Option<ContractSpec>,
+    #[serde(default)]
+    pub contracts: Vec<ContractSpec>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum GenesisSpecError {
+    #[error("unknown class {0}")]
+    UnknownClass(String),
+    #[error("class {0} is declared more than once")]
+    DuplicateClassName(String),
+    #[error("version 1 class {0} has no compiled class hash")]
+    MissingCompiledClassHash(String),
+    #[error("{0:?} is not a valid cairo short string")]
+    InvalidShortString(String),
+    #[error("the supply of ERC20 {symbol} overflows")]
+    SupplyOverflow { symbol: String },
+}
+
+/// Genesis data being expanded from a spec
+struct Expansion<'a> {
+    classes: HashMap<&'a str, &'a ClassSpec>,
+    data: GenesisData,
+}
+
+impl<'a> Expansion<'a> {
+    fn class_hash(&self, name: &str) -> Result<ClassHash, GenesisSpecError> {
+        self.classes.get(name).map(|class| class.class_hash).ok_or_else(|| GenesisSpecError::UnknownClass(name.into()))
+    }
+
+    fn store(&mut self, address: ContractAddress, key: FieldElement, value: FieldElement) {
+        self.data.storage.push(((address, StorageKey::from(key)), value.into()));
+    }
+
+    /// Store a u256 at `key` and `key + 1`, the way cairo 0 `Uint256` storage variables are laid
+    /// out
+    fn store_u256(&mut self, address: ContractAddress, key: FieldElement, value: u128) {
+        self.store(address, key, value.into());
+        self.store(address, key + FieldElement::ONE, FieldElement::ZERO);
+    }
+
+    /// Deploy an ERC20, `balances` being the initial balances on top of the supply of the recipient
+    fn deploy_erc20(
+        &mut self,
+        erc20: &Erc20Spec,
+        mut balances: Vec<(ContractAddress, u128)>,
+    ) -> Result<(), GenesisSpecError> {
+        let class_hash = self.class_hash(&erc20.class)?;
+        self.data.contracts.push((erc20.address, class_hash));
+
+        let short_string =
+            |s: &str| cairo_short_string_to_felt(s).map_err(|_| GenesisSpecError::InvalidShortString(s.into()));
+        self.store(erc20.address, storage_var_address(ERC20_NAME, &[]), short_string(&erc20.name)?);
+        self.store(erc20.address, storage_var_address(ERC20_SYMBOL, &[]), short_string(&erc20.symbol)?);
+        self.store(erc20.address, storage_var_address(ERC20_DECIMALS, &[]), erc20.decimals.into());
+
+        let overflow = || GenesisSpecError::SupplyOverflow { symbol: erc20.symbol.clone() };
+        if let Some(recipient) = erc20.recipient {
+            match balances.iter_mut().find(|(address, _)| *address == recipient) {
+                Some((_, balance)) => *balance = balance.checked_add(erc20.initial_supply).ok_or_else(overflow)?,
+                None => balances.push((recipient, erc20.initial_supply)),
+            }
+        }
+        let total_supply =
+            balances.iter().try_fold(0u128, |total, (_, balance)| total.checked_add(*balance)).ok_or_else(overflow)?;
+
+        self.store_u256(erc20.address, storage_var_address(ERC20_TOTAL_SUPPLY, &[]), total_supply);
+        for (address, balance) in balances.into_iter().filter(|(_, balance)| *balance > 0) {
+            self.store_u256(erc20.address, storage_var_address(ERC20_BALANCES, &[address.0]), balance);
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn storage_var_address(name: &str, keys: &[FieldElement]) -> FieldElement {
+    get_storage_var_address(name, keys).expect("storage variable names are ASCII")
+}
+
+impl GenesisSpec {
+    /// Expand the spec into the storage, contracts and predeployed accounts it describes
+    pub fn expand(&self) -> Result<GenesisData, GenesisSpecError> {
+        let mut expansion = Expansion { classes: HashMap::new(), data: GenesisData::builder(&self.chain_id).build() };
+
+        for class in &self.classes {
+            if expansion.classes.insert(&class.name, class).is_some() {
+                return Err(GenesisSpecError::DuplicateClassName(class.name.clone()));
+            }
+            let contract_class = ContractClass::Path {
+                path: class.path.clone(),
+                version: class.version,
+                sierra_path: class.sierra_path.clone(),
+            };
+            expansion.data.contract_classes.push((class.class_hash, contract_class));
+            if class.version == 1 {
+                let compiled_class_hash = class
+                    .compiled_class_hash
+                    .ok_or_else(|| GenesisSpecError::MissingCompiledClassHash(class.name.clone()))?;
+                expansion.data.sierra_class_hash_to_casm_class_hash.push((class.class_hash, compiled_class_hash));
+            }
+        }
+
+        let mut funded_with_eth = Vec::new();
+        let mut funded_with_strk = Vec::new();
+        for accounts in &self.accounts {
+            let class_hash = expansion.class_hash(&accounts.class)?;
+            for (index, public_key) in accounts.public_keys.iter().enumerate() {
+                let address: ContractAddress =
+                    get_contract_address(public_key.0, class_hash.0, &[public_key.0], FieldElement::ZERO).into();
+                expansion.data.contracts.push((address, class_hash));
+                expansion.store(address, storage_var_address(&accounts.public_key_storage_var, &[]), public_key.0);
+                expansion.data.predeployed_accounts.push(PredeployedAccount {
+                    contract_address: address,
+                    class_hash,
+                    name: format!("{} #{}", accounts.class, index),
+                    private_key: None,
+                    public_key: *public_key,
+                });
+                funded_with_eth.push((address, accounts.eth_balance));
+                funded_with_strk.push((address, accounts.strk_balance));
+            }
+        }
+
+        let (eth, strk) = (&self.fee_tokens.eth, &self.fee_tokens.strk);
+        expansion.deploy_erc20(eth, funded_with_eth)?;
+        expansion.deploy_erc20(strk, funded_with_strk)?;
+        expansion.data.eth_fee_token_address = eth.address;
+        expansion.data.strk_fee_token_address = strk.address;
+
+        for erc20 in &self.erc20_tokens {
+            expansion.deploy_erc20(erc20, Vec::new())?;
+        }
+
+        for contract in self.udc.iter().chain(&self.contracts) {
+            let class_hash = expansion.class_hash(&contract.class)?;
+            expansion.data.contracts.push((contract.address, class_hash));
+        }
+
+        Ok(expansion.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::GenesisLoader;
+
+    const OPENZEPPELIN_ACCOUNT_CLASS_HASH: &str = "0x006280083f8c2a2db9f737320d5e3029b380e0e820fe24b8d312a6a34fdba0cd";
+    const ERC20_CLASS_HASH: &str = "0x0372ee6669dc86563007245ed7343d5180b96221ce28f44408cff2898038dbd4";
+    const UDC_CLASS_HASH: &str = "0x07b3e05f48f0c69e4a65ce5e076a66271a527aff2c34ce1083ec6e1526997a69";
+
+    fn felt(hex: &str) -> HexFelt {
+        FieldElement::from_hex_be(hex).unwrap().into()
+    }
+
+    fn erc20(address: u64, name: &str, symbol: &str) -> Erc20Spec {
+        Erc20Spec {
+            address: FieldElement::from(address).into(),
+            class: "erc20".into(),
+            name: name.into(),
+            symbol: symbol.into(),
+            decimals: 18,
+            initial_supply: 0,
+            recipient: None,
+        }
+    }
+
+    fn spec() -> GenesisSpec {
+        let class = |name: &str, class_hash: &str, path: &str| ClassSpec {
+            name: name.into(),
+            class_hash: felt(class_hash),
+            path: path.into(),
+            version: 0,
+            compiled_class_hash: None,
+            sierra_path: None,
+        };
+        GenesisSpec {
+            chain_id: "MADARA".into(),
+            classes: vec![
+                class("account", OPENZEPPELIN_ACCOUNT_CLASS_HASH, "genesis-assets/OpenzeppelinAccount.json"),
+                class("erc20", ERC20_CLASS_HASH, "genesis-assets/ERC20.json"),
+                class("udc", UDC_CLASS_HASH, "genesis-assets/UniversalDeployer.json"),
+            ],
+            fee_tokens: FeeTokensSpec { eth: erc20(0x10, "Ether", "ETH"), strk: erc20(0x11, "Starknet Token", "STRK") },
+            erc20_tokens: vec![Erc20Spec {
+                initial_supply: 1000,
+                recipient: Some(FieldElement::from(0x20u8).into()),
+                ..erc20(0x12, "Token", "TKN")
+            }],
+            accounts: vec![AccountsSpec {
+                class: "account".into(),
+                public_keys: vec![FieldElement::ONE.into(), FieldElement::TWO.into()],
+                public_key_storage_var: DEFAULT_PUBLIC_KEY_STORAGE_VAR.into(),
+                eth_balance: 1000,
+                strk_balance: 10,
+            }],
+            udc: Some(ContractSpec { address: FieldElement::from(0x13u8).into(), class: "udc".into() }),
+            contracts: Vec::new(),
+        }
+    }
+
+    fn storage_value(data: &GenesisData, address: u64, key: FieldElement) -> Option<FieldElement> {
+        data.storage
+            .iter()
+            .find(|((contract, storage_key), _)| contract.0 == FieldElement::from(address) && storage_key.0 == key)
+            .map(|(_, value)| value.0)
+    }
+
+    #[test]
+    fn accounts_are_deployed_and_funded() {
+        let data = spec().expand().unwrap();
+
+        assert_eq!(data.predeployed_accounts.len(), 2);
+        let account = &data.predeployed_accounts[1];
+        let expected_address = get_contract_address(
+            FieldElement::TWO,
+            felt(OPENZEPPELIN_ACCOUNT_CLASS_HASH).0,
+            &[FieldElement::TWO],
+            FieldElement::ZERO,
+        );
+        assert_eq!(account.contract_address.0, expected_address);
+        assert!(data.contracts.contains(&(account.contract_address, felt(OPENZEPPELIN_ACCOUNT_CLASS_HASH))));
+        assert!(data.storage.contains(&(
+            (account.contract_address, storage_var_address(DEFAULT_PUBLIC_KEY_STORAGE_VAR, &[]).into()),
+            FieldElement::TWO.into()
+        )));
+
+        let balance_key = storage_var_address(ERC20_BALANCES, &[expected_address]);
+        assert_eq!(storage_value(&data, 0x10, balance_key), Some(FieldElement::from(1000u32)));
+        assert_eq!(storage_value(&data, 0x11, balance_key), Some(FieldElement::from(10u32)));
+        assert_eq!(storage_value(&data, 0x10, storage_var_address(ERC20_TOTAL_SUPPLY, &[])), Some(2000u32.into()));
+    }
+
+    #[test]
+    fn erc20_metadata_and_supply_are_stored() {
+        let data = spec().expand().unwrap();
+
+        let symbol = cairo_short_string_to_felt("TKN").unwrap();
+        assert_eq!(storage_value(&data, 0x12, storage_var_address(ERC20_SYMBOL, &[])), Some(symbol));
+        assert_eq!(storage_value(&data, 0x12, storage_var_address(ERC20_DECIMALS, &[])), Some(18u8.into()));
+        assert_eq!(storage_value(&data, 0x12, storage_var_address(ERC20_TOTAL_SUPPLY, &[])), Some(1000u32.into()));
+        let balance_key = storage_var_address(ERC20_BALANCES, &[FieldElement::from(0x20u8)]);
+        assert_eq!(storage_value(&data, 0x12, balance_key), Some(1000u32.into()));
+        assert_eq!(storage_value(&data, 0x12, balance_key + FieldElement::ONE), Some(FieldElement::ZERO));
+    }
+
+    #[test]
+    fn expanded_spec_is_valid() {
+        let data = spec().expand().unwrap();
+        let configs_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../configs");
+
+        GenesisLoader::new(configs_dir, data).validate().unwrap();
+    }
+
+    #[test]
+    fn unknown_classes_are_rejected() {
+        let mut spec = spec();
+        spec.accounts[0].class = "argent".into();
+
+        assert_eq!(spec.expand(), Err(GenesisSpecError::UnknownClass("argent".into())));
+    }
+
+    #[test]
+    fn spec_round_trips_through_json() {
+        let json = serde_json::to_string(&spec()).unwrap();
+
+        assert_eq!(serde_json::from_str::<GenesisSpec>(&json).unwrap(), spec());
+    }
+}
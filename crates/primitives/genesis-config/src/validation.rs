@@ -0,0 +1,320 @@
+//! Consistency checks of a [`GenesisLoader`] before it is turned into a genesis state.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+
+use starknet_core::types::contract::legacy::LegacyContractClass;
+use starknet_core::types::contract::{CompiledClass, SierraClass};
+use starknet_crypto::FieldElement;
+
+use crate::{ClassHash, ContractAddress, ContractClass, GenesisLoader};
+
+/// A faulty entry of the genesis data
+#[derive(thiserror::Error, Debug)]
+pub enum GenesisValidationError {
+    #[error("class {class_hash:#x}: unable to read {}: {reason}", path.display())]
+    UnreadableClass { class_hash: ClassHash, path: PathBuf, reason: String },
+    #[error("class {class_hash:#x}: {} is not a valid version {version} class: {reason}", path.display())]
+    InvalidClass { class_hash: ClassHash, path: PathBuf, version: u8, reason: String },
+    #[error("class {class_hash:#x}: unsupported class version {version}")]
+    UnsupportedClassVersion { class_hash: ClassHash, version: u8 },
+    #[error("class {declared:#x}: the class at {} has hash {computed:#x}", path.display())]
+    ClassHashMismatch { declared: ClassHash, computed: ClassHash, path: PathBuf },
+    #[error("class {class_hash:#x} is declared more than once")]
+    DuplicateClass { class_hash: ClassHash },
+    #[error("sierra class {class_hash:#x} has no compiled class hash")]
+    MissingCompiledClassHash { class_hash: ClassHash },
+    #[error("sierra class {class_hash:#x}: compiled class hash is {declared:#x} but the CASM class at {} hashes to {computed:#x}", path.display())]
+    CompiledClassHashMismatch { class_hash: ClassHash, declared: ClassHash, computed: ClassHash, path: PathBuf },
+    #[error("compiled class hash {compiled_class_hash:#x} is given for the undeclared sierra class {class_hash:#x}")]
+    CompiledClassHashOfUndeclaredClass { class_hash: ClassHash, compiled_class_hash: ClassHash },
+    #[error("contract {address:#x} is an instance of the undeclared class {class_hash:#x}")]
+    UndeclaredClass { address: ContractAddress, class_hash: ClassHash },
+    #[error("contract {address:#x} is deployed more than once")]
+    DuplicateAddress { address: ContractAddress },
+    #[error("predeployed account {name} at {address:#x} is not among the genesis contracts")]
+    UndeployedAccount { name: String, address: ContractAddress },
+    #[error(
+        "predeployed account {name} at {address:#x} is an instance of {declared:#x} but the contract is an instance \
+         of {deployed:#x}"
+    )]
+    AccountClassMismatch { name: String, address: ContractAddress, declared: ClassHash, deployed: ClassHash },
+}
+
+/// Every faulty entry of the genesis data, in the order they were found
+#[derive(Debug)]
+pub struct GenesisValidationErrors(pub Vec<GenesisValidationError>);
+
+impl fmt::Display for GenesisValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid genesis entries:", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n - {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GenesisValidationErrors {}
+
+impl GenesisLoader {
+    /// Check that the genesis data is consistent with itself and with the class files it points to.
+    ///
+    /// Version 0 classes are hashed and compared to their declared hash. Version 1 classes are
+    /// declared by their sierra class hash but it is their CASM class that is part of the genesis:
+    /// the compiled class hash of the CASM class is compared to the one in
+    /// `sierra_class_hash_to_casm_class_hash`, and the sierra class at `sierra_path`, when given,
+    /// is hashed and compared to the declared class hash.
+    pub fn validate(&self) -> Result<(), GenesisValidationErrors> {
+        let data = self.data();
+        let mut errors = Vec::new();
+
+        let compiled_class_hashes: HashMap<FieldElement, ClassHash> =
+            data.sierra_class_hash_to_casm_class_hash.iter().map(|(sierra, casm)| (sierra.0, *casm)).collect();
+
+        let mut classes = HashSet::new();
+        for (class_hash, class) in &data.contract_classes {
+            if !classes.insert(class_hash.0) {
+                errors.push(GenesisValidationError::DuplicateClass { class_hash: *class_hash });
+                continue;
+            }
+            if let Err(error) = self.validate_class(*class_hash, class, &compiled_class_hashes) {
+                errors.push(error);
+            }
+        }
+
+        for (class_hash, compiled_class_hash) in &data.sierra_class_hash_to_casm_class_hash {
+            if !classes.contains(&class_hash.0) {
+                errors.push(GenesisValidationError::CompiledClassHashOfUndeclaredClass {
+                    class_hash: *class_hash,
+                    compiled_class_hash: *compiled_class_hash,
+                });
+            }
+        }
+
+        let mut contracts = HashMap::new();
+        for (address, class_hash) in &data.contracts {
+            if contracts.insert(address.0, *class_hash).is_some() {
+                errors.push(GenesisValidationError::DuplicateAddress { address: *address });
+            }
+            if !classes.contains(&class_hash.0) {
+                errors.push(GenesisValidationError::UndeclaredClass { address: *address, class_hash: *class_hash });
+            }
+        }
+
+        for account in &data.predeployed_accounts {
+            match contracts.get(&account.contract_address.0) {
+                None => errors.push(GenesisValidationError::UndeployedAccount {
+                    name: account.name.clone(),
+                    address: account.contract_address,
+                }),
+                Some(deployed) if deployed.0 != account.class_hash.0 => {
+                    errors.push(GenesisValidationError::AccountClassMismatch {
+                        name: account.name.clone(),
+                        address: account.contract_address,
+                        declared: account.class_hash,
+                        deployed: *deployed,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(GenesisValidationErrors(errors)) }
+    }
+
+    fn validate_class(
+        &self,
+        class_hash: ClassHash,
+        class: &ContractClass,
+        compiled_class_hashes: &HashMap<FieldElement, ClassHash>,
+    ) -> Result<(), GenesisValidationError> {
+        let ContractClass::Path { path, version, sierra_path } = class;
+        let path = self.base_path().join(path);
+        let version = *version;
+
+        let read = |path: &PathBuf| {
+            std::fs::read_to_string(path).map_err(|e| GenesisValidationError::UnreadableClass {
+                class_hash,
+                path: path.clone(),
+                reason: e.to_string(),
+            })
+        };
+        let json = read(&path)?;
+        let invalid_class =
+            |reason: String| GenesisValidationError::InvalidClass { class_hash, path: path.clone(), version, reason };
+
+        match version {
+            0 => {
+                let class: LegacyContractClass =
+                    serde_json::from_str(&json).map_err(|e| invalid_class(e.to_string()))?;
+                let computed = class.class_hash().map_err(|e| invalid_class(e.to_string()))?;
+                if computed != class_hash.0 {
+                    return Err(GenesisValidationError::ClassHashMismatch {
+                        declared: class_hash,
+                        computed: computed.into(),
+                        path,
+                    });
+                }
+            }
+            1 => {
+                let declared = *compiled_class_hashes
+                    .get(&class_hash.0)
+                    .ok_or(GenesisValidationError::MissingCompiledClassHash { class_hash })?;
+                let class: CompiledClass = serde_json::from_str(&json).map_err(|e| invalid_class(e.to_string()))?;
+                let computed = class.class_hash().map_err(|e| invalid_class(e.to_string()))?;
+                if computed != declared.0 {
+                    return Err(GenesisValidationError::CompiledClassHashMismatch {
+                        class_hash,
+                        declared,
+                        computed: computed.into(),
+                        path,
+                    });
+                }
+
+                if let Some(sierra_path) = sierra_path {
+                    let path = self.base_path().join(sierra_path);
+                    let invalid_class = |reason: String| GenesisValidationError::InvalidClass {
+                        class_hash,
+                        path: path.clone(),
+                        version,
+                        reason,
+                    };
+                    let class: SierraClass =
+                        serde_json::from_str(&read(&path)?).map_err(|e| invalid_class(e.to_string()))?;
+                    let computed = class.class_hash().map_err(|e| invalid_class(e.to_string()))?;
+                    if computed != class_hash.0 {
+                        return Err(GenesisValidationError::ClassHashMismatch {
+                            declared: class_hash,
+                            computed: computed.into(),
+                            path,
+                        });
+                    }
+                }
+            }
+            version => return Err(GenesisValidationError::UnsupportedClassVersion { class_hash, version }),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use starknet_crypto::FieldElement;
+
+    use super::*;
+    use crate::GenesisData;
+
+    const NO_VALIDATE_ACCOUNT_CLASS_HASH: &str = "0x0279d77db761fba82e0054125a6fdb5f6baa6286fa3fb73450cc44d193c2d37f";
+    const NO_VALIDATE_ACCOUNT_SIERRA_CLASS_HASH: &str =
+        "0x035ccefcf9d5656da623468e27e682271cd327af196785df99e7fee1436b6276";
+    const NO_VALIDATE_ACCOUNT_COMPILED_CLASS_HASH: &str =
+        "0x02f99bf9799ada84cd5ac0d0fe36b9d8f65efcb377cd2e8cf8309ad2daf15e4b";
+
+    fn configs_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../configs")
+    }
+
+    fn felt(hex: &str) -> FieldElement {
+        FieldElement::from_hex_be(hex).unwrap()
+    }
+
+    fn no_validate_account() -> ContractClass {
+        ContractClass::Path { path: "genesis-assets/NoValidateAccount.json".into(), version: 0, sierra_path: None }
+    }
+
+    #[test]
+    fn default_genesis_is_valid() {
+        let data: GenesisData =
+            serde_json::from_str(&std::fs::read_to_string(configs_dir().join("genesis-assets/genesis.json")).unwrap())
+                .unwrap();
+
+        GenesisLoader::new(configs_dir(), data).validate().unwrap();
+    }
+
+    #[test]
+    fn class_hash_mismatch_is_reported() {
+        let data = GenesisData::builder("MADARA").class(FieldElement::ONE, no_validate_account()).build();
+
+        let errors = GenesisLoader::new(configs_dir(), data).validate().unwrap_err().0;
+
+        assert!(matches!(
+            errors.as_slice(),
+            [GenesisValidationError::ClassHashMismatch { declared, computed, .. }]
+                if declared.0 == FieldElement::ONE && computed.0 == felt(NO_VALIDATE_ACCOUNT_CLASS_HASH)
+        ));
+    }
+
+    #[test]
+    fn compiled_class_hash_mismatch_is_reported() {
+        let data = GenesisData::builder("MADARA")
+            .casm_class(
+                felt(NO_VALIDATE_ACCOUNT_SIERRA_CLASS_HASH),
+                FieldElement::ONE,
+                "genesis-assets/NoValidateAccount.casm.json",
+            )
+            .build();
+
+        let errors = GenesisLoader::new(configs_dir(), data).validate().unwrap_err().0;
+
+        assert!(matches!(
+            errors.as_slice(),
+            [GenesisValidationError::CompiledClassHashMismatch { declared, computed, .. }]
+                if declared.0 == FieldElement::ONE && computed.0 == felt(NO_VALIDATE_ACCOUNT_COMPILED_CLASS_HASH)
+        ));
+    }
+
+    #[test]
+    fn sierra_class_hash_mismatch_is_reported() {
+        let data = GenesisData::builder("MADARA")
+            .sierra_class(
+                FieldElement::ONE,
+                felt(NO_VALIDATE_ACCOUNT_COMPILED_CLASS_HASH),
+                "genesis-assets/NoValidateAccount.sierra.json",
+                "genesis-assets/NoValidateAccount.casm.json",
+            )
+            .build();
+
+        let errors = GenesisLoader::new(configs_dir(), data).validate().unwrap_err().0;
+
+        assert!(matches!(
+            errors.as_slice(),
+            [GenesisValidationError::ClassHashMismatch { declared, computed, .. }]
+                if declared.0 == FieldElement::ONE && computed.0 == felt(NO_VALIDATE_ACCOUNT_SIERRA_CLASS_HASH)
+        ));
+    }
+
+    #[test]
+    fn every_faulty_entry_is_reported() {
+        let class_hash = felt(NO_VALIDATE_ACCOUNT_CLASS_HASH);
+        let data = GenesisData::builder("MADARA")
+            .class(class_hash, no_validate_account())
+            .class(
+                FieldElement::TWO,
+                ContractClass::Path { path: "genesis-assets/missing.json".into(), version: 0, sierra_path: None },
+            )
+            .contract(FieldElement::ONE, class_hash)
+            .contract(FieldElement::ONE, class_hash)
+            .contract(FieldElement::THREE, FieldElement::from(42u8))
+            .build();
+
+        let errors = GenesisLoader::new(configs_dir(), data).validate().unwrap_err().0;
+
+        assert_eq!(errors.len(), 3);
+        assert!(
+            matches!(&errors[0], GenesisValidationError::UnreadableClass { class_hash, .. } if class_hash.0 == FieldElement::TWO)
+        );
+        assert!(
+            matches!(&errors[1], GenesisValidationError::DuplicateAddress { address } if address.0 == FieldElement::ONE)
+        );
+        assert!(matches!(
+            &errors[2],
+            GenesisValidationError::UndeclaredClass { address, class_hash }
+                if address.0 == FieldElement::THREE && class_hash.0 == FieldElement::from(42u8)
+        ));
+    }
+}
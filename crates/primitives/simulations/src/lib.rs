@@ -1,7 +1,9 @@
+use blockifier::execution::call_info::CallInfo;
 use blockifier::state::cached_state::CommitmentStateDiff;
 use blockifier::state::errors::StateError;
 use blockifier::transaction::errors::{TransactionExecutionError, TransactionFeeError};
 use blockifier::transaction::objects::{FeeType, TransactionExecutionInfo};
+use starknet_api::core::ContractAddress;
 use starknet_core::types::{PriceUnit, SimulationFlag, SimulationFlagForEstimateFee};
 
 #[derive(Debug, Clone)]
@@ -13,6 +15,7 @@ pub enum SimulationError {
     MissingL1GasUsage,
     StateDiff,
     EstimateFeeFailed(String),
+    TransactionRejectedByFilter(String),
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +50,19 @@ pub struct TransactionSimulation {
     pub execution_info: TransactionExecutionInfo,
     pub state_diff: CommitmentStateDiff,
     pub fee_estimate: FeeEstimate,
+    /// The paymaster sponsoring the transaction, if any
+    pub paymaster: Option<PaymasterValidation>,
+}
+
+/// The paymaster sponsoring a simulated transaction, and the outcome of its validation.
+#[derive(Debug)]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct PaymasterValidation {
+    pub address: ContractAddress,
+    /// The call to the paymaster validation entry point, which is also part of the
+    /// `validate_call_info` of the transaction
+    pub validate_call_info: Option<CallInfo>,
 }
 
 pub type TransactionSimulationResult = Result<TransactionSimulation, SimulationError>;
@@ -115,6 +131,8 @@ pub struct FeeEstimate {
     pub data_gas_price: u128,
     pub overall_fee: u128,
     pub fee_type: FeeType,
+    /// The paymaster charged the fee instead of the sender, if the transaction is sponsored
+    pub paymaster: Option<ContractAddress>,
 }
 
 impl From<&FeeEstimate> for starknet_core::types::FeeEstimate {
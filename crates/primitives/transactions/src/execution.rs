@@ -14,8 +14,8 @@ use blockifier::state::state_api::{State, StateReader, StateResult};
 use blockifier::transaction::account_transaction::{AccountTransaction, ValidateExecuteCallInfo};
 use blockifier::transaction::errors::{TransactionExecutionError, TransactionFeeError, TransactionPreValidationError};
 use blockifier::transaction::objects::{
-    GasVector, HasRelatedFeeType, ResourcesMapping, TransactionExecutionInfo, TransactionExecutionResult,
-    TransactionInfo, TransactionInfoCreator,
+    CurrentTransactionInfo, GasVector, HasRelatedFeeType, ResourcesMapping, TransactionExecutionInfo,
+    TransactionExecutionResult, TransactionInfo, TransactionInfoCreator,
 };
 use blockifier::transaction::transaction_types::TransactionType;
 use blockifier::transaction::transactions::{
@@ -32,8 +32,65 @@ use starknet_api::transaction::{Calldata, Fee, ResourceBounds, TransactionVersio
 
 use super::SIMULATE_TX_VERSION_OFFSET;
 
+/// Reason why a [`TransactionFilter`], or the block resource limits, refused a transaction.
+///
+/// The discriminant is used as the `InvalidTransaction::Custom` code when the transaction is
+/// rejected by the transaction pool, so that it can be mapped back to a meaningful RPC error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TransactionRejection {
+    /// The filter refused the transaction without giving a more specific reason.
+    Filtered = 0,
+    /// The sender address is in the deny list.
+    SenderDenied = 1,
+    /// The sender address is not allowed to declare classes.
+    DeclarerNotAllowed = 2,
+    /// The account class hash is not allowed to be deployed.
+    ClassHashNotAllowed = 3,
+    /// The calldata length exceeds the configured maximum.
+    CalldataTooLarge = 4,
+    /// Executing the transaction uses more resources than a block can.
+    ExceedsBlockResourceLimits = 5,
+}
+
+impl TransactionRejection {
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Self::Filtered),
+            1 => Some(Self::SenderDenied),
+            2 => Some(Self::DeclarerNotAllowed),
+            3 => Some(Self::ClassHashNotAllowed),
+            4 => Some(Self::CalldataTooLarge),
+            5 => Some(Self::ExceedsBlockResourceLimits),
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for TransactionRejection {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Filtered => write!(f, "transaction refused by the chain transaction filter"),
+            Self::SenderDenied => write!(f, "sender address is not allowed to send transactions"),
+            Self::DeclarerNotAllowed => write!(f, "sender address is not allowed to declare classes"),
+            Self::ClassHashNotAllowed => write!(f, "account class hash is not allowed to be deployed"),
+            Self::CalldataTooLarge => write!(f, "calldata exceeds the maximum allowed length"),
+            Self::ExceedsBlockResourceLimits => write!(f, "transaction exceeds the block resource limits"),
+        }
+    }
+}
+
 pub trait TransactionFilter<T> {
     fn is_valid(transaction: &T) -> bool;
+
+    /// Same as [`TransactionFilter::is_valid`], but reports why the transaction was refused.
+    fn check(transaction: &T) -> Result<(), TransactionRejection> {
+        if Self::is_valid(transaction) { Ok(()) } else { Err(TransactionRejection::Filtered) }
+    }
 }
 
 impl<T> TransactionFilter<T> for () {
@@ -42,6 +99,29 @@ impl<T> TransactionFilter<T> for () {
     }
 }
 
+/// Filters can be composed by grouping them in a tuple.
+/// The transaction is accepted only if every filter of the tuple accepts it.
+macro_rules! impl_transaction_filter_for_tuple {
+    ($($filter:ident),+) => {
+        impl<T, $($filter: TransactionFilter<T>),+> TransactionFilter<T> for ($($filter,)+) {
+            fn is_valid(transaction: &T) -> bool {
+                Self::check(transaction).is_ok()
+            }
+
+            fn check(transaction: &T) -> Result<(), TransactionRejection> {
+                $($filter::check(transaction)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_transaction_filter_for_tuple!(A);
+impl_transaction_filter_for_tuple!(A, B);
+impl_transaction_filter_for_tuple!(A, B, C);
+impl_transaction_filter_for_tuple!(A, B, C, D);
+impl_transaction_filter_for_tuple!(A, B, C, D, E);
+
 pub struct BanInvokeV0TransactionRule;
 
 impl TransactionFilter<InvokeTransaction> for BanInvokeV0TransactionRule {
@@ -171,8 +251,24 @@ impl GetTxType for L1HandlerTransaction {
     }
 }
 
+/// How the nonce of an incoming transaction is checked against the account nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceValidation {
+    /// The transaction nonce must be equal to the account nonce.
+    Strict,
+    /// The transaction nonce must be greater or equal to the account nonce.
+    /// Used when admitting transactions in the pool.
+    Lenient,
+    /// Any transaction nonce is accepted. The account nonce is still incremented.
+    Disabled,
+}
+
 pub trait HandleNonce {
-    fn handle_nonce(state: &mut dyn State, tx_info: &TransactionInfo, strict: bool) -> TransactionExecutionResult<()> {
+    fn handle_nonce(
+        state: &mut dyn State,
+        tx_info: &TransactionInfo,
+        nonce_validation: NonceValidation,
+    ) -> TransactionExecutionResult<()> {
         if tx_info.is_v0() {
             return Ok(());
         }
@@ -180,7 +276,11 @@ pub trait HandleNonce {
         let address = tx_info.sender_address();
         let account_nonce = state.get_nonce_at(address)?;
         let incoming_tx_nonce = tx_info.nonce();
-        let valid_nonce = if strict { account_nonce == incoming_tx_nonce } else { account_nonce <= incoming_tx_nonce };
+        let valid_nonce = match nonce_validation {
+            NonceValidation::Strict => account_nonce == incoming_tx_nonce,
+            NonceValidation::Lenient => account_nonce <= incoming_tx_nonce,
+            NonceValidation::Disabled => true,
+        };
 
         if valid_nonce {
             state.increment_nonce(address)?;
@@ -357,9 +457,191 @@ impl GetValidateEntryPointSelector for L1HandlerTransaction {
     }
 }
 
+/// Name of the entry point called on a paymaster contract to approve paying for a transaction.
+pub const VALIDATE_PAYMASTER_ENTRY_POINT_NAME: &str = "__validate_paymaster__";
+
+/// Interface of the paymaster contracts allowed to sponsor transactions.
+///
+/// A v3 transaction is sponsored when its `paymaster_data` is not empty: the first element is the
+/// address of the paymaster contract and the remaining ones are forwarded to its validation entry
+/// point, after the sender address and the transaction hash.
+/// The paymaster is charged the transaction fee instead of the sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "parity-scale-codec", derive(parity_scale_codec::Encode, parity_scale_codec::Decode))]
+#[cfg_attr(feature = "scale-info", derive(scale_info::TypeInfo))]
+pub struct PaymasterConfig {
+    /// Selector of the entry point called on the paymaster contract to approve paying for a
+    /// transaction.
+    pub validate_entry_point_selector: EntryPointSelector,
+}
+
+impl Default for PaymasterConfig {
+    fn default() -> Self {
+        Self { validate_entry_point_selector: selector_from_name(VALIDATE_PAYMASTER_ENTRY_POINT_NAME) }
+    }
+}
+
+/// The paymaster sponsoring a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paymaster {
+    /// Address of the paymaster contract.
+    pub address: ContractAddress,
+    /// Data forwarded to the paymaster validation entry point.
+    pub data: Vec<StarkFelt>,
+    /// Selector of the paymaster validation entry point.
+    pub validate_entry_point_selector: EntryPointSelector,
+}
+
+impl Paymaster {
+    /// Returns the paymaster declared in the `paymaster_data` of a transaction, if any.
+    ///
+    /// Always returns `None` when no [`PaymasterConfig`] is given, in which case the
+    /// `paymaster_data` is ignored and the sender pays for its own transaction.
+    pub fn from_tx_info(
+        tx_info: &TransactionInfo,
+        config: Option<&PaymasterConfig>,
+    ) -> TransactionExecutionResult<Option<Self>> {
+        let (Some(config), TransactionInfo::Current(CurrentTransactionInfo { paymaster_data, .. })) = (config, tx_info)
+        else {
+            return Ok(None);
+        };
+
+        match paymaster_data.0.split_first() {
+            Some((address, data)) => Ok(Some(Self {
+                address: ContractAddress::try_from(*address)?,
+                data: data.to_vec(),
+                validate_entry_point_selector: config.validate_entry_point_selector,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the paymaster sponsoring an account transaction, see [`Self::from_tx_info`].
+    pub fn from_account_transaction(
+        transaction: &AccountTransaction,
+        config: Option<&PaymasterConfig>,
+    ) -> TransactionExecutionResult<Option<Self>> {
+        Self::from_tx_info(&transaction.create_tx_info(), config)
+    }
+
+    /// Returns the call to the paymaster validation entry point among the validation calls of the
+    /// transaction it sponsors.
+    ///
+    /// The paymaster validation is reported as the last inner call of the account validation, or
+    /// as the whole validation when the account one is skipped, see [`Validate::validate`].
+    pub fn find_validate_call_info<'a>(&self, validate_call_info: Option<&'a CallInfo>) -> Option<&'a CallInfo> {
+        let is_paymaster_validation = |call_info: &&CallInfo| {
+            call_info.call.storage_address == self.address
+                && call_info.call.entry_point_selector == self.validate_entry_point_selector
+        };
+
+        let validate_call_info = validate_call_info?;
+        if is_paymaster_validation(&validate_call_info) {
+            return Some(validate_call_info);
+        }
+        validate_call_info.inner_calls.last().filter(is_paymaster_validation)
+    }
+
+    /// Runs the paymaster validation entry point.
+    ///
+    /// It is called with the sender address, the transaction hash and the paymaster data, and must
+    /// not fail for the paymaster to accept paying for the transaction.
+    pub fn run_validate_entrypoint(
+        &self,
+        state: &mut dyn State,
+        tx_context: Arc<TransactionContext>,
+        resources: &mut ExecutionResources,
+        remaining_gas: &mut u64,
+        limit_steps_by_resources: bool,
+    ) -> TransactionExecutionResult<CallInfo> {
+        let mut context = EntryPointExecutionContext::new_validate(tx_context, limit_steps_by_resources)?;
+        let tx_info = &context.tx_context.tx_info;
+
+        let mut calldata = Vec::with_capacity(self.data.len() + 2);
+        calldata.push(*tx_info.sender_address().0.key());
+        calldata.push(tx_info.transaction_hash().0);
+        calldata.extend_from_slice(&self.data);
+
+        let storage_address = self.address;
+        let selector = self.validate_entry_point_selector;
+        let validate_call = CallEntryPoint {
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: selector,
+            calldata: Calldata(calldata.into()),
+            class_hash: None,
+            code_address: None,
+            storage_address,
+            caller_address: tx_info.sender_address(),
+            call_type: CallType::Call,
+            initial_gas: *remaining_gas,
+        };
+
+        let validate_call_info = validate_call.execute(state, resources, &mut context).map_err(|error| {
+            TransactionExecutionError::ValidateTransactionError { error, storage_address, selector }
+        })?;
+
+        verify_validate_retdata(state, storage_address, &validate_call_info)?;
+        blockifier::transaction::transaction_utils::update_remaining_gas(remaining_gas, &validate_call_info);
+
+        Ok(validate_call_info)
+    }
+}
+
+/// Returns the context used to check and charge the fee of a transaction.
+///
+/// When the transaction is sponsored, the paymaster takes the place of the sender so that its
+/// balance is checked and it is the one transferring the fee to the sequencer.
+pub fn fee_payer_tx_context(
+    tx_context: &Arc<TransactionContext>,
+    paymaster: Option<&Paymaster>,
+) -> Arc<TransactionContext> {
+    match (paymaster, &tx_context.tx_info) {
+        (Some(paymaster), TransactionInfo::Current(tx_info)) => {
+            let mut tx_info = tx_info.clone();
+            tx_info.common_fields.sender_address = paymaster.address;
+
+            Arc::new(TransactionContext {
+                block_context: tx_context.block_context.clone(),
+                tx_info: TransactionInfo::Current(tx_info),
+            })
+        }
+        _ => tx_context.clone(),
+    }
+}
+
+/// Returns the fee a transaction was actually charged.
+///
+/// The fee is only charged when it is transferred to the sequencer, so it is zero for
+/// transactions executed while fees were disabled, as well as for L1 handlers which pay on L1.
+pub fn charged_fee(execution_info: &TransactionExecutionInfo) -> Fee {
+    if execution_info.fee_transfer_call_info.is_some() { execution_info.actual_fee } else { Fee(0) }
+}
+
+/// Cairo 1.0 validation entry points must return `VALID`.
+fn verify_validate_retdata(
+    state: &mut dyn State,
+    storage_address: ContractAddress,
+    validate_call_info: &CallInfo,
+) -> TransactionExecutionResult<()> {
+    let class_hash = state.get_class_hash_at(storage_address)?;
+    let contract_class = state.get_compiled_contract_class(class_hash)?;
+    if let ContractClass::V1(_) = contract_class {
+        let expected_retdata =
+            Retdata(vec![StarkFelt::try_from(blockifier::transaction::constants::VALIDATE_RETDATA)?]);
+        if validate_call_info.execution.retdata != expected_retdata {
+            return Err(TransactionExecutionError::InvalidValidateReturnData {
+                actual: validate_call_info.execution.retdata.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub trait Validate: Sized + GetValidateEntryPointSelector {
-    fn validate<F: TransactionFilter<Self>>(
+    fn validate(
         &self,
         state: &mut dyn State,
         tx_context: Arc<TransactionContext>,
@@ -367,15 +649,17 @@ pub trait Validate: Sized + GetValidateEntryPointSelector {
         remaining_gas: &mut u64,
         validate_tx: bool,
         charge_fee: bool,
-        strict_nonce_checking: bool,
+        nonce_validation: NonceValidation,
+        paymaster: Option<&Paymaster>,
     ) -> TransactionExecutionResult<Option<CallInfo>>;
 
-    fn perform_pre_validation_stage<F: TransactionFilter<Self>>(
+    fn perform_pre_validation_stage(
         &self,
         state: &mut dyn State,
         tx_context: Arc<TransactionContext>,
-        strict_nonce_checking: bool,
+        nonce_validation: NonceValidation,
         charge_fee: bool,
+        paymaster: Option<&Paymaster>,
     ) -> TransactionExecutionResult<()>;
 
     fn run_validate_entrypoint(
@@ -396,7 +680,7 @@ impl<
         + TransactionInfoCreator,
 > Validate for T
 {
-    fn validate<F: TransactionFilter<T>>(
+    fn validate(
         &self,
         state: &mut dyn State,
         tx_context: Arc<TransactionContext>,
@@ -404,36 +688,54 @@ impl<
         remaining_gas: &mut u64,
         validate_tx: bool,
         charge_fee: bool,
-        strict_nonce_checking: bool,
+        nonce_validation: NonceValidation,
+        paymaster: Option<&Paymaster>,
     ) -> TransactionExecutionResult<Option<CallInfo>> {
         // Check tx version, nonce and fee
-        self.perform_pre_validation_stage::<F>(state, tx_context.clone(), strict_nonce_checking, charge_fee)?;
+        self.perform_pre_validation_stage(state, tx_context.clone(), nonce_validation, charge_fee, paymaster)?;
 
         // Run the actual `validate` entrypoint
-        if validate_tx {
-            self.run_validate_entrypoint(state, tx_context, resources, remaining_gas, charge_fee)
+        let mut validate_call_info = if validate_tx {
+            self.run_validate_entrypoint(state, tx_context.clone(), resources, remaining_gas, charge_fee)?
         } else {
-            Ok(None)
+            None
+        };
+
+        // Then make sure the paymaster accepts to pay for the transaction.
+        // Its validation is reported as part of the account validation.
+        // It is not skipped along with the account validation: fee estimations and simulations must
+        // account for it and reject the transactions the paymaster would not pay for.
+        if let Some(paymaster) = paymaster {
+            let paymaster_call_info =
+                paymaster.run_validate_entrypoint(state, tx_context, resources, remaining_gas, charge_fee)?;
+            match validate_call_info.as_mut() {
+                Some(validate_call_info) => validate_call_info.inner_calls.push(paymaster_call_info),
+                None => validate_call_info = Some(paymaster_call_info),
+            }
         }
+
+        Ok(validate_call_info)
     }
 
-    fn perform_pre_validation_stage<F: TransactionFilter<T>>(
+    fn perform_pre_validation_stage(
         &self,
         state: &mut dyn State,
         tx_context: Arc<TransactionContext>,
-        strict_nonce_checking: bool,
+        nonce_validation: NonceValidation,
         charge_fee: bool,
+        paymaster: Option<&Paymaster>,
     ) -> TransactionExecutionResult<()> {
-        assert!(F::is_valid(self), "custom chain provided tx filter failed");
-
         // Check if nonce has a correct value
-        Self::handle_nonce(state, &tx_context.tx_info, strict_nonce_checking)?;
+        Self::handle_nonce(state, &tx_context.tx_info, nonce_validation)?;
 
-        // Check if user has funds to pay the worst case scenario fees
+        // Check if the fee payer has funds to pay the worst case scenario fees
         if charge_fee {
             self.check_fee_bounds(&tx_context)?;
 
-            blockifier::fee::fee_utils::verify_can_pay_committed_bounds(state, &tx_context)?;
+            blockifier::fee::fee_utils::verify_can_pay_committed_bounds(
+                state,
+                &fee_payer_tx_context(&tx_context, paymaster),
+            )?;
         }
 
         Ok(())
@@ -475,19 +777,7 @@ impl<
         })?;
 
         // Validate return data.
-        let class_hash = state.get_class_hash_at(storage_address)?;
-        let contract_class = state.get_compiled_contract_class(class_hash)?;
-        if let ContractClass::V1(_) = contract_class {
-            // The account contract class is a Cairo 1.0 contract; the `validate` entry point should
-            // return `VALID`.
-            let expected_retdata =
-                Retdata(vec![StarkFelt::try_from(blockifier::transaction::constants::VALIDATE_RETDATA)?]);
-            if validate_call_info.execution.retdata != expected_retdata {
-                return Err(TransactionExecutionError::InvalidValidateReturnData {
-                    actual: validate_call_info.execution.retdata,
-                });
-            }
-        }
+        verify_validate_retdata(state, storage_address, &validate_call_info)?;
 
         blockifier::transaction::transaction_utils::update_remaining_gas(remaining_gas, &validate_call_info);
 
@@ -565,25 +855,28 @@ impl<S: State + SetArbitraryNonce> SetArbitraryNonce for CachedState<S> {
     }
 }
 
-pub fn run_non_revertible_transaction<T, S, F>(
+pub fn run_non_revertible_transaction<T, S>(
     transaction: &T,
     state: &mut S,
     block_context: &BlockContext,
     validate: bool,
     charge_fee: bool,
+    disable_nonce_validation: bool,
+    paymaster_config: Option<&PaymasterConfig>,
 ) -> TransactionExecutionResult<TransactionExecutionInfo>
 where
     S: State,
-    F: TransactionFilter<T>,
     T: GetTxType + Executable<S> + Validate + GetActualCostBuilder + TransactionInfoCreator,
 {
     let mut resources = ExecutionResources::default();
     let mut remaining_gas = block_context.versioned_constants().tx_initial_gas();
     let tx_context = Arc::new(block_context.to_tx_context(transaction));
+    let paymaster = Paymaster::from_tx_info(&tx_context.tx_info, paymaster_config)?;
+    let fee_tx_context = fee_payer_tx_context(&tx_context, paymaster.as_ref());
 
     let validate_call_info: Option<CallInfo>;
     let execute_call_info: Option<CallInfo>;
-    let strinct_nonce_checking = true;
+    let nonce_validation = if disable_nonce_validation { NonceValidation::Disabled } else { NonceValidation::Strict };
     if matches!(T::tx_type(), TransactionType::DeployAccount) {
         // Handle `DeployAccount` transactions separately, due to different order of things.
         // Also, the execution context required form the `DeployAccount` execute phase is
@@ -591,25 +884,27 @@ where
         let mut execution_context = EntryPointExecutionContext::new_validate(tx_context.clone(), charge_fee)?;
         execute_call_info =
             transaction.run_execute(state, &mut resources, &mut execution_context, &mut remaining_gas)?;
-        validate_call_info = transaction.validate::<F>(
+        validate_call_info = transaction.validate(
             state,
             tx_context.clone(),
             &mut resources,
             &mut remaining_gas,
             validate,
             charge_fee,
-            strinct_nonce_checking,
+            nonce_validation,
+            paymaster.as_ref(),
         )?;
     } else {
         let mut execution_context = EntryPointExecutionContext::new_invoke(tx_context.clone(), charge_fee)?;
-        validate_call_info = transaction.validate::<F>(
+        validate_call_info = transaction.validate(
             state,
             tx_context.clone(),
             &mut resources,
             &mut remaining_gas,
             validate,
             charge_fee,
-            strinct_nonce_checking,
+            nonce_validation,
+            paymaster.as_ref(),
         )?;
         execute_call_info =
             transaction.run_execute(state, &mut resources, &mut execution_context, &mut remaining_gas)?;
@@ -621,7 +916,7 @@ where
         .with_execute_call_info(&execute_call_info)
         .build(&resources)?;
 
-    let post_execution_report = PostExecutionReport::new(state, &tx_context, &actual_cost, charge_fee)?;
+    let post_execution_report = PostExecutionReport::new(state, &fee_tx_context, &actual_cost, charge_fee)?;
     let validate_execute_call_info = match post_execution_report.error() {
         Some(error) => Err(TransactionExecutionError::from(error)),
         None => Ok(ValidateExecuteCallInfo::new_accepted(
@@ -634,7 +929,7 @@ where
 
     let fee_transfer_call_info = AccountTransaction::handle_fee(
         state,
-        tx_context,
+        fee_tx_context,
         validate_execute_call_info.final_cost.actual_fee,
         charge_fee,
     )?;
@@ -653,12 +948,14 @@ where
     Ok(tx_execution_info)
 }
 
-pub fn run_revertible_transaction<T, S, F>(
+pub fn run_revertible_transaction<T, S>(
     transaction: &T,
     state: &mut S,
     block_context: &BlockContext,
     validate: bool,
     charge_fee: bool,
+    disable_nonce_validation: bool,
+    paymaster_config: Option<&PaymasterConfig>,
 ) -> TransactionExecutionResult<TransactionExecutionInfo>
 where
     for<'a> T: Executable<CachedState<MutRefState<'a, S>>>
@@ -668,20 +965,27 @@ where
         + GetCalldataLen
         + TransactionInfoCreator,
     S: State + SetArbitraryNonce,
-    F: TransactionFilter<T>,
 {
     let mut resources = ExecutionResources::default();
     let mut remaining_gas = block_context.versioned_constants().tx_initial_gas();
     let tx_context = Arc::new(block_context.to_tx_context(transaction));
+    let paymaster = Paymaster::from_tx_info(&tx_context.tx_info, paymaster_config)?;
+    let fee_tx_context = fee_payer_tx_context(&tx_context, paymaster.as_ref());
+    let nonce_validation = match (disable_nonce_validation, validate) {
+        (true, _) => NonceValidation::Disabled,
+        (false, true) => NonceValidation::Strict,
+        (false, false) => NonceValidation::Lenient,
+    };
 
-    let validate_call_info = transaction.validate::<F>(
+    let validate_call_info = transaction.validate(
         state,
         tx_context.clone(),
         &mut resources,
         &mut remaining_gas,
         validate,
         charge_fee,
-        validate,
+        nonce_validation,
+        paymaster.as_ref(),
     )?;
 
     let mut execution_context = EntryPointExecutionContext::new_invoke(tx_context.clone(), charge_fee)?;
@@ -731,7 +1035,7 @@ where
 
                 // Post-execution checks.
                 let post_execution_report =
-                    PostExecutionReport::new(&transactional_state, &tx_context, &actual_cost, charge_fee)?;
+                    PostExecutionReport::new(&transactional_state, &fee_tx_context, &actual_cost, charge_fee)?;
                 match post_execution_report.error() {
                     Some(post_execution_error) => {
                         // Post-execution check failed. Revert the execution, compute the final fee
@@ -761,7 +1065,7 @@ where
             Err(execution_error) => {
                 // Error during execution. Revert, even if the error is sequencer-related.
                 abort_transactional_state(transactional_state);
-                let post_execution_report = PostExecutionReport::new(state, &tx_context, &revert_cost, charge_fee)?;
+                let post_execution_report = PostExecutionReport::new(state, &fee_tx_context, &revert_cost, charge_fee)?;
                 TransactionExecutionResult::Ok(ValidateExecuteCallInfo::new_reverted(
                     validate_call_info,
                     execution_error.to_string(),
@@ -774,7 +1078,7 @@ where
 
     let fee_transfer_call_info = AccountTransaction::handle_fee(
         state,
-        tx_context,
+        fee_tx_context,
         validate_execute_call_info.final_cost.actual_fee,
         charge_fee,
     )?;
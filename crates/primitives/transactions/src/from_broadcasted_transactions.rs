@@ -257,7 +257,10 @@ pub fn try_declare_tx_from_broadcasted_declare_tx(
 
             let casm_contract_class = flattened_sierra_to_casm_contract_class(flattened_contract_class)
                 .map_err(BroadcastedTransactionConversionError::SierraCompilationFailed)?;
-
+            // ensure that the user has sign the correct class hash
+            if get_casm_contract_class_hash(&casm_contract_class) != compiled_class_hash {
+                return Err(BroadcastedTransactionConversionError::InvalidCompiledClassHash);
+            }
             let tx =
                 starknet_api::transaction::DeclareTransaction::V3(starknet_api::transaction::DeclareTransactionV3 {
                     resource_bounds: resource_bounds_mapping_conversion(resource_bounds),
@@ -721,4 +724,79 @@ mod tests {
             Err(BroadcastedTransactionConversionError::InvalidCompiledClassHash)
         );
     }
+
+    fn get_resource_bounds() -> starknet_core::types::ResourceBoundsMapping {
+        starknet_core::types::ResourceBoundsMapping {
+            l1_gas: starknet_core::types::ResourceBounds { max_amount: 10_000, max_price_per_unit: 12_000_000_000 },
+            l2_gas: starknet_core::types::ResourceBounds { max_amount: 0, max_price_per_unit: 0 },
+        }
+    }
+
+    fn get_declare_transaction_v3(compiled_class_hash: FieldElement) -> BroadcastedDeclareTransaction {
+        BroadcastedDeclareTransaction::V3(BroadcastedDeclareTransactionV3 {
+            sender_address: FieldElement::default(),
+            compiled_class_hash,
+            signature: vec![FieldElement::default()],
+            nonce: FieldElement::default(),
+            contract_class: Arc::new(get_flattened_sierra_contract_class()),
+            resource_bounds: get_resource_bounds(),
+            tip: 0,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: starknet_core::types::DataAvailabilityMode::L1,
+            fee_data_availability_mode: starknet_core::types::DataAvailabilityMode::L1,
+            is_query: false,
+        })
+    }
+
+    #[test]
+    fn try_into_declare_transaction_v3_with_correct_compiled_class_hash() {
+        let input = get_declare_transaction_v3(
+            FieldElement::from_hex_be(CAIRO_1_NO_VALIDATE_ACCOUNT_COMPILED_CLASS_HASH).unwrap(),
+        );
+
+        assert!(try_declare_tx_from_broadcasted_declare_tx(input, Default::default()).is_ok());
+    }
+
+    #[test]
+    fn try_into_declare_transaction_v3_with_incorrect_compiled_class_hash() {
+        let input = get_declare_transaction_v3(FieldElement::from_hex_be("0x1").unwrap());
+
+        assert_matches!(
+            try_declare_tx_from_broadcasted_declare_tx(input, Default::default()),
+            Err(BroadcastedTransactionConversionError::InvalidCompiledClassHash)
+        );
+    }
+
+    #[test]
+    fn try_into_invoke_transaction_v3_keeps_fee_market_fields() {
+        let input = BroadcastedInvokeTransaction::V3(starknet_core::types::BroadcastedInvokeTransactionV3 {
+            sender_address: FieldElement::ONE,
+            calldata: vec![FieldElement::TWO],
+            signature: vec![FieldElement::THREE],
+            nonce: FieldElement::ONE,
+            resource_bounds: get_resource_bounds(),
+            tip: 5,
+            paymaster_data: vec![FieldElement::TWO],
+            account_deployment_data: vec![FieldElement::THREE],
+            nonce_data_availability_mode: starknet_core::types::DataAvailabilityMode::L1,
+            fee_data_availability_mode: starknet_core::types::DataAvailabilityMode::L2,
+            is_query: false,
+        });
+
+        let InvokeTransaction { tx, .. } = try_invoke_tx_from_broadcasted_invoke_tx(input, Default::default()).unwrap();
+        let starknet_api::transaction::InvokeTransaction::V3(tx) = tx else {
+            panic!("expected a v3 invoke transaction");
+        };
+
+        assert_eq!(tx.tip, Tip(5));
+        assert_eq!(tx.nonce_data_availability_mode, DataAvailabilityMode::L1);
+        assert_eq!(tx.fee_data_availability_mode, DataAvailabilityMode::L2);
+        assert_eq!(tx.paymaster_data, PaymasterData(vec![Felt252Wrapper::TWO.into()]));
+        assert_eq!(tx.account_deployment_data, AccountDeploymentData(vec![Felt252Wrapper::THREE.into()]));
+        assert_eq!(
+            tx.resource_bounds.0.get(&Resource::L1Gas),
+            Some(&ResourceBounds { max_amount: 10_000, max_price_per_unit: 12_000_000_000 })
+        );
+    }
 }
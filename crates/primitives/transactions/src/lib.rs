@@ -16,7 +16,7 @@ use blockifier::transaction::transaction_execution::Transaction;
 use sp_core::H256;
 use starknet_api::core::{ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::StarkFelt;
-use starknet_api::transaction::TransactionHash;
+use starknet_api::transaction::{TransactionHash, TransactionSignature};
 use starknet_core::types::{CompressedLegacyContractClass, TransactionExecutionStatus, TransactionFinalityStatus};
 use starknet_ff::FieldElement;
 
@@ -67,6 +67,18 @@ pub fn get_transaction_sender_address(tx: &Transaction) -> ContractAddress {
     }
 }
 
+/// Returns the signature of the transaction, or `None` for the L1 handlers which do not have one.
+pub fn get_transaction_signature(tx: &Transaction) -> Option<TransactionSignature> {
+    match tx {
+        Transaction::AccountTransaction(tx) => match tx {
+            AccountTransaction::Declare(tx) => Some(tx.tx.signature()),
+            AccountTransaction::DeployAccount(tx) => Some(tx.tx.signature()),
+            AccountTransaction::Invoke(tx) => Some(tx.tx.signature()),
+        },
+        Transaction::L1HandlerTransaction(_) => None,
+    }
+}
+
 /// Wrapper type for transaction execution error.
 /// Different tx types.
 /// See `https://docs.starknet.io/documentation/architecture_and_concepts/Blocks/transactions/` for more details.
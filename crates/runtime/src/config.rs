@@ -90,6 +90,22 @@ pub enum SealingMode {
     Instant {
         finalize: bool,
     },
+    /// Seal a block every `block_time` milliseconds
+    Interval {
+        block_time: u64,
+        finalize: bool,
+        skip_empty: bool,
+    },
+    /// Seal a block every `block_time` milliseconds, or sooner once the ready transactions reach
+    /// `max_pending_transactions` or `max_pending_bytes`, or would use more Starknet resources
+    /// than the `BlockResourceLimits` of a block
+    Hybrid {
+        block_time: u64,
+        max_pending_transactions: Option<u32>,
+        max_pending_bytes: Option<u32>,
+        finalize: bool,
+        skip_empty: bool,
+    },
 }
 
 impl SealingMode {
@@ -105,6 +121,17 @@ impl std::fmt::Display for SealingMode {
             SealingMode::Default => write!(f, "Default"),
             SealingMode::Manual => write!(f, "Manual"),
             SealingMode::Instant { finalize } => write!(f, "Instant (finalize: {})", finalize),
+            SealingMode::Interval { block_time, finalize, skip_empty } => {
+                write!(f, "Interval (block time: {}ms, finalize: {}, skip empty: {})", block_time, finalize, skip_empty)
+            }
+            SealingMode::Hybrid { block_time, max_pending_transactions, max_pending_bytes, finalize, skip_empty } => {
+                write!(
+                    f,
+                    "Hybrid (block time: {}ms, max pending transactions: {:?}, max pending bytes: {:?}, finalize: {}, \
+                     skip empty: {})",
+                    block_time, max_pending_transactions, max_pending_bytes, finalize, skip_empty
+                )
+            }
         }
     }
 }
@@ -32,18 +32,21 @@ pub use frame_support::weights::constants::{
 pub use frame_support::weights::{IdentityFee, Weight};
 pub use frame_support::{construct_runtime, parameter_types, StorageValue};
 pub use frame_system::Call as SystemCall;
+use mp_block::commitments::BlockStateDiff;
 use mp_felt::Felt252Wrapper;
 use mp_simulations::{
     FeeEstimate, InternalSubstrateError, ReExecutionResult, SimulationError, SimulationFlags,
     TransactionSimulationResult,
 };
 use mp_starknet_inherent::L1GasPrices;
+use mp_transactions::execution::PaymasterConfig;
 use pallet_grandpa::{fg_primitives, AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList};
 /// Import the Starknet pallet.
 pub use pallet_starknet;
 use pallet_starknet::Call::{consume_l1_message, declare, deploy_account, invoke};
 pub use pallet_starknet::DefaultChainId;
 pub use pallet_timestamp::Call as TimestampCall;
+pub use pallets::BlockResourceLimits;
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::crypto::KeyTypeId;
@@ -54,12 +57,13 @@ use sp_runtime::transaction_validity::{TransactionSource, TransactionValidity};
 pub use sp_runtime::BuildStorage;
 use sp_runtime::{generic, ApplyExtrinsicResult};
 pub use sp_runtime::{Perbill, Permill};
+use sp_std::collections::btree_map::BTreeMap;
 use sp_std::prelude::*;
 use sp_version::RuntimeVersion;
-use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, EntryPointSelector, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
-use starknet_api::transaction::{Calldata, Event as StarknetEvent, MessageToL1, TransactionHash};
+use starknet_api::transaction::{Calldata, Event as StarknetEvent, Fee, MessageToL1, TransactionHash};
 /// Import the types.
 pub use types::*;
 // For `format!`
@@ -263,6 +267,10 @@ impl_runtime_apis! {
             Starknet::contract_class_by_class_hash(class_hash.0)
         }
 
+        fn compiled_class_hash_by_class_hash(class_hash: ClassHash) -> Option<CompiledClassHash> {
+            Starknet::compiled_class_hash_by_class_hash(class_hash.0)
+        }
+
         fn chain_id() -> Felt252Wrapper {
             Starknet::chain_id()
         }
@@ -279,6 +287,23 @@ impl_runtime_apis! {
             Starknet::is_transaction_fee_disabled()
         }
 
+        fn is_nonce_validation_disabled() -> bool {
+            Starknet::is_nonce_validation_disabled()
+        }
+
+        fn paymaster_config() -> Option<PaymasterConfig> {
+            Starknet::paymaster_config()
+        }
+
+        fn sequencer_address_for_slot(slot: u64) -> Option<ContractAddress> {
+            let authorities = Aura::authorities();
+            if authorities.is_empty() {
+                return None;
+            }
+            let author = &authorities[(slot % authorities.len() as u64) as usize];
+            Starknet::authority_sequencer_address(author)
+        }
+
         fn estimate_fee(transactions: Vec<AccountTransaction>, simulation_flags: SimulationFlags) -> Result<Result<Vec<FeeEstimate>, SimulationError>, InternalSubstrateError> {
             Starknet::estimate_fee(transactions, &simulation_flags)
         }
@@ -342,6 +367,15 @@ impl_runtime_apis! {
             Starknet::tx_messages(tx_hash)
         }
 
+        fn get_tx_paymaster(tx_hash: TransactionHash) -> Option<ContractAddress> {
+            Starknet::tx_paymaster(tx_hash)
+        }
+
+        fn get_tx_execution_resources(tx_hash: TransactionHash) -> Option<(Fee, BTreeMap<String, u64>)> {
+            Starknet::tx_execution_resources(tx_hash)
+                .map(|execution_resources| (Starknet::tx_receipt_resources(tx_hash).actual_fee, execution_resources))
+        }
+
         fn get_events_for_tx_by_hash(tx_hash: TransactionHash) -> Vec<StarknetEvent> {
             Starknet::tx_events(tx_hash)
         }
@@ -361,6 +395,10 @@ impl_runtime_apis! {
         fn current_l1_gas_prices() -> L1GasPrices {
             Starknet::current_l1_gas_prices()
         }
+
+        fn last_state_diff() -> BlockStateDiff {
+            Starknet::last_state_diff()
+        }
     }
 
     impl pallet_starknet_runtime_api::ConvertTransactionRuntimeApi<Block> for Runtime {
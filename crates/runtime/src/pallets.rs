@@ -12,19 +12,28 @@ use blockifier::versioned_constants::VersionedConstants;
 pub use frame_support::traits::{
     ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, OnTimestampSet, Randomness, StorageInfo,
 };
+use frame_support::traits::{EitherOfDiverse, EnsureOrigin};
 pub use frame_support::weights::constants::{
     BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_REF_TIME_PER_SECOND,
 };
 pub use frame_support::weights::{IdentityFee, Weight};
 pub use frame_support::{construct_runtime, parameter_types, StorageValue};
 pub use frame_system::Call as SystemCall;
+use frame_system::{EnsureRoot, EnsureSignedBy};
 use lazy_static::lazy_static;
+use mp_block::PROTOCOL_VERSION_0_13_2;
 pub use mp_chain_id::SN_GOERLI_CHAIN_ID;
 pub use mp_program_hash::SN_OS_PROGRAM_HASH;
 /// Import the StarkNet pallet.
 pub use pallet_starknet;
+use pallet_starknet::bouncer::{BlockResources, BuiltinCount};
+use pallet_starknet::control_accounts::ControlAccountsOf;
+use pallet_starknet::transaction_filters::{
+    AllowDeclarers, AllowDeployAccountClassHashes, DenySenders, MaxCalldataLength,
+};
 pub use pallet_timestamp::Call as TimestampCall;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
+use sp_core::sr25519;
 use sp_runtime::traits::{AccountIdLookup, BlakeTwo256};
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
@@ -64,20 +73,22 @@ lazy_static! {
 
 /// Configure the Starknet pallet in pallets/starknet.
 impl pallet_starknet::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    /// Root, or a signed extrinsic of one of the control accounts set in the chain spec.
+    type ControlOrigin = EitherOfDiverse<EnsureRoot<AccountId>, EnsureSignedBy<ControlAccountsOf<Self>, AccountId>>;
+    type AuthorityId = AuraId;
+    type AuthorityOrigin = EnsureAuraAuthority;
     type TimestampProvider = Timestamp;
     type UnsignedPriority = UnsignedPriority;
     type TransactionLongevity = TransactionLongevity;
-    #[cfg(not(feature = "disable-transaction-fee"))]
-    type DisableTransactionFee = ConstBool<false>;
-    #[cfg(feature = "disable-transaction-fee")]
-    type DisableTransactionFee = ConstBool<true>;
-    type DisableNonceValidation = ConstBool<false>;
     type ProtocolVersion = ProtocolVersion;
     type ProgramHash = ProgramHash;
     type ExecutionConstants = ExecutionConstants;
-    type InvokeTransactionFilter = ();
-    type DeclareTransactionFilter = ();
-    type DeployAccountTransactionFilter = ();
+    type BlockResourceLimits = BlockResourceLimits;
+    type InvokeTransactionFilter = (DenySenders<Self>, MaxCalldataLength<Self>);
+    type DeclareTransactionFilter = (DenySenders<Self>, AllowDeclarers<Self>);
+    type DeployAccountTransactionFilter =
+        (DenySenders<Self>, AllowDeployAccountClassHashes<Self>, MaxCalldataLength<Self>);
 }
 
 /// --------------------------------------
@@ -190,9 +201,53 @@ fn get_execution_constants() -> Arc<VersionedConstants> {
 parameter_types! {
     pub const UnsignedPriority: u64 = 1 << 20;
     pub const TransactionLongevity: u64 = u64::MAX;
-    pub const ProtocolVersion: u8 = 0;
+    pub const ProtocolVersion: u8 = PROTOCOL_VERSION_0_13_2;
     pub const ProgramHash: Felt252Wrapper = SN_OS_PROGRAM_HASH;
     pub ExecutionConstants: Arc<VersionedConstants> = get_execution_constants();
+    /// Blocks of 40M steps, with the builtin instances the `starknet_with_keccak` layout provides
+    /// for them. The state diff and messages fit in a 4096 felts blob.
+    pub const BlockResourceLimits: BlockResources = BlockResources {
+        n_steps: 40_000_000,
+        builtins: BuiltinCount {
+            pedersen: 1_250_000,
+            range_check: 2_500_000,
+            ecdsa: 19_531,
+            bitwise: 625_000,
+            ec_op: 39_062,
+            keccak: 19_531,
+            poseidon: 1_250_000,
+            segment_arena: u64::MAX,
+        },
+        l1_gas: 5_000_000,
+        message_segment_length: 3_750,
+        state_diff_size: 4_000,
+    };
+}
+
+/// Ensures the origin is signed by the account matching the key of a current Aura authority.
+/// Succeeds with the authority id.
+pub struct EnsureAuraAuthority;
+impl EnsureOrigin<RuntimeOrigin> for EnsureAuraAuthority {
+    type Success = AuraId;
+
+    fn try_origin(o: RuntimeOrigin) -> Result<Self::Success, RuntimeOrigin> {
+        o.into().and_then(|o| match o {
+            frame_system::RawOrigin::Signed(who) => {
+                let authority = AuraId::from(sr25519::Public::from_raw(who.clone().into()));
+                if Aura::authorities().contains(&authority) {
+                    Ok(authority)
+                } else {
+                    Err(RuntimeOrigin::from(frame_system::RawOrigin::Signed(who)))
+                }
+            }
+            o => Err(RuntimeOrigin::from(o)),
+        })
+    }
+
+    #[cfg(feature = "runtime-benchmarks")]
+    fn try_successful_origin() -> Result<RuntimeOrigin, ()> {
+        Err(())
+    }
 }
 
 /// Implement the OnTimestampSet trait to override the default Aura.